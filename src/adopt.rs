@@ -0,0 +1,271 @@
+//! Migration from unguarded repositories to a guarded fleet.
+//!
+//! `--adopt <root>` finds every repository under `root`, infers a policy
+//! from its remotes and branch names, writes it as `.safetyrc`, installs a
+//! pre-push guard hook, and registers the repository for fleet status.
+//! Nothing existing is overwritten: a repository that already has a
+//! `.safetyrc` or a pre-push hook keeps it, and the report says so.
+
+use super::fleet::RepoGroup;
+use super::policy::Policy;
+use super::Repository;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Refuses force-pushes and remote branch deletions. Those go through
+/// the gate, not through a bare `git push`.
+pub const PRE_PUSH_HOOK: &str = "#!/bin/sh
+# Installed by safe_operations --adopt. Force-pushes and branch deletions
+# need consent from the gate; a plain push cannot grant it.
+zero=0000000000000000000000000000000000000000
+while read local_ref local_sha remote_ref remote_sha; do
+    if [ \"$local_sha\" = \"$zero\" ]; then
+        echo \"refused: deleting $remote_ref needs gate consent\" >&2
+        exit 1
+    fi
+    if [ \"$remote_sha\" != \"$zero\" ] && ! git merge-base --is-ancestor \"$remote_sha\" \"$local_sha\" 2>/dev/null; then
+        echo \"refused: non-fast-forward push to $remote_ref needs gate consent\" >&2
+        exit 1
+    fi
+done
+exit 0
+";
+
+/// What kind of repository it looks like, and so which policy it gets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Has a remote others push to or clone from.
+    Shared,
+    /// Shared, with release or hotfix branches: history is shipped.
+    Release,
+    /// No remote. Only this machine has it.
+    LocalOnly,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Shared => "shared",
+            Profile::Release => "release",
+            Profile::LocalOnly => "local-only",
+        }
+    }
+
+    /// The `.safetyrc` policy for this profile.
+    pub fn policy_text(self) -> String {
+        let rules: &[(&str, &str)] = match self {
+            Profile::Release => &[
+                ("force_push", "deny"),
+                ("filter_repo", "deny"),
+                ("remove_protection", "deny"),
+                ("transfer_ownership", "deny"),
+            ],
+            Profile::Shared => &[("filter_repo", "deny"), ("remove_protection", "deny")],
+            // Nothing is loosened: `allow` does not apply to an operation
+            // that cannot simply be undone, which is every one that matters here.
+            Profile::LocalOnly => &[],
+        };
+        let mut out = format!(
+            "# Written by safe_operations --adopt ({} profile). Review and edit.\nversion = \"adopt-{}\"\ndefault = \"require-consent\"\n\n[operations]\n",
+            self.name(),
+            self.name()
+        );
+        for (op, decision) in rules {
+            out.push_str(&format!("{} = \"{}\"\n", op, decision));
+        }
+        out
+    }
+}
+
+/// What adoption did for one repository.
+#[derive(Clone, Debug)]
+pub struct Adoption {
+    pub path: PathBuf,
+    pub remotes: Vec<String>,
+    pub branches: Vec<String>,
+    /// `None` if the repository could not be classified.
+    pub profile: Option<Profile>,
+    /// What was done, and what was left alone and why.
+    pub notes: Vec<String>,
+}
+
+pub struct AdoptionReport {
+    pub adopted: Vec<Adoption>,
+    pub group: RepoGroup,
+}
+
+impl AdoptionReport {
+    pub fn unclassified(&self) -> impl Iterator<Item = &Adoption> {
+        self.adopted.iter().filter(|a| a.profile.is_none())
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("Adoption report: {} repositories\n", self.adopted.len());
+        for a in &self.adopted {
+            let profile = a.profile.map_or("UNCLASSIFIED", Profile::name);
+            out.push_str(&format!("  {} [{}]\n", a.path.display(), profile));
+            for note in &a.notes {
+                out.push_str(&format!("      - {}\n", note));
+            }
+        }
+        let unclassified = self.unclassified().count();
+        if unclassified > 0 {
+            out.push_str(&format!("{} repositories need a human to classify them.\n", unclassified));
+        }
+        out
+    }
+}
+
+/// Every directory under `root` with a `.git` directory, not descending
+/// into repositories once found, nor more than `max_depth` levels.
+pub fn discover(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut stack = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        if dir.join(".git").is_dir() {
+            found.push(dir);
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_dir && !entry.file_name().to_string_lossy().starts_with('.') {
+                stack.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Remote URLs from `.git/config`.
+fn remotes(git_dir: &Path) -> Vec<String> {
+    fs::read_to_string(git_dir.join("config"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("url = ").map(str::to_string))
+        .collect()
+}
+
+/// Local branch names, loose and packed.
+fn branches(git_dir: &Path) -> Vec<String> {
+    fn walk(dir: &Path, prefix: &str, out: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else { return };
+        for entry in entries.flatten() {
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.path().is_dir() {
+                walk(&entry.path(), &format!("{}/", name), out);
+            } else {
+                out.push(name);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(&git_dir.join("refs").join("heads"), "", &mut out);
+    let packed = fs::read_to_string(git_dir.join("packed-refs")).unwrap_or_default();
+    out.extend(packed.lines().filter_map(|l| l.split_once(" refs/heads/").map(|(_, b)| b.to_string())));
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Infer a profile. Remotes that don't look like git URLs, or a
+/// repository with no branches at all, are left for a human.
+pub fn classify(remotes: &[String], branches: &[String]) -> Result<Profile, String> {
+    if branches.is_empty() {
+        return Err("no branches; empty or unusual repository".to_string());
+    }
+    let recognizable = |url: &String| {
+        url.starts_with("https://") || url.starts_with("ssh://") || url.starts_with("git@") || url.starts_with('/')
+    };
+    if let Some(odd) = remotes.iter().find(|u| !recognizable(u)) {
+        return Err(format!("unrecognized remote URL `{}`", odd));
+    }
+    if remotes.is_empty() {
+        Ok(Profile::LocalOnly)
+    } else if branches.iter().any(|b| b.starts_with("release/") || b.starts_with("hotfix/")) {
+        Ok(Profile::Release)
+    } else {
+        Ok(Profile::Shared)
+    }
+}
+
+fn write_new(path: &Path, contents: &str) -> io::Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+/// Adopt one repository.
+pub fn adopt(path: &Path) -> io::Result<Adoption> {
+    let git_dir = path.join(".git");
+    let remotes = remotes(&git_dir);
+    let branches = branches(&git_dir);
+    let mut notes = Vec::new();
+    let profile = match classify(&remotes, &branches) {
+        Ok(profile) => Some(profile),
+        Err(why) => {
+            notes.push(format!("not classified: {}", why));
+            None
+        }
+    };
+    if let Some(profile) = profile {
+        let text = profile.policy_text();
+        Policy::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        if write_new(&path.join(".safetyrc"), &text)? {
+            notes.push(format!("wrote .safetyrc ({} profile)", profile.name()));
+        } else {
+            notes.push("kept existing .safetyrc".to_string());
+        }
+    }
+    let hook = git_dir.join("hooks").join("pre-push");
+    fs::create_dir_all(git_dir.join("hooks"))?;
+    if write_new(&hook, PRE_PUSH_HOOK)? {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+        }
+        notes.push("installed pre-push guard hook".to_string());
+    } else if fs::read_to_string(&hook).is_ok_and(|h| h == PRE_PUSH_HOOK) {
+        notes.push("pre-push guard hook already installed".to_string());
+    } else {
+        notes.push("kept existing pre-push hook; add the guard by hand".to_string());
+    }
+    Ok(Adoption {
+        path: path.to_path_buf(),
+        remotes,
+        branches,
+        profile,
+        notes,
+    })
+}
+
+/// Discover, adopt, and register everything under `root`.
+pub fn scan(root: &Path) -> AdoptionReport {
+    let mut group = RepoGroup::new();
+    let mut adopted = Vec::new();
+    for path in discover(root, 6) {
+        match adopt(&path) {
+            Ok(adoption) => {
+                let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+                group.register_read_only(&Repository::open(&name, &path.display().to_string(), 0));
+                adopted.push(adoption);
+            }
+            Err(err) => adopted.push(Adoption {
+                path,
+                remotes: Vec::new(),
+                branches: Vec::new(),
+                profile: None,
+                notes: vec![format!("adoption failed: {}", err)],
+            }),
+        }
+    }
+    AdoptionReport { adopted, group }
+}
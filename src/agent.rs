@@ -0,0 +1,222 @@
+//! AgentHandle — exactly what the manifest grants, nothing more.
+
+use crate::{HostKey, HostSignature, OperationKind};
+#[cfg(feature = "std")]
+use crate::{sparse, CommitBuilder, Outcome, Protected, Repository};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Something an agent may do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capability {
+    Read,
+    Commit,
+    Push,
+    /// May ask the gate for consent to this operation. Asking is all it
+    /// grants: the human still decides.
+    Request(OperationKind),
+}
+
+impl Capability {
+    pub fn name(self) -> String {
+        match self {
+            Capability::Read => "read".to_string(),
+            Capability::Commit => "commit".to_string(),
+            Capability::Push => "push".to_string(),
+            Capability::Request(kind) => format!("request:{}", kind.name()),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        match name {
+            "read" => Some(Capability::Read),
+            "commit" => Some(Capability::Commit),
+            "push" => Some(Capability::Push),
+            _ => name
+                .strip_prefix("request:")
+                .and_then(OperationKind::from_name)
+                .map(Capability::Request),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ManifestError {
+    BadSignature,
+    Malformed(String),
+    /// The gate already has a manifest for this session.
+    AlreadyBound,
+    /// The manifest does not grant the capability that was used.
+    NotPermitted(Capability),
+    /// The handle is scoped to a sparse cone and these paths fall outside it.
+    OutOfScope(Vec<String>),
+}
+
+/// A host-signed declaration of what an agent may do.
+///
+/// ```text
+/// agent = claude-session-42
+/// capabilities = read, commit, push, request:force_push
+/// signature = 9f2c51e07ab34d18c0e4b7a1f3d26e9a5b8c14f07d3e6a29b1c5f8e2d04a7b36
+/// ```
+pub struct AgentManifest {
+    pub agent: String,
+    pub capabilities: Vec<Capability>,
+    signature: HostSignature,
+}
+
+impl AgentManifest {
+    /// Issue a manifest, signed with the host's key.
+    pub fn issue(agent: &str, capabilities: &[Capability], key: &HostKey) -> Self {
+        let mut manifest = AgentManifest {
+            agent: agent.to_string(),
+            capabilities: capabilities.to_vec(),
+            signature: HostSignature::default(),
+        };
+        manifest.signature = key.sign(&manifest.canonical());
+        manifest
+    }
+
+    fn canonical(&self) -> String {
+        let caps: Vec<String> = self.capabilities.iter().map(|c| c.name()).collect();
+        format!("agent = {}\ncapabilities = {}\n", self.agent, caps.join(", "))
+    }
+
+    pub fn verify(&self, key: &HostKey) -> bool {
+        key.verify(&self.canonical(), &self.signature)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("{}signature = {}\n", self.canonical(), self.signature.to_hex())
+    }
+
+    pub fn parse(text: &str) -> Result<AgentManifest, ManifestError> {
+        let (mut agent, mut capabilities, mut signature) = (None, None, None);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ManifestError::Malformed(line.to_string()))?;
+            let value = value.trim();
+            match key.trim() {
+                "agent" => agent = Some(value.to_string()),
+                "capabilities" => {
+                    let caps: Option<Vec<Capability>> = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(Capability::from_name)
+                        .collect();
+                    capabilities = Some(caps.ok_or_else(|| ManifestError::Malformed(line.to_string()))?);
+                }
+                "signature" => signature = HostSignature::from_hex(value),
+                _ => return Err(ManifestError::Malformed(line.to_string())),
+            }
+        }
+        match (agent, capabilities, signature) {
+            (Some(agent), Some(capabilities), Some(signature)) => Ok(AgentManifest {
+                agent,
+                capabilities,
+                signature,
+            }),
+            _ => Err(ManifestError::Malformed("missing agent, capabilities, or signature".to_string())),
+        }
+    }
+}
+
+/// What an agent is handed instead of a repository.
+///
+/// It borrows the repository, so it can never move or consume it, and it
+/// answers only for the capabilities in its verified manifest — an agent
+/// with a read-only manifest has a handle whose `commit` always refuses.
+#[cfg(feature = "std")]
+pub struct AgentHandle<'r> {
+    pub(crate) repo: &'r Repository<Protected>,
+    pub(crate) manifest: AgentManifest,
+    /// The directories the agent's task is confined to, if scoped.
+    pub(crate) cone: Option<sparse::SparseCone>,
+}
+
+#[cfg(feature = "std")]
+impl<'r> AgentHandle<'r> {
+    /// Build a handle whose capability set is exactly the manifest's.
+    /// A manifest that does not verify against the host key yields nothing.
+    pub fn from_manifest(
+        repo: &'r Repository<Protected>,
+        manifest: AgentManifest,
+        key: &HostKey,
+    ) -> Result<Self, ManifestError> {
+        if !manifest.verify(key) {
+            return Err(ManifestError::BadSignature);
+        }
+        Ok(AgentHandle {
+            repo,
+            manifest,
+            cone: None,
+        })
+    }
+
+    pub fn agent(&self) -> &str {
+        &self.manifest.agent
+    }
+
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.manifest.capabilities
+    }
+
+    pub(crate) fn require(&self, capability: Capability) -> Result<(), ManifestError> {
+        if self.manifest.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(ManifestError::NotPermitted(capability))
+        }
+    }
+
+    pub fn status(&self) -> Result<String, ManifestError> {
+        self.require(Capability::Read)?;
+        Ok(self.repo.status())
+    }
+
+    pub fn commit(&self) -> Result<CommitBuilder<'r>, ManifestError> {
+        self.require(Capability::Commit)?;
+        Ok(self.repo.commit())
+    }
+
+    /// `Ok(Err(..))` if the push was allowed and failed, as below.
+    pub fn push(&self) -> Result<Result<String, Outcome>, ManifestError> {
+        self.require(Capability::Push)?;
+        Ok(self.repo.push())
+    }
+
+    pub fn fetch(&self) -> Result<Result<String, Outcome>, ManifestError> {
+        self.require(Capability::Read)?;
+        Ok(self.repo.fetch())
+    }
+
+    pub fn diff(&self) -> Result<String, ManifestError> {
+        self.require(Capability::Read)?;
+        Ok(self.repo.diff())
+    }
+
+    pub fn log(&self, count: usize) -> Result<String, ManifestError> {
+        self.require(Capability::Read)?;
+        Ok(self.repo.log(count))
+    }
+
+    /// Pulling moves the local branch, so it needs commit access.
+    pub fn pull(&self) -> Result<Result<String, Outcome>, ManifestError> {
+        self.require(Capability::Commit)?;
+        Ok(self.repo.pull())
+    }
+
+    /// `Ok(Err(..))` if the branch already exists.
+    pub fn create_branch(&self, branch: &str) -> Result<Result<String, String>, ManifestError> {
+        self.require(Capability::Commit)?;
+        Ok(self.repo.create_branch(branch))
+    }
+
+    pub fn create_tag(&self, tag: &str, message: &str) -> Result<Result<String, Outcome>, ManifestError> {
+        self.require(Capability::Commit)?;
+        Ok(self.repo.create_tag(tag, message))
+    }
+}
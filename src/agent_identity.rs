@@ -0,0 +1,162 @@
+//! Who is asking: which agent, running which model, in which session and
+//! process.
+//!
+//! A host that runs several agents in one process, or hands work from one
+//! session to the next, has consent objects that could pass between them.
+//! Consent granted because one session explained what it was doing is not
+//! consent for another session to do it. The host [`enter`](Host::enter)s
+//! an identity on the thread that runs an agent; consent minted there carries it,
+//! signed with the rest of the grant, and every destructive method
+//! refuses consent presented under a different identity, or under none.
+//! Consent granted outside any session is likewise refused inside one.
+//!
+//! The process id is part of the identity, so consent does not survive
+//! its process, even through a resumed gate.
+//!
+//! Only the [`Host`] makes identities and enters them, and a process has
+//! one host: the first to [`claim`](Host::claim) it, at startup, before any
+//! agent code runs. Code running as an agent can read who it is, and can
+//! see who a consent was granted to, but cannot become someone else.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// One agent session, as the host knows it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AgentIdentity {
+    pid: u32,
+    agent: String,
+    /// The model and version driving the agent.
+    model: String,
+    session: String,
+}
+
+impl AgentIdentity {
+    /// An identity for a session in this process.
+    fn new(agent: &str, model: &str, session: &str) -> Self {
+        AgentIdentity {
+            pid: std::process::id(),
+            agent: agent.to_string(),
+            model: model.to_string(),
+            session: session.to_string(),
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn agent(&self) -> &str {
+        &self.agent
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    pub fn render(&self) -> String {
+        format!("agent '{}' ({}, session {}, pid {})", self.agent, self.model, self.session, self.pid)
+    }
+
+    /// One line, spaces escaped, for signatures and state files.
+    pub fn encode(&self) -> String {
+        let escape = |text: &str| text.replace('%', "%25").replace(' ', "%20").replace('\n', "%0A");
+        format!("{} {} {} {}", self.pid, escape(&self.session), escape(&self.model), escape(&self.agent))
+    }
+
+    pub(crate) fn decode(text: &str) -> Option<Self> {
+        let unescape = |text: &str| text.replace("%0A", "\n").replace("%20", " ").replace("%25", "%");
+        let fields: Vec<&str> = text.split(' ').collect();
+        let [pid, session, model, agent] = fields.as_slice() else {
+            return None;
+        };
+        Some(AgentIdentity {
+            pid: pid.parse().ok()?,
+            agent: unescape(agent),
+            model: unescape(model),
+            session: unescape(session),
+        })
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<AgentIdentity>> = const { RefCell::new(None) };
+}
+
+/// The identity this thread is running as, if any.
+pub fn current() -> Option<AgentIdentity> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Set once the process's [`Host`] has been claimed.
+static CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// The capability to make agent identities and run threads as them.
+///
+/// Not `Clone`, and not constructible outside this crate: the one host
+/// value a process has is the one [`claim`](Host::claim) handed out.
+pub struct Host {
+    pub(crate) _private: (),
+}
+
+impl Host {
+    /// The process's host, the first time it is asked for; `None` ever
+    /// after. The host claims it at startup, before any agent code runs,
+    /// so an agent that asks later gets nothing.
+    pub fn claim() -> Option<Host> {
+        let first = !CLAIMED.swap(true, Ordering::SeqCst);
+        first.then_some(Host { _private: () })
+    }
+
+    /// An identity for a session in this process.
+    pub fn identity(&self, agent: &str, model: &str, session: &str) -> AgentIdentity {
+        AgentIdentity::new(agent, model, session)
+    }
+
+    /// Run this thread as `identity` until the guard is dropped, when the
+    /// identity it replaced is restored.
+    pub fn enter(&self, identity: AgentIdentity) -> Entered {
+        let previous = CURRENT.with(|current| current.replace(Some(identity)));
+        Entered { previous }
+    }
+}
+
+/// An entered identity. Dropping it leaves the session.
+#[must_use = "the identity is left as soon as this is dropped"]
+pub struct Entered {
+    previous: Option<AgentIdentity>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current, AgentIdentity, Host};
+
+    #[test]
+    fn a_process_has_one_host() {
+        let _first = Host::claim();
+        assert!(Host::claim().is_none(), "a second claim must get nothing, whoever made the first");
+    }
+
+    #[test]
+    fn an_entered_identity_is_left_when_the_guard_drops() {
+        let host = Host { _private: () };
+        let identity = host.identity("builder", "model-1", "s-1");
+        assert_eq!(AgentIdentity::decode(&identity.encode()), Some(identity.clone()));
+        {
+            let _entered = host.enter(identity.clone());
+            assert_eq!(current(), Some(identity));
+        }
+        assert_eq!(current(), None);
+    }
+}
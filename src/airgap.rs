@@ -0,0 +1,608 @@
+//! Batch consent for approvers on a separate, disconnected system.
+//!
+//! In an air-gapped network the gate cannot ask anyone: the approver's
+//! machine is not reachable from the agent's. Instead the gate queues each
+//! request ([`SafetyGate::queue_offline`]) and exports them together as a
+//! batch signed with its consent key ([`SafetyGate::export_batch`]). The
+//! approver carries the file across, reviews it with an
+//! [`OfflineApprover`], which checks the gate's signature, shows each
+//! request's plan and challenge, and signs the answers with the approver's
+//! own Ed25519 key. The response is carried back and imported
+//! ([`SafetyGate::import_responses`]).
+//!
+//! Nothing about the gap weakens the checks. Each request's nonce is
+//! answered at most once; a request that expired while the file was in
+//! transit is denied; a response is only accepted from an approver the
+//! gate trusts, for a batch the gate exported, and for requests that were
+//! in it. A grant still goes through the gate's own decision — deny-list,
+//! manifest, policy, quorum — exactly as an answer typed at the console.
+//!
+//! ```text
+//! airgap-batch 1                       airgap-response 1
+//! gate <public key>                    batch <digest of the batch file>
+//! exported <secs>                      approver <public key>
+//! request <id> <op> <expires> <target> <description>
+//! plan <id> <line>                     answer <id> granted <reason>
+//! acknowledge <id>                     acknowledged <id> <sentence>
+//! signature <ed25519>                  signature <ed25519>
+//! ```
+
+use super::backend::GitBackend;
+use super::ed25519::{self, PublicKey, Signature, SigningKey};
+use super::{
+    policy, random_u64, unix_now, ConsentDenied, DestructionPlan, OperationKind, Repository, SafetyGate,
+    UserConsent, IRREVERSIBILITY_ACKNOWLEDGMENT,
+};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A request waiting for an answer from across the gap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    /// The request's nonce, as it appears in the files.
+    pub id: u64,
+    pub operation: OperationKind,
+    pub target: Option<String>,
+    pub description: String,
+    /// The itemized plan, one rendered line each, if there is one.
+    pub plan: Vec<String>,
+    /// Whether the approver must type [`IRREVERSIBILITY_ACKNOWLEDGMENT`].
+    pub acknowledge: bool,
+    pub expires_at: u64,
+    commits: Option<usize>,
+}
+
+impl Request {
+    /// What the offline approver types to grant it.
+    pub fn challenge(&self) -> String {
+        format!("{:08x}", self.id as u32)
+    }
+}
+
+/// The gate's side: requests still unanswered, and the batches they
+/// went out in.
+pub struct Outbox {
+    window: u64,
+    pending: BTreeMap<u64, Request>,
+    exported: Vec<(String, Vec<u64>)>,
+    approvers: Vec<(String, PublicKey)>,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Outbox {
+            window: 8 * 3600,
+            pending: BTreeMap::new(),
+            exported: Vec::new(),
+            approvers: Vec::new(),
+        }
+    }
+}
+
+impl Outbox {
+    pub fn pending(&self) -> impl Iterator<Item = &Request> {
+        self.pending.values()
+    }
+
+    /// How long a queued request may wait, in seconds.
+    pub(crate) fn window(&self) -> u64 {
+        self.window
+    }
+
+    /// The pending requests and exported batches, as lines of a
+    /// [`persist`](super::persist) state file.
+    pub(crate) fn state_lines(&self) -> String {
+        let mut out = String::new();
+        for request in self.pending.values() {
+            out.push_str(&format!(
+                "offline {:016x} {} {} {} {} {}\n",
+                request.id,
+                request.operation.name(),
+                request.expires_at,
+                request.commits.map_or("-".to_string(), |n| n.to_string()),
+                request.target.as_deref().unwrap_or("-"),
+                request.description
+            ));
+            for line in &request.plan {
+                out.push_str(&format!("offline-plan {:016x} {}\n", request.id, line));
+            }
+            if request.acknowledge {
+                out.push_str(&format!("offline-acknowledge {:016x}\n", request.id));
+            }
+        }
+        for (batch, ids) in &self.exported {
+            let ids: Vec<String> = ids.iter().map(|id| format!("{:016x}", id)).collect();
+            out.push_str(&format!("batch {} {}\n", batch, ids.join(" ")));
+        }
+        out
+    }
+
+    /// Add the requests and batches `other` holds. Returns how many
+    /// requests it added.
+    pub(crate) fn absorb(&mut self, mut other: Outbox) -> usize {
+        let added = other.pending.len();
+        self.pending.append(&mut other.pending);
+        self.exported.append(&mut other.exported);
+        added
+    }
+
+    /// Take back one line written by [`state_lines`](Self::state_lines).
+    /// `Ok(false)` if the line is not the outbox's.
+    pub(crate) fn restore_line(&mut self, word: &str, rest: &str) -> Result<bool, String> {
+        match word {
+            "offline" => {
+                let fields: Vec<&str> = rest.splitn(6, ' ').collect();
+                let [id, operation, expires_at, commits, target, description] = fields.as_slice() else {
+                    return Err(format!("bad offline line `{}`", rest));
+                };
+                let id = parse_id(id)?;
+                self.pending.insert(
+                    id,
+                    Request {
+                        id,
+                        operation: OperationKind::from_name(operation).ok_or(format!("unknown operation `{}`", operation))?,
+                        target: Some(target.to_string()).filter(|t| t != "-"),
+                        description: description.to_string(),
+                        plan: Vec::new(),
+                        acknowledge: false,
+                        expires_at: expires_at.parse().map_err(|_| format!("bad expiry in `{}`", rest))?,
+                        commits: match *commits {
+                            "-" => None,
+                            n => Some(n.parse().map_err(|_| format!("bad commit count in `{}`", rest))?),
+                        },
+                    },
+                );
+            }
+            "offline-plan" | "offline-acknowledge" => {
+                let (id, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                let id = parse_id(id)?;
+                let request = self.pending.get_mut(&id).ok_or(format!("`{} {}` names no request above it", word, rest))?;
+                match word {
+                    "offline-plan" => request.plan.push(text.to_string()),
+                    _ => request.acknowledge = true,
+                }
+            }
+            "batch" => {
+                let mut words = rest.split(' ').filter(|w| !w.is_empty());
+                let batch = words.next().ok_or("a batch line names no batch")?.to_string();
+                let ids = words.map(parse_id).collect::<Result<Vec<u64>, String>>()?;
+                self.exported.push((batch, ids));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+fn digest(text: &str) -> String {
+    ed25519::hex(&ed25519::sha512(&[text.as_bytes()])[..16])
+}
+
+pub(crate) fn one_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+/// Split a signed file into its body and signature. The signature
+/// covers every byte before its own line.
+pub(crate) fn split_signed<'a>(text: &'a str, header: &str) -> Result<(&'a str, Signature), String> {
+    if !text.starts_with(&format!("{}\n", header)) {
+        return Err(format!("not an `{}` file", header));
+    }
+    let at = text.rfind("signature ").filter(|&i| text[..i].ends_with('\n')).ok_or("the file is not signed")?;
+    let signature = Signature::from_hex(text[at + "signature ".len()..].trim()).ok_or("malformed signature")?;
+    Ok((&text[..at], signature))
+}
+
+fn parse_id(text: &str) -> Result<u64, String> {
+    u64::from_str_radix(text, 16).map_err(|_| format!("`{}` is not a request id", text))
+}
+
+/// A batch as the approver reads it.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    pub digest: String,
+    pub exported_at: u64,
+    pub requests: Vec<Request>,
+}
+
+impl Batch {
+    /// Parse a batch file and check it was signed by `gate`, whose key
+    /// the approver was given in person, not by the file.
+    pub fn verify(text: &str, gate: &PublicKey) -> Result<Batch, String> {
+        let (body, signature) = split_signed(text, "airgap-batch 1")?;
+        if !gate.verify(body.as_bytes(), &signature) {
+            return Err("the batch is not signed by this gate".to_string());
+        }
+        let mut batch = Batch {
+            digest: digest(text),
+            exported_at: 0,
+            requests: Vec::new(),
+        };
+        for line in body.lines().skip(1) {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match word {
+                "gate" => {}
+                "exported" => batch.exported_at = rest.parse().map_err(|_| "bad `exported` line".to_string())?,
+                "request" => {
+                    let fields: Vec<&str> = rest.splitn(5, ' ').collect();
+                    let [id, operation, expires_at, target, description] = fields.as_slice() else {
+                        return Err(format!("bad request line `{}`", line));
+                    };
+                    batch.requests.push(Request {
+                        id: parse_id(id)?,
+                        operation: OperationKind::from_name(operation)
+                            .ok_or(format!("unknown operation `{}`", operation))?,
+                        target: Some(target.to_string()).filter(|t| t != "-"),
+                        description: description.to_string(),
+                        plan: Vec::new(),
+                        acknowledge: false,
+                        expires_at: expires_at.parse().map_err(|_| format!("bad expiry in `{}`", line))?,
+                        commits: None,
+                    });
+                }
+                "plan" | "acknowledge" => {
+                    let (id, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let id = parse_id(id)?;
+                    let request = batch
+                        .requests
+                        .iter_mut()
+                        .find(|r| r.id == id)
+                        .ok_or(format!("`{}` names no request above it", line))?;
+                    match word {
+                        "plan" => request.plan.push(text.to_string()),
+                        _ => request.acknowledge = true,
+                    }
+                }
+                _ => return Err(format!("unknown line `{}`", line)),
+            }
+        }
+        Ok(batch)
+    }
+}
+
+/// The approver's decision on one request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Grant {
+        reason: Option<String>,
+        /// What the approver typed for an irreversible plan.
+        acknowledgment: Option<String>,
+    },
+    Deny(String),
+}
+
+/// The approver's side, on the disconnected machine.
+pub struct OfflineApprover {
+    key: SigningKey,
+}
+
+impl OfflineApprover {
+    pub fn new(key: SigningKey) -> Self {
+        OfflineApprover { key }
+    }
+
+    /// The key to give the gate's operator for
+    /// [`SafetyGate::trust_offline_approver`].
+    pub fn public_key(&self) -> PublicKey {
+        self.key.public_key()
+    }
+
+    /// A signed response to `batch`. Requests without an answer are
+    /// left out; the gate keeps them pending until they expire.
+    pub fn respond(&self, batch: &Batch, answers: &[(u64, Answer)]) -> String {
+        let mut body = format!(
+            "airgap-response 1\nbatch {}\napprover {}\n",
+            batch.digest,
+            self.key.public_key().to_hex()
+        );
+        for (id, answer) in answers {
+            match answer {
+                Answer::Grant { reason, acknowledgment } => {
+                    body.push_str(&format!("answer {:016x} granted {}\n", id, one_line(reason.as_deref().unwrap_or(""))));
+                    if let Some(typed) = acknowledgment {
+                        body.push_str(&format!("acknowledged {:016x} {}\n", id, one_line(typed)));
+                    }
+                }
+                Answer::Deny(why) => body.push_str(&format!("answer {:016x} denied {}\n", id, one_line(why))),
+            }
+        }
+        let signature = self.key.sign(body.as_bytes());
+        format!("{}signature {}\n", body, signature.to_hex())
+    }
+}
+
+/// A response whose signature, approver, and batch have been checked.
+struct Response {
+    approver: String,
+    batch: String,
+    answers: Vec<(u64, Answer)>,
+}
+
+/// One imported answer.
+pub struct Imported {
+    pub id: u64,
+    pub description: String,
+    pub consent: Result<UserConsent, ConsentDenied>,
+}
+
+impl SafetyGate {
+    /// How long a queued request may wait for its answer. Eight hours
+    /// unless set; a request is refused once it is older, however
+    /// valid the answer.
+    pub fn set_airgap_window(&mut self, window: Duration) {
+        self.airgap.window = window.as_secs();
+    }
+
+    /// Accept answers signed by `key` as `name`'s.
+    pub fn trust_offline_approver(&mut self, name: &str, key: PublicKey) {
+        self.log(format!("OFFLINE APPROVER: '{}' with key {}", name, key.to_hex()));
+        self.airgap.approvers.retain(|(n, _)| n != name);
+        self.airgap.approvers.push((name.to_string(), key));
+    }
+
+    /// The gate's air-gap queue.
+    pub fn airgap(&self) -> &super::airgap::Outbox {
+        &self.airgap
+    }
+
+    /// Queue a request for `repo` to be answered offline. Returns its id.
+    /// An operation the deny-list forbids or the policy denies is
+    /// refused now, not carried across the gap to be refused later.
+    pub fn queue_offline<S, B: GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<u64, ConsentDenied> {
+        let request = self.offline_request(operation, Some(&repo.name), operation_description)?;
+        Ok(self.enqueue(Request {
+            commits: repo.commit_count(),
+            ..request
+        }))
+    }
+
+    /// Queue an itemized plan. An irreversible plan needs the typed
+    /// acknowledgment in the response, as it would at the console.
+    pub fn queue_offline_plan(&mut self, plan: &DestructionPlan) -> Result<u64, ConsentDenied> {
+        let description = format!("{} on '{}' ({} items)", plan.operation.name(), plan.target, plan.items.len());
+        let request = self.offline_request(plan.operation, Some(&plan.target), &description)?;
+        Ok(self.enqueue(Request {
+            plan: plan.render().lines().skip(1).map(|l| l.trim().to_string()).collect(),
+            acknowledge: plan.is_irreversible(),
+            ..request
+        }))
+    }
+
+    fn offline_request(
+        &mut self,
+        operation: OperationKind,
+        target: Option<&str>,
+        operation_description: &str,
+    ) -> Result<Request, ConsentDenied> {
+        self.screen_forbidden(operation, target, operation_description)?;
+        if self.policy.decide(operation) == policy::Decision::Deny {
+            self.log(format!("DENIED BY POLICY: {}", operation_description));
+            let denied = ConsentDenied::new(
+                operation,
+                format!("policy {} denies {}", self.policy.version, operation.name()),
+            );
+            self.notify_denial(&denied);
+            return Err(denied);
+        }
+        if self.policy.decide(operation) == policy::Decision::RequireConsent {
+            self.screen_throttle(operation, operation_description)?;
+        }
+        Ok(Request {
+            id: random_u64(),
+            operation,
+            target: target.map(str::to_string),
+            description: one_line(operation_description),
+            plan: Vec::new(),
+            acknowledge: false,
+            expires_at: unix_now().saturating_add(self.airgap.window),
+            commits: None,
+        })
+    }
+
+    fn enqueue(&mut self, request: Request) -> u64 {
+        let id = request.id;
+        self.log(format!(
+            "QUEUED OFFLINE: {} (request {:016x}, expires {})",
+            request.description,
+            id,
+            super::report::format_utc(request.expires_at)
+        ));
+        self.airgap.pending.insert(id, request);
+        self.save_pending();
+        id
+    }
+
+    /// Every unexpired pending request, as a batch file signed with
+    /// the gate's consent key. A request may go out in more than one
+    /// batch if a file is lost; it is still answered only once.
+    pub fn export_batch(&mut self) -> String {
+        let now = unix_now();
+        let requests: Vec<&Request> = self.airgap.pending.values().filter(|r| r.expires_at >= now).collect();
+        let mut body = format!(
+            "airgap-batch 1\ngate {}\nexported {}\n",
+            self.signing_key.public_key().to_hex(),
+            now
+        );
+        for request in &requests {
+            body.push_str(&format!(
+                "request {:016x} {} {} {} {}\n",
+                request.id,
+                request.operation.name(),
+                request.expires_at,
+                request.target.as_deref().unwrap_or("-"),
+                request.description
+            ));
+            for line in &request.plan {
+                body.push_str(&format!("plan {:016x} {}\n", request.id, line));
+            }
+            if request.acknowledge {
+                body.push_str(&format!("acknowledge {:016x}\n", request.id));
+            }
+        }
+        let text = format!("{}signature {}\n", body, self.signing_key.sign(body.as_bytes()).to_hex());
+        let ids: Vec<u64> = requests.iter().map(|r| r.id).collect();
+        let batch = digest(&text);
+        self.log(format!("AIRGAP EXPORT: batch {} with {} requests", batch, ids.len()));
+        self.airgap.exported.push((batch, ids));
+        self.save_pending();
+        text
+    }
+
+    /// Import a signed response. The file as a whole is rejected if
+    /// the approver is not trusted, the signature does not verify, the
+    /// batch it answers was not exported here, or it answers anything
+    /// not pending in that batch. Otherwise each answer becomes
+    /// consent or a denial on its own.
+    pub fn import_responses(&mut self, text: &str) -> Result<Vec<Imported>, String> {
+        let response = match self.verify_response(text) {
+            Ok(response) => response,
+            Err(why) => {
+                self.log(format!("AIRGAP REJECTED: {}", why));
+                return Err(why);
+            }
+        };
+        self.log(format!(
+            "AIRGAP IMPORT: {} answers from '{}' for batch {}",
+            response.answers.len(),
+            response.approver,
+            response.batch
+        ));
+        let mut imported = Vec::new();
+        for (id, answer) in response.answers {
+            // The nonce is spent whatever the answer says.
+            let Some(request) = self.airgap.pending.remove(&id) else {
+                continue;
+            };
+            let consent = self.answer_offline(&response.approver, &request, answer);
+            imported.push(Imported {
+                id,
+                description: request.description,
+                consent,
+            });
+        }
+        self.save_pending();
+        Ok(imported)
+    }
+
+    fn verify_response(&self, text: &str) -> Result<Response, String> {
+        let (body, signature) = split_signed(text, "airgap-response 1")?;
+        let mut batch = None;
+        let mut approver = None;
+        let mut answers: Vec<(u64, Answer)> = Vec::new();
+        for line in body.lines().skip(1) {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match word {
+                "batch" => batch = Some(rest.to_string()),
+                "approver" => {
+                    let key = PublicKey::from_hex(rest).ok_or("malformed approver key")?;
+                    let (name, _) = self
+                        .airgap
+                        .approvers
+                        .iter()
+                        .find(|(_, k)| *k == key)
+                        .ok_or(format!("approver key {} is not trusted", rest))?;
+                    approver = Some((name.clone(), key));
+                }
+                "answer" => {
+                    let fields: Vec<&str> = rest.splitn(3, ' ').collect();
+                    let [id, verdict, text] = fields.as_slice() else {
+                        return Err(format!("bad answer line `{}`", line));
+                    };
+                    let id = parse_id(id)?;
+                    if answers.iter().any(|(i, _)| *i == id) {
+                        return Err(format!("request {:016x} is answered twice", id));
+                    }
+                    let answer = match *verdict {
+                        "granted" => Answer::Grant {
+                            reason: Some(text.to_string()).filter(|r| !r.trim().is_empty()),
+                            acknowledgment: None,
+                        },
+                        "denied" => Answer::Deny(text.to_string()),
+                        other => return Err(format!("unknown verdict `{}`", other)),
+                    };
+                    answers.push((id, answer));
+                }
+                "acknowledged" => {
+                    let (id, typed) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let id = parse_id(id)?;
+                    match answers.iter_mut().find(|(i, _)| *i == id) {
+                        Some((_, Answer::Grant { acknowledgment, .. })) => *acknowledgment = Some(typed.to_string()),
+                        _ => return Err(format!("`{}` follows no grant", line)),
+                    }
+                }
+                _ => return Err(format!("unknown line `{}`", line)),
+            }
+        }
+        let (name, key) = approver.ok_or("the response names no approver")?;
+        if !key.verify(body.as_bytes(), &signature) {
+            return Err(format!("signature does not verify against '{}'s key", name));
+        }
+        let batch = batch.ok_or("the response names no batch")?;
+        let (_, ids) = self
+            .airgap
+            .exported
+            .iter()
+            .find(|(d, _)| *d == batch)
+            .ok_or(format!("batch {} was not exported by this gate", batch))?;
+        // A replayed or forged answer taints the file it came in.
+        if let Some((id, _)) = answers.iter().find(|(id, _)| !ids.contains(id) || !self.airgap.pending.contains_key(id)) {
+            return Err(format!("request {:016x} is not pending in batch {}: never queued, or already answered", id, batch));
+        }
+        Ok(Response {
+            approver: name,
+            batch,
+            answers,
+        })
+    }
+
+    fn answer_offline(&mut self, approver: &str, request: &Request, answer: Answer) -> Result<UserConsent, ConsentDenied> {
+        let refuse = |gate: &mut SafetyGate, event: String, reason: String| {
+            gate.log(event);
+            let denied = ConsentDenied::new(request.operation, reason);
+            gate.notify_denial(&denied);
+            Err(denied)
+        };
+        if unix_now() > request.expires_at {
+            return refuse(
+                self,
+                format!("DENIED (offline answer expired): {}", request.description),
+                format!(
+                    "the request expired at {}, before the answer arrived",
+                    super::report::format_utc(request.expires_at)
+                ),
+            );
+        }
+        let (reason, acknowledgment) = match answer {
+            Answer::Deny(why) => {
+                self.log(format!("DENIED BY APPROVER {} (offline): {} ({})", approver, request.description, why));
+                let denied =
+                    ConsentDenied::new(request.operation, format!("the approver declined: {}", why)).by_approver(approver);
+                self.notify_denial(&denied);
+                return Err(denied);
+            }
+            Answer::Grant { reason, acknowledgment } => (reason, acknowledgment),
+        };
+        if request.acknowledge && acknowledgment.as_deref() != Some(IRREVERSIBILITY_ACKNOWLEDGMENT) {
+            return refuse(
+                self,
+                format!("DENIED (irreversibility not acknowledged): {}", request.description),
+                "irreversible plan not acknowledged".to_string(),
+            );
+        }
+        if let Some(reason) = reason {
+            self.give_reason(&reason);
+        }
+        self.answered_by = Some(format!("air-gapped approver '{}'", approver));
+        self.target = request.target.clone();
+        self.target_commits = request.commits;
+        let mut consent: UserConsent = self.mint(request.operation, &request.description)?;
+        // The gap does not extend the request's life.
+        consent._expires_at = consent._expires_at.min(request.expires_at);
+        Ok(consent)
+    }
+}
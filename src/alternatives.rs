@@ -0,0 +1,136 @@
+//! The safe version of every destructive operation.
+//!
+//! A refusal that names one way round it still leaves the agent guessing
+//! whether that way fits. The registry lists, for each operation, the
+//! equivalents that get the same job done without the destructive part —
+//! revert instead of reset, a new commit instead of amend and force-push,
+//! archive instead of delete — most generally useful first, with the
+//! command where there is one. Playbooks, denial feedback, the approver's
+//! console, and MCP error payloads all draw from it.
+
+#[cfg(feature = "std")]
+use super::json_string;
+use super::OperationKind;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// One safer way to do what an operation is usually for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alternative {
+    pub instead: &'static str,
+    /// The command, where one does it.
+    pub command: Option<&'static str>,
+}
+
+impl Alternative {
+    pub fn render(&self) -> String {
+        match self.command {
+            Some(command) => format!("{} (`{}`)", self.instead, command),
+            None => self.instead.to_string(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"instead\":{},\"command\":{}}}",
+            json_string(self.instead),
+            self.command.map(json_string).unwrap_or_else(|| "null".to_string())
+        )
+    }
+}
+
+/// The registered alternatives for `operation`, most useful first.
+/// Never empty.
+pub fn for_operation(operation: OperationKind) -> Vec<Alternative> {
+    table(operation).iter().map(|&(instead, command)| Alternative { instead, command }).collect()
+}
+
+fn table(operation: OperationKind) -> &'static [(&'static str, Option<&'static str>)] {
+    match operation {
+        OperationKind::RemoveProtection => &[
+            ("add a one-time bypass for a single actor instead of lifting protection", None),
+            ("open a pull request and have it merged through the protection", Some("gh pr create")),
+        ],
+        OperationKind::ForcePush => &[
+            ("push to a new branch and open a pull request", Some("git push origin HEAD:refs/heads/<new-branch>")),
+            ("add a new commit on top instead of amending, and push normally", Some("git commit && git push")),
+            ("undo a pushed commit with a revert commit", Some("git revert <commit> && git push")),
+        ],
+        OperationKind::FilterRepo => &[
+            ("fix attribution going forward with .mailmap; leave history intact", Some("git shortlog -se  # check .mailmap")),
+            ("record corrections as notes on the affected commits", Some("git notes append -m <correction> <commit>")),
+        ],
+        OperationKind::ResetHard => &[
+            ("git stash, then git reset --keep", Some("git stash && git reset --keep <commit>")),
+            ("undo committed changes with a revert instead of a reset", Some("git revert <commit>")),
+            ("look at the other state on a new branch", Some("git switch -c <name> <commit>")),
+        ],
+        OperationKind::DeleteBranch | OperationKind::ForceDeleteBranch => &[
+            ("archive the branch under refs/archive/ instead of deleting it", Some("git update-ref refs/archive/<branch> <branch>")),
+            ("tag the branch tip before anything else happens to it", Some("git tag archive/<branch> <branch>")),
+        ],
+        OperationKind::DeleteTag => &[
+            ("leave the tag, and tag the corrected commit under a new name", Some("git tag <new-tag> <commit>")),
+            ("record the tag's commit before anything else happens to it", Some("git rev-parse <tag>^{commit}")),
+        ],
+        OperationKind::Rebase => &[
+            ("merge the upstream branch instead of replaying onto it", Some("git merge <upstream>")),
+            ("rebase a copy on a new branch, leaving the original alone", Some("git switch -c <name> && git rebase <upstream>")),
+        ],
+        OperationKind::StripAttribution => &[
+            ("keep the trailers: attribution is history, not formatting", None),
+            ("correct how names display with .mailmap, leaving commits intact", Some("git shortlog -se  # check .mailmap")),
+        ],
+        OperationKind::DropStash => &[
+            ("turn the stash into a branch instead of dropping it", Some("git stash branch <name> <stash>")),
+            ("apply the stash and commit it on a scratch branch", Some("git switch -c <name> && git stash apply <stash>")),
+        ],
+        OperationKind::RemoveRemote => &[
+            ("point the remote at the new URL instead of removing it", Some("git remote set-url <remote> <url>")),
+            ("rename the remote, keeping its refs", Some("git remote rename <remote> <new-name>")),
+        ],
+        OperationKind::CiConfigChange => &[("open a pull request for review by the pipeline's code owners", Some("gh pr create"))],
+        OperationKind::DeleteFiles => &[
+            ("move the tree to a dated trash directory", Some("mv <path> .trash/$(date +%F)/")),
+            ("remove the files from the index only, keeping them on disk", Some("git rm --cached -r <path>")),
+        ],
+        OperationKind::TransferOwnership => &[("add the new owner as an admin collaborator", None)],
+        OperationKind::EditSchedule => &[
+            ("add a new entry alongside the existing ones", None),
+            ("comment the entry out instead of deleting it", None),
+        ],
+        OperationKind::DeleteImageTag => &[("let the registry's retention policy expire the tag", None)],
+        OperationKind::OverwriteImageTag => &[
+            ("push under a new immutable tag and move deployments to it", None),
+            ("pin deployments to the image digest instead of a tag", None),
+        ],
+        OperationKind::DeleteImageRepository => &[("make the repository private and stop pushing to it", None)],
+        OperationKind::DeleteIndex => &[
+            ("snapshot the index, remove it from its aliases, and delete it after a retention period", None),
+            ("close the index instead of deleting it", None),
+        ],
+        OperationKind::DeleteByQuery => &[("reindex the documents to keep into a new index and move the alias to it", None)],
+        OperationKind::CloseIndex => &[("remove the index from its aliases and leave it open", None)],
+        OperationKind::DeleteTopic => &[("stop producing to the topic and let retention empty it", None)],
+        OperationKind::PurgeQueue => &[("move the messages to a dead-letter queue for inspection", None)],
+        OperationKind::ResetConsumerOffsets => &[("start a new consumer group at the offset you need", None)],
+        OperationKind::ChangeVisibility => {
+            &[("add or remove collaborators instead of changing who can see the repository", None)]
+        }
+        OperationKind::MakePublic => &[("publish a new repository holding the reviewed tree and none of the history", None)],
+        OperationKind::ChangeLicense => {
+            &[("license new releases under the new terms after legal review; leave old ones as they are", None)]
+        }
+        OperationKind::DeleteBackup => &[
+            ("move the backup to cold storage instead of deleting it", None),
+            ("take a new full backup first, so nothing depends on the one going", None),
+        ],
+        OperationKind::ShortenRetention => &[("keep the retention and move older backups to a cheaper storage class", None)],
+        OperationKind::DisableBackupSchedule => &[
+            ("lower the schedule's frequency instead of switching it off", None),
+            ("pause the schedule with an expiry, so it resumes on its own", None),
+        ],
+    }
+}
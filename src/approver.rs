@@ -0,0 +1,291 @@
+//! Approver profiles and accessible prompt rendering.
+//!
+//! The gate describes what it shows as structured [`Line`]s — a notice, a
+//! diff line, a phrase to type — and the approver's profile decides how
+//! each is rendered. The standard style uses the familiar `[GATE]` prefixes
+//! and diff markers. The accessible style spells out what each line is, so
+//! a screen reader announces "Removed: ..." rather than "minus", and a
+//! phrase to type is introduced as one. Neither style ever signals anything
+//! with color alone: severity is always a word.
+
+use super::SafetyGate;
+use std::io::BufRead;
+
+/// One line of a prompt, by meaning rather than by look.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    Notice(String),
+    Warning(String),
+    /// A phrase the approver must type exactly.
+    Challenge(String),
+    Heading(String),
+    Item(String),
+    /// Supporting context, such as recent commits.
+    Detail(String),
+    File(String),
+    /// One line of a unified diff, marker included.
+    Diff(String),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PromptStyle {
+    #[default]
+    Standard,
+    Accessible,
+}
+
+/// How much supporting context is shown. What is being approved —
+/// plans, diffs, phrases to type — is shown at every level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Omit approval context such as recent commits and code owners.
+    Brief,
+    #[default]
+    Normal,
+    /// Also state each operation's severity and safer alternative.
+    Verbose,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ApproverProfile {
+    pub name: String,
+    pub style: PromptStyle,
+    pub verbosity: Verbosity,
+}
+
+impl ApproverProfile {
+    pub fn accessible(name: &str) -> Self {
+        ApproverProfile {
+            name: name.to_string(),
+            style: PromptStyle::Accessible,
+            verbosity: Verbosity::Normal,
+        }
+    }
+
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    pub fn render(&self, line: &Line) -> String {
+        match self.style {
+            PromptStyle::Standard => match line {
+                Line::Notice(t) => format!("  [GATE] {}", t),
+                Line::Warning(t) => format!("  [GATE] WARNING: {}", t),
+                Line::Challenge(p) => format!("  [GATE] To confirm, type: {}", p),
+                Line::Heading(t) => format!("  {}", t),
+                Line::Item(t) => format!("      - {}", t),
+                Line::Detail(t) | Line::Diff(t) => format!("    {}", t),
+                Line::File(p) => format!("  --- {}", p),
+            },
+            PromptStyle::Accessible => match line {
+                Line::Notice(t) => format!("Gate: {}", t),
+                Line::Warning(t) => format!("Warning: {}", t),
+                Line::Challenge(p) => format!("Confirmation required. Type this phrase exactly: {}", p),
+                Line::Heading(t) | Line::Detail(t) => t.trim().to_string(),
+                Line::Item(t) => format!("Item: {}", t),
+                Line::File(p) => format!("File: {}", p),
+                Line::Diff(t) => {
+                    let rest = t.get(1..).unwrap_or("").trim_start();
+                    match t.chars().next() {
+                        Some('+') => format!("Added: {}", rest),
+                        Some('-') => format!("Removed: {}", rest),
+                        Some('@') => format!("Location: {}", t),
+                        _ => format!("Unchanged: {}", rest),
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// How the approver is asked to reply to a challenge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// Type `yes`.
+    Yes,
+    /// Type the phrase the gate showed.
+    Phrase,
+    /// Type the current code from an authenticator.
+    Code,
+}
+
+/// What a responder is told about the challenge it answers: how the
+/// approver is asked to reply, and never what the reply must be. The
+/// phrase itself is shown to the approver, not passed to responders, so
+/// a responder cannot type it back unread.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Prompt {
+    reply: Reply,
+    label: String,
+    expected: Option<String>,
+}
+
+impl Prompt {
+    /// Ask for `phrase` to be typed back.
+    pub(crate) fn phrase(phrase: &str) -> Self {
+        Prompt {
+            reply: if phrase == "yes" { Reply::Yes } else { Reply::Phrase },
+            label: "the phrase shown".to_string(),
+            expected: Some(phrase.to_string()),
+        }
+    }
+
+    /// Ask for an authenticator code, which the gate does not know in
+    /// advance either.
+    pub(crate) fn code(label: &str) -> Self {
+        Prompt {
+            reply: Reply::Code,
+            label: label.to_string(),
+            expected: None,
+        }
+    }
+
+    pub fn reply(&self) -> Reply {
+        self.reply
+    }
+
+    /// What to ask the approver for, in words.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The answer that would match, for the channels in this crate that
+    /// carry it to the approver.
+    pub(crate) fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+}
+
+impl std::fmt::Debug for Prompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Prompt").field("reply", &self.reply).field("label", &self.label).finish_non_exhaustive()
+    }
+}
+
+/// Whoever types the answer to a consent challenge.
+pub trait Responder {
+    fn name(&self) -> &str;
+    /// The line typed in response to `prompt`, or `None` if no answer
+    /// came.
+    fn answer(&mut self, prompt: &Prompt) -> Option<String>;
+    /// Each line the gate shows, as rendered, except the challenge
+    /// itself. A responder that is not at the terminal needs to pass
+    /// them on.
+    fn shown(&mut self, _line: &str) {}
+}
+
+/// An answer that says no: `no`, or `no` followed by the reason, e.g.
+/// `no: the release is still going out`. Returns the reason.
+pub fn refusal(typed: &str) -> Option<String> {
+    let typed = typed.trim();
+    let rest = typed.get(..2).filter(|w| w.eq_ignore_ascii_case("no")).map(|_| &typed[2..])?;
+    let why = match rest.chars().next() {
+        None => "",
+        Some(':' | ' ' | ',' | '.' | '-') => rest.trim_start_matches([':', ',', '.', '-']).trim(),
+        Some(_) => return None,
+    };
+    Some(if why.is_empty() { "no reason given".to_string() } else { why.to_string() })
+}
+
+/// The approver at the terminal: reads one line from stdin.
+pub struct Console;
+
+impl Responder for Console {
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+        let mut line = String::new();
+        match std::io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line),
+        }
+    }
+}
+
+/// Types every challenge back unread. For replays, chaos runs, and tests
+/// where nobody is at the terminal: a gate using it asks no one, and its
+/// audit log says so. Outside this crate, only a
+/// [`rehearsal`](SafetyGate::rehearsal) gate has one.
+pub(crate) struct Rehearsal;
+
+impl Responder for Rehearsal {
+    fn name(&self) -> &str {
+        "rehearsal (challenges answered automatically; no human asked)"
+    }
+
+    fn answer(&mut self, prompt: &Prompt) -> Option<String> {
+        prompt.expected().map(str::to_string)
+    }
+}
+
+impl SafetyGate {
+    /// A gate for demonstrations where nobody is at the terminal. It
+    /// answers its own challenges, so it only observes: every consent
+    /// it mints is simulated, and its audit log says no human was asked.
+    pub fn rehearsal() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.observe();
+        gate.set_responder(Box::new(Rehearsal));
+        gate
+    }
+
+    /// Render prompts for this approver from now on.
+    pub fn set_approver_profile(&mut self, profile: ApproverProfile) {
+        self.log(format!(
+            "APPROVER PROFILE: '{}' ({:?}, {:?})",
+            profile.name, profile.style, profile.verbosity
+        ));
+        self.profile = profile;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Prompt, Responder};
+    use crate::observe::GateMode;
+    use crate::{OperationKind, SafetyGate};
+
+    /// Types back whatever it was told: every line shown, and the label.
+    #[derive(Default)]
+    struct Parrot {
+        heard: Vec<String>,
+    }
+
+    impl Responder for Parrot {
+        fn name(&self) -> &str {
+            "parrot"
+        }
+
+        fn answer(&mut self, prompt: &Prompt) -> Option<String> {
+            self.heard.push(prompt.label().to_string());
+            self.heard.push(format!("{:?}", prompt));
+            let words = self.heard.iter().flat_map(|line| line.split_whitespace());
+            words.last().map(str::to_string)
+        }
+
+        fn shown(&mut self, line: &str) {
+            self.heard.push(line.to_string());
+        }
+    }
+
+    #[test]
+    fn a_responder_is_never_given_the_phrase() {
+        let prompt = Prompt::phrase("4fecf18c");
+        assert!(!format!("{:?}", prompt).contains("4fecf18c"));
+        assert!(!prompt.label().contains("4fecf18c"));
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Parrot::default()));
+        let denied = gate.request_consent(OperationKind::ForcePush, "force-push main").err().expect("no phrase to parrot");
+        assert!(denied.reason.contains("did not match"), "{}", denied.reason);
+    }
+
+    #[test]
+    fn a_rehearsal_gate_only_observes() {
+        let mut gate = SafetyGate::rehearsal();
+        assert_eq!(gate.mode(), GateMode::Observe);
+        assert!(gate.request_consent(OperationKind::ForcePush, "force-push main").is_ok());
+    }
+}
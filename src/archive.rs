@@ -0,0 +1,474 @@
+//! Lifecycle management for the audit log.
+//!
+//! Entries older than a threshold are cut into a segment, signed, gzipped,
+//! and written to a [`ColdStore`]. Each segment's header carries the digest
+//! of the segment before it, so the archive is one SHA-256 chain: dropping,
+//! reordering, or editing a segment breaks verification at that boundary.
+//! Headers are signed with Ed25519, so checking an archive takes only the
+//! archive's public key.
+//! [`AuditArchive::retrieve`] pulls back the segments overlapping a time
+//! range and verifies them, and the link to the segment before them,
+//! before returning a single entry.
+
+use super::ceremony::Cast;
+use super::ed25519::{self, PublicKey, Signature, SigningKey};
+use super::wire::{decode_audit_entry, encode_audit_entry, encode_receipt};
+use super::{AuditEntry, Receipt, SafetyGate};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// The `prev` of the first segment.
+pub const GENESIS: &str = "genesis";
+
+/// Somewhere segments can be put and fetched by name.
+pub trait ColdStore {
+    fn put(&mut self, name: &str, bytes: &[u8]) -> io::Result<()>;
+    fn get(&self, name: &str) -> io::Result<Vec<u8>>;
+    fn list(&self) -> io::Result<Vec<String>>;
+}
+
+/// Segments as files in a directory.
+pub struct DirStore {
+    pub root: PathBuf,
+}
+
+impl ColdStore for DirStore {
+    fn put(&mut self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        let tmp = self.root.join(format!(".{}.tmp", name));
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, self.root.join(name))
+    }
+
+    fn get(&self, name: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(name))
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        match fs::read_dir(&self.root) {
+            Ok(dir) => dir
+                .map(|e| e.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .filter(|n| n.as_ref().map_or(true, |n| n.ends_with(".seg")))
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Segments in an S3 bucket, through the `aws` CLI and its usual
+/// credential chain.
+pub struct S3Store {
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl S3Store {
+    fn url(&self, name: &str) -> String {
+        format!("s3://{}/{}{}", self.bucket, self.prefix, name)
+    }
+}
+
+impl ColdStore for S3Store {
+    fn put(&mut self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        pipe("aws", &["s3", "cp", "-", &self.url(name)], bytes).map(|_| ())
+    }
+
+    fn get(&self, name: &str) -> io::Result<Vec<u8>> {
+        pipe("aws", &["s3", "cp", &self.url(name), "-"], &[])
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let out = pipe("aws", &["s3", "ls", &self.url("")], &[])?;
+        Ok(String::from_utf8_lossy(&out)
+            .lines()
+            .filter_map(|l| l.split_whitespace().last())
+            .filter(|n| n.ends_with(".seg"))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Run `program` with `input` on stdin and return its stdout.
+fn pipe(program: &str, args: &[&str], input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let out = child.wait_with_output()?;
+    writer.join().map_err(|_| io::Error::other("stdin writer panicked"))??;
+    if !out.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+    Ok(out.stdout)
+}
+
+fn gzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+fn gunzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(bytes.to_vec());
+    }
+    let mut out = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// SHA-256 of the previous segment's digest and this segment's lines.
+fn digest(prev: &str, lines: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    for line in lines {
+        hasher.update(line);
+        hasher.update(b"\n");
+    }
+    format!("sha256:{}", ed25519::hex(&hasher.finalize()))
+}
+
+/// A segment's header: `segment <index> <first_at> <last_at> <prev> <digest> <signature>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentHeader {
+    pub index: u64,
+    pub first_at: u64,
+    pub last_at: u64,
+    pub prev: String,
+    pub digest: String,
+    pub signature: Signature,
+}
+
+impl SegmentHeader {
+    fn name(&self) -> String {
+        format!("{:08}.seg", self.index)
+    }
+
+    fn signed_message(&self) -> String {
+        format!("segment|{}|{}|{}", self.index, self.prev, self.digest)
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "segment {} {} {} {} {} {}",
+            self.index,
+            self.first_at,
+            self.last_at,
+            self.prev,
+            self.digest,
+            self.signature.to_hex()
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let f: Vec<&str> = line.split(' ').collect();
+        match f.as_slice() {
+            ["segment", index, first, last, prev, digest, signature] => Some(SegmentHeader {
+                index: index.parse().ok()?,
+                first_at: first.parse().ok()?,
+                last_at: last.parse().ok()?,
+                prev: prev.to_string(),
+                digest: digest.to_string(),
+                signature: Signature::from_hex(signature)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    Malformed(String),
+    /// The header's signature does not verify under the archive's public key.
+    BadSignature(u64),
+    /// The segment's entries do not hash to its recorded digest.
+    Tampered(u64),
+    /// The segment does not follow the one before it.
+    BrokenChain(u64),
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(err: io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+impl std::fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::Io(err) => write!(f, "{}", err),
+            ArchiveError::Malformed(why) => write!(f, "malformed segment: {}", why),
+            ArchiveError::BadSignature(i) => write!(f, "segment {} has a bad signature", i),
+            ArchiveError::Tampered(i) => write!(f, "segment {} does not match its digest", i),
+            ArchiveError::BrokenChain(i) => write!(f, "segment {} does not follow segment {}", i, i.wrapping_sub(1)),
+        }
+    }
+}
+
+/// Archived audit segments and the key that signs them.
+pub struct AuditArchive {
+    store: Box<dyn ColdStore>,
+    key: SigningKey,
+    /// Digest of the newest segment; the next segment's `prev`.
+    head: String,
+    next_index: u64,
+}
+
+impl AuditArchive {
+    /// Open an archive, continuing the chain from its newest segment.
+    pub fn open(store: Box<dyn ColdStore>, key: SigningKey) -> Result<Self, ArchiveError> {
+        let mut archive = AuditArchive {
+            store,
+            key,
+            head: GENESIS.to_string(),
+            next_index: 0,
+        };
+        if let Some(newest) = archive.segment_names()?.pop() {
+            let (header, _) = archive.fetch(&newest)?;
+            archive.head = header.digest;
+            archive.next_index = header.index + 1;
+        }
+        Ok(archive)
+    }
+
+    /// The key the archive's segments verify under. Publish it where
+    /// auditors can find it; the signing key stays with the archive.
+    pub fn public_key(&self) -> PublicKey {
+        self.key.public_key()
+    }
+
+    fn segment_names(&self) -> Result<Vec<String>, ArchiveError> {
+        let mut names = self.store.list()?;
+        names.sort();
+        Ok(names)
+    }
+
+    fn fetch(&self, name: &str) -> Result<(SegmentHeader, Vec<String>), ArchiveError> {
+        let text = String::from_utf8(gunzip(&self.store.get(name)?)?)
+            .map_err(|_| ArchiveError::Malformed(format!("{} is not UTF-8", name)))?;
+        let mut lines = text.lines();
+        let header = lines
+            .next()
+            .and_then(SegmentHeader::parse)
+            .ok_or_else(|| ArchiveError::Malformed(format!("{} has no header", name)))?;
+        let entries: Vec<String> = lines.map(str::to_string).collect();
+        if !self.public_key().verify(header.signed_message().as_bytes(), &header.signature) {
+            return Err(ArchiveError::BadSignature(header.index));
+        }
+        if digest(&header.prev, &entries) != header.digest {
+            return Err(ArchiveError::Tampered(header.index));
+        }
+        Ok((header, entries))
+    }
+
+    /// Seal `entries` as the next segment. Returns its header.
+    pub fn append(&mut self, entries: &[AuditEntry]) -> Result<Option<SegmentHeader>, ArchiveError> {
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Ok(None);
+        };
+        let lines: Vec<String> = entries.iter().map(encode_audit_entry).collect();
+        let mut header = SegmentHeader {
+            index: self.next_index,
+            first_at: first.at,
+            last_at: last.at,
+            digest: digest(&self.head, &lines),
+            prev: self.head.clone(),
+            signature: Signature([0; 64]),
+        };
+        header.signature = self.key.sign(header.signed_message().as_bytes());
+        let mut text = header.render();
+        for line in &lines {
+            text.push('\n');
+            text.push_str(line);
+        }
+        self.store.put(&header.name(), &gzip(text.as_bytes())?)?;
+        self.head = header.digest.clone();
+        self.next_index += 1;
+        Ok(Some(header))
+    }
+
+    /// Every entry recorded in `range` (seconds since the Unix epoch),
+    /// from the segments that overlap it. Each segment is verified, and
+    /// so is its link to the segment before it.
+    pub fn retrieve(&self, range: Range<u64>) -> Result<Vec<AuditEntry>, ArchiveError> {
+        let mut out = Vec::new();
+        let mut prev: Option<SegmentHeader> = None;
+        for name in self.segment_names()? {
+            let (header, lines) = self.fetch(&name)?;
+            let expected = prev.as_ref().map_or(GENESIS, |p| p.digest.as_str());
+            if header.prev != expected {
+                return Err(ArchiveError::BrokenChain(header.index));
+            }
+            if header.first_at < range.end && header.last_at >= range.start {
+                for line in &lines {
+                    let entry = decode_audit_entry(line).map_err(|e| ArchiveError::Malformed(format!("{:?}", e)))?;
+                    if range.contains(&entry.at) {
+                        out.push(entry);
+                    }
+                }
+            }
+            if header.first_at >= range.end {
+                break;
+            }
+            prev = Some(header);
+        }
+        Ok(out)
+    }
+
+    /// Verify the whole chain, genesis to head. Returns the segment count.
+    pub fn verify_chain(&self) -> Result<u64, ArchiveError> {
+        let mut expected = GENESIS.to_string();
+        let mut count = 0;
+        for name in self.segment_names()? {
+            let (header, _) = self.fetch(&name)?;
+            if header.prev != expected || header.index != count {
+                return Err(ArchiveError::BrokenChain(header.index));
+            }
+            expected = header.digest;
+            count += 1;
+        }
+        if expected != self.head {
+            return Err(ArchiveError::BrokenChain(count));
+        }
+        Ok(count)
+    }
+
+    /// Write an evidence bundle for `receipt` to `dir`: the receipt and
+    /// the archive's signature over it, every segment, and the approval
+    /// recording if there is one. `safe-verify` checks it without this
+    /// crate, against the [`public_key`](Self::public_key) the auditor got
+    /// from you separately; the bundle carries no key of its own.
+    pub fn export_bundle(&self, dir: &Path, receipt: &Receipt, cast: Option<&Cast>) -> Result<(), ArchiveError> {
+        fs::create_dir_all(dir.join("segments"))?;
+        let encoded = encode_receipt(receipt);
+        let signature = self.key.sign(format!("receipt|{}", encoded).as_bytes());
+        fs::write(dir.join("receipt.json"), encoded + "\n")?;
+        fs::write(dir.join("receipt.sig"), signature.to_hex() + "\n")?;
+        for name in self.segment_names()? {
+            fs::write(dir.join("segments").join(&name), self.store.get(&name)?)?;
+        }
+        if let Some(cast) = cast {
+            fs::write(dir.join("ceremony.cast"), cast.to_asciicast())?;
+        }
+        Ok(())
+    }
+}
+
+impl SafetyGate {
+    /// Move audit entries older than `max_age` into the archive. They
+    /// leave the in-memory log only once their segment is stored.
+    pub fn archive_audit(
+        &mut self,
+        archive: &mut AuditArchive,
+        max_age: Duration,
+    ) -> Result<Option<SegmentHeader>, ArchiveError> {
+        let cutoff = self.clock().saturating_sub(max_age.as_secs());
+        let old = self.audit.entries.iter().take_while(|e| e.at < cutoff).count();
+        let sealed = archive.append(&self.audit.entries[..old])?;
+        if let Some(header) = &sealed {
+            self.audit.entries.drain(..old);
+            self.log(format!(
+                "AUDIT ARCHIVED: segment {} ({} entries, {})",
+                header.index, old, header.digest
+            ));
+        }
+        Ok(sealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchiveError, AuditArchive, DirStore};
+    use crate::ed25519::SigningKey;
+    use crate::AuditEntry;
+    use std::path::PathBuf;
+
+    fn store(name: &str) -> (PathBuf, Box<DirStore>) {
+        let root = std::env::temp_dir().join(format!("safe-operations-archive-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        (root.clone(), Box::new(DirStore { root }))
+    }
+
+    fn entries(at: &[u64]) -> Vec<AuditEntry> {
+        at.iter()
+            .map(|&at| AuditEntry {
+                at,
+                policy_version: "test".to_string(),
+                event: format!("GRANTED at {}", at),
+                blocked: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segments_chain_and_come_back_verified() {
+        let (root, store) = store("chain");
+        let mut archive = AuditArchive::open(store, SigningKey::from_seed([1; 32])).unwrap();
+        let first = archive.append(&entries(&[10, 20])).unwrap().unwrap();
+        let second = archive.append(&entries(&[30])).unwrap().unwrap();
+        assert!(first.digest.starts_with("sha256:"));
+        assert_eq!(second.prev, first.digest);
+        assert_eq!(archive.verify_chain().unwrap(), 2);
+        let found = archive.retrieve(15..31).unwrap();
+        assert_eq!(found.iter().map(|e| e.at).collect::<Vec<_>>(), [20, 30]);
+
+        let reopened = AuditArchive::open(Box::new(DirStore { root: root.clone() }), SigningKey::from_seed([1; 32]));
+        assert_eq!(reopened.unwrap().verify_chain().unwrap(), 2);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn a_segment_verifies_only_under_the_archive_key() {
+        let (root, store) = store("key");
+        let mut archive = AuditArchive::open(store, SigningKey::from_seed([1; 32])).unwrap();
+        archive.append(&entries(&[10])).unwrap();
+        let other = AuditArchive::open(Box::new(DirStore { root: root.clone() }), SigningKey::from_seed([2; 32]));
+        assert!(matches!(other.err(), Some(ArchiveError::BadSignature(0))));
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn a_bundle_carries_no_key() {
+        let (root, store) = store("bundle");
+        let mut archive = AuditArchive::open(store, SigningKey::from_seed([1; 32])).unwrap();
+        archive.append(&entries(&[10])).unwrap();
+        let target = crate::backend::Target {
+            name: "repo",
+            path: "/repos/repo",
+            total_commits: 1,
+            head: "main",
+            branches: &[],
+            uncommitted: &[],
+        };
+        let (_, receipt) = crate::backend::run(crate::OperationKind::ForcePush, &target, || Ok(()));
+        let bundle = root.join("bundle");
+        archive.export_bundle(&bundle, &receipt, None).unwrap();
+        assert!(bundle.join("segments/00000000.seg").exists());
+        assert!(!bundle.join("keys").exists());
+        let encoded = std::fs::read_to_string(bundle.join("receipt.json")).unwrap();
+        let signature = std::fs::read_to_string(bundle.join("receipt.sig")).unwrap();
+        let signature = crate::ed25519::Signature::from_hex(signature.trim()).unwrap();
+        let message = format!("receipt|{}", encoded.trim_end());
+        assert!(archive.public_key().verify(message.as_bytes(), &signature));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
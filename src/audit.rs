@@ -0,0 +1,56 @@
+//! AuditLog — what the gate decided, and when.
+
+use crate::blocked;
+
+/// One entry in the gate's audit log.
+pub struct AuditEntry {
+    /// When the gate recorded the event, in seconds since the Unix epoch.
+    pub at: u64,
+    /// The policy version in force when the event was recorded.
+    pub policy_version: String,
+    /// What happened, in the gate's words.
+    pub event: String,
+    /// The refusal, if the entry records one. See [`blocked`].
+    pub blocked: Option<blocked::Blocked>,
+}
+
+/// Append-only record of gate decisions.
+///
+/// In the incident, the only record of what happened was reconstructed
+/// afterwards from reflogs and shell history. The gate keeps its own.
+pub struct AuditLog {
+    pub(crate) entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub(crate) fn new() -> Self {
+        AuditLog { entries: Vec::new() }
+    }
+
+    pub(crate) fn record(&mut self, at: u64, event: String, policy_version: &str, blocked: Option<blocked::Blocked>) {
+        self.entries.push(AuditEntry {
+            at,
+            policy_version: policy_version.to_string(),
+            event,
+            blocked,
+        });
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Quote and escape a string as a JSON string literal, for the JSON the
+/// crate writes by hand around it.
+pub(crate) fn json_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
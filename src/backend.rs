@@ -0,0 +1,887 @@
+//! Execution of long-running backend operations, and the [`GitBackend`]
+//! trait that runs version control itself: [`Simulated`] by default,
+//! [`Shell`] for the `git` binary, and the jj and Sapling backends in
+//! [`vcs`](super::vcs).
+//!
+//! Work is done in units (commits rewritten, objects pushed). Between units
+//! the backend checks the deadline and the cancel token — cooperative
+//! cancellation, so an operation is never killed halfway through a unit.
+//! A backend that hands the work to a child process cannot see its units;
+//! it [`wait`]s on the child instead, and kills it once the deadline
+//! passes or the token is tripped.
+//!
+//! [`GitBackend`]: backend::GitBackend
+
+use super::clock::Instant;
+#[cfg(feature = "std")]
+use super::scoped_env::{self, ScopedToken};
+use super::{unix_now, BranchInfo, OperationKind, Outcome, Receipt, SafetyError};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+/// A shared flag another thread (or a human) can trip to stop an
+/// operation at the next unit boundary.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Receives partial progress while an operation runs.
+pub trait Progress {
+    fn on_progress(&mut self, done: usize, total: usize, current: &str);
+}
+
+/// Discards progress.
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn on_progress(&mut self, _done: usize, _total: usize, _current: &str) {}
+}
+
+/// Per-call execution limits.
+#[derive(Clone, Default)]
+pub struct ExecOptions {
+    /// Stop after this long. `None` runs to completion.
+    pub timeout: Option<Duration>,
+    pub cancel: CancelToken,
+}
+
+impl ExecOptions {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        ExecOptions {
+            timeout: Some(timeout),
+            cancel: CancelToken::new(),
+        }
+    }
+}
+
+impl ExecOptions {
+    /// Why the operation must stop now, `processed` of `total` units in,
+    /// if it must.
+    fn interrupted(&self, deadline: Option<Instant>, processed: usize, total: usize) -> Option<Outcome> {
+        if self.cancel.is_cancelled() {
+            return Some(Outcome::Cancelled { processed, total });
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Some(Outcome::TimedOut { processed, total });
+        }
+        None
+    }
+}
+
+/// Do `total` units of work, reporting each through `progress`, and stop
+/// between units once `options` says to.
+pub fn units(
+    total: usize,
+    options: &ExecOptions,
+    progress: &mut dyn Progress,
+    mut unit: impl FnMut(usize) -> String,
+) -> Result<(), Outcome> {
+    let deadline = options.timeout.map(|t| Instant::now() + t);
+    for processed in 0..total {
+        if let Some(outcome) = options.interrupted(deadline, processed, total) {
+            return Err(outcome);
+        }
+        let current = unit(processed);
+        progress.on_progress(processed + 1, total, &current);
+    }
+    Ok(())
+}
+
+/// Wait for `child` to exit, killing it once `options` says to stop. A
+/// killed child is reported with none of its `total` units done: what
+/// it got through before the kill cannot be vouched for.
+#[cfg(feature = "std")]
+pub fn wait(child: &mut Child, total: usize, options: &ExecOptions) -> io::Result<Result<ExitStatus, Outcome>> {
+    let deadline = options.timeout.map(|t| Instant::now() + t);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Ok(status));
+        }
+        if let Some(outcome) = options.interrupted(deadline, 0, total) {
+            child.kill()?;
+            child.wait()?;
+            return Ok(Err(outcome));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Run `work` for `operation` on `repo`, timing it, and record how it
+/// ended in a receipt.
+pub fn run<T>(operation: OperationKind, repo: &Target<'_>, work: impl FnOnce() -> Result<T, Outcome>) -> (Option<T>, Receipt) {
+    let started_at = unix_now();
+    let start = Instant::now();
+    let (done, outcome) = match work() {
+        Ok(done) => (Some(done), Outcome::Completed),
+        Err(outcome) => (None, outcome),
+    };
+    let receipt = Receipt {
+        operation,
+        repo: repo.name.to_string(),
+        path: repo.path.to_string(),
+        outcome,
+        started_at,
+        elapsed: start.elapsed(),
+        retries: 0,
+        ceremony: None,
+        gate_chain: Vec::new(),
+        #[cfg(feature = "std")]
+        verification: None,
+        approval_reason: None,
+        observed: false,
+        approved_by: Vec::new(),
+    };
+    (done, receipt)
+}
+
+/// [`run`] to completion: what the work returned, or the error its
+/// receipt makes.
+pub(crate) fn run_to_end<T>(
+    operation: OperationKind,
+    repo: &Target<'_>,
+    work: impl FnOnce() -> Result<T, Outcome>,
+) -> Result<T, SafetyError> {
+    let (done, receipt) = run(operation, repo, work);
+    done.ok_or_else(|| SafetyError::from(receipt))
+}
+
+/// A destructive change to history, named by what it does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryEdit {
+    /// Drop commits from visible history.
+    DiscardCommits(String),
+    /// Rewrite every commit's message with a callback, as `filter_repo` does.
+    RewriteMessages(String),
+    /// Replace a remote branch with a local one that does not contain it.
+    ForcePush(String),
+    DeleteBranch(String),
+}
+
+impl HistoryEdit {
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            HistoryEdit::DiscardCommits(_) => OperationKind::ResetHard,
+            HistoryEdit::RewriteMessages(_) => OperationKind::FilterRepo,
+            HistoryEdit::ForcePush(_) => OperationKind::ForcePush,
+            HistoryEdit::DeleteBranch(_) => OperationKind::DeleteBranch,
+        }
+    }
+}
+
+/// The repository a [`GitBackend`] is asked to act on.
+#[derive(Clone, Copy, Debug)]
+pub struct Target<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub total_commits: usize,
+    /// The checked-out branch, as the repository was opened with.
+    pub head: &'a str,
+    /// Local branches, as the repository was opened with.
+    pub branches: &'a [BranchInfo],
+    /// Files with uncommitted changes, as the repository was opened with.
+    pub uncommitted: &'a [String],
+}
+
+/// What actually runs git.
+///
+/// The typestate layer decides whether an operation may run at all:
+/// which methods exist in which state, and which consent each one
+/// spends. A backend is only ever asked to run what that layer has
+/// already allowed, so swapping a mock for libgit2 or for a shell
+/// changes what happens on disk and nothing about what is allowed.
+///
+/// Each method performs one operation and returns the line the caller
+/// is handed, or the [`Outcome`] that says why it did not happen: a
+/// refused push is an error, never a line with "failed" in it. A
+/// rewrite returns how many commits it rewrote; a reset returns
+/// nothing, since what it destroyed is the report. The two that can run
+/// for hours take [`ExecOptions`] and stop when told to, handing back
+/// how far they got instead.
+///
+/// The dangerous verbs have other names on other version-control
+/// systems — `jj abandon`, `sl hide` — and other undo stories. A backend
+/// that is not git says so through [`name`](Self::name),
+/// [`command`](Self::command), and [`undo_hint`](Self::undo_hint); the
+/// defaults are git's.
+pub trait GitBackend {
+    /// What the backend runs, in announcements: `git`, `jj`, `sl`.
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    /// The command that performs `edit`.
+    fn command(&self, edit: &HistoryEdit) -> String {
+        match edit {
+            HistoryEdit::DiscardCommits(rev) => format!("git reset --hard {}~", rev),
+            HistoryEdit::RewriteMessages(_) => "git filter-repo --message-callback <callback>".to_string(),
+            HistoryEdit::ForcePush(branch) => format!("git push --force-with-lease origin {}", branch),
+            HistoryEdit::DeleteBranch(branch) => format!("git branch -D {}", branch),
+        }
+    }
+
+    /// How to take `edit` back, if this backend keeps what it needs.
+    fn undo_hint(&self, edit: &HistoryEdit) -> Option<String> {
+        match edit {
+            HistoryEdit::DiscardCommits(_) | HistoryEdit::DeleteBranch(_) => {
+                Some("git reflog; uncommitted work is not recoverable".to_string())
+            }
+            HistoryEdit::RewriteMessages(_) | HistoryEdit::ForcePush(_) => None,
+        }
+    }
+
+    /// How many commits the repository holds, across every ref. The gate
+    /// asks this, not the caller, how large a history is.
+    fn commit_count(&self, repo: &Target) -> Result<usize, Outcome>;
+    /// The checked-out branch.
+    fn head(&self, repo: &Target) -> Result<String, Outcome>;
+    /// Whether `branch` is merged into the checked-out branch, so deleting
+    /// it loses no commits. A branch that does not exist is not merged.
+    fn merged(&self, repo: &Target, branch: &str) -> Result<bool, Outcome>;
+    /// The commit `branch` points at: locally, or as last fetched from
+    /// the remote if `fetched`.
+    fn tip(&self, repo: &Target, branch: &str, fetched: bool) -> Result<String, Outcome>;
+    /// The files with uncommitted changes in the working tree, untracked
+    /// files included.
+    fn status(&self, repo: &Target) -> Result<Vec<String>, Outcome>;
+    fn fetch(&self, repo: &Target) -> Result<String, Outcome>;
+    /// Fast-forward only.
+    fn pull(&self, repo: &Target, branch: &str) -> Result<String, Outcome>;
+    /// Commit `files`, or whatever is staged if `files` is empty.
+    fn commit(&self, repo: &Target, message: &str, files: &[String]) -> Result<String, Outcome>;
+    fn amend(&self, repo: &Target, message: &str) -> Result<String, Outcome>;
+    fn push(&self, repo: &Target, branch: &str) -> Result<String, Outcome>;
+    fn force_push(
+        &self,
+        repo: &Target,
+        branch: &str,
+        options: &ExecOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<String, Outcome>;
+    fn create_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome>;
+    fn create_tag(&self, repo: &Target, tag: &str, message: &str) -> Result<String, Outcome>;
+    fn delete_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome>;
+    /// Point `backup` at the tip of `branch`, so deleting it loses nothing.
+    fn backup_ref(&self, repo: &Target, branch: &str, backup: &str) -> Result<String, Outcome>;
+    fn delete_tag(&self, repo: &Target, tag: &str) -> Result<String, Outcome>;
+    fn remove_remote(&self, repo: &Target, remote: &str) -> Result<String, Outcome>;
+    fn filter_repo(
+        &self,
+        repo: &Target,
+        callback: &str,
+        options: &ExecOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<usize, Outcome>;
+    fn reset_hard(&self, repo: &Target) -> Result<(), Outcome>;
+    /// Drop `rev` from visible history, as [`command`](Self::command)
+    /// names it for [`HistoryEdit::DiscardCommits`].
+    fn discard(&self, repo: &Target, rev: &str) -> Result<String, Outcome>;
+}
+
+/// Runs nothing, and reports what would have run. The default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Simulated;
+
+impl GitBackend for Simulated {
+    fn commit_count(&self, repo: &Target) -> Result<usize, Outcome> {
+        Ok(repo.total_commits)
+    }
+
+    fn head(&self, repo: &Target) -> Result<String, Outcome> {
+        Ok(repo.head.to_string())
+    }
+
+    fn merged(&self, repo: &Target, branch: &str) -> Result<bool, Outcome> {
+        Ok(repo.branches.iter().any(|b| b.name == branch && b.merged))
+    }
+
+    /// There are no commits to name.
+    fn tip(&self, repo: &Target, branch: &str, _fetched: bool) -> Result<String, Outcome> {
+        Err(Outcome::Failed(format!("[{}] simulated: {} points at no commit", repo.name, branch)))
+    }
+
+    fn status(&self, repo: &Target) -> Result<Vec<String>, Outcome> {
+        Ok(repo.uncommitted.to_vec())
+    }
+
+    fn fetch(&self, repo: &Target) -> Result<String, Outcome> {
+        Ok(format!("[{}] fetched origin", repo.name))
+    }
+
+    fn pull(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] fast-forwarded {} to origin/{}", repo.name, branch, branch))
+    }
+
+    fn commit(&self, repo: &Target, message: &str, files: &[String]) -> Result<String, Outcome> {
+        let subject = message.lines().next().unwrap_or("");
+        Ok(if files.is_empty() {
+            format!("[{}] committed: {}", repo.name, subject)
+        } else {
+            format!("[{}] committed {} files: {}", repo.name, files.len(), subject)
+        })
+    }
+
+    fn amend(&self, repo: &Target, message: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] amended: {}", repo.name, message.lines().next().unwrap_or("")))
+    }
+
+    fn push(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] pushed to origin/{}", repo.name, branch))
+    }
+
+    fn force_push(
+        &self,
+        repo: &Target,
+        branch: &str,
+        options: &ExecOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<String, Outcome> {
+        units(repo.total_commits, options, progress, |i| format!("object {} -> origin/{}", i + 1, branch))?;
+        Ok(format!("[{}] force-pushed to origin/{}", repo.name, branch))
+    }
+
+    fn create_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] created branch '{}' at HEAD", repo.name, branch))
+    }
+
+    fn create_tag(&self, repo: &Target, tag: &str, message: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] tagged HEAD as '{}': {}", repo.name, tag, message))
+    }
+
+    fn delete_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] deleted branch '{}'", repo.name, branch))
+    }
+
+    fn backup_ref(&self, repo: &Target, branch: &str, backup: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] {} -> {}", repo.name, branch, backup))
+    }
+
+    fn delete_tag(&self, repo: &Target, tag: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] deleted tag '{}'", repo.name, tag))
+    }
+
+    fn remove_remote(&self, repo: &Target, remote: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] removed remote '{}'", repo.name, remote))
+    }
+
+    fn filter_repo(
+        &self,
+        repo: &Target,
+        callback: &str,
+        options: &ExecOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<usize, Outcome> {
+        units(repo.total_commits, options, progress, |i| format!("commit {} ({})", i + 1, callback))?;
+        Ok(repo.total_commits)
+    }
+
+    fn reset_hard(&self, _repo: &Target) -> Result<(), Outcome> {
+        Ok(())
+    }
+
+    fn discard(&self, repo: &Target, rev: &str) -> Result<String, Outcome> {
+        Ok(format!("[{}] discarded {}", repo.name, rev))
+    }
+}
+
+/// Runs the `git` binary in the repository's directory.
+///
+/// Every invocation goes through [`scoped_env`]: an empty environment, a
+/// scratch `HOME`, no prompts. A destructive operation reaches a remote
+/// only with the [`ScopedToken`] the gate minted for that operation on
+/// that repository, if one was handed over; everything else runs with no
+/// credentials at all.
+#[cfg(feature = "std")]
+pub struct Shell {
+    cli: Cli,
+}
+
+#[cfg(feature = "std")]
+impl Default for Shell {
+    fn default() -> Self {
+        Shell {
+            cli: Cli::new("git", "-C"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Shell {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate the operation `token` was minted for, on its
+    /// repository, with it.
+    pub fn with_token(mut self, token: ScopedToken) -> Self {
+        self.cli.token = Some(token);
+        self
+    }
+}
+
+/// One version-control binary, run against a repository the way
+/// [`Shell`] runs git: through [`scoped_env`], with the token only for
+/// the operation and repository it was minted for. The jj and Sapling
+/// backends run on it too.
+#[cfg(feature = "std")]
+pub(crate) struct Cli {
+    program: &'static str,
+    /// How the binary is told which repository: `-C` for git, `-R` for
+    /// jj and Sapling.
+    repository_flag: &'static str,
+    pub(crate) token: Option<ScopedToken>,
+}
+
+#[cfg(feature = "std")]
+impl Cli {
+    pub(crate) fn new(program: &'static str, repository_flag: &'static str) -> Self {
+        Cli {
+            program,
+            repository_flag,
+            token: None,
+        }
+    }
+
+    /// `<program> <flag> <repo> <args>`, in the environment `operation` gets.
+    fn command(&self, repo: &Target, operation: Option<OperationKind>, args: &[&str]) -> Result<Command, String> {
+        let mut cmd = match operation {
+            Some(operation) => {
+                let token = self.token.as_ref().filter(|t| t.operation == operation && t.repo == repo.path);
+                scoped_env::command(self.program, operation, token)?
+            }
+            None => scoped_env::uncredentialed(self.program)?,
+        };
+        cmd.arg(self.repository_flag).arg(repo.path).args(args).stdin(Stdio::null());
+        Ok(cmd)
+    }
+
+    /// Run one command to completion: the line to report, or why it
+    /// failed.
+    pub(crate) fn run(&self, repo: &Target, operation: Option<OperationKind>, args: &[&str]) -> Result<String, Outcome> {
+        self.output(repo, operation, args).map(|_| format!("[{}] {} {}", repo.name, self.program, args.join(" ")))
+    }
+
+    /// Run one command to completion: what it printed, or why it failed.
+    pub(crate) fn output(&self, repo: &Target, operation: Option<OperationKind>, args: &[&str]) -> Result<String, Outcome> {
+        let command = format!("{} {}", self.program, args.join(" "));
+        let mut cmd = self.command(repo, operation, args).map_err(|why| Outcome::Failed(format!("{} not run: {}", command, why)))?;
+        match cmd.output() {
+            Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+            Ok(output) => Err(Outcome::Failed(format!(
+                "{} exited with {}: {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))),
+            Err(why) => Err(Outcome::Failed(format!("{} not run: {}", command, why))),
+        }
+    }
+
+    /// Whether a read-only query exits zero.
+    pub(crate) fn succeeds(&self, repo: &Target, args: &[&str]) -> bool {
+        self.command(repo, None, args)
+            .is_ok_and(|mut cmd| cmd.stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok_and(|s| s.success()))
+    }
+
+    /// Run one command under `options`, killing it if it overruns.
+    /// `total` is how many units the caller counts it as.
+    pub(crate) fn run_with(
+        &self,
+        repo: &Target,
+        operation: OperationKind,
+        args: &[&str],
+        total: usize,
+        options: &ExecOptions,
+    ) -> Result<(), Outcome> {
+        let command = format!("{} {}", self.program, args.join(" "));
+        let not_run = |why: String| Outcome::Failed(format!("{} not run: {}", command, why));
+        let mut cmd = self.command(repo, Some(operation), args).map_err(not_run)?;
+        let mut child = cmd.stdout(Stdio::null()).stderr(Stdio::null()).spawn().map_err(|e| not_run(e.to_string()))?;
+        match wait(&mut child, total, options).map_err(|e| not_run(e.to_string()))?? {
+            status if status.success() => Ok(()),
+            status => Err(Outcome::Failed(format!("{} exited with {}", command, status))),
+        }
+    }
+}
+
+/// The second step of two failed after the first was done. Says so: a
+/// failure that reads as "nothing happened" would be wrong.
+#[cfg(feature = "std")]
+pub(crate) fn after(done: &str, outcome: Outcome) -> Outcome {
+    Outcome::Failed(format!("after {}: {}", done, outcome.label()))
+}
+
+#[cfg(feature = "std")]
+impl GitBackend for Shell {
+    fn commit_count(&self, repo: &Target) -> Result<usize, Outcome> {
+        let count = self.cli.output(repo, None, &["rev-list", "--count", "--all"])?;
+        count.parse().map_err(|_| Outcome::Failed(format!("git rev-list --count printed `{}`", count)))
+    }
+
+    /// A detached HEAD is an error: there is no branch to name.
+    fn head(&self, repo: &Target) -> Result<String, Outcome> {
+        self.cli.output(repo, None, &["symbolic-ref", "--short", "HEAD"])
+    }
+
+    fn merged(&self, repo: &Target, branch: &str) -> Result<bool, Outcome> {
+        let merged = self.cli.output(repo, None, &["branch", "--merged", "HEAD", "--format=%(refname:short)"])?;
+        Ok(merged.lines().any(|name| name == branch))
+    }
+
+    fn tip(&self, repo: &Target, branch: &str, fetched: bool) -> Result<String, Outcome> {
+        let refname = match fetched {
+            true => format!("refs/remotes/origin/{}", branch),
+            false => format!("refs/heads/{}", branch),
+        };
+        self.cli.output(repo, None, &["rev-parse", "--verify", "--quiet", &refname])
+    }
+
+    /// A rename is reported by the path it was renamed to.
+    fn status(&self, repo: &Target) -> Result<Vec<String>, Outcome> {
+        let status = self.cli.output(repo, None, &["status", "--porcelain"])?;
+        Ok(status
+            .lines()
+            .filter_map(|line| line.trim_start().split_once(' '))
+            .map(|(_, path)| {
+                let path = path.trim_start();
+                path.rsplit_once(" -> ").map_or(path, |(_, to)| to).to_string()
+            })
+            .collect())
+    }
+
+    fn fetch(&self, repo: &Target) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["fetch", "origin"])
+    }
+
+    fn pull(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["pull", "--ff-only", "origin", branch])
+    }
+
+    fn commit(&self, repo: &Target, message: &str, files: &[String]) -> Result<String, Outcome> {
+        if !files.is_empty() {
+            let mut add = vec!["add", "--"];
+            add.extend(files.iter().map(String::as_str));
+            self.cli.run(repo, None, &add)?;
+        }
+        self.cli.run(repo, None, &["commit", "-m", message])
+    }
+
+    fn amend(&self, repo: &Target, message: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["commit", "--amend", "-m", message])
+    }
+
+    fn push(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["push", "origin", branch])
+    }
+
+    fn force_push(
+        &self,
+        repo: &Target,
+        branch: &str,
+        options: &ExecOptions,
+        _progress: &mut dyn Progress,
+    ) -> Result<String, Outcome> {
+        let args = ["push", "--force-with-lease", "origin", branch];
+        self.cli.run_with(repo, OperationKind::ForcePush, &args, repo.total_commits, options)?;
+        Ok(format!("[{}] force-pushed to origin/{}", repo.name, branch))
+    }
+
+    fn create_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["branch", branch])
+    }
+
+    fn create_tag(&self, repo: &Target, tag: &str, message: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["tag", "-a", tag, "-m", message])
+    }
+
+    /// The remote copy goes too, if there is one: a branch that was never
+    /// pushed has no `origin/<branch>` to delete.
+    fn delete_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        let tracking = format!("refs/remotes/origin/{}", branch);
+        let pushed = self.cli.succeeds(repo, &["rev-parse", "--verify", "--quiet", &tracking]);
+        let local = self.cli.run(repo, Some(OperationKind::DeleteBranch), &["branch", "-D", branch])?;
+        if !pushed {
+            return Ok(local);
+        }
+        let remote = self
+            .cli
+            .run(repo, Some(OperationKind::DeleteBranch), &["push", "origin", "--delete", branch])
+            .map_err(|outcome| after(&local, outcome))?;
+        Ok(format!("{}; {}", local, remote))
+    }
+
+    fn backup_ref(&self, repo: &Target, branch: &str, backup: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, None, &["update-ref", backup, &format!("refs/heads/{}", branch)])
+    }
+
+    fn delete_tag(&self, repo: &Target, tag: &str) -> Result<String, Outcome> {
+        let local = self.cli.run(repo, Some(OperationKind::DeleteTag), &["tag", "-d", tag])?;
+        if !self.cli.succeeds(repo, &["remote", "get-url", "origin"]) {
+            return Ok(local);
+        }
+        let remote = format!("refs/tags/{}", tag);
+        let remote = self
+            .cli
+            .run(repo, Some(OperationKind::DeleteTag), &["push", "origin", "--delete", &remote])
+            .map_err(|outcome| after(&local, outcome))?;
+        Ok(format!("{}; {}", local, remote))
+    }
+
+    fn remove_remote(&self, repo: &Target, remote: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, Some(OperationKind::RemoveRemote), &["remote", "remove", remote])
+    }
+
+    /// A rewrite that fails rewrote nothing: filter-repo moves refs only
+    /// once every commit is done.
+    fn filter_repo(
+        &self,
+        repo: &Target,
+        callback: &str,
+        options: &ExecOptions,
+        _progress: &mut dyn Progress,
+    ) -> Result<usize, Outcome> {
+        let args = ["filter-repo", "--force", "--message-callback", callback];
+        self.cli.run_with(repo, OperationKind::FilterRepo, &args, repo.total_commits, options)?;
+        Ok(repo.total_commits)
+    }
+
+    fn reset_hard(&self, repo: &Target) -> Result<(), Outcome> {
+        self.cli.run(repo, Some(OperationKind::ResetHard), &["reset", "--hard", "HEAD"]).map(drop)
+    }
+
+    /// `rev` and everything after it on the checked-out branch.
+    fn discard(&self, repo: &Target, rev: &str) -> Result<String, Outcome> {
+        self.cli.run(repo, Some(OperationKind::ResetHard), &["reset", "--hard", &format!("{}~", rev)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wait, ExecOptions, GitBackend, NoProgress, Shell, Target};
+    use crate::{
+        approver, AgentManifest, Capability, HostKey, OperationDescriptor, Outcome, Repository, ResetResult,
+        RewriteResult, SafetyGate, WorkingTree,
+    };
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let out = Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+        assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    }
+
+    /// A fresh repository with one file written and nothing committed.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("safe-operations-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q", "-b", "main"]);
+        git(&dir, &["config", "user.name", "Test"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "commit.gpgsign", "false"]);
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        dir
+    }
+
+    fn target(path: &Path) -> Target<'_> {
+        Target {
+            name: "shell",
+            path: path.to_str().unwrap(),
+            total_commits: 1,
+            head: "main",
+            branches: &[],
+            uncommitted: &[],
+        }
+    }
+
+    #[test]
+    fn the_shell_backend_runs_git_and_writes_backup_refs() {
+        let dir = scratch("shell");
+        let repo = target(&dir);
+        let shell = Shell::new();
+        shell.commit(&repo, "first", &["a.txt".to_string()]).unwrap();
+        shell.create_branch(&repo, "spike").unwrap();
+        shell.backup_ref(&repo, "spike", "refs/backup/cleanup/spike").unwrap();
+        assert_eq!(git(&dir, &["rev-parse", "refs/backup/cleanup/spike"]), git(&dir, &["rev-parse", "main"]));
+        shell.delete_branch(&repo, "spike").unwrap();
+        assert_eq!(git(&dir, &["branch", "--list", "spike"]), "");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_git_failure_is_an_error_not_a_line() {
+        let dir = scratch("shell-fails");
+        let repo = target(&dir);
+        let shell = Shell::new();
+        let Err(Outcome::Failed(why)) = shell.commit(&repo, "nothing staged", &["missing.txt".to_string()]) else {
+            panic!("missing.txt does not exist");
+        };
+        assert!(why.contains("git add"), "{}", why);
+        shell.commit(&repo, "first", &["a.txt".to_string()]).unwrap();
+        assert!(matches!(shell.create_branch(&repo, "main"), Err(Outcome::Failed(_))));
+        assert!(matches!(shell.push(&repo, "main"), Err(Outcome::Failed(_))), "there is no origin");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_force_push_or_rewrite_reports_failure() {
+        let dir = scratch("shell-destructive");
+        let repo = target(&dir);
+        let shell = Shell::new();
+        shell.commit(&repo, "first", &["a.txt".to_string()]).unwrap();
+        let options = ExecOptions::default();
+        let pushed = shell.force_push(&repo, "main", &options, &mut NoProgress);
+        assert!(matches!(pushed, Err(Outcome::Failed(_))), "there is no origin: {:?}", pushed);
+        let head = git(&dir, &["rev-parse", "HEAD"]);
+        let rewritten = shell.filter_repo(&repo, "return message", &options, &mut NoProgress);
+        if let Err(outcome) = &rewritten {
+            assert!(matches!(outcome, Outcome::Failed(_)), "{:?}", outcome);
+            assert_eq!(git(&dir, &["rev-parse", "HEAD"]), head, "a failed rewrite moves no refs");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_reset_outside_a_repository_fails() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-no-repo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(matches!(Shell::new().reset_hard(&target(&dir)), Err(Outcome::Failed(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn whether_a_deletion_loses_commits_is_asked_of_git_not_the_record() {
+        let dir = scratch("shell-merged");
+        let shell = Shell::new();
+        shell.commit(&target(&dir), "first", &["a.txt".to_string()]).unwrap();
+        git(&dir, &["branch", "merged"]);
+        git(&dir, &["checkout", "-q", "-b", "ahead"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "only on ahead"]);
+        git(&dir, &["checkout", "-q", "main"]);
+        let path = dir.to_str().unwrap();
+        // The record claims the opposite of what git says about each.
+        let repo = Repository::protected_with("merged", path, 2, Shell::new())
+            .with_head("ahead")
+            .with_branch("merged", false, 0)
+            .with_branch("ahead", true, 0);
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(approver::Rehearsal));
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: path.to_string() })
+            .unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+        assert!(matches!(repo.deletion_of("merged"), OperationDescriptor::DeleteBranch { .. }));
+        assert!(matches!(repo.deletion_of("ahead"), OperationDescriptor::ForceDeleteBranch { .. }), "unmerged");
+        assert!(matches!(repo.deletion_of("main"), OperationDescriptor::ForceDeleteBranch { .. }), "checked out");
+        assert!(matches!(repo.deletion_of("missing"), OperationDescriptor::ForceDeleteBranch { .. }));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_working_tree_is_read_from_git_on_open_and_again_on_grant() {
+        let dir = scratch("shell-status");
+        let path = dir.to_str().unwrap();
+        let key = HostKey::generate();
+        let manifest = AgentManifest::issue("agent", &[Capability::Read, Capability::Commit], &key);
+
+        let repo = Repository::open_with("status", path, 0, Shell::new());
+        assert_eq!(repo.uncommitted(), ["a.txt"], "untracked, and nothing recorded it");
+        let Ok(WorkingTree::Dirty(repo)) = repo.grant_commit(&manifest, &key) else {
+            panic!("a dirty working tree is granted dirty");
+        };
+        repo.commit_all("first").map_err(|failed| failed.outcome).unwrap();
+
+        let repo = Repository::open_with("status", path, 0, Shell::new());
+        assert!(repo.uncommitted().is_empty());
+        git(&dir, &["mv", "a.txt", "b.txt"]);
+        let Ok(WorkingTree::Dirty(repo)) = repo.grant_commit(&manifest, &key) else {
+            panic!("changed after opening, and read again on the grant");
+        };
+        assert_eq!(repo.uncommitted(), ["b.txt"], "a rename, by where it went");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_working_tree_git_cannot_read_is_not_granted_clean() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-unreadable-{}", std::process::id()));
+        let key = HostKey::generate();
+        let manifest = AgentManifest::issue("agent", &[Capability::Commit], &key);
+        let repo = Repository::open_with("unreadable", dir.to_str().unwrap(), 0, Shell::new());
+        assert!(matches!(repo.grant_commit(&manifest, &key), Ok(WorkingTree::Dirty(_))));
+    }
+
+    #[test]
+    fn a_rewrite_or_reset_the_backend_fails_hands_the_repository_back() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-handed-back-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(approver::Rehearsal));
+        let repo = Repository::protected_with("handed-back", path, 3, Shell::new());
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: path.to_string() })
+            .unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+
+        let rewrite = OperationDescriptor::FilterRepo {
+            repo: path.to_string(),
+            callback: "return message".to_string(),
+        };
+        let consent = gate.request_consent_to(&repo, rewrite).unwrap();
+        let Ok(RewriteResult::Interrupted(repo, receipt)) = repo.filter_repo("return message", consent) else {
+            panic!("there is no repository to rewrite");
+        };
+        assert!(matches!(receipt.outcome, Outcome::Failed(_)), "{}", receipt.outcome.label());
+        assert_eq!(repo.name, "handed-back");
+
+        let consent = gate.request_consent_to(&repo, OperationDescriptor::ResetHard { repo: path.to_string() }).unwrap();
+        let Ok(ResetResult::Failed(repo, receipt)) = repo.reset_hard(consent) else {
+            panic!("there is no repository to reset");
+        };
+        assert!(matches!(receipt.outcome, Outcome::Failed(_)), "{}", receipt.outcome.label());
+        assert_eq!(repo.total_commits, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_child_past_its_deadline_is_killed() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let start = Instant::now();
+        let options = ExecOptions::with_timeout(Duration::from_millis(100));
+        let outcome = wait(&mut child, 7, &options).unwrap();
+        assert_eq!(outcome.err(), Some(Outcome::TimedOut { processed: 0, total: 7 }));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(child.try_wait().unwrap().is_some(), "the child was reaped");
+    }
+
+    #[test]
+    fn a_cancelled_child_is_killed() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let options = ExecOptions::default();
+        options.cancel.cancel();
+        let outcome = wait(&mut child, 1, &options).unwrap();
+        assert_eq!(outcome.err(), Some(Outcome::Cancelled { processed: 0, total: 1 }));
+    }
+}
@@ -0,0 +1,335 @@
+//! Guarded backup-system administration.
+//!
+//! Every other module here leans on backups being there to restore from.
+//! An agent "freeing up space" by deleting old backups, shortening a
+//! retention period, or switching off a schedule it thinks is redundant
+//! takes away the recovery path for everything else. Listing and creating
+//! backups are free. Deleting one, shortening retention, and disabling a
+//! schedule go through a [`BackupPlan`] that follows the dependency chain
+//! — an incremental backup restores only with every backup it was taken
+//! against — and names what would become unrecoverable: the backups that
+//! would no longer restore, and the datasets left with nothing to restore
+//! from. All three are Critical, and the deny-list is consulted for them
+//! with the dataset as the target, so `delete_backup  payments-* = "..."`
+//! puts a dataset's backups out of any approver's reach.
+
+use super::{
+    report, unix_now, ConsentDenied, DestructionPlan, OperationKind, Protected, RecoveryCost, SafetyGate,
+    UserConsent,
+};
+use core::marker::PhantomData;
+
+/// Consent scope for destructive backup-system changes.
+pub struct BackupChange;
+
+const DAY: u64 = 24 * 3600;
+
+/// A backup as the backup system reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backup {
+    pub id: String,
+    pub dataset: String,
+    pub taken_at: u64,
+    /// The backup this one is an increment on. `None` for a full backup.
+    pub parent: Option<String>,
+}
+
+/// A recurring backup job and how long what it takes is kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub name: String,
+    pub dataset: String,
+    pub every_hours: u32,
+    pub keep_days: u32,
+    pub enabled: bool,
+}
+
+/// The backup system's API the plan's checks go through.
+pub trait BackupStore {
+    fn backups(&self) -> Vec<Backup>;
+    fn schedules(&self) -> Vec<Schedule>;
+    /// Take a full backup of `dataset` now.
+    fn create(&mut self, dataset: &str) -> Backup;
+    fn delete(&mut self, id: &str);
+    fn set_retention(&mut self, schedule: &str, keep_days: u32);
+    fn disable(&mut self, schedule: &str);
+}
+
+/// A backup system held in memory, for demonstrations and fixtures.
+#[derive(Default)]
+pub struct InMemoryBackupStore {
+    pub backups: Vec<Backup>,
+    pub schedules: Vec<Schedule>,
+}
+
+impl BackupStore for InMemoryBackupStore {
+    fn backups(&self) -> Vec<Backup> {
+        self.backups.clone()
+    }
+
+    fn schedules(&self) -> Vec<Schedule> {
+        self.schedules.clone()
+    }
+
+    fn create(&mut self, dataset: &str) -> Backup {
+        let backup = Backup {
+            id: format!("{}-{}", dataset, self.backups.len() + 1),
+            dataset: dataset.to_string(),
+            taken_at: unix_now(),
+            parent: None,
+        };
+        self.backups.push(backup.clone());
+        backup
+    }
+
+    fn delete(&mut self, id: &str) {
+        self.backups.retain(|b| b.id != id);
+    }
+
+    fn set_retention(&mut self, schedule: &str, keep_days: u32) {
+        for s in self.schedules.iter_mut().filter(|s| s.name == schedule) {
+            s.keep_days = keep_days;
+        }
+    }
+
+    fn disable(&mut self, schedule: &str) {
+        for s in self.schedules.iter_mut().filter(|s| s.name == schedule) {
+            s.enabled = false;
+        }
+    }
+}
+
+/// Every backup that no longer restores once `removed` are gone: the
+/// removed ones, and every increment whose chain runs through one.
+pub fn unrestorable(backups: &[Backup], removed: &[String]) -> Vec<Backup> {
+    let mut lost: Vec<String> = removed.to_vec();
+    loop {
+        let before = lost.len();
+        for b in backups {
+            if !lost.contains(&b.id) && b.parent.as_ref().is_some_and(|p| lost.contains(p)) {
+                lost.push(b.id.clone());
+            }
+        }
+        if lost.len() == before {
+            break;
+        }
+    }
+    backups.iter().filter(|b| lost.contains(&b.id)).cloned().collect()
+}
+
+/// What a destructive backup change does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackupAction {
+    DeleteBackup { id: String },
+    ShortenRetention { schedule: String, keep_days: u32 },
+    DisableSchedule { schedule: String },
+}
+
+impl BackupAction {
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            BackupAction::DeleteBackup { .. } => OperationKind::DeleteBackup,
+            BackupAction::ShortenRetention { .. } => OperationKind::ShortenRetention,
+            BackupAction::DisableSchedule { .. } => OperationKind::DisableBackupSchedule,
+        }
+    }
+}
+
+/// A backup change, checked against the backup system before anyone
+/// is asked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupPlan {
+    pub dataset: String,
+    pub action: BackupAction,
+    /// Backups that would no longer restore, the dependents included.
+    pub unrestorable: Vec<Backup>,
+    /// Whether `dataset` would be left with no backup that restores.
+    pub leaves_nothing: bool,
+    /// For a disabled schedule: when the newest backup it took ages out
+    /// under retention, if no other schedule covers the dataset.
+    pub uncovered_from: Option<u64>,
+}
+
+impl BackupPlan {
+    pub fn to_destruction_plan(&self) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(self.action.operation(), &self.dataset);
+        plan = match &self.action {
+            BackupAction::DeleteBackup { id } => plan.item(format!("backup {} of {}", id, self.dataset)),
+            BackupAction::ShortenRetention { schedule, keep_days } => {
+                plan.item(format!("{} keeps {} days of {}", schedule, keep_days, self.dataset))
+            }
+            BackupAction::DisableSchedule { schedule } => {
+                plan.item(format!("{} stops backing up {}", schedule, self.dataset))
+            }
+        };
+        for b in &self.unrestorable {
+            let dependency = match &b.parent {
+                Some(parent) => format!(", an increment on {}", parent),
+                None => String::new(),
+            };
+            plan = plan.item(format!("{} from {}{} no longer restores", b.id, report::format_utc(b.taken_at), dependency));
+        }
+        if !self.unrestorable.is_empty() {
+            plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                "{} backup(s) of {}",
+                self.unrestorable.len(),
+                self.dataset
+            )));
+        }
+        if self.leaves_nothing {
+            plan = plan.item(format!("{} is left with no backup to restore from", self.dataset));
+            plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                "every restore point of {}; the next mistake on it is permanent",
+                self.dataset
+            )));
+        }
+        if let Some(at) = self.uncovered_from {
+            plan = plan.item(format!(
+                "no schedule backs up {}; its last backup ages out at {}",
+                self.dataset,
+                report::format_utc(at)
+            ));
+            plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                "any change to {} after the last backup, once something goes wrong",
+                self.dataset
+            )));
+        }
+        plan
+    }
+}
+
+/// Consent to apply exactly one backup plan.
+pub struct BackupApproval {
+    plan: BackupPlan,
+    _consent: UserConsent<BackupChange>,
+}
+
+/// A backup system.
+pub struct BackupVault<State = Protected> {
+    store: Box<dyn BackupStore>,
+    _state: PhantomData<State>,
+}
+
+impl BackupVault<Protected> {
+    pub fn connect(store: Box<dyn BackupStore>) -> Self {
+        BackupVault {
+            store,
+            _state: PhantomData,
+        }
+    }
+
+    /// The backups of `dataset`, oldest first.
+    pub fn list(&self, dataset: &str) -> Vec<Backup> {
+        let mut backups: Vec<Backup> = self.store.backups().into_iter().filter(|b| b.dataset == dataset).collect();
+        backups.sort_by_key(|b| b.taken_at);
+        backups
+    }
+
+    pub fn schedules(&self) -> Vec<Schedule> {
+        self.store.schedules()
+    }
+
+    /// Taking another backup is always allowed.
+    pub fn create(&mut self, dataset: &str) -> Backup {
+        self.store.create(dataset)
+    }
+
+    /// Check a destructive change against the backup system.
+    /// Lengthening retention is not destructive and is refused here:
+    /// it needs no plan.
+    pub fn plan(&self, action: BackupAction) -> Result<BackupPlan, String> {
+        let backups = self.store.backups();
+        let schedules = self.store.schedules();
+        let schedule = |name: &str| {
+            schedules.iter().find(|s| s.name == name).cloned().ok_or(format!("no schedule named {}", name))
+        };
+        let (dataset, removed, uncovered_from) = match &action {
+            BackupAction::DeleteBackup { id } => {
+                let backup = backups.iter().find(|b| b.id == *id).ok_or(format!("no backup {}", id))?;
+                (backup.dataset.clone(), vec![id.clone()], None)
+            }
+            BackupAction::ShortenRetention { schedule: name, keep_days } => {
+                let s = schedule(name)?;
+                if *keep_days >= s.keep_days {
+                    return Err(format!("{} already keeps {} days; that is not shorter", name, s.keep_days));
+                }
+                let cutoff = unix_now().saturating_sub(*keep_days as u64 * DAY);
+                let expired = backups
+                    .iter()
+                    .filter(|b| b.dataset == s.dataset && b.taken_at < cutoff)
+                    .map(|b| b.id.clone())
+                    .collect();
+                (s.dataset, expired, None)
+            }
+            BackupAction::DisableSchedule { schedule: name } => {
+                let s = schedule(name)?;
+                if !s.enabled {
+                    return Err(format!("{} is already disabled", name));
+                }
+                let covered = schedules.iter().any(|o| o.name != s.name && o.dataset == s.dataset && o.enabled);
+                let newest = backups.iter().filter(|b| b.dataset == s.dataset).map(|b| b.taken_at).max();
+                let ages_out = (!covered).then(|| newest.unwrap_or(unix_now()) + s.keep_days as u64 * DAY);
+                (s.dataset, Vec::new(), ages_out)
+            }
+        };
+        let unrestorable = unrestorable(&backups, &removed);
+        let remaining = backups.iter().filter(|b| b.dataset == dataset && !unrestorable.contains(b)).count();
+        Ok(BackupPlan {
+            leaves_nothing: !unrestorable.is_empty() && remaining == 0,
+            dataset,
+            action,
+            unrestorable,
+            uncovered_from,
+        })
+    }
+
+    fn check(&self, approval: &BackupApproval) -> Result<(), String> {
+        let current = self.plan(approval.plan.action.clone())?;
+        if current != approval.plan {
+            return Err("backups changed since the plan was approved".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn delete_backup(&mut self, approval: BackupApproval) -> Result<String, String> {
+        let BackupAction::DeleteBackup { id } = &approval.plan.action else {
+            return Err("approval is not for a backup deletion".to_string());
+        };
+        self.check(&approval)?;
+        self.store.delete(id);
+        Ok(format!("deleted backup {} of {}", id, approval.plan.dataset))
+    }
+
+    pub fn shorten_retention(&mut self, approval: BackupApproval) -> Result<String, String> {
+        let BackupAction::ShortenRetention { schedule, keep_days } = &approval.plan.action else {
+            return Err("approval is not for a retention change".to_string());
+        };
+        self.check(&approval)?;
+        self.store.set_retention(schedule, *keep_days);
+        Ok(format!("{} now keeps {} days", schedule, keep_days))
+    }
+
+    pub fn disable_schedule(&mut self, approval: BackupApproval) -> Result<String, String> {
+        let BackupAction::DisableSchedule { schedule } = &approval.plan.action else {
+            return Err("approval is not for disabling a schedule".to_string());
+        };
+        self.check(&approval)?;
+        self.store.disable(schedule);
+        Ok(format!("disabled {}", schedule))
+    }
+}
+
+impl SafetyGate {
+    /// Request consent for a backup plan. A plan that leaves anything
+    /// unrecoverable needs the typed acknowledgment.
+    pub fn request_backup_consent(
+        &mut self,
+        plan: &BackupPlan,
+    ) -> Result<BackupApproval, ConsentDenied> {
+        let consent = self.plan_consent(&plan.to_destruction_plan())?;
+        Ok(BackupApproval {
+            plan: plan.clone(),
+            _consent: consent,
+        })
+    }
+}
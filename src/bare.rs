@@ -0,0 +1,149 @@
+//! BareRepository<State, B> — no working tree, and what it accepts matters.
+
+use crate::{
+    backend, post_ops, Outcome, Protected, Refused, Repository, RewriteResult, Unprotected,
+    UserConsent,
+};
+
+/// A bare repository: a mirror, or the remote everyone else pushes to.
+///
+/// It has no working tree, so it has no `reset_hard`, `stash`, `diff`,
+/// `commit`, or `worktree`. It wraps a [`Repository`] for the protection
+/// state and the consent checks, and never hands that repository out.
+///
+/// What matters on a bare repository is what it accepts. Protected, it
+/// refuses ref updates that lose commits — non-fast-forward updates and
+/// deletions — as `receive.denyNonFastForwards` and `receive.denyDeletes`
+/// do, and as the [`adopt`] pre-push hook does from the other side. Only
+/// with protection removed, with consent, does it take them.
+pub struct BareRepository<State = Protected, B = backend::Simulated> {
+    repo: Repository<State, B>,
+}
+
+impl<State, B> BareRepository<State, B> {
+    pub fn name(&self) -> &str {
+        &self.repo.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.repo.path
+    }
+
+    /// The most recent `count` commits.
+    pub fn log(&self, count: usize) -> String {
+        self.repo.log(count)
+    }
+
+    /// Fetch is safe: a mirror's refs move forward with its origin.
+    pub fn fetch(&self) -> Result<String, Outcome>
+    where
+        B: backend::GitBackend,
+    {
+        self.repo.fetch()
+    }
+
+    fn accepted(&self, update: &post_ops::RefUpdate) -> String {
+        if is_deletion(update) {
+            format!("[{}] deleted {}", self.repo.name, update.refname)
+        } else {
+            format!("[{}] {} {}..{}", self.repo.name, update.refname, update.old_sha, update.new_sha)
+        }
+    }
+}
+
+/// Whether `update` deletes its ref: git reports the new SHA as all zeros.
+fn is_deletion(update: &post_ops::RefUpdate) -> bool {
+    update.new_sha.chars().all(|c| c == '0')
+}
+
+impl BareRepository<Protected> {
+    /// Open a bare repository. It is protected: with no working tree, there
+    /// is nothing for a commit grant to guard.
+    pub fn open_bare(name: &str, path: &str, total_commits: usize) -> Self {
+        BareRepository {
+            repo: Repository::protected(name, path, total_commits),
+        }
+    }
+}
+
+impl<B: backend::GitBackend> BareRepository<Protected, B> {
+    /// Open a bare repository whose git operations `backend` runs.
+    pub fn open_bare_with(name: &str, path: &str, total_commits: usize, backend: B) -> Self {
+        BareRepository {
+            repo: Repository::protected_with(name, path, total_commits, backend),
+        }
+    }
+
+    pub fn status(&self) -> String {
+        format!("{}: {} commits, bare, protected", self.repo.name, self.repo.total_commits)
+    }
+
+    /// Accept a pushed ref update, or refuse it if it would lose commits:
+    /// a deletion, or an update that is not a fast-forward.
+    pub fn receive(&self, update: &post_ops::RefUpdate, fast_forward: bool) -> Result<String, String> {
+        if is_deletion(update) {
+            return Err(format!("refused: deleting {} in '{}' needs protection removed", update.refname, self.repo.name));
+        }
+        if !fast_forward {
+            return Err(format!(
+                "refused: non-fast-forward update to {} in '{}' needs protection removed",
+                update.refname, self.repo.name
+            ));
+        }
+        Ok(self.accepted(update))
+    }
+
+    /// Remove protection. Requires consent, as for any repository.
+    pub fn remove_protection(self, consent: UserConsent) -> Result<BareRepository<Unprotected, B>, Refused<Self>> {
+        match self.repo.remove_protection(consent) {
+            Ok(repo) => Ok(BareRepository { repo }),
+            Err(refused) => Err(Refused {
+                repo: Box::new(BareRepository { repo: *refused.repo }),
+                denied: refused.denied,
+            }),
+        }
+    }
+}
+
+impl<B: backend::GitBackend> BareRepository<Unprotected, B> {
+    pub fn status(&self) -> String {
+        format!("{}: {} commits, bare, UNPROTECTED", self.repo.name, self.repo.total_commits)
+    }
+
+    /// Accept any pushed ref update, including deletions and
+    /// non-fast-forward updates. The consent was spent lifting protection.
+    pub fn receive(&self, update: &post_ops::RefUpdate) -> String {
+        self.accepted(update)
+    }
+
+    /// Rewrite history with filter-repo. Consumes the repository; see
+    /// [`Repository::filter_repo`].
+    pub fn filter_repo(
+        self,
+        callback: &str,
+        consent: UserConsent,
+    ) -> Result<RewriteResult<Self>, Refused<Self>> {
+        match self.repo.filter_repo(callback, consent) {
+            Ok(RewriteResult::Rewritten(filtered, bundle, receipt)) => Ok(RewriteResult::Rewritten(filtered, bundle, receipt)),
+            Ok(RewriteResult::Interrupted(repo, receipt)) => Ok(RewriteResult::Interrupted(BareRepository { repo }, receipt)),
+            Err(refused) => Err(Refused {
+                repo: Box::new(BareRepository { repo: *refused.repo }),
+                denied: refused.denied,
+            }),
+        }
+    }
+
+    /// Restore protection. Always allowed.
+    pub fn restore_protection(self) -> BareRepository<Protected, B> {
+        BareRepository {
+            repo: self.repo.restore_protection(),
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // What a bare repository never offers, in any state:
+    //
+    //   bare.reset_hard(..)  — there is no working tree to reset
+    //   bare.stash()         — there is no uncommitted work to stash
+    // -----------------------------------------------------------------------
+}
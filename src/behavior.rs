@@ -0,0 +1,232 @@
+//! Per-agent behavioral baselines, learned from the audit log.
+//!
+//! Every request the gate decides is recorded as a `REQUEST:` audit entry
+//! naming the operation, the repository, and the agent. A [`Baseline`] is
+//! those entries for one agent: which operations it asks for, which
+//! repositories it touches, and at what hours. A request that departs from
+//! it — the agent's first destructive request ever, an operation it has
+//! never asked for, activity at an hour it is never active, a burst across
+//! more repositories than it ever touches at once — is flagged.
+//!
+//! A deviation is context, not a verdict. The approver sees it above the
+//! challenge, the audit log records it, and an alert goes out if one is
+//! configured; the decision itself is still the policy's and the human's.
+//!
+//! Hours are UTC. Judging hours and spread needs [`MIN_HISTORY`] requests
+//! first: a baseline of three requests finds everything unusual.
+
+use super::post_ops::{Notification, Notifier, Recipient};
+use super::{AuditEntry, OperationKind, SafetyGate};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Requests needed before hours and spread are judged.
+pub const MIN_HISTORY: usize = 20;
+/// The window recent repository spread is measured over, in seconds.
+pub const SPREAD_WINDOW: u64 = 3600;
+
+/// One request, as read back from the audit log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observation {
+    pub at: u64,
+    pub agent: String,
+    pub operation: OperationKind,
+    pub repo: Option<String>,
+}
+
+/// The audit event recorded for every request the gate decides.
+pub(crate) fn request_event(operation: OperationKind, repo: Option<&str>, agent: &str) -> String {
+    match repo {
+        Some(repo) => format!("REQUEST: {} on '{}' by '{}'", operation.name(), repo, agent),
+        None => format!("REQUEST: {} by '{}'", operation.name(), agent),
+    }
+}
+
+/// The requests recorded in `entries`, in order.
+pub fn observations(entries: &[AuditEntry]) -> Vec<Observation> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let event = entry.event.strip_suffix(" [observe]").unwrap_or(&entry.event);
+            let rest = event.strip_prefix("REQUEST: ")?;
+            let (head, agent) = rest.rsplit_once(" by '")?;
+            let (name, repo) = match head.split_once(" on '") {
+                Some((name, repo)) => (name, Some(repo.strip_suffix('\'')?.to_string())),
+                None => (head, None),
+            };
+            Some(Observation {
+                at: entry.at,
+                agent: agent.strip_suffix('\'')?.to_string(),
+                operation: OperationKind::from_name(name)?,
+                repo,
+            })
+        })
+        .collect()
+}
+
+fn hour(at: u64) -> usize {
+    (at % 86_400 / 3600) as usize
+}
+
+/// How a request departs from its agent's baseline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Deviation {
+    /// The agent has never made a destructive request before.
+    FirstEver,
+    /// The agent has never asked for this operation.
+    NewOperation(OperationKind),
+    /// No request from this agent has ever come in this UTC hour.
+    UnusualHour { hour: usize, usual: Vec<usize> },
+    /// More repositories in the last hour than the agent ever touches
+    /// in one.
+    RepoSpread { recent: usize, typical: usize },
+}
+
+impl Deviation {
+    pub fn describe(&self) -> String {
+        match self {
+            Deviation::FirstEver => "first destructive request this agent has ever made".to_string(),
+            Deviation::NewOperation(kind) => format!("first {} request from this agent", kind.name()),
+            Deviation::UnusualHour { hour, usual } => {
+                let usual: Vec<String> = usual.iter().map(|h| format!("{:02}:00", h)).collect();
+                format!("activity at {:02}:00 UTC; this agent is usually active at {}", hour, usual.join(", "))
+            }
+            Deviation::RepoSpread { recent, typical } => format!(
+                "{} repositories in the last hour; this agent has never touched more than {} in one",
+                recent, typical
+            ),
+        }
+    }
+}
+
+/// What one agent usually does.
+#[derive(Clone, Debug, Default)]
+pub struct Baseline {
+    pub agent: String,
+    pub requests: usize,
+    pub operations: BTreeMap<&'static str, usize>,
+    pub repos: BTreeSet<String>,
+    /// Requests per UTC hour of day.
+    pub hours: [usize; 24],
+    /// The most repositories touched in any one hour.
+    pub peak_spread: usize,
+    history: Vec<Observation>,
+}
+
+impl Baseline {
+    /// The baseline for `agent` from everything in `observations`.
+    pub fn learn(agent: &str, observations: &[Observation]) -> Baseline {
+        let mut baseline = Baseline {
+            agent: agent.to_string(),
+            ..Baseline::default()
+        };
+        let mut spread: BTreeMap<u64, BTreeSet<&str>> = BTreeMap::new();
+        for seen in observations.iter().filter(|o| o.agent == agent) {
+            baseline.requests += 1;
+            *baseline.operations.entry(seen.operation.name()).or_default() += 1;
+            baseline.hours[hour(seen.at)] += 1;
+            if let Some(repo) = &seen.repo {
+                baseline.repos.insert(repo.clone());
+                spread.entry(seen.at / SPREAD_WINDOW).or_default().insert(repo);
+            }
+            baseline.history.push(seen.clone());
+        }
+        baseline.peak_spread = spread.values().map(BTreeSet::len).max().unwrap_or(0);
+        baseline
+    }
+
+    /// How a request for `operation` on `repo` at `at` departs from
+    /// this baseline.
+    pub fn assess(&self, operation: OperationKind, repo: Option<&str>, at: u64) -> Vec<Deviation> {
+        if self.requests == 0 {
+            return vec![Deviation::FirstEver];
+        }
+        let mut deviations = Vec::new();
+        if !self.operations.contains_key(operation.name()) {
+            deviations.push(Deviation::NewOperation(operation));
+        }
+        if self.requests < MIN_HISTORY {
+            return deviations;
+        }
+        if self.hours[hour(at)] == 0 {
+            deviations.push(Deviation::UnusualHour {
+                hour: hour(at),
+                usual: (0..24).filter(|h| self.hours[*h] > 0).collect(),
+            });
+        }
+        let mut recent: BTreeSet<&str> = self
+            .history
+            .iter()
+            .filter(|o| o.at + SPREAD_WINDOW > at)
+            .filter_map(|o| o.repo.as_deref())
+            .collect();
+        recent.extend(repo);
+        if recent.len() > self.peak_spread.max(1) * 2 && recent.len() >= 3 {
+            deviations.push(Deviation::RepoSpread {
+                recent: recent.len(),
+                typical: self.peak_spread,
+            });
+        }
+        deviations
+    }
+}
+
+/// What the gate needs to judge requests against baselines.
+pub(crate) struct Watch {
+    /// Requests from before this session, e.g. an archive's entries.
+    history: Vec<Observation>,
+    alerts: Option<(Box<dyn Notifier>, Recipient)>,
+}
+
+impl SafetyGate {
+    /// Judge every request against its agent's baseline, learned from
+    /// `history` (past sessions' audit entries) and this session's log.
+    pub fn watch_behavior(&mut self, history: &[AuditEntry]) {
+        let history = observations(history);
+        self.log(format!("BEHAVIOR: baselines learned from {} past requests", history.len()));
+        self.behavior = Some(Watch { history, alerts: None });
+    }
+
+    /// Send deviations to `recipient` as they are found. Requires
+    /// [`watch_behavior`](Self::watch_behavior) first.
+    pub fn alert_on_deviation(&mut self, notifier: Box<dyn Notifier>, recipient: Recipient) {
+        if let Some(watch) = &mut self.behavior {
+            watch.alerts = Some((notifier, recipient));
+        }
+    }
+
+    /// The baseline the gate would judge `agent` against right now.
+    pub fn baseline(&self, agent: &str) -> Baseline {
+        let mut seen = self.behavior.as_ref().map(|w| w.history.clone()).unwrap_or_default();
+        seen.extend(observations(self.audit.entries()));
+        Baseline::learn(agent, &seen)
+    }
+
+    /// Assess a request before it is recorded. Deviations are logged,
+    /// alerted, and returned for the prompt.
+    pub(crate) fn assess_behavior(&mut self, agent: &str, operation: OperationKind, repo: Option<&str>, at: u64) -> Vec<String> {
+        if self.behavior.is_none() {
+            return Vec::new();
+        }
+        let deviations: Vec<String> = self.baseline(agent).assess(operation, repo, at).iter().map(Deviation::describe).collect();
+        if deviations.is_empty() {
+            return deviations;
+        }
+        self.log(format!("ELEVATED RISK: {} by '{}': {}", operation.name(), agent, deviations.join("; ")));
+        if let Some((notifier, recipient)) = self.behavior.as_mut().and_then(|w| w.alerts.as_mut()) {
+            let alert = Notification {
+                recipient: recipient.clone(),
+                body: format!(
+                    "ELEVATED RISK: agent '{}' requested {}{}:\n  {}",
+                    agent,
+                    operation.name(),
+                    repo.map(|r| format!(" on '{}'", r)).unwrap_or_default(),
+                    deviations.join("\n  ")
+                ),
+            };
+            if let Err(err) = notifier.deliver(&alert) {
+                eprintln!("  [BEHAVIOR] alert delivery failed: {}", err);
+            }
+        }
+        deviations
+    }
+}
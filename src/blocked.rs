@@ -0,0 +1,107 @@
+//! Structured records of refused requests and refused uses of consent.
+//!
+//! The gate's DENIED lines say what it refused in its own words, which
+//! a person can read and a report cannot be built from. And consent
+//! refused when it is *used* — expired, revoked, still time-locked,
+//! presented for another repository, branch or operation, or from
+//! another agent session — was refused by the destructive method, which
+//! has no gate to write to: the agent got its [`Refused`] back and the
+//! log said nothing. Every consent carries its gate's [`Trail`], and
+//! each refusal, at request or at use, lands in the audit log as a
+//! `BLOCKED` entry with the operation, the repository, the agent
+//! session and the reason attached ([`AuditEntry::blocked`]).
+//!
+//! Refused uses are written to the log the next time the gate writes
+//! anything, or when its log is read.
+
+use super::agent_identity::{self, AgentIdentity};
+use super::{unix_now, ConsentDenied, OperationKind, SafetyGate, UserConsent};
+pub use super::BlockKind;
+use std::sync::{Arc, Mutex};
+
+/// One refusal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blocked {
+    pub kind: BlockKind,
+    pub operation: OperationKind,
+    /// The repository asked about, or the consent was presented for.
+    /// `None` if nothing named one.
+    pub target: Option<String>,
+    /// The agent session that asked, or presented the consent.
+    pub agent: Option<AgentIdentity>,
+    pub reason: String,
+}
+
+impl Blocked {
+    /// The audit event for this refusal.
+    pub fn render(&self) -> String {
+        let mut event = format!("BLOCKED ({}): {}", self.kind.label(), self.operation.name());
+        if let Some(target) = &self.target {
+            event.push_str(&format!(" on '{}'", target));
+        }
+        if let Some(agent) = &self.agent {
+            event.push_str(&format!(" by {}", agent.render()));
+        }
+        event.push_str(&format!(": {}", self.reason));
+        event
+    }
+}
+
+/// Refused uses of consent not yet in the log, shared by a gate and
+/// everything it minted.
+#[derive(Clone, Debug, Default)]
+pub struct Trail(Arc<Mutex<Vec<(u64, Blocked)>>>);
+
+impl Trail {
+    fn record(&self, blocked: Blocked) {
+        // A poisoned lock is still the trail; recording must not fail.
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push((unix_now(), blocked));
+    }
+
+    fn drain(&self) -> Vec<(u64, Blocked)> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl<Scope> UserConsent<Scope> {
+    /// Record `denied` as a refused use of this consent for `target`,
+    /// and hand it on.
+    pub(crate) fn blocked(&self, kind: BlockKind, target: Option<&str>, denied: ConsentDenied) -> ConsentDenied {
+        self._trail.record(Blocked {
+            kind,
+            operation: denied.operation,
+            target: target.map(str::to_string),
+            agent: agent_identity::current(),
+            reason: denied.reason.clone(),
+        });
+        denied
+    }
+}
+
+impl SafetyGate {
+    /// Every refusal in the audit log, oldest first.
+    pub fn blocked(&mut self) -> Vec<Blocked> {
+        self.collect_blocked();
+        self.audit.entries().iter().filter_map(|e| e.blocked.clone()).collect()
+    }
+
+    /// Record a request refused for `target`.
+    pub(crate) fn log_denied(&mut self, denied: &ConsentDenied, target: Option<String>) {
+        let blocked = Blocked {
+            kind: BlockKind::Denied,
+            operation: denied.operation,
+            target,
+            agent: agent_identity::current(),
+            reason: denied.reason.clone(),
+        };
+        self.collect_blocked();
+        self.append(unix_now(), blocked.render(), Some(blocked));
+    }
+
+    /// Move the refused uses consent recorded into the log.
+    pub(crate) fn collect_blocked(&mut self) {
+        for (at, blocked) in self.trail.drain() {
+            self.append(at, blocked.render(), Some(blocked));
+        }
+    }
+}
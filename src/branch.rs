@@ -0,0 +1,200 @@
+//! Branch<State> — protection per branch, as the forge enforces it.
+
+use crate::{
+    backend, ConsentDenied, OperationDescriptor, OperationKind, Outcome, Protected, Receipt,
+    Refused, Repository, SafetyError, Unprotected, UserConsent,
+};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::marker::PhantomData;
+
+/// A local branch as the repository was opened with, and as the cleanup
+/// workflow sees it.
+#[derive(Clone, Debug)]
+pub struct BranchInfo {
+    pub name: String,
+    /// Merged into the default branch: deleting it loses no commits.
+    pub merged: bool,
+    pub days_since_commit: u32,
+}
+
+/// One branch of a repository, parameterized by its own protection state.
+///
+/// Forges protect branches, not repositories. An agent that needs to
+/// force-push its feature branch should not have to lift protection from
+/// the whole repository to do it: `Branch<Unprotected>` for `feature/x`
+/// says nothing about `main`, which is still a `Branch<Protected>` and
+/// still has no `force_push`.
+///
+/// A branch borrows its repository, so a repository cannot be consumed —
+/// rewritten, reset, transferred — while one of its branches is in hand.
+pub struct Branch<'r, State = Protected> {
+    repo_name: &'r str,
+    repo_path: &'r str,
+    total_commits: usize,
+    repo_head: &'r str,
+    repo_branches: &'r [BranchInfo],
+    name: String,
+    backend: &'r dyn backend::GitBackend,
+    _state: PhantomData<State>,
+}
+
+impl<'r, State> Branch<'r, State> {
+    pub(crate) fn of<R, B: backend::GitBackend>(repo: &'r Repository<R, B>, name: &str) -> Self {
+        Branch {
+            repo_name: &repo.name,
+            repo_path: &repo.path,
+            total_commits: repo.total_commits,
+            repo_head: &repo.head,
+            repo_branches: &repo.branches,
+            name: name.to_string(),
+            backend: &repo.backend,
+            _state: PhantomData,
+        }
+    }
+
+    fn into_state<Next>(self) -> Branch<'r, Next> {
+        Branch {
+            repo_name: self.repo_name,
+            repo_path: self.repo_path,
+            total_commits: self.total_commits,
+            repo_head: self.repo_head,
+            repo_branches: self.repo_branches,
+            name: self.name,
+            backend: self.backend,
+            _state: PhantomData,
+        }
+    }
+
+    fn target(&self) -> backend::Target<'r> {
+        backend::Target {
+            name: self.repo_name,
+            path: self.repo_path,
+            total_commits: self.total_commits,
+            head: self.repo_head,
+            branches: self.repo_branches,
+            uncommitted: &[],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<'r> Branch<'r, Protected> {
+    pub fn status(&self) -> String {
+        format!("{}/{}: protected", self.repo_name, self.name)
+    }
+
+    /// Regular push is safe. No consent required.
+    pub fn push(&self) -> Result<String, Outcome> {
+        self.backend.push(&self.target(), &self.name)
+    }
+
+    /// Remove this branch's protection. Requires consent for exactly this
+    /// branch: consent to unprotect the repository, or another branch,
+    /// does not cover it.
+    pub fn remove_protection(self, consent: UserConsent) -> Result<Branch<'r, Unprotected>, Refused<Self>> {
+        let expected = OperationDescriptor::UnprotectBranch {
+            repo: self.repo_path.to_string(),
+            branch: self.name.clone(),
+        };
+        let checked = consent
+            .check_target(OperationKind::RemoveProtection, self.repo_name, self.repo_path)
+            .and_then(|()| consent.check_descriptor(&expected));
+        if let Err(denied) = checked {
+            return Err(Refused {
+                repo: Box::new(self),
+                denied,
+            });
+        }
+        consent.announce(&format!(
+            "Protection removed on branch '{}' of '{}' with user approval.",
+            self.name, self.repo_name
+        ));
+        Ok(self.into_state())
+    }
+}
+
+impl<'r> Branch<'r, Unprotected> {
+    fn push_to(&self) -> OperationDescriptor {
+        OperationDescriptor::ForcePush {
+            repo: self.repo_path.to_string(),
+            branch: self.name.clone(),
+        }
+    }
+
+    fn check_push(&self, consent: &UserConsent) -> Result<(), ConsentDenied> {
+        consent.check_target(OperationKind::ForcePush, self.repo_name, self.repo_path)?;
+        consent.check_branch(&self.name)?;
+        consent.check_descriptor(&self.push_to())
+    }
+
+    /// Force-push this branch. Requires consent granted for this branch.
+    pub fn force_push(&self, consent: UserConsent) -> Result<String, SafetyError> {
+        self.check_push(&consent)?;
+        if self.name == "main" {
+            consent.announce(&format!("Force-push to '{}' with user approval.", self.repo_name));
+        } else {
+            consent.announce(&format!(
+                "Force-push to '{}' branch '{}' with user approval.",
+                self.repo_name, self.name
+            ));
+        }
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] force-push to origin/{} simulated", self.repo_name, self.name));
+        }
+        let target = self.target();
+        #[cfg(feature = "std")]
+        let fetched = self.backend.tip(&target, &self.name, true);
+        let (pushed, receipt) = backend::run(OperationKind::ForcePush, &target, || {
+            let options = backend::ExecOptions::default();
+            self.backend.force_push(&target, &self.name, &options, &mut backend::NoProgress)
+        });
+        let Some(pushed) = pushed else {
+            return Err(SafetyError::from(receipt));
+        };
+        #[cfg(feature = "std")]
+        self.tell_downstream(&consent, &receipt, fetched);
+        Ok(pushed)
+    }
+
+    /// `force_push` with a deadline, cancellation, and progress reporting,
+    /// one unit per commit transferred.
+    pub fn force_push_with(
+        &self,
+        consent: UserConsent,
+        options: &backend::ExecOptions,
+        progress: &mut dyn backend::Progress,
+    ) -> Result<Receipt, ConsentDenied> {
+        self.check_push(&consent)?;
+        let target = self.target();
+        #[cfg(feature = "std")]
+        let fetched = self.backend.tip(&target, &self.name, true);
+        let (_, mut receipt) = backend::run(OperationKind::ForcePush, &target, || match consent.is_observe_only() {
+            true => backend::GitBackend::force_push(&backend::Simulated, &target, &self.name, options, progress),
+            false => self.backend.force_push(&target, &self.name, options, progress),
+        });
+        receipt.observed = consent.is_observe_only();
+        #[cfg(feature = "std")]
+        self.tell_downstream(&consent, &receipt, fetched);
+        Ok(receipt)
+    }
+
+    /// Tell the gate's downstream, if it has one, that this branch moved
+    /// on the remote from `fetched` to the local tip.
+    #[cfg(feature = "std")]
+    fn tell_downstream(&self, consent: &UserConsent, receipt: &Receipt, fetched: Result<String, Outcome>) {
+        let pushed = self.backend.tip(&self.target(), &self.name, false);
+        let update = crate::post_ops::RefUpdate::branch(&self.name, fetched, pushed);
+        consent._downstream.rewrote(receipt, &[update]);
+    }
+
+    /// Put this branch's protection back. Always safe.
+    pub fn restore_protection(self) -> Branch<'r, Protected> {
+        say!("  [OK] Protection restored on branch '{}' of '{}'.", self.name, self.repo_name);
+        self.into_state()
+    }
+}
@@ -0,0 +1,229 @@
+//! Operation budgets granted at the start of a session.
+//!
+//! Supervised batch work — a rebase of a dozen branches onto a fork, say —
+//! asks the same question over and over, and an approver asked a dozen
+//! times stops reading. A [`ConsentBudget`] is the answer given once:
+//!
+//! ```no_run
+//! # use safe_operations::{budget::ConsentBudget, ConsentDenied, OperationKind, SafetyGate};
+//! # fn grant(gate: &mut SafetyGate) -> Result<(), ConsentDenied> {
+//! let budget = ConsentBudget::new()
+//!     .allow(OperationKind::ForcePush, 2, Some("/home/me/fork"))
+//!     .forbid(OperationKind::FilterRepo);
+//! gate.grant_budget(budget, "rebase the fork's feature branches")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The approver is challenged once, for the gravest operation the budget
+//! allows. After that, each request an allowance covers is debited from
+//! it instead of prompting, with an audit entry for every debit. An
+//! allowance that runs out does not fall back to asking: the request is
+//! refused outright, and so is any request a zero allowance covers.
+//! Requests the budget says nothing about are prompted as usual.
+//!
+//! A debit is still a decision of the gate. The deny-list, manifest,
+//! policy, quorum, and reason rules in force are applied first, so a debit
+//! only ever stands in for the challenge. A budget that only forbids needs
+//! no approval to take effect.
+
+use super::{policy, ConsentDenied, OperationKind, SafetyGate};
+
+/// Uses of one operation, optionally on one repository or branch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub operation: OperationKind,
+    /// The repository's path. `None` covers every repository.
+    pub target: Option<String>,
+    /// Branches it covers: a name, or a prefix ending in `*`. `None`
+    /// places no limit on the branch.
+    pub branches: Option<String>,
+    pub limit: u32,
+    used: u32,
+}
+
+impl Allowance {
+    pub fn used(&self) -> u32 {
+        self.used
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.limit - self.used
+    }
+
+    fn covers(&self, operation: OperationKind, target: Option<&str>, branch: Option<&str>) -> bool {
+        let branch_covered = match (&self.branches, branch) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pattern), Some(branch)) => match pattern.strip_suffix('*') {
+                Some(prefix) => branch.starts_with(prefix),
+                None => branch == pattern,
+            },
+        };
+        self.operation == operation
+            && self.target.as_deref().is_none_or(|t| target == Some(t))
+            && branch_covered
+    }
+
+    fn render(&self) -> String {
+        let mut out = match self.limit {
+            0 => format!("no {}", self.operation.name()),
+            n => format!("up to {} {}", n, self.operation.name()),
+        };
+        if let Some(target) = &self.target {
+            out.push_str(&format!(" on '{}'", target));
+        }
+        if let Some(branches) = &self.branches {
+            out.push_str(&format!(" to `{}`", branches));
+        }
+        out
+    }
+}
+
+/// What the approver pre-authorized for the session. Earlier
+/// allowances take precedence over later ones that cover the same
+/// request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsentBudget {
+    allowances: Vec<Allowance>,
+}
+
+impl ConsentBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Up to `limit` uses of `operation`, on `target` if given.
+    pub fn allow(mut self, operation: OperationKind, limit: u32, target: Option<&str>) -> Self {
+        self.allowances.push(Allowance {
+            operation,
+            target: target.map(str::to_string),
+            branches: None,
+            limit,
+            used: 0,
+        });
+        self
+    }
+
+    /// Narrow the allowance added last to branches matching `pattern`.
+    pub fn branches(mut self, pattern: &str) -> Self {
+        if let Some(last) = self.allowances.last_mut() {
+            last.branches = Some(pattern.to_string());
+        }
+        self
+    }
+
+    /// None of `operation`, anywhere, for the rest of the session.
+    pub fn forbid(self, operation: OperationKind) -> Self {
+        self.allow(operation, 0, None)
+    }
+
+    pub fn allowances(&self) -> &[Allowance] {
+        &self.allowances
+    }
+
+    pub fn render(&self) -> String {
+        self.allowances.iter().map(Allowance::render).collect::<Vec<_>>().join("; ")
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.allowances.is_empty() {
+            return Err("a budget must name at least one operation".to_string());
+        }
+        if let Some(pattern) = self.allowances.iter().filter_map(|a| a.branches.as_ref()).find(|p| {
+            p.is_empty() || p[..p.len() - 1].contains('*')
+        }) {
+            return Err(format!("branch pattern `{}`: `*` may only end it", pattern));
+        }
+        Ok(())
+    }
+}
+
+impl SafetyGate {
+    /// Ask the approver, once, to grant `budget` for the rest of the
+    /// session. Replaces any budget granted before.
+    pub fn grant_budget(&mut self, budget: ConsentBudget, operation_description: &str) -> Result<(), ConsentDenied> {
+        // The budget being replaced must not pay for its replacement.
+        self.budget = None;
+        let granted: Vec<OperationKind> = budget.allowances.iter().filter(|a| a.limit > 0).map(|a| a.operation).collect();
+        let first = budget.allowances.first().map_or(OperationKind::ForcePush, |a| a.operation);
+        if let Err(why) = budget.validate() {
+            self.log(format!("DENIED (budget): {}: {}", operation_description, why));
+            let denied = ConsentDenied::new(first, why);
+            self.notify_denial(&denied);
+            return Err(denied);
+        }
+        for &operation in &granted {
+            self.screen_forbidden(operation, None, operation_description)?;
+            if self.policy.decide(operation) == policy::Decision::Deny {
+                let reason = format!("policy {} denies {}", self.policy.version, operation.name());
+                self.log(format!("DENIED (budget): {}: {}", operation_description, reason));
+                let denied = ConsentDenied::new(operation, reason);
+                self.notify_denial(&denied);
+                return Err(denied);
+            }
+        }
+        if let Some(gravest) = granted.iter().copied().max_by_key(|k| k.severity()) {
+            let description = format!("budget {}: {}", budget.render(), operation_description);
+            self.mint::<super::General>(gravest, &description)?;
+        }
+        self.log(format!("BUDGET GRANTED: {}", budget.render()));
+        self.budget = Some(budget);
+        Ok(())
+    }
+
+    /// The session's budget, with what has been spent from it.
+    pub fn budget(&self) -> Option<&ConsentBudget> {
+        self.budget.as_ref()
+    }
+
+    /// End the session's budget. Requests are prompted again.
+    pub fn close_budget(&mut self) {
+        if let Some(budget) = self.budget.take() {
+            let spent: Vec<String> = budget
+                .allowances
+                .iter()
+                .filter(|a| a.limit > 0)
+                .map(|a| format!("{} {} of {}", a.operation.name(), a.used, a.limit))
+                .collect();
+            self.log(format!("BUDGET CLOSED: spent {}", spent.join(", ")));
+        }
+    }
+
+    /// Debit the allowance covering this request, if one does. `Ok(None)`
+    /// means the budget says nothing about it; an allowance with
+    /// nothing left refuses it.
+    pub(crate) fn debit_budget(
+        &mut self,
+        operation: OperationKind,
+        target: Option<&str>,
+        branch: Option<&str>,
+        operation_description: &str,
+    ) -> Result<Option<String>, ConsentDenied> {
+        let Some(allowance) = self
+            .budget
+            .as_mut()
+            .and_then(|b| b.allowances.iter_mut().find(|a| a.covers(operation, target, branch)))
+        else {
+            return Ok(None);
+        };
+        if allowance.remaining() == 0 {
+            let reason = match allowance.limit {
+                0 => format!("the session budget allows no {}", operation.name()),
+                n => format!("the session budget of {} {} is spent", n, operation.name()),
+            };
+            self.log(format!("DENIED BY BUDGET: {}", operation_description));
+            return Err(ConsentDenied::new(operation, reason));
+        }
+        allowance.used += 1;
+        let event = format!(
+            "BUDGET DEBIT: {} ({} of {} {} left)",
+            operation_description,
+            allowance.remaining(),
+            allowance.limit,
+            operation.name()
+        );
+        self.log(event);
+        Ok(Some("session budget".to_string()))
+    }
+}
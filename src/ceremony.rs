@@ -0,0 +1,142 @@
+//! Terminal capture of the approval ceremony.
+//!
+//! A receipt says consent was given; it does not say what the approver was
+//! looking at when they gave it. With recording on, everything the gate
+//! prints is captured as an asciinema v2 cast, and the cast's digest goes
+//! on the receipt. A reviewer replays the cast, checks the digest, and sees
+//! the destruction plan exactly as it was presented.
+
+use super::{json_string, unix_now, DestructionPlan, Receipt, SafetyGate};
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+
+/// A capture in progress.
+pub struct Recording {
+    started_at: u64,
+    start: Instant,
+    events: Vec<(f64, String)>,
+}
+
+/// A finished capture.
+#[derive(Clone, Debug)]
+pub struct Cast {
+    started_at: u64,
+    events: Vec<(f64, String)>,
+}
+
+impl Cast {
+    /// The capture as an asciinema v2 file: a header line, then one
+    /// `[seconds, "o", text]` output event per line.
+    pub fn to_asciicast(&self) -> String {
+        let mut out = format!(
+            "{{\"version\": 2, \"width\": 100, \"height\": 40, \"timestamp\": {}, \"title\": \"consent ceremony\"}}\n",
+            self.started_at
+        );
+        for (at, text) in &self.events {
+            out.push_str(&format!("[{:.6}, \"o\", {}]\n", at, json_string(&format!("{}\r\n", text))));
+        }
+        out
+    }
+
+    /// SHA-256 over the cast file, so a reviewer can recompute it from
+    /// the cast alone, and nobody can make a different cast that matches.
+    pub fn digest(&self) -> String {
+        let hex: String = Sha256::digest(self.to_asciicast()).iter().map(|b| format!("{:02x}", b)).collect();
+        format!("sha256:{}", hex)
+    }
+}
+
+impl Receipt {
+    /// Attach the approval session this execution was consented in.
+    pub fn with_ceremony(mut self, cast: &Cast) -> Self {
+        self.ceremony = Some(cast.digest());
+        self
+    }
+}
+
+impl SafetyGate {
+    /// Begin capturing everything the gate shows the approver.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording {
+            started_at: unix_now(),
+            start: Instant::now(),
+            events: Vec::new(),
+        });
+    }
+
+    /// Stop capturing and return the cast, if a recording was running.
+    pub fn finish_recording(&mut self) -> Option<Cast> {
+        self.recording.take().map(|r| Cast {
+            started_at: r.started_at,
+            events: r.events,
+        })
+    }
+
+    /// Show the approver a destruction plan before asking for consent.
+    pub fn present_plan(&mut self, plan: &DestructionPlan) {
+        use super::approver::Line;
+        self.provenance.note(super::provenance::Link::Plan(plan.render()));
+        self.show_line(Line::Heading(format!(
+            "{} on '{}' ({} items):",
+            plan.operation.name(),
+            plan.target,
+            plan.items.len()
+        )));
+        for item in &plan.items {
+            self.show_line(Line::Item(item.clone()));
+        }
+        if !plan.costs.is_empty() {
+            self.show_line(Line::Heading("Cost to recover:".to_string()));
+            for cost in &plan.costs {
+                self.show_line(Line::Item(cost.render()));
+            }
+        }
+    }
+
+    /// Show the approver one line, rendered for their profile.
+    pub(crate) fn show_line(&mut self, line: super::approver::Line) {
+        let rendered = self.profile.render(&line);
+        // The phrase to type goes to the approver, never to the responder.
+        self.show(&rendered, !matches!(line, super::approver::Line::Challenge(_)));
+    }
+
+    /// Print a line to the approver, capturing it if recording, and pass
+    /// it on to the responder unless it is the challenge.
+    fn show(&mut self, line: &str, to_responder: bool) {
+        let line = match self.mode {
+            super::observe::GateMode::Enforce => line.to_string(),
+            super::observe::GateMode::Observe => {
+                let body = line.trim_start();
+                format!("{}[OBSERVE] {}", &line[..line.len() - body.len()], body)
+            }
+        };
+        println!("{}", line);
+        if to_responder {
+            self.responder.shown(&line);
+        }
+        self.capture(&line);
+    }
+
+    /// Capture a line the terminal already shows, such as what the
+    /// approver typed.
+    pub(crate) fn capture(&mut self, line: &str) {
+        if let Some(recording) = &mut self.recording {
+            let at = recording.start.elapsed().as_secs_f64();
+            recording.events.push((at, line.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cast;
+
+    #[test]
+    fn a_cast_digest_is_the_sha256_of_its_file() {
+        let cast = Cast {
+            started_at: 1772000000,
+            events: vec![(0.5, "Approve?".to_string())],
+        };
+        assert_eq!(cast.digest(), "sha256:99db730f10147bf731c3da8eb00b5ed2e7368965fcc984fc173568db718d1bb1");
+    }
+}
@@ -0,0 +1,148 @@
+//! Chaos testing for the gate's failure paths (`--features chaos`).
+//!
+//! Faults are injected at each [`FaultPoint`] from a seeded generator, so a
+//! failing run is reproduced exactly by its seed. [`run_trials`] drives the
+//! built-in scenarios, a manifest bind, and an ownership transfer through
+//! faulty gates and checks three properties:
+//! - no consent is granted on a path where a fault fired;
+//! - no consent is granted without its audit entry;
+//! - once faults stop, the same gate grants a clean request again.
+
+use super::{scenario, AgentManifest, Capability, FaultPoint, HostKey, OperationKind, Repository, SafetyGate};
+
+/// A seeded fault plan.
+pub struct Chaos {
+    state: u64,
+    /// Chance, in thousandths, that any one point fires.
+    rate_per_mille: u64,
+    /// Points that fire every time, for reproducing one fault.
+    forced: Vec<FaultPoint>,
+    /// Every fault that fired, in order.
+    pub injected: Vec<FaultPoint>,
+}
+
+impl Chaos {
+    pub fn seeded(seed: u64, rate_per_mille: u64) -> Self {
+        Chaos {
+            state: seed.max(1),
+            rate_per_mille,
+            forced: Vec::new(),
+            injected: Vec::new(),
+        }
+    }
+
+    /// A plan where `point` always fires and nothing else does.
+    pub fn always(point: FaultPoint) -> Self {
+        let mut chaos = Chaos::seeded(1, 0);
+        chaos.forced.push(point);
+        chaos
+    }
+
+    fn next(&mut self) -> u64 {
+        // xorshift64*: deterministic per seed, no dependencies.
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub(crate) fn fire(&mut self, point: FaultPoint) -> bool {
+        let fired = self.forced.contains(&point) || self.next() % 1000 < self.rate_per_mille;
+        if fired {
+            self.injected.push(point);
+        }
+        fired
+    }
+
+    /// Clock skew for a [`FaultPoint::Clock`] fault: up to a day either way.
+    pub(crate) fn skew(&mut self) -> i64 {
+        (self.next() % 172_800) as i64 - 86_400
+    }
+}
+
+impl SafetyGate {
+    pub fn set_chaos(&mut self, chaos: Option<Chaos>) {
+        self.chaos = chaos;
+    }
+
+    fn injected(&self) -> usize {
+        self.chaos.as_ref().map_or(0, |c| c.injected.len())
+    }
+}
+
+pub struct ChaosReport {
+    pub trials: u64,
+    pub faults: usize,
+    pub violations: Vec<String>,
+}
+
+impl ChaosReport {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "chaos: {} trials, {} faults injected, {} violations\n",
+            self.trials,
+            self.faults,
+            self.violations.len()
+        );
+        for v in &self.violations {
+            out.push_str(&format!("  VIOLATION {}\n", v));
+        }
+        out
+    }
+}
+
+/// Run `trials` fault-injected sessions, trial `i` seeded `seed + i`.
+pub fn run_trials(seed: u64, trials: u64) -> ChaosReport {
+    let mut report = ChaosReport {
+        trials,
+        faults: 0,
+        violations: Vec::new(),
+    };
+    for trial in 0..trials {
+        let trial_seed = seed + trial;
+        let mut violate = |what: String| report.violations.push(format!("seed {}: {}", trial_seed, what));
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(super::approver::Rehearsal));
+        gate.set_chaos(Some(Chaos::seeded(trial_seed, 300)));
+
+        for s in scenario::all() {
+            for step in s.steps.iter().filter(|st| st.asked_user) {
+                let before = gate.injected();
+                let granted = gate.request_consent(step.operation, &step.description).is_ok();
+                let faulted = gate.injected() > before;
+                if granted && faulted {
+                    violate(format!("granted '{}' after a fault", step.description));
+                }
+                let event = format!("GRANTED {}: {}", step.operation.name(), step.description);
+                let recorded = gate.audit_log().entries().iter().any(|e| e.event == event);
+                if granted && !recorded {
+                    violate(format!("granted '{}' with no audit entry", step.description));
+                }
+            }
+        }
+
+        let key = HostKey::generate();
+        let manifest = AgentManifest::issue(
+            "chaos-agent",
+            &[Capability::Read, Capability::Request(OperationKind::DeleteBranch)],
+            &key,
+        );
+        let before = gate.injected();
+        if gate.bind_manifest(&manifest, &key).is_ok() && gate.injected() > before {
+            violate("bound a manifest after a signing fault".to_string());
+        }
+
+        let repo = Repository::protected("chaos-repo", "/tmp/chaos-repo", 1);
+        let ceremony = gate.begin_transfer(&repo, "elsewhere");
+        if gate.complete_transfer(ceremony).is_ok() {
+            violate("completed a transfer inside its waiting period".to_string());
+        }
+
+        report.faults += gate.injected();
+        gate.set_chaos(None);
+        if gate.request_consent(OperationKind::DeleteBranch, "recovery check").is_err() {
+            violate("gate did not recover once faults stopped".to_string());
+        }
+    }
+    report
+}
@@ -0,0 +1,530 @@
+//! Machine-verifiable safety claims.
+//!
+//! Every guarantee this file makes in prose is registered here with a
+//! probe: code an agent might write that must *not* compile, and the error
+//! code rustc must reject it with. `--claims` emits the registry as
+//! `CLAIMS.json`. Every probe is also a `compile_fail` doctest on
+//! [`CLAIMS`], so `cargo test` fails the moment one of them compiles;
+//! `--verify-claims` goes further and checks the error code, building each
+//! probe as a separate crate against this one — the agent's view, outside
+//! the crate, where private fields really are private. A guarantee that
+//! silently stops holding turns into a probe that compiles.
+//!
+//! The claims that are facts about the API — a method missing from a
+//! typestate, a type with no constructor — are also registered in
+//! [`SURFACE`] and checked against rustdoc's JSON for the crate as built
+//! ([`verify_surface`]). A probe can rot into failing for another reason
+//! with the same error code; the API as rustdoc lists it cannot.
+//!
+//! This is a verifier run on the built crate, not a build script: a build
+//! script runs before the crate it builds exists, so it has no API to
+//! document. rustdoc's JSON output needs a nightly toolchain.
+
+use super::json_string;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+pub struct Claim {
+    pub id: &'static str,
+    pub statement: &'static str,
+    /// Body of a function in a crate that depends on this one.
+    pub probe: &'static str,
+    /// The rustc error code the probe must fail with.
+    pub expect: &'static str,
+}
+
+/// Registers the claims, and renders each probe as a `compile_fail`
+/// doctest on [`CLAIMS`].
+macro_rules! claims {
+    ($(Claim { id: $id:literal, statement: $statement:literal, probe: $probe:literal, expect: $expect:literal $(,)? }),* $(,)?) => {
+        /// Every guarantee, with the probe that must not compile.
+        ///
+        /// The probes below are doctests: each must fail to compile, with
+        /// the error code in its header.
+        $(#[doc = concat!("\n`", $id, "`: ", $statement, "\n\n```compile_fail,", $expect, "\n# use safe_operations::*;\n", $probe, "\n```")])*
+        pub const CLAIMS: &[Claim] = &[$(Claim { id: $id, statement: $statement, probe: $probe, expect: $expect }),*];
+    };
+}
+
+claims! {
+    Claim {
+        id: "force-push-unreachable-from-protected",
+        statement: "force_push is not callable on Repository<Protected>",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { r.force_push(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "filter-repo-unreachable-from-protected",
+        statement: "filter_repo is not callable on Repository<Protected>",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { r.filter_repo(\"cb\", c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "reset-hard-unreachable-from-protected",
+        statement: "reset_hard is not callable on Repository<Protected>",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { r.reset_hard(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "force-push-unreachable-from-protected-branch",
+        statement: "force_push is not callable on a Branch<Protected>, whatever other branches allow",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { r.branch(\"main\").force_push(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "submodule-history-unreachable-from-pointer-update",
+        statement: "force_push is not callable on a Submodule<Protected>, whatever its superproject allows",
+        probe: "fn probe(r: Repository<Unprotected>, c: UserConsent) { r.submodules()[0].force_push(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "repository-not-consumable-under-worktree",
+        statement: "a repository cannot be unprotected while one of its worktrees is in hand",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { let w = r.worktree(\"/tmp/w\"); let _ = r.remove_protection(c); w.status(); }",
+        expect: "E0505",
+    },
+    Claim {
+        id: "reset-hard-unreachable-on-bare-repository",
+        statement: "reset_hard is not callable on a BareRepository, even unprotected",
+        probe: "fn probe(r: BareRepository<Unprotected>, c: UserConsent) { r.reset_hard(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "commit-unreachable-mid-rebase",
+        statement: "nothing but continue, abort, and conflict inspection is callable mid-rebase",
+        probe: "fn probe(r: Repository<RebaseInProgress>) { r.commit(); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "remove-protection-requires-consent",
+        statement: "remove_protection cannot be called without a UserConsent",
+        probe: "fn probe(r: Repository<Protected>) { r.remove_protection(); }",
+        expect: "E0061",
+    },
+    Claim {
+        id: "user-consent-no-public-constructor",
+        statement: "UserConsent has no public constructor",
+        probe: "fn probe() -> UserConsent { UserConsent { _operation: String::new(), _nonce: 0, _issued_at: 0, _signature: ed25519::Signature([0; 64]), _kind: OperationKind::ForcePush, _target: None, _branch: None, _descriptor: None, _observe: false, _expires_at: u64::MAX, _not_before: 0, _revocations: Default::default(), _agent: None, _trail: Default::default(), _ledger: Default::default(), _downstream: Default::default(), _scope: std::marker::PhantomData } }",
+        expect: "E0451",
+    },
+    Claim {
+        id: "consent-scopes-not-interchangeable",
+        statement: "UserConsent<CiConfigChange> cannot stand in for general consent",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent<CiConfigChange>) { r.remove_protection(c); }",
+        expect: "E0308",
+    },
+    Claim {
+        id: "attribution-strip-needs-its-own-consent",
+        statement: "general consent cannot stand in for consent to strip Co-Authored-By trailers",
+        probe: "fn probe(b: CommitBuilder, c: UserConsent) { b.build_with_consent(c); }",
+        expect: "E0308",
+    },
+    Claim {
+        id: "filter-repo-consumes-repository",
+        statement: "a repository cannot be used after filter_repo",
+        probe: "fn probe(r: Repository<Unprotected>, c: UserConsent, d: UserConsent) { let _f = r.filter_repo(\"cb\", c); r.reset_hard(d); }",
+        expect: "E0382",
+    },
+    Claim {
+        id: "consent-single-use",
+        statement: "one UserConsent cannot authorize two destructive operations",
+        probe: "fn probe(r: &Repository<Unprotected>, c: UserConsent) { let _ = r.force_push(c); let _ = r.force_push(c); }",
+        expect: "E0382",
+    },
+    Claim {
+        id: "workflow-force-push-needs-unprotect",
+        statement: "a composed workflow cannot force-push before protection is removed",
+        probe: "fn probe() { let _ = compose::Workflow::new().then(compose::gated(compose::ForcePush, Severity::High)); }",
+        expect: "E0277",
+    },
+    Claim {
+        id: "workflow-destructive-steps-gated",
+        statement: "a composed workflow cannot include a destructive step that is not gated",
+        probe: "fn probe() { let _ = compose::Workflow::new().then(compose::RemoveProtection); }",
+        expect: "E0277",
+    },
+    Claim {
+        id: "workflow-nothing-after-consuming-step",
+        statement: "a composed workflow cannot continue after a step that consumes the repository",
+        probe: "fn probe() { let _ = compose::Workflow::new().then(compose::gated(compose::RemoveProtection, Severity::High)).then(compose::gated(compose::ResetHard, Severity::High)).then(compose::Push); }",
+        expect: "E0277",
+    },
+    Claim {
+        id: "session-ends-restored",
+        statement: "a protection ceremony cannot end without restoring protection",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent) { let _ = session::lifted(r, c, |lifted| lifted); }",
+        expect: "E0308",
+    },
+    Claim {
+        id: "session-refusal-still-restores",
+        statement: "a refused step inside a protection ceremony cannot skip the restore",
+        probe: "fn probe(r: Repository<Protected>, c: UserConsent, d: UserConsent) { let _ = session::lifted(r, c, |lifted| match lifted.force_push(d) { Ok(pushed) => pushed.restore(), Err(stalled) => *stalled.session }); }",
+        expect: "E0308",
+    },
+    Claim {
+        id: "custom-gate-keeps-typestate",
+        statement: "consent from a custom ConsentGate cannot force-push a protected repository",
+        probe: "fn probe<G: consent_gate::ConsentGate>(r: Repository<Protected>, g: &mut G, c: G::Consent) { r.force_push_via(g, c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "responder-not-replaceable-by-gate-holder",
+        statement: "code holding a SafetyGate cannot replace who answers its challenges",
+        probe: "fn probe(g: &mut SafetyGate) { g.set_responder(Box::new(approver::Console)); }",
+        expect: "E0624",
+    },
+    Claim {
+        id: "rehearsal-responder-private",
+        statement: "the responder that types challenges back is not reachable outside the crate",
+        probe: "fn probe() -> Box<dyn approver::Responder> { Box::new(approver::Rehearsal) }",
+        expect: "E0603",
+    },
+    Claim {
+        id: "unsigned-policy-not-enforceable",
+        statement: "code holding a SafetyGate cannot swap in a policy that was not signed",
+        probe: "fn probe(g: &mut SafetyGate) { g.set_shadow_policy(policy::Policy::builtin()); g.promote_shadow_policy(); }",
+        expect: "E0624",
+    },
+    Claim {
+        id: "commit-count-not-writable",
+        statement: "code holding a repository cannot shrink the commit count it was opened with",
+        probe: "fn probe(r: &mut Repository<Unprotected>) { r.total_commits = 0; }",
+        expect: "E0616",
+    },
+    Claim {
+        id: "merge-status-not-writable",
+        statement: "code holding a repository cannot mark a branch merged so that deleting it needs less consent",
+        probe: "fn probe(r: &mut Repository<Protected>) { r.branches[0].merged = true; }",
+        expect: "E0616",
+    },
+    Claim {
+        id: "opened-repository-is-read-only",
+        statement: "a repository is opened read-only: removing its protection needs a commit grant first",
+        probe: "fn probe(c: UserConsent) { let _ = Repository::open(\"r\", \"/r\", 1).remove_protection(c); }",
+        expect: "E0599",
+    },
+    Claim {
+        id: "working-tree-not-writable",
+        statement: "code holding a read-only repository cannot clear its uncommitted files so a grant comes back clean",
+        probe: "fn probe(r: &mut Repository<ReadOnly>) { r.uncommitted.clear(); }",
+        expect: "E0616",
+    },
+    Claim {
+        id: "agent-identity-not-forgeable",
+        statement: "code running as an agent cannot make an identity to enter; only the host can",
+        probe: "fn probe() { let _ = agent_identity::AgentIdentity::new(\"builder\", \"model\", \"s-1\"); }",
+        expect: "E0624",
+    },
+    Claim {
+        id: "agent-host-not-constructible",
+        statement: "code running as an agent cannot construct the host capability that enters identities",
+        probe: "fn probe(i: agent_identity::AgentIdentity) { let h = agent_identity::Host { _private: () }; h.enter(i); }",
+        expect: "E0451",
+    },
+}
+
+/// What a claim says about the public API, as rustdoc lists it.
+pub enum Surface {
+    /// No method `method` on `ty` in any impl that covers the typestate
+    /// `state`: one for `state` itself, or one generic over the state.
+    NoMethod {
+        ty: &'static str,
+        state: &'static str,
+        method: &'static str,
+    },
+    /// `ty` has no public fields, no `Default`, and no associated function
+    /// without `self` that returns one.
+    NoConstructor(&'static str),
+}
+
+impl Surface {
+    pub fn render(&self) -> String {
+        match self {
+            Surface::NoMethod { ty, state, method } => format!("no method {} on {}<{}>", method, ty, state),
+            Surface::NoConstructor(ty) => format!("no public constructor for {}", ty),
+        }
+    }
+}
+
+/// The claims checked against the API as well as by their probe, by id.
+pub const SURFACE: &[(&str, Surface)] = &[
+    (
+        "force-push-unreachable-from-protected",
+        Surface::NoMethod {
+            ty: "Repository",
+            state: "Protected",
+            method: "force_push",
+        },
+    ),
+    (
+        "filter-repo-unreachable-from-protected",
+        Surface::NoMethod {
+            ty: "Repository",
+            state: "Protected",
+            method: "filter_repo",
+        },
+    ),
+    (
+        "reset-hard-unreachable-from-protected",
+        Surface::NoMethod {
+            ty: "Repository",
+            state: "Protected",
+            method: "reset_hard",
+        },
+    ),
+    (
+        "force-push-unreachable-from-protected-branch",
+        Surface::NoMethod {
+            ty: "Branch",
+            state: "Protected",
+            method: "force_push",
+        },
+    ),
+    (
+        "reset-hard-unreachable-on-bare-repository",
+        Surface::NoMethod {
+            ty: "BareRepository",
+            state: "Unprotected",
+            method: "reset_hard",
+        },
+    ),
+    (
+        "commit-unreachable-mid-rebase",
+        Surface::NoMethod {
+            ty: "Repository",
+            state: "RebaseInProgress",
+            method: "commit",
+        },
+    ),
+    ("user-consent-no-public-constructor", Surface::NoConstructor("UserConsent")),
+];
+
+/// The registry as `CLAIMS.json`.
+pub fn to_json() -> String {
+    let claims: Vec<String> = CLAIMS
+        .iter()
+        .map(|c| {
+            let api = SURFACE.iter().find(|(id, _)| *id == c.id).map(|(_, surface)| json_string(&surface.render()));
+            format!(
+                "  {{\"id\": {}, \"statement\": {}, \"probe\": {}, \"expect\": {}, \"api\": {}}}",
+                json_string(c.id),
+                json_string(c.statement),
+                json_string(c.probe),
+                json_string(c.expect),
+                api.as_deref().unwrap_or("null")
+            )
+        })
+        .collect();
+    format!("{{\"claims\": [\n{}\n]}}\n", claims.join(",\n"))
+}
+
+/// One claim's verdict: `Err` says why it does not hold.
+pub type Verdict = (&'static str, Result<(), String>);
+
+/// Check every claim against the package at `package` with the `cargo`
+/// on `PATH`.
+///
+/// Each probe is built as a binary crate that depends on the package by
+/// path. Returns one result per claim. `Err` carries why it failed: the
+/// probe compiled (the guarantee is gone) or failed for the wrong reason
+/// (the probe has rotted and no longer tests the guarantee).
+pub fn verify(package: &Path) -> Result<Vec<Verdict>, String> {
+    let package = package.canonicalize().map_err(|e| format!("{}: {}", package.display(), e))?;
+    let dir = std::env::temp_dir().join(format!("safe-operations-claims-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).map_err(|e| e.to_string())?;
+    let manifest = format!(
+        "[package]\nname = \"claim-probe\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n[dependencies]\nsafe-operations = {{ path = {} }}\n\n[workspace]\n",
+        json_string(&package.display().to_string())
+    );
+    fs::write(dir.join("Cargo.toml"), manifest).map_err(|e| e.to_string())?;
+    // Same dependency versions as the package itself, when it has a lock.
+    let _ = fs::copy(package.join("Cargo.lock"), dir.join("Cargo.lock"));
+    let check = |text: &str| -> Result<(bool, String), String> {
+        fs::write(dir.join("src/main.rs"), text).map_err(|e| e.to_string())?;
+        let out = Command::new("cargo")
+            .args(["check", "--quiet", "--message-format", "short"])
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", package.join("target/claims"))
+            .output()
+            .map_err(|e| format!("cannot run cargo: {}", e))?;
+        Ok((out.status.success(), String::from_utf8_lossy(&out.stderr).into_owned()))
+    };
+    let (built, stderr) = check("fn main() {}\n")?;
+    if !built {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!("{} does not build as a library: {}", package.display(), stderr.lines().next().unwrap_or("")));
+    }
+    let results = CLAIMS
+        .iter()
+        .map(|claim| {
+            let text = format!("#![allow(unused)]\nuse safe_operations::*;\n{}\nfn main() {{}}\n", claim.probe);
+            let result = check(&text).and_then(|(compiled, stderr)| {
+                if compiled {
+                    Err("probe compiled: the guarantee no longer holds".to_string())
+                } else if !stderr.contains(&format!("error[{}]", claim.expect)) {
+                    Err(format!("probe failed without {}: {}", claim.expect, stderr.lines().next().unwrap_or("")))
+                } else {
+                    Ok(())
+                }
+            });
+            (claim.id, result)
+        })
+        .collect();
+    let _ = fs::remove_dir_all(&dir);
+    Ok(results)
+}
+
+/// Check every [`SURFACE`] claim against rustdoc's JSON for the package
+/// at `package`, built with `cargo +nightly rustdoc`. `Err` if it could
+/// not be built: without a nightly toolchain, say.
+pub fn verify_surface(package: &Path) -> Result<Vec<Verdict>, String> {
+    let package = package.canonicalize().map_err(|e| format!("{}: {}", package.display(), e))?;
+    let target = package.join("target/claims-doc");
+    let out = Command::new("cargo")
+        .args(["+nightly", "rustdoc", "--lib", "--quiet", "--target-dir"])
+        .arg(&target)
+        .args(["--", "-Z", "unstable-options", "--output-format", "json"])
+        .current_dir(&package)
+        .output()
+        .map_err(|e| format!("cannot run cargo: {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(format!("no rustdoc JSON (it needs a nightly toolchain): {}", stderr.lines().next().unwrap_or("")));
+    }
+    let json = fs::read_to_string(target.join("doc/safe_operations.json")).map_err(|e| e.to_string())?;
+    let krate: Value = serde_json::from_str(&json).map_err(|e| format!("rustdoc JSON: {}", e))?;
+    Ok(SURFACE.iter().map(|(id, surface)| (*id, check_surface(&krate, surface))).collect())
+}
+
+/// Whether `krate`, as rustdoc renders it, bears `surface` out.
+fn check_surface(krate: &Value, surface: &Surface) -> Result<(), String> {
+    let index = krate["index"].as_object().ok_or("rustdoc JSON has no index")?;
+    let item = |id: &Value| index.get(&id.to_string());
+    let named = |name: &str| index.values().find(|it| it["name"] == name && it["inner"].get("struct").is_some());
+    match surface {
+        Surface::NoMethod { ty, state, method } => {
+            let ty = named(ty).ok_or_else(|| format!("no struct {} in the API", ty))?;
+            for id in ty["inner"]["struct"]["impls"].as_array().into_iter().flatten() {
+                let Some(imp) = item(id).map(|it| &it["inner"]["impl"]) else {
+                    continue;
+                };
+                // The typestate is the first type argument; lifetimes come first.
+                let args = imp["for"]["resolved_path"]["args"]["angle_bracketed"]["args"].as_array();
+                let first = args.into_iter().flatten().find_map(|arg| arg.get("type"));
+                let covers = match first {
+                    Some(arg) if arg.get("generic").is_some() => true,
+                    Some(arg) => name_of(&arg["resolved_path"]) == Some(*state),
+                    None => false,
+                };
+                let mut items = imp["items"].as_array().into_iter().flatten().filter_map(item);
+                let has = items.any(|it| it["name"] == *method);
+                if covers && has {
+                    return Err(format!("{} is in an impl that covers {}", method, state));
+                }
+            }
+            Ok(())
+        }
+        Surface::NoConstructor(ty) => {
+            let ty = named(ty).ok_or_else(|| format!("no struct {} in the API", ty))?;
+            let kind = &ty["inner"]["struct"]["kind"];
+            let public = kind["plain"]["fields"].as_array().is_some_and(|f| !f.is_empty())
+                || kind["tuple"].as_array().is_some_and(|f| f.iter().any(|field| !field.is_null()));
+            if public {
+                return Err("it has public fields".to_string());
+            }
+            for id in ty["inner"]["struct"]["impls"].as_array().into_iter().flatten() {
+                let Some(imp) = item(id).map(|it| &it["inner"]["impl"]) else {
+                    continue;
+                };
+                if name_of(&imp["trait"]) == Some("Default") {
+                    return Err("it implements Default".to_string());
+                }
+                if !imp["trait"].is_null() {
+                    continue;
+                }
+                for function in imp["items"].as_array().into_iter().flatten().filter_map(item) {
+                    let sig = &function["inner"]["function"]["sig"];
+                    let takes_self = sig["inputs"].as_array().into_iter().flatten().any(|input| input[0] == "self");
+                    let output = &sig["output"];
+                    let named = name_of(&output["resolved_path"]);
+                    let returns = output["generic"] == "Self" || (named.is_some() && named == ty["name"].as_str());
+                    if !takes_self && returns {
+                        return Err(format!("{} makes one", function["name"].as_str().unwrap_or("a function")));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The last segment of a rustdoc path: `Protected` for `crate::Protected`.
+fn name_of(path: &Value) -> Option<&str> {
+    path["path"].as_str().and_then(|p| p.rsplit("::").next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_surface, Surface, CLAIMS, SURFACE};
+    use serde_json::{json, Value};
+
+    /// A crate with one struct and one impl, as rustdoc renders them.
+    fn krate(kind: Value, for_arg: Value, trait_: Value, method: &str) -> Value {
+        json!({ "index": {
+            "1": { "name": "Repository", "inner": { "struct": { "kind": kind, "impls": [2] } } },
+            "2": { "name": null, "inner": { "impl": {
+                "for": { "resolved_path": { "path": "Repository", "args": { "angle_bracketed": { "args": [
+                    { "type": for_arg }
+                ] } } } },
+                "trait": trait_,
+                "items": [3],
+            } } },
+            "3": { "name": method, "inner": { "function": { "sig": {
+                "inputs": [],
+                "output": { "generic": "Self" },
+            } } } },
+        } })
+    }
+
+    fn no_force_push() -> Surface {
+        Surface::NoMethod { ty: "Repository", state: "Protected", method: "force_push" }
+    }
+
+    #[test]
+    fn every_api_claim_names_a_claim() {
+        for (id, _) in SURFACE {
+            assert!(CLAIMS.iter().any(|claim| claim.id == *id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn a_method_in_a_generic_impl_covers_every_state() {
+        let private = json!({ "plain": { "fields": [] } });
+        let generic = krate(private.clone(), json!({ "generic": "S" }), Value::Null, "force_push");
+        let broken = check_surface(&generic, &no_force_push()).unwrap_err();
+        assert!(broken.contains("covers Protected"), "{}", broken);
+
+        let unprotected = json!({ "resolved_path": { "path": "crate::Unprotected" } });
+        assert_eq!(check_surface(&krate(private, unprotected, Value::Null, "force_push"), &no_force_push()), Ok(()));
+    }
+
+    #[test]
+    fn a_default_impl_or_a_public_field_is_a_constructor() {
+        let private = json!({ "plain": { "fields": [] } });
+        let default = json!({ "path": "core::default::Default" });
+        let unprotected = json!({ "resolved_path": { "path": "Unprotected" } });
+        let no_constructor = Surface::NoConstructor("Repository");
+
+        let krate_with =
+            |kind: &Value, trait_: &Value, method: &str| krate(kind.clone(), unprotected.clone(), trait_.clone(), method);
+        let broken = check_surface(&krate_with(&private, &default, "default"), &no_constructor).unwrap_err();
+        assert!(broken.contains("Default"), "{}", broken);
+        let broken = check_surface(&krate_with(&private, &Value::Null, "new"), &no_constructor).unwrap_err();
+        assert!(broken.contains("new makes one"), "{}", broken);
+        let public = json!({ "plain": { "fields": [4] } });
+        let broken = check_surface(&krate_with(&public, &Value::Null, "new"), &no_constructor).unwrap_err();
+        assert!(broken.contains("public fields"), "{}", broken);
+    }
+}
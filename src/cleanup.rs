@@ -0,0 +1,229 @@
+//! Branch cleanup — a guided workflow instead of `git branch -D`.
+
+use crate::{
+    backend, glob_match, ConsentDenied, DestructionPlan, OperationDescriptor, OperationKind, Protected, Repository,
+    SafetyError, UserConsent,
+};
+
+/// What counts as an old branch, and which branches are never candidates.
+pub struct CleanupPolicy {
+    pub stale_after_days: u32,
+    /// Glob patterns (`release/*`) for branches that are never deleted.
+    pub protected_patterns: Vec<String>,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        CleanupPolicy {
+            stale_after_days: 90,
+            protected_patterns: ["main", "master", "develop", "release/*", "hotfix/*"]
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Why a branch is on the deletion list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CleanupReason {
+    Merged,
+    /// Unmerged, with no commits for longer than the policy allows. Its
+    /// unique commits survive only in the backup ref.
+    Stale,
+}
+
+/// The proposed cleanup: a deletion list, and what was left off it and why.
+///
+/// The list is fixed when the cleanup is proposed. Consent for it is
+/// bound to a descriptor naming exactly those branches of exactly that
+/// repository, so it deletes what the approver was shown and nothing else.
+pub struct BranchCleanup {
+    /// The repository's path, as descriptors name it.
+    repo: String,
+    candidates: Vec<(String, CleanupReason)>,
+    pub excluded: Vec<(String, String)>,
+}
+
+impl BranchCleanup {
+    /// The branches on the deletion list, and why each is there.
+    pub fn candidates(&self) -> &[(String, CleanupReason)] {
+        &self.candidates
+    }
+
+    /// What consent for this cleanup must describe.
+    pub fn descriptor(&self) -> OperationDescriptor {
+        OperationDescriptor::CleanupBranches {
+            repo: self.repo.clone(),
+            branches: self.candidates.iter().map(|(name, _)| name.clone()).collect(),
+        }
+    }
+
+    /// The deletion list as one plan, for one consent request.
+    pub fn plan(&self) -> DestructionPlan {
+        self.candidates
+            .iter()
+            .fold(DestructionPlan::new(OperationKind::DeleteBranch, &self.repo), |plan, (name, reason)| {
+                plan.item(match reason {
+                    CleanupReason::Merged => format!("{} (merged)", name),
+                    CleanupReason::Stale => format!("{} (stale, UNMERGED — kept only in backup ref)", name),
+                })
+            })
+            .describing(self.descriptor())
+    }
+
+    /// Delete exactly the branches on the list, each after writing a
+    /// backup ref under `refs/backup/cleanup/`.
+    ///
+    /// Takes `&mut Repository`: an agent holding `&Repository` can propose
+    /// a cleanup but cannot carry one out. The checked-out branch and the
+    /// default branch are refused even if the list somehow names them.
+    pub fn execute<B: backend::GitBackend>(
+        self,
+        repo: &mut Repository<Protected, B>,
+        consent: UserConsent,
+    ) -> Result<Vec<String>, SafetyError> {
+        consent.check(OperationKind::DeleteBranch, repo)?;
+        consent.check_descriptor(&self.descriptor())?;
+        if self.repo != repo.path {
+            let reason = format!("this cleanup was proposed for '{}', not '{}'", self.repo, repo.path);
+            return Err(ConsentDenied::new(OperationKind::DeleteBranch, reason).into());
+        }
+        // The backend, not the record, says what is checked out.
+        let head = repo.backend.head(&repo.target()).map_err(|outcome| {
+            let reason = format!("cannot tell which branch is checked out in '{}': {}", repo.name, outcome.label());
+            ConsentDenied::new(OperationKind::DeleteBranch, reason)
+        })?;
+        for (name, _) in &self.candidates {
+            let role = if *name == head {
+                "checked out"
+            } else if *name == repo.default_branch {
+                "the default branch"
+            } else {
+                continue;
+            };
+            let reason = format!("'{}' is {} in '{}'; a cleanup never deletes it", name, role, repo.name);
+            return Err(ConsentDenied::new(OperationKind::DeleteBranch, reason).into());
+        }
+        let mut deleted = Vec::new();
+        for (name, _) in self.candidates {
+            let backup = format!("refs/backup/cleanup/{}", name);
+            if !consent.is_observe_only() {
+                // No backup, no deletion: the branch stays, and so does
+                // everything after it on the list.
+                backend::run_to_end(OperationKind::DeleteBranch, &repo.target(), || {
+                    repo.backend.backup_ref(&repo.target(), &name, &backup)
+                })?;
+                repo.backup_refs.push(backup.clone());
+                backend::run_to_end(OperationKind::DeleteBranch, &repo.target(), || {
+                    repo.backend.delete_branch(&repo.target(), &name)
+                })?;
+                repo.branches.retain(|b| b.name != name);
+            }
+            consent.announce(&format!("'{}': {} -> {}, branch deleted.", repo.name, name, backup));
+            deleted.push(name);
+        }
+        Ok(deleted)
+    }
+}
+
+impl<B: backend::GitBackend> Repository<Protected, B> {
+    /// Propose a branch cleanup. Reads only; nothing is deleted until the
+    /// proposal is executed with consent for its [`BranchCleanup::plan`].
+    ///
+    /// "Clean up old branches" is where agents reach for
+    /// `git branch | xargs git branch -D`. This instead narrows the list to
+    /// merged or stale branches, drops anything matching a protected
+    /// pattern, and says why for every branch it leaves alone.
+    pub fn cleanup_branches(&self, policy: &CleanupPolicy) -> BranchCleanup {
+        let target = self.target();
+        let head = self.backend.head(&target).map_err(|outcome| outcome.label());
+        let mut cleanup = BranchCleanup {
+            repo: self.path.clone(),
+            candidates: Vec::new(),
+            excluded: Vec::new(),
+        };
+        for branch in &self.branches {
+            if let Err(why) = &head {
+                cleanup.excluded.push((branch.name.clone(), format!("cannot tell what is checked out: {}", why)));
+            } else if head.as_deref() == Ok(branch.name.as_str()) {
+                cleanup.excluded.push((branch.name.clone(), "checked out".to_string()));
+            } else if branch.name == self.default_branch {
+                cleanup.excluded.push((branch.name.clone(), "the default branch".to_string()));
+            } else if let Some(p) = policy.protected_patterns.iter().find(|p| glob_match(p, &branch.name)) {
+                cleanup.excluded.push((branch.name.clone(), format!("matches protected pattern `{}`", p)));
+            } else if self.backend.merged(&target, &branch.name).unwrap_or(false) {
+                cleanup.candidates.push((branch.name.clone(), CleanupReason::Merged));
+            } else if branch.days_since_commit >= policy.stale_after_days {
+                cleanup.candidates.push((branch.name.clone(), CleanupReason::Stale));
+            } else {
+                cleanup.excluded.push((branch.name.clone(), "unmerged and recently active".to_string()));
+            }
+        }
+        cleanup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CleanupPolicy;
+    use crate::approver::Rehearsal;
+    use crate::{Repository, SafetyGate};
+
+    fn repo() -> Repository {
+        Repository::protected("app", "/repos/app", 100)
+            .with_branch("main", true, 0)
+            .with_branch("old-fix", true, 200)
+            .with_branch("spike", false, 400)
+            .with_branch("wip", false, 2)
+    }
+
+    fn gate() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        gate
+    }
+
+    #[test]
+    fn a_cleanup_deletes_exactly_the_branches_it_listed() {
+        let mut repo = repo();
+        let cleanup = repo.cleanup_branches(&CleanupPolicy::default());
+        let consent = gate().request_plan_consent(&cleanup.plan()).unwrap();
+        assert_eq!(cleanup.execute(&mut repo, consent).unwrap(), ["old-fix", "spike"]);
+        let left: Vec<&str> = repo.branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(left, ["main", "wip"]);
+        assert_eq!(repo.backup_refs, ["refs/backup/cleanup/old-fix", "refs/backup/cleanup/spike"]);
+    }
+
+    #[test]
+    fn consent_for_one_deletion_list_does_not_cover_another() {
+        let mut repo = repo();
+        let approved = repo.cleanup_branches(&CleanupPolicy::default());
+        let consent = gate().request_plan_consent(&approved.plan()).unwrap();
+        repo = repo.with_branch("feature", true, 1);
+        let wider = repo.cleanup_branches(&CleanupPolicy::default());
+        let denied = wider.execute(&mut repo, consent).expect_err("the list grew").to_string();
+        assert!(denied.contains("consent was granted to"), "{}", denied);
+        assert_eq!(repo.branches.len(), 5);
+    }
+
+    #[test]
+    fn a_cleanup_never_deletes_the_checked_out_or_default_branch() {
+        let mut repo = repo();
+        let cleanup = repo.cleanup_branches(&CleanupPolicy::default());
+        repo.head = "old-fix".to_string();
+        let consent = gate().request_plan_consent(&cleanup.plan()).unwrap();
+        let denied = cleanup.execute(&mut repo, consent).expect_err("old-fix is checked out").to_string();
+        assert!(denied.contains("checked out"), "{}", denied);
+
+        let policy = CleanupPolicy {
+            stale_after_days: 90,
+            protected_patterns: Vec::new(),
+        };
+        let repo = repo.with_default_branch("spike");
+        let cleanup = repo.cleanup_branches(&policy);
+        let names: Vec<&str> = cleanup.candidates().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["main"]);
+        assert!(cleanup.excluded.iter().any(|(name, why)| name == "spike" && why == "the default branch"));
+    }
+}
@@ -0,0 +1,104 @@
+//! The clock consent expiry, deadlines, and receipts are measured by.
+//!
+//! With std it is the system's. A device without std has no clock the
+//! crate can read, so it keeps one here: it calls `set_unix_time` from
+//! its RTC, GNSS fix, or network time, and consent is judged by the last
+//! time it set.
+
+use alloc::format;
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use core::time::Duration;
+
+/// Seconds since the Unix epoch, read from the gate's clock.
+///
+/// The gate's clock is the trusted time source. Every other timeline in an
+/// investigation — shell history, CI logs, webhook exports — is corrected
+/// against it, not the other way round.
+#[cfg(feature = "std")]
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Without std: the time the device last set, zero until it sets one.
+#[cfg(not(feature = "std"))]
+pub fn unix_now() -> u64 {
+    u64::from(NOW.load(core::sync::atomic::Ordering::SeqCst))
+}
+
+/// Seconds since the epoch, to 2106: a 32-bit atomic is what the smaller
+/// cores have.
+#[cfg(not(feature = "std"))]
+static NOW: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+
+/// Set the time, in seconds since the Unix epoch. The clock only moves
+/// forward: setting it back would bring expired consent back to life, so
+/// an earlier time is ignored.
+#[cfg(not(feature = "std"))]
+pub fn set_unix_time(now: u64) {
+    let now = u32::try_from(now).unwrap_or(u32::MAX);
+    NOW.fetch_max(now, core::sync::atomic::Ordering::SeqCst);
+}
+
+/// A moment to measure elapsed time and deadlines from: the monotonic
+/// clock with std, and the device's clock, to the second, without.
+#[cfg(feature = "std")]
+pub(crate) use std::time::Instant;
+
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Instant(u64);
+
+#[cfg(not(feature = "std"))]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant(unix_now())
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.0))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::ops::Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// Rounded up to the second, so a deadline is never early.
+    fn add(self, duration: Duration) -> Instant {
+        let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+        Instant(self.0.saturating_add(seconds))
+    }
+}
+
+/// Format Unix seconds as `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Howard Hinnant's civil calendar algorithm.
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if m <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, m, d)
+}
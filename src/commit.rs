@@ -0,0 +1,296 @@
+//! CommitBuilder — a commit message, checked before it is written.
+
+use crate::{
+    backend, AttributionChange, ConsentDenied, OperationDescriptor, OperationKind, Outcome,
+    Protected, Repository, UserConsent,
+};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// A `Key: value` line in a commit message's final paragraph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    pub fn new(key: &str, value: &str) -> Self {
+        Trailer {
+            key: key.trim().to_string(),
+            value: value.trim().to_string(),
+        }
+    }
+
+    /// Parse one trailer line. Keys are a single token of letters, digits,
+    /// and hyphens.
+    pub fn parse(line: &str) -> Option<Trailer> {
+        let (key, value) = line.split_once(": ")?;
+        let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        (valid && !value.trim().is_empty()).then(|| Trailer::new(key, value))
+    }
+
+    /// Whether this trailer credits someone with the commit.
+    pub fn is_attribution(&self) -> bool {
+        self.key.eq_ignore_ascii_case("Co-Authored-By")
+    }
+
+    pub fn render(&self) -> String {
+        format!("{}: {}", self.key, self.value)
+    }
+
+    /// Git compares trailer keys without regard to case.
+    fn same(&self, other: &Trailer) -> bool {
+        self.key.eq_ignore_ascii_case(&other.key) && self.value == other.value
+    }
+}
+
+/// Why a commit was not written.
+#[derive(Debug)]
+pub enum CommitError {
+    EmptySubject,
+    /// The subject line is longer than [`CommitBuilder::MAX_SUBJECT`].
+    SubjectTooLong(usize),
+    MalformedTrailer(String),
+    /// The amend drops these `Co-Authored-By` trailers, and was built
+    /// without consent to.
+    StripsAttribution(Vec<String>),
+    Denied(ConsentDenied),
+    /// These changed paths are CI/CD pipeline definitions, and the commit
+    /// was made without consent to change them.
+    CiConfig(Vec<String>),
+    /// The backend did not write it, and says why.
+    Failed(Outcome),
+}
+
+/// Split a message into its subject, its body, and its trailers: the last
+/// paragraph, if every line of it is one.
+fn split_message(text: &str) -> (String, String, Vec<Trailer>) {
+    let text = text.trim();
+    let (subject, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let mut paragraphs: Vec<&str> = rest.trim().split("\n\n").filter(|p| !p.trim().is_empty()).collect();
+    let trailers = match paragraphs.last().map(|p| p.lines().map(Trailer::parse).collect::<Option<Vec<_>>>()) {
+        Some(Some(trailers)) => {
+            paragraphs.pop();
+            trailers
+        }
+        _ => Vec::new(),
+    };
+    (subject.trim().to_string(), paragraphs.join("\n\n"), trailers)
+}
+
+/// A commit, built up and checked before it is written.
+///
+/// Trailers are part of the history, not of the message's formatting. An
+/// amend keeps every trailer of the commit it replaces unless one is
+/// removed by name, and removing a `Co-Authored-By` line — deleting
+/// someone's credit for their work — is refused without consent scoped
+/// to exactly that. Asking "can these lines be removed?" is the question
+/// the incident began with.
+#[must_use = "build the commit, or nothing is written"]
+pub struct CommitBuilder<'r, B = backend::Simulated> {
+    pub(crate) repo: &'r Repository<Protected, B>,
+    subject: String,
+    body: String,
+    trailers: Vec<Trailer>,
+    /// The trailers of the commit being amended, if this is an amend.
+    amends: Option<Vec<Trailer>>,
+    removed: Vec<Trailer>,
+}
+
+impl<'r, B: backend::GitBackend> CommitBuilder<'r, B> {
+    /// The longest subject line accepted.
+    pub const MAX_SUBJECT: usize = 72;
+
+    pub(crate) fn new(repo: &'r Repository<Protected, B>) -> Self {
+        CommitBuilder {
+            repo,
+            subject: String::new(),
+            body: String::new(),
+            trailers: Vec::new(),
+            amends: None,
+            removed: Vec::new(),
+        }
+    }
+
+    /// The message: a subject line, then optionally a body and trailers.
+    pub fn message(mut self, text: &str) -> Self {
+        let (subject, body, trailers) = split_message(text);
+        self.subject = subject;
+        self.body = body;
+        for trailer in trailers {
+            self = self.with(trailer);
+        }
+        self
+    }
+
+    /// Add a trailer.
+    pub fn trailer(self, key: &str, value: &str) -> Self {
+        self.with(Trailer::new(key, value))
+    }
+
+    fn with(mut self, trailer: Trailer) -> Self {
+        if !self.trailers.iter().any(|t| t.same(&trailer)) {
+            self.trailers.push(trailer);
+        }
+        self
+    }
+
+    /// Amend the commit whose message is `original`. Its trailers carry
+    /// over to the new message.
+    pub fn amend(mut self, original: &str) -> Self {
+        let (_, _, trailers) = split_message(original);
+        self.amends = Some(trailers);
+        self
+    }
+
+    /// Leave `key: value` out of the amended commit.
+    pub fn remove_trailer(mut self, key: &str, value: &str) -> Self {
+        self.removed.push(Trailer::new(key, value));
+        self
+    }
+
+    /// The trailers the commit will have: its own, then any carried over
+    /// from the amended commit, less those removed.
+    pub fn trailers(&self) -> Vec<Trailer> {
+        let carried = self.amends.iter().flatten().filter(|t| !self.trailers.iter().any(|own| own.same(t)));
+        self.trailers
+            .iter()
+            .chain(carried)
+            .filter(|t| !self.removed.iter().any(|r| r.same(t)))
+            .cloned()
+            .collect()
+    }
+
+    /// The amended commit's `Co-Authored-By` trailers this commit drops.
+    pub fn stripped(&self) -> Vec<Trailer> {
+        let kept = self.trailers();
+        self.amends
+            .iter()
+            .flatten()
+            .filter(|t| t.is_attribution() && !kept.iter().any(|k| k.same(t)))
+            .cloned()
+            .collect()
+    }
+
+    /// The full message, as it would be written.
+    pub fn render(&self) -> String {
+        let mut message = self.subject.clone();
+        if !self.body.is_empty() {
+            message.push_str(&format!("\n\n{}", self.body));
+        }
+        let trailers: Vec<String> = self.trailers().iter().map(Trailer::render).collect();
+        if !trailers.is_empty() {
+            message.push_str(&format!("\n\n{}", trailers.join("\n")));
+        }
+        message
+    }
+
+    fn validate(&self) -> Result<(), CommitError> {
+        if self.subject.is_empty() {
+            return Err(CommitError::EmptySubject);
+        }
+        let length = self.subject.chars().count();
+        if length > Self::MAX_SUBJECT {
+            return Err(CommitError::SubjectTooLong(length));
+        }
+        if let Some(bad) = self.trailers.iter().find(|t| Trailer::parse(&t.render()).is_none()) {
+            return Err(CommitError::MalformedTrailer(bad.render()));
+        }
+        Ok(())
+    }
+
+    fn written(&self) -> Result<String, CommitError> {
+        let (target, message) = (self.repo.target(), self.render());
+        let written = if self.amends.is_some() {
+            self.repo.backend.amend(&target, &message)
+        } else {
+            self.repo.backend.commit(&target, &message, &[])
+        };
+        written.map_err(CommitError::Failed)
+    }
+
+    /// Write the commit. Refused if the message is invalid, or if it
+    /// amends a commit and drops any of its `Co-Authored-By` trailers.
+    pub fn build(self) -> Result<String, CommitError> {
+        self.validate()?;
+        let stripped = self.stripped();
+        if !stripped.is_empty() {
+            return Err(CommitError::StripsAttribution(stripped.iter().map(Trailer::render).collect()));
+        }
+        self.written()
+    }
+
+    /// Write an amend that drops attribution, with consent to drop exactly
+    /// the trailers it drops.
+    pub fn build_with_consent(self, consent: UserConsent<AttributionChange>) -> Result<String, CommitError> {
+        self.validate()?;
+        let expected = self.strip_descriptor();
+        consent
+            .check(OperationKind::StripAttribution, self.repo)
+            .and_then(|()| consent.check_descriptor(&expected))
+            .map_err(CommitError::Denied)?;
+        consent.announce(&format!("Attribution removed from a commit on '{}' with user approval.", self.repo.name));
+        self.written()
+    }
+
+    pub(crate) fn strip_descriptor(&self) -> OperationDescriptor {
+        let stripped: Vec<String> = self.stripped().iter().map(Trailer::render).collect();
+        OperationDescriptor::StripAttribution {
+            repo: self.repo.path.clone(),
+            trailers: stripped.join("; "),
+        }
+    }
+}
+
+/// A change to one file, with its unified diff.
+pub struct FileChange {
+    pub path: String,
+    pub diff: String,
+}
+
+impl FileChange {
+    pub fn new(path: &str, diff: &str) -> Self {
+        FileChange {
+            path: path.to_string(),
+            diff: diff.to_string(),
+        }
+    }
+}
+
+/// Paths that define what CI/CD runners execute.
+pub const CI_CONFIG_PATTERNS: &[&str] = &[
+    ".github/workflows/*",
+    ".github/actions/*",
+    ".gitlab-ci.yml",
+    ".circleci/*",
+    ".buildkite/*",
+    "azure-pipelines.yml",
+    "Jenkinsfile",
+];
+
+/// Whether `path` is a CI/CD pipeline definition.
+pub fn is_ci_config(path: &str) -> bool {
+    CI_CONFIG_PATTERNS.iter().any(|p| glob_match(p, path))
+}
+
+/// Match `name` against a glob where `*` matches any run of characters.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || name.len() < first.len() + last.len() || !name.ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
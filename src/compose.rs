@@ -0,0 +1,509 @@
+//! A typed builder for multi-step workflows.
+//!
+//! ```
+//! # use safe_operations::compose::*;
+//! # use safe_operations::Severity;
+//! # fn main() -> Result<(), Vec<Understated>> {
+//! let batch = Workflow::new()
+//!     .then(Commit("fix attribution".into()))
+//!     .then(gated(RemoveProtection, Severity::High))
+//!     .then(gated(ForcePush, Severity::High))
+//!     .then(RestoreProtection)
+//!     .compile()?;
+//! # let _ = batch;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The workflow carries the same typestate as [`Repository`](super::Repository),
+//! so the ordering rules are checked by the compiler: force-pushing before
+//! protection is removed does not type-check, a destructive step that is not
+//! wrapped in [`gated`] does not type-check, and nothing can follow a step
+//! that consumes the repository. [`Workflow::compile`] then checks what types
+//! cannot: that each gated step's declared severity is not understated.
+//! The result is an [`OperationBatch`] a reviewer can read before anything
+//! runs, and the gate asks for consent once per gated step.
+//!
+//! [`SafetyGate::run_batch`] runs it through a [`StepRunner`]. A batch
+//! that fails part way is not reported as plain failure: it comes back as
+//! [`PartiallyApplied`], recording which steps ran, to be completed,
+//! rolled back, or escalated to recovery.
+
+use super::tool_risk::Reversibility;
+use super::{ConsentDenied, OperationKind, Playbook, PlaybookStep, Protected, SafetyGate, Severity, Unprotected, UserConsent};
+use core::marker::PhantomData;
+use std::collections::VecDeque;
+
+/// The typestate after a step that consumes the repository.
+pub struct Finished;
+
+pub struct Commit(pub String);
+pub struct Push;
+pub struct RestoreProtection;
+pub struct RemoveProtection;
+pub struct ForcePush;
+pub struct FilterRepo(pub String);
+pub struct ResetHard;
+
+/// A destructive operation. Only a [`Gated`] one can be a step.
+pub trait Destructive {
+    const OPERATION: OperationKind;
+    fn describe(&self) -> String {
+        Self::OPERATION.name().to_string()
+    }
+}
+
+impl Destructive for RemoveProtection {
+    const OPERATION: OperationKind = OperationKind::RemoveProtection;
+}
+
+impl Destructive for ForcePush {
+    const OPERATION: OperationKind = OperationKind::ForcePush;
+}
+
+impl Destructive for FilterRepo {
+    const OPERATION: OperationKind = OperationKind::FilterRepo;
+    fn describe(&self) -> String {
+        format!("filter_repo with `{}`", self.0)
+    }
+}
+
+impl Destructive for ResetHard {
+    const OPERATION: OperationKind = OperationKind::ResetHard;
+}
+
+/// A destructive step, with the severity its author believes it has.
+pub struct Gated<O> {
+    op: O,
+    declared: Severity,
+}
+
+pub fn gated<O: Destructive>(op: O, declared: Severity) -> Gated<O> {
+    Gated { op, declared }
+}
+
+/// One step of a compiled workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchStep {
+    pub description: String,
+    /// The operation consent is needed for; `None` for safe steps.
+    pub operation: Option<OperationKind>,
+    pub declared: Option<Severity>,
+}
+
+/// A step allowed in typestate `S`, and the typestate after it.
+pub trait Step<S> {
+    type Next;
+    fn into_step(self) -> BatchStep;
+}
+
+/// Typestates in which the repository still exists.
+pub trait Writable {}
+impl Writable for Protected {}
+impl Writable for Unprotected {}
+
+fn safe(description: String) -> BatchStep {
+    BatchStep {
+        description,
+        operation: None,
+        declared: None,
+    }
+}
+
+impl<S> Step<S> for Commit
+where
+    S: Writable,
+{
+    type Next = S;
+    fn into_step(self) -> BatchStep {
+        safe(format!("commit: {}", self.0))
+    }
+}
+
+impl<S> Step<S> for Push
+where
+    S: Writable,
+{
+    type Next = S;
+    fn into_step(self) -> BatchStep {
+        safe("push".to_string())
+    }
+}
+
+impl Step<Unprotected> for RestoreProtection {
+    type Next = Protected;
+    fn into_step(self) -> BatchStep {
+        safe("restore protection".to_string())
+    }
+}
+
+fn gated_step<O: Destructive>(g: Gated<O>) -> BatchStep {
+    BatchStep {
+        description: g.op.describe(),
+        operation: Some(O::OPERATION),
+        declared: Some(g.declared),
+    }
+}
+
+impl Step<Protected> for Gated<RemoveProtection> {
+    type Next = Unprotected;
+    fn into_step(self) -> BatchStep {
+        gated_step(self)
+    }
+}
+
+impl Step<Unprotected> for Gated<ForcePush> {
+    type Next = Unprotected;
+    fn into_step(self) -> BatchStep {
+        gated_step(self)
+    }
+}
+
+impl Step<Unprotected> for Gated<FilterRepo> {
+    type Next = Finished;
+    fn into_step(self) -> BatchStep {
+        gated_step(self)
+    }
+}
+
+impl Step<Unprotected> for Gated<ResetHard> {
+    type Next = Finished;
+    fn into_step(self) -> BatchStep {
+        gated_step(self)
+    }
+}
+
+/// A workflow under construction, in typestate `S`.
+pub struct Workflow<S = Protected> {
+    steps: Vec<BatchStep>,
+    _state: PhantomData<S>,
+}
+
+impl Workflow<Protected> {
+    pub fn new() -> Self {
+        Workflow {
+            steps: Vec::new(),
+            _state: PhantomData,
+        }
+    }
+}
+
+impl Default for Workflow<Protected> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A declared severity below the operation's real one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Understated {
+    pub step: usize,
+    pub operation: OperationKind,
+    pub declared: Severity,
+    pub actual: Severity,
+}
+
+impl<S> Workflow<S> {
+    pub fn then<T: Step<S>>(mut self, step: T) -> Workflow<T::Next> {
+        self.steps.push(step.into_step());
+        Workflow {
+            steps: self.steps,
+            _state: PhantomData,
+        }
+    }
+
+    /// Check every gated step's declared severity and produce the batch.
+    pub fn compile(self) -> Result<OperationBatch, Vec<Understated>> {
+        let understated: Vec<Understated> = self
+            .steps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, step)| match (step.operation, step.declared) {
+                (Some(operation), Some(declared)) if declared < operation.severity() => Some(Understated {
+                    step: i + 1,
+                    operation,
+                    declared,
+                    actual: operation.severity(),
+                }),
+                _ => None,
+            })
+            .collect();
+        if !understated.is_empty() {
+            return Err(understated);
+        }
+        Ok(OperationBatch { steps: self.steps })
+    }
+}
+
+/// A compiled workflow: ordered steps, each destructive one marked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OperationBatch {
+    pub steps: Vec<BatchStep>,
+}
+
+impl OperationBatch {
+    /// The operations that need consent, in order.
+    pub fn gated(&self) -> Vec<OperationKind> {
+        self.steps.iter().filter_map(|s| s.operation).collect()
+    }
+
+    pub fn render(&self) -> String {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| match step.operation {
+                Some(op) => format!("{}. {} [consent: {}]", i + 1, step.description, op.severity().label()),
+                None => format!("{}. {}", i + 1, step.description),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl SafetyGate {
+    /// One consent per gated step, in order. The first denial stops
+    /// the batch; consents already minted are dropped with it.
+    pub fn request_batch_consent(&mut self, batch: &OperationBatch) -> Result<Vec<UserConsent>, ConsentDenied> {
+        // One forbidden step sinks the batch before any step is asked about.
+        for step in &batch.steps {
+            if let Some(op) = step.operation {
+                self.screen_forbidden(op, None, &step.description)?;
+            }
+        }
+        let total = batch.gated().len();
+        batch
+            .steps
+            .iter()
+            .filter_map(|step| step.operation.map(|op| (op, &step.description)))
+            .enumerate()
+            .map(|(i, (op, description))| {
+                self.mint(op, &format!("{} (step {} of {} gated in batch)", description, i + 1, total))
+            })
+            .collect()
+    }
+}
+
+/// One step of a batch that did not run: where it was, and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepFailure {
+    /// 1-based, as in [`OperationBatch::render`].
+    pub step: usize,
+    pub error: String,
+}
+
+/// What carries out the steps of a batch. Compose decides what runs and
+/// in what order; the runner does the work.
+pub trait StepRunner {
+    /// Carry out `step`. A gated step is handed the consent minted for it.
+    fn run(&mut self, step: &BatchStep, consent: Option<UserConsent>) -> Result<(), String>;
+
+    /// Undo a step that ran. Asked only of steps the batch ran, newest
+    /// first; `Err` when this step cannot be undone after all.
+    fn undo(&mut self, step: &BatchStep) -> Result<(), String>;
+}
+
+/// How far a batch got.
+#[must_use = "a partially applied batch leaves the repository between states"]
+pub enum BatchOutcome {
+    /// Every step ran.
+    Applied(OperationBatch),
+    /// The first step failed, or the batch never started; nothing changed.
+    NotApplied { batch: OperationBatch, failure: StepFailure },
+    /// Some steps ran and one failed. The repository is in neither the
+    /// state the batch started from nor the one it was going to.
+    PartiallyApplied(PartiallyApplied),
+}
+
+/// A batch that stopped part way.
+///
+/// Records exactly which steps ran, which one failed and why, and holds
+/// the consents minted for the gated steps that never ran. It is not a
+/// state to leave lying around: each way out consumes it —
+/// [`complete`](Self::complete) retries the failed step and runs the
+/// rest, [`roll_back`](Self::roll_back) undoes what ran, and
+/// [`escalate`](Self::escalate) hands the repository to recovery.
+#[must_use = "complete it, roll it back, or escalate it"]
+pub struct PartiallyApplied {
+    batch: OperationBatch,
+    /// Steps `0..applied` ran.
+    applied: usize,
+    failure: StepFailure,
+    /// Consents for the gated steps after the failed one, in order.
+    unspent: VecDeque<UserConsent>,
+}
+
+/// A partially applied batch whose steps were all undone.
+#[derive(Debug)]
+pub struct RolledBack {
+    pub batch: OperationBatch,
+    /// The steps undone, newest first.
+    pub undone: Vec<BatchStep>,
+}
+
+impl PartiallyApplied {
+    /// The steps that ran, in order.
+    pub fn applied(&self) -> &[BatchStep] {
+        &self.batch.steps[..self.applied]
+    }
+
+    /// The step that failed.
+    pub fn failed(&self) -> (&BatchStep, &StepFailure) {
+        (&self.batch.steps[self.applied], &self.failure)
+    }
+
+    /// The steps that did not run, the failed one first.
+    pub fn remaining(&self) -> &[BatchStep] {
+        &self.batch.steps[self.applied..]
+    }
+
+    /// Retry the failed step and run the rest of the batch. The failed
+    /// step's consent was spent when it was presented, so a gated one is
+    /// asked for again; a denial leaves the batch where it was.
+    pub fn complete(mut self, gate: &mut SafetyGate, runner: &mut dyn StepRunner) -> BatchOutcome {
+        let step = &self.batch.steps[self.applied];
+        if let Some(op) = step.operation {
+            let description = format!("{} (retry of step {} of {})", step.description, self.applied + 1, self.batch.steps.len());
+            match gate.mint(op, &description) {
+                Ok(consent) => self.unspent.push_front(consent),
+                Err(denied) => {
+                    self.failure.error = denied.to_string();
+                    return BatchOutcome::PartiallyApplied(self);
+                }
+            }
+        }
+        gate.log(format!("BATCH RESUMED: at step {} of {}", self.applied + 1, self.batch.steps.len()));
+        gate.run_from(self.batch, self.applied, self.unspent, runner)
+    }
+
+    /// Undo the steps that ran, newest first. Refused before anything is
+    /// touched if a gated step that ran is not
+    /// [`Reversible`](Reversibility::Reversible); an undo that fails part
+    /// way leaves a smaller partial batch, to escalate.
+    pub fn roll_back(mut self, gate: &mut SafetyGate, runner: &mut dyn StepRunner) -> Result<RolledBack, PartiallyApplied> {
+        if let Some((i, step)) = self.applied().iter().enumerate().rev().find(|(_, step)| {
+            step.operation.is_some_and(|op| !matches!(op.reversibility(), Reversibility::Reversible(_)))
+        }) {
+            gate.log(format!(
+                "BATCH ROLLBACK REFUSED: step {} ({}) cannot be undone",
+                i + 1,
+                step.description
+            ));
+            return Err(self);
+        }
+        let mut undone = Vec::new();
+        while self.applied > 0 {
+            let step = &self.batch.steps[self.applied - 1];
+            if let Err(error) = runner.undo(step) {
+                gate.log(format!(
+                    "BATCH ROLLBACK FAILED: step {} ({}): {}",
+                    self.applied, step.description, error
+                ));
+                // The step that would not undo is still applied; it is
+                // now the one that failed.
+                self.applied -= 1;
+                self.failure = StepFailure {
+                    step: self.applied + 1,
+                    error: format!("undo failed: {}", error),
+                };
+                self.unspent.clear();
+                return Err(self);
+            }
+            gate.log(format!("BATCH STEP UNDONE: {} of {}: {}", self.applied, self.batch.steps.len(), step.description));
+            undone.push(step.clone());
+            self.applied -= 1;
+        }
+        gate.log(format!("BATCH ROLLED BACK: {} step(s) undone", undone.len()));
+        Ok(RolledBack { batch: self.batch, undone })
+    }
+
+    /// Stop here and hand the repository to recovery. Unspent consents
+    /// are dropped; the playbook lists what ran, newest first, and how
+    /// far each can be undone.
+    pub fn escalate(self, gate: &mut SafetyGate) -> Playbook {
+        let total = self.batch.steps.len();
+        gate.log(format!(
+            "BATCH ESCALATED TO RECOVERY: {} of {} step(s) applied; step {} failed: {}",
+            self.applied, total, self.failure.step, self.failure.error
+        ));
+        let mut playbook = Playbook::new(format!(
+            "batch stopped after {} of {} steps: step {} ({}) failed",
+            self.applied,
+            total,
+            self.failure.step,
+            self.batch.steps[self.applied].description
+        ));
+        for (i, step) in self.applied().iter().enumerate().rev() {
+            let Some(op) = step.operation else { continue };
+            playbook = playbook.step(match op.reversibility() {
+                Reversibility::Reversible(how) => PlaybookStep::Run(format!("{} (undoes step {}: {})", how, i + 1, step.description)),
+                Reversibility::Recoverable(how) => {
+                    PlaybookStep::RestoreFrom(format!("step {} ({}): {}", i + 1, step.description, how))
+                }
+                Reversibility::Irreversible => PlaybookStep::ContactApprover(format!(
+                    "the repository owner: step {} ({}) cannot be undone",
+                    i + 1,
+                    step.description
+                )),
+            });
+        }
+        playbook.step(PlaybookStep::ContactApprover("the batch's approver, before any step is retried".to_string()))
+    }
+}
+
+impl SafetyGate {
+    /// Run a batch with the consents from
+    /// [`request_batch_consent`](Self::request_batch_consent). Stops at
+    /// the first step that fails and reports how far it got.
+    pub fn run_batch(
+        &mut self,
+        batch: OperationBatch,
+        consents: Vec<UserConsent>,
+        runner: &mut dyn StepRunner,
+    ) -> BatchOutcome {
+        let gated = batch.gated();
+        let mismatch = consents.len() != gated.len()
+            || consents.iter().zip(&gated).any(|(consent, op)| consent._kind != *op);
+        if mismatch {
+            let error = format!("{} consent(s) for {} gated step(s), or out of order", consents.len(), gated.len());
+            self.log(format!("BATCH NOT STARTED: {}", error));
+            return BatchOutcome::NotApplied {
+                batch,
+                failure: StepFailure { step: 1, error },
+            };
+        }
+        self.run_from(batch, 0, consents.into(), runner)
+    }
+
+    fn run_from(
+        &mut self,
+        batch: OperationBatch,
+        start: usize,
+        mut consents: VecDeque<UserConsent>,
+        runner: &mut dyn StepRunner,
+    ) -> BatchOutcome {
+        let total = batch.steps.len();
+        for i in start..total {
+            let step = &batch.steps[i];
+            let consent = match step.operation {
+                Some(_) => consents.pop_front(),
+                None => None,
+            };
+            if let Err(error) = runner.run(step, consent) {
+                self.log(format!("BATCH STEP FAILED: {} of {}: {}: {}", i + 1, total, step.description, error));
+                let failure = StepFailure { step: i + 1, error };
+                if i == 0 {
+                    return BatchOutcome::NotApplied { batch, failure };
+                }
+                self.log(format!("BATCH PARTIALLY APPLIED: {} of {} step(s) ran", i, total));
+                return BatchOutcome::PartiallyApplied(PartiallyApplied {
+                    batch,
+                    applied: i,
+                    failure,
+                    unspent: consents,
+                });
+            }
+            self.log(format!("BATCH STEP APPLIED: {} of {}: {}", i + 1, total, step.description));
+        }
+        BatchOutcome::Applied(batch)
+    }
+}
@@ -0,0 +1,440 @@
+//! UserConsent — the unforgeable proof of human approval.
+
+use crate::clock::format_utc;
+use crate::{ed25519, unix_now, BlockKind, ConsentDenied, General, OperationDescriptor, OperationKind, Repository};
+#[cfg(feature = "std")]
+use crate::{agent_identity, blocked, nonce_ledger, post_ops, revocation};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// Proof that a human approved a specific destructive operation.
+///
+/// This struct has no public constructor. It cannot be created by calling
+/// `UserConsent { .. }` because the fields are private. It cannot be
+/// created by `Default` because it does not implement `Default`. It can
+/// only be created through [`SafetyGate::request_consent`], which in a
+/// real system would require interactive human input.
+///
+/// An agent cannot forge this. It cannot reason its way into creating one.
+/// It cannot decide that the user "probably" wants to approve. The type
+/// system makes fabrication a compile error.
+///
+/// The `Scope` parameter separates kinds of consent that must never be
+/// interchangeable. Consent to edit a CI workflow is a
+/// `UserConsent<CiConfigChange>`; it cannot be passed where a force-push
+/// wants a `UserConsent`, and vice versa.
+///
+/// Every destructive method takes consent by value. Presenting it spends
+/// it, whether or not it is accepted, so one approval authorizes at most
+/// one operation and a second force-push with the same consent is a
+/// use-after-move error, not a policy question.
+///
+/// Within a scope, consent is bound at runtime to the operation and the
+/// repository it was granted for. Consent to force-push repo A is refused
+/// by `remove_protection` on repo A, and by `force_push` on repo B.
+///
+/// It also expires, by default five minutes after it was granted (the
+/// policy's `consent_ttl`). The approver judged the repository as it was
+/// then; an agent holding consent from an hour ago is holding approval for
+/// a situation that no longer exists. Until then, the approver can revoke
+/// it through a [`ConsentHandle`](revocation::ConsentHandle).
+pub struct UserConsent<Scope = General> {
+    /// What the user approved. Private — cannot be set externally.
+    pub(crate) _operation: String,
+    /// The random value the challenge was derived from. Unique per grant.
+    pub(crate) _nonce: u64,
+    /// When it was granted, in seconds since the epoch.
+    pub(crate) _issued_at: u64,
+    /// The minting gate's Ed25519 signature over the operation, target,
+    /// grant time, and nonce. See [`verify`](UserConsent::verify).
+    pub(crate) _signature: ed25519::Signature,
+    /// The operation the approval was for.
+    pub(crate) _kind: OperationKind,
+    /// The repository it was for: its path, or a plan's target name.
+    /// `None` if the request named no repository.
+    pub(crate) _target: Option<String>,
+    /// The one branch it may push to, if it was granted for one. `None`
+    /// means the default branch of a plain [`force_push`](Repository::force_push).
+    pub(crate) _branch: Option<String>,
+    /// Granted by a gate in observe mode: the ceremony was real, the
+    /// execution it permits is simulated.
+    pub(crate) _observe: bool,
+    /// What the approval covers, as the gate rendered it to the approver.
+    /// `None` if the request was free-form and did not pin it down.
+    pub(crate) _descriptor: Option<OperationDescriptor>,
+    /// When the consent stops being accepted, in seconds since the epoch.
+    pub(crate) _expires_at: u64,
+    /// When the consent starts being accepted; zero for at once. See
+    /// [`time_lock`].
+    pub(crate) _not_before: u64,
+    #[cfg(feature = "std")]
+    /// The minting gate's revocation registry, checked on every use.
+    pub(crate) _revocations: revocation::Registry,
+    #[cfg(feature = "std")]
+    /// The agent session it was granted in, if the request came from one.
+    /// Only that session can use it.
+    pub(crate) _agent: Option<Box<agent_identity::AgentIdentity>>,
+    #[cfg(feature = "std")]
+    /// The minting gate's record of refused uses. See [`blocked`].
+    pub(crate) _trail: blocked::Trail,
+    #[cfg(feature = "std")]
+    /// The minting gate's issued and spent nonces. See [`nonce_ledger`].
+    pub(crate) _ledger: nonce_ledger::Ledger,
+    #[cfg(feature = "std")]
+    /// Who the minting gate tells about completed rewrites. See [`post_ops`].
+    pub(crate) _downstream: post_ops::Subscribers,
+    pub(crate) _scope: PhantomData<Scope>,
+}
+
+impl<Scope> UserConsent<Scope> {
+    /// Whether this consent came from a gate in observe mode. See [`observe`].
+    pub fn is_observe_only(&self) -> bool {
+        self._observe
+    }
+
+    /// When this consent expires, in seconds since the Unix epoch.
+    pub fn expires_at(&self) -> u64 {
+        self._expires_at
+    }
+
+    /// When this consent can first be used, in seconds since the Unix
+    /// epoch. Later than its grant only if it was time-locked.
+    pub fn usable_at(&self) -> u64 {
+        self._not_before.max(self._issued_at)
+    }
+
+    /// The operation this consent was granted for, if the request
+    /// described it.
+    pub fn descriptor(&self) -> Option<&OperationDescriptor> {
+        self._descriptor.as_ref()
+    }
+
+    /// The agent session this consent was granted in, if any, for
+    /// display. Only the [`Host`](agent_identity::Host) enters identities.
+    #[cfg(feature = "std")]
+    pub fn agent(&self) -> Option<String> {
+        self._agent.as_deref().map(agent_identity::AgentIdentity::render)
+    }
+
+    /// Whether this consent was minted by the gate holding the private
+    /// half of `key`, for exactly the operation, target, time, and nonce
+    /// it carries. A system downstream of the gate needs only the gate's
+    /// [`consent_key`](SafetyGate::consent_key) to check it.
+    pub fn verify(&self, key: &ed25519::PublicKey) -> bool {
+        let message = consent_message(
+            self._kind,
+            self._target.as_deref(),
+            self._branch.as_deref(),
+            self._descriptor.as_ref(),
+            self.session().as_deref(),
+            self._issued_at,
+            self._nonce,
+        );
+        key.verify(&message, &self._signature)
+    }
+
+    pub fn signature(&self) -> ed25519::Signature {
+        self._signature
+    }
+
+    /// The agent session it was granted in, as its signature covers it.
+    #[cfg(feature = "std")]
+    fn session(&self) -> Option<String> {
+        self._agent.as_deref().map(agent_identity::AgentIdentity::encode)
+    }
+
+    /// Without std there are no agent sessions to bind consent to.
+    #[cfg(not(feature = "std"))]
+    fn session(&self) -> Option<String> {
+        None
+    }
+
+    /// Refuse this consent once it has been revoked or has expired, when
+    /// it is presented from an agent session other than the one it was
+    /// granted in, or when it was already redeemed. Otherwise redeem it.
+    #[cfg(feature = "std")]
+    pub(crate) fn check_live(&self) -> Result<(), ConsentDenied> {
+        self.freshness().map_err(|(kind, denied)| self.blocked(kind, self._target.as_deref(), denied))?;
+        let caller = agent_identity::current();
+        if self._agent.as_deref() == caller.as_ref() {
+            return self.redeem().map_err(|denied| self.blocked(BlockKind::Replayed, self._target.as_deref(), denied));
+        }
+        let render = |identity: Option<&agent_identity::AgentIdentity>, preposition: &str| {
+            identity.map_or("outside any agent session".to_string(), |i| format!("{} {}", preposition, i.render()))
+        };
+        let denied = ConsentDenied::new(
+            self._kind,
+            format!(
+                "consent was granted {} and presented {}",
+                render(self._agent.as_deref(), "to"),
+                render(caller.as_ref(), "by")
+            ),
+        );
+        Err(self.blocked(BlockKind::WrongSession, self._target.as_deref(), denied))
+    }
+
+    /// Without std, refuse this consent once it has expired. There is no
+    /// registry to revoke it in, no session to bind it to, and no ledger
+    /// to redeem it in: it is spent by being moved into the call that
+    /// checks it, and nothing can copy it.
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn check_live(&self) -> Result<(), ConsentDenied> {
+        self.freshness().map_err(|(kind, denied)| self.blocked(kind, self._target.as_deref(), denied))
+    }
+
+    /// Without std there is no trail to record a refused use in: the
+    /// refusal is handed on as it is.
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn blocked(&self, _kind: BlockKind, _target: Option<&str>, denied: ConsentDenied) -> ConsentDenied {
+        denied
+    }
+
+    /// Refuse this consent once it has been revoked or has expired.
+    #[cfg(feature = "std")]
+    pub(crate) fn check_fresh(&self) -> Result<(), ConsentDenied> {
+        self.freshness().map_err(|(_, denied)| denied)
+    }
+
+    /// [`check_fresh`](Self::check_fresh), saying which way it failed.
+    fn freshness(&self) -> Result<(), (BlockKind, ConsentDenied)> {
+        #[cfg(feature = "std")]
+        self.check_revoked().map_err(|denied| (BlockKind::Revoked, denied))?;
+        let now = unix_now();
+        if now < self._not_before {
+            let denied = ConsentDenied::new(
+                self._kind,
+                format!(
+                    "consent is time-locked until {} ({}s from now); the approver can still cancel it",
+                    format_utc(self._not_before),
+                    self._not_before - now
+                ),
+            );
+            return Err((BlockKind::TimeLocked, denied));
+        }
+        if now < self._expires_at {
+            return Ok(());
+        }
+        let denied = ConsentDenied::new(
+            self._kind,
+            format!("consent expired {}s ago; request it again", now - self._expires_at),
+        );
+        Err((BlockKind::Expired, denied))
+    }
+
+    /// Announce what was done under this consent, marking it simulated if
+    /// it was.
+    pub(crate) fn announce(&self, message: &str) {
+        if self._observe {
+            say!("  [OBSERVE] {} Simulated; nothing real was changed.", message);
+        } else {
+            say!("  [CONSENT] {}", message);
+        }
+    }
+
+    /// Refuse this consent for anything but `kind` on `repo`. Consent that
+    /// names no repository covers none of them.
+    pub(crate) fn check<S, B>(&self, kind: OperationKind, repo: &Repository<S, B>) -> Result<(), ConsentDenied> {
+        self.check_target(kind, &repo.name, &repo.path)
+    }
+
+    /// [`check`](Self::check), for the repository named `name` at `path`.
+    pub(crate) fn check_target(&self, kind: OperationKind, name: &str, path: &str) -> Result<(), ConsentDenied> {
+        let reason = if self._kind != kind {
+            format!("consent was granted for {}, not {}", self._kind.name(), kind.name())
+        } else {
+            match &self._target {
+                Some(target) if target == path || target == name => return self.check_live(),
+                Some(target) => format!("consent was granted for '{}', not '{}'", target, path),
+                None => "consent names no repository; request it with request_consent_for".to_string(),
+            }
+        };
+        Err(self.blocked(BlockKind::ScopeMismatch, Some(path), ConsentDenied::new(kind, reason)))
+    }
+
+    /// Refuse this consent for a push to any branch but the one it was
+    /// granted for. Consent for no particular branch covers `main` only.
+    pub(crate) fn check_branch(&self, branch: &str) -> Result<(), ConsentDenied> {
+        let granted = self._branch.as_deref().unwrap_or("main");
+        if granted == branch {
+            return Ok(());
+        }
+        let denied = ConsentDenied::new(self._kind, format!("consent was granted for branch '{}', not '{}'", granted, branch));
+        Err(self.blocked(BlockKind::ScopeMismatch, self._target.as_deref(), denied))
+    }
+
+    /// Refuse this consent for anything but `expected`. Destructive
+    /// methods run exactly what the approver was shown, so consent that
+    /// did not describe the operation, or described a different one,
+    /// covers nothing.
+    pub(crate) fn check_descriptor(&self, expected: &OperationDescriptor) -> Result<(), ConsentDenied> {
+        let reason = match &self._descriptor {
+            Some(granted) if granted.same_apart_from_repo(expected) => return Ok(()),
+            Some(granted) => format!("consent was granted to {}, not to {}", granted, expected),
+            None => format!(
+                "consent does not describe the operation ({}); request it with request_consent_to",
+                expected
+            ),
+        };
+        let denied = ConsentDenied::new(expected.kind(), reason);
+        Err(self.blocked(BlockKind::ScopeMismatch, Some(expected.repo()), denied))
+    }
+}
+
+/// What a consent's signature covers: the operation, the repository (empty
+/// if none), the branch if it names one, the operation's descriptor if it
+/// has one, the agent session if it was granted in one, the grant time,
+/// and the nonce.
+pub(crate) fn consent_message(
+    kind: OperationKind,
+    target: Option<&str>,
+    branch: Option<&str>,
+    descriptor: Option<&OperationDescriptor>,
+    agent: Option<&str>,
+    issued_at: u64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = format!("consent|{}|{}|{}|{:016x}", kind.name(), target.unwrap_or(""), issued_at, nonce);
+    if let Some(branch) = branch {
+        message.push_str(&format!("|branch={}", branch));
+    }
+    if let Some(descriptor) = descriptor {
+        message.push_str(&format!("|descriptor={}", descriptor.encode()));
+    }
+    if let Some(agent) = agent {
+        message.push_str(&format!("|agent={}", agent));
+    }
+    message.into_bytes()
+}
+
+/// Consent presented for an operation or repository it was not granted
+/// for, or after it expired or was revoked. Nothing was done, and the
+/// repository is handed back.
+pub struct Refused<R> {
+    pub repo: Box<R>,
+    pub denied: ConsentDenied,
+}
+
+impl<S, B> Repository<S, B> {
+    /// Check `consent` covers `expected` on this repository, or hand it back.
+    pub(crate) fn refuse_unless(self, expected: &OperationDescriptor, consent: &UserConsent) -> Result<Self, Refused<Self>> {
+        let checked = consent.check(expected.kind(), &self).and_then(|()| consent.check_descriptor(expected));
+        match checked {
+            Ok(()) => Ok(self),
+            Err(denied) => Err(Refused {
+                repo: Box::new(self),
+                denied,
+            }),
+        }
+    }
+}
+
+// No `impl UserConsent` with a `pub fn new()`. Deliberate.
+// The only factory is SafetyGate::request_consent().
+
+#[cfg(test)]
+mod tests {
+    use crate::{approver, OperationDescriptor, OperationKind, Repository, SafetyGate, Unprotected};
+
+    fn gate() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(approver::Rehearsal));
+        gate
+    }
+
+    fn unprotected(gate: &mut SafetyGate, path: &str) -> Repository<Unprotected> {
+        let repo = Repository::protected("repo", path, 10);
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: path.to_string() })
+            .expect("rehearsal approves");
+        repo.remove_protection(consent).map_err(|refused| refused.denied).expect("consent matches")
+    }
+
+    #[test]
+    fn consent_for_one_repository_is_refused_on_another() {
+        let mut gate = gate();
+        let mine = Repository::protected("mine", "/repos/mine", 10);
+        let other = Repository::protected("other", "/repos/other", 10);
+        let consent = gate
+            .request_consent_to(&mine, OperationDescriptor::RemoveProtection { repo: "/repos/mine".to_string() })
+            .unwrap();
+        let refused = other.remove_protection(consent).err().expect("another repository");
+        assert_eq!(refused.denied.operation, OperationKind::RemoveProtection);
+    }
+
+    #[test]
+    fn consent_granted_in_a_session_is_refused_outside_it() {
+        let host = crate::agent_identity::Host { _private: () };
+        let mut gate = gate();
+        let repo = Repository::protected("repo", "/repos/session", 10);
+        let entered = host.enter(host.identity("builder", "model-1", "s-1"));
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: "/repos/session".to_string() })
+            .unwrap();
+        assert!(consent.agent().is_some_and(|agent| agent.contains("session s-1")));
+        drop(entered);
+        let refused = repo.remove_protection(consent).err().expect("presented outside the session");
+        assert!(refused.denied.reason.contains("outside any agent session"), "{}", refused.denied.reason);
+    }
+
+    #[test]
+    fn consent_for_one_operation_is_refused_for_another() {
+        let mut gate = gate();
+        let repo = unprotected(&mut gate, "/repos/a");
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::ResetHard { repo: "/repos/a".to_string() })
+            .unwrap();
+        assert!(repo.force_push(consent).is_err());
+    }
+
+    #[test]
+    fn consent_for_one_branch_is_refused_on_another() {
+        let mut gate = gate();
+        let repo = unprotected(&mut gate, "/repos/a");
+        let descriptor = OperationDescriptor::ForcePush {
+            repo: "/repos/a".to_string(),
+            branch: "feature".to_string(),
+        };
+        let consent = gate.request_consent_to(&repo, descriptor).unwrap();
+        assert!(repo.force_push_branch("main", consent).is_err());
+        let descriptor = OperationDescriptor::ForcePush {
+            repo: "/repos/a".to_string(),
+            branch: "feature".to_string(),
+        };
+        let consent = gate.request_consent_to(&repo, descriptor).unwrap();
+        assert!(repo.force_push_branch("feature", consent).is_ok());
+    }
+
+    #[test]
+    fn a_push_that_names_no_branch_is_to_the_default_branch() {
+        let mut gate = gate();
+        let repo = Repository::protected("repo", "/repos/a", 10).with_default_branch("trunk");
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: "/repos/a".to_string() })
+            .unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+        let consent = gate.request_consent_for(&repo, &[], OperationKind::ForcePush, "force-push").unwrap();
+        assert_eq!(repo.force_push(consent).unwrap(), "[repo] force-pushed to origin/trunk");
+        let main = OperationDescriptor::ForcePush {
+            repo: "/repos/a".to_string(),
+            branch: "main".to_string(),
+        };
+        let consent = gate.request_consent_to(&repo, main).unwrap();
+        assert!(repo.force_push(consent).is_err(), "granted for main, not trunk");
+        assert_eq!(repo.restore_protection().push().unwrap(), "[repo] pushed to origin/trunk");
+    }
+
+    #[test]
+    fn revoked_consent_is_refused() {
+        let mut gate = gate();
+        let repo = unprotected(&mut gate, "/repos/a");
+        let (consent, handle) = gate
+            .request_revocable_consent_for(&repo, &[], OperationKind::ForcePush, "force-push main")
+            .unwrap();
+        gate.revoke(&handle);
+        let denied = repo.force_push(consent).expect_err("revoked").to_string();
+        assert!(denied.contains("revoked"), "{}", denied);
+    }
+}
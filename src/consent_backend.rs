@@ -0,0 +1,505 @@
+//! Approval for gates with no TTY.
+//!
+//! Inside an agent harness or a CI runner nobody is at stdin, and
+//! [`approver::Console`](super::approver::Console) would wait on nothing. A
+//! [`ConsentBackend`] posts the prompt where a human will see it — a
+//! webhook, a Slack channel — and is polled for their reply. [`Approval`]
+//! is that exchange, and [`Headless`] wraps it as a [`Responder`] so
+//! `request_consent` and every other path through the gate work
+//! unchanged:
+//!
+//! ```text
+//! gate.set_consent_backend(SlackBackend { .. }, Wait::default());
+//! ```
+//!
+//! The human answers the way they would at the console, by sending back
+//! the challenge. The backend carries the challenge to them; it never
+//! passes through the agent. No reply before the deadline is no answer,
+//! and no answer is not a yes.
+//!
+//! Where one channel is not enough, [`DualChannelGate`] asks two — the
+//! terminal and a phone, say — and [`SafetyGate::set_dual_channel`] makes
+//! it the gate's responder.
+
+use super::approver::{self, Responder};
+use super::consent_client::{ClientError, SourceError};
+use super::{json_string, random_u64, SafetyGate};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What the human is shown: the rendered prompt, challenge included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prompt {
+    pub challenge: String,
+    pub text: String,
+}
+
+/// Somewhere a prompt can be posted and a human's reply collected.
+pub trait ConsentBackend {
+    fn name(&self) -> String;
+    /// Post the prompt. Returns an id to poll for the reply with.
+    fn post(&mut self, prompt: &Prompt) -> Result<String, SourceError>;
+    /// The human's reply to the post `id`, or `None` if there is none
+    /// yet. Must not block waiting for one.
+    fn poll(&mut self, id: &str) -> Result<Option<String>, SourceError>;
+}
+
+/// How often to look for a reply, and for how long.
+#[derive(Clone, Copy, Debug)]
+pub struct Wait {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for Wait {
+    fn default() -> Self {
+        Wait {
+            interval: Duration::from_secs(3),
+            timeout: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// One prompt, posted on first poll and resolved by the reply.
+/// Transient failures are retried at the polling interval until the
+/// deadline; a permanent one ends the wait.
+pub struct Approval<'a> {
+    backend: &'a mut dyn ConsentBackend,
+    prompt: Prompt,
+    wait: Wait,
+    started: Instant,
+    posted: Option<String>,
+    polls: u32,
+    last: String,
+}
+
+impl<'a> Approval<'a> {
+    pub fn new(backend: &'a mut dyn ConsentBackend, prompt: Prompt, wait: Wait) -> Self {
+        Approval {
+            backend,
+            prompt,
+            wait,
+            started: Instant::now(),
+            posted: None,
+            polls: 0,
+            last: "no reply yet".to_string(),
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<String>, SourceError> {
+        let id = match &self.posted {
+            Some(id) => id.clone(),
+            None => {
+                let id = self.backend.post(&self.prompt)?;
+                self.posted = Some(id.clone());
+                id
+            }
+        };
+        self.backend.poll(&id)
+    }
+
+    /// Poll at the interval until there is a reply, a permanent
+    /// failure, or the deadline would pass before the next poll.
+    pub fn wait(mut self) -> Result<String, ClientError> {
+        loop {
+            self.polls += 1;
+            match self.step() {
+                Ok(Some(reply)) => return Ok(reply),
+                Ok(None) => self.last = "no reply yet".to_string(),
+                Err(SourceError::Transient(error)) => self.last = error,
+                Err(SourceError::Permanent(error)) => {
+                    return Err(ClientError::Failed {
+                        source: self.backend.name(),
+                        error,
+                    })
+                }
+            }
+            let spent = self.started.elapsed();
+            if spent + self.wait.interval > self.wait.timeout {
+                return Err(ClientError::Exhausted {
+                    source: self.backend.name(),
+                    attempts: self.polls,
+                    spent,
+                    last: self.last.clone(),
+                });
+            }
+            thread::sleep(self.wait.interval);
+        }
+    }
+}
+
+/// A [`Responder`] that asks through a backend instead of stdin.
+pub struct Headless<B> {
+    backend: B,
+    name: String,
+    wait: Wait,
+    /// What the gate has shown since the last challenge.
+    shown: Vec<String>,
+}
+
+impl<B: ConsentBackend> Headless<B> {
+    pub fn new(backend: B, wait: Wait) -> Self {
+        Headless {
+            name: format!("headless via {}", backend.name()),
+            backend,
+            wait,
+            shown: Vec::new(),
+        }
+    }
+}
+
+impl<B: ConsentBackend> Responder for Headless<B> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn shown(&mut self, line: &str) {
+        self.shown.push(line.trim().to_string());
+    }
+
+    fn answer(&mut self, prompt: &approver::Prompt) -> Option<String> {
+        // Carrying the phrase to the approver is this channel's job; the
+        // gate shows it to no responder outside this crate.
+        let mut text = std::mem::take(&mut self.shown);
+        let challenge = match prompt.expected() {
+            Some(phrase) => {
+                text.push(format!("To confirm, type: {}", phrase));
+                phrase.to_string()
+            }
+            None => {
+                text.push(format!("To confirm, enter the {}", prompt.label()));
+                prompt.label().to_string()
+            }
+        };
+        let prompt = Prompt {
+            challenge,
+            text: text.join("\n"),
+        };
+        match Approval::new(&mut self.backend, prompt, self.wait).wait() {
+            Ok(reply) => Some(reply),
+            Err(error) => {
+                eprintln!("  [GATE] {}", error);
+                None
+            }
+        }
+    }
+}
+
+/// Run curl, returning the body and the HTTP status (0 if there was no
+/// response at all).
+pub(crate) fn curl(args: &[&str], timeout: Duration) -> Result<(String, u16), SourceError> {
+    curl_with_headers(args, &[], timeout)
+}
+
+/// As [`curl`], sending `headers` as well. They go to curl on stdin
+/// (`-H @-`), not in its arguments, which any user on the host can read
+/// from the process table: a bearer token belongs here.
+pub(crate) fn curl_with_headers(args: &[&str], headers: &[&str], timeout: Duration) -> Result<(String, u16), SourceError> {
+    let not_run = |e: std::io::Error| SourceError::Permanent(format!("cannot run curl: {}", e));
+    if headers.iter().any(|h| h.contains(['\r', '\n'])) {
+        return Err(SourceError::Permanent("a header value spans lines".to_string()));
+    }
+    let mut command = Command::new("curl");
+    command.args(["-sS", "--max-time"]).arg(timeout.as_secs().max(1).to_string());
+    if !headers.is_empty() {
+        command.args(["-H", "@-"]);
+    }
+    let mut child = command
+        .args(args)
+        .args(["-w", "\n%{http_code}"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(not_run)?;
+    // A write curl did not wait for shows in its own exit and status.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(headers.join("\n").as_bytes());
+    }
+    let out = child.wait_with_output().map_err(not_run)?;
+    let text = String::from_utf8_lossy(&out.stdout).into_owned();
+    let (body, status) = text.rsplit_once('\n').unwrap_or(("", &text));
+    let status = status.trim().parse::<u16>().unwrap_or(0);
+    if matches!(status, 0 | 408 | 429 | 500..=599) {
+        let detail = String::from_utf8_lossy(&out.stderr);
+        return Err(SourceError::Transient(format!("HTTP {} {}", status, detail.trim())));
+    }
+    Ok((body.to_string(), status))
+}
+
+/// An approval service of your own. The prompt is POSTed to
+/// `post_url` as `{"challenge": .., "prompt": ..}` and the response
+/// body is the post's id. `GET <poll_url>/<id>` answers 200 with
+/// `answer <reply>` once the human has replied, or 202 until then.
+pub struct WebhookBackend {
+    pub post_url: String,
+    pub poll_url: String,
+    pub timeout: Duration,
+}
+
+impl ConsentBackend for WebhookBackend {
+    fn name(&self) -> String {
+        format!("webhook {}", self.post_url)
+    }
+
+    fn post(&mut self, prompt: &Prompt) -> Result<String, SourceError> {
+        let body = format!(
+            "{{\"challenge\":{},\"prompt\":{}}}",
+            json_string(&prompt.challenge),
+            json_string(&prompt.text)
+        );
+        let args = ["-X", "POST", "-H", "Content-Type: application/json", "-d", &body, &self.post_url];
+        match curl(&args, self.timeout)? {
+            (id, 200..=299) if !id.trim().is_empty() => Ok(id.trim().to_string()),
+            (_, 200..=299) => Err(SourceError::Permanent("the webhook returned no id".to_string())),
+            (_, code) => Err(SourceError::Permanent(format!("HTTP {}", code))),
+        }
+    }
+
+    fn poll(&mut self, id: &str) -> Result<Option<String>, SourceError> {
+        let url = format!("{}/{}", self.poll_url.trim_end_matches('/'), id);
+        match curl(&[&url], self.timeout)? {
+            (body, 200) => match body.trim().strip_prefix("answer ") {
+                Some(reply) => Ok(Some(reply.to_string())),
+                None => Err(SourceError::Permanent(format!("unrecognized reply `{}`", body.trim()))),
+            },
+            (_, 202 | 204) => Ok(None),
+            (_, code) => Err(SourceError::Permanent(format!("HTTP {}", code))),
+        }
+    }
+}
+
+/// A Slack channel. The prompt is posted with `chat.postMessage`;
+/// the reply is the first in its thread from one of `approvers`
+/// (Slack user ids). Replies from anyone else are ignored: being in
+/// the channel is not being an approver.
+pub struct SlackBackend {
+    pub token: String,
+    pub channel: String,
+    pub approvers: Vec<String>,
+    pub api: String,
+    pub timeout: Duration,
+}
+
+impl SlackBackend {
+    pub fn new(token: &str, channel: &str, approvers: &[&str]) -> Self {
+        SlackBackend {
+            token: token.to_string(),
+            channel: channel.to_string(),
+            approvers: approvers.iter().map(|a| a.to_string()).collect(),
+            api: "https://slack.com/api".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    fn call(&self, args: &[&str]) -> Result<Value, SourceError> {
+        let auth = format!("Authorization: Bearer {}", self.token);
+        let (body, status) = curl_with_headers(args, &[&auth], self.timeout)?;
+        if !(200..=299).contains(&status) {
+            return Err(SourceError::Permanent(format!("HTTP {}", status)));
+        }
+        let fields: Value = serde_json::from_str(&body)
+            .ok()
+            .filter(Value::is_object)
+            .ok_or_else(|| SourceError::Permanent("Slack sent no JSON object".to_string()))?;
+        if fields["ok"] == true {
+            return Ok(fields);
+        }
+        Err(match fields["error"].as_str() {
+            Some("ratelimited") => SourceError::Transient("Slack rate limit".to_string()),
+            Some(error) => SourceError::Permanent(format!("Slack: {}", error)),
+            None => SourceError::Permanent("Slack: request failed".to_string()),
+        })
+    }
+}
+
+impl ConsentBackend for SlackBackend {
+    fn name(&self) -> String {
+        format!("slack #{}", self.channel)
+    }
+
+    fn post(&mut self, prompt: &Prompt) -> Result<String, SourceError> {
+        let text = format!("{}\nReply in this thread with the phrase to approve.", prompt.text);
+        let body = format!("{{\"channel\":{},\"text\":{}}}", json_string(&self.channel), json_string(&text));
+        let url = format!("{}/chat.postMessage", self.api);
+        let fields = self.call(&["-X", "POST", "-H", "Content-Type: application/json; charset=utf-8", "-d", &body, &url])?;
+        fields["ts"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| SourceError::Permanent("Slack returned no message ts".to_string()))
+    }
+
+    fn poll(&mut self, id: &str) -> Result<Option<String>, SourceError> {
+        let url = format!("{}/conversations.replies?channel={}&ts={}", self.api, self.channel, id);
+        let fields = self.call(&[&url])?;
+        let messages = fields["messages"].as_array().map(Vec::as_slice).unwrap_or_default();
+        // The first message is the prompt itself.
+        for message in messages.iter().skip(1) {
+            if message["user"].as_str().is_some_and(|u| self.approvers.iter().any(|a| a == u)) {
+                return Ok(message["text"].as_str().map(str::to_string));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The terminal as a backend: the prompt is already on screen, so
+/// posting only starts listening for the line typed in reply. Lets the
+/// console take part in a [`DualChannelGate`].
+#[derive(Default)]
+pub struct TerminalBackend {
+    replies: Option<mpsc::Receiver<String>>,
+}
+
+impl ConsentBackend for TerminalBackend {
+    fn name(&self) -> String {
+        "terminal".to_string()
+    }
+
+    fn post(&mut self, _prompt: &Prompt) -> Result<String, SourceError> {
+        let (send, receive) = mpsc::channel();
+        // Reading stdin blocks; the reader does it so polling need not.
+        thread::spawn(move || {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).is_ok_and(|n| n > 0) {
+                let _ = send.send(line.trim_end().to_string());
+            }
+        });
+        self.replies = Some(receive);
+        Ok("stdin".to_string())
+    }
+
+    fn poll(&mut self, _id: &str) -> Result<Option<String>, SourceError> {
+        match self.replies.as_ref().map(mpsc::Receiver::try_recv) {
+            Some(Ok(reply)) => Ok(Some(reply)),
+            Some(Err(mpsc::TryRecvError::Empty)) => Ok(None),
+            _ => Err(SourceError::Permanent("stdin closed with no answer".to_string())),
+        }
+    }
+}
+
+/// Consent that has to be confirmed on two channels, each with its own
+/// code.
+///
+/// The first backend is asked the gate's own challenge — usually
+/// through the terminal. The second, out-of-band channel (a phone, a
+/// mailbox, a chat the approver is in) is asked a different code,
+/// drawn fresh for each prompt and never shown on the first. The gate
+/// hears a matching answer only when both channels have replied with
+/// their code, so whoever controls one channel alone — an agent with
+/// the terminal, say — cannot complete the loop. A refusal on either
+/// channel is the answer at once.
+///
+/// It is itself a [`ConsentBackend`], so it composes: wrap it in
+/// [`Headless`], or put another `DualChannelGate` on either side.
+pub struct DualChannelGate<A, B> {
+    first: A,
+    second: B,
+    pending: Option<Pending>,
+}
+
+struct Pending {
+    challenge: String,
+    code: String,
+    first: Option<String>,
+    second: Option<String>,
+    first_reply: Option<String>,
+    second_reply: Option<String>,
+}
+
+impl<A: ConsentBackend, B: ConsentBackend> DualChannelGate<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        DualChannelGate {
+            first,
+            second,
+            pending: None,
+        }
+    }
+}
+
+impl<A: ConsentBackend, B: ConsentBackend> ConsentBackend for DualChannelGate<A, B> {
+    fn name(&self) -> String {
+        format!("{} and {}", self.first.name(), self.second.name())
+    }
+
+    fn post(&mut self, prompt: &Prompt) -> Result<String, SourceError> {
+        // A retried post resumes where the last one failed rather than
+        // asking the first channel twice.
+        let pending = match self.pending.take().filter(|p| p.challenge == prompt.challenge) {
+            Some(pending) => pending,
+            None => Pending {
+                challenge: prompt.challenge.clone(),
+                code: format!("{:06}", random_u64() % 1_000_000),
+                first: None,
+                second: None,
+                first_reply: None,
+                second_reply: None,
+            },
+        };
+        let pending = self.pending.insert(pending);
+        if pending.first.is_none() {
+            pending.first = Some(self.first.post(prompt)?);
+        }
+        if pending.second.is_none() {
+            let second = Prompt {
+                challenge: pending.code.clone(),
+                text: format!(
+                    "{}\nThis is the second confirmation for challenge {}: answer here with {}.",
+                    prompt.text, prompt.challenge, pending.code
+                ),
+            };
+            pending.second = Some(self.second.post(&second)?);
+        }
+        Ok(prompt.challenge.clone())
+    }
+
+    fn poll(&mut self, _id: &str) -> Result<Option<String>, SourceError> {
+        let Some(pending) = self.pending.as_mut() else {
+            return Err(SourceError::Permanent("nothing was posted".to_string()));
+        };
+        if let (None, Some(id)) = (&pending.first_reply, &pending.first) {
+            pending.first_reply = self.first.poll(id)?;
+        }
+        if let (None, Some(id)) = (&pending.second_reply, &pending.second) {
+            pending.second_reply = self.second.poll(id)?;
+        }
+        let refusal = [&pending.first_reply, &pending.second_reply]
+            .into_iter()
+            .flatten()
+            .find(|reply| approver::refusal(reply).is_some())
+            .cloned();
+        if let Some(no) = refusal {
+            self.pending = None;
+            return Ok(Some(no));
+        }
+        let (Some(first), Some(second)) = (&pending.first_reply, &pending.second_reply) else {
+            return Ok(None);
+        };
+        // Anything but both codes is passed on as a reply that cannot
+        // match the challenge.
+        let reply = match (first.trim() == pending.challenge, second.trim() == pending.code) {
+            (true, true) => pending.challenge.clone(),
+            (false, _) => format!("(wrong answer on {})", self.first.name()),
+            (true, false) => format!("(wrong code on {})", self.second.name()),
+        };
+        self.pending = None;
+        Ok(Some(reply))
+    }
+}
+
+impl SafetyGate {
+    /// Ask for consent through `backend` instead of the terminal.
+    pub fn set_consent_backend(&mut self, backend: impl ConsentBackend + 'static, wait: Wait) {
+        self.set_responder(Box::new(Headless::new(backend, wait)));
+    }
+
+    /// Require every challenge to be confirmed on `first` and on
+    /// `second`, each with its own code; see [`DualChannelGate`].
+    pub fn set_dual_channel(&mut self, first: impl ConsentBackend + 'static, second: impl ConsentBackend + 'static, wait: Wait) {
+        self.set_responder(Box::new(Headless::new(DualChannelGate::new(first, second), wait)));
+    }
+}
@@ -0,0 +1,345 @@
+//! Retries for remote approval channels.
+//!
+//! The gate decides locally, but the human's answer often arrives from
+//! somewhere else: a chat bot on a local socket, an on-call tool behind a
+//! webhook. Those flake. A [`ConsentSource`] is one such channel, and
+//! [`Retrying`] wraps any of them with capped exponential backoff, full
+//! jitter, and a total time budget.
+//!
+//! Every attempt carries the same [`IdempotencyKey`], so a request that
+//! reached the approver before its response was lost is answered again,
+//! not asked again. When the retries run out the gate denies, and the
+//! denial says it was the channel that failed, not the approver who said no.
+//!
+//! There is no gRPC source here: it needs an HTTP/2 and protobuf stack this
+//! crate does not carry. One would implement [`ConsentSource`] like the
+//! socket and webhook sources do, sending the key as request metadata.
+
+use super::dedup::IdempotencyKey;
+use super::{json_string, policy, ConsentDenied, OperationKind, SafetyGate, UserConsent};
+use core::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// What is sent to the approver, identically on every attempt.
+pub struct ConsentRequest {
+    pub key: IdempotencyKey,
+    pub operation: OperationKind,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Granted { reason: Option<String> },
+    Denied(String),
+}
+
+/// Why one attempt produced no answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceError {
+    /// Worth another attempt: refused connections, timeouts, 429, 5xx.
+    Transient(String),
+    /// Will fail the same way again: a 4xx, a reply that does not parse.
+    Permanent(String),
+}
+
+/// One approval channel.
+pub trait ConsentSource {
+    fn name(&self) -> String;
+    /// Ask once. `request.key` must reach the far end with every
+    /// attempt, so it can return a decision it already made.
+    fn ask(&mut self, request: &ConsentRequest) -> Result<Answer, SourceError>;
+}
+
+/// How hard to try before giving up.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub max_attempts: u32,
+    /// Ceiling of the first retry's delay; doubled for each retry after.
+    pub base: Duration,
+    /// No single delay is longer than this.
+    pub cap: Duration,
+    /// Time spent asking and waiting, across all attempts.
+    pub budget: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            max_attempts: 5,
+            base: Duration::from_millis(200),
+            cap: Duration::from_secs(5),
+            budget: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before retry `retry` (1 for the first), drawn
+    /// uniformly from zero to its exponential ceiling. Full jitter
+    /// keeps a fleet of agents from retrying in lockstep.
+    pub fn delay(&self, retry: u32, entropy: u64) -> Duration {
+        let ceiling = self.base.saturating_mul(1 << retry.saturating_sub(1).min(16)).min(self.cap);
+        let millis = ceiling.as_millis() as u64;
+        Duration::from_millis(entropy % (millis + 1))
+    }
+}
+
+/// Why the client stopped asking. Either way, no answer is not a yes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientError {
+    /// Every attempt the backoff allowed failed transiently.
+    Exhausted { source: String, attempts: u32, spent: Duration, last: String },
+    /// The channel failed in a way retrying cannot fix.
+    Failed { source: String, error: String },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Exhausted { source, attempts, spent, last } => write!(
+                f,
+                "approval channel '{}' gave no answer after {} attempts in {}ms (last: {}); no answer is not a yes",
+                source,
+                attempts,
+                spent.as_millis(),
+                last
+            ),
+            ClientError::Failed { source, error } => {
+                write!(f, "approval channel '{}' failed: {}; no answer is not a yes", source, error)
+            }
+        }
+    }
+}
+
+/// A [`ConsentSource`] that retries transient failures.
+pub struct Retrying<S> {
+    source: S,
+    backoff: Backoff,
+    sleep: fn(Duration),
+    state: u64,
+}
+
+impl<S: ConsentSource> Retrying<S> {
+    pub fn new(source: S, backoff: Backoff) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        Retrying {
+            source,
+            backoff,
+            sleep: std::thread::sleep,
+            state: u64::from(nanos) | 1,
+        }
+    }
+
+    /// The channel being retried.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Wait with `sleep` instead of the thread sleeping. Time it is
+    /// handed still counts against the budget.
+    pub fn with_sleep(mut self, sleep: fn(Duration)) -> Self {
+        self.sleep = sleep;
+        self
+    }
+
+    pub fn source_name(&self) -> String {
+        self.source.name()
+    }
+
+    fn entropy(&mut self) -> u64 {
+        // xorshift64*, as in `chaos`: jitter needs spread, not secrecy.
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Ask until the source answers, fails permanently, or the backoff
+    /// is spent. Returns the answer and how many attempts it took.
+    pub fn ask(&mut self, request: &ConsentRequest) -> Result<(Answer, u32), ClientError> {
+        let mut spent = Duration::ZERO;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let started = Instant::now();
+            let result = self.source.ask(request);
+            spent += started.elapsed();
+            let last = match result {
+                Ok(answer) => return Ok((answer, attempts)),
+                Err(SourceError::Permanent(error)) => {
+                    return Err(ClientError::Failed { source: self.source.name(), error })
+                }
+                Err(SourceError::Transient(error)) => error,
+            };
+            let entropy = self.entropy();
+            let delay = self.backoff.delay(attempts, entropy);
+            if attempts >= self.backoff.max_attempts || spent + delay > self.backoff.budget {
+                return Err(ClientError::Exhausted {
+                    source: self.source.name(),
+                    attempts,
+                    spent,
+                    last,
+                });
+            }
+            (self.sleep)(delay);
+            spent += delay;
+        }
+    }
+}
+
+/// Both sources answer with one line: `granted`, `granted <reason>`,
+/// or `denied <why>`.
+pub fn parse_answer(line: &str) -> Result<Answer, SourceError> {
+    let line = line.trim();
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match word {
+        "granted" => Ok(Answer::Granted {
+            reason: Some(rest.trim().to_string()).filter(|r| !r.is_empty()),
+        }),
+        "denied" => Ok(Answer::Denied(rest.trim().to_string())),
+        _ => Err(SourceError::Permanent(format!("unrecognized answer `{}`", line))),
+    }
+}
+
+/// The request as the socket protocol sends it, without the newline.
+pub fn socket_line(request: &ConsentRequest) -> String {
+    let description = request.description.replace(['\r', '\n'], " ");
+    format!("ask {} {} {}", request.key.to_hex(), request.operation.name(), description)
+}
+
+/// The request as the webhook protocol posts it.
+pub fn webhook_body(request: &ConsentRequest) -> String {
+    format!(
+        "{{\"key\":{},\"operation\":{},\"description\":{}}}",
+        json_string(&request.key.to_hex()),
+        json_string(request.operation.name()),
+        json_string(&request.description)
+    )
+}
+
+/// An answer as both protocols write it; [`parse_answer`] reads it back.
+pub fn answer_line(answer: &Answer) -> String {
+    match answer {
+        Answer::Granted { reason: Some(reason) } => format!("granted {}", reason),
+        Answer::Granted { reason: None } => "granted".to_string(),
+        Answer::Denied(why) => format!("denied {}", why),
+    }
+}
+
+/// An approval bot listening on a Unix socket. One request per
+/// connection: `ask <key> <operation> <description>`, one line back.
+pub struct SocketSource {
+    pub path: PathBuf,
+    /// How long to wait for the approver before counting it a timeout.
+    pub timeout: Duration,
+}
+
+impl ConsentSource for SocketSource {
+    fn name(&self) -> String {
+        format!("socket {}", self.path.display())
+    }
+
+    fn ask(&mut self, request: &ConsentRequest) -> Result<Answer, SourceError> {
+        let transient = |e: io::Error| SourceError::Transient(e.to_string());
+        let mut stream = UnixStream::connect(&self.path).map_err(transient)?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(transient)?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(transient)?;
+        writeln!(stream, "{}", socket_line(request)).map_err(transient)?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).map_err(transient)?;
+        if line.is_empty() {
+            return Err(SourceError::Transient("connection closed before an answer".to_string()));
+        }
+        parse_answer(&line)
+    }
+}
+
+/// An on-call tool's webhook, through `curl`. The key is sent as an
+/// `Idempotency-Key` header; the response body is one answer line.
+pub struct WebhookSource {
+    pub url: String,
+    pub timeout: Duration,
+}
+
+impl ConsentSource for WebhookSource {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    fn ask(&mut self, request: &ConsentRequest) -> Result<Answer, SourceError> {
+        let body = webhook_body(request);
+        let out = Command::new("curl")
+            .args(["-sS", "-X", "POST", "--max-time"])
+            .arg(self.timeout.as_secs().max(1).to_string())
+            .args(["-H", "Content-Type: application/json", "-H"])
+            .arg(format!("Idempotency-Key: {}", request.key.to_hex()))
+            .args(["-d", &body, "-w", "\n%{http_code}", &self.url])
+            .output()
+            .map_err(|e| SourceError::Permanent(format!("cannot run curl: {}", e)))?;
+        let text = String::from_utf8_lossy(&out.stdout);
+        let (answer, status) = text.rsplit_once('\n').unwrap_or(("", &text));
+        match status.trim().parse::<u16>().unwrap_or(0) {
+            200..=299 => parse_answer(answer),
+            // 000: curl never got a response at all.
+            0 | 408 | 429 | 500..=599 => Err(SourceError::Transient(format!(
+                "HTTP {} {}",
+                status.trim(),
+                String::from_utf8_lossy(&out.stderr).trim()
+            ))),
+            code => Err(SourceError::Permanent(format!("HTTP {}", code))),
+        }
+    }
+}
+
+impl SafetyGate {
+    /// Ask `client` for the approver's answer, then decide as usual.
+    /// A grant's reason is passed on as if given at the console.
+    /// Requests the manifest or policy settle without a human are never
+    /// sent to the channel.
+    pub fn consent_via<S: ConsentSource>(
+        &mut self,
+        client: &mut Retrying<S>,
+        key: IdempotencyKey,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent, ConsentDenied> {
+        self.screen_forbidden(operation, None, operation_description)?;
+        let outside_manifest = self.manifest.as_ref().is_some_and(|(_, permitted)| !permitted.contains(&operation));
+        if outside_manifest || self.policy.decide(operation) != policy::Decision::RequireConsent {
+            return self.mint(operation, operation_description);
+        }
+        self.screen_throttle(operation, operation_description)?;
+        let request = ConsentRequest {
+            key,
+            operation,
+            description: operation_description.to_string(),
+        };
+        let denied = match client.ask(&request) {
+            Ok((Answer::Granted { reason }, attempts)) => {
+                if attempts > 1 {
+                    self.log(format!("CHANNEL: answered on attempt {} for key {}", attempts, key.to_hex()));
+                }
+                if let Some(reason) = reason {
+                    self.give_reason(&reason);
+                }
+                self.answered_by = Some(client.source_name());
+                return self.mint(operation, operation_description);
+            }
+            Ok((Answer::Denied(why), _)) => {
+                let who = client.source_name();
+                self.log(format!("DENIED BY APPROVER {}: {} ({})", who, operation_description, why));
+                ConsentDenied::new(operation, format!("the approver declined: {}", why)).by_approver(&who)
+            }
+            Err(error) => {
+                self.log(format!("DENIED (approval channel): {}: {}", operation_description, error));
+                ConsentDenied::new(operation, error.to_string())
+            }
+        };
+        self.notify_denial(&denied);
+        Err(denied)
+    }
+}
@@ -0,0 +1,227 @@
+//! Repository operations over any gate, not only [`SafetyGate`].
+//!
+//! An organization that already runs an approval system — a change
+//! board, a ticketing workflow, a privileged-access broker — implements
+//! [`ConsentGate`] for it: how consent is requested, how a consent
+//! presented for an operation is checked, and where events are audited.
+//! The repository's `*_via` methods take the gate and its consent:
+//!
+//! ```no_run
+//! # use safe_operations::consent_gate::ConsentGate;
+//! # use safe_operations::{ConsentDenied, OperationDescriptor, Repository};
+//! # fn lift<G: ConsentGate>(board: &mut G, repo: Repository) -> Result<(), ConsentDenied> {
+//! let consent = board.request(OperationDescriptor::RemoveProtection { repo: repo.path.clone() })?;
+//! let repo = repo.remove_protection_via(board, consent).map_err(|refused| refused.denied)?;
+//! # let _ = repo;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! What a gate cannot change is the typestate. `force_push_via` exists on
+//! `Repository<Unprotected>` only, `filter_repo_via` and `reset_hard_via`
+//! consume the repository, and the only way to `Unprotected` is still
+//! `remove_protection`, through some gate's `verify`. A consent is handed
+//! to `verify` by value, so it is spent whether or not it is accepted.
+//!
+//! A gate is trusted code, chosen by the host. The guarantee a custom gate
+//! gives against forged consent is exactly the one its `verify` makes;
+//! [`SafetyGate`]'s is that only it can mint a [`UserConsent`] it accepts.
+
+use super::{
+    backend, ConsentDenied, FilteredRepository, OperationDescriptor, OperationKind, Protected, RecoveryBundle, Refused,
+    Repository, ResetRepository, ResetResult, RewriteResult, SafetyError, SafetyGate, Unprotected, UserConsent,
+};
+
+/// Whether an accepted consent permits the real operation or only a
+/// rehearsal of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Real,
+    /// From a gate in observe mode; see [`observe`](super::observe).
+    Simulated,
+}
+
+/// A source of consent the repository's `*_via` methods accept.
+pub trait ConsentGate {
+    /// The gate's proof of approval.
+    type Consent;
+
+    fn name(&self) -> String;
+
+    /// Ask for consent to exactly `descriptor`, which names the
+    /// repository by path.
+    fn request(&mut self, descriptor: OperationDescriptor) -> Result<Self::Consent, ConsentDenied>;
+
+    /// Refuse `consent` unless it is this gate's, still good, and for
+    /// exactly `expected`. Called before the operation, which runs
+    /// only on `Ok`.
+    fn verify(&mut self, consent: Self::Consent, expected: &OperationDescriptor) -> Result<Effect, ConsentDenied>;
+
+    /// Record that something was done under consent.
+    fn audit(&mut self, event: String);
+}
+
+impl ConsentGate for SafetyGate {
+    type Consent = UserConsent;
+
+    fn name(&self) -> String {
+        format!("safety gate (policy {})", self.policy.version)
+    }
+
+    fn request(&mut self, descriptor: OperationDescriptor) -> Result<UserConsent, ConsentDenied> {
+        let operation = descriptor.kind();
+        let description = descriptor.render();
+        self.screen_forbidden(operation, Some(descriptor.repo()), &description)?;
+        self.target = Some(descriptor.repo().to_string());
+        self.branch = descriptor.branch().map(str::to_string);
+        self.descriptor = Some(descriptor);
+        self.mint(operation, &description)
+    }
+
+    fn verify(&mut self, consent: UserConsent, expected: &OperationDescriptor) -> Result<Effect, ConsentDenied> {
+        let checked = match consent._target.as_deref() {
+            _ if !consent.verify(&self.consent_key()) => {
+                Err(ConsentDenied::new(expected.kind(), "consent was not minted by this gate".to_string()))
+            }
+            Some(target) if target == expected.repo() => consent
+                .check_live()
+                .and_then(|()| consent.check_descriptor(expected))
+                .and_then(|()| match expected.branch() {
+                    Some(branch) => consent.check_branch(branch),
+                    None => Ok(()),
+                }),
+            Some(target) => Err(ConsentDenied::new(
+                expected.kind(),
+                format!("consent was granted for '{}', not '{}'", target, expected.repo()),
+            )),
+            None => Err(ConsentDenied::new(expected.kind(), "consent names no repository".to_string())),
+        };
+        match checked {
+            Ok(()) if consent.is_observe_only() => Ok(Effect::Simulated),
+            Ok(()) => Ok(Effect::Real),
+            Err(denied) => {
+                self.log(format!("CONSENT REFUSED: {} ({})", expected.render(), denied.reason));
+                Err(denied)
+            }
+        }
+    }
+
+    fn audit(&mut self, event: String) {
+        self.log(event);
+    }
+}
+
+/// Check `consent` with `gate`, handing `repo` back if it is refused.
+fn verified<G: ConsentGate, R>(
+    gate: &mut G,
+    consent: G::Consent,
+    expected: &OperationDescriptor,
+    repo: R,
+) -> Result<(Effect, R), Refused<R>> {
+    match gate.verify(consent, expected) {
+        Ok(effect) => Ok((effect, repo)),
+        Err(denied) => Err(Refused {
+            repo: Box::new(repo),
+            denied,
+        }),
+    }
+}
+
+/// Audit and announce what was done, or rehearsed.
+fn done<G: ConsentGate>(gate: &mut G, effect: Effect, what: String) {
+    match effect {
+        Effect::Real => println!("  [CONSENT] {} Approved through {}.", what, gate.name()),
+        Effect::Simulated => println!("  [OBSERVE] {} Simulated; nothing real was changed.", what),
+    }
+    gate.audit(format!("EXECUTED: {}", what));
+}
+
+impl<B: backend::GitBackend> Repository<Protected, B> {
+    /// [`remove_protection`](Repository::remove_protection), with
+    /// consent from any [`ConsentGate`].
+    pub fn remove_protection_via<G: ConsentGate>(
+        self,
+        gate: &mut G,
+        consent: G::Consent,
+    ) -> Result<Repository<Unprotected, B>, Refused<Self>> {
+        let expected = OperationDescriptor::RemoveProtection { repo: self.path.clone() };
+        let (effect, repo) = verified(gate, consent, &expected, self)?;
+        done(gate, effect, format!("Branch protection removed on '{}'.", repo.name));
+        Ok(repo.into_state())
+    }
+}
+
+impl<B: backend::GitBackend> Repository<Unprotected, B> {
+    /// [`force_push`](Repository::force_push), with consent from any
+    /// [`ConsentGate`].
+    pub fn force_push_via<G: ConsentGate>(&self, gate: &mut G, consent: G::Consent) -> Result<String, SafetyError> {
+        let branch = &self.default_branch;
+        let effect = gate.verify(consent, &self.push_to(branch))?;
+        let pushed = match effect {
+            Effect::Real => backend::run_to_end(OperationKind::ForcePush, &self.target(), || {
+                let options = backend::ExecOptions::default();
+                self.backend.force_push(&self.target(), branch, &options, &mut backend::NoProgress)
+            })?,
+            Effect::Simulated => format!("[{}] force-push to origin/{} simulated", self.name, branch),
+        };
+        done(gate, effect, format!("Force-push to '{}'.", self.name));
+        Ok(pushed)
+    }
+
+    /// [`filter_repo`](Repository::filter_repo), with consent from any
+    /// [`ConsentGate`]. Consumes the repository, unless the backend fails
+    /// the rewrite.
+    pub fn filter_repo_via<G: ConsentGate>(
+        self,
+        gate: &mut G,
+        callback: &str,
+        consent: G::Consent,
+    ) -> Result<RewriteResult<Self>, Refused<Self>> {
+        let expected = OperationDescriptor::FilterRepo {
+            repo: self.path.clone(),
+            callback: callback.to_string(),
+        };
+        let (effect, repo) = verified(gate, consent, &expected, self)?;
+        let bundle = RecoveryBundle::snapshot(&repo);
+        let (rewritten, mut receipt) = backend::run(OperationKind::FilterRepo, &repo.target(), || match effect {
+            Effect::Real => {
+                let options = backend::ExecOptions::default();
+                repo.backend.filter_repo(&repo.target(), callback, &options, &mut backend::NoProgress)
+            }
+            Effect::Simulated => Ok(repo.total_commits),
+        });
+        receipt.observed = effect == Effect::Simulated;
+        let Some(rewritten_commits) = rewritten else {
+            return Ok(RewriteResult::Interrupted(repo, receipt));
+        };
+        done(gate, effect, format!("History rewrite on '{}'. Callback: {}.", repo.name, callback));
+        let filtered = FilteredRepository {
+            name: repo.name,
+            path: repo.path,
+            rewritten_commits,
+        };
+        Ok(RewriteResult::Rewritten(filtered, bundle, receipt))
+    }
+
+    /// [`reset_hard`](Repository::reset_hard), with consent from any
+    /// [`ConsentGate`]. Consumes the repository, unless the backend fails
+    /// the reset.
+    pub fn reset_hard_via<G: ConsentGate>(self, gate: &mut G, consent: G::Consent) -> Result<ResetResult<Self>, Refused<Self>> {
+        let expected = OperationDescriptor::ResetHard { repo: self.path.clone() };
+        let (effect, repo) = verified(gate, consent, &expected, self)?;
+        let (reset, mut receipt) = backend::run(OperationKind::ResetHard, &repo.target(), || match effect {
+            Effect::Real => repo.backend.reset_hard(&repo.target()),
+            Effect::Simulated => Ok(()),
+        });
+        receipt.observed = effect == Effect::Simulated;
+        if reset.is_none() {
+            return Ok(ResetResult::Failed(repo, receipt));
+        }
+        done(gate, effect, format!("Hard reset on '{}'.", repo.name));
+        let reset = ResetRepository {
+            name: repo.name,
+            path: repo.path,
+        };
+        Ok(ResetResult::Reset(reset, receipt))
+    }
+}
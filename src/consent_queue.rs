@@ -0,0 +1,139 @@
+//! Consent for a multi-step workflow, asked for up front.
+//!
+//! An agent that stops to ask before each destructive step shows the
+//! approver one step at a time, and the approver judges each without
+//! seeing where the workflow is going: the protection removal looks routine
+//! until the force-push after it. Instead the agent enqueues every request
+//! it will need ([`SafetyGate::enqueue_request`]) and the approver reviews
+//! them together ([`SafetyGate::review_pending`]): the whole list first,
+//! itemized, then each item on its own challenge, approved or refused
+//! individually.
+//!
+//! What comes back is one consent per approved item, each bound to the
+//! item's repository and operation as if it had been requested alone, so
+//! approving the batch does not approve anything in it twice, or anything
+//! that was not in it. A refusal of one item leaves the others to be
+//! answered. Requests the deny-list forbids or the policy denies are
+//! refused when enqueued rather than put in front of the approver.
+
+use super::backend::GitBackend;
+use super::{approver, policy, ConsentDenied, OperationKind, Repository, SafetyGate, UserConsent};
+
+/// A request waiting for review.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Queued {
+    /// Its number in the queue, as the approver sees it.
+    pub id: u64,
+    pub operation: OperationKind,
+    /// The repository's path.
+    pub target: String,
+    pub description: String,
+    commits: Option<usize>,
+}
+
+impl Queued {
+    pub fn render(&self) -> String {
+        format!(
+            "#{} [{}] {} on '{}': {}",
+            self.id,
+            self.operation.severity().label(),
+            self.operation.name(),
+            self.target,
+            self.description
+        )
+    }
+}
+
+/// The gate's side: requests not yet reviewed.
+#[derive(Debug, Default)]
+pub struct ReviewQueue {
+    next_id: u64,
+    pending: Vec<Queued>,
+}
+
+impl ReviewQueue {
+    pub fn pending(&self) -> &[Queued] {
+        &self.pending
+    }
+}
+
+/// One reviewed item.
+pub struct Reviewed {
+    pub id: u64,
+    pub operation: OperationKind,
+    pub description: String,
+    pub consent: Result<UserConsent, ConsentDenied>,
+}
+
+impl SafetyGate {
+    /// Queue a request for `repo`, to be answered at the next
+    /// [`review_pending`](Self::review_pending). Returns its id.
+    pub fn enqueue_request<S, B: GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<u64, ConsentDenied> {
+        self.screen_forbidden(operation, Some(&repo.name), operation_description)?;
+        if self.policy.decide(operation) == policy::Decision::Deny {
+            self.log(format!("DENIED BY POLICY: {}", operation_description));
+            let denied = ConsentDenied::new(
+                operation,
+                format!("policy {} denies {}", self.policy.version, operation.name()),
+            );
+            self.notify_denial_for(&denied, Some(repo.path.clone()));
+            return Err(denied);
+        }
+        self.review.next_id += 1;
+        let queued = Queued {
+            id: self.review.next_id,
+            operation,
+            target: repo.path.clone(),
+            description: operation_description.to_string(),
+            commits: repo.commit_count(),
+        };
+        self.log(format!("QUEUED FOR REVIEW: {} (#{})", queued.description, queued.id));
+        self.review.pending.push(queued);
+        Ok(self.review.next_id)
+    }
+
+    /// The requests waiting for review, in the order they were queued.
+    pub fn review_queue(&self) -> &ReviewQueue {
+        &self.review
+    }
+
+    /// Show the approver every queued request, then ask about each in
+    /// turn. The queue is emptied whatever the answers; a refused item
+    /// must be enqueued again to be asked about again.
+    pub fn review_pending(&mut self) -> Vec<Reviewed> {
+        let pending = std::mem::take(&mut self.review.pending);
+        if pending.is_empty() {
+            return Vec::new();
+        }
+        let total = pending.len();
+        self.log(format!("REVIEW: {} queued requests", total));
+        self.show_line(approver::Line::Heading(format!("{} requests queued for review:", total)));
+        for queued in &pending {
+            self.show_line(approver::Line::Item(queued.render()));
+        }
+        self.show_line(approver::Line::Detail(
+            "Each is asked about on its own; approving one approves nothing else.".to_string(),
+        ));
+        let mut reviewed = Vec::with_capacity(total);
+        for (i, queued) in pending.into_iter().enumerate() {
+            self.target = Some(queued.target.clone());
+            self.target_commits = queued.commits;
+            let description = format!("{} (item {} of {} in review)", queued.description, i + 1, total);
+            let consent = self.mint(queued.operation, &description);
+            reviewed.push(Reviewed {
+                id: queued.id,
+                operation: queued.operation,
+                description: queued.description,
+                consent,
+            });
+        }
+        let approved = reviewed.iter().filter(|r| r.consent.is_ok()).count();
+        self.log(format!("REVIEW DONE: {} of {} approved", approved, total));
+        reviewed
+    }
+}
@@ -0,0 +1,262 @@
+//! Guarded container image and registry operations.
+//!
+//! Deleting a tag looks like tidying up. If a deployment pins that tag, the
+//! next pod restart fails to pull and the service goes down hours later,
+//! far from the cause. Retagging `latest` changes what every unpinned
+//! consumer runs. Reads and additive tagging are free; deleting or moving
+//! a tag needs consent for a plan that lists who pulls it and what
+//! deploys it.
+
+use super::{
+    ConsentDenied, DestructionPlan, OperationKind, Protected, RecoveryCost, SafetyGate, UserConsent,
+};
+use core::marker::PhantomData;
+
+/// Consent scope for registry changes.
+pub struct ImageChange;
+
+/// A tag as the registry reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagInfo {
+    pub tag: String,
+    pub digest: String,
+}
+
+/// The registry API the plan's checks go through.
+pub trait Registry {
+    fn tags(&self, repository: &str) -> Vec<TagInfo>;
+    /// Pulls of `tag` in the last 30 days.
+    fn pull_count(&self, repository: &str, tag: &str) -> u64;
+    /// Deployments that reference `digest`, by name.
+    fn deployment_refs(&self, repository: &str, digest: &str) -> Vec<String>;
+    fn put_tag(&mut self, repository: &str, tag: &str, digest: &str);
+    fn delete_tag(&mut self, repository: &str, tag: &str);
+}
+
+/// A registry held in memory, for demonstrations and fixtures.
+#[derive(Default)]
+pub struct InMemoryRegistry {
+    pub tags: Vec<(String, TagInfo)>,
+    pub pulls: Vec<(String, String, u64)>,
+    pub deployments: Vec<(String, String)>,
+}
+
+impl Registry for InMemoryRegistry {
+    fn tags(&self, repository: &str) -> Vec<TagInfo> {
+        self.tags.iter().filter(|(r, _)| r == repository).map(|(_, t)| t.clone()).collect()
+    }
+
+    fn pull_count(&self, repository: &str, tag: &str) -> u64 {
+        self.pulls
+            .iter()
+            .filter(|(r, t, _)| r == repository && t == tag)
+            .map(|(_, _, n)| n)
+            .sum()
+    }
+
+    fn deployment_refs(&self, _repository: &str, digest: &str) -> Vec<String> {
+        self.deployments.iter().filter(|(d, _)| d == digest).map(|(_, name)| name.clone()).collect()
+    }
+
+    fn put_tag(&mut self, repository: &str, tag: &str, digest: &str) {
+        self.delete_tag(repository, tag);
+        self.tags.push((
+            repository.to_string(),
+            TagInfo {
+                tag: tag.to_string(),
+                digest: digest.to_string(),
+            },
+        ));
+    }
+
+    fn delete_tag(&mut self, repository: &str, tag: &str) {
+        self.tags.retain(|(r, t)| !(r == repository && t.tag == tag));
+    }
+}
+
+/// What a destructive registry change does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageAction {
+    DeleteTag(String),
+    OverwriteTag { tag: String, digest: String },
+    DeleteRepository,
+}
+
+impl ImageAction {
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            ImageAction::DeleteTag(_) => OperationKind::DeleteImageTag,
+            ImageAction::OverwriteTag { .. } => OperationKind::OverwriteImageTag,
+            ImageAction::DeleteRepository => OperationKind::DeleteImageRepository,
+        }
+    }
+}
+
+/// One tag the change affects, with who depends on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagUsage {
+    pub tag: TagInfo,
+    pub pulls_30d: u64,
+    pub deployments: Vec<String>,
+}
+
+/// A registry change, checked against the registry before anyone is asked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImagePlan {
+    pub repository: String,
+    pub action: ImageAction,
+    pub affected: Vec<TagUsage>,
+}
+
+impl ImagePlan {
+    pub fn to_destruction_plan(&self) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(self.action.operation(), &self.repository);
+        for usage in &self.affected {
+            plan = plan.item(format!(
+                "{} ({}): {} pulls in 30 days, {} deployments",
+                usage.tag.tag,
+                usage.tag.digest,
+                usage.pulls_30d,
+                usage.deployments.len()
+            ));
+            if !usage.deployments.is_empty() {
+                let effect = match self.action {
+                    ImageAction::OverwriteTag { .. } => "silently run a different image for",
+                    _ => "fail to pull",
+                };
+                plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                    "restarts of {} {} {}",
+                    usage.deployments.join(", "),
+                    effect,
+                    usage.tag.tag
+                )));
+            }
+        }
+        if let ImageAction::OverwriteTag { tag, digest } = &self.action {
+            plan = plan.item(format!("{} moves to {}", tag, digest));
+        }
+        plan
+    }
+}
+
+/// Consent to apply exactly one image plan.
+pub struct ImageApproval {
+    plan: ImagePlan,
+    _consent: UserConsent<ImageChange>,
+}
+
+/// A repository in a container registry.
+pub struct ImageRepo<State = Protected> {
+    pub name: String,
+    registry: Box<dyn Registry>,
+    _state: PhantomData<State>,
+}
+
+impl ImageRepo<Protected> {
+    pub fn open(name: &str, registry: Box<dyn Registry>) -> Self {
+        ImageRepo {
+            name: name.to_string(),
+            registry,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn pull(&self, tag: &str) -> Result<String, String> {
+        self.inspect(tag).map(|t| format!("pulled {}:{} ({})", self.name, t.tag, t.digest))
+    }
+
+    pub fn inspect(&self, tag: &str) -> Result<TagInfo, String> {
+        self.registry
+            .tags(&self.name)
+            .into_iter()
+            .find(|t| t.tag == tag)
+            .ok_or_else(|| format!("{}:{} not found", self.name, tag))
+    }
+
+    /// Add a tag. Refused if the tag exists: moving a tag is
+    /// [`overwrite_tag`](Self::overwrite_tag), and needs consent.
+    pub fn tag_add(&mut self, tag: &str, digest: &str) -> Result<(), String> {
+        if self.inspect(tag).is_ok() {
+            return Err(format!("{}:{} exists; overwriting needs consent", self.name, tag));
+        }
+        self.registry.put_tag(&self.name, tag, digest);
+        Ok(())
+    }
+
+    /// Check a destructive change against the registry: which tags it
+    /// touches, how often they are pulled, what deploys them.
+    pub fn plan(&self, action: ImageAction) -> Result<ImagePlan, String> {
+        let tags = match &action {
+            ImageAction::DeleteTag(tag) | ImageAction::OverwriteTag { tag, .. } => vec![self.inspect(tag)?],
+            ImageAction::DeleteRepository => self.registry.tags(&self.name),
+        };
+        let affected = tags
+            .into_iter()
+            .map(|tag| TagUsage {
+                pulls_30d: self.registry.pull_count(&self.name, &tag.tag),
+                deployments: self.registry.deployment_refs(&self.name, &tag.digest),
+                tag,
+            })
+            .collect();
+        Ok(ImagePlan {
+            repository: self.name.clone(),
+            action,
+            affected,
+        })
+    }
+
+    fn check(&self, approval: &ImageApproval) -> Result<(), String> {
+        let current = self.plan(approval.plan.action.clone())?;
+        if current != approval.plan {
+            return Err("registry changed since the plan was approved".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn delete_tag(&mut self, approval: ImageApproval) -> Result<String, String> {
+        let ImageAction::DeleteTag(tag) = &approval.plan.action else {
+            return Err("approval is not for a tag deletion".to_string());
+        };
+        self.check(&approval)?;
+        self.registry.delete_tag(&self.name, tag);
+        Ok(format!("deleted {}:{}", self.name, tag))
+    }
+
+    pub fn overwrite_tag(&mut self, approval: ImageApproval) -> Result<String, String> {
+        let ImageAction::OverwriteTag { tag, digest } = &approval.plan.action else {
+            return Err("approval is not for a tag overwrite".to_string());
+        };
+        self.check(&approval)?;
+        self.registry.put_tag(&self.name, tag, digest);
+        Ok(format!("moved {}:{} to {}", self.name, tag, digest))
+    }
+
+    /// Delete every tag. Consumes the handle: there is no repository left.
+    pub fn delete_repository(mut self, approval: ImageApproval) -> Result<String, (Self, String)> {
+        if approval.plan.action != ImageAction::DeleteRepository {
+            return Err((self, "approval is not for a repository deletion".to_string()));
+        }
+        if let Err(why) = self.check(&approval) {
+            return Err((self, why));
+        }
+        for usage in &approval.plan.affected {
+            self.registry.delete_tag(&self.name, &usage.tag.tag);
+        }
+        Ok(format!("deleted {} ({} tags)", self.name, approval.plan.affected.len()))
+    }
+}
+
+impl SafetyGate {
+    /// Request consent for an image plan. A plan that removes a tag a
+    /// deployment still references needs the typed acknowledgment.
+    pub fn request_image_consent(
+        &mut self,
+        plan: &ImagePlan,
+    ) -> Result<ImageApproval, ConsentDenied> {
+        let consent = self.plan_consent(&plan.to_destruction_plan())?;
+        Ok(ImageApproval {
+            plan: plan.clone(),
+            _consent: consent,
+        })
+    }
+}
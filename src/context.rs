@@ -0,0 +1,145 @@
+//! Approval context from repository metadata.
+//!
+//! On February 25 the only description of the operation came from the
+//! agent. These providers put facts about the repository in front of the
+//! approver instead: who has been committing, what is in review, when it
+//! last shipped, and who owns the paths being touched.
+
+use super::{glob_match, report::format_utc, unix_now};
+use std::path::Path;
+use std::process::Command;
+
+/// Facts about a repository at approval time.
+#[derive(Clone, Debug, Default)]
+pub struct ApprovalContext {
+    /// Most recent commits, newest first, one line each.
+    pub recent_commits: Vec<String>,
+    pub open_prs: Option<usize>,
+    /// Last deploy, in seconds since the Unix epoch.
+    pub last_deploy: Option<u64>,
+    /// Owners of the affected paths, from CODEOWNERS.
+    pub codeowners: Vec<String>,
+}
+
+impl ApprovalContext {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.recent_commits.is_empty() {
+            out.push_str("Recent commits: unknown\n");
+        } else {
+            out.push_str("Recent commits:\n");
+            for commit in &self.recent_commits {
+                out.push_str(&format!("  {}\n", commit));
+            }
+        }
+        match self.open_prs {
+            Some(n) => out.push_str(&format!("Open pull requests: {}\n", n)),
+            None => out.push_str("Open pull requests: unknown\n"),
+        }
+        match self.last_deploy {
+            Some(at) => out.push_str(&format!(
+                "Last deploy: {} ({} hours ago)\n",
+                format_utc(at),
+                unix_now().saturating_sub(at) / 3600
+            )),
+            None => out.push_str("Last deploy: unknown\n"),
+        }
+        if self.codeowners.is_empty() {
+            out.push_str("Code owners: none listed\n");
+        } else {
+            out.push_str(&format!("Code owners: {}\n", self.codeowners.join(" ")));
+        }
+        out
+    }
+}
+
+/// A source of approval context.
+pub trait ContextProvider {
+    fn approval_context(&self, repo_name: &str, repo_path: &str, paths: &[&str]) -> ApprovalContext;
+}
+
+/// Context known ahead of time — from a platform API sync, or fixtures.
+#[derive(Clone, Default)]
+pub struct RepoMetadata {
+    pub recent_commits: Vec<String>,
+    pub open_prs: Option<usize>,
+    pub last_deploy: Option<u64>,
+    /// Contents of the CODEOWNERS file.
+    pub codeowners: String,
+}
+
+impl ContextProvider for RepoMetadata {
+    fn approval_context(&self, _repo_name: &str, _repo_path: &str, paths: &[&str]) -> ApprovalContext {
+        ApprovalContext {
+            recent_commits: self.recent_commits.clone(),
+            open_prs: self.open_prs,
+            last_deploy: self.last_deploy,
+            codeowners: owners_for(&self.codeowners, paths),
+        }
+    }
+}
+
+/// Context read from the local checkout: `git log` and the CODEOWNERS
+/// file. Open PRs and deploys live on the platform and are left
+/// unknown rather than guessed.
+pub struct LocalCheckout;
+
+impl ContextProvider for LocalCheckout {
+    fn approval_context(&self, _repo_name: &str, repo_path: &str, paths: &[&str]) -> ApprovalContext {
+        let recent_commits = Command::new("git")
+            .args(["-C", repo_path, "log", "-n", "5", "--format=%h %ar %an: %s"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        let codeowners = ["CODEOWNERS", ".github/CODEOWNERS", "docs/CODEOWNERS"]
+            .iter()
+            .find_map(|f| std::fs::read_to_string(Path::new(repo_path).join(f)).ok())
+            .map(|text| owners_for(&text, paths))
+            .unwrap_or_default();
+        ApprovalContext {
+            recent_commits,
+            codeowners,
+            ..ApprovalContext::default()
+        }
+    }
+}
+
+/// Owners of `paths` under CODEOWNERS rules: for each path the last
+/// matching line wins. Owners are returned once each, in first-seen order.
+pub fn owners_for(codeowners: &str, paths: &[&str]) -> Vec<String> {
+    let rules: Vec<(&str, Vec<&str>)> = codeowners
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .filter_map(|l| {
+            let mut parts = l.split_whitespace();
+            parts.next().map(|pattern| (pattern, parts.collect()))
+        })
+        .collect();
+    let mut owners: Vec<String> = Vec::new();
+    for path in paths {
+        if let Some((_, rule_owners)) = rules.iter().rev().find(|(p, _)| pattern_matches(p, path)) {
+            for owner in rule_owners {
+                if !owners.iter().any(|o| o == owner) {
+                    owners.push(owner.to_string());
+                }
+            }
+        }
+    }
+    owners
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let anchored = pattern.starts_with('/');
+    let pattern = pattern.trim_start_matches('/');
+    if let Some(dir) = pattern.strip_suffix('/') {
+        return path.starts_with(&format!("{}/", dir))
+            || (!anchored && path.contains(&format!("/{}/", dir)));
+    }
+    if !anchored && !pattern.contains('/') {
+        return path.split('/').any(|component| glob_match(pattern, component));
+    }
+    glob_match(pattern, path) || path.starts_with(&format!("{}/", pattern))
+}
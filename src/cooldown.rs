@@ -0,0 +1,73 @@
+//! Raised friction on a repository that was just changed destructively.
+//!
+//! The incident's worst damage came after the first force-push, in the
+//! "recovery": each fix rewrote what the last one had left, on a
+//! repository nobody had looked at since. Once consent is granted for an
+//! operation that cannot simply be undone, the repository it names cools
+//! down for the policy's `destructive_cooldown`, thirty minutes unless
+//! set. Until then every request for it is held to Critical severity,
+//! whatever the operation: the approver is told why, the policy's
+//! Critical requirements apply, and the prompts count against the
+//! Critical rate limit.
+
+use super::tool_risk::Reversibility;
+use super::{report, unix_now, OperationKind, SafetyGate, Severity, UserConsent};
+
+/// A repository cooling down, and what started it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cooldown {
+    pub target: String,
+    pub after: OperationKind,
+    pub until: u64,
+}
+
+impl SafetyGate {
+    /// The repositories cooling down now.
+    pub fn cooldowns(&self) -> Vec<Cooldown> {
+        let now = unix_now();
+        self.cooldowns.iter().filter(|c| c.until > now).cloned().collect()
+    }
+
+    /// The severity a request for `operation` on `target`, a repository
+    /// of `commits` commits, is held to, and the cooldown that raised
+    /// it, if one did.
+    pub(crate) fn severity_for(
+        &self,
+        operation: OperationKind,
+        target: Option<&str>,
+        commits: Option<usize>,
+    ) -> (Severity, Option<Cooldown>) {
+        let now = unix_now();
+        let cooling = target.and_then(|t| self.cooldowns.iter().find(|c| c.target == t && c.until > now));
+        match cooling {
+            Some(cooling) => (Severity::Critical, Some(cooling.clone())),
+            None => (self.tier_of(operation, commits), None),
+        }
+    }
+
+    /// Start, or extend, the cooldown on the repository `consent` is
+    /// for, if its operation cannot simply be undone.
+    pub(crate) fn start_cooldown<Scope>(&mut self, consent: &UserConsent<Scope>) {
+        let period = self.policy.destructive_cooldown();
+        let Some(target) = consent._target.clone() else {
+            return;
+        };
+        if period == 0 || matches!(consent._kind.reversibility(), Reversibility::Reversible(_)) {
+            return;
+        }
+        let now = unix_now();
+        let until = now.saturating_add(period);
+        self.cooldowns.retain(|c| c.until > now && c.target != target);
+        self.log(format!(
+            "COOLDOWN: '{}' held to Critical until {} after {}",
+            target,
+            report::format_utc(until),
+            consent._kind.name()
+        ));
+        self.cooldowns.push(Cooldown {
+            target,
+            after: consent._kind,
+            until,
+        });
+    }
+}
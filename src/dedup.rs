@@ -0,0 +1,137 @@
+//! Duplicate operation detection.
+//!
+//! An agent whose push failed will push again, and again. Without
+//! coalescing, each retry is a fresh consent prompt and — if the human
+//! clicks through the fifth one — a fresh execution. With it, identical
+//! submissions inside the window share one consent decision and one
+//! execution, and the retries are counted on the original receipt.
+
+use super::{unix_now, ConsentDenied, OperationKind, Receipt, SafetyGate, UserConsent};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+/// What makes two submissions "the same": the operation, its target,
+/// and whatever parameters change its effect (refspec, filter, paths).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey(u64);
+
+impl IdempotencyKey {
+    pub fn new(operation: OperationKind, repo: &str, params: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        (operation, repo, params).hash(&mut hasher);
+        IdempotencyKey(hasher.finish())
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        u64::from_str_radix(hex, 16).ok().map(IdempotencyKey)
+    }
+}
+
+/// Proof that this submission was the first of its kind. Handed back
+/// with the receipt so the execution is recorded against it.
+#[must_use = "record the execution, or retries will wait on it until the window closes"]
+pub struct Ticket {
+    key: IdempotencyKey,
+}
+
+/// How the gate admitted a submission.
+pub enum Admission {
+    /// First submission in the window: consent was requested once.
+    Fresh(UserConsent, Ticket),
+    /// A retry of a submission that is still executing. Do nothing.
+    InFlight { retries: u32 },
+    /// A retry of a submission that already ran. This is its receipt.
+    Completed(Receipt),
+}
+
+struct Submission {
+    key: IdempotencyKey,
+    first_seen: u64,
+    retries: u32,
+    denied: Option<ConsentDenied>,
+    receipt: Option<Receipt>,
+}
+
+/// Recent submissions, forgotten once they age out of the window.
+pub struct Coalescer {
+    window: Duration,
+    submissions: Vec<Submission>,
+}
+
+impl Default for Coalescer {
+    fn default() -> Self {
+        Coalescer::new(Duration::from_secs(60))
+    }
+}
+
+impl Coalescer {
+    pub fn new(window: Duration) -> Self {
+        Coalescer { window, submissions: Vec::new() }
+    }
+
+    fn prune(&mut self, now: u64) {
+        let window = self.window.as_secs();
+        self.submissions.retain(|s| now.saturating_sub(s.first_seen) < window);
+    }
+
+    fn find(&mut self, key: IdempotencyKey) -> Option<&mut Submission> {
+        self.submissions.iter_mut().find(|s| s.key == key)
+    }
+}
+
+impl SafetyGate {
+    pub fn set_dedup_window(&mut self, window: Duration) {
+        self.dedup = Coalescer::new(window);
+    }
+
+    /// Submit an operation under an idempotency key. Only the first
+    /// submission in the window reaches the consent prompt; a denial is
+    /// remembered too, so a retry loop cannot wear the human down.
+    pub fn submit(
+        &mut self,
+        key: IdempotencyKey,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<Admission, ConsentDenied> {
+        let now = unix_now();
+        self.dedup.prune(now);
+        if let Some(existing) = self.dedup.find(key) {
+            existing.retries += 1;
+            let retries = existing.retries;
+            let result = if let Some(denied) = &existing.denied {
+                Err(denied.clone())
+            } else if let Some(receipt) = &mut existing.receipt {
+                receipt.retries = retries;
+                Ok(Admission::Completed(receipt.clone()))
+            } else {
+                Ok(Admission::InFlight { retries })
+            };
+            self.log(format!("COALESCED: retry {} of {}", retries, operation_description));
+            return result;
+        }
+        let consent = self.mint(operation, operation_description);
+        self.dedup.submissions.push(Submission {
+            key,
+            first_seen: now,
+            retries: 0,
+            denied: consent.as_ref().err().cloned(),
+            receipt: None,
+        });
+        Ok(Admission::Fresh(consent?, Ticket { key }))
+    }
+
+    /// Attach the execution's receipt to its submission. Retries that
+    /// arrived while it ran are counted on the returned receipt.
+    pub fn record_execution(&mut self, ticket: Ticket, mut receipt: Receipt) -> Receipt {
+        if let Some(submission) = self.dedup.find(ticket.key) {
+            receipt.retries = submission.retries;
+            submission.receipt = Some(receipt.clone());
+        }
+        receipt
+    }
+}
@@ -0,0 +1,355 @@
+//! Consent delegated ahead of time, within bounds the human chose.
+//!
+//! Some work needs the same operation many times: an agent rebasing its
+//! own `wip/` branches force-pushes after every rebase. Asking each time
+//! trains the approver to type challenges without reading them. Instead
+//! the approver grants a [`DelegatedConsent`] once — "force-push, only to
+//! branches matching `wip/*`, in this repository, for the next hour" —
+//! and the agent exchanges it for per-operation consent with
+//! [`SafetyGate::exchange`], no prompt involved.
+//!
+//! A delegation only ever narrows. [`SafetyGate::attenuate`] derives a
+//! child from it — for a sub-agent, say — and refuses any child that
+//! covers an operation, repository, branch, moment, or use its parent does
+//! not. Every exchange is counted against the delegation and each of its
+//! ancestors, and revoking one revokes everything derived from it.
+//!
+//! An exchange is still a decision of the gate: the deny-list, manifest,
+//! policy, and quorum rules in force at the time are applied to it, so a
+//! policy tightened after the delegation was granted wins. Operations a
+//! quorum covers cannot be delegated at all.
+
+use super::backend::GitBackend;
+use super::ed25519::Signature;
+use super::revocation::{ConsentHandle, Registry};
+use super::{policy, random_u64, report, unix_now, ConsentDenied, OperationKind, Repository, SafetyGate, UserConsent};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// The longest a delegation may last.
+pub const MAX_LIFETIME: Duration = Duration::from_secs(24 * 3600);
+
+/// What a delegation covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub operations: Vec<OperationKind>,
+    /// The repository, by name.
+    pub repo: String,
+    /// Branches it may push to: a name, or a prefix ending in `*`.
+    /// `None` places no limit on the branch.
+    pub branches: Option<String>,
+    pub expires_at: u64,
+    pub max_uses: u32,
+}
+
+impl Bounds {
+    /// Nothing, in `repo`, for the next hour, once.
+    pub fn new(repo: &str) -> Self {
+        Bounds {
+            operations: Vec::new(),
+            repo: repo.to_string(),
+            branches: None,
+            expires_at: unix_now() + 3600,
+            max_uses: 1,
+        }
+    }
+
+    pub fn operation(mut self, operation: OperationKind) -> Self {
+        if !self.operations.contains(&operation) {
+            self.operations.push(operation);
+        }
+        self
+    }
+
+    pub fn branches(mut self, pattern: &str) -> Self {
+        self.branches = Some(pattern.to_string());
+        self
+    }
+
+    pub fn lasting(mut self, lifetime: Duration) -> Self {
+        self.expires_at = unix_now().saturating_add(lifetime.as_secs());
+        self
+    }
+
+    pub fn uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = max_uses;
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.operations.is_empty() {
+            return Err("a delegation must name at least one operation".to_string());
+        }
+        if self.max_uses == 0 {
+            return Err("a delegation of zero uses delegates nothing".to_string());
+        }
+        if let Some(pattern) = &self.branches {
+            if pattern.is_empty() || pattern[..pattern.len() - 1].contains('*') {
+                return Err(format!("branch pattern `{}`: `*` may only end it", pattern));
+            }
+        }
+        if self.expires_at > unix_now().saturating_add(MAX_LIFETIME.as_secs()) {
+            return Err(format!("a delegation may last at most {}h", MAX_LIFETIME.as_secs() / 3600));
+        }
+        Ok(())
+    }
+
+    /// Whether `branch` is one these bounds cover.
+    pub fn covers_branch(&self, branch: Option<&str>) -> bool {
+        match (&self.branches, branch) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pattern), Some(branch)) => match pattern.strip_suffix('*') {
+                Some(prefix) => branch.starts_with(prefix),
+                None => branch == pattern,
+            },
+        }
+    }
+
+    /// Why `self` is not within `parent`, if it is not. `remaining` is
+    /// how many uses the parent has left.
+    fn within(&self, parent: &Bounds, remaining: u32) -> Result<(), String> {
+        if let Some(op) = self.operations.iter().find(|op| !parent.operations.contains(op)) {
+            return Err(format!("{} is not delegated", op.name()));
+        }
+        if self.repo != parent.repo {
+            return Err(format!("the delegation covers '{}', not '{}'", parent.repo, self.repo));
+        }
+        let branches_within = match (&parent.branches, &self.branches) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            // A pattern is within another if everything it matches does.
+            (Some(_), Some(child)) => parent.covers_branch(Some(child.strip_suffix('*').unwrap_or(child)))
+                && (!child.ends_with('*') || parent.branches.as_ref().is_some_and(|p| p.ends_with('*'))),
+        };
+        if !branches_within {
+            return Err(format!(
+                "branches `{}` are not within `{}`",
+                self.branches.as_deref().unwrap_or("*"),
+                parent.branches.as_deref().unwrap_or("*")
+            ));
+        }
+        if self.expires_at > parent.expires_at {
+            return Err("it would outlive the delegation it derives from".to_string());
+        }
+        if self.max_uses > remaining {
+            return Err(format!("{} uses requested, {} remain", self.max_uses, remaining));
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "[{}] on '{}', branches {}, until {}, {} use{}",
+            self.operations.iter().map(|k| k.name()).collect::<Vec<_>>().join(", "),
+            self.repo,
+            self.branches.as_deref().unwrap_or("any"),
+            report::format_utc(self.expires_at),
+            self.max_uses,
+            if self.max_uses == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// A delegation, as held by the agent. Like [`UserConsent`], it has
+/// no public constructor, and its signature is the issuing gate's.
+#[derive(Debug)]
+pub struct DelegatedConsent {
+    bounds: Bounds,
+    token: u64,
+    parent: Option<u64>,
+    signature: Signature,
+}
+
+impl DelegatedConsent {
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    fn message(bounds: &Bounds, token: u64, parent: Option<u64>) -> Vec<u8> {
+        format!("delegation|{:016x}|{:016x}|{}", token, parent.unwrap_or(0), bounds.render()).into_bytes()
+    }
+}
+
+/// What the gate remembers of each delegation it issued.
+#[derive(Default)]
+pub struct Ledger {
+    entries: BTreeMap<u64, Entry>,
+}
+
+struct Entry {
+    parent: Option<u64>,
+    max_uses: u32,
+    used: u32,
+}
+
+impl Ledger {
+    /// The token and its ancestors, nearest first.
+    fn chain(&self, token: u64) -> Vec<u64> {
+        let mut chain = vec![token];
+        while let Some(parent) = self.entries.get(chain.last().unwrap_or(&token)).and_then(|e| e.parent) {
+            chain.push(parent);
+        }
+        chain
+    }
+
+    /// Uses left on `token`: the fewest left anywhere up its chain.
+    fn remaining(&self, token: u64) -> u32 {
+        self.chain(token)
+            .iter()
+            .map(|t| self.entries.get(t).map_or(0, |e| e.max_uses - e.used))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl SafetyGate {
+    /// Ask the approver, once, to delegate consent within `bounds`.
+    /// Returns the delegation for the agent and a handle for the
+    /// approver to revoke it with.
+    pub fn delegate<S, B: GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        bounds: Bounds,
+        operation_description: &str,
+    ) -> Result<(DelegatedConsent, ConsentHandle), ConsentDenied> {
+        let first = bounds.operations.first().copied().unwrap_or(OperationKind::ForcePush);
+        let refuse = |gate: &mut SafetyGate, operation: OperationKind, reason: String| {
+            gate.log(format!("DENIED (delegation): {}: {}", operation_description, reason));
+            let denied = ConsentDenied::new(operation, reason);
+            gate.notify_denial_for(&denied, Some(repo.path.clone()));
+            Err(denied)
+        };
+        if let Err(why) = bounds.validate() {
+            return refuse(self, first, why);
+        }
+        if bounds.repo != repo.name {
+            return refuse(self, first, format!("the bounds name '{}', not '{}'", bounds.repo, repo.name));
+        }
+        for &operation in &bounds.operations {
+            self.screen_forbidden(operation, Some(&repo.name), operation_description)?;
+            if self.policy.decide(operation) == policy::Decision::Deny {
+                let reason = format!("policy {} denies {}", self.policy.version, operation.name());
+                return refuse(self, operation, reason);
+            }
+            if self.quorum_rule(operation, repo.commit_count()).is_some() {
+                let reason = format!("{} here needs a quorum, which cannot be delegated", operation.name());
+                return refuse(self, operation, reason);
+            }
+        }
+        // The approver is asked once, about the most severe of them.
+        let gravest = bounds.operations.iter().copied().max_by_key(|k| k.severity()).unwrap_or(first);
+        let description = format!("delegate {}: {}", bounds.render(), operation_description);
+        self.target = Some(repo.path.clone());
+        self.target_commits = repo.commit_count();
+        self.mint::<super::General>(gravest, &description)?;
+        let delegation = self.issue_delegation(bounds, None);
+        let handle = ConsentHandle::new(delegation.token, gravest, &description, self.revocations.clone());
+        Ok((delegation, handle))
+    }
+
+    fn issue_delegation(&mut self, bounds: Bounds, parent: Option<u64>) -> DelegatedConsent {
+        let token = random_u64();
+        let signature = self.signing_key.sign(&DelegatedConsent::message(&bounds, token, parent));
+        self.log(format!("DELEGATED {:016x}: {}", token, bounds.render()));
+        self.delegations.entries.insert(
+            token,
+            Entry {
+                parent,
+                max_uses: bounds.max_uses,
+                used: 0,
+            },
+        );
+        DelegatedConsent {
+            bounds,
+            token,
+            parent,
+            signature,
+        }
+    }
+
+    /// Why `delegation` cannot be used now, if it cannot.
+    fn check_delegation(&self, delegation: &DelegatedConsent) -> Result<(), String> {
+        let message = DelegatedConsent::message(&delegation.bounds, delegation.token, delegation.parent);
+        let issued_here = self.delegations.entries.get(&delegation.token).is_some_and(|e| e.parent == delegation.parent);
+        if !issued_here || !self.signing_key.public_key().verify(&message, &delegation.signature) {
+            return Err("the delegation was not issued by this gate".to_string());
+        }
+        let revocations: &Registry = &self.revocations;
+        if self.delegations.chain(delegation.token).iter().any(|t| revocations.is_revoked(*t)) {
+            return Err("the approver revoked this delegation".to_string());
+        }
+        if unix_now() >= delegation.bounds.expires_at {
+            return Err(format!("the delegation expired at {}", report::format_utc(delegation.bounds.expires_at)));
+        }
+        if self.delegations.remaining(delegation.token) == 0 {
+            return Err("the delegation has no uses left".to_string());
+        }
+        Ok(())
+    }
+
+    /// Derive a narrower delegation from `parent`, without asking.
+    /// Refused unless `bounds` lies entirely within the parent's.
+    pub fn attenuate(&mut self, parent: &DelegatedConsent, bounds: Bounds) -> Result<DelegatedConsent, ConsentDenied> {
+        let operation = bounds.operations.first().copied().unwrap_or(OperationKind::ForcePush);
+        let remaining = self.delegations.remaining(parent.token);
+        let checked = bounds
+            .validate()
+            .and_then(|()| self.check_delegation(parent))
+            .and_then(|()| bounds.within(&parent.bounds, remaining));
+        if let Err(reason) = checked {
+            self.log(format!("DENIED (attenuation of {:016x}): {}", parent.token, reason));
+            return Err(ConsentDenied::new(operation, reason));
+        }
+        Ok(self.issue_delegation(bounds, Some(parent.token)))
+    }
+
+    /// Exchange `delegation` for consent to one `operation` on `repo`,
+    /// pushing to `branch` if it is a push. No one is prompted.
+    pub fn exchange<S, B: GitBackend>(
+        &mut self,
+        delegation: &DelegatedConsent,
+        repo: &Repository<S, B>,
+        branch: Option<&str>,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent, ConsentDenied> {
+        let bounds = &delegation.bounds;
+        let checked = self.check_delegation(delegation).and_then(|()| {
+            if !bounds.operations.contains(&operation) {
+                return Err(format!("{} is not delegated", operation.name()));
+            }
+            if bounds.repo != repo.name {
+                return Err(format!("the delegation covers '{}', not '{}'", bounds.repo, repo.name));
+            }
+            if !bounds.covers_branch(branch) {
+                return Err(format!(
+                    "branch '{}' is outside `{}`",
+                    branch.unwrap_or("main"),
+                    bounds.branches.as_deref().unwrap_or("")
+                ));
+            }
+            Ok(())
+        });
+        if let Err(reason) = checked {
+            self.log(format!("DENIED (delegation {:016x}): {}: {}", delegation.token, operation_description, reason));
+            let denied = ConsentDenied::new(operation, reason);
+            self.notify_denial(&denied);
+            return Err(denied);
+        }
+        self.screen_forbidden(operation, Some(&repo.name), operation_description)?;
+        self.answered_by = Some(format!("delegation {:016x}", delegation.token));
+        self.target = Some(repo.path.clone());
+        self.target_commits = repo.commit_count();
+        self.branch = branch.map(str::to_string);
+        let mut consent: UserConsent = self.mint(operation, operation_description)?;
+        for token in self.delegations.chain(delegation.token) {
+            if let Some(entry) = self.delegations.entries.get_mut(&token) {
+                entry.used += 1;
+            }
+        }
+        // Consent from a delegation does not outlive it.
+        consent._expires_at = consent._expires_at.min(bounds.expires_at);
+        Ok(consent)
+    }
+}
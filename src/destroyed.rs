@@ -0,0 +1,160 @@
+//! Post-destruction types — the repo is gone, and the type system knows it.
+
+use crate::{unix_now, BranchInfo, Protected, Receipt, Repository, Unprotected};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// What remains after filter-repo rewrites history.
+///
+/// This is a different type. It is not a `Repository`. You cannot push it,
+/// commit to it, or operate on it as if it were the original. The old repo
+/// was consumed. This is what replaced it.
+pub struct FilteredRepository {
+    pub name: String,
+    pub path: String,
+    pub rewritten_commits: usize,
+}
+
+impl FilteredRepository {
+    /// The only thing you can do with a rewritten repo is acknowledge what happened.
+    pub fn summary(&self) -> String {
+        format!(
+            "[{}] history rewritten: {} commits now have new SHAs. \
+             Original history is unreachable from this repository.",
+            self.name, self.rewritten_commits
+        )
+    }
+}
+
+/// The way back from a history rewrite: a `git bundle` of every ref, and
+/// the repository's shape, taken before the rewrite began.
+///
+/// Destruction mints the artifact that undoes it. A rewrite that hands
+/// back only the rewritten repository leaves recovery to whoever happens
+/// to have an old clone; this is that clone, made on purpose.
+#[must_use = "this bundle is the only record of the history before the rewrite"]
+pub struct RecoveryBundle {
+    pub name: String,
+    pub path: String,
+    /// The bundle file.
+    pub bundle: String,
+    /// The refs the bundle holds, as they were.
+    pub refs: Vec<String>,
+    total_commits: usize,
+    branches: Vec<BranchInfo>,
+    head: String,
+}
+
+impl RecoveryBundle {
+    pub(crate) fn snapshot<S, B>(repo: &Repository<S, B>) -> Self {
+        let mut refs: Vec<String> = repo.branches.iter().map(|b| format!("refs/heads/{}", b.name)).collect();
+        if !repo.branches.iter().any(|b| b.name == repo.head) {
+            refs.insert(0, format!("refs/heads/{}", repo.head));
+        }
+        RecoveryBundle {
+            name: repo.name.clone(),
+            path: repo.path.clone(),
+            bundle: format!("{}/.git/recovery/pre-rewrite-{}.bundle", repo.path, unix_now()),
+            refs,
+            total_commits: repo.total_commits,
+            branches: repo.branches.clone(),
+            head: repo.head.clone(),
+        }
+    }
+
+    /// The commands that write the bundle and, later, restore from it.
+    pub fn commands(&self) -> Vec<String> {
+        vec![
+            format!("git bundle create {} --all", self.bundle),
+            format!("git bundle verify {}", self.bundle),
+            format!("git fetch --force --update-head-ok {} 'refs/*:refs/*'", self.bundle),
+        ]
+    }
+
+    /// Put the original history back. Every ref returns to where it was
+    /// before the rewrite, and the repository comes back protected.
+    pub fn restore(self) -> Repository<Protected> {
+        say!("  [OK] '{}' restored from {}: {} ref(s) back where they were.", self.name, self.bundle, self.refs.len());
+        let mut repo = Repository::protected(&self.name, &self.path, self.total_commits).with_head(&self.head);
+        repo.branches = self.branches;
+        repo.backup_refs.push(self.bundle);
+        repo
+    }
+}
+
+/// How a rewrite ended.
+// `R` is a repository, which outweighs the rewritten variant.
+#[allow(clippy::large_enum_variant)]
+pub enum RewriteResult<R = Repository<Unprotected>> {
+    /// Every commit was rewritten and refs were moved.
+    Rewritten(FilteredRepository, RecoveryBundle, Receipt),
+    /// Stopped, or failed, before refs were moved. The original
+    /// repository is intact and handed back; the receipt says why.
+    Interrupted(R, Receipt),
+}
+
+/// How a hard reset ended.
+pub enum ResetResult<R = Repository<Unprotected>> {
+    Reset(ResetRepository, Receipt),
+    /// The backend did not reset. The repository is handed back, and the
+    /// receipt says why.
+    Failed(R, Receipt),
+}
+
+/// What remains after a repository is transferred: a record, not a handle.
+///
+/// The repository now belongs to someone else. Nothing here can push to
+/// it, protect it, or undo the transfer.
+pub struct TransferredRepository {
+    pub name: String,
+    pub new_owner: String,
+    /// Everything the ceremony listed as affected downstream.
+    pub impact: Vec<String>,
+}
+
+impl TransferredRepository {
+    pub fn summary(&self) -> String {
+        format!(
+            "[{}] transferred to '{}'. {} downstream items affected.",
+            self.name,
+            self.new_owner,
+            self.impact.len()
+        )
+    }
+}
+
+impl<S, B> Repository<S, B> {
+    /// What handing this repository to `new_owner` breaks downstream, as
+    /// the repository knows it. The ceremony lists it, and the transfer
+    /// record keeps it.
+    pub fn transfer_impact(&self, new_owner: &str) -> Vec<String> {
+        let mut impact = vec![
+            format!("'{}' moves to '{}'; its old URL only redirects", self.name, new_owner),
+            format!("every clone's remote, {} commits of history, points at the redirect", self.total_commits),
+            "collaborator and team permissions reset; the new owner decides who keeps access".to_string(),
+            "webhooks, deploy keys, and CI integrations of the old owner stop working".to_string(),
+        ];
+        if !self.branches.is_empty() {
+            impact.push(format!("{} branches and their protection rules move with it", self.branches.len()));
+        }
+        impact.push(format!("getting '{}' back depends on '{}' transferring it again", self.name, new_owner));
+        impact
+    }
+}
+
+/// What remains after reset --hard.
+pub struct ResetRepository {
+    pub name: String,
+    pub path: String,
+}
+
+impl ResetRepository {
+    pub fn summary(&self) -> String {
+        format!(
+            "[{}] reset to HEAD. All uncommitted work is permanently lost.",
+            self.name
+        )
+    }
+}
@@ -0,0 +1,185 @@
+//! Ed25519 (RFC 8032), with the SHA-512 it is built on.
+//!
+//! Consent is signed with it, so a system downstream of the gate can
+//! check that consent was minted by a particular gate holding only that
+//! gate's public key.
+//!
+//! The arithmetic is `ed25519-dalek`'s and the hash is `sha2`'s; this
+//! module only fixes the shapes the rest of the crate passes around —
+//! raw key and signature bytes, and their hex — so no caller depends on
+//! either crate's types. Verification is strict: a signature with a
+//! non-canonical scalar or a small-order key is refused.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use ed25519_dalek::Signer;
+use sha2::{Digest, Sha512};
+
+/// SHA-512 of the concatenation of `parts`.
+pub fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// A public key: what a verifier holds.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey(pub [u8; 32]);
+
+/// A 64-byte signature: the point R, then the scalar S.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 64]);
+
+/// A signing key. Its seed never leaves the struct.
+pub struct SigningKey {
+    key: ed25519_dalek::SigningKey,
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn unhex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != 2 * N || !text.is_ascii() {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+impl PublicKey {
+    pub fn to_hex(&self) -> String {
+        hex(&self.0)
+    }
+
+    pub fn from_hex(text: &str) -> Option<Self> {
+        unhex(text).map(PublicKey)
+    }
+
+    /// Whether `signature` is this key's signature of `message`.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        let Ok(key) = ed25519_dalek::VerifyingKey::from_bytes(&self.0) else {
+            return false;
+        };
+        let signature = ed25519_dalek::Signature::from_bytes(&signature.0);
+        key.verify_strict(message, &signature).is_ok()
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", self.to_hex())
+    }
+}
+
+impl Signature {
+    pub fn to_hex(&self) -> String {
+        hex(&self.0)
+    }
+
+    pub fn from_hex(text: &str) -> Option<Self> {
+        unhex(text).map(Signature)
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signature({})", self.to_hex())
+    }
+}
+
+impl SigningKey {
+    /// A key from 32 bytes of the OS random source. If it cannot be
+    /// read, from the process's random hasher keys instead. Without std
+    /// there is neither; a device brings its own seed to
+    /// [`from_seed`](Self::from_seed).
+    #[cfg(feature = "std")]
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 32];
+        if super::os_random(&mut seed).is_err() {
+            for chunk in seed.chunks_mut(8) {
+                chunk.copy_from_slice(&super::random_u64().to_le_bytes());
+            }
+        }
+        Self::from_seed(seed)
+    }
+
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        SigningKey {
+            key: ed25519_dalek::SigningKey::from_bytes(&seed),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(self.key.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.key.sign(message).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sha512, unhex, PublicKey, Signature, SigningKey};
+
+    #[test]
+    fn sha512_matches_the_fips_vectors() {
+        let abc = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                   2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+        assert_eq!(sha512(&[b"a", b"bc"]), unhex::<64>(abc).unwrap());
+        let empty = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+                     47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e";
+        assert_eq!(sha512(&[]), unhex::<64>(empty).unwrap());
+    }
+
+    /// RFC 8032, section 7.1, TEST 1 and TEST 2.
+    #[test]
+    fn signatures_match_rfc_8032() {
+        let vectors: [(&str, &str, &[u8], &str); 2] = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                b"",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+                 5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                &[0x72],
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+                 085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+        ];
+        for (seed, public, message, signature) in vectors {
+            let key = SigningKey::from_seed(unhex(seed).unwrap());
+            assert_eq!(key.public_key(), PublicKey::from_hex(public).unwrap());
+            let expected = Signature::from_hex(signature).unwrap();
+            assert_eq!(key.sign(message), expected);
+            assert!(key.public_key().verify(message, &expected));
+            assert!(!key.public_key().verify(b"another message", &expected));
+        }
+    }
+
+    #[test]
+    fn a_non_canonical_scalar_is_refused() {
+        let key = SigningKey::from_seed([7; 32]);
+        let mut signature = key.sign(b"m");
+        // S + L: the same point arithmetic, but not the scalar the signer wrote.
+        let l = unhex::<32>("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010").unwrap();
+        let mut carry = 0u16;
+        for (s, l) in signature.0[32..].iter_mut().zip(l) {
+            let sum = u16::from(*s) + u16::from(l) + carry;
+            *s = sum as u8;
+            carry = sum >> 8;
+        }
+        assert!(!key.public_key().verify(b"m", &signature));
+    }
+}
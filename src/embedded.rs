@@ -0,0 +1,214 @@
+//! A gate for devices without std.
+//!
+//! Without std the crate is the same typestate core the gate's host runs:
+//! [`Repository`], [`UserConsent`], and a [`backend::GitBackend`] the device
+//! implements to carry out what was approved. What it does not have is the
+//! [`SafetyGate`](crate::SafetyGate), with its terminal, audit log, and
+//! approval channels. This [`Gate`] stands in for it: it asks the device's
+//! [`Approver`] — a button, a paired phone, a line to an operator — and
+//! mints the same `UserConsent`, signed with the device's key, bound to the
+//! repository and the [`OperationDescriptor`] it was granted for, and
+//! expiring by the device's clock (see [`unix_now`]).
+//!
+//! A repository an agent opens has no force-push to call:
+//!
+//! ```compile_fail
+//! use safe_operations::embedded::Gate;
+//! use safe_operations::{ed25519, OperationDescriptor, Repository};
+//!
+//! let mut gate = Gate::new(|_: &OperationDescriptor| true, ed25519::SigningKey::from_seed([7; 32]), 300);
+//! let repo = Repository::open("firmware", "/flash/firmware", 12);
+//! let push = OperationDescriptor::ForcePush { repo: "/flash/firmware".into(), branch: "main".into() };
+//! let consent = gate.request_consent_to(&repo, push).unwrap();
+//! repo.force_push(consent);
+//! ```
+//!
+//! [`backend::GitBackend`]: crate::backend::GitBackend
+
+use crate::consent::consent_message;
+#[cfg(feature = "std")]
+use crate::nonce_ledger;
+use crate::{ed25519, unix_now, ConsentDenied, OperationDescriptor, Repository, UserConsent};
+use alloc::format;
+use alloc::string::ToString;
+use core::marker::PhantomData;
+
+/// Whoever is asked.
+pub trait Approver {
+    /// Whether a person approved `request`, as rendered by
+    /// [`OperationDescriptor::render`]. No answer is a no.
+    fn approve(&mut self, request: &OperationDescriptor) -> bool;
+}
+
+impl<F: FnMut(&OperationDescriptor) -> bool> Approver for F {
+    fn approve(&mut self, request: &OperationDescriptor) -> bool {
+        self(request)
+    }
+}
+
+/// The device's source of [`UserConsent`]: it asks its approver, and
+/// mints consent that lasts `ttl` seconds.
+///
+/// It binds no agent session and keeps no revocation registry: a device
+/// has one agent, and consent it mints is spent by the call it is
+/// presented to. A host with std uses the [`SafetyGate`](crate::SafetyGate).
+pub struct Gate<A> {
+    approver: A,
+    signing_key: ed25519::SigningKey,
+    ttl: u64,
+    /// Grants so far; each one's nonce.
+    issued: u64,
+    #[cfg(feature = "std")]
+    ledger: nonce_ledger::Ledger,
+}
+
+impl<A: Approver> Gate<A> {
+    /// A gate signing with `signing_key`, which the device provisions: it
+    /// has no random source to generate one from.
+    pub fn new(approver: A, signing_key: ed25519::SigningKey, ttl: u64) -> Self {
+        Gate {
+            approver,
+            signing_key,
+            ttl,
+            issued: 0,
+            #[cfg(feature = "std")]
+            ledger: nonce_ledger::Ledger::default(),
+        }
+    }
+
+    /// The public half of this gate's key, for checking consent it minted
+    /// with [`UserConsent::verify`].
+    pub fn consent_key(&self) -> ed25519::PublicKey {
+        self.signing_key.public_key()
+    }
+
+    /// Ask for consent to `descriptor` on `repo`, as
+    /// [`SafetyGate::request_consent_to`](crate::SafetyGate::request_consent_to)
+    /// does.
+    pub fn request_consent_to<S, B>(
+        &mut self,
+        repo: &Repository<S, B>,
+        descriptor: OperationDescriptor,
+    ) -> Result<UserConsent, ConsentDenied> {
+        let operation = descriptor.kind();
+        if descriptor.repo() != repo.path && descriptor.repo() != repo.name {
+            let reason = format!("the descriptor is for '{}', not '{}'", descriptor.repo(), repo.path);
+            return Err(ConsentDenied::new(operation, reason));
+        }
+        if !self.approver.approve(&descriptor) {
+            let reason = format!("not approved: {}", descriptor.render());
+            return Err(ConsentDenied::new(operation, reason).by_approver("approver"));
+        }
+        self.issued += 1;
+        let nonce = self.issued;
+        #[cfg(feature = "std")]
+        self.ledger.register(nonce);
+        let issued_at = unix_now();
+        let branch = descriptor.branch().map(str::to_string);
+        let message = consent_message(
+            operation,
+            Some(&repo.path),
+            branch.as_deref(),
+            Some(&descriptor),
+            None,
+            issued_at,
+            nonce,
+        );
+        Ok(UserConsent {
+            _operation: descriptor.render(),
+            _nonce: nonce,
+            _issued_at: issued_at,
+            _signature: self.signing_key.sign(&message),
+            _kind: operation,
+            _target: Some(repo.path.clone()),
+            _branch: branch,
+            _observe: false,
+            _descriptor: Some(descriptor),
+            _expires_at: issued_at.saturating_add(self.ttl),
+            _not_before: 0,
+            #[cfg(feature = "std")]
+            _revocations: Default::default(),
+            #[cfg(feature = "std")]
+            _agent: None,
+            #[cfg(feature = "std")]
+            _trail: Default::default(),
+            #[cfg(feature = "std")]
+            _ledger: self.ledger.clone(),
+            #[cfg(feature = "std")]
+            _downstream: Default::default(),
+            _scope: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gate;
+    use crate::{ed25519, OperationDescriptor, Repository, Unprotected};
+
+    type Device = fn(&OperationDescriptor) -> bool;
+
+    fn gate(approve: Device, ttl: u64) -> Gate<Device> {
+        Gate::new(approve, ed25519::SigningKey::from_seed([7; 32]), ttl)
+    }
+
+    fn unprotect(gate: &mut Gate<Device>) -> Repository<Unprotected> {
+        let repo = Repository::protected("firmware", "/flash/firmware", 12);
+        let consent = gate
+            .request_consent_to(&repo, OperationDescriptor::RemoveProtection { repo: "/flash/firmware".to_string() })
+            .unwrap();
+        repo.remove_protection(consent).map_err(|refused| refused.denied).expect("consent covers remove_protection")
+    }
+
+    fn push(branch: &str) -> OperationDescriptor {
+        OperationDescriptor::ForcePush {
+            repo: "/flash/firmware".to_string(),
+            branch: branch.to_string(),
+        }
+    }
+
+    #[test]
+    fn a_refusal_mints_nothing() {
+        let repo = Repository::protected("firmware", "/flash/firmware", 12);
+        let denied = gate(|_| false, 300)
+            .request_consent_to(&repo, OperationDescriptor::ResetHard { repo: "/flash/firmware".to_string() })
+            .err()
+            .expect("the approver said no");
+        assert!(denied.is_refusal());
+    }
+
+    #[test]
+    fn the_device_mints_the_consent_the_repository_takes() {
+        let mut gate = gate(|_| true, 300);
+        let repo = unprotect(&mut gate);
+
+        let consent = gate.request_consent_to(&repo, push("release")).unwrap();
+        assert!(consent.verify(&gate.consent_key()));
+        let denied = repo.force_push_branch("main", consent).expect_err("consented to 'release'").to_string();
+        assert!(denied.contains("release"), "{}", denied);
+
+        let consent = gate.request_consent_to(&repo, push("main")).unwrap();
+        assert_eq!(repo.force_push(consent).unwrap(), "[firmware] force-pushed to origin/main");
+    }
+
+    #[test]
+    fn consent_is_refused_once_the_device_clock_passes_its_expiry() {
+        let mut expiring = gate(|_| true, 0);
+        let repo = unprotect(&mut gate(|_| true, 300));
+        let consent = expiring.request_consent_to(&repo, push("main")).unwrap();
+        let denied = repo.force_push(consent).expect_err("lasts no seconds").to_string();
+        assert!(denied.contains("expired"), "{}", denied);
+    }
+
+    #[test]
+    fn consent_for_another_repository_is_not_minted_or_taken() {
+        let mut gate = gate(|_| true, 300);
+        let repo = Repository::protected("firmware", "/flash/firmware", 12);
+        let bootloader = Repository::protected("bootloader", "/flash/bootloader", 3);
+        let descriptor = OperationDescriptor::RemoveProtection { repo: "/flash/bootloader".to_string() };
+        assert!(gate.request_consent_to(&repo, descriptor.clone()).is_err());
+        let consent = gate.request_consent_to(&bootloader, descriptor).unwrap();
+        let refused = repo.remove_protection(consent).err().expect("consented to 'bootloader'");
+        assert!(refused.denied.reason.contains("bootloader"), "{}", refused.denied.reason);
+    }
+}
@@ -0,0 +1,217 @@
+//! SafetyError and playbooks — what to do next.
+
+use crate::{
+    alternatives, ConsentDenied, DeniedBy, FilteredRepository, ManifestError, OperationKind,
+    Outcome, Receipt, ResetRepository, TransferredRepository,
+};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// One remediation step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaybookStep {
+    /// Restore from a snapshot, backup ref, or other saved state.
+    RestoreFrom(String),
+    /// Ask a person in this role; the agent cannot resolve it alone.
+    ContactApprover(String),
+    /// Do this instead; it gets the job done without the destructive part.
+    UseAlternative(String),
+    /// Run this command.
+    Run(String),
+}
+
+impl PlaybookStep {
+    pub fn render(&self) -> String {
+        match self {
+            PlaybookStep::RestoreFrom(what) => format!("restore from {}", what),
+            PlaybookStep::ContactApprover(role) => format!("contact {}", role),
+            PlaybookStep::UseAlternative(what) => format!("instead: {}", what),
+            PlaybookStep::Run(command) => format!("run: {}", command),
+        }
+    }
+}
+
+/// Structured remediation for a blocked operation or a destroyed state.
+///
+/// A refusal that only says "no" teaches an agent to look for another way
+/// to say yes. A refusal with a playbook points at the safe path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Playbook {
+    pub title: String,
+    pub steps: Vec<PlaybookStep>,
+}
+
+impl Playbook {
+    pub(crate) fn new(title: impl Into<String>) -> Self {
+        Playbook {
+            title: title.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub(crate) fn step(mut self, step: PlaybookStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("Playbook: {}", self.title);
+        for (i, step) in self.steps.iter().enumerate() {
+            out.push_str(&format!("\n  {}. {}", i + 1, step.render()));
+        }
+        out
+    }
+}
+
+impl OperationKind {
+    /// The non-destructive way to get what this operation is usually for:
+    /// the first of its [`alternatives`].
+    pub fn safe_alternative(&self) -> &'static str {
+        alternatives::for_operation(*self)[0].instead
+    }
+}
+
+
+/// Everything the safety layer can refuse or report.
+#[derive(Debug)]
+pub enum SafetyError {
+    /// The gate refused consent.
+    Denied(ConsentDenied),
+    /// The agent's manifest or scope does not allow it.
+    Manifest(ManifestError),
+    /// A destructive operation stopped partway. Its receipt says where.
+    Interrupted(Box<Receipt>),
+    /// A destructive operation did not run: the backend refused it or
+    /// could not start it. Its receipt says why.
+    Failed(Box<Receipt>),
+}
+
+impl From<Receipt> for SafetyError {
+    /// The error a receipt that did not complete makes.
+    fn from(receipt: Receipt) -> Self {
+        match receipt.outcome {
+            Outcome::Failed(_) => SafetyError::Failed(Box::new(receipt)),
+            _ => SafetyError::Interrupted(Box::new(receipt)),
+        }
+    }
+}
+
+impl From<ConsentDenied> for SafetyError {
+    fn from(denied: ConsentDenied) -> Self {
+        SafetyError::Denied(denied)
+    }
+}
+
+impl From<ManifestError> for SafetyError {
+    fn from(err: ManifestError) -> Self {
+        SafetyError::Manifest(err)
+    }
+}
+
+impl core::fmt::Display for SafetyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SafetyError::Denied(denied) => write!(f, "{}", denied),
+            SafetyError::Manifest(err) => write!(f, "manifest: {:?}", err),
+            SafetyError::Interrupted(receipt) | SafetyError::Failed(receipt) => write!(
+                f,
+                "{} on '{}' {}",
+                receipt.operation.name(),
+                receipt.repo,
+                receipt.outcome.label()
+            ),
+        }
+    }
+}
+
+impl SafetyError {
+    /// What to do about it.
+    pub fn playbook(&self) -> Playbook {
+        use PlaybookStep::*;
+        match self {
+            SafetyError::Denied(ConsentDenied {
+                operation,
+                by: DeniedBy::Approver(who),
+                ..
+            }) => alternatives::for_operation(*operation)
+                .iter()
+                .fold(Playbook::new(format!("{} was refused by {}", operation.name(), who)), |playbook, alternative| {
+                    playbook.step(UseAlternative(alternative.render()))
+                })
+                .step(ContactApprover(format!(
+                    "{}, to learn what would change the answer; do not ask again with the request reworded",
+                    who
+                ))),
+            SafetyError::Denied(denied) => {
+                let approver = if denied.reason.contains("manifest") {
+                    "the host operator, to issue a manifest that lists this operation"
+                } else if denied.reason.contains("policy") {
+                    "the policy owner, if this operation should be allowed"
+                } else {
+                    "the repository owner, with the destruction plan"
+                };
+                alternatives::for_operation(denied.operation)
+                    .iter()
+                    .fold(Playbook::new(format!("{} was not approved", denied.operation.name())), |playbook, alternative| {
+                        playbook.step(UseAlternative(alternative.render()))
+                    })
+                    .step(ContactApprover(approver.to_string()))
+            }
+            SafetyError::Manifest(ManifestError::NotPermitted(capability)) => {
+                Playbook::new(format!("capability {} not granted", capability.name()))
+                    .step(ContactApprover("the host operator, to sign a new manifest for a new session".to_string()))
+            }
+            SafetyError::Manifest(ManifestError::OutOfScope(paths)) => {
+                Playbook::new(format!("{} paths outside the agent's cone", paths.len()))
+                    .step(UseAlternative("commit the in-cone files; hand the rest to a task scoped to them".to_string()))
+                    .step(ContactApprover("the task owner, if the cone is too narrow".to_string()))
+            }
+            SafetyError::Manifest(_) => Playbook::new("manifest rejected")
+                .step(ContactApprover("the host operator, to re-issue and re-sign the manifest".to_string())),
+            SafetyError::Interrupted(receipt) => {
+                Playbook::new(format!("{} stopped: {}", receipt.operation.name(), receipt.outcome.label()))
+                    .step(Run("git status && git reflog -n 20".to_string()))
+                    .step(RestoreFrom("the refs recorded before the operation started".to_string()))
+                    .step(ContactApprover("the approver of the original request, before retrying".to_string()))
+            }
+            SafetyError::Failed(receipt) => {
+                Playbook::new(format!("{} did not run: {}", receipt.operation.name(), receipt.outcome.label()))
+                    .step(Run("git status && git remote -v".to_string()))
+                    .step(ContactApprover(
+                        "the approver of the original request: the consent was spent, and a retry needs new consent"
+                            .to_string(),
+                    ))
+            }
+        }
+    }
+}
+
+impl FilteredRepository {
+    pub fn playbook(&self) -> Playbook {
+        use PlaybookStep::*;
+        Playbook::new(format!("'{}' history was rewritten", self.name))
+            .step(RestoreFrom("a mirror clone or any collaborator's clone taken before the rewrite".to_string()))
+            .step(Run("git push --force --all origin && git push --force --tags origin  # from the restored clone".to_string()))
+            .step(ContactApprover("every contributor, to re-clone rather than merge the rewritten history".to_string()))
+    }
+}
+
+impl TransferredRepository {
+    pub fn playbook(&self) -> Playbook {
+        use PlaybookStep::*;
+        Playbook::new(format!("'{}' now belongs to {}", self.name, self.new_owner))
+            .step(ContactApprover(format!("{}, to transfer it back", self.new_owner)))
+            .step(Run("git remote set-url origin <new location>".to_string()))
+    }
+}
+
+impl ResetRepository {
+    pub fn playbook(&self) -> Playbook {
+        use PlaybookStep::*;
+        Playbook::new(format!("'{}' uncommitted work was discarded", self.name))
+            .step(Run("git fsck --lost-found  # recovers staged blobs only".to_string()))
+            .step(RestoreFrom("editor local history or filesystem snapshots".to_string()))
+    }
+}
@@ -0,0 +1,62 @@
+//! Training data export.
+//!
+//! Researchers building classifiers for "is this agent about to destroy
+//! something?" need labeled examples of both answers. Scenario traces are
+//! exactly that, once the names of real repositories are taken out.
+
+use super::json_string;
+use super::scenario::Trace;
+
+/// Emit traces as JSONL, one trace per line.
+///
+/// Repository names are replaced with stable pseudonyms (`repo-1`,
+/// `repo-2`, ...) assigned in order of first appearance across the
+/// whole export, and scrubbed from every free-text field. The same
+/// repository keeps the same pseudonym in every trace, so cross-repo
+/// spread — a signal in itself — survives anonymization.
+pub fn training_data(traces: &[Trace]) -> String {
+    let mut pseudonyms: Vec<String> = Vec::new();
+    for trace in traces {
+        for step in &trace.steps {
+            if !pseudonyms.contains(&step.repo) {
+                pseudonyms.push(step.repo.clone());
+            }
+        }
+    }
+    // Longest names first, so "anima-mcp-v2" is not scrubbed as "anima-mcp".
+    let mut order: Vec<usize> = (0..pseudonyms.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(pseudonyms[i].len()));
+    let scrub = |text: &str| {
+        let mut text = text.to_string();
+        for &i in &order {
+            text = text.replace(&pseudonyms[i], &format!("repo-{}", i + 1));
+        }
+        text
+    };
+
+    let mut out = String::new();
+    for trace in traces {
+        let steps: Vec<String> = trace
+            .steps
+            .iter()
+            .map(|s| {
+                format!(
+                    "{{\"operation\":{},\"repo\":{},\"description\":{},\"decision\":{},\"outcome\":{}}}",
+                    json_string(s.operation.name()),
+                    json_string(&scrub(&s.repo)),
+                    json_string(&scrub(&s.description)),
+                    json_string(&s.decision),
+                    json_string(&s.outcome)
+                )
+            })
+            .collect();
+        out.push_str(&format!(
+            "{{\"scenario\":{},\"label\":{},\"intent\":{},\"steps\":[{}]}}\n",
+            json_string(&trace.scenario),
+            json_string(trace.label.name()),
+            json_string(&scrub(&trace.intent)),
+            steps.join(",")
+        ));
+    }
+    out
+}
@@ -0,0 +1,281 @@
+//! Gate federation for cross-organization approvals.
+//!
+//! A contractor's agent working on our repository runs behind the
+//! contractor's gate. That gate can vouch for the request; it cannot
+//! approve it. Gates exchange signed trust anchors naming the repositories
+//! each one owns, a request for a foreign-owned repository is forwarded to
+//! the owner's gate, and the grant that comes back carries the chain of
+//! gates it passed through, onto the receipt.
+
+use super::{
+    consent_message, unix_now, ConsentDenied, HostKey, HostSignature, OperationDescriptor, OperationKind, Receipt, SafetyGate,
+    UserConsent,
+};
+use core::marker::PhantomData;
+
+/// A gate's published identity: who it is, what it owns, and the key
+/// its grants verify against. Signed by that key, so an anchor cannot
+/// be edited to claim someone else's repositories.
+///
+/// Keyed like [`HostKey`], with an HMAC: the verification key is the
+/// signing secret, so an anchor is only for peers that may hold it.
+pub struct TrustAnchor {
+    pub gate: String,
+    pub owns: Vec<String>,
+    key: HostKey,
+    signature: HostSignature,
+}
+
+impl TrustAnchor {
+    fn message(gate: &str, owns: &[String]) -> String {
+        format!("anchor|{}|{}", gate, owns.join(","))
+    }
+
+    fn verify(&self) -> bool {
+        self.key.verify(&Self::message(&self.gate, &self.owns), &self.signature)
+    }
+
+    fn verifies(&self, message: &str, signature: &HostSignature) -> bool {
+        self.key.verify(message, signature)
+    }
+}
+
+/// This gate's place in the federation.
+pub struct Membership {
+    id: String,
+    key: HostKey,
+    owns: Vec<String>,
+    trusted: Vec<TrustAnchor>,
+}
+
+/// A consent request on its way to the owning gate.
+pub struct ForwardedRequest {
+    pub origin: String,
+    pub owner: String,
+    pub operation: OperationKind,
+    pub repo: String,
+    pub description: String,
+    signature: HostSignature,
+}
+
+impl ForwardedRequest {
+    fn message(&self) -> String {
+        format!(
+            "request|{}|{}|{}|{}|{}",
+            self.origin,
+            self.owner,
+            self.operation.name(),
+            self.repo,
+            self.description
+        )
+    }
+}
+
+/// The owning gate's answer, signed by it.
+pub struct ForwardedGrant {
+    pub request: ForwardedRequest,
+    pub chain: Vec<String>,
+    /// The owning gate's approver's reason, signed with the grant.
+    pub reason: Option<String>,
+    signature: HostSignature,
+}
+
+impl ForwardedGrant {
+    fn message(&self) -> String {
+        format!(
+            "grant|{}|{}|{}",
+            self.request.message(),
+            self.chain.join(","),
+            self.reason.as_deref().unwrap_or("")
+        )
+    }
+}
+
+/// Where a request was decided.
+pub enum Routing {
+    /// The repository is ours or unclaimed: decided here.
+    Local(Result<UserConsent, ConsentDenied>),
+    /// The repository belongs to another gate. Send it this.
+    Forward(ForwardedRequest),
+}
+
+fn denied(operation: OperationKind, reason: String) -> ConsentDenied {
+    ConsentDenied::new(operation, reason)
+}
+
+impl SafetyGate {
+    /// Join a federation as `id`, owning `owns`.
+    pub fn federate(&mut self, id: &str, key: HostKey, owns: &[&str]) {
+        self.federation = Some(Membership {
+            id: id.to_string(),
+            key,
+            owns: owns.iter().map(|r| r.to_string()).collect(),
+            trusted: Vec::new(),
+        });
+    }
+
+    /// This gate's anchor, for handing to peers.
+    pub fn trust_anchor(&self) -> Option<TrustAnchor> {
+        let m = self.federation.as_ref()?;
+        Some(TrustAnchor {
+            gate: m.id.clone(),
+            owns: m.owns.clone(),
+            key: m.key.clone(),
+            signature: m.key.sign(&TrustAnchor::message(&m.id, &m.owns)),
+        })
+    }
+
+    /// Trust a peer's anchor. Refused if its signature does not verify
+    /// or it claims a repository this gate owns.
+    pub fn trust(&mut self, anchor: TrustAnchor) -> Result<(), String> {
+        let Some(m) = &mut self.federation else {
+            return Err("gate is not federated".to_string());
+        };
+        if !anchor.verify() {
+            return Err(format!("anchor for '{}' has a bad signature", anchor.gate));
+        }
+        if let Some(repo) = anchor.owns.iter().find(|r| m.owns.contains(r)) {
+            return Err(format!("'{}' claims '{}', which this gate owns", anchor.gate, repo));
+        }
+        let event = format!("TRUSTED: gate '{}' owning [{}]", anchor.gate, anchor.owns.join(", "));
+        m.trusted.retain(|a| a.gate != anchor.gate);
+        m.trusted.push(anchor);
+        self.log(event);
+        Ok(())
+    }
+
+    /// Request consent for an operation on `repo`, forwarding it if a
+    /// trusted peer owns the repository.
+    pub fn route_consent(&mut self, repo: &str, operation: OperationKind, description: &str) -> Routing {
+        if let Err(denied) = self.screen_forbidden(operation, Some(repo), description) {
+            return Routing::Local(Err(denied));
+        }
+        let forward = self.federation.as_ref().and_then(|m| {
+            let owner = m.trusted.iter().find(|a| a.owns.iter().any(|r| r == repo))?;
+            let mut request = ForwardedRequest {
+                origin: m.id.clone(),
+                owner: owner.gate.clone(),
+                operation,
+                repo: repo.to_string(),
+                description: description.to_string(),
+                signature: HostSignature::default(),
+            };
+            request.signature = m.key.sign(&request.message());
+            Some(request)
+        });
+        match forward {
+            Some(request) => {
+                self.log(format!("FORWARDED to gate '{}': {}", request.owner, description));
+                Routing::Forward(request)
+            }
+            None => {
+                self.target = Some(repo.to_string());
+                Routing::Local(self.mint(operation, description))
+            }
+        }
+    }
+
+    /// As the owning gate: decide a forwarded request with this gate's
+    /// own policy and approver, and sign the answer.
+    pub fn decide_forwarded(&mut self, request: ForwardedRequest) -> Result<ForwardedGrant, ConsentDenied> {
+        let operation = request.operation;
+        let Some(m) = &self.federation else {
+            return Err(denied(operation, "gate is not federated".to_string()));
+        };
+        if request.owner != m.id || !m.owns.contains(&request.repo) {
+            return Err(denied(operation, format!("gate '{}' does not own '{}'", m.id, request.repo)));
+        }
+        let origin_ok = m
+            .trusted
+            .iter()
+            .any(|a| a.gate == request.origin && a.verifies(&request.message(), &request.signature));
+        if !origin_ok {
+            let reason = format!("request from untrusted or unverified gate '{}'", request.origin);
+            self.log(format!("DENIED (federation): {}", reason));
+            return Err(denied(operation, reason));
+        }
+        let description = format!("{} (for gate '{}')", request.description, request.origin);
+        self.mint::<super::General>(operation, &description)?;
+        let m = self.federation.as_ref().expect("checked above");
+        let mut grant = ForwardedGrant {
+            chain: vec![request.origin.clone(), m.id.clone()],
+            request,
+            reason: self.last_reason.clone(),
+            signature: HostSignature::default(),
+        };
+        grant.signature = m.key.sign(&grant.message());
+        Ok(grant)
+    }
+
+    /// As the requesting gate: turn a verified grant from the owning
+    /// gate into consent. The grant's chain goes on the receipt via
+    /// [`Receipt::with_gate_chain`].
+    pub fn accept_grant(&mut self, grant: &ForwardedGrant) -> Result<UserConsent, ConsentDenied> {
+        let operation = grant.request.operation;
+        let verified = !self.fault(super::FaultPoint::Signing) && self.federation.as_ref().is_some_and(|m| {
+            grant.request.origin == m.id
+                && m.trusted.iter().any(|a| {
+                    a.gate == grant.request.owner
+                        && a.owns.contains(&grant.request.repo)
+                        && a.verifies(&grant.message(), &grant.signature)
+                })
+        });
+        if !verified {
+            self.log(format!("REJECTED GRANT from gate '{}'", grant.request.owner));
+            return Err(denied(operation, "grant did not verify against the owner's anchor".to_string()));
+        }
+        self.log(format!(
+            "GRANTED {} on {} BY gate '{}': {} (chain: {})",
+            operation.name(),
+            super::json_string(&grant.request.repo),
+            grant.request.owner,
+            grant.request.description,
+            grant.chain.join(" -> ")
+        ));
+        self.last_reason = grant.reason.clone();
+        self.last_approvers.clear();
+        let issued_at = unix_now();
+        // The grant's signature doubles as its nonce: any eight of its
+        // bytes are as unpredictable as the whole.
+        let nonce = u64::from_le_bytes(grant.signature.0[..8].try_into().expect("eight bytes"));
+        let not_before = self.time_lock(operation, &grant.request.description, nonce, issued_at);
+        self.ledger.register(nonce);
+        let descriptor = OperationDescriptor::implied(operation, &grant.request.repo, None);
+        let agent = super::agent_identity::current();
+        let message = consent_message(
+            operation,
+            Some(&grant.request.repo),
+            None,
+            descriptor.as_ref(),
+            agent.as_ref().map(|agent| agent.encode()).as_deref(),
+            issued_at,
+            nonce,
+        );
+        Ok(UserConsent {
+            _operation: grant.request.description.clone(),
+            _nonce: nonce,
+            _issued_at: issued_at,
+            _signature: self.signing_key.sign(&message),
+            _kind: operation,
+            _target: Some(grant.request.repo.clone()),
+            _branch: None,
+            _descriptor: descriptor,
+            _observe: self.mode == super::observe::GateMode::Observe,
+            _expires_at: issued_at.max(not_before).saturating_add(self.policy.consent_ttl()),
+            _not_before: not_before,
+            _revocations: self.revocations.clone(),
+            _agent: agent.map(Box::new),
+            _trail: self.trail.clone(),
+            _ledger: self.ledger.clone(),
+            _downstream: self.downstream.clone(),
+            _scope: PhantomData,
+        })
+    }
+}
+
+impl Receipt {
+    pub fn with_gate_chain(mut self, grant: &ForwardedGrant) -> Self {
+        self.gate_chain = grant.chain.clone();
+        self
+    }
+}
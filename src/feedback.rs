@@ -0,0 +1,143 @@
+//! Denials shaped for the agent's context window.
+//!
+//! A tool call that fails with "consent denied" reads to a model like a
+//! transient error, and the next move is a retry or a workaround. The
+//! payload here says what was refused, which rule refused it, and what to
+//! do instead, so the host can hand it back to the model as a tool result
+//! it can reason about.
+
+use super::{json_string, ConsentDenied, DeniedBy, OperationKind, SafetyError, SafetyGate};
+
+/// Receives every denial the gate makes.
+pub type DenialHook = Box<dyn FnMut(&DenialFeedback)>;
+
+/// A structured account of one refusal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DenialFeedback {
+    /// `None` when the refusal was about the agent's capabilities
+    /// rather than one operation.
+    pub operation: Option<OperationKind>,
+    pub reason: String,
+    /// The rule that decided it, or the approver who refused. `None`
+    /// for refusals caused by a failure (approval channel down, audit
+    /// unavailable).
+    pub citation: Option<String>,
+    pub alternatives: Vec<String>,
+    /// Whether asking again can change the answer. A policy or manifest
+    /// denial cannot, nor can an approver's no; a failed approval
+    /// channel might.
+    pub retryable: bool,
+}
+
+impl DenialFeedback {
+    /// Feedback for any safety error, not only a gate denial. The
+    /// citation is unknown outside the gate.
+    pub fn from_error(err: &SafetyError) -> Self {
+        let operation = match err {
+            SafetyError::Denied(denied) => Some(denied.operation),
+            SafetyError::Interrupted(receipt) | SafetyError::Failed(receipt) => Some(receipt.operation),
+            SafetyError::Manifest(_) => None,
+        };
+        let citation = match err {
+            SafetyError::Manifest(_) => Some("agent manifest".to_string()),
+            SafetyError::Denied(ConsentDenied { by: DeniedBy::Approver(who), .. }) => Some(format!("approver {}", who)),
+            _ => None,
+        };
+        DenialFeedback {
+            operation,
+            reason: err.to_string(),
+            citation,
+            alternatives: alternatives_from(err),
+            retryable: matches!(err, SafetyError::Interrupted(_)),
+        }
+    }
+
+    /// The message to put in front of the model.
+    pub fn to_agent_message(&self) -> String {
+        let mut out = match self.operation {
+            Some(operation) => format!("The operation `{}` was refused.\n", operation.name()),
+            None => "The request was refused.\n".to_string(),
+        };
+        out.push_str(&format!("Reason: {}\n", self.reason));
+        if let Some(citation) = &self.citation {
+            out.push_str(&format!("Decided by: {}\n", citation));
+        }
+        if self.retryable {
+            out.push_str("This may succeed if requested again later.\n");
+        } else {
+            out.push_str("Requesting it again will be refused again. Do not retry or work around it.\n");
+        }
+        if !self.alternatives.is_empty() {
+            out.push_str("What to do:\n");
+            for alternative in &self.alternatives {
+                out.push_str(&format!("- {}\n", alternative));
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":\"consent_denied\",\"operation\":{},\"severity\":{},\"reason\":{},\"citation\":{},\"retryable\":{},\"alternatives\":[{}],\"safe_alternatives\":[{}]}}",
+            self.operation.map(|o| json_string(o.name())).unwrap_or_else(|| "null".to_string()),
+            self.operation.map(|o| json_string(o.severity().label())).unwrap_or_else(|| "null".to_string()),
+            json_string(&self.reason),
+            self.citation.as_deref().map(json_string).unwrap_or_else(|| "null".to_string()),
+            self.retryable,
+            self.alternatives.iter().map(|a| json_string(a)).collect::<Vec<_>>().join(","),
+            self.operation
+                .map(|o| super::alternatives::for_operation(o).iter().map(|a| a.to_json()).collect::<Vec<_>>().join(","))
+                .unwrap_or_default()
+        )
+    }
+
+    /// The refusal as an MCP `tools/call` result: the agent message as
+    /// text, flagged as an error, with [`Self::to_json`] as structured
+    /// content so a client can offer the safe alternatives directly.
+    pub fn to_mcp_result(&self) -> String {
+        format!(
+            "{{\"content\":[{{\"type\":\"text\",\"text\":{}}}],\"isError\":true,\"structuredContent\":{}}}",
+            json_string(&self.to_agent_message()),
+            self.to_json()
+        )
+    }
+}
+
+fn alternatives_from(err: &SafetyError) -> Vec<String> {
+    err.playbook().steps.iter().map(|step| step.render()).collect()
+}
+
+impl SafetyGate {
+    /// Register a hook called with a [`DenialFeedback`] every time the
+    /// gate refuses consent. Hosts typically forward
+    /// [`DenialFeedback::to_agent_message`] as the tool result.
+    pub fn on_denial(&mut self, hook: impl FnMut(&DenialFeedback) + 'static) {
+        self.on_denial = Some(Box::new(hook));
+    }
+
+    /// Build the feedback for a denial this gate just made, citing the
+    /// manifest or policy line responsible.
+    pub fn denial_feedback(&self, denied: &ConsentDenied) -> DenialFeedback {
+        let operation = denied.operation;
+        let citation = match (&denied.by, &self.manifest) {
+            (DeniedBy::Approver(who), _) => Some(format!("approver {}", who)),
+            _ if denied.reason.starts_with("forbidden by deny-list") => {
+                self.deny_list.as_ref().map(|list| format!("organization deny-list {}", list.version))
+            }
+            (_, Some((agent, permitted))) if !permitted.contains(&operation) => {
+                Some(format!("agent manifest for '{}'", agent))
+            }
+            _ if self.policy.decide(operation) == super::policy::Decision::Deny => {
+                Some(self.policy.citation(operation))
+            }
+            _ => None,
+        };
+        DenialFeedback {
+            operation: Some(operation),
+            reason: denied.reason.clone(),
+            retryable: citation.is_none(),
+            citation,
+            alternatives: alternatives_from(&SafetyError::Denied(denied.clone())),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Batch status across a fleet of registered repositories.
+//!
+//! Asking three hundred repositories for their status one at a time takes
+//! as long as all of them together, and one hung network mount stalls the
+//! rest. A [`RepoGroup`] asks them from a bounded pool of workers, gives
+//! each repository its own timeout, and reports a timeout as a timeout.
+
+use super::backend::{self, ExecOptions, GitBackend};
+use super::{Outcome, Protected, ReadOnly, Repository, Unprotected};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Reads one repository's working-tree status.
+pub trait StatusProbe: Send + Sync {
+    /// The status of the repository at `path`. A probe stops once
+    /// `options` says to, and says so with the outcome: the worker that
+    /// called it has nothing else to do until it returns.
+    fn status(&self, path: &str, options: &ExecOptions) -> Result<String, Outcome>;
+}
+
+/// Status from the git CLI: branch and count of changed paths.
+pub struct GitCli;
+
+impl StatusProbe for GitCli {
+    /// A `git status` that overruns is killed, as any backend command is.
+    fn status(&self, path: &str, options: &ExecOptions) -> Result<String, Outcome> {
+        let not_run = |why: std::io::Error| Outcome::Failed(format!("git status not run: {}", why));
+        let mut child = Command::new("git")
+            .args(["-C", path, "status", "--porcelain", "--branch"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(not_run)?;
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        // Drained while it runs: a child blocked on a full pipe would
+        // only ever time out.
+        let (status, stdout, stderr) = thread::scope(|scope| {
+            let stdout = scope.spawn(|| drain(stdout));
+            let stderr = scope.spawn(|| drain(stderr));
+            let status = backend::wait(&mut child, 1, options);
+            (status, stdout.join().unwrap_or_default(), stderr.join().unwrap_or_default())
+        });
+        match status.map_err(not_run)?? {
+            status if status.success() => {
+                let mut lines = stdout.lines();
+                let branch = lines.next().unwrap_or("## (unknown)").trim_start_matches("## ").to_string();
+                Ok(format!("{}, {} changed", branch, lines.count()))
+            }
+            _ => Err(Outcome::Failed(stderr.trim().to_string())),
+        }
+    }
+}
+
+/// Everything `pipe` carries until it closes.
+fn drain(pipe: Option<impl Read>) -> String {
+    let mut text = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut text);
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProbeResult {
+    Ok(String),
+    Failed(String),
+    TimedOut,
+}
+
+/// One repository's line in the fleet report.
+#[derive(Clone, Debug)]
+pub struct RepoReport {
+    pub name: String,
+    pub path: String,
+    /// Whether the registered handle is protected. Known from its type,
+    /// not asked of the repository.
+    pub protected: bool,
+    pub result: ProbeResult,
+    pub elapsed: Duration,
+}
+
+impl RepoReport {
+    pub fn render(&self) -> String {
+        let result = match &self.result {
+            ProbeResult::Ok(status) => status.clone(),
+            ProbeResult::Failed(err) => format!("FAILED: {}", err),
+            ProbeResult::TimedOut => format!("TIMED OUT after {:?}", self.elapsed),
+        };
+        let protection = if self.protected { "protected" } else { "UNPROTECTED" };
+        format!("{:<24} {:<11} {}", self.name, protection, result)
+    }
+}
+
+/// A set of registered repositories.
+pub struct RepoGroup {
+    repos: Vec<(String, String, bool)>,
+    workers: usize,
+    timeout: Duration,
+}
+
+impl Default for RepoGroup {
+    fn default() -> Self {
+        RepoGroup::new()
+    }
+}
+
+impl RepoGroup {
+    pub fn new() -> Self {
+        RepoGroup {
+            repos: Vec::new(),
+            workers: 16,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn register<B: GitBackend>(&mut self, repo: &Repository<Protected, B>) {
+        self.repos.push((repo.name.clone(), repo.path.clone(), true));
+    }
+
+    /// A read-only repository is protected: nothing on it can change history.
+    pub fn register_read_only<B: GitBackend>(&mut self, repo: &Repository<ReadOnly, B>) {
+        self.repos.push((repo.name.clone(), repo.path.clone(), true));
+    }
+
+    pub fn register_unprotected<B: GitBackend>(&mut self, repo: &Repository<Unprotected, B>) {
+        self.repos.push((repo.name.clone(), repo.path.clone(), false));
+    }
+
+    pub fn len(&self) -> usize {
+        self.repos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    /// Probe every repository, at most `workers` at a time, and return
+    /// the reports in registration order.
+    ///
+    /// Each worker runs its probe itself, with the group's timeout: a
+    /// probe that overruns it is stopped and reported, and no more than
+    /// `workers` probes are ever running.
+    pub fn status_all(&self, probe: &dyn StatusProbe) -> Vec<RepoReport> {
+        let next = AtomicUsize::new(0);
+        let reports: Mutex<Vec<Option<RepoReport>>> = Mutex::new(vec![None; self.repos.len()]);
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(self.repos.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((name, path, protected)) = self.repos.get(i) else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let result = match probe.status(path, &ExecOptions::with_timeout(self.timeout)) {
+                        Ok(status) => ProbeResult::Ok(status),
+                        Err(Outcome::TimedOut { .. }) => ProbeResult::TimedOut,
+                        Err(Outcome::Failed(err)) => ProbeResult::Failed(err),
+                        Err(other) => ProbeResult::Failed(other.label()),
+                    };
+                    let report = RepoReport {
+                        name: name.clone(),
+                        path: path.clone(),
+                        protected: *protected,
+                        result,
+                        elapsed: start.elapsed(),
+                    };
+                    reports.lock().expect("no worker panics while holding the lock")[i] = Some(report);
+                });
+            }
+        });
+        reports.into_inner().unwrap_or_default().into_iter().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GitCli, ProbeResult, RepoGroup, StatusProbe};
+    use crate::backend::{self, ExecOptions, Shell};
+    use crate::{Outcome, Repository};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    /// A probe that hangs, and counts how many of it run at once.
+    #[derive(Default)]
+    struct Hung {
+        running: AtomicUsize,
+        most: AtomicUsize,
+    }
+
+    impl StatusProbe for Hung {
+        fn status(&self, _path: &str, options: &ExecOptions) -> Result<String, Outcome> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(running, Ordering::SeqCst);
+            let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+            let status = backend::wait(&mut child, 1, options).unwrap();
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            status.map(|_| "woke".to_string())
+        }
+    }
+
+    #[test]
+    fn a_hung_probe_is_stopped_and_no_more_than_the_workers_run() {
+        let mut group = RepoGroup::new().with_workers(2).with_timeout(Duration::from_millis(50));
+        for i in 0..5 {
+            group.register(&Repository::protected(&format!("r{}", i), "/nowhere", 0));
+        }
+        let probe = Hung::default();
+        let start = Instant::now();
+        let reports = group.status_all(&probe);
+        assert!(start.elapsed() < Duration::from_secs(10), "{:?}", start.elapsed());
+        assert!(reports.iter().all(|report| report.result == ProbeResult::TimedOut));
+        assert_eq!(probe.most.load(Ordering::SeqCst), 2);
+        assert_eq!(probe.running.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn git_status_reports_the_branch_and_what_changed() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-fleet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let init = Command::new("git").arg("-C").arg(&dir).args(["init", "-q", "-b", "main"]).status().unwrap();
+        assert!(init.success());
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        let status = GitCli.status(dir.to_str().unwrap(), &ExecOptions::default()).unwrap();
+        assert!(status.contains("main") && status.ends_with("1 changed"), "{}", status);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn every_failed_probe_is_reported_with_why() {
+        let mut group = RepoGroup::new();
+        group.register(&Repository::protected_with("missing", "/nonexistent/safe-operations-fleet", 0, Shell::new()));
+        group.register_read_only(&Repository::open("gone", "/nonexistent/safe-operations-fleet-2", 0));
+        let reports = group.status_all(&GitCli);
+        assert_eq!(reports.len(), 2);
+        for report in &reports {
+            let ProbeResult::Failed(why) = &report.result else {
+                panic!("no repository at {}: {:?}", report.path, report.result);
+            };
+            assert!(why.contains(&report.path), "{}", why);
+            assert!(report.protected);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Guarded filesystem deletion.
+//!
+//! Consent to "delete the build directory" is not consent to delete
+//! 48,000 files because `$BUILD_DIR` was empty and the path resolved to
+//! `/`. Every deletion is measured first; the measurement is what the
+//! human approves, and the measurement is what gets deleted.
+
+use super::approver::Line;
+use super::{policy, ConsentDenied, OperationKind, SafetyGate, Severity, UserConsent};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Consent scope for recursive deletion.
+pub struct RecursiveDelete;
+
+/// What a recursive deletion would remove, computed before anyone is asked.
+///
+/// Only [`scan`](Self::scan) makes one outside the crate: the counts the
+/// approver reads are the counts of what is on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeletionManifest {
+    root: PathBuf,
+    files: u64,
+    bytes: u64,
+}
+
+impl DeletionManifest {
+    pub(crate) fn new(root: impl Into<PathBuf>, files: u64, bytes: u64) -> Self {
+        DeletionManifest {
+            root: root.into(),
+            files,
+            bytes,
+        }
+    }
+
+    /// Walk `root` and count what is under it. Read-only. Symlinks are
+    /// counted as links, never followed — deleting a tree does not
+    /// delete what its symlinks point at, so neither does counting.
+    pub fn scan(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        let (mut files, mut bytes) = (0, 0);
+        let mut pending = vec![root.clone()];
+        while let Some(path) = pending.pop() {
+            let meta = fs::symlink_metadata(&path)?;
+            if meta.is_dir() {
+                for entry in fs::read_dir(&path)? {
+                    pending.push(entry?.path());
+                }
+            } else {
+                files += 1;
+                bytes += meta.len();
+            }
+        }
+        Ok(DeletionManifest::new(root, files, bytes))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// The exact phrase an approver types to confirm an over-limit
+    /// deletion. Typing the numbers means reading the numbers.
+    pub fn confirmation_phrase(&self) -> String {
+        format!("delete {} files {} bytes", self.files, self.bytes)
+    }
+}
+
+/// Quantitative limits applied to every recursive deletion. The gate
+/// takes the counts from its policy; see
+/// [`Policy::deletion_limits`](crate::policy::Policy::deletion_limits).
+pub struct DeletionLimits {
+    pub max_files: u64,
+    pub max_bytes: u64,
+    /// Paths that can never be deleted, nor any ancestor of them.
+    pub forbidden_roots: Vec<PathBuf>,
+}
+
+impl Default for DeletionLimits {
+    fn default() -> Self {
+        let mut forbidden_roots = vec![PathBuf::from("/")];
+        if let Some(home) = std::env::var_os("HOME") {
+            forbidden_roots.push(PathBuf::from(home));
+        }
+        DeletionLimits {
+            max_files: policy::DEFAULT_DELETE_MAX_FILES,
+            max_bytes: policy::DEFAULT_DELETE_MAX_BYTES,
+            forbidden_roots,
+        }
+    }
+}
+
+/// How a manifest measures up against the limits.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    WithinLimits,
+    /// Allowed only at Critical severity, with the counts typed back.
+    OverLimits(Vec<String>),
+    /// Never allowed, whatever anyone approves.
+    Forbidden(String),
+}
+
+impl DeletionLimits {
+    pub fn evaluate(&self, manifest: &DeletionManifest) -> Verdict {
+        let root = normalize(&manifest.root);
+        for forbidden in &self.forbidden_roots {
+            let forbidden = normalize(forbidden);
+            if forbidden.starts_with(&root) {
+                return Verdict::Forbidden(format!(
+                    "{} is or contains forbidden root {}",
+                    root.display(),
+                    forbidden.display()
+                ));
+            }
+        }
+        let mut exceeded = Vec::new();
+        if manifest.files > self.max_files {
+            exceeded.push(format!("{} files exceeds limit of {}", manifest.files, self.max_files));
+        }
+        if manifest.bytes > self.max_bytes {
+            exceeded.push(format!("{} bytes exceeds limit of {}", manifest.bytes, self.max_bytes));
+        }
+        if exceeded.is_empty() {
+            Verdict::WithinLimits
+        } else {
+            Verdict::OverLimits(exceeded)
+        }
+    }
+}
+
+/// The path `path` names: canonical if it exists, so symlinks and `..`
+/// are resolved as the filesystem resolves them. Otherwise made absolute
+/// and resolved lexically, `.` dropped and `..` taking its parent.
+fn normalize(path: &Path) -> PathBuf {
+    use std::path::Component;
+    if let Ok(real) = fs::canonicalize(path) {
+        return real;
+    }
+    let absolute = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => std::env::current_dir().unwrap_or_default().join(path),
+    };
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// Consent to delete exactly one measured tree.
+pub struct DeletionApproval {
+    manifest: DeletionManifest,
+    pub severity: Severity,
+    consent: UserConsent<RecursiveDelete>,
+}
+
+impl SafetyGate {
+    /// Request consent to delete the tree described by `manifest`,
+    /// measured against the limits of the policy in force.
+    ///
+    /// Forbidden roots are refused outright. Over-limit deletions are
+    /// escalated to Critical, and the approver must type the manifest's
+    /// [`confirmation_phrase`](DeletionManifest::confirmation_phrase)
+    /// back before the consent challenge is even shown.
+    pub fn request_deletion_consent(&mut self, manifest: &DeletionManifest) -> Result<DeletionApproval, ConsentDenied> {
+        let operation = OperationKind::DeleteFiles;
+        let mut severity = operation.severity();
+        match self.policy.deletion_limits().evaluate(manifest) {
+            Verdict::Forbidden(reason) => {
+                self.log(format!("DENIED (forbidden root): {}", reason));
+                return Err(ConsentDenied::new(operation, reason));
+            }
+            Verdict::OverLimits(exceeded) => {
+                severity = Severity::Critical;
+                self.show_line(Line::Notice(format!("Escalated to {}: {}", severity.label(), exceeded.join("; "))));
+                if !self.confirm_phrase(&manifest.confirmation_phrase()) {
+                    let reason = "over-limit deletion not confirmed with exact counts".to_string();
+                    self.log(format!("DENIED: {} ({})", reason, manifest.root.display()));
+                    return Err(ConsentDenied::new(operation, reason));
+                }
+            }
+            Verdict::WithinLimits => {}
+        }
+        let consent = self.mint(
+            operation,
+            &format!(
+                "Delete {} ({} files, {} bytes) [{}]",
+                manifest.root.display(),
+                manifest.files,
+                manifest.bytes,
+                severity.label()
+            ),
+        )?;
+        Ok(DeletionApproval {
+            manifest: manifest.clone(),
+            severity,
+            consent,
+        })
+    }
+}
+
+/// Delete an approved tree.
+///
+/// The tree is scanned again immediately before deletion. If it is not
+/// what was approved — more files appeared, a path now resolves
+/// elsewhere — or the consent has expired or been revoked since, nothing
+/// is deleted. The demonstration reports the deletion instead of
+/// performing it.
+pub fn remove_tree(approval: DeletionApproval) -> Result<String, String> {
+    approval.consent.check_live().map_err(|denied| denied.reason)?;
+    let current = DeletionManifest::scan(&approval.manifest.root).map_err(|e| e.to_string())?;
+    if current != approval.manifest {
+        return Err(format!(
+            "{} changed since approval: approved {} files/{} bytes, found {} files/{} bytes",
+            current.root.display(),
+            approval.manifest.files,
+            approval.manifest.bytes,
+            current.files,
+            current.bytes
+        ));
+    }
+    Ok(format!(
+        "[{}] deleted {} files ({} bytes)",
+        current.root.display(),
+        current.files,
+        current.bytes
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{remove_tree, DeletionLimits, DeletionManifest, Verdict};
+    use crate::approver::{Prompt, Rehearsal, Responder};
+    use crate::{policy, SafetyGate};
+    use std::path::PathBuf;
+
+    struct Silent;
+
+    impl Responder for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+            None
+        }
+    }
+
+    /// A scratch tree of `files` one-byte files, removed on drop.
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(name: &str, files: usize) -> Tree {
+            let root = std::env::temp_dir().join(format!("safe-operations-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&root).unwrap();
+            for i in 0..files {
+                std::fs::write(root.join(format!("f{}", i)), "x").unwrap();
+            }
+            Tree(root)
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn gate(responder: Box<dyn Responder>, max_files: u64) -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(responder);
+        let text = format!("version = \"test\"\ndelete_max_files = \"{}\"\n", max_files);
+        gate.policy = policy::Policy::parse(&text).unwrap();
+        gate
+    }
+
+    #[test]
+    fn a_path_that_climbs_to_a_forbidden_root_is_forbidden() {
+        let limits = DeletionLimits {
+            forbidden_roots: vec![PathBuf::from("/srv/data")],
+            ..DeletionLimits::default()
+        };
+        let manifest = DeletionManifest::new("/srv/data/cache/../..", 1, 1);
+        assert!(matches!(limits.evaluate(&manifest), Verdict::Forbidden(_)));
+        let manifest = DeletionManifest::new("/srv/data/cache/./tmp", 1, 1);
+        assert_eq!(limits.evaluate(&manifest), Verdict::WithinLimits);
+    }
+
+    #[test]
+    fn an_over_limit_deletion_is_confirmed_by_the_approver_under_the_gate_policy() {
+        let tree = Tree::new("over-limit", 3);
+        let manifest = DeletionManifest::scan(&tree.0).unwrap();
+        assert!(gate(Box::new(Rehearsal), 10).request_deletion_consent(&manifest).is_ok());
+        let denied = gate(Box::new(Silent), 2).request_deletion_consent(&manifest).err().expect("over the policy's limit");
+        assert!(denied.reason.contains("exact counts"), "{}", denied.reason);
+        let Ok(approval) = gate(Box::new(Rehearsal), 2).request_deletion_consent(&manifest) else {
+            panic!("the counts were typed back");
+        };
+        assert_eq!(approval.severity, crate::Severity::Critical);
+    }
+
+    #[test]
+    fn a_tree_is_not_removed_once_its_consent_lapsed_or_it_changed() {
+        let tree = Tree::new("remove", 2);
+        let manifest = DeletionManifest::scan(&tree.0).unwrap();
+        let Ok(mut approval) = gate(Box::new(Rehearsal), 10).request_deletion_consent(&manifest) else {
+            panic!("within limits");
+        };
+        approval.consent._expires_at = 0;
+        assert!(remove_tree(approval).unwrap_err().contains("expired"));
+
+        let Ok(approval) = gate(Box::new(Rehearsal), 10).request_deletion_consent(&manifest) else {
+            panic!("within limits");
+        };
+        std::fs::write(tree.0.join("late"), "x").unwrap();
+        assert!(remove_tree(approval).unwrap_err().contains("changed since approval"));
+    }
+}
@@ -0,0 +1,1027 @@
+//! SafetyGate — the only source of UserConsent.
+
+use crate::{
+    agent_identity, airgap, alternatives, approver, backend, behavior, blocked, budget, ceremony,
+    consent_message, consent_queue, context, cooldown, dedup, delegation, ed25519, federation,
+    feedback, hardware_key, identity, is_ci_config, json_string, nonce_ledger, observe, persist, post_ops,
+    policy, provenance, quorum, random_u64, report, revocation, throttle, tiers, time_lock, totp, unix_now,
+    AgentManifest, AttributionChange, AuditLog, Capability, CiConfigChange, CommitBuilder,
+    ConsentDenied, FileChange, HostKey, ManifestError, OperationDescriptor, OperationKind,
+    Repository, Severity, Submodule, UserConsent,
+};
+use core::marker::PhantomData;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A point on the gate's failure paths where a fault can be injected.
+///
+/// Without the `chaos` feature no fault ever fires; the points mark where
+/// the gate must fail closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultPoint {
+    /// Writing the audit entry that records a grant.
+    AuditWrite,
+    /// Reaching the human approver.
+    ApprovalChannel,
+    /// Verifying a signature.
+    Signing,
+    /// Reading the wall clock.
+    Clock,
+}
+
+/// The enforcement mechanism. The only way to obtain `UserConsent`.
+///
+/// `request_consent` presents a random challenge and waits for the human
+/// to type it back. The point is architectural: consent is a capability
+/// that must be granted, not a flag that can be set.
+pub struct SafetyGate {
+    pub(crate) audit: AuditLog,
+    /// The policy every decision is made under.
+    pub(crate) policy: policy::Policy,
+    /// A candidate policy evaluated alongside the enforced one. It decides
+    /// nothing; the gate only logs what it would have decided.
+    shadow: Option<policy::Policy>,
+    /// The policy file being watched for hot-reload, and its last-seen mtime.
+    policy_file: Option<(PathBuf, Option<SystemTime>)>,
+    /// The key a reloaded policy file must be signed with.
+    policy_key: Option<HostKey>,
+    /// Where approval context comes from, if configured.
+    context: Option<Box<dyn context::ContextProvider>>,
+    /// The operations the session's agent manifest permits requesting.
+    /// `None` until a manifest is bound.
+    pub(crate) manifest: Option<(String, Vec<OperationKind>)>,
+    /// Every policy decision made this session, for what-if analysis.
+    pub(crate) session: policy::Session,
+    /// Recent keyed submissions, so retries coalesce instead of re-prompting.
+    pub(crate) dedup: dedup::Coalescer,
+    /// The terminal session being captured, if recording is on.
+    pub(crate) recording: Option<ceremony::Recording>,
+    /// This gate's identity and the foreign gates it trusts, if federated.
+    pub(crate) federation: Option<federation::Membership>,
+    /// How prompts are rendered for the approver at the console.
+    pub(crate) profile: approver::ApproverProfile,
+    /// Fault injection plan for chaos runs.
+    #[cfg(feature = "chaos")]
+    pub(crate) chaos: Option<crate::chaos::Chaos>,
+    /// Called with a structured payload whenever consent is denied.
+    pub(crate) on_denial: Option<feedback::DenialHook>,
+    /// The approver's reason for the decision about to be made.
+    reason: Option<String>,
+    /// The reason given for the most recent grant.
+    pub(crate) last_reason: Option<String>,
+    /// The organization's hard deny-list, consulted before anything else.
+    pub(crate) deny_list: Option<policy::DenyList>,
+    /// Who types back consent challenges.
+    pub(crate) responder: Box<dyn approver::Responder>,
+    /// A remote channel that already put the next decision to the approver.
+    pub(crate) answered_by: Option<String>,
+    /// The repository the next decision is for, bound into its consent.
+    pub(crate) target: Option<String>,
+    /// Whether granted consent executes for real. Only ever moves to observe.
+    pub(crate) mode: observe::GateMode,
+    /// Behavioral baselines requests are judged against, if enabled.
+    pub(crate) behavior: Option<behavior::Watch>,
+    /// Consent the approver has taken back, shared with every consent minted.
+    pub(crate) revocations: revocation::Registry,
+    /// Refused uses of consent, shared with every consent minted.
+    pub(crate) trail: blocked::Trail,
+    /// Nonces issued and spent, shared with every consent minted.
+    pub(crate) ledger: nonce_ledger::Ledger,
+    /// The approvers and rules for operations that need more than one.
+    pub(crate) quorum: Option<quorum::QuorumGate>,
+    /// The commit count of the repository the next decision is for.
+    pub(crate) target_commits: Option<usize>,
+    /// Signs every consent this gate mints.
+    pub(crate) signing_key: ed25519::SigningKey,
+    /// Whether the next decision's quorum has already approved it.
+    pub(crate) quorum_met: bool,
+    /// Requests waiting on an approver across an air gap.
+    pub(crate) airgap: airgap::Outbox,
+    /// The branch the next decision is for, if it is for one.
+    pub(crate) branch: Option<String>,
+    /// What the next decision is for, if the request described it.
+    pub(crate) descriptor: Option<OperationDescriptor>,
+    /// Uses of every delegation this gate has issued.
+    pub(crate) delegations: delegation::Ledger,
+    /// What each declared operation was meant to do and what became of it.
+    pub(crate) provenance: provenance::Provenance,
+    /// Approval state kept across restarts, and where it is kept.
+    pub(crate) pending: persist::Pending,
+    /// How many prompts of each severity the approver may be sent.
+    pub(crate) throttle: throttle::Throttle,
+    /// Repositories recently changed destructively, held to Critical.
+    pub(crate) cooldowns: Vec<cooldown::Cooldown>,
+    /// Where approver identities are resolved, if anywhere.
+    pub(crate) identity: Option<identity::Directory>,
+    /// How the approver of the next decision identifies themselves.
+    pub(crate) principal: Option<String>,
+    /// Approvers a channel already verified for the next decision.
+    pub(crate) verified: Vec<String>,
+    /// The verified approvers of the most recent grant.
+    pub(crate) last_approvers: Vec<String>,
+    /// Consent the approver granted up front for this session, if any.
+    pub(crate) budget: Option<budget::ConsentBudget>,
+    /// The security key that confirms approvals the policy says need one.
+    pub(crate) hardware_key: Option<hardware_key::HardwareConsentBackend>,
+    /// The authenticator whose codes answer challenges instead of echoes.
+    pub(crate) totp: Option<totp::TotpVerifier>,
+    /// Who hears about completed rewrites, shared with every consent minted.
+    pub(crate) downstream: post_ops::Subscribers,
+    /// Requests the agent queued for the approver to review together.
+    pub(crate) review: consent_queue::ReviewQueue,
+    /// How long consent to the operations it covers is held back.
+    pub(crate) time_lock: Option<time_lock::TimeLockGate>,
+    /// What consent takes at each severity.
+    pub(crate) tiers: Option<tiers::TierPolicy>,
+}
+
+impl Default for SafetyGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SafetyGate {
+    pub fn new() -> Self {
+        SafetyGate {
+            audit: AuditLog::new(),
+            policy: policy::Policy::builtin(),
+            shadow: None,
+            policy_file: None,
+            policy_key: None,
+            context: None,
+            manifest: None,
+            session: policy::Session::default(),
+            dedup: dedup::Coalescer::default(),
+            recording: None,
+            federation: None,
+            profile: approver::ApproverProfile::default(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+            on_denial: None,
+            reason: None,
+            last_reason: None,
+            deny_list: None,
+            responder: Box::new(approver::Console),
+            answered_by: None,
+            target: None,
+            mode: observe::GateMode::Enforce,
+            behavior: None,
+            revocations: revocation::Registry::default(),
+            trail: blocked::Trail::default(),
+            ledger: nonce_ledger::Ledger::default(),
+            quorum: None,
+            target_commits: None,
+            quorum_met: false,
+            signing_key: ed25519::SigningKey::generate(),
+            airgap: airgap::Outbox::default(),
+            branch: None,
+            descriptor: None,
+            delegations: delegation::Ledger::default(),
+            provenance: provenance::Provenance::default(),
+            pending: persist::Pending::default(),
+            throttle: throttle::Throttle::default(),
+            cooldowns: Vec::new(),
+            identity: None,
+            principal: None,
+            verified: Vec::new(),
+            last_approvers: Vec::new(),
+            budget: None,
+            hardware_key: None,
+            totp: None,
+            downstream: post_ops::Subscribers::default(),
+            review: consent_queue::ReviewQueue::default(),
+            time_lock: None,
+            tiers: None,
+        }
+    }
+
+    /// Request consent for a destructive operation.
+    ///
+    /// 1. Display what the operation does and what it affects
+    /// 2. Generate a random challenge token
+    /// 3. Wait for the user to type the token back
+    /// 4. Return `UserConsent` only if the token matches
+    ///
+    /// The user can say no instead, by typing `no: <why>`. That comes back
+    /// as a [`ConsentDenied`] naming them, with the time and their reason,
+    /// and is in the audit log before it is returned. Every other refusal
+    /// comes back the same way, attributed to the gate. There is no
+    /// `UserConsent` on that path to assume.
+    ///
+    /// An agent cannot skip this. An agent cannot call this and answer
+    /// its own challenge. The interactive input comes from the user's
+    /// terminal, not from the agent's reasoning.
+    ///
+    /// Before any of that, the enforced policy is consulted. An operation
+    /// the policy denies never reaches the human at all.
+    pub fn request_consent(
+        &mut self,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent, ConsentDenied> {
+        self.mint(operation, operation_description)
+    }
+
+    /// Whether an injected fault fires at `point`.
+    pub(crate) fn fault(&mut self, point: FaultPoint) -> bool {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &mut self.chaos {
+            return chaos.fire(point);
+        }
+        let _ = point;
+        false
+    }
+
+    /// The gate's wall clock, in seconds since the Unix epoch.
+    pub(crate) fn clock(&mut self) -> u64 {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &mut self.chaos {
+            if chaos.fire(FaultPoint::Clock) {
+                return unix_now().saturating_add_signed(chaos.skew());
+            }
+        }
+        unix_now()
+    }
+
+    /// Request consent with the repository's own context in the prompt.
+    ///
+    /// The configured [`context::ContextProvider`] is asked about `repo` and
+    /// the affected `paths`, and what it reports — recent commits, open
+    /// PRs, the last deploy, code owners — is shown above the request. The
+    /// approver judges the operation against the repository, not against
+    /// the agent's description of the repository.
+    ///
+    /// The consent is bound to `repo`: the repository methods refuse it
+    /// anywhere else.
+    pub fn request_consent_for<S, B: backend::GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        paths: &[&str],
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent, ConsentDenied> {
+        self.bind_request(repo, paths, operation, None, operation_description)?;
+        if operation == OperationKind::ForcePush {
+            // A push that names no branch is to the repository's default.
+            self.branch = Some(repo.default_branch.clone());
+        }
+        self.mint(operation, operation_description)
+    }
+
+    /// Request consent for exactly `descriptor`, on `repo`.
+    ///
+    /// The agent does not describe the operation; the gate renders the
+    /// prompt from the descriptor, and the consent carries it. The
+    /// repository methods refuse it for anything else: another branch,
+    /// another callback, another repository.
+    pub fn request_consent_to<S, B: backend::GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        descriptor: OperationDescriptor,
+    ) -> Result<UserConsent, ConsentDenied> {
+        let operation = descriptor.kind();
+        let description = descriptor.render();
+        if descriptor.repo() != repo.path && descriptor.repo() != repo.name {
+            self.log(format!("DENIED (descriptor names another repository): {}", description));
+            let denied = ConsentDenied::new(
+                operation,
+                format!("the descriptor is for '{}', not '{}'", descriptor.repo(), repo.path),
+            );
+            self.notify_denial_for(&denied, Some(repo.path.clone()));
+            return Err(denied);
+        }
+        self.bind_request(repo, &[], operation, descriptor.branch(), &description)?;
+        self.branch = descriptor.branch().map(str::to_string);
+        self.descriptor = Some(descriptor);
+        self.mint(operation, &description)
+    }
+
+    /// Screen a request on `repo` and bind the next decision to it,
+    /// showing the approver the repository's context, and what the
+    /// operation would destroy there, on the way.
+    fn bind_request<S, B: backend::GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        paths: &[&str],
+        operation: OperationKind,
+        branch: Option<&str>,
+        operation_description: &str,
+    ) -> Result<(), ConsentDenied> {
+        self.screen_forbidden(operation, Some(&repo.name), operation_description)?;
+        if let Some(provider) = self.context.as_ref().filter(|_| self.profile.verbosity != approver::Verbosity::Brief) {
+            let rendered = provider.approval_context(&repo.name, &repo.path, paths).render();
+            self.show_line(approver::Line::Notice(format!("Context for '{}':", repo.name)));
+            for line in rendered.lines() {
+                self.show_line(approver::Line::Detail(line.to_string()));
+            }
+        }
+        self.show_impact(repo, operation, branch);
+        self.target = Some(repo.path.clone());
+        // Counted by the backend, not read off the repository the caller
+        // hands over: a history that cannot be counted is sized as large.
+        self.target_commits = repo.commit_count();
+        Ok(())
+    }
+
+    /// Show what `operation` would destroy in `repo`, if anything, as the
+    /// repository itself counts it.
+    pub(crate) fn show_impact<S, B>(&mut self, repo: &Repository<S, B>, operation: OperationKind, branch: Option<&str>) {
+        let impact = repo.preview_impact(operation);
+        let impact = match branch {
+            Some(branch) => impact.for_branch(branch),
+            None => impact,
+        };
+        if impact.is_empty() {
+            return;
+        }
+        self.show_line(approver::Line::Heading(format!("This will destroy, in '{}':", repo.name)));
+        for line in impact.lines() {
+            self.show_line(approver::Line::Item(line));
+        }
+    }
+
+    /// Bind the session's agent manifest. From now on, a request for any
+    /// operation the manifest does not list is refused before a human is
+    /// asked — a human approving it would not change the answer.
+    ///
+    /// A gate binds one manifest for its lifetime. Widening an agent's
+    /// permissions is a host-side change: sign a new manifest, start a new
+    /// session. It is never something the running session can do.
+    pub fn bind_manifest(&mut self, manifest: &AgentManifest, key: &HostKey) -> Result<(), ManifestError> {
+        if self.manifest.is_some() {
+            return Err(ManifestError::AlreadyBound);
+        }
+        if self.fault(FaultPoint::Signing) || !manifest.verify(key) {
+            self.log(format!("MANIFEST REJECTED: bad signature for agent '{}'", manifest.agent));
+            return Err(ManifestError::BadSignature);
+        }
+        let permitted: Vec<OperationKind> = manifest
+            .capabilities
+            .iter()
+            .filter_map(|c| match c {
+                Capability::Request(kind) => Some(*kind),
+                _ => None,
+            })
+            .collect();
+        self.log(format!(
+            "MANIFEST BOUND: agent '{}' may request [{}]",
+            manifest.agent,
+            permitted.iter().map(|k| k.name()).collect::<Vec<_>>().join(", ")
+        ));
+        self.manifest = Some((manifest.agent.clone(), permitted));
+        Ok(())
+    }
+
+    /// Configure where approval context comes from.
+    pub fn set_context_provider(&mut self, provider: Box<dyn context::ContextProvider>) {
+        self.context = Some(provider);
+    }
+
+    /// Request consent to change CI/CD pipeline files.
+    ///
+    /// The approver is shown the diff of every pipeline file in `changes`,
+    /// not the agent's summary of it. A one-line "bump action version" can
+    /// also add `curl | sh` with `${{ secrets.DEPLOY_KEY }}` in scope.
+    pub fn request_ci_consent(
+        &mut self,
+        changes: &[FileChange],
+    ) -> Result<UserConsent<CiConfigChange>, ConsentDenied> {
+        let ci: Vec<&FileChange> = changes.iter().filter(|c| is_ci_config(&c.path)).collect();
+        let paths: Vec<&str> = ci.iter().map(|c| c.path.as_str()).collect();
+        let description = format!("Modify CI/CD pipeline files: {}", paths.join(", "));
+        self.screen_forbidden(OperationKind::CiConfigChange, None, &description)?;
+        self.show_line(approver::Line::Notice(format!(
+            "{} severity: CI/CD pipeline change ({} files)",
+            OperationKind::CiConfigChange.severity().label(),
+            ci.len()
+        )));
+        for change in &ci {
+            self.show_line(approver::Line::File(change.path.clone()));
+            for line in change.diff.lines() {
+                self.show_line(approver::Line::Diff(line.to_string()));
+            }
+        }
+        self.mint(OperationKind::CiConfigChange, &description)
+    }
+
+    /// Request consent for `commit` to drop the attribution it drops.
+    ///
+    /// The approver is shown each `Co-Authored-By` line that would go, not
+    /// the agent's account of why, and the consent covers exactly those
+    /// lines on that repository.
+    pub fn request_attribution_consent<B: backend::GitBackend>(
+        &mut self,
+        commit: &CommitBuilder<B>,
+    ) -> Result<UserConsent<AttributionChange>, ConsentDenied> {
+        let descriptor = commit.strip_descriptor();
+        let description = descriptor.render();
+        self.bind_request(commit.repo, &[], OperationKind::StripAttribution, None, &description)?;
+        self.show_line(approver::Line::Notice(format!(
+            "{} severity: removing {} Co-Authored-By trailer(s) from a commit",
+            OperationKind::StripAttribution.severity().label(),
+            commit.stripped().len()
+        )));
+        for trailer in commit.stripped() {
+            self.show_line(approver::Line::Diff(format!("-{}", trailer.render())));
+        }
+        self.descriptor = Some(descriptor);
+        self.mint(OperationKind::StripAttribution, &description)
+    }
+
+    /// Request consent for exactly `descriptor`, on `submodule`. The
+    /// descriptor names the submodule's path or checkout: consent bound to
+    /// its superproject covers nothing in it.
+    pub fn request_submodule_consent<S>(
+        &mut self,
+        submodule: &Submodule<S>,
+        descriptor: OperationDescriptor,
+    ) -> Result<UserConsent, ConsentDenied> {
+        self.request_consent_to(&submodule.as_repository(), descriptor)
+    }
+
+    /// The single path by which consent of any scope comes into existence.
+    pub(crate) fn mint<Scope>(
+        &mut self,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent<Scope>, ConsentDenied> {
+        let target = self.target.clone();
+        let minted = self.decide_and_mint(operation, operation_description);
+        match &minted {
+            Ok(consent) => {
+                self.provenance.note_grant(consent, self.last_reason.as_deref());
+                self.start_cooldown(consent);
+            }
+            Err(denied) => self.notify_denial_for(denied, target),
+        }
+        minted
+    }
+
+    /// Record a denial of a request for the gate's current target, link
+    /// it to the open operation, and hand it to the `on_denial` hook, if
+    /// one is set.
+    pub(crate) fn notify_denial(&mut self, denied: &ConsentDenied) {
+        self.notify_denial_for(denied, self.target.clone());
+    }
+
+    /// [`notify_denial`](Self::notify_denial) for a request for `target`.
+    pub(crate) fn notify_denial_for(&mut self, denied: &ConsentDenied, target: Option<String>) {
+        self.log_denied(denied, target);
+        self.provenance.note_denial(denied);
+        for alternative in alternatives::for_operation(denied.operation) {
+            self.show_line(approver::Line::Detail(format!("Instead: {}", alternative.render())));
+        }
+        if self.on_denial.is_some() {
+            let payload = self.denial_feedback(denied);
+            if let Some(hook) = &mut self.on_denial {
+                hook(&payload);
+            }
+        }
+    }
+
+    /// Refuse an operation the deny-list forbids. Nothing the gate can be
+    /// told — policy, manifest, a grant from another gate, an approver's
+    /// yes — is consulted first.
+    fn check_forbidden(
+        &mut self,
+        operation: OperationKind,
+        target: Option<&str>,
+        operation_description: &str,
+    ) -> Result<(), ConsentDenied> {
+        let Some(list) = &self.deny_list else {
+            return Ok(());
+        };
+        let Some(entry) = list.forbids(operation, target, operation_description) else {
+            return Ok(());
+        };
+        let reason = format!(
+            "forbidden by deny-list {}: {} on '{}' ({}); no consent can unlock it",
+            list.version,
+            operation.name(),
+            entry.target,
+            entry.why
+        );
+        self.log(format!("DENIED BY DENY-LIST: {}", operation_description));
+        Err(ConsentDenied::new(operation, reason))
+    }
+
+    /// [`check_forbidden`](Self::check_forbidden) ahead of a prompt, so a
+    /// forbidden operation is never put in front of the approver.
+    pub(crate) fn screen_forbidden(
+        &mut self,
+        operation: OperationKind,
+        target: Option<&str>,
+        operation_description: &str,
+    ) -> Result<(), ConsentDenied> {
+        let screened = self.check_forbidden(operation, target, operation_description);
+        if let Err(denied) = &screened {
+            self.notify_denial(denied);
+        }
+        screened
+    }
+
+    /// Install the organization's deny-list, signed with `key`. A list
+    /// already in place is only replaced by a newer one from the same
+    /// signer; a policy reload never touches it.
+    pub fn install_deny_list(&mut self, text: &str, key: &HostKey) -> Result<(), policy::PolicyError> {
+        let next = match policy::DenyList::parse(text, key) {
+            Ok(next) => next,
+            Err(err) => {
+                self.log(format!("DENY-LIST REJECTED: {}", err));
+                return Err(err);
+            }
+        };
+        if let Some(current) = &self.deny_list {
+            if let Err(message) = current.may_be_replaced_by(&next) {
+                self.log(format!("DENY-LIST REJECTED: {}", message));
+                return Err(policy::PolicyError { line: 0, message });
+            }
+        }
+        let event = match &self.deny_list {
+            Some(current) => format!("DENY-LIST REPLACED: {} -> {}", current.version, next.version),
+            None => format!("DENY-LIST INSTALLED: {} ({} entries)", next.version, next.entries.len()),
+        };
+        self.deny_list = Some(next);
+        self.log(event);
+        Ok(())
+    }
+
+    fn decide_and_mint<Scope>(
+        &mut self,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent<Scope>, ConsentDenied> {
+        // A reason is given for one decision; it never carries over.
+        let reason = self.reason.take().filter(|r| !r.trim().is_empty());
+        let answered_by = self.answered_by.take();
+        let target = self.target.take();
+        let branch = self.branch.take();
+        let target_commits = self.target_commits.take();
+        let quorum_met = std::mem::take(&mut self.quorum_met);
+        let principal = self.principal.take();
+        let described = self.descriptor.take();
+        let explicit = described.is_some();
+        let mut approvers = std::mem::take(&mut self.verified);
+        self.last_reason = None;
+        self.last_approvers.clear();
+        self.check_forbidden(operation, None, operation_description)?;
+        self.poll_policy();
+        if let Some((agent, permitted)) = &self.manifest {
+            if !permitted.contains(&operation) {
+                let reason = format!("{} is outside the manifest for agent '{}'", operation.name(), agent);
+                self.log(format!("DENIED BY MANIFEST: {}", operation_description));
+                return Err(ConsentDenied::new(operation, reason));
+            }
+        }
+        let agent = self.manifest.as_ref().map_or("unbound", |(agent, _)| agent.as_str()).to_string();
+        let deviations = self.assess_behavior(&agent, operation, target.as_deref(), unix_now());
+        self.log(behavior::request_event(operation, target.as_deref(), &agent));
+        let decision = self.policy.decide(operation);
+        if let Some(shadow) = &self.shadow {
+            let would = shadow.decide(operation);
+            let event = format!(
+                "SHADOW (policy {}): would {} {} (enforced: {})",
+                shadow.version,
+                would.label(),
+                operation.name(),
+                decision.label()
+            );
+            self.log(event);
+        }
+        self.session.decisions.push(policy::RecordedDecision {
+            operation,
+            description: operation_description.to_string(),
+            decision,
+            reason: reason.clone(),
+        });
+        if decision == policy::Decision::Deny {
+            self.log(format!("DENIED BY POLICY: {}", operation_description));
+            return Err(ConsentDenied::new(
+                operation,
+                format!("policy {} denies {}", self.policy.version, operation.name()),
+            ));
+        }
+        if decision == policy::Decision::RequireConsent && !quorum_met {
+            if let Some(rule) = self.quorum_rule(operation, target_commits) {
+                self.log(format!("DENIED (quorum required): {}", operation_description));
+                return Err(ConsentDenied::new(
+                    operation,
+                    format!(
+                        "{}{} needs {} approvers; request it with request_quorum_consent",
+                        operation.name(),
+                        match rule.min_commits {
+                            0 => String::new(),
+                            n => format!(" on repositories of {}+ commits", n),
+                        },
+                        rule.required
+                    ),
+                ));
+            }
+        }
+        if decision == policy::Decision::RequireConsent && self.fault(FaultPoint::ApprovalChannel) {
+            self.log(format!("DENIED (approval channel failed): {}", operation_description));
+            return Err(ConsentDenied::new(operation, "approval channel failed; no answer is not a yes".to_string()));
+        }
+        let (severity, cooling) = self.severity_for(operation, target.as_deref(), target_commits);
+        if let Some(cooling) = cooling.as_ref().filter(|_| decision == policy::Decision::RequireConsent) {
+            self.log(format!(
+                "ESCALATED to {} (cooldown after {} on '{}'): {}",
+                severity.label(),
+                cooling.after.name(),
+                cooling.target,
+                operation_description
+            ));
+        }
+        if decision == policy::Decision::RequireConsent && reason.is_none() && self.policy.requires_reason(severity) {
+            self.log(format!("DENIED (no reason given): {}", operation_description));
+            return Err(ConsentDenied::new(
+                operation,
+                format!(
+                    "policy {} requires the approver's reason for {} operations",
+                    self.policy.version,
+                    severity.label()
+                ),
+            ));
+        }
+        // A budget debit stands in for the challenge, and is not a prompt.
+        let answered_by = match answered_by {
+            None if decision == policy::Decision::RequireConsent => {
+                self.debit_budget(operation, target.as_deref(), branch.as_deref(), operation_description)?
+            }
+            answered_by => answered_by,
+        };
+        if decision == policy::Decision::RequireConsent && answered_by.is_none() {
+            self.check_throttle(operation, severity, operation_description)?;
+        }
+        let descriptor = described.or_else(|| {
+            let target = target.as_deref()?;
+            OperationDescriptor::implied(operation, target, branch.as_deref())
+        });
+        let nonce = random_u64();
+        if decision == policy::Decision::RequireConsent {
+            if let Some(cooling) = &cooling {
+                self.show_line(approver::Line::Warning(format!(
+                    "'{}' is cooling down after {} until {}; this request is held to {} severity",
+                    cooling.target,
+                    cooling.after.name(),
+                    report::format_utc(cooling.until),
+                    severity.label()
+                )));
+            }
+            for deviation in deviations {
+                self.show_line(approver::Line::Warning(format!("Unusual for agent '{}': {}", agent, deviation)));
+            }
+            // A free-form request is shown as the agent worded it, so the
+            // gate says what it will actually permit alongside.
+            if let Some(implied) = descriptor.as_ref().filter(|_| !explicit && answered_by.is_none()) {
+                self.show_line(approver::Line::Detail(format!("This permits: {}", implied.render())));
+            }
+            match answered_by {
+                Some(channel) => self.log(format!("CHALLENGE ANSWERED via {}: {}", channel, operation_description)),
+                None => {
+                    match self.verify_approver(principal.as_deref()) {
+                        Ok(identity) => approvers.extend(identity.map(|i| i.verified_subject())),
+                        Err(why) => {
+                            self.log(format!("DENIED (approver not verified): {}", operation_description));
+                            return Err(ConsentDenied::new(operation, why));
+                        }
+                    }
+                    let approver = match approvers.last() {
+                        Some(subject) => subject.clone(),
+                        None => self.approver_name(),
+                    };
+                    self.challenge(severity, operation, operation_description, nonce, &approver)?
+                }
+            }
+        }
+        if decision == policy::Decision::RequireConsent {
+            self.confirm_on_hardware_key(operation, severity, operation_description, target.as_deref(), nonce)?;
+        }
+        // Consent that was never recorded must not exist: if the audit
+        // write fails, so does the grant.
+        if self.fault(FaultPoint::AuditWrite) {
+            return Err(ConsentDenied::new(operation, "audit sink unavailable".to_string()));
+        }
+        // Operation and target first, before anything the requester
+        // worded, so an auditor reads them as fields, not as a search.
+        let granted = match target.as_deref() {
+            Some(target) => format!("GRANTED {} on {}", operation.name(), json_string(target)),
+            None => format!("GRANTED {}", operation.name()),
+        };
+        match &reason {
+            Some(why) => self.log(format!("{}: {} (reason: {})", granted, operation_description, why)),
+            None => self.log(format!("{}: {}", granted, operation_description)),
+        }
+        self.last_reason = reason;
+        self.last_approvers = approvers;
+        if self.profile.verbosity == approver::Verbosity::Verbose {
+            self.show_line(approver::Line::Detail(format!(
+                "Severity: {}. Safer alternative: {}.",
+                severity.label(),
+                operation.safe_alternative()
+            )));
+        }
+        self.show_line(approver::Line::Notice(format!("User consented to: {}", operation_description)));
+        let agent = agent_identity::current();
+        if let Some(agent) = &agent {
+            self.log(format!("CONSENT BOUND: {} to {}", operation_description, agent.render()));
+        }
+        let issued_at = unix_now();
+        let not_before = self.time_lock(operation, operation_description, nonce, issued_at);
+        self.ledger.register(nonce);
+        Ok(UserConsent {
+            _operation: operation_description.to_string(),
+            _nonce: nonce,
+            _issued_at: issued_at,
+            _signature: self.signing_key.sign(&consent_message(
+                operation,
+                target.as_deref(),
+                branch.as_deref(),
+                descriptor.as_ref(),
+                agent.as_ref().map(|agent| agent.encode()).as_deref(),
+                issued_at,
+                nonce,
+            )),
+            _kind: operation,
+            _target: target,
+            _branch: branch,
+            _descriptor: descriptor,
+            _observe: self.mode == observe::GateMode::Observe,
+            _expires_at: issued_at.max(not_before).saturating_add(self.policy.consent_ttl()),
+            _not_before: not_before,
+            _revocations: self.revocations.clone(),
+            _agent: agent.map(Box::new),
+            _trail: self.trail.clone(),
+            _ledger: self.ledger.clone(),
+            _downstream: self.downstream.clone(),
+            _scope: PhantomData,
+        })
+    }
+
+    /// Show a fresh challenge derived from `nonce` and require the
+    /// responder to type it back. The agent never sees the challenge
+    /// before it is shown, so it cannot answer ahead of the human. With an
+    /// authenticator configured, the responder types its current code
+    /// instead; see [`totp`].
+    ///
+    /// The approver can also say no, with `no: <why>`. That is a refusal
+    /// by them, recorded as theirs, not a failed challenge.
+    fn challenge(
+        &mut self,
+        severity: Severity,
+        operation: OperationKind,
+        operation_description: &str,
+        nonce: u64,
+        approver: &str,
+    ) -> Result<(), ConsentDenied> {
+        if self.totp.is_some() {
+            return self.totp_challenge(severity, operation, operation_description, approver);
+        }
+        // A Low-tier prompt needs only a yes; everything else, the token.
+        let challenge = match self.ceremony(severity) {
+            tiers::Ceremony::Prompt => "yes".to_string(),
+            _ => format!("{:08x}", nonce as u32),
+        };
+        self.show_line(approver::Line::Notice(format!(
+            "{} severity: {}",
+            severity.label(),
+            operation_description
+        )));
+        self.show_line(approver::Line::Challenge(challenge.clone()));
+        self.show_line(approver::Line::Detail("To refuse, type: no: <why>".to_string()));
+        let typed = self.responder.answer(&approver::Prompt::phrase(&challenge));
+        self.capture(&format!("> {}", typed.as_deref().unwrap_or("").trim_end()));
+        if typed.as_deref().map(str::trim) == Some(challenge.as_str()) {
+            return Ok(());
+        }
+        if let Some(why) = typed.as_deref().and_then(approver::refusal) {
+            self.log(format!("DENIED BY APPROVER {}: {} ({})", approver, operation_description, why));
+            return Err(ConsentDenied::new(operation, format!("the approver declined: {}", why)).by_approver(approver));
+        }
+        let reason = match typed {
+            None => "no response to the challenge; no answer is not a yes",
+            Some(_) => "response did not match the challenge",
+        };
+        self.log(format!("DENIED (challenge failed): {}", operation_description));
+        Err(ConsentDenied::new(operation, reason.to_string()))
+    }
+
+    /// Show `phrase` and require the responder to type it back exactly.
+    /// For what a decision takes beyond the consent challenge: the counts
+    /// of an over-limit deletion, the acknowledgment of a loss. The
+    /// caller's code never sees what was typed.
+    pub(crate) fn confirm_phrase(&mut self, phrase: &str) -> bool {
+        self.show_line(approver::Line::Challenge(phrase.to_string()));
+        let typed = self.responder.answer(&approver::Prompt::phrase(phrase));
+        self.capture(&format!("> {}", typed.as_deref().unwrap_or("").trim_end()));
+        typed.as_deref().map(str::trim) == Some(phrase)
+    }
+
+    /// Who is answering challenges, as the gate knows them: the approver
+    /// profile's name, or else the responder's.
+    fn approver_name(&self) -> String {
+        match self.profile.name.is_empty() {
+            true => self.responder.name().to_string(),
+            false => self.profile.name.clone(),
+        }
+    }
+
+    /// Who types the answer to consent challenges. The default is the
+    /// terminal. Not public: whoever holds the gate must not be able to
+    /// put something in front of it that answers for the approver.
+    pub(crate) fn set_responder(&mut self, responder: Box<dyn approver::Responder>) {
+        self.log(format!("RESPONDER: {}", responder.name()));
+        self.responder = responder;
+    }
+
+    /// The approver's reason for the next decision. Every approval channel
+    /// passes along what the approver wrote; a policy with
+    /// `require_reason` refuses consent at that severity without one.
+    pub fn give_reason(&mut self, reason: &str) {
+        self.reason = Some(reason.to_string());
+    }
+
+    /// The reason given for the most recent grant, for its receipt.
+    pub fn approval_reason(&self) -> Option<&str> {
+        self.last_reason.as_deref()
+    }
+
+    /// Record an event stamped with the enforced policy version.
+    pub(crate) fn log(&mut self, event: String) {
+        self.collect_blocked();
+        self.append(unix_now(), event, None);
+    }
+
+    pub(crate) fn append(&mut self, at: u64, event: String, blocked: Option<blocked::Blocked>) {
+        let version = self.policy.version.clone();
+        let event = match self.mode {
+            observe::GateMode::Enforce => event,
+            observe::GateMode::Observe => format!("{} [observe]", event),
+        };
+        self.audit.record(at, event, &version, blocked);
+    }
+
+    /// The policy decisions recorded this session.
+    pub fn session(&self) -> &policy::Session {
+        &self.session
+    }
+
+    /// The version of the policy currently being enforced.
+    pub fn policy_version(&self) -> &str {
+        &self.policy.version
+    }
+
+    /// Watch a policy file signed with `key`. It is loaded now and re-read
+    /// whenever its modification time changes, without restarting the gate.
+    pub fn watch_policy(&mut self, path: impl Into<PathBuf>, key: &HostKey) -> Result<(), policy::PolicyError> {
+        let path = path.into();
+        let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        self.policy_key = Some(key.clone());
+        self.reload_policy(&path)?;
+        self.policy_file = Some((path, mtime));
+        Ok(())
+    }
+
+    /// Reload the watched policy file if it changed since the last check.
+    ///
+    /// Called before every decision. A file that fails validation, or is
+    /// not signed with the watched key, is logged and ignored: the gate
+    /// keeps enforcing the last good policy rather than running with none.
+    pub fn poll_policy(&mut self) {
+        let Some((path, seen)) = &self.policy_file else {
+            return;
+        };
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if mtime == *seen {
+            return;
+        }
+        let path = path.clone();
+        let _ = self.reload_policy(&path);
+        self.policy_file = Some((path, mtime));
+    }
+
+    /// Validate a policy file and, only if it is valid and signed with the
+    /// watched key, swap it in.
+    pub(crate) fn reload_policy(&mut self, path: &Path) -> Result<(), policy::PolicyError> {
+        let loaded = match &self.policy_key {
+            Some(key) => policy::Policy::load_signed(path, key),
+            None => Err(policy::PolicyError {
+                line: 0,
+                message: "no key to verify the policy against".to_string(),
+            }),
+        };
+        match loaded {
+            Ok(next) => {
+                let event = format!("POLICY RELOADED: {} -> {}", self.policy.version, next.version);
+                self.policy = next;
+                self.log(event);
+                Ok(())
+            }
+            Err(err) => {
+                self.log(format!("POLICY RELOAD REJECTED ({}): {}", path.display(), err));
+                Err(err)
+            }
+        }
+    }
+
+    /// Enforce the policy `repo` inherits through `tree`, whose org file
+    /// must be signed with `key`. As with a reload, a hierarchy that does
+    /// not resolve leaves the current policy in force.
+    pub fn resolve_policy<S, B>(
+        &mut self,
+        tree: &policy::PolicyTree,
+        repo: &Repository<S, B>,
+        key: &HostKey,
+    ) -> Result<policy::Effective, policy::PolicyError> {
+        match tree.signed_policy(repo, key) {
+            Ok(effective) => {
+                let event = format!("POLICY RESOLVED for '{}': {} -> {}", repo.name, self.policy.version, effective.policy.version);
+                self.policy = effective.policy.clone();
+                self.log(event);
+                Ok(effective)
+            }
+            Err(err) => {
+                self.log(format!("POLICY RESOLUTION REJECTED for '{}': {}", repo.name, err));
+                Err(err)
+            }
+        }
+    }
+
+    /// Run a candidate policy, signed with `key`, in shadow mode: every
+    /// request is evaluated against it and the would-be decision logged,
+    /// but not enforced.
+    pub fn propose_policy(&mut self, text: &str, key: &HostKey) -> Result<(), policy::PolicyError> {
+        match policy::Policy::parse_signed(text, key) {
+            Ok(candidate) => {
+                self.set_shadow_policy(candidate);
+                Ok(())
+            }
+            Err(err) => {
+                self.log(format!("SHADOW POLICY REJECTED: {}", err));
+                Err(err)
+            }
+        }
+    }
+
+    /// Enforce the proposed policy, after its log has been reviewed. Only
+    /// the key that signed the proposal can promote it.
+    pub fn promote_proposed_policy(&mut self, key: &HostKey) -> bool {
+        match &self.shadow {
+            Some(candidate) if candidate.signed_by(key) => self.promote_shadow_policy(),
+            Some(candidate) => {
+                let event = format!("SHADOW POLICY NOT PROMOTED: {} was signed by another key", candidate.version);
+                self.log(event);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn set_shadow_policy(&mut self, candidate: policy::Policy) {
+        self.log(format!("SHADOW POLICY SET: {}", candidate.version));
+        self.shadow = Some(candidate);
+    }
+
+    pub(crate) fn promote_shadow_policy(&mut self) -> bool {
+        match self.shadow.take() {
+            Some(candidate) => {
+                let event = format!("SHADOW POLICY PROMOTED: {} -> {}", self.policy.version, candidate.version);
+                self.policy = candidate;
+                self.log(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The key consent minted by this gate verifies against. Publish it to
+    /// whatever checks consent downstream.
+    pub fn consent_key(&self) -> ed25519::PublicKey {
+        self.signing_key.public_key()
+    }
+
+    /// Sign consent with `key` from now on, instead of the key generated
+    /// with the gate. For a deployment whose verifiers pin a public key.
+    pub fn set_signing_key(&mut self, key: ed25519::SigningKey) {
+        self.log(format!("SIGNING KEY: consent now verifies against {}", key.public_key().to_hex()));
+        self.signing_key = key;
+    }
+
+    /// The gate's audit log. Read-only: nothing outside the gate can append.
+    pub fn audit_log(&mut self) -> &AuditLog {
+        self.collect_blocked();
+        &self.audit
+    }
+
+    /// Print the full consent audit trail.
+    pub fn print_audit_log(&mut self) {
+        self.collect_blocked();
+        println!();
+        println!("  Consent audit trail ({} entries):", self.audit.len());
+        for (i, entry) in self.audit.entries().iter().enumerate() {
+            println!("    {}. {}", i + 1, entry.event);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! FIDO2 confirmation for the operations that need more than typed text.
+//!
+//! A challenge typed back proves someone at the terminal read it — unless
+//! the terminal is the compromised part, in which case it proves nothing.
+//! For operations the policy lists under `require_hardware_key`, approval
+//! is also confirmed by a touch on a security key registered to the
+//! approver. The gate asks the key for a CTAP2 assertion over a hash of
+//! the operation and that decision's nonce, then checks it: the
+//! relying-party hash, the user-presence flag (and user verification, if
+//! required), a signature counter that only goes up, and the signature,
+//! under the credential's public key.
+//!
+//! Credentials must be EdDSA (COSE algorithm -8, `fido2-cred -M ... eddsa`)
+//! so the signature is checked with the gate's own [`ed25519`](super::ed25519).
+//! [`Fido2Tool`] talks to the key through libfido2's `fido2-assert`.
+//!
+//! The touch is required however the rest of the decision was answered —
+//! at the console, over a channel, from a delegation or budget — since
+//! each of those is text something other than the approver could produce.
+
+use super::consent_client::SourceError;
+use super::ed25519::{sha512, PublicKey, Signature};
+use super::{approver, tiers, ConsentDenied, OperationKind, SafetyGate, Severity};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// User present: the key was touched.
+const FLAG_UP: u8 = 0x01;
+/// User verified: a PIN or biometric was checked by the key.
+const FLAG_UV: u8 = 0x04;
+
+/// What a key returns for one assertion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assertion {
+    /// rpIdHash, flags, and signature counter, at least.
+    pub authenticator_data: Vec<u8>,
+    pub signature: Signature,
+}
+
+/// Something that can get an assertion from a security key.
+pub trait Authenticator {
+    fn name(&self) -> String;
+    /// Ask the key to sign `client_data_hash` with credential
+    /// `credential_id` for `rp_id`. Blocks until the key is touched or
+    /// gives up waiting.
+    fn get_assertion(
+        &mut self,
+        rp_id: &str,
+        client_data_hash: &[u8; 32],
+        credential_id: &[u8],
+        user_verification: bool,
+    ) -> Result<Assertion, SourceError>;
+}
+
+/// A key at `device`, through `fido2-assert -G`.
+pub struct Fido2Tool {
+    pub device: String,
+}
+
+impl Fido2Tool {
+    pub fn new(device: &str) -> Self {
+        Fido2Tool {
+            device: device.to_string(),
+        }
+    }
+}
+
+impl Authenticator for Fido2Tool {
+    fn name(&self) -> String {
+        format!("fido2-assert {}", self.device)
+    }
+
+    fn get_assertion(
+        &mut self,
+        rp_id: &str,
+        client_data_hash: &[u8; 32],
+        credential_id: &[u8],
+        user_verification: bool,
+    ) -> Result<Assertion, SourceError> {
+        let mut command = Command::new("fido2-assert");
+        command.args(["-G", "-t", "up=true"]);
+        if user_verification {
+            command.args(["-t", "uv=true"]);
+        }
+        let mut child = command
+            .arg(&self.device)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| SourceError::Permanent(format!("cannot run fido2-assert: {}", e)))?;
+        let input = format!("{}\n{}\n{}\n", base64(client_data_hash), rp_id, base64(credential_id));
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.as_bytes())
+                .map_err(|e| SourceError::Transient(format!("cannot write to fido2-assert: {}", e)))?;
+        }
+        let out = child
+            .wait_with_output()
+            .map_err(|e| SourceError::Transient(format!("fido2-assert: {}", e)))?;
+        if !out.status.success() {
+            let detail = String::from_utf8_lossy(&out.stderr);
+            return Err(SourceError::Transient(format!("fido2-assert failed: {}", detail.trim())));
+        }
+        // client data hash, rp id, authenticator data, signature, ...
+        let text = String::from_utf8_lossy(&out.stdout);
+        let lines: Vec<&str> = text.lines().collect();
+        let field = |i: usize| lines.get(i).and_then(|l| unbase64(l.trim()));
+        let (Some(cbor), Some(signature)) = (field(2), field(3)) else {
+            return Err(SourceError::Permanent("fido2-assert printed no assertion".to_string()));
+        };
+        let signature: [u8; 64] = signature
+            .try_into()
+            .map_err(|_| SourceError::Permanent("not an EdDSA signature; the credential must be eddsa".to_string()))?;
+        Ok(Assertion {
+            authenticator_data: unwrap_byte_string(&cbor)
+                .ok_or(SourceError::Permanent("authenticator data is not a CBOR byte string".to_string()))?,
+            signature: Signature(signature),
+        })
+    }
+}
+
+/// A registered credential: which key, under which relying party, and
+/// the public key its assertions are checked with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credential {
+    pub rp_id: String,
+    pub id: Vec<u8>,
+    pub public_key: PublicKey,
+}
+
+impl Credential {
+    /// A credential from `fido2-cred`'s output: the credential id in
+    /// base64 and the EdDSA public key in hex.
+    pub fn new(rp_id: &str, id_base64: &str, public_key_hex: &str) -> Result<Self, String> {
+        let id = unbase64(id_base64).ok_or("credential id is not base64")?;
+        let public_key = PublicKey::from_hex(public_key_hex).ok_or("public key is not 32 bytes of hex")?;
+        Ok(Credential {
+            rp_id: rp_id.to_string(),
+            id,
+            public_key,
+        })
+    }
+}
+
+/// A security key and the credential on it that confirms approvals.
+pub struct HardwareConsentBackend {
+    key: Box<dyn Authenticator>,
+    credential: Credential,
+    require_verification: bool,
+    /// The highest signature counter seen. A key that reports one
+    /// lower, or the same, may have been cloned.
+    sign_count: u32,
+}
+
+impl HardwareConsentBackend {
+    pub fn new(key: Box<dyn Authenticator>, credential: Credential) -> Self {
+        HardwareConsentBackend {
+            key,
+            credential,
+            require_verification: false,
+            sign_count: 0,
+        }
+    }
+
+    /// Require the key to verify the user (PIN or biometric), not just
+    /// their presence.
+    pub fn require_verification(mut self) -> Self {
+        self.require_verification = true;
+        self
+    }
+
+    pub fn name(&self) -> String {
+        format!("{} for {}", self.key.name(), self.credential.rp_id)
+    }
+
+    /// Ask the key to confirm `message`, and check what it returns.
+    /// `Ok` carries the new signature counter.
+    fn confirm(&mut self, message: &[u8]) -> Result<u32, String> {
+        let digest = sha512(&[b"safe-operations hardware consent|", message]);
+        let mut client_data_hash = [0u8; 32];
+        client_data_hash.copy_from_slice(&digest[..32]);
+        let assertion = self
+            .key
+            .get_assertion(&self.credential.rp_id, &client_data_hash, &self.credential.id, self.require_verification)
+            .map_err(|e| match e {
+                SourceError::Transient(why) | SourceError::Permanent(why) => format!("no touch confirmed: {}", why),
+            })?;
+        let data = &assertion.authenticator_data;
+        if data.len() < 37 {
+            return Err("authenticator data is truncated".to_string());
+        }
+        if data[..32] != Sha256::digest(self.credential.rp_id.as_bytes())[..] {
+            return Err(format!("the assertion is not for '{}'", self.credential.rp_id));
+        }
+        if data[32] & FLAG_UP == 0 {
+            return Err("the key was not touched".to_string());
+        }
+        if self.require_verification && data[32] & FLAG_UV == 0 {
+            return Err("the key did not verify the user".to_string());
+        }
+        if !self.credential.public_key.verify(&[data.as_slice(), &client_data_hash].concat(), &assertion.signature) {
+            return Err("the assertion's signature does not verify".to_string());
+        }
+        let count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+        // Keys without a counter always report zero.
+        if (count != 0 || self.sign_count != 0) && count <= self.sign_count {
+            return Err(format!("signature counter went from {} to {}; the key may be cloned", self.sign_count, count));
+        }
+        self.sign_count = count;
+        Ok(count)
+    }
+}
+
+impl SafetyGate {
+    /// Confirm approvals the policy lists under `require_hardware_key`
+    /// on `backend`'s key.
+    pub fn set_hardware_key(&mut self, backend: HardwareConsentBackend) {
+        self.log(format!("HARDWARE KEY: {}", backend.name()));
+        self.hardware_key = Some(backend);
+    }
+
+    /// Require a touch confirming `operation` under this decision's
+    /// `nonce`, if the policy, or the tier for `severity`, says it
+    /// needs one.
+    pub(crate) fn confirm_on_hardware_key(
+        &mut self,
+        operation: OperationKind,
+        severity: Severity,
+        operation_description: &str,
+        target: Option<&str>,
+        nonce: u64,
+    ) -> Result<(), ConsentDenied> {
+        let tiered = matches!(self.ceremony(severity), tiers::Ceremony::QuorumAndKey { .. });
+        if !tiered && !self.policy.requires_hardware_key(operation) {
+            return Ok(());
+        }
+        let refuse = |gate: &mut SafetyGate, why: String| {
+            gate.log(format!("DENIED (hardware key): {}: {}", operation_description, why));
+            Err(ConsentDenied::new(operation, why))
+        };
+        if self.hardware_key.is_none() {
+            let why = match tiered {
+                true => format!("{} operations need a hardware key touch, and no key is configured", severity.label()),
+                false => format!(
+                    "policy {} requires a hardware key touch for {}, and no key is configured",
+                    self.policy.version,
+                    operation.name()
+                ),
+            };
+            return refuse(self, why);
+        }
+        self.show_line(approver::Line::Notice(format!("Touch your security key to confirm: {}", operation_description)));
+        let message = format!("{}|{}|{:016x}", operation.name(), target.unwrap_or(""), nonce);
+        let confirmed = match self.hardware_key.as_mut() {
+            Some(backend) => backend.confirm(message.as_bytes()),
+            None => Err("no key is configured".to_string()),
+        };
+        match confirmed {
+            Ok(count) => {
+                self.log(format!("HARDWARE KEY CONFIRMED: {} (signature count {})", operation_description, count));
+                Ok(())
+            }
+            Err(why) => refuse(self, why),
+        }
+    }
+}
+
+// Standard, padded base64 (RFC 4648 §4), as libfido2's tools print it.
+// No base64 crate is among this crate's dependencies, and these two are
+// the only uses.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// `None` for anything but canonical padded base64: a length that is
+/// not a multiple of four, padding anywhere but the end, or bits left
+/// over after the last byte.
+fn unbase64(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let body = text.trim_end_matches('=');
+    if text.len() - body.len() > 2 {
+        return None;
+    }
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in body.bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+        acc = acc << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    (acc == 0).then_some(out)
+}
+
+/// The contents of a CBOR byte string (major type 2), which is how
+/// `fido2-assert` prints authenticator data.
+fn unwrap_byte_string(cbor: &[u8]) -> Option<Vec<u8>> {
+    let (&head, rest) = cbor.split_first()?;
+    if head >> 5 != 2 {
+        return None;
+    }
+    let (len, body) = match head & 0x1f {
+        n @ 0..=23 => (n as usize, rest),
+        24 => (*rest.first()? as usize, &rest[1..]),
+        25 => (u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize, &rest[2..]),
+        _ => return None,
+    };
+    (body.len() == len).then(|| body.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, unbase64, Assertion, Authenticator, Credential, HardwareConsentBackend, FLAG_UP, FLAG_UV};
+    use crate::consent_client::SourceError;
+    use crate::ed25519::SigningKey;
+    use sha2::{Digest, Sha256};
+
+    /// A key that answers each assertion from a script: the relying party
+    /// it hashes, its flags, and its counter. `None` is a key that gave up.
+    struct ScriptedKey {
+        signer: SigningKey,
+        answers: Vec<Option<(&'static str, u8, u32)>>,
+    }
+
+    impl Authenticator for ScriptedKey {
+        fn name(&self) -> String {
+            "scripted key".to_string()
+        }
+
+        fn get_assertion(&mut self, _: &str, hash: &[u8; 32], _: &[u8], _: bool) -> Result<Assertion, SourceError> {
+            let answer = self.answers.remove(0);
+            let (rp_id, flags, count) = answer.ok_or(SourceError::Transient("timed out".to_string()))?;
+            let mut authenticator_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            authenticator_data.push(flags);
+            authenticator_data.extend(count.to_be_bytes());
+            let signature = self.signer.sign(&[authenticator_data.as_slice(), hash].concat());
+            Ok(Assertion { authenticator_data, signature })
+        }
+    }
+
+    /// A backend for the credential made from seed 7, on a key that signs
+    /// with seed 9 if it is a `forge`.
+    fn backend(answers: Vec<Option<(&'static str, u8, u32)>>, forge: bool) -> HardwareConsentBackend {
+        let credential = Credential {
+            rp_id: "safe-operations".to_string(),
+            id: b"credential".to_vec(),
+            public_key: SigningKey::from_seed([7; 32]).public_key(),
+        };
+        let signer = SigningKey::from_seed(if forge { [9; 32] } else { [7; 32] });
+        HardwareConsentBackend::new(Box::new(ScriptedKey { signer, answers }), credential)
+    }
+
+    fn refusal(backend: &mut HardwareConsentBackend) -> String {
+        backend.confirm(b"force-push|/srv/app|0000000000000001").expect_err("the key's answer is refused")
+    }
+
+    /// RFC 4648 §10.
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn base64_matches_the_rfc_vectors_both_ways() {
+        for (plain, encoded) in VECTORS {
+            assert_eq!(base64(plain.as_bytes()), encoded);
+            assert_eq!(unbase64(encoded).as_deref(), Some(plain.as_bytes()), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn base64_that_is_not_canonical_is_refused() {
+        for bad in ["Zg", "Zg=", "Zh==", "Z===", "Zm9v!A==", "=Zm9"] {
+            assert_eq!(unbase64(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn a_touch_under_the_registered_credential_is_confirmed_and_counted() {
+        let touch = |count| Some(("safe-operations", FLAG_UP, count));
+        let mut backend = backend(vec![touch(5), touch(6)], false);
+        assert_eq!(backend.confirm(b"one"), Ok(5));
+        assert_eq!(backend.confirm(b"two"), Ok(6));
+    }
+
+    #[test]
+    fn each_failed_check_is_reported_as_the_reason_for_refusal() {
+        let why = refusal(&mut backend(vec![None], false));
+        assert!(why.contains("no touch confirmed: timed out"), "{}", why);
+        let why = refusal(&mut backend(vec![Some(("elsewhere", FLAG_UP, 1))], false));
+        assert!(why.contains("not for 'safe-operations'"), "{}", why);
+        let why = refusal(&mut backend(vec![Some(("safe-operations", 0, 1))], false));
+        assert!(why.contains("not touched"), "{}", why);
+        let why = refusal(&mut backend(vec![Some(("safe-operations", FLAG_UP, 1))], false).require_verification());
+        assert!(why.contains("did not verify the user"), "{}", why);
+        let why = refusal(&mut backend(vec![Some(("safe-operations", FLAG_UP | FLAG_UV, 1))], true));
+        assert!(why.contains("signature does not verify"), "{}", why);
+    }
+
+    #[test]
+    fn a_counter_that_does_not_go_up_is_refused_and_not_recorded() {
+        let touch = |count| Some(("safe-operations", FLAG_UP, count));
+        let mut backend = backend(vec![touch(5), touch(5), touch(6)], false);
+        assert_eq!(backend.confirm(b"one"), Ok(5));
+        let why = refusal(&mut backend);
+        assert!(why.contains("went from 5 to 5"), "{}", why);
+        assert_eq!(backend.confirm(b"three"), Ok(6));
+    }
+}
@@ -0,0 +1,112 @@
+//! HostKey — signing for host-issued artifacts.
+
+#[cfg(feature = "std")]
+use crate::unix_now;
+use alloc::string::String;
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+/// A fresh random value from the standard library's per-instance random
+/// hasher keys. Good enough for challenges and nonces in a demonstration;
+/// a deployment would read the OS CSPRNG.
+#[cfg(feature = "std")]
+pub(crate) fn random_u64() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(unix_now());
+    hasher.finish()
+}
+
+/// Fill `bytes` from the OS random source. Anything that must not be
+/// guessed — keys, credentials — comes from here, and fails rather than
+/// fall back to something weaker.
+#[cfg(feature = "std")]
+pub(crate) fn os_random(bytes: &mut [u8]) -> std::io::Result<()> {
+    use std::io::Read;
+    std::fs::File::open("/dev/urandom")?.read_exact(bytes)
+}
+
+/// The host's signing key for manifests and other host-issued artifacts.
+///
+/// HMAC-SHA-256 under a 256-bit secret, so only holders of the key can
+/// sign or verify. That suits artifacts the host both issues and checks
+/// itself; evidence checked by someone else is signed with
+/// [`ed25519`](crate::ed25519) instead, so the verifier never holds the
+/// secret. What matters here is where the key lives — with the host,
+/// never in the agent's process.
+#[derive(Clone)]
+pub struct HostKey {
+    pub(crate) secret: [u8; 32],
+}
+
+/// An HMAC-SHA-256 tag made with a [`HostKey`].
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostSignature(pub [u8; 32]);
+
+impl HostSignature {
+    pub fn to_hex(&self) -> String {
+        crate::ed25519::hex(&self.0)
+    }
+
+    pub fn from_hex(text: &str) -> Option<Self> {
+        crate::ed25519::unhex(text).map(HostSignature)
+    }
+}
+
+impl core::fmt::Debug for HostSignature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "HostSignature({})", self.to_hex())
+    }
+}
+
+impl HostKey {
+    /// A key from the OS random source, or, if it cannot be read, from
+    /// the process's random hasher keys. A device without std has neither,
+    /// and provisions its key with [`from_secret`](Self::from_secret).
+    #[cfg(feature = "std")]
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        if os_random(&mut secret).is_err() {
+            for chunk in secret.chunks_mut(8) {
+                chunk.copy_from_slice(&random_u64().to_le_bytes());
+            }
+        }
+        HostKey { secret }
+    }
+
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        HostKey { secret }
+    }
+
+    fn mac(&self, message: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(&self.secret).expect("HMAC takes any key length");
+        mac.update(message.as_bytes());
+        mac
+    }
+
+    pub fn sign(&self, message: &str) -> HostSignature {
+        HostSignature(self.mac(message).finalize().into_bytes().into())
+    }
+
+    /// Whether `signature` is this key's tag for `message`, compared in
+    /// constant time.
+    pub fn verify(&self, message: &str, signature: &HostSignature) -> bool {
+        self.mac(message).verify_slice(&signature.0).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HostKey, HostSignature};
+
+    #[test]
+    fn a_signature_verifies_only_under_its_key_and_message() {
+        let key = HostKey::from_secret([7; 32]);
+        let signature = key.sign("policy|body");
+        assert!(key.verify("policy|body", &signature));
+        assert!(!key.verify("policy|body2", &signature));
+        assert!(!HostKey::from_secret([8; 32]).verify("policy|body", &signature));
+        assert_eq!(HostSignature::from_hex(&signature.to_hex()), Some(signature));
+    }
+}
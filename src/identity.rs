@@ -0,0 +1,367 @@
+//! Approver identity from the organization's identity provider.
+//!
+//! A name typed into the gate's configuration is not an identity: it does
+//! not leave when the person leaves, and it does not know they moved
+//! teams last week. With a [`Directory`] set, the gate keeps no accounts.
+//! Each approver is resolved through the provider at the moment they
+//! approve, their groups and roles are checked against the ones the
+//! directory requires, and the subject the provider vouched for goes on
+//! the receipt. A provider that cannot be reached is a refusal, like any
+//! other approval channel that gives no answer.
+//!
+//! At the console the approver identifies themselves with
+//! [`SafetyGate::identify_approver`] before the decision. Quorum approvers
+//! are resolved by the names they were configured under.
+
+use super::consent_backend::curl_with_headers;
+use super::consent_client::SourceError;
+use super::SafetyGate;
+use serde_json::Value;
+use std::process::Command;
+use std::time::Duration;
+
+/// An approver as the identity provider describes them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    /// Which provider vouched for them, e.g. `oidc:https://login.example.com`.
+    pub issuer: String,
+    /// The provider's stable id for them: `sub`, or an LDAP DN.
+    pub subject: String,
+    pub display_name: String,
+    /// As the provider names them: OIDC group names, LDAP group DNs.
+    pub groups: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+impl Identity {
+    /// The subject as receipts record it, qualified by its issuer.
+    pub fn verified_subject(&self) -> String {
+        format!("{}|{}", self.issuer, self.subject)
+    }
+
+    /// Whether any of the identity's groups or roles is in `wanted`.
+    /// An empty `wanted` asks for none. Names are compared exactly, and
+    /// DNs as DNs: `cn=Admins, ou=Groups` is `CN=admins,ou=groups`, and
+    /// not `cn=admins,ou=contractors`.
+    pub fn holds_any(&self, wanted: &[String]) -> bool {
+        wanted.is_empty() || self.groups.iter().chain(&self.roles).any(|g| wanted.iter().any(|w| same_group(g, w)))
+    }
+}
+
+/// Whether `a` and `b` name the same group: the same DN, if both are DNs,
+/// or else the same name.
+fn same_group(a: &str, b: &str) -> bool {
+    match (normalized_dn(a), normalized_dn(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// `dn` with the space around its separators dropped and its case folded,
+/// as LDAP compares group DNs; `None` if it is not a DN. An escaped comma
+/// stays inside its RDN.
+fn normalized_dn(dn: &str) -> Option<Vec<(String, String)>> {
+    let mut rdns = Vec::new();
+    let (mut rdn, mut escaped) = (String::new(), false);
+    for c in dn.chars().chain([',']) {
+        if c == ',' && !escaped {
+            let (attribute, value) = rdn.split_once('=')?;
+            rdns.push((attribute.trim().to_ascii_lowercase(), value.trim().to_lowercase()));
+            rdn.clear();
+        } else {
+            rdn.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    Some(rdns)
+}
+
+/// Where approver identities are looked up.
+pub trait IdentityProvider {
+    fn name(&self) -> String;
+    /// Resolve `principal` now: an access token for OIDC, a login for
+    /// LDAP. Never answered from a cache, so a revoked account or a
+    /// removed group is seen at the next approval.
+    fn resolve(&mut self, principal: &str) -> Result<Identity, SourceError>;
+}
+
+/// A provider, and the groups or roles an approver needs in it.
+pub struct Directory {
+    provider: Box<dyn IdentityProvider>,
+    required: Vec<String>,
+}
+
+impl Directory {
+    /// Any identity `provider` resolves may approve.
+    pub fn new(provider: Box<dyn IdentityProvider>) -> Self {
+        Directory {
+            provider,
+            required: Vec::new(),
+        }
+    }
+
+    /// Only approvers in `group`, or holding it as a role, may
+    /// approve. Several calls accept any one of them. For LDAP, `group`
+    /// is the group's DN.
+    pub fn require(mut self, group: &str) -> Self {
+        self.required.push(group.to_string());
+        self
+    }
+}
+
+/// An OpenID Connect provider. The approver's access token is sent to
+/// the provider's userinfo endpoint; `sub` is the subject, and the
+/// `groups_claim` and `roles_claim` members list their groups and roles.
+pub struct OidcProvider {
+    pub issuer: String,
+    pub userinfo_url: String,
+    pub groups_claim: String,
+    pub roles_claim: String,
+    pub timeout: Duration,
+}
+
+impl OidcProvider {
+    pub fn new(issuer: &str, userinfo_url: &str) -> Self {
+        OidcProvider {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            userinfo_url: userinfo_url.to_string(),
+            groups_claim: "groups".to_string(),
+            roles_claim: "roles".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl IdentityProvider for OidcProvider {
+    fn name(&self) -> String {
+        format!("oidc {}", self.issuer)
+    }
+
+    fn resolve(&mut self, principal: &str) -> Result<Identity, SourceError> {
+        let auth = format!("Authorization: Bearer {}", principal.trim());
+        let (body, status) = curl_with_headers(&[&self.userinfo_url], &[&auth], self.timeout)?;
+        match status {
+            200..=299 => {}
+            401 | 403 => return Err(SourceError::Permanent("the provider rejected the token".to_string())),
+            code => return Err(SourceError::Permanent(format!("userinfo: HTTP {}", code))),
+        }
+        let claims: Value = serde_json::from_str(&body)
+            .ok()
+            .filter(Value::is_object)
+            .ok_or_else(|| SourceError::Permanent("userinfo sent no JSON object".to_string()))?;
+        let claim = |key: &str| claims[key].as_str().map(str::to_string);
+        let strings = |key: &str| {
+            let values = claims[key].as_array().map(Vec::as_slice).unwrap_or_default();
+            values.iter().filter_map(Value::as_str).map(str::to_string).collect()
+        };
+        let subject = claim("sub").ok_or_else(|| SourceError::Permanent("userinfo has no `sub`".to_string()))?;
+        let display_name = ["name", "preferred_username", "email"]
+            .into_iter()
+            .find_map(claim)
+            .unwrap_or_else(|| subject.clone());
+        Ok(Identity {
+            issuer: format!("oidc:{}", self.issuer),
+            subject,
+            display_name,
+            groups: strings(&self.groups_claim),
+            roles: strings(&self.roles_claim),
+        })
+    }
+}
+
+/// An LDAP directory, searched with `ldapsearch`. The login is looked
+/// up under `base_dn`; the entry's DN is the subject, and its groups are
+/// the DNs in `memberOf`, which [`Directory::require`] is given in turn:
+/// `cn=admins` under two OUs is two groups. A disabled account
+/// (`nsAccountLock: true`) does not resolve.
+pub struct LdapProvider {
+    pub uri: String,
+    pub base_dn: String,
+    /// Simple bind, as `(dn, password file)`. Anonymous if `None`.
+    pub bind: Option<(String, String)>,
+    /// The login attribute, `uid` unless set.
+    pub login_attribute: String,
+    pub timeout: Duration,
+}
+
+impl LdapProvider {
+    pub fn new(uri: &str, base_dn: &str) -> Self {
+        LdapProvider {
+            uri: uri.to_string(),
+            base_dn: base_dn.to_string(),
+            bind: None,
+            login_attribute: "uid".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// `value` escaped for an LDAP search filter (RFC 4515), so a login
+/// cannot widen the search.
+fn escape_filter(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '*' | '(' | ')' | '\\' | '\0' => format!("\\{:02x}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+impl IdentityProvider for LdapProvider {
+    fn name(&self) -> String {
+        format!("ldap {}", self.uri)
+    }
+
+    fn resolve(&mut self, principal: &str) -> Result<Identity, SourceError> {
+        let filter = format!("({}={})", self.login_attribute, escape_filter(principal.trim()));
+        let mut command = Command::new("ldapsearch");
+        command.args(["-x", "-LLL", "-o", "ldif-wrap=no", "-l"]);
+        command.arg(self.timeout.as_secs().max(1).to_string());
+        command.args(["-H", &self.uri, "-b", &self.base_dn]);
+        if let Some((dn, password_file)) = &self.bind {
+            command.args(["-D", dn, "-y", password_file]);
+        }
+        command.args([&filter, "cn", "displayName", "memberOf", "nsAccountLock"]);
+        let out = command
+            .output()
+            .map_err(|e| SourceError::Permanent(format!("cannot run ldapsearch: {}", e)))?;
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        match out.status.code() {
+            Some(0) => {}
+            // 255: the server could not be contacted.
+            Some(255) | None => return Err(SourceError::Transient(format!("ldapsearch: {}", stderr.trim()))),
+            Some(code) => return Err(SourceError::Permanent(format!("ldapsearch exited {}: {}", code, stderr.trim()))),
+        }
+        let text = String::from_utf8_lossy(&out.stdout);
+        let entries: Vec<&str> = text.split("\n\n").filter(|e| e.trim_start().starts_with("dn:")).collect();
+        let entry = match entries.as_slice() {
+            [entry] => *entry,
+            [] => return Err(SourceError::Permanent(format!("no account `{}`", principal.trim()))),
+            _ => return Err(SourceError::Permanent(format!("`{}` matches {} accounts", principal.trim(), entries.len()))),
+        };
+        let values = |attribute: &str| -> Vec<String> {
+            entry
+                .lines()
+                .filter_map(|l| l.split_once(": "))
+                .filter(|(a, _)| a.eq_ignore_ascii_case(attribute))
+                .map(|(_, v)| v.trim().to_string())
+                .collect()
+        };
+        if values("nsAccountLock").iter().any(|v| v.eq_ignore_ascii_case("true")) {
+            return Err(SourceError::Permanent(format!("account `{}` is disabled", principal.trim())));
+        }
+        let subject = values("dn").into_iter().next().unwrap_or_default();
+        let groups = values("memberOf");
+        Ok(Identity {
+            issuer: format!("ldap:{}", self.uri),
+            display_name: values("displayName").into_iter().chain(values("cn")).next().unwrap_or_else(|| subject.clone()),
+            subject,
+            groups,
+            roles: Vec::new(),
+        })
+    }
+}
+
+impl SafetyGate {
+    /// Resolve approvers through `directory` from now on. The gate's
+    /// own approver names become logins to look up, not identities.
+    pub fn set_identity_directory(&mut self, directory: Directory) {
+        let required = match directory.required.is_empty() {
+            true => "any identity it resolves".to_string(),
+            false => format!("one of [{}]", directory.required.join(", ")),
+        };
+        self.log(format!("IDENTITY PROVIDER: {}, approvers need {}", directory.provider.name(), required));
+        self.identity = Some(directory);
+    }
+
+    /// How the approver of the next decision identifies themselves to
+    /// the identity provider: an access token, or a login.
+    pub fn identify_approver(&mut self, principal: &str) {
+        self.principal = Some(principal.to_string());
+    }
+
+    /// The verified subjects who approved the most recent grant, for
+    /// its receipt.
+    pub fn approved_by(&self) -> &[String] {
+        &self.last_approvers
+    }
+
+    /// Resolve `principal` and check it may approve. `Ok(None)` when
+    /// no directory is set: there is nothing to verify against.
+    pub(crate) fn verify_approver(&mut self, principal: Option<&str>) -> Result<Option<Identity>, String> {
+        let Some(directory) = &mut self.identity else {
+            return Ok(None);
+        };
+        let provider = directory.provider.name();
+        let Some(principal) = principal else {
+            return Err(format!("approvals are verified by {}, and the approver did not identify themselves", provider));
+        };
+        let identity = match directory.provider.resolve(principal) {
+            Ok(identity) => identity,
+            Err(SourceError::Transient(why) | SourceError::Permanent(why)) => {
+                return Err(format!("{} could not verify the approver: {}", provider, why))
+            }
+        };
+        if !identity.holds_any(&directory.required) {
+            return Err(format!(
+                "{} is not in any of [{}] at {}",
+                identity.display_name,
+                directory.required.join(", "),
+                provider
+            ));
+        }
+        self.log(format!("APPROVER VERIFIED: {} ({}) by {}", identity.display_name, identity.verified_subject(), provider));
+        Ok(Some(identity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_filter, Identity};
+    use crate::consent_backend::curl_with_headers;
+    use crate::consent_client::SourceError;
+    use std::time::Duration;
+
+    fn member_of(groups: &[&str]) -> Identity {
+        Identity {
+            issuer: "ldap:ldap://ldap.example.com".to_string(),
+            subject: "uid=jdoe,ou=people,dc=example,dc=com".to_string(),
+            display_name: "J. Doe".to_string(),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+            roles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn an_ldap_group_is_matched_by_its_whole_dn() {
+        let required = ["cn=admins,ou=groups,dc=example,dc=com".to_string()];
+        assert!(member_of(&["CN=Admins, ou=Groups,dc=example,dc=com"]).holds_any(&required));
+        assert!(!member_of(&["cn=admins,ou=contractors,dc=example,dc=com"]).holds_any(&required), "same first RDN");
+        assert!(!member_of(&["cn=admins"]).holds_any(&required));
+        assert!(!member_of(&["admins"]).holds_any(&required), "a bare name is not the DN");
+        let escaped = ["cn=a\\,b,dc=example".to_string()];
+        assert!(member_of(&["cn=A\\,B, dc=example"]).holds_any(&escaped));
+        assert!(!member_of(&["cn=a,cn=b,dc=example"]).holds_any(&escaped));
+    }
+
+    #[test]
+    fn a_group_name_is_matched_exactly() {
+        let required = ["release-managers".to_string()];
+        assert!(member_of(&["release-managers"]).holds_any(&required));
+        assert!(!member_of(&["Release-Managers"]).holds_any(&required));
+        assert!(member_of(&[]).holds_any(&[]), "nothing required");
+    }
+
+    #[test]
+    fn a_login_cannot_widen_the_search() {
+        assert_eq!(escape_filter("*)(uid=*"), "\\2a\\29\\28uid=\\2a");
+    }
+
+    #[test]
+    fn a_header_cannot_smuggle_another() {
+        let header = "Authorization: Bearer token\r\nX-Injected: 1";
+        let refused = curl_with_headers(&["http://127.0.0.1:9"], &[header], Duration::from_secs(1));
+        assert!(matches!(refused, Err(SourceError::Permanent(why)) if why.contains("spans lines")));
+    }
+}
@@ -0,0 +1,82 @@
+//! An impact preview, computed by the repository and shown in the prompt.
+//!
+//! A [`DestructionPlan`] is only as good as whoever wrote it, and an agent
+//! that never counts does not write one. For a request bound to a
+//! repository the gate asks the repository itself what the operation
+//! would do — which commits get new SHAs, which branches are overwritten,
+//! which uncommitted files are lost — and puts the answer in front of the
+//! approver before the challenge, at every verbosity.
+
+use super::{OperationKind, Repository};
+
+/// What one operation on one repository destroys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImpactReport {
+    pub operation: OperationKind,
+    pub repo: String,
+    /// Commits that get new SHAs.
+    pub rewritten_commits: usize,
+    /// Branches whose history is overwritten.
+    pub overwritten_branches: Vec<String>,
+    /// Files whose uncommitted changes are lost.
+    pub lost_files: Vec<String>,
+}
+
+impl ImpactReport {
+    /// The same report for a push to `branch` rather than `main`.
+    pub fn for_branch(mut self, branch: &str) -> Self {
+        if self.operation == OperationKind::ForcePush {
+            self.overwritten_branches = vec![branch.to_string()];
+        }
+        self
+    }
+
+    /// Whether the preview found nothing the operation destroys.
+    pub fn is_empty(&self) -> bool {
+        self.rewritten_commits == 0 && self.overwritten_branches.is_empty() && self.lost_files.is_empty()
+    }
+
+    /// One line per thing destroyed.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.rewritten_commits > 0 {
+            lines.push(format!(
+                "{} commits get new SHAs; every clone, fork, and link to them goes stale",
+                self.rewritten_commits
+            ));
+        }
+        for branch in &self.overwritten_branches {
+            lines.push(format!("branch '{}' is overwritten", branch));
+        }
+        for file in &self.lost_files {
+            lines.push(format!("uncommitted changes to '{}' are lost", file));
+        }
+        lines
+    }
+}
+
+impl<S, B> Repository<S, B> {
+    /// What `operation` would destroy here, as far as the repository
+    /// knows: a force-push to the default branch, a history rewrite of
+    /// every commit and branch, a hard reset of the working tree.
+    /// Other operations destroy nothing the repository can count.
+    pub fn preview_impact(&self, operation: OperationKind) -> ImpactReport {
+        let mut report = ImpactReport {
+            operation,
+            repo: self.name.clone(),
+            rewritten_commits: 0,
+            overwritten_branches: Vec::new(),
+            lost_files: Vec::new(),
+        };
+        match operation {
+            OperationKind::ForcePush => report.overwritten_branches.push(self.default_branch.clone()),
+            OperationKind::FilterRepo => {
+                report.rewritten_commits = self.total_commits;
+                report.overwritten_branches = self.branches.iter().map(|b| b.name.clone()).collect();
+            }
+            OperationKind::ResetHard => report.lost_files = self.uncommitted.clone(),
+            _ => {}
+        }
+        report
+    }
+}
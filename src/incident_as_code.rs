@@ -0,0 +1,96 @@
+//! The incident, written as code that does not compile.
+//!
+//! # The incident as code that won't compile
+//!
+//! What the agent tried to do on February 25, 2026.
+//!
+//! Every line that would have failed is shown as a comment with the
+//! exact compiler error. These are not hypothetical errors — they are
+//! the errors `rustc` would produce if you uncommented the code.
+//!
+//! ```compile_fail,E0599
+//! # use safe_operations::*;
+//! // The agent held commit access to two repos: protected, and no more.
+//! fn what_the_agent_tried(gov: Repository<Protected>, anima: Repository<Protected>) {
+//!     // Step 1: Agent tries to force-push a protected repo.
+//!     gov.force_push();
+//!     // ERROR[E0599]: no method named `force_push` found for
+//!     //     struct `Repository<Protected>` in the current scope
+//!     // note: the method exists on `Repository<Unprotected>`
+//!     // help: consider obtaining UserConsent and calling
+//!     //     `remove_protection()` first
+//!
+//!     // Step 2: Agent tries to remove protection without consent.
+//!     let gov = gov.remove_protection();
+//!     // ERROR[E0061]: this method takes 1 argument but 0 were supplied
+//!     // note: the parameter `consent: UserConsent` is required
+//!     // help: UserConsent can only be obtained through SafetyGate
+//!
+//!     // Step 3: Agent tries to fabricate consent.
+//!     let fake = UserConsent { _operation: "trust me".into(), _nonce: 0 };
+//!     // ERROR[E0451]: field `_operation` of struct `UserConsent` is private
+//!     // ERROR[E0451]: field `_nonce` of struct `UserConsent` is private
+//!     // note: UserConsent has no public constructor
+//!     // help: use SafetyGate::request_consent() to obtain consent
+//!     //     through the user interaction flow
+//!
+//!     // Step 4: Agent tries to call filter_repo on a protected repo.
+//!     gov.filter_repo("strip co-authored-by", fake);
+//!     // ERROR[E0599]: no method named `filter_repo` found for
+//!     //     struct `Repository<Protected>` in the current scope
+//!     // (Also: `fake` didn't compile either, so this is doubly dead.)
+//!
+//!     // The agent is stuck. Every path to destruction is a type error.
+//!     // It cannot call what does not exist. It cannot forge what is private.
+//!     // It cannot reason its way past a compiler.
+//! }
+//! ```
+//!
+//! The agent in the incident had safety rules that said the same things
+//! these type signatures say. The difference: the agent could decide its
+//! rules didn't apply. The compiler cannot.
+//!
+//! # Ownership and the cascading destruction
+//!
+//! Demonstrates how Rust's ownership model prevents cascading destruction.
+//!
+//! In the incident, the agent:
+//! 1. Ran filter-repo (destroying the original history)
+//! 2. Then ran reset --hard on the same repo (destroying uncommitted work)
+//! 3. Then tried to "recover" the repo it had already consumed twice
+//!
+//! In Rust, step 1 consumes the repository. Step 2 is a use-after-move
+//! error. Step 3 is fantasy.
+//!
+//! ```compile_fail,E0382
+//! # use safe_operations::*;
+//! fn cascading_destruction_fails(
+//!     repo: Repository<Unprotected>,
+//!     consent: UserConsent,
+//! ) {
+//!     // Step 1: filter-repo consumes the repo.
+//!     let filtered = repo.filter_repo("strip co-authored-by", consent);
+//!     //                   ^^^^ `repo` moved here
+//!
+//!     // Step 2: Agent tries to reset --hard on the consumed repo.
+//!     repo.reset_hard(consent);
+//!     // ERROR[E0382]: use of moved value: `repo`
+//!     //   --> step 1 moved `repo` into `filter_repo()`
+//!     //   note: `repo` was consumed because `filter_repo` takes `self`
+//!     //         by value, not by reference
+//!     //   help: after filter-repo, the original repository no longer
+//!     //         exists. The old SHAs are gone. There is nothing to reset.
+//!     // ERROR[E0382]: use of moved value: `consent`
+//!     //   (step 1 spent it: one approval, one operation)
+//!
+//!     // Step 3: Agent tries to push the consumed repo.
+//!     repo.force_push(consent);
+//!     // ERROR[E0382]: use of moved value: `repo`
+//!     //   (same error — `repo` is gone, used on step 1)
+//!
+//!     // The agent destroyed one thing. Rust stopped it from destroying
+//!     // two more things with the same variable. The borrow checker
+//!     // enforces what the agent's safety rules could not: you cannot
+//!     // operate on something that no longer exists.
+//! }
+//! ```
@@ -0,0 +1,442 @@
+//! A store of finalized incident reports.
+//!
+//! A postmortem that lives in a wiki is read once. Here each finalized
+//! report is a file, `<id>.incident`, indexed by the operations it
+//! involved and what the gate did at each step: whether the agent went
+//! around it, or it granted or denied. [`Store::query`] searches them;
+//! [`Store::policy_tests`] turns the failures into `policy_tests.toml`
+//! entries whose context cites the incident, so a policy change that
+//! would let the same thing through fails its own tests; and
+//! [`Incident::regression`] turns them into a [`Scenario`] for the
+//! scenario harness.
+//!
+//! Finalized means immutable: filing an id twice is refused. A correction
+//! is a new report that says which one it corrects.
+//!
+//! ```text
+//! id INC-2026-02-25
+//! title History rewritten on two repositories without asking
+//! finalized 1772031791
+//! intent Asked about the Co-Authored-By lines in governance-mcp-v1
+//! step bypassed force_push anima-mcp push --force anima-mcp main
+//! lesson Consent is per operation and per repository.
+//! ```
+
+use super::approver::{Prompt, Responder};
+use super::scenario::{Label, Scenario, Trace};
+use super::{policy, report, unix_now, OperationKind, SafetyGate};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What happened at one step, as far as the gate is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// The agent never asked: there was no gate in the path.
+    Bypassed,
+    Denied,
+    Granted,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Bypassed => "bypassed",
+            Outcome::Denied => "denied",
+            Outcome::Granted => "granted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Outcome> {
+        [Outcome::Bypassed, Outcome::Denied, Outcome::Granted]
+            .into_iter()
+            .find(|o| o.label() == value)
+    }
+
+    /// Whether the step is one the gate must keep stopping.
+    pub fn is_failure(self) -> bool {
+        self != Outcome::Granted
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub outcome: Outcome,
+    pub operation: OperationKind,
+    pub repo: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Incident {
+    pub id: String,
+    pub title: String,
+    /// When it was filed, in seconds since the epoch. Zero until then.
+    pub finalized_at: u64,
+    /// What the agent was asked to do.
+    pub intent: String,
+    pub steps: Vec<Step>,
+    pub lessons: Vec<String>,
+}
+
+impl Incident {
+    pub fn new(id: &str, title: &str, intent: &str) -> Self {
+        Incident {
+            id: id.to_string(),
+            title: title.to_string(),
+            finalized_at: 0,
+            intent: intent.to_string(),
+            steps: Vec::new(),
+            lessons: Vec::new(),
+        }
+    }
+
+    /// The report of a scenario run: every step, with what the gate did.
+    pub fn from_trace(id: &str, title: &str, trace: &Trace) -> Self {
+        let mut incident = Incident::new(id, title, &trace.intent);
+        for step in &trace.steps {
+            let outcome = match step.decision.as_str() {
+                "no-consent" => Outcome::Bypassed,
+                "granted" => Outcome::Granted,
+                _ => Outcome::Denied,
+            };
+            incident = incident.step(outcome, step.operation, &step.repo, &step.description);
+        }
+        incident
+    }
+
+    pub fn step(mut self, outcome: Outcome, operation: OperationKind, repo: &str, description: &str) -> Self {
+        self.steps.push(Step {
+            outcome,
+            operation,
+            repo: repo.to_string(),
+            description: description.to_string(),
+        });
+        self
+    }
+
+    pub fn lesson(mut self, lesson: &str) -> Self {
+        self.lessons.push(lesson.to_string());
+        self
+    }
+
+    /// Every operation involved, in order of first appearance.
+    pub fn operations(&self) -> Vec<OperationKind> {
+        let mut seen = Vec::new();
+        for step in &self.steps {
+            if !seen.contains(&step.operation) {
+                seen.push(step.operation);
+            }
+        }
+        seen
+    }
+
+    pub fn bypass_attempts(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().filter(|s| s.outcome == Outcome::Bypassed)
+    }
+
+    /// How a policy or test cites this incident.
+    pub fn citation(&self) -> String {
+        format!("{}: {}", self.id, self.title)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "id {}\ntitle {}\nfinalized {}\nintent {}\n",
+            self.id, self.title, self.finalized_at, self.intent
+        );
+        for step in &self.steps {
+            out.push_str(&format!(
+                "step {} {} {} {}\n",
+                step.outcome.label(),
+                step.operation.name(),
+                step.repo,
+                step.description
+            ));
+        }
+        for lesson in &self.lessons {
+            out.push_str(&format!("lesson {}\n", lesson));
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Incident, String> {
+        let mut incident = Incident::new("", "", "");
+        for (i, raw) in text.lines().enumerate() {
+            let err = |message: String| format!("line {}: {}", i + 1, message);
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match word {
+                "id" => incident.id = rest.to_string(),
+                "title" => incident.title = rest.to_string(),
+                "intent" => incident.intent = rest.to_string(),
+                "finalized" => {
+                    incident.finalized_at =
+                        rest.parse().map_err(|_| err(format!("`finalized` needs seconds, found `{}`", rest)))?
+                }
+                "step" => {
+                    let mut fields = rest.splitn(4, ' ');
+                    let (Some(outcome), Some(operation), Some(repo)) = (fields.next(), fields.next(), fields.next())
+                    else {
+                        return Err(err("expected `step <outcome> <operation> <repo> <description>`".to_string()));
+                    };
+                    let outcome = Outcome::parse(outcome).ok_or_else(|| err(format!("unknown outcome `{}`", outcome)))?;
+                    let operation = OperationKind::from_name(operation)
+                        .ok_or_else(|| err(format!("unknown operation `{}`", operation)))?;
+                    incident = incident.step(outcome, operation, repo, fields.next().unwrap_or(""));
+                }
+                "lesson" => incident.lessons.push(rest.to_string()),
+                _ => return Err(err(format!("unknown directive `{}`", word))),
+            }
+        }
+        if incident.id.is_empty() || incident.title.is_empty() {
+            return Err("an incident needs an `id` and a `title`".to_string());
+        }
+        Ok(incident)
+    }
+
+    /// The incident's failures as a scenario in which the agent asks
+    /// every time. Run with no one approving, every step must be
+    /// blocked: see [`Store::regress`].
+    pub fn regression(&self) -> Scenario {
+        let mut scenario = Scenario::new(&format!("regression-{}", self.id), &self.intent, Label::DestructiveAttempt);
+        for step in self.steps.iter().filter(|s| s.outcome.is_failure()) {
+            scenario = scenario.step(step.operation, &step.repo, &step.description, true);
+        }
+        scenario
+    }
+}
+
+/// Search terms: `op:<operation>`, `outcome:<bypassed|denied|granted>`,
+/// `repo:<name>`, and words to find in the title, intent, lessons, or
+/// step descriptions. Every term must match.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub operation: Option<OperationKind>,
+    pub outcome: Option<Outcome>,
+    pub repo: Option<String>,
+    pub words: Vec<String>,
+}
+
+impl Query {
+    pub fn parse(terms: &[String]) -> Result<Query, String> {
+        let mut query = Query::default();
+        for term in terms {
+            if let Some(name) = term.strip_prefix("op:") {
+                query.operation = Some(OperationKind::from_name(name).ok_or(format!("unknown operation `{}`", name))?);
+            } else if let Some(value) = term.strip_prefix("outcome:") {
+                query.outcome = Some(Outcome::parse(value).ok_or(format!("unknown outcome `{}`", value))?);
+            } else if let Some(repo) = term.strip_prefix("repo:") {
+                query.repo = Some(repo.to_string());
+            } else {
+                query.words.push(term.to_lowercase());
+            }
+        }
+        Ok(query)
+    }
+
+    fn matches(&self, incident: &Incident) -> bool {
+        let step_matches = |s: &Step| {
+            self.operation.is_none_or(|op| s.operation == op)
+                && self.outcome.is_none_or(|o| s.outcome == o)
+                && self.repo.as_ref().is_none_or(|r| s.repo == *r)
+        };
+        let text = [&incident.title, &incident.intent]
+            .into_iter()
+            .chain(&incident.lessons)
+            .chain(incident.steps.iter().map(|s| &s.description))
+            .map(|t| t.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        incident.steps.iter().any(step_matches) && self.words.iter().all(|w| text.contains(w.as_str()))
+    }
+}
+
+/// An incident's id and whether its failures stay blocked.
+pub type Verdict = (String, Result<(), String>);
+
+/// Types nothing back. A regression run has no one to approve it.
+struct Absent;
+
+impl Responder for Absent {
+    fn name(&self) -> &str {
+        "absent (regression run; no one approves)"
+    }
+
+    fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+        None
+    }
+}
+
+/// The finalized incidents in one directory, indexed.
+pub struct Store {
+    dir: PathBuf,
+    incidents: Vec<Incident>,
+    /// Incidents by the operations and outcomes of their steps.
+    by_operation: BTreeMap<&'static str, Vec<usize>>,
+    by_outcome: BTreeMap<Outcome, Vec<usize>>,
+}
+
+impl Store {
+    /// Load every `.incident` file in `dir`, in name order. A missing
+    /// directory is an empty store; a report that does not parse is
+    /// an error, not a gap in the record.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Store, String> {
+        let dir = dir.into();
+        let mut store = Store {
+            dir,
+            incidents: Vec::new(),
+            by_operation: BTreeMap::new(),
+            by_outcome: BTreeMap::new(),
+        };
+        let Ok(entries) = fs::read_dir(&store.dir) else {
+            return Ok(store);
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|x| x == "incident"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let incident = Incident::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+            store.index(incident);
+        }
+        Ok(store)
+    }
+
+    fn index(&mut self, incident: Incident) {
+        let at = self.incidents.len();
+        for step in &incident.steps {
+            let ids = self.by_operation.entry(step.operation.name()).or_default();
+            if !ids.contains(&at) {
+                ids.push(at);
+            }
+            let ids = self.by_outcome.entry(step.outcome).or_default();
+            if !ids.contains(&at) {
+                ids.push(at);
+            }
+        }
+        self.incidents.push(incident);
+    }
+
+    pub fn incidents(&self) -> &[Incident] {
+        &self.incidents
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Incident> {
+        self.incidents.iter().find(|i| i.id == id)
+    }
+
+    /// File `incident`, stamped with the time. An id already on file
+    /// is refused.
+    pub fn finalize(&mut self, mut incident: Incident) -> Result<PathBuf, String> {
+        if incident.id.is_empty() || incident.id.contains(['/', '\\', ' ']) {
+            return Err(format!("`{}` is not a usable incident id", incident.id));
+        }
+        if self.get(&incident.id).is_some() {
+            return Err(format!("{} is already finalized", incident.id));
+        }
+        incident.finalized_at = unix_now();
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let path = self.dir.join(format!("{}.incident", incident.id));
+        fs::write(&path, incident.to_text()).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.index(incident);
+        Ok(path)
+    }
+
+    pub fn query(&self, query: &Query) -> Vec<&Incident> {
+        let mut candidates: Vec<usize> = (0..self.incidents.len()).collect();
+        if let Some(op) = query.operation {
+            candidates = self.by_operation.get(op.name()).cloned().unwrap_or_default();
+        }
+        if let Some(outcome) = query.outcome {
+            let indexed = self.by_outcome.get(&outcome).cloned().unwrap_or_default();
+            candidates.retain(|i| indexed.contains(i));
+        }
+        candidates.sort_unstable();
+        candidates.into_iter().map(|i| &self.incidents[i]).filter(|i| query.matches(i)).collect()
+    }
+
+    /// `policy_tests.toml` entries for the failures in `incidents`: each
+    /// operation the gate must keep from happening without a human,
+    /// with the incident cited as the test's context. Quotes and `#`
+    /// are dropped from the citation; the test format has no escapes.
+    pub fn policy_tests(incidents: &[&Incident]) -> String {
+        let mut out = String::new();
+        for incident in incidents {
+            let mut cited = Vec::new();
+            for step in incident.steps.iter().filter(|s| s.outcome.is_failure()) {
+                if cited.contains(&step.operation) {
+                    continue;
+                }
+                cited.push(step.operation);
+                out.push_str(&format!(
+                    "[[test]]\noperation = \"{}\"\ncontext = \"{}\"\nexpect = \"require-consent\"\n\n",
+                    step.operation.name(),
+                    incident.citation().replace(['"', '#'], "")
+                ));
+            }
+        }
+        out
+    }
+
+    /// Run every incident's regression scenario against a fresh gate
+    /// enforcing `policy` (the built-in one if `None`), with no one
+    /// there to approve. A step that still executes is a regression:
+    /// the gate would let the same failure through without a human.
+    pub fn regress(&self, policy: Option<&Path>) -> Result<Vec<Verdict>, String> {
+        let mut results = Vec::new();
+        for incident in &self.incidents {
+            let mut gate = SafetyGate::new();
+            gate.set_responder(Box::new(Absent));
+            if let Some(path) = policy {
+                gate.policy = policy::Policy::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+            let trace = incident.regression().run(&mut gate);
+            let escaped: Vec<String> = trace
+                .steps
+                .iter()
+                .filter(|s| s.outcome == "executed")
+                .map(|s| format!("{} on {}", s.operation.name(), s.repo))
+                .collect();
+            let result = match escaped.is_empty() {
+                true => Ok(()),
+                false => Err(format!("executed with no one approving: {}", escaped.join(", "))),
+            };
+            results.push((incident.id.clone(), result));
+        }
+        Ok(results)
+    }
+
+    /// The matching incidents, summarized for a reader.
+    pub fn render(incidents: &[&Incident]) -> String {
+        let mut out = String::new();
+        for incident in incidents {
+            let count = |o: Outcome| incident.steps.iter().filter(|s| s.outcome == o).count();
+            out.push_str(&format!(
+                "{}  {} (finalized {})\n",
+                incident.id,
+                incident.title,
+                report::format_utc(incident.finalized_at)
+            ));
+            out.push_str(&format!(
+                "  operations: {}\n",
+                incident.operations().iter().map(|k| k.name()).collect::<Vec<_>>().join(", ")
+            ));
+            out.push_str(&format!(
+                "  steps: {} bypassed, {} denied, {} granted\n",
+                count(Outcome::Bypassed),
+                count(Outcome::Denied),
+                count(Outcome::Granted)
+            ));
+            for lesson in &incident.lessons {
+                out.push_str(&format!("  lesson: {}\n", lesson));
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,284 @@
+//! Capability introspection.
+//!
+//! UIs, MCP tool filters, and agent planners all need to know which
+//! operations are callable on a repository. Re-deriving that in each layer
+//! is how they drift from what the types and the gate enforce. The answer
+//! comes from here instead: the typestate decides which methods exist, and
+//! the policy and manifest decide which of those can actually get consent.
+
+use super::policy::{Decision, Policy};
+use super::{
+    json_string, AgentHandle, Capability, DirtyWorkingTree, OperationKind, Protected, ReadOnly, RebaseInProgress,
+    Repository, SafetyGate, Unprotected,
+};
+
+/// Whether a method can be called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    Available,
+    /// Callable with a consent for this operation.
+    NeedsConsent(OperationKind),
+    /// Not callable here, and why.
+    Unavailable(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Callable {
+    pub method: &'static str,
+    pub access: Access,
+    /// The type the repository becomes, if the method consumes it.
+    pub becomes: Option<&'static str>,
+    /// A limit the access is subject to, such as a sparse cone.
+    pub note: Option<String>,
+}
+
+fn safe(method: &'static str) -> Callable {
+    Callable {
+        method,
+        access: Access::Available,
+        becomes: None,
+        note: None,
+    }
+}
+
+fn gated(method: &'static str, operation: OperationKind, becomes: Option<&'static str>) -> Callable {
+    Callable {
+        method,
+        access: Access::NeedsConsent(operation),
+        becomes,
+        note: None,
+    }
+}
+
+/// A repository typestate and the methods it has.
+pub trait Typestate {
+    const NAME: &'static str;
+    fn methods() -> Vec<Callable>;
+}
+
+impl Typestate for Protected {
+    const NAME: &'static str = "Repository<Protected>";
+    fn methods() -> Vec<Callable> {
+        vec![
+            safe("status"),
+            safe("fetch"),
+            safe("diff"),
+            safe("log"),
+            safe("commit"),
+            safe("pull"),
+            safe("create_branch"),
+            safe("create_tag"),
+            safe("push"),
+            safe("commit_changes"),
+            safe("cleanup_branches"),
+            safe("branch"),
+            safe("submodules"),
+            safe("worktree"),
+            safe("clone_to"),
+            Callable {
+                becomes: Some(ReadOnly::NAME),
+                ..safe("read_only")
+            },
+            gated("commit_ci_change", OperationKind::CiConfigChange, None),
+            gated("transfer_ownership", OperationKind::TransferOwnership, Some("TransferredRepository")),
+            gated("remove_protection", OperationKind::RemoveProtection, Some(Unprotected::NAME)),
+            gated("start_rebase", OperationKind::Rebase, Some("Repository<RebaseInProgress>")),
+        ]
+    }
+}
+
+impl Typestate for Unprotected {
+    const NAME: &'static str = "Repository<Unprotected>";
+    fn methods() -> Vec<Callable> {
+        vec![
+            safe("branch"),
+            safe("submodules"),
+            Callable {
+                becomes: Some(Protected::NAME),
+                ..safe("clone_to")
+            },
+            gated("force_push", OperationKind::ForcePush, None),
+            gated("force_push_with", OperationKind::ForcePush, None),
+            gated("filter_repo", OperationKind::FilterRepo, Some("FilteredRepository")),
+            gated("filter_repo_with", OperationKind::FilterRepo, Some("FilteredRepository")),
+            gated("reset_hard", OperationKind::ResetHard, Some("ResetRepository")),
+            Callable {
+                note: Some("force_delete_branch if the branch is checked out or unmerged".to_string()),
+                ..gated("delete_branch", OperationKind::DeleteBranch, None)
+            },
+            gated("delete_tag", OperationKind::DeleteTag, None),
+            gated("remove_remote", OperationKind::RemoveRemote, None),
+            gated("start_rebase", OperationKind::Rebase, Some("Repository<RebaseInProgress>")),
+            Callable {
+                becomes: Some(Protected::NAME),
+                ..safe("restore_protection")
+            },
+        ]
+    }
+}
+
+impl Typestate for ReadOnly {
+    const NAME: &'static str = "Repository<ReadOnly>";
+    fn methods() -> Vec<Callable> {
+        vec![
+            safe("status"),
+            safe("fetch"),
+            safe("diff"),
+            safe("log"),
+            safe("clone_to"),
+            Callable {
+                becomes: Some("WorkingTree"),
+                ..safe("grant_commit")
+            },
+        ]
+    }
+}
+
+impl Typestate for DirtyWorkingTree {
+    const NAME: &'static str = "Repository<DirtyWorkingTree>";
+    fn methods() -> Vec<Callable> {
+        vec![
+            safe("status"),
+            safe("fetch"),
+            safe("diff"),
+            safe("log"),
+            Callable {
+                becomes: Some(Protected::NAME),
+                ..safe("commit_all")
+            },
+            Callable {
+                becomes: Some(Protected::NAME),
+                ..safe("stash")
+            },
+            Callable {
+                becomes: Some(ReadOnly::NAME),
+                ..safe("read_only")
+            },
+        ]
+    }
+}
+
+impl<From: Typestate> Typestate for RebaseInProgress<From> {
+    const NAME: &'static str = "Repository<RebaseInProgress>";
+    fn methods() -> Vec<Callable> {
+        vec![
+            safe("status"),
+            safe("conflicts"),
+            safe("mark_resolved"),
+            Callable {
+                becomes: Some(From::NAME),
+                ..safe("continue_rebase")
+            },
+            Callable {
+                becomes: Some(From::NAME),
+                ..safe("abort_rebase")
+            },
+        ]
+    }
+}
+
+impl<S: Typestate, B> Repository<S, B> {
+    /// The methods this repository's typestate has. Policy is not
+    /// consulted; see [`SafetyGate::capabilities_of`].
+    pub fn capabilities(&self) -> Vec<Callable> {
+        S::methods()
+    }
+}
+
+/// Narrow `callables` by what `policy` decides: denied operations
+/// become unavailable, allowed ones need no interactive consent.
+pub fn under_policy(callables: Vec<Callable>, policy: &Policy) -> Vec<Callable> {
+    callables
+        .into_iter()
+        .map(|mut c| {
+            if let Access::NeedsConsent(operation) = c.access {
+                c.access = match policy.decide(operation) {
+                    Decision::Allow => Access::Available,
+                    Decision::RequireConsent => Access::NeedsConsent(operation),
+                    Decision::Deny => Access::Unavailable(format!(
+                        "policy {} denies {}",
+                        policy.version,
+                        operation.name()
+                    )),
+                };
+            }
+            c
+        })
+        .collect()
+}
+
+impl AgentHandle<'_> {
+    /// What this agent can call: its manifest's methods, and each
+    /// operation it may request, as `policy` would decide it.
+    pub fn callable(&self, policy: &Policy) -> Vec<Callable> {
+        let granted = |cap: Capability| self.manifest.capabilities.contains(&cap);
+        let cone = self.cone.as_ref().map(|c| format!("only paths under {}", c.dirs().join(", ")));
+        let mut out: Vec<Callable> = [
+            ("status", Capability::Read),
+            ("fetch", Capability::Read),
+            ("diff", Capability::Read),
+            ("log", Capability::Read),
+            ("commit", Capability::Commit),
+            ("pull", Capability::Commit),
+            ("create_branch", Capability::Commit),
+            ("create_tag", Capability::Commit),
+            ("push", Capability::Push),
+        ]
+        .into_iter()
+        .map(|(method, cap)| Callable {
+            access: if granted(cap) {
+                Access::Available
+            } else {
+                Access::Unavailable(format!("manifest does not grant {}", cap.name()))
+            },
+            note: if method == "commit" { cone.clone() } else { None },
+            ..safe(method)
+        })
+        .collect();
+        let requests = self.manifest.capabilities.iter().filter_map(|c| match c {
+            Capability::Request(kind) => Some(gated(kind.name(), *kind, None)),
+            _ => None,
+        });
+        out.extend(under_policy(requests.collect(), policy));
+        out
+    }
+}
+
+impl SafetyGate {
+    /// `repo`'s capabilities under this gate's policy and bound manifest.
+    pub fn capabilities_of<S: Typestate, B>(&self, repo: &Repository<S, B>) -> Vec<Callable> {
+        // The manifest is checked first, as in `mint`: a policy that
+        // allows an operation does not widen the agent's manifest.
+        let mut out = repo.capabilities();
+        if let Some((agent, permitted)) = &self.manifest {
+            for c in &mut out {
+                if matches!(c.access, Access::NeedsConsent(op) if !permitted.contains(&op)) {
+                    c.access = Access::Unavailable(format!("outside the manifest for agent '{}'", agent));
+                }
+            }
+        }
+        under_policy(out, &self.policy)
+    }
+}
+
+pub fn to_json(callables: &[Callable]) -> String {
+    let entries: Vec<String> = callables
+        .iter()
+        .map(|c| {
+            let (access, detail) = match &c.access {
+                Access::Available => ("available", "null".to_string()),
+                Access::NeedsConsent(op) => ("needs_consent", json_string(op.name())),
+                Access::Unavailable(why) => ("unavailable", json_string(why)),
+            };
+            format!(
+                "{{\"method\":{},\"access\":\"{}\",\"detail\":{},\"becomes\":{},\"note\":{}}}",
+                json_string(c.method),
+                access,
+                detail,
+                c.becomes.map(json_string).unwrap_or_else(|| "null".to_string()),
+                c.note.as_deref().map(json_string).unwrap_or_else(|| "null".to_string())
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
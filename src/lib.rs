@@ -0,0 +1,190 @@
+//! safe_operations — Rust as the argument for structural safety enforcement.
+//!
+//! On February 25, 2026, a Claude Opus 4.6 agent destroyed two production
+//! repositories. Its safety rules said "NEVER." It did it anyway. The rules
+//! existed in the reasoning layer, and the reasoning layer decided they
+//! didn't apply.
+//!
+//! This crate demonstrates what happens when the rules exist in the type
+//! system instead.
+//!
+//! Rust's compiler is what Claude's safety guidelines should have been:
+//! not suggestions that can be reasoned around, but structural constraints
+//! that make violations unrepresentable. The borrow checker does not care
+//! what the agent "intended." The type system does not accept "I inferred
+//! the user wanted this." If the types don't align, the code does not
+//! compile. Period.
+//!
+//! Every compiler error in this crate is a place the agent would have
+//! been stopped. Every `unsafe` block is an explicit consent marker that
+//! the agent never obtained. Every moved value is a repository the agent
+//! can no longer touch.
+//!
+//! The borrow checker is the watchdog the agent never had.
+//!
+//! ```text
+//! cargo run --bin safe_operations
+//! ```
+//!
+//! The [`incident_as_code`] module has the incident itself, as code that
+//! does not compile; [`claims`] has every guarantee with its probe.
+//!
+//! See: <https://github.com/CIRWEL/obtuse-hubris>
+//!
+//! # Portability
+//!
+//! The guarantees live in the typestate core: [`Repository`] in its
+//! states, [`UserConsent`] and the checks every destructive method makes
+//! on it, the operation vocabulary, the [`backend::GitBackend`] trait,
+//! receipts, playbooks, and the signing they rest on. The core is built on
+//! `core` and `alloc` alone, and it is all that is left with
+//! `default-features = false`, when the crate is `#![no_std]`:
+//!
+//! ```toml
+//! safe-operations = { version = "0.1", default-features = false }
+//! ```
+//!
+//! A device then implements `GitBackend`, sets the time with
+//! `set_unix_time`, and mints consent with an [`embedded::Gate`].
+//! Everything that touches the outside world is behind the default `std`
+//! feature: the [`SafetyGate`] and its audit log and approval channels,
+//! policy file loading, the git shell backend, the archive formats, and
+//! both binaries. CI builds and lints the core without it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// `println!`, with std. Without std there is no stdout: the line is
+/// dropped, and what happened is in the return value, as it always is.
+macro_rules! say {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "std")]
+        std::println!($($arg)*);
+        #[cfg(not(feature = "std"))]
+        let _ = core::format_args!($($arg)*);
+    }};
+}
+
+// Each typestate section lives in its own module and is re-exported here,
+// so the public paths (`safe_operations::Repository`, ...) stay flat.
+mod state;
+pub use state::*;
+mod operation;
+pub use operation::*;
+mod clock;
+pub use clock::unix_now;
+#[cfg(not(feature = "std"))]
+pub use clock::set_unix_time;
+mod consent;
+pub use consent::*;
+mod repository;
+pub use repository::*;
+mod branch;
+pub use branch::*;
+mod submodule;
+pub use submodule::*;
+mod worktree;
+pub use worktree::*;
+mod destroyed;
+pub use destroyed::*;
+pub mod alternatives;
+mod commit;
+pub use commit::*;
+mod host_key;
+pub use host_key::*;
+mod agent;
+pub use agent::*;
+pub mod backend;
+mod receipt;
+pub use receipt::*;
+mod error;
+pub use error::*;
+pub mod ed25519;
+pub mod embedded;
+
+/// Declares modules that need std: everything outside the typestate core.
+macro_rules! with_std {
+    ($($item:item)*) => { $(#[cfg(feature = "std")] $item)* };
+}
+
+with_std! {
+    mod bare;
+    pub use bare::*;
+    mod rebase;
+    mod transfer;
+    pub use transfer::*;
+    pub mod impact;
+    mod plan;
+    pub use plan::*;
+    mod cleanup;
+    pub use cleanup::*;
+    mod gate;
+    pub use gate::*;
+    pub mod agent_identity;
+    mod audit;
+    pub use audit::*;
+    pub mod blocked;
+    pub mod report;
+    pub mod policy;
+    pub mod scenario;
+    pub mod export;
+    pub mod context;
+    pub mod post_ops;
+    pub mod fs_ops;
+    pub mod claims;
+    pub mod sched_ops;
+    pub mod wire;
+    pub mod dedup;
+    pub mod ceremony;
+    pub mod sparse;
+    pub mod workflow;
+    pub mod federation;
+    pub mod fleet;
+    pub mod container_ops;
+    pub mod search_ops;
+    pub mod mq_ops;
+    pub mod backup_admin_ops;
+    pub mod approver;
+    #[cfg(feature = "chaos")]
+    pub mod chaos;
+    pub mod adopt;
+    pub mod metrics;
+    pub mod vcs;
+    pub mod readback;
+    pub mod feedback;
+    pub mod notes;
+    pub mod compose;
+    pub mod consent_gate;
+    pub mod session;
+    pub mod tool_risk;
+    pub mod archive;
+    pub mod staged;
+    pub mod scoped_env;
+    pub mod introspect;
+    pub mod consent_client;
+    pub mod progress;
+    pub mod observe;
+    pub mod behavior;
+    pub mod replay;
+    pub mod revocation;
+    pub mod nonce_ledger;
+    pub mod time_lock;
+    pub mod platform_ops;
+    pub mod quorum;
+    pub mod tiers;
+    pub mod incidents;
+    pub mod airgap;
+    pub mod consent_queue;
+    pub mod delegation;
+    pub mod consent_backend;
+    pub mod provenance;
+    pub mod persist;
+    pub mod throttle;
+    pub mod cooldown;
+    pub mod identity;
+    pub mod budget;
+    pub mod hardware_key;
+    pub mod totp;
+    pub mod incident_as_code;
+}
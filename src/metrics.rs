@@ -0,0 +1,126 @@
+//! Differentially private export of aggregate gate metrics.
+//!
+//! Per-operation request and denial counts, summed over many tenants'
+//! sessions, with Laplace noise added before anything leaves the process.
+//!
+//! Guarantee: [`export`] is `epsilon`-differentially private at the tenant
+//! level. Each tenant contributes at most `per_tenant_cap` decisions (the
+//! rest are dropped before counting), so adding or removing one tenant
+//! changes the vector of counts by at most `2 * per_tenant_cap` in L1 norm
+//! — each decision adds one request and at most one denial. Noise is drawn
+//! from Laplace(`2 * per_tenant_cap / epsilon`) per count. Rates computed
+//! from the noisy counts are post-processing and cost no extra budget.
+//!
+//! Not covered: repeated exports of the same data each spend `epsilon`
+//! again; the sampler uses floating point and std's `RandomState`, not a
+//! hardened discrete mechanism. Publishing should go through a budget
+//! tracker and a vetted sampler.
+
+use super::policy::{Decision, Session};
+use super::{json_string, random_u64, OperationKind};
+
+#[derive(Clone, Copy, Debug)]
+pub struct DpConfig {
+    pub epsilon: f64,
+    pub per_tenant_cap: u64,
+}
+
+impl Default for DpConfig {
+    fn default() -> Self {
+        DpConfig {
+            epsilon: 1.0,
+            per_tenant_cap: 100,
+        }
+    }
+}
+
+/// Exact counts. Never exported; [`export`] is the only way out.
+struct Counts {
+    rows: Vec<(OperationKind, u64, u64)>,
+}
+
+fn tally(sessions: &[&Session], cap: u64) -> Counts {
+    let mut rows: Vec<(OperationKind, u64, u64)> = OperationKind::ALL.iter().map(|k| (*k, 0, 0)).collect();
+    for session in sessions {
+        for recorded in session.decisions.iter().take(cap as usize) {
+            if let Some(row) = rows.iter_mut().find(|r| r.0 == recorded.operation) {
+                row.1 += 1;
+                if recorded.decision == Decision::Deny {
+                    row.2 += 1;
+                }
+            }
+        }
+    }
+    Counts { rows }
+}
+
+/// A sample from Laplace(0, scale), by inverse CDF.
+fn laplace(scale: f64) -> f64 {
+    // Uniform on (-0.5, 0.5), excluding the endpoints.
+    let u = ((random_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64 - 0.5;
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// Noisy aggregate counts, safe to publish under the module's guarantee.
+#[derive(Clone, Debug)]
+pub struct NoisyMetrics {
+    pub epsilon: f64,
+    /// `(operation, requests, denials)`, noised and clamped at zero.
+    /// The number of tenants is deliberately absent: it is exact, and
+    /// would reveal whether any one tenant took part.
+    pub rows: Vec<(OperationKind, f64, f64)>,
+}
+
+impl NoisyMetrics {
+    /// Overall denial rate. Post-processing: no extra privacy cost.
+    pub fn denial_rate(&self) -> f64 {
+        let requests: f64 = self.rows.iter().map(|r| r.1).sum();
+        let denials: f64 = self.rows.iter().map(|r| r.2).sum();
+        if requests > 0.0 { (denials / requests).min(1.0) } else { 0.0 }
+    }
+
+    pub fn to_json(&self) -> String {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|(op, requests, denials)| {
+                format!(
+                    "{{\"operation\":{},\"requests\":{:.1},\"denials\":{:.1}}}",
+                    json_string(op.name()),
+                    requests,
+                    denials
+                )
+            })
+            .collect();
+        format!(
+            "{{\"epsilon\":{},\"denial_rate\":{:.4},\"operations\":[{}]}}",
+            self.epsilon,
+            self.denial_rate(),
+            rows.join(",")
+        )
+    }
+}
+
+/// Aggregate `sessions`, one per tenant, and add calibrated noise.
+pub fn export(sessions: &[&Session], config: &DpConfig) -> Result<NoisyMetrics, String> {
+    if !(config.epsilon.is_finite() && config.epsilon > 0.0) {
+        return Err(format!("epsilon must be positive and finite, got {}", config.epsilon));
+    }
+    if config.per_tenant_cap == 0 {
+        return Err("per_tenant_cap must be at least 1".to_string());
+    }
+    let scale = 2.0 * config.per_tenant_cap as f64 / config.epsilon;
+    let rows = tally(sessions, config.per_tenant_cap)
+        .rows
+        .into_iter()
+        .map(|(op, requests, denials)| {
+            let requests = (requests as f64 + laplace(scale)).max(0.0);
+            let denials = (denials as f64 + laplace(scale)).max(0.0);
+            (op, requests, denials)
+        })
+        .collect();
+    Ok(NoisyMetrics {
+        epsilon: config.epsilon,
+        rows,
+    })
+}
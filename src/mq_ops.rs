@@ -0,0 +1,345 @@
+//! Guarded message queue administration.
+//!
+//! Purging a "stuck" queue drops every order in it; deleting a topic
+//! strands every consumer group reading it. Producing, consuming, and
+//! describing are free. Deleting a topic, purging a queue, and resetting a
+//! consumer group's offsets go through an [`MqPlan`] that counts the
+//! messages affected and lists each consumer group with its lag and live
+//! members, so the approver sees who is downstream.
+
+use super::{
+    ConsentDenied, DestructionPlan, OperationKind, Protected, RecoveryCost, SafetyGate, UserConsent,
+};
+use core::marker::PhantomData;
+
+/// Consent scope for destructive queue changes.
+pub struct QueueChange;
+
+/// A consumer group as the broker reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub group: String,
+    /// Messages retained but not yet consumed by the group.
+    pub lag: u64,
+    pub active_members: u32,
+}
+
+/// Where a consumer group's offset is reset to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetReset {
+    /// Re-read everything retained.
+    Earliest,
+    /// Skip everything not yet read.
+    Latest,
+}
+
+/// The broker API the plan's checks go through. A queue is treated as
+/// a topic with the broker's own consumer.
+pub trait Broker {
+    fn destinations(&self) -> Vec<String>;
+    /// Messages currently retained.
+    fn message_count(&self, destination: &str) -> u64;
+    fn consumer_groups(&self, destination: &str) -> Vec<GroupInfo>;
+    fn produce(&mut self, destination: &str, message: &str);
+    fn consume(&mut self, destination: &str, group: &str, max: usize) -> Vec<String>;
+    fn delete(&mut self, destination: &str);
+    fn purge(&mut self, destination: &str);
+    fn reset_offsets(&mut self, destination: &str, group: &str, to: OffsetReset);
+}
+
+/// A broker held in memory, for demonstrations and fixtures.
+#[derive(Default)]
+pub struct InMemoryBroker {
+    /// `(destination, retained messages)`.
+    pub destinations: Vec<(String, Vec<String>)>,
+    /// `(destination, group, next offset, active members)`.
+    pub groups: Vec<(String, String, usize, u32)>,
+}
+
+impl InMemoryBroker {
+    fn messages(&self, destination: &str) -> &[String] {
+        self.destinations
+            .iter()
+            .find(|(d, _)| d == destination)
+            .map_or(&[], |(_, m)| m.as_slice())
+    }
+}
+
+impl Broker for InMemoryBroker {
+    fn destinations(&self) -> Vec<String> {
+        self.destinations.iter().map(|(d, _)| d.clone()).collect()
+    }
+
+    fn message_count(&self, destination: &str) -> u64 {
+        self.messages(destination).len() as u64
+    }
+
+    fn consumer_groups(&self, destination: &str) -> Vec<GroupInfo> {
+        let total = self.messages(destination).len();
+        self.groups
+            .iter()
+            .filter(|(d, ..)| d == destination)
+            .map(|(_, group, offset, members)| GroupInfo {
+                group: group.clone(),
+                lag: total.saturating_sub(*offset) as u64,
+                active_members: *members,
+            })
+            .collect()
+    }
+
+    fn produce(&mut self, destination: &str, message: &str) {
+        match self.destinations.iter_mut().find(|(d, _)| d == destination) {
+            Some((_, messages)) => messages.push(message.to_string()),
+            None => self.destinations.push((destination.to_string(), vec![message.to_string()])),
+        }
+    }
+
+    fn consume(&mut self, destination: &str, group: &str, max: usize) -> Vec<String> {
+        let messages = self.messages(destination).to_vec();
+        let entry = match self.groups.iter().position(|(d, g, ..)| d == destination && g == group) {
+            Some(i) => &mut self.groups[i],
+            None => {
+                self.groups.push((destination.to_string(), group.to_string(), 0, 0));
+                self.groups.last_mut().expect("just pushed")
+            }
+        };
+        let read: Vec<String> = messages.iter().skip(entry.2).take(max).cloned().collect();
+        entry.2 += read.len();
+        read
+    }
+
+    fn delete(&mut self, destination: &str) {
+        self.destinations.retain(|(d, _)| d != destination);
+        self.groups.retain(|(d, ..)| d != destination);
+    }
+
+    fn purge(&mut self, destination: &str) {
+        for (d, messages) in &mut self.destinations {
+            if d == destination {
+                messages.clear();
+            }
+        }
+        for (d, _, offset, _) in &mut self.groups {
+            if d == destination {
+                *offset = 0;
+            }
+        }
+    }
+
+    fn reset_offsets(&mut self, destination: &str, group: &str, to: OffsetReset) {
+        let total = self.messages(destination).len();
+        for (d, g, offset, _) in &mut self.groups {
+            if d == destination && g == group {
+                *offset = match to {
+                    OffsetReset::Earliest => 0,
+                    OffsetReset::Latest => total,
+                };
+            }
+        }
+    }
+}
+
+/// What a destructive queue change does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MqAction {
+    DeleteTopic,
+    PurgeQueue,
+    ResetOffsets { group: String, to: OffsetReset },
+}
+
+impl MqAction {
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            MqAction::DeleteTopic => OperationKind::DeleteTopic,
+            MqAction::PurgeQueue => OperationKind::PurgeQueue,
+            MqAction::ResetOffsets { .. } => OperationKind::ResetConsumerOffsets,
+        }
+    }
+}
+
+/// A queue change, checked against the broker before anyone is asked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MqPlan {
+    pub destination: String,
+    pub action: MqAction,
+    pub messages: u64,
+    pub groups: Vec<GroupInfo>,
+}
+
+impl MqPlan {
+    pub fn to_destruction_plan(&self) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(self.action.operation(), &self.destination);
+        let affected: Vec<&GroupInfo> = match &self.action {
+            MqAction::ResetOffsets { group, .. } => self.groups.iter().filter(|g| g.group == *group).collect(),
+            _ => self.groups.iter().collect(),
+        };
+        match &self.action {
+            MqAction::DeleteTopic => {
+                plan = plan.item(format!("{} and its {} retained messages", self.destination, self.messages));
+                if self.messages > 0 {
+                    plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                        "{} messages in {}",
+                        self.messages, self.destination
+                    )));
+                }
+            }
+            MqAction::PurgeQueue => {
+                plan = plan.item(format!("{} messages in {}", self.messages, self.destination));
+                if self.messages > 0 {
+                    plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                        "{} messages in {}",
+                        self.messages, self.destination
+                    )));
+                }
+            }
+            MqAction::ResetOffsets { group, to } => {
+                let lag = affected.first().map_or(0, |g| g.lag);
+                match to {
+                    OffsetReset::Earliest => {
+                        let replayed = self.messages - lag;
+                        plan = plan.item(format!("{} re-reads {} already-processed messages", group, replayed));
+                        plan.costs.push(RecoveryCost::Effort {
+                            what: format!("undo side effects of {} replayed messages if consumers are not idempotent", replayed),
+                            hours: (replayed as f64 / 100_000.0).max(0.5),
+                        });
+                    }
+                    OffsetReset::Latest => {
+                        plan = plan.item(format!("{} skips {} unread messages", group, lag));
+                        if lag > 0 {
+                            plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                                "{} messages {} never processes, once retention expires",
+                                lag, group
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        for g in affected {
+            plan = plan.item(format!(
+                "consumer group {}: lag {}, {} active members",
+                g.group, g.lag, g.active_members
+            ));
+        }
+        plan
+    }
+}
+
+/// Consent to apply exactly one queue plan.
+pub struct MqApproval {
+    plan: MqPlan,
+    _consent: UserConsent<QueueChange>,
+}
+
+/// A message broker.
+pub struct MessageBroker<State = Protected> {
+    broker: Box<dyn Broker>,
+    _state: PhantomData<State>,
+}
+
+impl MessageBroker<Protected> {
+    pub fn connect(broker: Box<dyn Broker>) -> Self {
+        MessageBroker {
+            broker,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn produce(&mut self, destination: &str, message: &str) {
+        self.broker.produce(destination, message);
+    }
+
+    pub fn consume(&mut self, destination: &str, group: &str, max: usize) -> Vec<String> {
+        self.broker.consume(destination, group, max)
+    }
+
+    pub fn describe(&self, destination: &str) -> String {
+        let groups: Vec<String> = self
+            .broker
+            .consumer_groups(destination)
+            .iter()
+            .map(|g| format!("{} (lag {}, {} active)", g.group, g.lag, g.active_members))
+            .collect();
+        format!(
+            "{}: {} messages; groups: {}",
+            destination,
+            self.broker.message_count(destination),
+            if groups.is_empty() { "none".to_string() } else { groups.join(", ") }
+        )
+    }
+
+    /// Check a destructive change against the broker. Resetting the
+    /// offsets of a group with live members is refused outright: the
+    /// members would commit over the reset.
+    pub fn plan(&self, destination: &str, action: MqAction) -> Result<MqPlan, String> {
+        if !self.broker.destinations().iter().any(|d| d == destination) {
+            return Err(format!("no topic or queue named {}", destination));
+        }
+        let groups = self.broker.consumer_groups(destination);
+        if let MqAction::ResetOffsets { group, .. } = &action {
+            match groups.iter().find(|g| g.group == *group) {
+                None => return Err(format!("no consumer group {} on {}", group, destination)),
+                Some(g) if g.active_members > 0 => {
+                    return Err(format!("{} has {} active members; stop them first", group, g.active_members))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(MqPlan {
+            destination: destination.to_string(),
+            messages: self.broker.message_count(destination),
+            action,
+            groups,
+        })
+    }
+
+    fn check(&self, approval: &MqApproval) -> Result<(), String> {
+        let current = self.plan(&approval.plan.destination, approval.plan.action.clone())?;
+        if current != approval.plan {
+            return Err("broker changed since the plan was approved".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn delete_topic(&mut self, approval: MqApproval) -> Result<String, String> {
+        if approval.plan.action != MqAction::DeleteTopic {
+            return Err("approval is not for a topic deletion".to_string());
+        }
+        self.check(&approval)?;
+        self.broker.delete(&approval.plan.destination);
+        Ok(format!("deleted {} ({} messages)", approval.plan.destination, approval.plan.messages))
+    }
+
+    pub fn purge_queue(&mut self, approval: MqApproval) -> Result<String, String> {
+        if approval.plan.action != MqAction::PurgeQueue {
+            return Err("approval is not for a purge".to_string());
+        }
+        self.check(&approval)?;
+        self.broker.purge(&approval.plan.destination);
+        Ok(format!("purged {} messages from {}", approval.plan.messages, approval.plan.destination))
+    }
+
+    pub fn reset_consumer_offsets(&mut self, approval: MqApproval) -> Result<String, String> {
+        let MqAction::ResetOffsets { group, to } = &approval.plan.action else {
+            return Err("approval is not for an offset reset".to_string());
+        };
+        self.check(&approval)?;
+        self.broker.reset_offsets(&approval.plan.destination, group, *to);
+        Ok(format!("reset {} on {} to {:?}", group, approval.plan.destination, to))
+    }
+}
+
+impl SafetyGate {
+    /// Request consent for a queue plan. A plan that loses messages
+    /// needs the typed acknowledgment.
+    pub fn request_mq_consent(
+        &mut self,
+        plan: &MqPlan,
+    ) -> Result<MqApproval, ConsentDenied> {
+        let consent = self.plan_consent(&plan.to_destruction_plan())?;
+        Ok(MqApproval {
+            plan: plan.clone(),
+            _consent: consent,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+//! Replay protection for consent that leaves the process it was minted in.
+//!
+//! Within one process the type system already makes consent single-use:
+//! a destructive method takes it by value. But consent written to a state
+//! file by [`persist`] comes back as a new value in every process that
+//! resumes the file — and an old copy of the file brings back consent
+//! that was since used. So every consent's nonce is registered in its
+//! gate's [`Ledger`] when minted or resumed, and burned the first time it
+//! is presented; a nonce already burned, or never registered, is refused.
+//!
+//! With [`SafetyGate::burn_nonces_in`] the burned nonces are also kept in
+//! a directory, one empty file per nonce. Creating the file is the burn,
+//! and the filesystem lets only one process create it, so gates in
+//! different processes sharing the directory cannot redeem the same
+//! consent twice. A burn that cannot be written is a refusal: a nonce
+//! not recorded as spent is not allowed to be spent.
+
+use super::{ConsentDenied, SafetyGate, UserConsent};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct State {
+    issued: BTreeSet<u64>,
+    spent: BTreeSet<u64>,
+    dir: Option<PathBuf>,
+}
+
+/// The nonces a gate has issued and the ones already spent, shared by
+/// the gate and everything it minted.
+#[derive(Clone, Debug, Default)]
+pub struct Ledger(Arc<Mutex<State>>);
+
+impl Ledger {
+    pub(crate) fn register(&self, nonce: u64) {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).issued.insert(nonce);
+    }
+
+    /// Whether `nonce` was already spent, here or by another process
+    /// sharing the directory.
+    pub fn is_spent(&self, nonce: u64) -> bool {
+        let Ok(state) = self.0.lock() else {
+            return true;
+        };
+        state.spent.contains(&nonce) || state.dir.as_deref().is_some_and(|dir| spent_file(dir, nonce).exists())
+    }
+
+    /// Spend `nonce`, or say why it cannot be.
+    fn burn(&self, nonce: u64) -> Result<(), String> {
+        // A ledger that cannot be read answers no: unknown is not a yes.
+        let Ok(mut state) = self.0.lock() else {
+            return Err("the nonce ledger is unavailable".to_string());
+        };
+        if !state.issued.contains(&nonce) {
+            return Err(format!("nonce {:016x} was never issued by this gate", nonce));
+        }
+        if state.spent.contains(&nonce) {
+            return Err(format!("consent {:016x} was already redeemed", nonce));
+        }
+        if let Some(dir) = &state.dir {
+            match fs::OpenOptions::new().write(true).create_new(true).open(spent_file(dir, nonce)) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    state.spent.insert(nonce);
+                    return Err(format!("consent {:016x} was already redeemed by another process", nonce));
+                }
+                Err(e) => return Err(format!("cannot record nonce {:016x} as spent: {}", nonce, e)),
+            }
+        }
+        state.spent.insert(nonce);
+        Ok(())
+    }
+}
+
+fn spent_file(dir: &Path, nonce: u64) -> PathBuf {
+    dir.join(format!("{:016x}", nonce))
+}
+
+impl<Scope> UserConsent<Scope> {
+    /// Burn this consent's nonce, or refuse it.
+    pub(crate) fn redeem(&self) -> Result<(), ConsentDenied> {
+        self._ledger.burn(self._nonce).map_err(|why| ConsentDenied::new(self._kind, why))
+    }
+}
+
+impl SafetyGate {
+    /// Keep spent nonces in `dir` as well, from now on, creating it if
+    /// needed. Returns how many it already holds.
+    pub fn burn_nonces_in(&mut self, dir: impl Into<PathBuf>) -> io::Result<usize> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let spent = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| u64::from_str_radix(&entry.file_name().to_string_lossy(), 16).ok())
+            .count();
+        self.log(format!("NONCE LEDGER: {} ({} spent)", dir.display(), spent));
+        self.ledger.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).dir = Some(dir);
+        Ok(spent)
+    }
+
+    /// The ledger every consent this gate mints is redeemed against.
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+}
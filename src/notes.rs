@@ -0,0 +1,86 @@
+//! Receipts mirrored into git notes.
+//!
+//! Each receipt is appended as a note under [`NOTES_REF`] on the commits it
+//! concerns, so `git log --show-notes=safety` shows which operations were
+//! approved right next to the history they touched. A note is readable
+//! lines followed by the receipt in [`wire`] format, which [`read`] parses
+//! back. Notes are not pushed by default; share them with
+//! `git push origin refs/notes/safety`.
+//!
+//! This is a mirror for convenience, not the record: the audit log remains
+//! the source of truth, and anyone with push access can edit notes.
+
+use super::{wire, Receipt};
+use std::process::Command;
+
+pub const NOTES_REF: &str = "refs/notes/safety";
+
+const WIRE_PREFIX: &str = "Safety-Receipt: ";
+
+/// The note text for one receipt.
+pub fn note_text(receipt: &Receipt) -> String {
+    let mut out = format!(
+        "Safety-Operation: {} ({})\nSafety-Outcome: {}\n",
+        receipt.operation.name(),
+        receipt.operation.severity().label(),
+        receipt.outcome.label()
+    );
+    if let Some(digest) = &receipt.ceremony {
+        out.push_str(&format!("Safety-Ceremony: {}\n", digest));
+    }
+    if !receipt.gate_chain.is_empty() {
+        out.push_str(&format!("Safety-Approved-Via: {}\n", receipt.gate_chain.join(" -> ")));
+    }
+    if let Some(verification) = &receipt.verification {
+        out.push_str(&format!("Safety-Verification: {}\n", verification));
+    }
+    if let Some(reason) = &receipt.approval_reason {
+        out.push_str(&format!("Safety-Reason: {}\n", reason.replace('\n', " ")));
+    }
+    if !receipt.approved_by.is_empty() {
+        out.push_str(&format!("Safety-Approved-By: {}\n", receipt.approved_by.join("; ")));
+    }
+    if receipt.observed {
+        out.push_str("Safety-Mode: observe (simulated; nothing was executed)\n");
+    }
+    out.push_str(WIRE_PREFIX);
+    out.push_str(&wire::encode_receipt(receipt));
+    out
+}
+
+/// Append `receipt` as a note on each of `targets` (commits or refs) in
+/// the repository at `repo_path`. Appending keeps earlier receipts on a
+/// commit that more than one operation touched.
+pub fn attach(repo_path: &str, targets: &[&str], receipt: &Receipt) -> Result<(), String> {
+    let text = note_text(receipt);
+    for target in targets {
+        let out = Command::new("git")
+            .args(["-C", repo_path, "notes", "--ref", NOTES_REF, "append", "-m", &text, target])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !out.status.success() {
+            return Err(format!(
+                "cannot attach note to {}: {}",
+                target,
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The receipts noted on `target`, oldest first. No note is no receipts.
+pub fn read(repo_path: &str, target: &str) -> Result<Vec<Receipt>, String> {
+    let out = Command::new("git")
+        .args(["-C", repo_path, "notes", "--ref", NOTES_REF, "show", target])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Ok(Vec::new());
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix(WIRE_PREFIX))
+        .map(|encoded| wire::decode_receipt(encoded).map_err(|e| format!("{:?}", e)))
+        .collect()
+}
@@ -0,0 +1,133 @@
+//! A gate that can be watched but not used.
+//!
+//! In [`GateMode::Observe`] every request still goes through the deny-list,
+//! the policy, the prompt and the challenge, exactly as it would for real,
+//! so a new teammate or an auditor can see what the gate would ask. The
+//! consent it mints is marked observe-only: the typed operations announce
+//! themselves as simulated, receipts carry `observed`, and every audit
+//! entry and prompt line is tagged.
+//!
+//! Executors that run real `git` take their working copy from
+//! [`SafetyGate::workspace`], which in observe mode is a [`ShadowClone`]:
+//! a throwaway copy of the repository whose `origin` is a local bare clone,
+//! so even a push lands somewhere that is deleted afterwards.
+//!
+//! The switch only goes one way. A gate put in observe mode stays there.
+
+use super::SafetyGate;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GateMode {
+    /// Granted consent executes.
+    #[default]
+    Enforce,
+    /// Granted consent is simulated against a shadow clone.
+    Observe,
+}
+
+impl GateMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            GateMode::Enforce => "enforce",
+            GateMode::Observe => "observe",
+        }
+    }
+}
+
+fn git(args: &[&str]) -> io::Result<()> {
+    let out = Command::new("git").args(args).output()?;
+    if !out.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&out.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+/// A disposable copy of a repository, removed on drop.
+pub struct ShadowClone {
+    root: PathBuf,
+    work: PathBuf,
+}
+
+impl ShadowClone {
+    /// Clone `repo_path` twice under a temporary directory: a bare
+    /// `remote.git` and a `work` clone of it, whose `origin` is that
+    /// bare clone rather than anything the real repository pushes to.
+    pub fn of(repo_path: &str) -> io::Result<ShadowClone> {
+        let root = std::env::temp_dir().join(format!(
+            "safe-operations-shadow-{}-{:08x}",
+            std::process::id(),
+            super::random_u64() as u32
+        ));
+        std::fs::create_dir_all(&root)?;
+        let shadow = ShadowClone {
+            work: root.join("work"),
+            root,
+        };
+        let remote = shadow.root.join("remote.git");
+        let (remote, work) = (remote.to_string_lossy(), shadow.work.to_string_lossy());
+        git(&["clone", "--quiet", "--bare", "--no-local", repo_path, &remote])?;
+        git(&["clone", "--quiet", &remote, &work])?;
+        Ok(shadow)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.work
+    }
+}
+
+impl Drop for ShadowClone {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Where an executor should run.
+pub enum Workspace {
+    Real(String),
+    Shadow(ShadowClone),
+}
+
+impl Workspace {
+    pub fn path(&self) -> String {
+        match self {
+            Workspace::Real(path) => path.clone(),
+            Workspace::Shadow(shadow) => shadow.path().to_string_lossy().into_owned(),
+        }
+    }
+
+    pub fn is_shadow(&self) -> bool {
+        matches!(self, Workspace::Shadow(_))
+    }
+}
+
+impl SafetyGate {
+    /// Put the gate in observe mode for the rest of its life.
+    pub fn observe(&mut self) {
+        if self.mode == GateMode::Observe {
+            return;
+        }
+        self.log("MODE: observe; every grant from here on is simulated".to_string());
+        self.mode = GateMode::Observe;
+    }
+
+    pub fn mode(&self) -> GateMode {
+        self.mode
+    }
+
+    /// The working copy for an executor that runs real commands against
+    /// `repo_path`: the repository itself, or in observe mode a fresh
+    /// shadow clone of it.
+    pub fn workspace(&mut self, repo_path: &str) -> io::Result<Workspace> {
+        match self.mode {
+            GateMode::Enforce => Ok(Workspace::Real(repo_path.to_string())),
+            GateMode::Observe => {
+                let shadow = ShadowClone::of(repo_path)?;
+                self.log(format!("SHADOW: {} -> {}", repo_path, shadow.path().display()));
+                Ok(Workspace::Shadow(shadow))
+            }
+        }
+    }
+}
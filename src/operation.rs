@@ -0,0 +1,598 @@
+//! Operation vocabulary — what consent is for, and what refusing it says.
+//!
+//! Needs only `core` and `alloc`, like the rest of the typestate core.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Consent scope for the repository-level destructive operations.
+pub struct General;
+
+/// Consent scope for handing a repository to another owner. Only a
+/// completed [`TransferCeremony`](crate::TransferCeremony) mints it.
+pub struct OwnershipTransfer;
+
+/// Consent scope for rewriting a commit so that it loses its
+/// `Co-Authored-By` trailers. Only
+/// [`request_attribution_consent`](crate::SafetyGate::request_attribution_consent)
+/// mints it, and nothing else accepts it.
+pub struct AttributionChange;
+
+/// Consent scope for changes to CI/CD pipeline definitions.
+///
+/// Editing `.github/workflows` is granting yourself code execution on
+/// runners that hold deployment secrets. It gets its own consent type.
+pub struct CiConfigChange;
+
+/// The destructive operations the gate knows how to reason about.
+///
+/// Policy rules, manifests, and audit entries refer to operations by
+/// these names, never by the agent's free-form description of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperationKind {
+    RemoveProtection,
+    ForcePush,
+    FilterRepo,
+    ResetHard,
+    DeleteBranch,
+    /// Deleting the checked-out branch, or one not merged into it.
+    ForceDeleteBranch,
+    DeleteTag,
+    RemoveRemote,
+    Rebase,
+    DropStash,
+    /// Rewriting a commit so that it loses `Co-Authored-By` trailers.
+    StripAttribution,
+    CiConfigChange,
+    DeleteFiles,
+    TransferOwnership,
+    EditSchedule,
+    DeleteImageTag,
+    OverwriteImageTag,
+    DeleteImageRepository,
+    DeleteIndex,
+    DeleteByQuery,
+    CloseIndex,
+    DeleteTopic,
+    PurgeQueue,
+    ResetConsumerOffsets,
+    ChangeVisibility,
+    MakePublic,
+    ChangeLicense,
+    DeleteBackup,
+    ShortenRetention,
+    DisableBackupSchedule,
+}
+
+impl OperationKind {
+    pub const ALL: &'static [OperationKind] = &[
+        OperationKind::RemoveProtection,
+        OperationKind::ForcePush,
+        OperationKind::FilterRepo,
+        OperationKind::ResetHard,
+        OperationKind::DeleteBranch,
+        OperationKind::ForceDeleteBranch,
+        OperationKind::DeleteTag,
+        OperationKind::RemoveRemote,
+        OperationKind::Rebase,
+        OperationKind::DropStash,
+        OperationKind::StripAttribution,
+        OperationKind::CiConfigChange,
+        OperationKind::DeleteFiles,
+        OperationKind::TransferOwnership,
+        OperationKind::EditSchedule,
+        OperationKind::DeleteImageTag,
+        OperationKind::OverwriteImageTag,
+        OperationKind::DeleteImageRepository,
+        OperationKind::DeleteIndex,
+        OperationKind::DeleteByQuery,
+        OperationKind::CloseIndex,
+        OperationKind::DeleteTopic,
+        OperationKind::PurgeQueue,
+        OperationKind::ResetConsumerOffsets,
+        OperationKind::ChangeVisibility,
+        OperationKind::MakePublic,
+        OperationKind::ChangeLicense,
+        OperationKind::DeleteBackup,
+        OperationKind::ShortenRetention,
+        OperationKind::DisableBackupSchedule,
+    ];
+
+    /// The stable name used in policy files and audit entries.
+    pub fn name(self) -> &'static str {
+        match self {
+            OperationKind::RemoveProtection => "remove_protection",
+            OperationKind::ForcePush => "force_push",
+            OperationKind::FilterRepo => "filter_repo",
+            OperationKind::ResetHard => "reset_hard",
+            OperationKind::DeleteBranch => "delete_branch",
+            OperationKind::ForceDeleteBranch => "force_delete_branch",
+            OperationKind::DeleteTag => "delete_tag",
+            OperationKind::RemoveRemote => "remove_remote",
+            OperationKind::Rebase => "rebase",
+            OperationKind::DropStash => "drop_stash",
+            OperationKind::StripAttribution => "strip_attribution",
+            OperationKind::CiConfigChange => "ci_config_change",
+            OperationKind::DeleteFiles => "delete_files",
+            OperationKind::TransferOwnership => "transfer_ownership",
+            OperationKind::EditSchedule => "edit_schedule",
+            OperationKind::DeleteImageTag => "delete_image_tag",
+            OperationKind::OverwriteImageTag => "overwrite_image_tag",
+            OperationKind::DeleteImageRepository => "delete_image_repository",
+            OperationKind::DeleteIndex => "delete_index",
+            OperationKind::DeleteByQuery => "delete_by_query",
+            OperationKind::CloseIndex => "close_index",
+            OperationKind::DeleteTopic => "delete_topic",
+            OperationKind::PurgeQueue => "purge_queue",
+            OperationKind::ResetConsumerOffsets => "reset_consumer_offsets",
+            OperationKind::ChangeVisibility => "change_visibility",
+            OperationKind::MakePublic => "make_public",
+            OperationKind::ChangeLicense => "change_license",
+            OperationKind::DeleteBackup => "delete_backup",
+            OperationKind::ShortenRetention => "shorten_retention",
+            OperationKind::DisableBackupSchedule => "disable_backup_schedule",
+        }
+    }
+
+    /// How bad it is if this operation turns out to be a mistake.
+    pub fn severity(self) -> Severity {
+        match self {
+            OperationKind::DeleteBranch
+            | OperationKind::DeleteTag
+            | OperationKind::RemoveRemote
+            | OperationKind::CiConfigChange
+            | OperationKind::EditSchedule
+            | OperationKind::CloseIndex
+            | OperationKind::ChangeVisibility => Severity::Medium,
+            OperationKind::RemoveProtection
+            | OperationKind::ForcePush
+            | OperationKind::ResetHard
+            | OperationKind::ForceDeleteBranch
+            | OperationKind::Rebase
+            | OperationKind::DropStash
+            | OperationKind::DeleteFiles
+            | OperationKind::DeleteImageTag
+            | OperationKind::OverwriteImageTag
+            | OperationKind::DeleteByQuery
+            | OperationKind::PurgeQueue
+            | OperationKind::ResetConsumerOffsets
+            | OperationKind::MakePublic
+            | OperationKind::ChangeLicense => Severity::High,
+            OperationKind::FilterRepo
+            | OperationKind::StripAttribution
+            | OperationKind::TransferOwnership
+            | OperationKind::DeleteImageRepository
+            | OperationKind::DeleteIndex
+            | OperationKind::DeleteTopic
+            | OperationKind::DeleteBackup
+            | OperationKind::ShortenRetention
+            | OperationKind::DisableBackupSchedule => Severity::Critical,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OperationKind> {
+        OperationKind::ALL.iter().copied().find(|k| k.name() == name)
+    }
+}
+
+/// A destructive repository operation, spelled out.
+///
+/// A free-form description is whatever the agent chooses to call the
+/// operation, and "routine cleanup" is a description. A descriptor is what
+/// the operation actually is: the gate renders the prompt from it, the
+/// consent carries it under the gate's signature, and the repository
+/// methods refuse consent whose descriptor is not the call being made. A
+/// force-push approved for `release` does not push `main`; a history
+/// rewrite approved for one callback does not run another.
+///
+/// `repo` is the repository's name or path, as for consent's own binding.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OperationDescriptor {
+    RemoveProtection { repo: String },
+    /// Lift protection from one branch, leaving the others protected.
+    UnprotectBranch { repo: String, branch: String },
+    ForcePush { repo: String, branch: String },
+    FilterRepo { repo: String, callback: String },
+    ResetHard { repo: String },
+    DeleteBranch { repo: String, branch: String },
+    /// Delete the checked-out branch, or one with commits not merged into it.
+    ForceDeleteBranch { repo: String, branch: String },
+    /// Delete exactly these branches, each after writing a backup ref.
+    CleanupBranches { repo: String, branches: Vec<String> },
+    DeleteTag { repo: String, tag: String },
+    RemoveRemote { repo: String, remote: String },
+    Rebase { repo: String, onto: String },
+    DropStash { repo: String, stash: String },
+    /// Rewrite a commit without these trailers, rendered and joined by `; `.
+    StripAttribution { repo: String, trailers: String },
+    /// Hand the repository to another owner.
+    TransferOwnership { repo: String, new_owner: String },
+}
+
+impl OperationDescriptor {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            OperationDescriptor::RemoveProtection { .. } | OperationDescriptor::UnprotectBranch { .. } => {
+                OperationKind::RemoveProtection
+            }
+            OperationDescriptor::ForcePush { .. } => OperationKind::ForcePush,
+            OperationDescriptor::FilterRepo { .. } => OperationKind::FilterRepo,
+            OperationDescriptor::ResetHard { .. } => OperationKind::ResetHard,
+            OperationDescriptor::DeleteBranch { .. } | OperationDescriptor::CleanupBranches { .. } => {
+                OperationKind::DeleteBranch
+            }
+            OperationDescriptor::ForceDeleteBranch { .. } => OperationKind::ForceDeleteBranch,
+            OperationDescriptor::DeleteTag { .. } => OperationKind::DeleteTag,
+            OperationDescriptor::RemoveRemote { .. } => OperationKind::RemoveRemote,
+            OperationDescriptor::Rebase { .. } => OperationKind::Rebase,
+            OperationDescriptor::DropStash { .. } => OperationKind::DropStash,
+            OperationDescriptor::StripAttribution { .. } => OperationKind::StripAttribution,
+            OperationDescriptor::TransferOwnership { .. } => OperationKind::TransferOwnership,
+        }
+    }
+
+    pub fn repo(&self) -> &str {
+        match self {
+            OperationDescriptor::RemoveProtection { repo }
+            | OperationDescriptor::UnprotectBranch { repo, .. }
+            | OperationDescriptor::ForcePush { repo, .. }
+            | OperationDescriptor::FilterRepo { repo, .. }
+            | OperationDescriptor::ResetHard { repo }
+            | OperationDescriptor::DeleteBranch { repo, .. }
+            | OperationDescriptor::ForceDeleteBranch { repo, .. }
+            | OperationDescriptor::CleanupBranches { repo, .. }
+            | OperationDescriptor::DeleteTag { repo, .. }
+            | OperationDescriptor::RemoveRemote { repo, .. }
+            | OperationDescriptor::Rebase { repo, .. }
+            | OperationDescriptor::DropStash { repo, .. }
+            | OperationDescriptor::StripAttribution { repo, .. }
+            | OperationDescriptor::TransferOwnership { repo, .. } => repo,
+        }
+    }
+
+    /// The branch it unprotects, pushes to, or deletes, if it has one.
+    pub fn branch(&self) -> Option<&str> {
+        match self {
+            OperationDescriptor::UnprotectBranch { branch, .. }
+            | OperationDescriptor::ForcePush { branch, .. }
+            | OperationDescriptor::DeleteBranch { branch, .. }
+            | OperationDescriptor::ForceDeleteBranch { branch, .. } => Some(branch),
+            _ => None,
+        }
+    }
+
+    /// What the approver is shown: the operation and its consequence, in
+    /// the gate's words.
+    pub fn render(&self) -> String {
+        match self {
+            OperationDescriptor::RemoveProtection { repo } => format!(
+                "Remove branch protection on '{}'; force-pushes and history rewrites become possible",
+                repo
+            ),
+            OperationDescriptor::UnprotectBranch { repo, branch } => format!(
+                "Remove protection on branch '{}' of '{}'; force-pushes to that branch become possible",
+                branch, repo
+            ),
+            OperationDescriptor::ForcePush { repo, branch } => {
+                format!("Force-push '{}' to origin/{}, overwriting that branch's remote history", repo, branch)
+            }
+            OperationDescriptor::FilterRepo { repo, callback } => format!(
+                "Rewrite every commit of '{}' with filter-repo callback `{}`; every commit gets a new SHA",
+                repo, callback
+            ),
+            OperationDescriptor::ResetHard { repo } => {
+                format!("Hard reset '{}' to HEAD, discarding all uncommitted work", repo)
+            }
+            OperationDescriptor::DeleteBranch { repo, branch } => {
+                format!("Delete branch '{}' of '{}'", branch, repo)
+            }
+            OperationDescriptor::ForceDeleteBranch { repo, branch } => format!(
+                "Force-delete branch '{}' of '{}', which is checked out or not fully merged; its unmerged commits are lost",
+                branch, repo
+            ),
+            OperationDescriptor::CleanupBranches { repo, branches } => format!(
+                "Delete {} branches of '{}', each kept in a backup ref: {}",
+                branches.len(),
+                repo,
+                branches.join(", ")
+            ),
+            OperationDescriptor::DeleteTag { repo, tag } => {
+                format!("Delete tag '{}' of '{}', locally and on origin", tag, repo)
+            }
+            OperationDescriptor::RemoveRemote { repo, remote } => format!(
+                "Remove remote '{}' from '{}', with its remote-tracking branches",
+                remote, repo
+            ),
+            OperationDescriptor::Rebase { repo, onto } => format!(
+                "Rebase the checked-out branch of '{}' onto '{}'; every replayed commit gets a new SHA",
+                repo, onto
+            ),
+            OperationDescriptor::DropStash { repo, stash } => format!(
+                "Drop stash '{}' of '{}'; the uncommitted work it holds is lost",
+                stash, repo
+            ),
+            OperationDescriptor::StripAttribution { repo, trailers } => format!(
+                "Rewrite a commit of '{}' without its attribution: {}",
+                repo, trailers
+            ),
+            OperationDescriptor::TransferOwnership { repo, new_owner } => format!(
+                "Transfer '{}' to '{}'; getting it back depends on the new owner",
+                repo, new_owner
+            ),
+        }
+    }
+
+    /// The descriptor a request that only named the operation, its
+    /// repository, and maybe a branch still pins down. A history rewrite
+    /// is never implied: the callback is the part that matters.
+    pub fn implied(kind: OperationKind, repo: &str, branch: Option<&str>) -> Option<OperationDescriptor> {
+        let repo = repo.to_string();
+        match kind {
+            OperationKind::RemoveProtection => Some(OperationDescriptor::RemoveProtection { repo }),
+            OperationKind::ResetHard => Some(OperationDescriptor::ResetHard { repo }),
+            OperationKind::ForcePush => Some(OperationDescriptor::ForcePush {
+                repo,
+                branch: branch.unwrap_or("main").to_string(),
+            }),
+            OperationKind::DeleteBranch => branch.map(|branch| OperationDescriptor::DeleteBranch {
+                repo,
+                branch: branch.to_string(),
+            }),
+            OperationKind::ForceDeleteBranch => branch.map(|branch| OperationDescriptor::ForceDeleteBranch {
+                repo,
+                branch: branch.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `other` is the same operation, with the repository left to
+    /// the consent's own binding, which accepts a name or a path.
+    pub(crate) fn same_apart_from_repo(&self, other: &OperationDescriptor) -> bool {
+        use OperationDescriptor::*;
+        match (self, other) {
+            (RemoveProtection { .. }, RemoveProtection { .. }) | (ResetHard { .. }, ResetHard { .. }) => true,
+            (UnprotectBranch { branch: a, .. }, UnprotectBranch { branch: b, .. })
+            | (ForcePush { branch: a, .. }, ForcePush { branch: b, .. })
+            | (DeleteBranch { branch: a, .. }, DeleteBranch { branch: b, .. })
+            | (ForceDeleteBranch { branch: a, .. }, ForceDeleteBranch { branch: b, .. })
+            | (DeleteTag { tag: a, .. }, DeleteTag { tag: b, .. })
+            | (RemoveRemote { remote: a, .. }, RemoveRemote { remote: b, .. })
+            | (Rebase { onto: a, .. }, Rebase { onto: b, .. })
+            | (DropStash { stash: a, .. }, DropStash { stash: b, .. })
+            | (StripAttribution { trailers: a, .. }, StripAttribution { trailers: b, .. })
+            | (TransferOwnership { new_owner: a, .. }, TransferOwnership { new_owner: b, .. })
+            | (FilterRepo { callback: a, .. }, FilterRepo { callback: b, .. }) => a == b,
+            (CleanupBranches { branches: a, .. }, CleanupBranches { branches: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+
+    /// One line, for signatures and state files: `kind|repo`, then the
+    /// branch or callback if there is one. A cleanup's branches follow
+    /// `cleanup:`, which no branch name can contain.
+    pub fn encode(&self) -> String {
+        if let OperationDescriptor::CleanupBranches { repo, branches } = self {
+            return format!("{}|{}|cleanup:{}", self.kind().name(), repo, branches.join(" "));
+        }
+        let detail = match self {
+            OperationDescriptor::UnprotectBranch { branch, .. }
+            | OperationDescriptor::ForcePush { branch, .. }
+            | OperationDescriptor::DeleteBranch { branch, .. }
+            | OperationDescriptor::ForceDeleteBranch { branch, .. } => Some(branch),
+            OperationDescriptor::DeleteTag { tag, .. } => Some(tag),
+            OperationDescriptor::RemoveRemote { remote, .. } => Some(remote),
+            OperationDescriptor::Rebase { onto, .. } => Some(onto),
+            OperationDescriptor::DropStash { stash, .. } => Some(stash),
+            OperationDescriptor::StripAttribution { trailers, .. } => Some(trailers),
+            OperationDescriptor::TransferOwnership { new_owner, .. } => Some(new_owner),
+            OperationDescriptor::FilterRepo { callback, .. } => Some(callback),
+            _ => None,
+        };
+        match detail {
+            Some(detail) => format!("{}|{}|{}", self.kind().name(), self.repo(), detail),
+            None => format!("{}|{}", self.kind().name(), self.repo()),
+        }
+    }
+
+    pub fn decode(line: &str) -> Option<OperationDescriptor> {
+        let mut fields = line.splitn(3, '|');
+        let (kind, repo) = (OperationKind::from_name(fields.next()?)?, fields.next()?.to_string());
+        let detail = fields.next().map(str::to_string);
+        match (kind, detail) {
+            (OperationKind::RemoveProtection, None) => Some(OperationDescriptor::RemoveProtection { repo }),
+            (OperationKind::RemoveProtection, Some(branch)) => Some(OperationDescriptor::UnprotectBranch { repo, branch }),
+            (OperationKind::ResetHard, None) => Some(OperationDescriptor::ResetHard { repo }),
+            (OperationKind::ForcePush, Some(branch)) => Some(OperationDescriptor::ForcePush { repo, branch }),
+            (OperationKind::DeleteBranch, Some(detail)) => match detail.strip_prefix("cleanup:") {
+                Some(branches) => Some(OperationDescriptor::CleanupBranches {
+                    repo,
+                    branches: branches.split(' ').map(str::to_string).collect(),
+                }),
+                None => Some(OperationDescriptor::DeleteBranch { repo, branch: detail }),
+            },
+            (OperationKind::ForceDeleteBranch, Some(branch)) => {
+                Some(OperationDescriptor::ForceDeleteBranch { repo, branch })
+            }
+            (OperationKind::DeleteTag, Some(tag)) => Some(OperationDescriptor::DeleteTag { repo, tag }),
+            (OperationKind::RemoveRemote, Some(remote)) => Some(OperationDescriptor::RemoveRemote { repo, remote }),
+            (OperationKind::Rebase, Some(onto)) => Some(OperationDescriptor::Rebase { repo, onto }),
+            (OperationKind::DropStash, Some(stash)) => Some(OperationDescriptor::DropStash { repo, stash }),
+            (OperationKind::StripAttribution, Some(trailers)) => {
+                Some(OperationDescriptor::StripAttribution { repo, trailers })
+            }
+            (OperationKind::FilterRepo, Some(callback)) => Some(OperationDescriptor::FilterRepo { repo, callback }),
+            (OperationKind::TransferOwnership, Some(new_owner)) => {
+                Some(OperationDescriptor::TransferOwnership { repo, new_owner })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for OperationDescriptor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OperationDescriptor::UnprotectBranch { repo, branch } => {
+                write!(f, "remove_protection '{}' on {}", repo, branch)
+            }
+            OperationDescriptor::ForcePush { repo, branch } => write!(f, "force_push '{}' to {}", repo, branch),
+            OperationDescriptor::DeleteBranch { repo, branch } => write!(f, "delete_branch '{}' on '{}'", branch, repo),
+            OperationDescriptor::CleanupBranches { repo, branches } => {
+                write!(f, "delete_branch {} on '{}'", branches.join(", "), repo)
+            }
+            OperationDescriptor::ForceDeleteBranch { repo, branch } => {
+                write!(f, "force_delete_branch '{}' on '{}'", branch, repo)
+            }
+            OperationDescriptor::DeleteTag { repo, tag } => write!(f, "delete_tag '{}' on '{}'", tag, repo),
+            OperationDescriptor::RemoveRemote { repo, remote } => write!(f, "remove_remote '{}' on '{}'", remote, repo),
+            OperationDescriptor::Rebase { repo, onto } => write!(f, "rebase '{}' onto {}", repo, onto),
+            OperationDescriptor::DropStash { repo, stash } => write!(f, "drop_stash '{}' on '{}'", stash, repo),
+            OperationDescriptor::StripAttribution { repo, trailers } => {
+                write!(f, "strip_attribution '{}' on '{}'", trailers, repo)
+            }
+            OperationDescriptor::FilterRepo { repo, callback } => {
+                write!(f, "filter_repo '{}' with `{}`", repo, callback)
+            }
+            OperationDescriptor::TransferOwnership { repo, new_owner } => {
+                write!(f, "transfer_ownership '{}' to '{}'", repo, new_owner)
+            }
+            other => write!(f, "{} '{}'", other.kind().name(), other.repo()),
+        }
+    }
+}
+
+/// How much damage an operation can do if it was the wrong call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Low => "Low",
+            Severity::Medium => "Medium",
+            Severity::High => "High",
+            Severity::Critical => "Critical",
+        }
+    }
+}
+
+/// Consent was refused: by the gate, or by the person asked.
+///
+/// A refusal is an outcome, not a missing grant. It records who refused,
+/// when, and why, the gate writes it to the audit log before returning
+/// it, and nothing turns it into consent. The caller decides what to do
+/// instead; [`SafetyError::playbook`](crate::SafetyError::playbook) suggests what.
+#[derive(Clone, Debug)]
+pub struct ConsentDenied {
+    pub operation: OperationKind,
+    pub reason: String,
+    pub by: DeniedBy,
+    /// When, in seconds since the Unix epoch.
+    pub at: u64,
+}
+
+/// Who refused consent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeniedBy {
+    /// The gate: a rule refused before anyone was asked, no answer came,
+    /// or consent presented for an operation failed its checks.
+    Gate,
+    /// A person said no, at the challenge or over an approval channel.
+    /// Holds who, as the gate knows them.
+    Approver(String),
+}
+
+impl ConsentDenied {
+    /// A refusal by the gate, as of now.
+    pub fn new(operation: OperationKind, reason: String) -> Self {
+        Self::new_at(operation, reason, crate::unix_now())
+    }
+
+    /// A refusal by the gate, as of `at`: for callers that keep their
+    /// own clock.
+    pub fn new_at(operation: OperationKind, reason: String, at: u64) -> Self {
+        ConsentDenied {
+            operation,
+            reason,
+            by: DeniedBy::Gate,
+            at,
+        }
+    }
+
+    /// The same refusal, made by `approver`.
+    pub fn by_approver(mut self, approver: &str) -> Self {
+        self.by = DeniedBy::Approver(approver.to_string());
+        self
+    }
+
+    /// Whether a person refused, rather than the gate.
+    pub fn is_refusal(&self) -> bool {
+        matches!(self.by, DeniedBy::Approver(_))
+    }
+}
+
+impl core::fmt::Display for ConsentDenied {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.by {
+            DeniedBy::Gate => write!(f, "consent denied for {}: {}", self.operation.name(), self.reason),
+            DeniedBy::Approver(who) => write!(f, "consent denied for {} by {}: {}", self.operation.name(), who, self.reason),
+        }
+    }
+}
+
+/// Why the gate, or the consent, said no.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockKind {
+    /// A request was refused: by a rule, or by the approver.
+    Denied,
+    /// Consent was presented after it expired.
+    Expired,
+    /// Consent was presented before its time lock opened.
+    TimeLocked,
+    /// Consent was presented after the approver revoked it.
+    Revoked,
+    /// Consent was presented for an operation, repository, branch, or
+    /// operation detail it was not granted for.
+    ScopeMismatch,
+    /// Consent was presented from an agent session other than the one
+    /// it was granted in.
+    WrongSession,
+    /// Consent was presented after it was already redeemed, or was
+    /// never issued by the gate it claims.
+    Replayed,
+}
+
+impl BlockKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            BlockKind::Denied => "denied",
+            BlockKind::Expired => "expired",
+            BlockKind::TimeLocked => "time-locked",
+            BlockKind::Revoked => "revoked",
+            BlockKind::ScopeMismatch => "scope mismatch",
+            BlockKind::WrongSession => "wrong session",
+            BlockKind::Replayed => "replayed",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            BlockKind::Denied,
+            BlockKind::Expired,
+            BlockKind::TimeLocked,
+            BlockKind::Revoked,
+            BlockKind::ScopeMismatch,
+            BlockKind::WrongSession,
+            BlockKind::Replayed,
+        ]
+        .into_iter()
+        .find(|kind| kind.label() == label)
+    }
+}
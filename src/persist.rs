@@ -0,0 +1,340 @@
+//! Approval state kept on disk across process restarts.
+//!
+//! Some approvals take hours: an answer carried back across an air gap, a
+//! second approver who is in a meeting. If the process dies in between,
+//! everything in flight dies with it, and the approvers who already said
+//! yes are asked again from the start. Once [`SafetyGate::persist_pending`]
+//! names a state file, the gate rewrites it whenever that state changes:
+//! offline requests and the batches they went out in, the approvals each
+//! unsettled quorum has collected, and consent the agent handed to
+//! [`hold`](SafetyGate::hold) while it waits on the rest. A restarted gate
+//! pointed at the same file picks all of it back up.
+//!
+//! The file is signed with the gate's consent key, so it resumes only
+//! under a gate given the same key with
+//! [`set_signing_key`](SafetyGate::set_signing_key), and an edit to it —
+//! an expiry pushed out, an approval added — rejects the whole file.
+//! Nothing in it outlives its own expiry: held consent still lapses at its
+//! TTL, and a quorum tally after the air-gap window. What a signature
+//! cannot say is whether the file is the latest one. Keep it where the
+//! agent cannot write, and give the gate a directory of spent nonces
+//! ([`burn_nonces_in`](SafetyGate::burn_nonces_in)) that outlives the
+//! process: consent a stale copy brings back is then dropped on resume
+//! if it was since used.
+
+use super::agent_identity::AgentIdentity;
+use super::airgap::{one_line, split_signed, Outbox};
+use super::ed25519::Signature;
+use super::{unix_now, OperationDescriptor, OperationKind, SafetyGate, UserConsent};
+use core::marker::PhantomData;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The approvals an unsettled quorum request has collected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tally {
+    pub operation: OperationKind,
+    pub target: String,
+    pub description: String,
+    pub started_at: u64,
+    pub approved: Vec<String>,
+}
+
+/// The gate's state file, and what it holds besides the air-gap queue.
+#[derive(Default)]
+pub struct Pending {
+    file: Option<PathBuf>,
+    tallies: Vec<Tally>,
+    held: BTreeMap<u64, UserConsent>,
+}
+
+/// What a gate picked back up from its state file.
+#[derive(Debug, Default)]
+pub struct Resumed {
+    pub offline: usize,
+    pub tallies: usize,
+    /// The ids of held consent, for [`take_held`](SafetyGate::take_held).
+    pub held: Vec<u64>,
+    /// What was left behind, and why.
+    pub dropped: Vec<String>,
+}
+
+impl SafetyGate {
+    /// Keep pending approval state in `path` from now on, first
+    /// resuming whatever it already holds. A missing file is a fresh
+    /// start. A file that does not verify is refused and left as it
+    /// is, and the gate persists nothing until given one that does.
+    pub fn persist_pending(&mut self, path: impl Into<PathBuf>) -> Result<Resumed, String> {
+        let path = path.into();
+        let resumed = match fs::read_to_string(&path) {
+            Ok(text) => self.resume_pending(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Resumed::default()),
+            Err(e) => Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+        let resumed = match resumed {
+            Ok(resumed) => resumed,
+            Err(why) => {
+                self.log(format!("PENDING STATE REJECTED ({}): {}", path.display(), why));
+                return Err(why);
+            }
+        };
+        self.log(format!(
+            "PENDING STATE: {} ({} offline requests, {} quorum tallies, {} held consents resumed)",
+            path.display(),
+            resumed.offline,
+            resumed.tallies,
+            resumed.held.len()
+        ));
+        for why in &resumed.dropped {
+            self.log(format!("PENDING STATE DROPPED: {}", why));
+        }
+        self.pending.file = Some(path);
+        self.save_pending();
+        Ok(resumed)
+    }
+
+    /// Keep `consent` for the agent until it is taken back. While held
+    /// it is in the state file, so it survives a restart; it is never
+    /// both held and in the agent's hands. Returns its id.
+    pub fn hold(&mut self, consent: UserConsent) -> u64 {
+        let id = consent._nonce;
+        self.log(format!("HELD: {}", consent._operation));
+        self.pending.held.insert(id, consent);
+        self.save_pending();
+        id
+    }
+
+    /// Hand held consent back, to be spent.
+    pub fn take_held(&mut self, id: u64) -> Option<UserConsent> {
+        let consent = self.pending.held.remove(&id)?;
+        self.log(format!("TAKEN FROM HOLD: {}", consent._operation));
+        self.save_pending();
+        Some(consent)
+    }
+
+    /// The approvals already given for a quorum request, if it is
+    /// still within the window.
+    pub(crate) fn quorum_tally(&mut self, operation: OperationKind, target: &str, description: &str) -> Vec<String> {
+        let oldest = unix_now().saturating_sub(self.airgap.window());
+        self.pending.tallies.retain(|t| t.started_at >= oldest);
+        self.pending
+            .tallies
+            .iter()
+            .find(|t| t.operation == operation && t.target == target && t.description == description)
+            .map(|t| t.approved.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn note_quorum_tally(&mut self, operation: OperationKind, target: &str, description: &str, approved: &[String]) {
+        let tallies = &mut self.pending.tallies;
+        match tallies.iter_mut().find(|t| t.operation == operation && t.target == target && t.description == description) {
+            Some(tally) => tally.approved = approved.to_vec(),
+            None => tallies.push(Tally {
+                operation,
+                target: target.to_string(),
+                description: description.to_string(),
+                started_at: unix_now(),
+                approved: approved.to_vec(),
+            }),
+        }
+        self.save_pending();
+    }
+
+    /// Forget a quorum request's tally once it is settled either way.
+    pub(crate) fn settle_quorum_tally(&mut self, operation: OperationKind, target: &str, description: &str) {
+        let before = self.pending.tallies.len();
+        self.pending
+            .tallies
+            .retain(|t| !(t.operation == operation && t.target == target && t.description == description));
+        if self.pending.tallies.len() != before {
+            self.save_pending();
+        }
+    }
+
+    /// Rewrite the state file, if there is one. A write that fails is
+    /// logged; what was pending is still pending in this process.
+    pub(crate) fn save_pending(&mut self) {
+        let Some(path) = self.pending.file.clone() else {
+            return;
+        };
+        let text = self.pending_state();
+        // Write-then-rename, so a crash mid-save never leaves half a file.
+        let tmp = path.with_extension("tmp");
+        if let Err(err) = fs::write(&tmp, text).and_then(|()| fs::rename(&tmp, &path)) {
+            self.log(format!("PENDING STATE NOT SAVED ({}): {}", path.display(), err));
+        }
+    }
+
+    fn pending_state(&self) -> String {
+        let mut body = format!("pending-state 1\ngate {}\n", self.signing_key.public_key().to_hex());
+        body.push_str(&self.airgap.state_lines());
+        for (n, tally) in self.pending.tallies.iter().enumerate() {
+            body.push_str(&format!(
+                "tally {} {} {} {}\ntally-target {} {}\n",
+                n,
+                tally.started_at,
+                tally.operation.name(),
+                one_line(&tally.description),
+                n,
+                tally.target
+            ));
+            for name in &tally.approved {
+                body.push_str(&format!("tally-approved {} {}\n", n, one_line(name)));
+            }
+        }
+        // Consent that could no longer be used is not worth keeping.
+        for (id, consent) in self.pending.held.iter().filter(|(_, c)| c.check_fresh().is_ok()) {
+            body.push_str(&format!(
+                "held {:016x} {} {} {} {} {} {}\n",
+                id,
+                consent._kind.name(),
+                consent._issued_at,
+                consent._expires_at,
+                u8::from(consent._observe),
+                consent._signature.to_hex(),
+                one_line(&consent._operation)
+            ));
+            if let Some(target) = &consent._target {
+                body.push_str(&format!("held-target {:016x} {}\n", id, target));
+            }
+            if let Some(branch) = &consent._branch {
+                body.push_str(&format!("held-branch {:016x} {}\n", id, branch));
+            }
+            if let Some(descriptor) = &consent._descriptor {
+                body.push_str(&format!("held-descriptor {:016x} {}\n", id, one_line(&descriptor.encode())));
+            }
+            if let Some(agent) = &consent._agent {
+                body.push_str(&format!("held-agent {:016x} {}\n", id, agent.encode()));
+            }
+            if consent._not_before != 0 {
+                body.push_str(&format!("held-not-before {:016x} {}\n", id, consent._not_before));
+            }
+        }
+        format!("{}signature {}\n", body, self.signing_key.sign(body.as_bytes()).to_hex())
+    }
+
+    /// Verify a state file and add what it holds to the gate. Nothing
+    /// is added unless all of it parses.
+    fn resume_pending(&mut self, text: &str) -> Result<Resumed, String> {
+        let (body, signature) = split_signed(text, "pending-state 1")?;
+        if !self.signing_key.public_key().verify(body.as_bytes(), &signature) {
+            return Err("the state file is not signed by this gate's consent key".to_string());
+        }
+        let mut outbox = Outbox::default();
+        let mut tallies: BTreeMap<usize, Tally> = BTreeMap::new();
+        let mut held: BTreeMap<u64, UserConsent> = BTreeMap::new();
+        let parse_n = |n: &str| n.parse::<usize>().map_err(|_| format!("`{}` is not a tally number", n));
+        let parse_id = |id: &str| u64::from_str_radix(id, 16).map_err(|_| format!("`{}` is not a consent id", id));
+        for line in body.lines().skip(1) {
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            if outbox.restore_line(word, rest)? {
+                continue;
+            }
+            match word {
+                "gate" => {}
+                "tally" => {
+                    let fields: Vec<&str> = rest.splitn(4, ' ').collect();
+                    let [n, started_at, operation, description] = fields.as_slice() else {
+                        return Err(format!("bad tally line `{}`", line));
+                    };
+                    let tally = Tally {
+                        operation: OperationKind::from_name(operation).ok_or(format!("unknown operation `{}`", operation))?,
+                        target: String::new(),
+                        description: description.to_string(),
+                        started_at: started_at.parse().map_err(|_| format!("bad start time in `{}`", line))?,
+                        approved: Vec::new(),
+                    };
+                    tallies.insert(parse_n(n)?, tally);
+                }
+                "tally-target" | "tally-approved" => {
+                    let (n, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let tally = tallies.get_mut(&parse_n(n)?).ok_or(format!("`{}` names no tally above it", line))?;
+                    match word {
+                        "tally-target" => tally.target = text.to_string(),
+                        _ => tally.approved.push(text.to_string()),
+                    }
+                }
+                "held" => {
+                    let fields: Vec<&str> = rest.splitn(7, ' ').collect();
+                    let [id, kind, issued_at, expires_at, observe, signature, description] = fields.as_slice() else {
+                        return Err(format!("bad held line `{}`", line));
+                    };
+                    let id = parse_id(id)?;
+                    let time = |t: &str| t.parse::<u64>().map_err(|_| format!("bad time in `{}`", line));
+                    held.insert(
+                        id,
+                        UserConsent {
+                            _operation: description.to_string(),
+                            _nonce: id,
+                            _issued_at: time(issued_at)?,
+                            _signature: Signature::from_hex(signature).ok_or("malformed consent signature")?,
+                            _kind: OperationKind::from_name(kind).ok_or(format!("unknown operation `{}`", kind))?,
+                            _target: None,
+                            _branch: None,
+                            _descriptor: None,
+                            _observe: *observe == "1",
+                            _expires_at: time(expires_at)?,
+                            _not_before: 0,
+                            _revocations: self.revocations.clone(),
+                            _agent: None,
+                            _trail: self.trail.clone(),
+                            _ledger: self.ledger.clone(),
+                            _downstream: self.downstream.clone(),
+                            _scope: PhantomData,
+                        },
+                    );
+                }
+                "held-target" | "held-branch" | "held-descriptor" | "held-agent" | "held-not-before" => {
+                    let (id, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let consent = held.get_mut(&parse_id(id)?).ok_or(format!("`{}` names no consent above it", line))?;
+                    match word {
+                        "held-target" => consent._target = Some(text.to_string()),
+                        "held-branch" => consent._branch = Some(text.to_string()),
+                        "held-agent" => {
+                            let agent = AgentIdentity::decode(text).ok_or(format!("bad agent identity in `{}`", line))?;
+                            consent._agent = Some(Box::new(agent));
+                        }
+                        "held-not-before" => {
+                            consent._not_before = text.parse().map_err(|_| format!("bad time in `{}`", line))?;
+                        }
+                        _ => {
+                            let descriptor = OperationDescriptor::decode(text).ok_or(format!("bad descriptor in `{}`", line))?;
+                            consent._descriptor = Some(descriptor);
+                        }
+                    }
+                }
+                _ => return Err(format!("unknown line `{}`", line)),
+            }
+        }
+        let mut resumed = Resumed {
+            offline: self.airgap.absorb(outbox),
+            ..Resumed::default()
+        };
+        let oldest = unix_now().saturating_sub(self.airgap.window());
+        for tally in tallies.into_values() {
+            if tally.started_at < oldest {
+                resumed.dropped.push(format!("quorum tally for {}: older than the window", tally.description));
+                continue;
+            }
+            self.pending.tallies.push(tally);
+            resumed.tallies += 1;
+        }
+        for (id, consent) in held {
+            if let Err(denied) = consent.check_fresh() {
+                resumed.dropped.push(format!("held consent for {}: {}", consent._operation, denied.reason));
+                continue;
+            }
+            // A stale copy of the file brings back consent since used.
+            if self.ledger.is_spent(id) {
+                resumed.dropped.push(format!("held consent for {}: already redeemed", consent._operation));
+                continue;
+            }
+            self.ledger.register(id);
+            self.pending.held.insert(id, consent);
+            resumed.held.push(id);
+        }
+        Ok(resumed)
+    }
+}
@@ -0,0 +1,218 @@
+//! DestructionPlan — what will be destroyed, itemized.
+
+use crate::{approver, ConsentDenied, OperationDescriptor, OperationKind, SafetyGate, UserConsent};
+
+/// An itemized list of what an operation will destroy, shown to the human
+/// before consent is requested.
+///
+/// The agent in the incident never produced one. "Strip Co-Authored-By
+/// lines" was the whole plan; "rewrite 549 commits on two public repos"
+/// was never said out loud.
+pub struct DestructionPlan {
+    pub operation: OperationKind,
+    pub target: String,
+    pub items: Vec<String>,
+    /// What undoing the operation would cost, from [`estimate_costs`](Self::estimate_costs).
+    pub costs: Vec<RecoveryCost>,
+    /// What consent for the plan is bound to, if the plan spells it out.
+    pub(crate) descriptor: Option<OperationDescriptor>,
+}
+
+/// What an approver must type to consent to a plan that destroys
+/// something for good.
+pub const IRREVERSIBILITY_ACKNOWLEDGMENT: &str = "I understand this cannot be undone";
+
+/// Repository metadata the recovery-cost estimate is computed from.
+#[derive(Clone, Debug, Default)]
+pub struct RepoFacts {
+    pub contributors: usize,
+    pub clone_size_mb: u64,
+    /// Whether the repository is public: links to its commits exist in
+    /// places nobody here controls.
+    pub public: bool,
+    /// Whether CI caches are keyed on commit SHAs.
+    pub ci_cached: bool,
+    /// Whether a backup ref or snapshot of what is destroyed exists.
+    pub backed_up: bool,
+}
+
+/// One cost of undoing an operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecoveryCost {
+    /// Recoverable with this much human effort.
+    Effort { what: String, hours: f64 },
+    /// Recoverable by machines, slowly: caches rebuild on their own.
+    CacheInvalidation(String),
+    /// Not recoverable at any cost.
+    NotRecoverable(String),
+}
+
+impl RecoveryCost {
+    pub fn render(&self) -> String {
+        match self {
+            RecoveryCost::Effort { what, hours } => format!("~{:.1} hours: {}", hours, what),
+            RecoveryCost::CacheInvalidation(what) => format!("cache invalidation: {}", what),
+            RecoveryCost::NotRecoverable(what) => format!("NOT RECOVERABLE: {}", what),
+        }
+    }
+}
+
+impl DestructionPlan {
+    pub fn new(operation: OperationKind, target: &str) -> Self {
+        DestructionPlan {
+            operation,
+            target: target.to_string(),
+            items: Vec::new(),
+            costs: Vec::new(),
+            descriptor: None,
+        }
+    }
+
+    /// Bind consent for this plan to `descriptor`, so it covers exactly
+    /// the operation the plan lists.
+    pub(crate) fn describing(mut self, descriptor: OperationDescriptor) -> Self {
+        self.descriptor = Some(descriptor);
+        self
+    }
+
+    /// Estimate what undoing the operation would cost on a repository
+    /// described by `facts`.
+    ///
+    /// The estimates are deliberately coarse — half an hour per
+    /// contributor to re-clone and rebase local work, download time at
+    /// 50 MB/min — because the approver needs the order of magnitude, not
+    /// a schedule.
+    pub fn estimate_costs(mut self, facts: &RepoFacts) -> Self {
+        let rewrites_history = matches!(
+            self.operation,
+            OperationKind::ForcePush | OperationKind::FilterRepo | OperationKind::ResetHard
+        );
+        if rewrites_history {
+            let per_contributor = 0.5 + facts.clone_size_mb as f64 / 50.0 / 60.0;
+            self.costs.push(RecoveryCost::Effort {
+                what: format!("{} contributors re-clone and rebase local work", facts.contributors),
+                hours: per_contributor * facts.contributors as f64,
+            });
+            if facts.ci_cached {
+                self.costs.push(RecoveryCost::CacheInvalidation(
+                    "CI caches keyed on rewritten commit SHAs rebuild from scratch".to_string(),
+                ));
+            }
+            if facts.public {
+                self.costs.push(RecoveryCost::NotRecoverable(
+                    "links to rewritten commits from issues, PRs, and external sites".to_string(),
+                ));
+            }
+        }
+        if self.operation == OperationKind::TransferOwnership {
+            self.costs.push(RecoveryCost::Effort {
+                what: format!("{} contributors update remotes and credentials", facts.contributors),
+                hours: 0.1 * facts.contributors as f64,
+            });
+        }
+        if !facts.backed_up && self.operation != OperationKind::TransferOwnership {
+            self.costs.push(RecoveryCost::NotRecoverable(format!(
+                "the items listed: no backup of '{}' exists",
+                self.target
+            )));
+        }
+        self
+    }
+
+    /// Whether any item cannot be recovered at all.
+    pub fn is_irreversible(&self) -> bool {
+        self.costs.iter().any(|c| matches!(c, RecoveryCost::NotRecoverable(_)))
+    }
+
+    pub fn item(mut self, item: impl Into<String>) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    /// The plan as a single consent description, every item listed.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{} on '{}' ({} items):",
+            self.operation.name(),
+            self.target,
+            self.items.len()
+        );
+        for item in &self.items {
+            out.push_str("\n      - ");
+            out.push_str(item);
+        }
+        if !self.costs.is_empty() {
+            out.push_str("\n    Cost to recover:");
+            for cost in &self.costs {
+                out.push_str("\n      - ");
+                out.push_str(&cost.render());
+            }
+        }
+        out
+    }
+}
+
+impl SafetyGate {
+    /// Request consent for an itemized plan, shown to the approver first.
+    ///
+    /// A plan with any not-recoverable cost is refused unless the approver
+    /// types exactly [`IRREVERSIBILITY_ACKNOWLEDGMENT`] at the gate's own
+    /// prompt. Clicking "yes" is a habit; typing the sentence is a decision.
+    pub fn request_plan_consent(&mut self, plan: &DestructionPlan) -> Result<UserConsent, ConsentDenied> {
+        self.plan_consent(plan)
+    }
+
+    /// [`request_plan_consent`](Self::request_plan_consent) for any consent scope.
+    pub(crate) fn plan_consent<Scope>(&mut self, plan: &DestructionPlan) -> Result<UserConsent<Scope>, ConsentDenied> {
+        let description = format!("{} on '{}' ({} items)", plan.operation.name(), plan.target, plan.items.len());
+        self.screen_forbidden(plan.operation, Some(&plan.target), &description)?;
+        self.present_plan(plan);
+        if plan.is_irreversible() {
+            self.show_line(approver::Line::Notice("This plan cannot be fully undone.".to_string()));
+            if !self.confirm_phrase(IRREVERSIBILITY_ACKNOWLEDGMENT) {
+                self.log(format!("DENIED (irreversibility not acknowledged): {}", description));
+                return Err(ConsentDenied::new(plan.operation, "irreversible plan not acknowledged".to_string()));
+            }
+        }
+        self.target = Some(plan.target.clone());
+        self.descriptor = plan.descriptor.clone();
+        self.mint(plan.operation, &description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DestructionPlan, RepoFacts};
+    use crate::approver::{Prompt, Rehearsal, Responder};
+    use crate::{OperationKind, SafetyGate};
+
+    struct Silent;
+
+    impl Responder for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+            None
+        }
+    }
+
+    fn gate(responder: Box<dyn Responder>) -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(responder);
+        gate
+    }
+
+    #[test]
+    fn an_irreversible_plan_needs_the_acknowledgment_typed_at_the_gate() {
+        let facts = RepoFacts { contributors: 1, clone_size_mb: 1, public: false, ci_cached: false, backed_up: false };
+        let plan = DestructionPlan::new(OperationKind::DeleteBranch, "feature").item("feature").estimate_costs(&facts);
+        assert!(plan.is_irreversible());
+        let Err(denied) = gate(Box::new(Silent)).request_plan_consent(&plan) else {
+            panic!("nobody typed the acknowledgment");
+        };
+        assert!(denied.reason.contains("not acknowledged"), "{}", denied.reason);
+        assert!(gate(Box::new(Rehearsal)).request_plan_consent(&plan).is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+//! Guarded repository visibility and license changes.
+//!
+//! Both look like a settings toggle. Flipping a private repository to
+//! public publishes every commit it ever had, including the ones that
+//! added a key and the ones that "removed" it; whatever is cloned while
+//! it is public stays public. A license change binds everyone who builds
+//! on the code, and the old license still covers what was already
+//! released. So each needs its own consent scope, and publication is a
+//! High-severity ceremony whose plan includes a scan of the whole history
+//! for secrets, run before anyone is asked.
+//!
+//! A history that cannot be scanned is not a clean history: the plan says
+//! so, and the approver has to acknowledge it like any other loss.
+
+use super::{ConsentDenied, DestructionPlan, OperationKind, RecoveryCost, Repository, SafetyGate, UserConsent};
+use std::process::Command;
+
+/// Consent scope for changing who can see a repository.
+pub struct VisibilityChange;
+
+/// Consent scope for relicensing a repository.
+pub struct LicenseChange;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Visibility {
+    Private,
+    /// Everyone in the organization.
+    Internal,
+    Public,
+}
+
+impl Visibility {
+    pub fn label(self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Internal => "internal",
+            Visibility::Public => "public",
+        }
+    }
+
+    /// The operation that changes a repository to this visibility.
+    pub fn operation(self) -> OperationKind {
+        match self {
+            Visibility::Public => OperationKind::MakePublic,
+            Visibility::Private | Visibility::Internal => OperationKind::ChangeVisibility,
+        }
+    }
+}
+
+/// A likely secret somewhere in a repository's history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// The newest commit that added it.
+    pub commit: String,
+    pub path: String,
+    pub rule: &'static str,
+}
+
+/// Token prefixes, the characters that must follow them, and what they are.
+const TOKEN_PREFIXES: &[(&str, usize, &str)] = &[
+    ("AKIA", 16, "AWS access key ID"),
+    ("ghp_", 36, "GitHub personal access token"),
+    ("github_pat_", 22, "GitHub personal access token"),
+    ("xoxb-", 10, "Slack bot token"),
+    ("xoxp-", 10, "Slack user token"),
+    ("sk_live_", 24, "Stripe live secret key"),
+];
+
+/// Names that, assigned a quoted literal, are probably a credential.
+const SECRET_NAMES: &[&str] = &["password", "passwd", "secret", "api_key", "apikey", "access_token"];
+
+/// What kind of secret `line` appears to contain, if any.
+fn secret_rule(line: &str) -> Option<&'static str> {
+    if line.contains("PRIVATE KEY-----") {
+        return Some("private key");
+    }
+    for (prefix, len, rule) in TOKEN_PREFIXES {
+        for (at, _) in line.match_indices(prefix) {
+            let tail = &line[at + prefix.len()..];
+            if tail.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-').count() >= *len {
+                return Some(rule);
+            }
+        }
+    }
+    // `password = "hunter2hunter2"`, but not `password = os.environ[...]`.
+    let lower = line.to_ascii_lowercase();
+    for name in SECRET_NAMES {
+        let Some(at) = lower.find(name) else {
+            continue;
+        };
+        let rest = line[at + name.len()..].trim_start_matches(['"', '\'', ' ', '\t']);
+        let Some(value) = rest.strip_prefix(['=', ':']) else {
+            continue;
+        };
+        let value = value.trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        let literal = value[1..].split(quote).next().unwrap_or("");
+        if literal.len() >= 8 && !literal.contains(['$', '{', '<']) {
+            return Some("hard-coded credential");
+        }
+    }
+    None
+}
+
+/// The result of scanning every line ever added, on every ref.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretScan {
+    pub commits: usize,
+    /// One per path and rule, at the newest commit that added it.
+    pub findings: Vec<Finding>,
+}
+
+impl SecretScan {
+    /// Scan the history of the git repository at `path`.
+    pub fn history(path: &str) -> Result<SecretScan, String> {
+        let out = Command::new("git")
+            .args(["-C", path, "log", "--all", "-p", "--no-color", "--no-ext-diff", "--format=commit %h"])
+            .output()
+            .map_err(|e| format!("cannot run git: {}", e))?;
+        if !out.status.success() {
+            return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+        }
+        let mut scan = SecretScan {
+            commits: 0,
+            findings: Vec::new(),
+        };
+        let (mut commit, mut file) = (String::new(), String::new());
+        for line in String::from_utf8_lossy(&out.stdout).lines() {
+            if let Some(id) = line.strip_prefix("commit ") {
+                commit = id.to_string();
+                scan.commits += 1;
+            } else if let Some(path) = line.strip_prefix("+++ b/") {
+                file = path.to_string();
+            } else if line.starts_with("+++") {
+                continue;
+            } else if let Some(rule) = line.strip_prefix('+').and_then(secret_rule) {
+                if !scan.findings.iter().any(|f| f.path == file && f.rule == rule) {
+                    scan.findings.push(Finding {
+                        commit: commit.clone(),
+                        path: file.clone(),
+                        rule,
+                    });
+                }
+            }
+        }
+        Ok(scan)
+    }
+}
+
+impl<State, B> Repository<State, B> {
+    /// What changing to `visibility` exposes, with the history's
+    /// secret scan. Narrowing visibility scans nothing: it exposes
+    /// nothing new.
+    pub fn visibility_plan(&self, visibility: Visibility) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(visibility.operation(), &self.name);
+        plan = plan.item(format!("'{}' becomes {}", self.name, visibility.label()));
+        if visibility == Visibility::Private {
+            plan = plan.item("forks and links held by people outside the collaborators stop working");
+            plan.costs.push(RecoveryCost::Effort {
+                what: "re-share with everyone who lost access".to_string(),
+                hours: 1.0,
+            });
+            return plan;
+        }
+        let audience = match visibility {
+            Visibility::Public => "anyone on the internet",
+            _ => "everyone in the organization",
+        };
+        match SecretScan::history(&self.path) {
+            Ok(scan) => {
+                plan = plan.item(format!("{} commits of history readable by {}", scan.commits, audience));
+                if scan.findings.is_empty() {
+                    plan = plan.item("secret scan: nothing found in any commit on any ref");
+                }
+                for finding in &scan.findings {
+                    plan = plan.item(format!(
+                        "secret scan: {} in {} (added in {})",
+                        finding.rule, finding.path, finding.commit
+                    ));
+                }
+                if !scan.findings.is_empty() {
+                    plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                        "the secrets found in history are exposed the moment it is {}; rotate them first",
+                        visibility.label()
+                    )));
+                }
+            }
+            Err(why) => {
+                plan = plan.item(format!("secret scan FAILED: {}", why));
+                plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                    "history that could not be scanned is exposed to {} unchecked",
+                    audience
+                )));
+            }
+        }
+        if visibility == Visibility::Public {
+            plan.costs.push(RecoveryCost::NotRecoverable(
+                "clones, forks, and caches taken while public; making it private again does not recall them"
+                    .to_string(),
+            ));
+        }
+        plan
+    }
+
+    /// What relicensing from `from` to `to` binds.
+    pub fn license_plan(&self, from: &str, to: &str) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(OperationKind::ChangeLicense, &self.name)
+            .item(format!("'{}' is relicensed from {} to {}", self.name, from, to))
+            .item(format!("copies and releases already distributed stay under {}", from));
+        plan.costs.push(RecoveryCost::Effort {
+            what: "legal review, and agreement from contributors who did not assign copyright".to_string(),
+            hours: 8.0,
+        });
+        plan.costs.push(RecoveryCost::NotRecoverable(format!(
+            "rights granted under {} to anyone who received the code under it",
+            to
+        )));
+        plan
+    }
+
+    /// Change who can see the repository. Consent for narrowing does
+    /// not cover publishing: [`Visibility::Public`] is its own operation.
+    pub fn set_visibility(
+        &self,
+        visibility: Visibility,
+        consent: UserConsent<VisibilityChange>,
+    ) -> Result<String, ConsentDenied> {
+        consent.check(visibility.operation(), self)?;
+        consent.announce(&format!("'{}' made {} with user approval.", self.name, visibility.label()));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] visibility change to {} simulated", self.name, visibility.label()));
+        }
+        Ok(format!("[{}] visibility set to {}", self.name, visibility.label()))
+    }
+
+    /// Replace the repository's license with `license`.
+    pub fn change_license(&self, license: &str, consent: UserConsent<LicenseChange>) -> Result<String, ConsentDenied> {
+        consent.check(OperationKind::ChangeLicense, self)?;
+        consent.announce(&format!("'{}' relicensed under {} with user approval.", self.name, license));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] relicensing under {} simulated", self.name, license));
+        }
+        Ok(format!("[{}] license changed to {}", self.name, license))
+    }
+}
+
+impl SafetyGate {
+    /// Request consent to change `repo` to `visibility`, showing the
+    /// plan and its secret scan first. A plan that exposes anything
+    /// for good needs the typed acknowledgment.
+    pub fn request_visibility_consent<S, B>(
+        &mut self,
+        repo: &Repository<S, B>,
+        visibility: Visibility,
+    ) -> Result<UserConsent<VisibilityChange>, ConsentDenied> {
+        self.plan_consent(&repo.visibility_plan(visibility))
+    }
+
+    /// Request consent to relicense `repo` from `from` to `to`.
+    pub fn request_license_consent<S, B>(
+        &mut self,
+        repo: &Repository<S, B>,
+        from: &str,
+        to: &str,
+    ) -> Result<UserConsent<LicenseChange>, ConsentDenied> {
+        self.plan_consent(&repo.license_plan(from, to))
+    }
+}
@@ -0,0 +1,1501 @@
+//! Gate policy: per-operation decisions loaded from a file.
+//!
+//! ```text
+//! # .safety-policy
+//! version = "2026-03-01.1"
+//! default = "require-consent"
+//! require_reason = "high"   # approvers of High and Critical say why
+//! consent_ttl = "300"       # seconds before granted consent expires
+//! destructive_cooldown = "1800"  # seconds a repository stays Critical after
+//!                                # an operation that cannot simply be undone
+//! require_hardware_key = "filter_repo"  # a security-key touch, not just
+//!                                       # typed text, confirms these
+//! delete_max_files = "1000"        # a recursive deletion past either limit
+//! delete_max_bytes = "104857600"   # is Critical, with its counts typed back
+//! transfer_waiting_period = "86400"  # seconds between opening a transfer
+//!                                    # of ownership and completing it
+//!
+//! [operations]
+//! force_push = "require-consent"
+//! filter_repo = "deny"
+//! ```
+//!
+//! The policy is data, not reasoning. It decides before the human is ever
+//! asked, and a `deny` cannot be argued with. A fleet composes it from
+//! org, team, and repository layers with a [`PolicyTree`](policy::PolicyTree),
+//! where a `deny` cannot be overridden either. Above it sits the
+//! organization's [`DenyList`](policy::DenyList), which the team that
+//! writes the policy cannot edit.
+
+use super::fs_ops::DeletionLimits;
+use super::{HostKey, HostSignature, OperationKind, Repository, Severity};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What the policy says about an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Decision {
+    /// Consent is minted without an interactive challenge, for an
+    /// operation that can simply be undone. For any other, `allow`
+    /// decides as `require-consent`: it skips no human.
+    Allow,
+    /// A human must approve through the gate.
+    RequireConsent,
+    /// No consent can be minted. The human is never asked.
+    Deny,
+}
+
+impl Decision {
+    pub fn label(self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::RequireConsent => "require-consent",
+            Decision::Deny => "deny",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Decision> {
+        [Decision::Allow, Decision::RequireConsent, Decision::Deny]
+            .into_iter()
+            .find(|d| d.label() == value)
+    }
+}
+
+/// A policy file that failed validation, with the line at fault.
+#[derive(Debug)]
+pub struct PolicyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// What one policy file sets, and only that: `None` where it is silent.
+/// A [`Policy`] fills the silences with defaults; a [`PolicyTree`]
+/// fills them from the layer above.
+#[derive(Clone, Debug, Default)]
+struct Settings {
+    version: Option<String>,
+    /// The team a repository's `.safetyrc` says it belongs to.
+    team: Option<String>,
+    default: Option<Decision>,
+    rules: Vec<(OperationKind, Decision)>,
+    require_reason: Option<Option<Severity>>,
+    consent_ttl: Option<u64>,
+    destructive_cooldown: Option<u64>,
+    require_hardware_key: Option<Vec<OperationKind>>,
+    delete_max_files: Option<u64>,
+    delete_max_bytes: Option<u64>,
+    transfer_waiting_period: Option<u64>,
+}
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// The file is rejected.
+    Error,
+    /// The file loads, but probably does not say what was meant.
+    Warning,
+}
+
+/// One finding in a policy file, positioned for an editor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// A stable name for the kind of finding, such as `unknown-operation`.
+    pub code: &'static str,
+    /// 1-based, as in [`PolicyError`]. Zero for the file as a whole.
+    pub line: usize,
+    /// Columns of the offending text, in UTF-16 code units as editors
+    /// count them.
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// The UTF-16 column of byte offset `at` in `line`.
+fn column(line: &str, at: usize) -> usize {
+    line[..at].encode_utf16().count()
+}
+
+/// The closest operation name to `typo`, if one is close enough to be
+/// what was meant.
+fn suggest(typo: &str) -> Option<&'static str> {
+    let distance = |a: &str, b: &str| {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let next = (row[j + 1] + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+                diagonal = row[j + 1];
+                row[j + 1] = next;
+            }
+        }
+        row[b.len()]
+    };
+    OperationKind::ALL
+        .iter()
+        .map(|k| (distance(&typo.to_lowercase().replace('-', "_"), k.name()), k.name()))
+        .filter(|(d, _)| *d <= 3)
+        .min()
+        .map(|(_, name)| name)
+}
+
+impl Settings {
+    fn parse(text: &str) -> Result<Settings, PolicyError> {
+        let (settings, diagnostics) = Settings::scan(text);
+        match diagnostics.into_iter().find(|d| d.level == Level::Error) {
+            Some(error) => Err(PolicyError {
+                line: error.line,
+                message: error.message,
+            }),
+            None => Ok(settings),
+        }
+    }
+
+    /// Read every line, recording what is wrong with each instead of
+    /// stopping at the first, for [`diagnose`].
+    fn scan(text: &str) -> (Settings, Vec<Diagnostic>) {
+        let mut settings = Settings::default();
+        let mut diagnostics = Vec::new();
+        let mut section = None;
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let code_part = raw.split('#').next().unwrap_or("");
+            let content = code_part.trim();
+            if content.is_empty() {
+                continue;
+            }
+            let lead = code_part.len() - code_part.trim_start().len();
+            let mut flag = |code: &'static str, from: usize, len: usize, message: String| {
+                diagnostics.push(Diagnostic {
+                    level: Level::Error,
+                    code,
+                    line,
+                    start: column(raw, from),
+                    end: column(raw, from + len),
+                    message,
+                })
+            };
+            if let Some(name) = content.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                if name != "operations" {
+                    flag("unknown-section", lead, content.len(), format!("unknown section [{}]", name));
+                }
+                section = Some(name == "operations");
+                continue;
+            }
+            let Some((key, value)) = content.split_once('=') else {
+                let message = format!("expected `key = \"value\"`, found `{}`", content);
+                flag("syntax", lead, content.len(), message);
+                continue;
+            };
+            let key_at = lead + (key.len() - key.trim_start().len());
+            let key = key.trim();
+            let raw_value = &content[content.len() - value.len()..];
+            let mut value_at = lead + content.len() - value.len() + (raw_value.len() - raw_value.trim_start().len());
+            if raw_value.trim_start().starts_with('"') {
+                value_at += 1;
+            }
+            let value = value.trim().trim_matches('"');
+            match section {
+                // Keys in an unknown section were already reported with it.
+                Some(false) => {}
+                Some(true) => {
+                    let Some(kind) = OperationKind::from_name(key) else {
+                        let hint = suggest(key).map(|name| format!("; did you mean `{}`?", name)).unwrap_or_default();
+                        flag("unknown-operation", key_at, key.len(), format!("unknown operation `{}`{}", key, hint));
+                        continue;
+                    };
+                    let Some(decision) = Decision::parse(value) else {
+                        flag("unknown-decision", value_at, value.len(), format!("unknown decision `{}`", value));
+                        continue;
+                    };
+                    if settings.rules.iter().any(|(k, _)| *k == kind) {
+                        flag("duplicate-rule", key_at, key.len(), format!("duplicate rule for `{}`", key));
+                        continue;
+                    }
+                    settings.rules.push((kind, decision));
+                }
+                None => match key {
+                    "version" if !value.is_empty() => settings.version = Some(value.to_string()),
+                    "team" if !value.is_empty() => settings.team = Some(value.to_string()),
+                    "default" => match Decision::parse(value) {
+                        Some(decision) => settings.default = Some(decision),
+                        None => flag("unknown-decision", value_at, value.len(), format!("unknown decision `{}`", value)),
+                    },
+                    "require_reason" => {
+                        let severity = [Severity::Low, Severity::Medium, Severity::High, Severity::Critical]
+                            .into_iter()
+                            .find(|s| s.label().eq_ignore_ascii_case(value));
+                        match (value, severity) {
+                            ("none", _) => settings.require_reason = Some(None),
+                            (_, Some(severity)) => settings.require_reason = Some(Some(severity)),
+                            (_, None) => {
+                                flag("unknown-severity", value_at, value.len(), format!("unknown severity `{}`", value))
+                            }
+                        }
+                    }
+                    "consent_ttl" => match value.parse().ok().filter(|ttl| *ttl > 0) {
+                        Some(ttl) => settings.consent_ttl = Some(ttl),
+                        None => flag(
+                            "bad-ttl",
+                            value_at,
+                            value.len(),
+                            format!("`consent_ttl` must be a positive number of seconds, found `{}`", value),
+                        ),
+                    },
+                    "destructive_cooldown" => match value.parse() {
+                        Ok(cooldown) => settings.destructive_cooldown = Some(cooldown),
+                        Err(_) => flag(
+                            "bad-cooldown",
+                            value_at,
+                            value.len(),
+                            format!("`destructive_cooldown` must be a number of seconds, found `{}`", value),
+                        ),
+                    },
+                    "delete_max_files" | "delete_max_bytes" => match value.parse().ok().filter(|max| *max > 0) {
+                        Some(max) if key == "delete_max_files" => settings.delete_max_files = Some(max),
+                        Some(max) => settings.delete_max_bytes = Some(max),
+                        None => flag(
+                            "bad-limit",
+                            value_at,
+                            value.len(),
+                            format!("`{}` must be a positive number, found `{}`", key, value),
+                        ),
+                    },
+                    "transfer_waiting_period" => match value.parse() {
+                        Ok(wait) => settings.transfer_waiting_period = Some(wait),
+                        Err(_) => flag(
+                            "bad-waiting-period",
+                            value_at,
+                            value.len(),
+                            format!("`transfer_waiting_period` must be a number of seconds, found `{}`", value),
+                        ),
+                    },
+                    "require_hardware_key" => {
+                        let mut kinds = Vec::new();
+                        for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                            match OperationKind::from_name(name) {
+                                Some(kind) if !kinds.contains(&kind) => kinds.push(kind),
+                                Some(_) => {}
+                                None => {
+                                    let at = value_at + value.find(name).unwrap_or(0);
+                                    flag("unknown-operation", at, name.len(), format!("unknown operation `{}`", name));
+                                }
+                            }
+                        }
+                        settings.require_hardware_key = Some(kinds);
+                    }
+                    _ => flag("unknown-key", key_at, key.len(), format!("unknown key `{}`", key)),
+                },
+            }
+        }
+        (settings, diagnostics)
+    }
+}
+
+/// Everything wrong with policy file `text`, errors and warnings, in
+/// line order. Empty means it loads and says nothing suspicious.
+///
+/// Beyond what [`Policy::parse`] rejects, it warns about a rule that
+/// repeats the default and so decides nothing, an `allow` that does not
+/// apply because its operation cannot simply be undone, and a consent
+/// lifetime over an hour. The policy format has no approver roles to check: who may
+/// approve is the gate's configuration, not the file's.
+pub fn diagnose(text: &str) -> Vec<Diagnostic> {
+    let (settings, mut diagnostics) = Settings::scan(text);
+    if settings.version.is_none() {
+        diagnostics.push(Diagnostic {
+            level: Level::Error,
+            code: "missing-version",
+            line: 0,
+            start: 0,
+            end: 0,
+            message: "policy has no version".to_string(),
+        });
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    let locate = |needle: &str| {
+        lines.iter().enumerate().find_map(|(i, raw)| {
+            let code = raw.split('#').next().unwrap_or("");
+            let key = code.split_once('=')?.0;
+            (key.trim() == needle).then(|| {
+                let at = key.len() - key.trim_start().len();
+                (i + 1, column(raw, at), column(raw, at + needle.len()))
+            })
+        })
+    };
+    let mut warn = |code: &'static str, (line, start, end): (usize, usize, usize), message: String| {
+        diagnostics.push(Diagnostic {
+            level: Level::Warning,
+            code,
+            line,
+            start,
+            end,
+            message,
+        })
+    };
+    let default = settings.default.unwrap_or(Decision::RequireConsent);
+    for (kind, decision) in &settings.rules {
+        let at = locate(kind.name()).unwrap_or((0, 0, 0));
+        if *decision == default {
+            let message = format!("rule has no effect: the default already says `{}`", decision.label());
+            warn("redundant-rule", at, message);
+        }
+        if *decision == Decision::Allow && !undoable(*kind) {
+            let message = format!("`{}` cannot simply be undone, so `allow` does not apply: a human is still asked", kind.name());
+            warn("allow-ignored", at, message);
+        }
+    }
+    let unruled: Vec<&str> = OperationKind::ALL
+        .iter()
+        .filter(|k| !undoable(**k))
+        .filter(|k| !settings.rules.iter().any(|(r, _)| r == *k))
+        .map(|k| k.name())
+        .collect();
+    if default == Decision::Allow && !unruled.is_empty() {
+        let message = format!("the default `allow` does not apply to these, which cannot simply be undone: {}", unruled.join(", "));
+        warn("allow-ignored", locate("default").unwrap_or((0, 0, 0)), message);
+    }
+    if let Some(ttl) = settings.consent_ttl.filter(|ttl| *ttl > 3600) {
+        let message = format!("consent stays usable for {}s; the approver judged the repository as it was", ttl);
+        warn("long-ttl", locate("consent_ttl").unwrap_or((0, 0, 0)), message);
+    }
+    diagnostics.sort_by_key(|d| (d.line, d.start));
+    diagnostics
+}
+
+/// Diagnostics as an LSP `Diagnostic[]`: 0-based lines, UTF-16
+/// columns, severity 1 for errors and 2 for warnings.
+pub fn diagnostics_json(diagnostics: &[Diagnostic]) -> String {
+    let items: Vec<String> = diagnostics
+        .iter()
+        .map(|d| {
+            let line = d.line.saturating_sub(1);
+            format!(
+                "{{\"range\":{{\"start\":{{\"line\":{},\"character\":{}}},\"end\":{{\"line\":{},\"character\":{}}}}},\"severity\":{},\"code\":{},\"source\":\"safe-operations\",\"message\":{}}}",
+                line,
+                d.start,
+                line,
+                d.end,
+                if d.level == Level::Error { 1 } else { 2 },
+                super::json_string(d.code),
+                super::json_string(&d.message)
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+/// `--policy-lsp`: policy diagnostics for editors, over the Language
+/// Server Protocol on stdin and stdout.
+///
+/// Only what diagnostics need is implemented: documents are synced in
+/// full, and each open or change publishes [`diagnose`] for the text.
+pub mod lsp {
+    use super::super::json_string;
+    use super::{diagnose, diagnostics_json};
+    use serde_json::Value;
+    use std::io::{self, BufRead, Write};
+
+    /// Read one framed message, or `None` at end of input.
+    fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+        let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no Content-Length"))?;
+        let mut body = vec![0; length];
+        input.read_exact(&mut body)?;
+        Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    fn write_message(output: &mut impl Write, body: &str) -> io::Result<()> {
+        write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        output.flush()
+    }
+
+    fn publish(output: &mut impl Write, uri: &str, text: &str) -> io::Result<()> {
+        let body = format!(
+            "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":{},\"diagnostics\":{}}}}}",
+            json_string(uri),
+            diagnostics_json(&diagnose(text))
+        );
+        write_message(output, &body)
+    }
+
+    /// Serve until the client sends `exit` or closes the stream.
+    pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut input)? {
+            let message: Value = serde_json::from_str(&message).unwrap_or_default();
+            let method = message["method"].as_str().unwrap_or_default();
+            let id = message.get("id");
+            let params = &message["params"];
+            let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+            let reply = |result: &str| format!("{{\"jsonrpc\":\"2.0\",\"id\":{},{}}}", id.unwrap_or(&Value::Null), result);
+            match method {
+                "initialize" => write_message(
+                    &mut output,
+                    &reply("\"result\":{\"capabilities\":{\"textDocumentSync\":1},\"serverInfo\":{\"name\":\"safe-operations policy\"}}"),
+                )?,
+                "textDocument/didOpen" => {
+                    let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                    publish(&mut output, uri, text)?;
+                }
+                "textDocument/didChange" => {
+                    let changes = params["contentChanges"].as_array().map(Vec::as_slice).unwrap_or_default();
+                    if let Some(text) = changes.last().and_then(|c| c["text"].as_str()) {
+                        publish(&mut output, uri, text)?;
+                    }
+                }
+                "textDocument/didClose" => write_message(
+                    &mut output,
+                    &format!(
+                        "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":{},\"diagnostics\":[]}}}}",
+                        json_string(uri)
+                    ),
+                )?,
+                "shutdown" => write_message(&mut output, &reply("\"result\":null"))?,
+                "exit" => return Ok(()),
+                // Requests need an answer; notifications we do not handle do not.
+                _ if id.is_some() => write_message(
+                    &mut output,
+                    &reply("\"error\":{\"code\":-32601,\"message\":\"method not found\"}"),
+                )?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `operation` can simply be undone, and so may be let through
+/// by an `allow` with no human asked.
+fn undoable(operation: OperationKind) -> bool {
+    matches!(operation.reversibility(), super::tool_risk::Reversibility::Reversible(_))
+}
+
+/// The body of `text` whose last line is its `signature = "..."`, or
+/// `None` for the signature if it has none.
+fn split_signature(text: &str) -> (&str, Option<HostSignature>) {
+    let text = text.trim_end();
+    let (body, last) = text.rsplit_once('\n').unwrap_or(("", text));
+    let signature = last
+        .trim()
+        .strip_prefix("signature")
+        .and_then(|rest| rest.trim_start().strip_prefix('='))
+        .and_then(|hex| HostSignature::from_hex(hex.trim().trim_matches('"')));
+    match signature {
+        Some(signature) => (body.trim_end(), Some(signature)),
+        None => (text, None),
+    }
+}
+
+/// The body of `text`, a `what` file signed with `key`. Anything that
+/// does not end with a signature verifying under `key` is refused
+/// before it is read.
+fn signed_body<'a>(text: &'a str, what: &str, key: &HostKey) -> Result<&'a str, PolicyError> {
+    let (body, signature) = split_signature(text);
+    let unsigned = |message: String| PolicyError {
+        line: body.lines().count() + 1,
+        message,
+    };
+    let signature = signature.ok_or_else(|| unsigned(format!("{} does not end with its signature", what)))?;
+    if !key.verify(&format!("{}|{}", what, body), &signature) {
+        return Err(unsigned(format!("{} signature does not verify", what)));
+    }
+    Ok(body)
+}
+
+/// A validated policy. Only [`Policy::parse`] and [`Policy::builtin`]
+/// produce one, so a `Policy` value is always a policy that parsed.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    pub version: String,
+    default: Decision,
+    rules: Vec<(OperationKind, Decision)>,
+    /// Operations at or above this severity need the approver's reason.
+    require_reason: Option<Severity>,
+    /// Seconds granted consent stays usable.
+    consent_ttl: u64,
+    /// Seconds a repository's requests are held to Critical after a
+    /// grant for an operation that is not simply reversible. Zero
+    /// turns it off.
+    destructive_cooldown: u64,
+    /// Operations whose approval must be confirmed on a hardware key.
+    require_hardware_key: Vec<OperationKind>,
+    /// Files and bytes past which a recursive deletion is over its limits.
+    delete_max_files: u64,
+    delete_max_bytes: u64,
+    /// Seconds a transfer of ownership waits between opening and completing.
+    transfer_waiting_period: u64,
+    /// Which key signed the policy, if it came signed.
+    signer: Option<HostSignature>,
+}
+
+/// How long consent lasts when the policy does not say.
+pub const DEFAULT_CONSENT_TTL: u64 = 5 * 60;
+
+/// How long a repository cools down after a destructive grant when the
+/// policy does not say.
+pub const DEFAULT_DESTRUCTIVE_COOLDOWN: u64 = 30 * 60;
+
+/// How many files a recursive deletion may remove before it is over its
+/// limits, when the policy does not say.
+pub const DEFAULT_DELETE_MAX_FILES: u64 = 1_000;
+
+/// How many bytes a recursive deletion may remove before it is over its
+/// limits, when the policy does not say.
+pub const DEFAULT_DELETE_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// How long a transfer of ownership waits before it can complete, when
+/// the policy does not say.
+pub const DEFAULT_TRANSFER_WAITING_PERIOD: u64 = 24 * 60 * 60;
+
+impl Policy {
+    /// The policy a gate starts with: every destructive operation
+    /// requires consent.
+    pub fn builtin() -> Policy {
+        Policy {
+            version: "builtin".to_string(),
+            default: Decision::RequireConsent,
+            rules: Vec::new(),
+            require_reason: None,
+            consent_ttl: DEFAULT_CONSENT_TTL,
+            destructive_cooldown: DEFAULT_DESTRUCTIVE_COOLDOWN,
+            require_hardware_key: Vec::new(),
+            delete_max_files: DEFAULT_DELETE_MAX_FILES,
+            delete_max_bytes: DEFAULT_DELETE_MAX_BYTES,
+            transfer_waiting_period: DEFAULT_TRANSFER_WAITING_PERIOD,
+            signer: None,
+        }
+    }
+
+    /// Read a policy file to inspect it. A signature, if the file has
+    /// one, is skipped unchecked: a gate only enforces a policy from
+    /// [`Policy::load_signed`].
+    pub fn load(path: &Path) -> Result<Policy, PolicyError> {
+        Policy::parse(split_signature(&read_policy(path)?).0)
+    }
+
+    /// Read a policy file signed with `key`.
+    pub fn load_signed(path: &Path, key: &HostKey) -> Result<Policy, PolicyError> {
+        Policy::parse_signed(&read_policy(path)?, key)
+    }
+
+    /// Sign the body of a policy file, returning the file to install.
+    pub fn sign(body: &str, key: &HostKey) -> String {
+        let body = body.trim_end();
+        format!("{}\nsignature = \"{}\"\n", body, key.sign(&format!("policy|{}", body)).to_hex())
+    }
+
+    /// Parse a signed policy, the same way a [`DenyList`] is: the last
+    /// line must be its signature under `key`.
+    pub fn parse_signed(text: &str, key: &HostKey) -> Result<Policy, PolicyError> {
+        let policy = Policy::parse(signed_body(text, "policy", key)?)?;
+        Ok(Policy {
+            signer: Some(key.sign("policy-signer")),
+            ..policy
+        })
+    }
+
+    /// Whether this policy came signed with `key`.
+    pub fn signed_by(&self, key: &HostKey) -> bool {
+        self.signer == Some(key.sign("policy-signer"))
+    }
+
+    /// Parse and validate policy text. Unknown keys, unknown operation
+    /// names, bad decisions, and a missing version are all errors —
+    /// a typo must never silently fall through to the default.
+    pub fn parse(text: &str) -> Result<Policy, PolicyError> {
+        let settings = Settings::parse(text)?;
+        let version = settings.version.ok_or(PolicyError {
+            line: 0,
+            message: "policy has no version".to_string(),
+        })?;
+        Ok(Policy {
+            version,
+            default: settings.default.unwrap_or(Decision::RequireConsent),
+            rules: settings.rules,
+            require_reason: settings.require_reason.unwrap_or(None),
+            consent_ttl: settings.consent_ttl.unwrap_or(DEFAULT_CONSENT_TTL),
+            destructive_cooldown: settings.destructive_cooldown.unwrap_or(DEFAULT_DESTRUCTIVE_COOLDOWN),
+            require_hardware_key: settings.require_hardware_key.unwrap_or_default(),
+            delete_max_files: settings.delete_max_files.unwrap_or(DEFAULT_DELETE_MAX_FILES),
+            delete_max_bytes: settings.delete_max_bytes.unwrap_or(DEFAULT_DELETE_MAX_BYTES),
+            transfer_waiting_period: settings.transfer_waiting_period.unwrap_or(DEFAULT_TRANSFER_WAITING_PERIOD),
+            signer: None,
+        })
+    }
+
+    pub fn decide(&self, operation: OperationKind) -> Decision {
+        let decision = self
+            .rules
+            .iter()
+            .find(|(k, _)| *k == operation)
+            .map(|(_, d)| *d)
+            .unwrap_or(self.default);
+        // `allow` skips the prompt; it never stands in for the human on
+        // an operation that cannot simply be undone.
+        match undoable(operation) {
+            true => decision,
+            false => decision.max(Decision::RequireConsent),
+        }
+    }
+
+    /// Seconds from grant until consent expires.
+    pub fn consent_ttl(&self) -> u64 {
+        self.consent_ttl
+    }
+
+    /// Seconds a repository is held to Critical after a destructive grant.
+    pub fn destructive_cooldown(&self) -> u64 {
+        self.destructive_cooldown
+    }
+
+    /// The limits a recursive deletion is measured against.
+    pub fn deletion_limits(&self) -> DeletionLimits {
+        DeletionLimits {
+            max_files: self.delete_max_files,
+            max_bytes: self.delete_max_bytes,
+            ..DeletionLimits::default()
+        }
+    }
+
+    /// How long a transfer of ownership waits before it can complete.
+    pub fn transfer_waiting_period(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.transfer_waiting_period)
+    }
+
+    /// Whether approval of `operation` must be confirmed with a touch
+    /// on a hardware security key.
+    pub fn requires_hardware_key(&self, operation: OperationKind) -> bool {
+        self.require_hardware_key.contains(&operation)
+    }
+
+    /// Whether an approval at `severity` must state why.
+    pub fn requires_reason(&self, severity: Severity) -> bool {
+        self.require_reason.is_some_and(|min| severity >= min)
+    }
+
+    /// The line of this policy that decides `operation`, as it would
+    /// be written in the policy file.
+    pub fn citation(&self, operation: OperationKind) -> String {
+        let (line, written) = match self.rules.iter().find(|(k, _)| *k == operation) {
+            Some((kind, decision)) => (format!("[operations] {} = \"{}\"", kind.name(), decision.label()), *decision),
+            None => (format!("default = \"{}\"", self.default.label()), self.default),
+        };
+        match written == self.decide(operation) {
+            true => format!("policy {}, {}", self.version, line),
+            false => format!("policy {}, {} (not applied: {} cannot simply be undone)", self.version, line, operation.name()),
+        }
+    }
+}
+
+fn read_policy(path: &Path) -> Result<String, PolicyError> {
+    std::fs::read_to_string(path).map_err(|e| PolicyError {
+        line: 0,
+        message: format!("cannot read {}: {}", path.display(), e),
+    })
+}
+
+/// Which layer of a [`PolicyTree`] a setting came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Org,
+    Team,
+    Repo,
+}
+
+impl Tier {
+    pub fn label(self) -> &'static str {
+        match self {
+            Tier::Org => "org",
+            Tier::Team => "team",
+            Tier::Repo => "repo",
+        }
+    }
+}
+
+/// One policy file in the hierarchy.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub tier: Tier,
+    pub source: PathBuf,
+    settings: Settings,
+}
+
+impl Layer {
+    /// Load one layer, refusing it unless it is signed with `key` if one
+    /// is given. Without one, a signature is skipped unchecked.
+    fn load(tier: Tier, source: PathBuf, key: Option<&HostKey>) -> Result<Layer, PolicyError> {
+        let text = read_policy(&source)?;
+        let located = |e: PolicyError| PolicyError {
+            line: e.line,
+            message: format!("{}: {}", source.display(), e.message),
+        };
+        let body = match key {
+            Some(key) => signed_body(&text, "policy", key).map_err(located)?,
+            None => split_signature(&text).0,
+        };
+        let settings = Settings::parse(body).map_err(located)?;
+        if tier != Tier::Repo && settings.team.is_some() {
+            return Err(PolicyError {
+                line: 0,
+                message: format!("{}: only a repository's .safetyrc names a team", source.display()),
+            });
+        }
+        Ok(Layer { tier, source, settings })
+    }
+
+    /// `tier:version`, or just the tier if the file has no version.
+    fn name(&self) -> String {
+        match &self.settings.version {
+            Some(version) => format!("{}:{}", self.tier.label(), version),
+            None => self.tier.label().to_string(),
+        }
+    }
+}
+
+/// Where one effective setting came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// The setting as written in a policy file: `default`, `force_push`.
+    pub setting: String,
+    pub value: String,
+    /// `None` if no layer set it and the builtin value stands.
+    pub tier: Option<Tier>,
+    /// What the winning layer overrode, or what it refused to let a
+    /// lower layer override.
+    pub notes: Vec<String>,
+}
+
+/// A repository's policy with every setting traced to its layer.
+#[derive(Clone, Debug)]
+pub struct Effective {
+    pub policy: Policy,
+    pub layers: Vec<(Tier, PathBuf)>,
+    pub provenance: Vec<Provenance>,
+}
+
+impl Effective {
+    /// One line per setting: its value, the layer it came from, and
+    /// anything that layer overrode.
+    pub fn explain(&self) -> String {
+        let mut out = format!("effective policy {}\n", self.policy.version);
+        for (tier, source) in &self.layers {
+            out.push_str(&format!("  {:<5} {}\n", tier.label(), source.display()));
+        }
+        for entry in &self.provenance {
+            let assignment = format!("{} = \"{}\"", entry.setting, entry.value);
+            let from = entry.tier.map_or("builtin", Tier::label);
+            out.push_str(&format!("  {:<36} {}", assignment, from));
+            for note in &entry.notes {
+                out.push_str(&format!("; {}", note));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// A fleet's policy hierarchy, org → team → repo:
+///
+/// ```text
+/// <root>/org.safetyrc             the base every repository inherits
+/// <root>/teams/<team>.safetyrc    the team named by `team = "..."`
+/// <repo>/.safetyrc                the repository's own overrides
+/// ```
+///
+/// Precedence, setting by setting: the org sets what it likes, and a
+/// layer below may only tighten what it inherits. A stricter decision,
+/// a shorter consent lifetime, a longer cooldown, a lower reason
+/// threshold, and another operation needing a hardware key all stick;
+/// an attempt to loosen any of them is ignored and shows up in
+/// [`Effective::explain`]. A team cannot reopen what the org closed,
+/// and a repository cannot reopen what its team closed.
+///
+/// The org file is required. A repository without a `.safetyrc`, or
+/// whose `.safetyrc` names no team, inherits from the layers it has. A
+/// team that is named but has no file is an error: a misspelt team must
+/// not quietly mean "no team policy". A gate enforces the tree only if
+/// the org file is signed, as it would a single policy file.
+pub struct PolicyTree {
+    root: PathBuf,
+}
+
+impl PolicyTree {
+    pub fn new(root: impl Into<PathBuf>) -> PolicyTree {
+        PolicyTree { root: root.into() }
+    }
+
+    fn layers(&self, repo: &Path, key: Option<&HostKey>) -> Result<Vec<Layer>, PolicyError> {
+        let mut layers = vec![Layer::load(Tier::Org, self.root.join("org.safetyrc"), key)?];
+        let rc = repo.join(".safetyrc");
+        let own = if rc.exists() { Some(Layer::load(Tier::Repo, rc, None)?) } else { None };
+        if let Some(team) = own.as_ref().and_then(|l| l.settings.team.as_deref()) {
+            let source = self.root.join("teams").join(format!("{}.safetyrc", team));
+            layers.push(Layer::load(Tier::Team, source, None)?);
+        }
+        layers.extend(own);
+        Ok(layers)
+    }
+
+    /// Resolve the policy `repo` runs under, to explain it. The org file's
+    /// signature is not checked; a gate enforces only
+    /// [`signed_policy`](Self::signed_policy).
+    pub fn effective_policy<S, B>(&self, repo: &Repository<S, B>) -> Result<Effective, PolicyError> {
+        self.resolve(Path::new(&repo.path), None)
+    }
+
+    /// Resolve the policy `repo` runs under, refusing an org file that is
+    /// not signed with `key`.
+    pub fn signed_policy<S, B>(&self, repo: &Repository<S, B>, key: &HostKey) -> Result<Effective, PolicyError> {
+        self.resolve(Path::new(&repo.path), Some(key))
+    }
+
+    fn resolve(&self, repo: &Path, key: Option<&HostKey>) -> Result<Effective, PolicyError> {
+        let layers = self.layers(repo, key)?;
+        let builtin = Policy::builtin();
+        let mut default = (builtin.default, None, Vec::new());
+        let mut require_reason = (builtin.require_reason, None, Vec::new());
+        let mut consent_ttl = (builtin.consent_ttl, None, Vec::new());
+        let mut destructive_cooldown = (builtin.destructive_cooldown, None, Vec::new());
+        let mut delete_max_files = (builtin.delete_max_files, None, Vec::new());
+        let mut delete_max_bytes = (builtin.delete_max_bytes, None, Vec::new());
+        let mut transfer_waiting_period = (builtin.transfer_waiting_period, None, Vec::new());
+        // A lower layer can add to what needs a hardware key, never take away.
+        let mut hardware_key: (Vec<OperationKind>, Option<Tier>, Vec<String>) = (Vec::new(), None, Vec::new());
+        let mut rules: Vec<(OperationKind, Decision, Option<Tier>, Vec<String>)> = Vec::new();
+
+        /// Apply one layer's value. The org sets what it likes; a layer
+        /// below may only move a setting the way `tighter` picks.
+        fn set<T: Copy + PartialEq>(
+            slot: &mut (T, Option<Tier>, Vec<String>),
+            value: Option<T>,
+            tier: Tier,
+            show: fn(T) -> String,
+            tighter: fn(T, T) -> T,
+        ) {
+            let Some(value) = value else {
+                return;
+            };
+            let above = slot.1.map_or("builtin", Tier::label);
+            if tier != Tier::Org && tighter(slot.0, value) != value {
+                let note = format!("{} \"{}\" ignored: looser than {} \"{}\"", tier.label(), show(value), above, show(slot.0));
+                slot.2.push(note);
+                return;
+            }
+            let notes = match slot.1 {
+                Some(_) => vec![format!("overrides {} \"{}\"", above, show(slot.0))],
+                None => Vec::new(),
+            };
+            *slot = (value, Some(tier), notes);
+        }
+        let severity = |s: Option<Severity>| s.map_or("none", Severity::label).to_lowercase();
+        // A reason is needed from the lower of two thresholds; `none` is the loosest.
+        let reason_threshold = |a: Option<Severity>, b: Option<Severity>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        for layer in &layers {
+            let settings = &layer.settings;
+            // A rule is held to what its operation inherited, not to this layer's own default.
+            let inherited = (default.0, default.1);
+            set(&mut default, settings.default, layer.tier, |d| d.label().to_string(), Ord::max);
+            for &(kind, decision) in &settings.rules {
+                let ruled = rules.iter().position(|(k, ..)| *k == kind);
+                let (above, from) = match ruled {
+                    Some(i) => (rules[i].1, rules[i].2),
+                    None => inherited,
+                };
+                if layer.tier != Tier::Org && decision < above {
+                    let note = format!(
+                        "{} {} \"{}\" ignored: looser than {} {} \"{}\"",
+                        layer.tier.label(),
+                        kind.name(),
+                        decision.label(),
+                        from.map_or("builtin", Tier::label),
+                        if ruled.is_some() { kind.name() } else { "default" },
+                        above.label()
+                    );
+                    match ruled {
+                        Some(i) => rules[i].3.push(note),
+                        None => default.2.push(note),
+                    }
+                    continue;
+                }
+                let overridden = match (ruled, from) {
+                    (Some(_), Some(tier)) => vec![format!("overrides {} \"{}\"", tier.label(), above.label())],
+                    _ => Vec::new(),
+                };
+                match ruled {
+                    Some(i) => rules[i] = (kind, decision, Some(layer.tier), overridden),
+                    None => rules.push((kind, decision, Some(layer.tier), overridden)),
+                }
+            }
+            set(&mut require_reason, settings.require_reason, layer.tier, severity, reason_threshold);
+            set(&mut consent_ttl, settings.consent_ttl, layer.tier, |t| t.to_string(), Ord::min);
+            set(&mut destructive_cooldown, settings.destructive_cooldown, layer.tier, |t| t.to_string(), Ord::max);
+            set(&mut delete_max_files, settings.delete_max_files, layer.tier, |n| n.to_string(), Ord::min);
+            set(&mut delete_max_bytes, settings.delete_max_bytes, layer.tier, |n| n.to_string(), Ord::min);
+            let wait = settings.transfer_waiting_period;
+            set(&mut transfer_waiting_period, wait, layer.tier, |t| t.to_string(), Ord::max);
+            for &kind in settings.require_hardware_key.iter().flatten() {
+                if !hardware_key.0.contains(&kind) {
+                    hardware_key.0.push(kind);
+                    hardware_key.2.push(format!("{} adds {}", layer.tier.label(), kind.name()));
+                    hardware_key.1 = Some(layer.tier);
+                }
+            }
+        }
+
+        let mut provenance = vec![
+            Provenance {
+                setting: "default".to_string(),
+                value: default.0.label().to_string(),
+                tier: default.1,
+                notes: default.2,
+            },
+            Provenance {
+                setting: "require_reason".to_string(),
+                value: severity(require_reason.0),
+                tier: require_reason.1,
+                notes: require_reason.2,
+            },
+            Provenance {
+                setting: "consent_ttl".to_string(),
+                value: consent_ttl.0.to_string(),
+                tier: consent_ttl.1,
+                notes: consent_ttl.2,
+            },
+            Provenance {
+                setting: "destructive_cooldown".to_string(),
+                value: destructive_cooldown.0.to_string(),
+                tier: destructive_cooldown.1,
+                notes: destructive_cooldown.2,
+            },
+            Provenance {
+                setting: "delete_max_files".to_string(),
+                value: delete_max_files.0.to_string(),
+                tier: delete_max_files.1,
+                notes: delete_max_files.2,
+            },
+            Provenance {
+                setting: "delete_max_bytes".to_string(),
+                value: delete_max_bytes.0.to_string(),
+                tier: delete_max_bytes.1,
+                notes: delete_max_bytes.2,
+            },
+            Provenance {
+                setting: "transfer_waiting_period".to_string(),
+                value: transfer_waiting_period.0.to_string(),
+                tier: transfer_waiting_period.1,
+                notes: transfer_waiting_period.2,
+            },
+            Provenance {
+                setting: "require_hardware_key".to_string(),
+                value: hardware_key.0.iter().map(|k| k.name()).collect::<Vec<_>>().join(", "),
+                tier: hardware_key.1,
+                notes: hardware_key.2,
+            },
+        ];
+        rules.sort_by_key(|(kind, ..)| OperationKind::ALL.iter().position(|k| k == kind));
+        for (kind, decision, tier, notes) in &rules {
+            provenance.push(Provenance {
+                setting: kind.name().to_string(),
+                value: decision.label().to_string(),
+                tier: *tier,
+                notes: notes.clone(),
+            });
+        }
+        Ok(Effective {
+            policy: Policy {
+                version: layers.iter().map(Layer::name).collect::<Vec<_>>().join("/"),
+                default: default.0,
+                rules: rules.into_iter().map(|(kind, decision, ..)| (kind, decision)).collect(),
+                require_reason: require_reason.0,
+                consent_ttl: consent_ttl.0,
+                destructive_cooldown: destructive_cooldown.0,
+                delete_max_files: delete_max_files.0,
+                delete_max_bytes: delete_max_bytes.0,
+                transfer_waiting_period: transfer_waiting_period.0,
+                require_hardware_key: hardware_key.0,
+                signer: key.map(|key| key.sign("policy-signer")),
+            },
+            layers: layers.into_iter().map(|l| (l.tier, l.source)).collect(),
+            provenance,
+        })
+    }
+}
+
+/// One entry of the organization's hard deny-list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forbidden {
+    /// `None` forbids every operation on the target.
+    pub operation: Option<OperationKind>,
+    /// A target name, or a prefix ending in `*`.
+    pub target: String,
+    pub why: String,
+}
+
+impl Forbidden {
+    /// Whether this entry covers `operation` on `target`. A request
+    /// whose target the gate does not know is covered if its
+    /// description names the target: a hard deny errs toward denying.
+    pub fn covers(&self, operation: OperationKind, target: Option<&str>, description: &str) -> bool {
+        if self.operation.is_some_and(|op| op != operation) {
+            return false;
+        }
+        match self.target.strip_suffix('*') {
+            Some(prefix) => target.is_some_and(|t| t.starts_with(prefix)) || description.contains(prefix),
+            None => target == Some(self.target.as_str()) || description.contains(self.target.as_str()),
+        }
+    }
+}
+
+/// Operations no consent can unlock.
+///
+/// ```text
+/// # .safety-forbidden
+/// version = "2026-04-01.1"
+/// delete_files   audit-logs-prod = "the audit trail outlives every incident"
+/// filter_repo    compliance-*    = "history on compliance repos is evidence"
+/// *              legal-hold      = "under legal hold"
+/// signature = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+/// ```
+///
+/// It is not part of [`Policy`]. A policy file cannot contain it, a
+/// policy reload cannot touch it, and the gate consults it before the
+/// manifest, the policy, or any prompt. Changing it is a separate
+/// process: the list is signed with the organization's key, and a gate
+/// that has one installed only accepts a newer version signed by the
+/// same key.
+#[derive(Clone, Debug)]
+pub struct DenyList {
+    pub version: String,
+    pub entries: Vec<Forbidden>,
+    /// Which key signed the list, so a replacement can be held to it.
+    signer: HostSignature,
+}
+
+impl DenyList {
+    /// Sign the body of a deny-list file, returning the file to install.
+    pub fn sign(body: &str, key: &HostKey) -> String {
+        let body = body.trim_end();
+        format!("{}\nsignature = \"{}\"\n", body, key.sign(&format!("deny-list|{}", body)).to_hex())
+    }
+
+    /// Parse a signed deny-list. The last line must be its signature
+    /// under `key`; anything else is refused before it is read.
+    pub fn parse(text: &str, key: &HostKey) -> Result<DenyList, PolicyError> {
+        let body = signed_body(text, "deny-list", key)?;
+        let mut version = None;
+        let mut entries = Vec::new();
+        for (i, raw) in body.lines().enumerate() {
+            let err = |message: String| PolicyError { line: i + 1, message };
+            let content = raw.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = content.split_once('=') else {
+                return Err(err(format!("expected `operation target = \"why\"`, found `{}`", content)));
+            };
+            let value = value.trim().trim_matches('"');
+            match key.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["version"] if !value.is_empty() => version = Some(value.to_string()),
+                [operation, target] => {
+                    let operation = match *operation {
+                        "*" => None,
+                        name => Some(
+                            OperationKind::from_name(name)
+                                .ok_or_else(|| err(format!("unknown operation `{}`", name)))?,
+                        ),
+                    };
+                    if value.is_empty() {
+                        return Err(err(format!("no reason given for forbidding `{}`", target)));
+                    }
+                    entries.push(Forbidden {
+                        operation,
+                        target: target.to_string(),
+                        why: value.to_string(),
+                    });
+                }
+                _ => return Err(err(format!("unknown key `{}`", key.trim()))),
+            }
+        }
+        Ok(DenyList {
+            version: version.ok_or(PolicyError {
+                line: 0,
+                message: "deny-list has no version".to_string(),
+            })?,
+            entries,
+            signer: key.sign("deny-list-signer"),
+        })
+    }
+
+    /// The entry forbidding `operation` on `target`, if any.
+    pub fn forbids(&self, operation: OperationKind, target: Option<&str>, description: &str) -> Option<&Forbidden> {
+        self.entries.iter().find(|f| f.covers(operation, target, description))
+    }
+
+    /// Whether `next` may replace this list: same signer, newer version.
+    /// Versions compare as strings, so write them to sort.
+    pub fn may_be_replaced_by(&self, next: &DenyList) -> Result<(), String> {
+        if next.signer != self.signer {
+            return Err("signed by a different key than the installed list".to_string());
+        }
+        if next.version <= self.version {
+            return Err(format!("version {} is not newer than {}", next.version, self.version));
+        }
+        Ok(())
+    }
+}
+
+/// One decision the gate made, as recorded at the time.
+#[derive(Clone, Debug)]
+pub struct RecordedDecision {
+    pub operation: OperationKind,
+    pub description: String,
+    pub decision: Decision,
+    /// Why the approver said yes, if they said.
+    pub reason: Option<String>,
+}
+
+/// A recorded agent session: every request and what the policy said.
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    pub decisions: Vec<RecordedDecision>,
+    /// Sparse cones agents were confined to, as `(agent, directories)`.
+    pub scopes: Vec<(String, Vec<String>)>,
+}
+
+/// How a past decision changes under a candidate policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Unchanged,
+    /// Was allowed or consentable; would now be denied.
+    Blocked,
+    /// Was denied, or needed consent; would now be let through more easily.
+    Allowed,
+    /// Was allowed outright; would now need consent.
+    Escalated,
+}
+
+/// The result of replaying a session against a candidate policy.
+pub struct WhatIf {
+    pub policy_version: String,
+    pub rows: Vec<(RecordedDecision, Decision, Change)>,
+}
+
+impl WhatIf {
+    pub fn count(&self, change: Change) -> usize {
+        self.rows.iter().filter(|(_, _, c)| *c == change).count()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "What-if under policy {}: {} blocked, {} allowed, {} escalated, {} unchanged\n",
+            self.policy_version,
+            self.count(Change::Blocked),
+            self.count(Change::Allowed),
+            self.count(Change::Escalated),
+            self.count(Change::Unchanged)
+        );
+        for (recorded, now, change) in &self.rows {
+            if *change != Change::Unchanged {
+                out.push_str(&format!(
+                    "  {:?}: {} ({} -> {}) {}\n",
+                    change,
+                    recorded.operation.name(),
+                    recorded.decision.label(),
+                    now.label(),
+                    recorded.description
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Replay every decision in `session` against `candidate`.
+///
+/// Safety teams can see what a policy change would have done to real
+/// traffic — which requests it would have stopped, and which it would
+/// have waved through — before it decides anything.
+pub fn whatif(session: &Session, candidate: &Policy) -> WhatIf {
+    let rows = session
+        .decisions
+        .iter()
+        .map(|recorded| {
+            let now = candidate.decide(recorded.operation);
+            let change = match (recorded.decision, now) {
+                (was, now) if was == now => Change::Unchanged,
+                (_, Decision::Deny) => Change::Blocked,
+                (Decision::Allow, Decision::RequireConsent) => Change::Escalated,
+                _ => Change::Allowed,
+            };
+            (recorded.clone(), now, change)
+        })
+        .collect();
+    WhatIf {
+        policy_version: candidate.version.clone(),
+        rows,
+    }
+}
+
+/// One expectation: in this situation, this operation gets this decision.
+#[derive(Clone, Debug)]
+pub struct PolicyTest {
+    pub operation: OperationKind,
+    /// The situation the team cares about, in their words. Reported
+    /// with a failure so the reader knows what broke, not just where.
+    pub context: String,
+    pub expect: Decision,
+    /// Line of the `[[test]]` header, for error messages.
+    pub line: usize,
+}
+
+/// A policy together with the expectations written next to it.
+pub struct PolicySet {
+    pub policy: Policy,
+    pub tests: Vec<PolicyTest>,
+}
+
+/// The outcome of running a policy's tests.
+pub struct TestReport {
+    pub policy_version: String,
+    pub passed: usize,
+    /// Each failing test, with what the policy actually decided.
+    pub failures: Vec<(PolicyTest, Decision)>,
+}
+
+impl TestReport {
+    pub fn ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (test, got) in &self.failures {
+            out.push_str(&format!(
+                "  FAIL line {}: {} ({}) expected {}, policy says {}\n",
+                test.line,
+                test.operation.name(),
+                test.context,
+                test.expect.label(),
+                got.label()
+            ));
+        }
+        out.push_str(&format!(
+            "policy {}: {} passed, {} failed\n",
+            self.policy_version,
+            self.passed,
+            self.failures.len()
+        ));
+        out
+    }
+}
+
+impl PolicySet {
+    /// Load `policy` and the `policy_tests.toml` beside it.
+    pub fn load(policy: &Path) -> Result<PolicySet, PolicyError> {
+        let tests = policy.with_file_name("policy_tests.toml");
+        PolicySet::load_with_tests(policy, &tests)
+    }
+
+    pub fn load_with_tests(policy: &Path, tests: &Path) -> Result<PolicySet, PolicyError> {
+        let text = std::fs::read_to_string(tests).map_err(|e| PolicyError {
+            line: 0,
+            message: format!("cannot read {}: {}", tests.display(), e),
+        })?;
+        Ok(PolicySet {
+            policy: Policy::load(policy)?,
+            tests: parse_tests(&text)?,
+        })
+    }
+
+    pub fn run_tests(&self) -> TestReport {
+        let mut report = TestReport {
+            policy_version: self.policy.version.clone(),
+            passed: 0,
+            failures: Vec::new(),
+        };
+        for test in &self.tests {
+            let got = self.policy.decide(test.operation);
+            if got == test.expect {
+                report.passed += 1;
+            } else {
+                report.failures.push((test.clone(), got));
+            }
+        }
+        report
+    }
+}
+
+/// Parse a tests file: `[[test]]` tables of `operation`, `context`,
+/// and `expect`. Validated as strictly as the policy itself — a test
+/// that silently never runs is worse than none.
+pub fn parse_tests(text: &str) -> Result<Vec<PolicyTest>, PolicyError> {
+    type Partial = (usize, Option<OperationKind>, String, Option<Decision>);
+    let finish = |(line, operation, context, expect): Partial| -> Result<PolicyTest, PolicyError> {
+        match (operation, expect) {
+            (Some(operation), Some(expect)) => Ok(PolicyTest {
+                operation,
+                context,
+                expect,
+                line,
+            }),
+            _ => Err(PolicyError {
+                line,
+                message: "test needs both `operation` and `expect`".to_string(),
+            }),
+        }
+    };
+    let mut tests = Vec::new();
+    let mut current: Option<Partial> = None;
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let err = |message: String| PolicyError { line, message };
+        let content = raw.split('#').next().unwrap_or("").trim();
+        if content.is_empty() {
+            continue;
+        }
+        if content == "[[test]]" {
+            if let Some(done) = current.take() {
+                tests.push(finish(done)?);
+            }
+            current = Some((line, None, String::new(), None));
+            continue;
+        }
+        let Some(test) = current.as_mut() else {
+            return Err(err("expected `[[test]]` before any keys".to_string()));
+        };
+        let Some((key, value)) = content.split_once('=') else {
+            return Err(err(format!("expected `key = \"value\"`, found `{}`", content)));
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim() {
+            "operation" => {
+                test.1 = Some(
+                    OperationKind::from_name(value)
+                        .ok_or_else(|| err(format!("unknown operation `{}`", value)))?,
+                )
+            }
+            "context" => test.2 = value.to_string(),
+            "expect" => {
+                test.3 = Some(Decision::parse(value).ok_or_else(|| err(format!("unknown decision `{}`", value)))?)
+            }
+            other => return Err(err(format!("unknown key `{}`", other))),
+        }
+    }
+    if let Some(done) = current {
+        tests.push(finish(done)?);
+    }
+    Ok(tests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decision, Policy, PolicyTree};
+    use crate::approver::{Prompt, Responder};
+    use crate::{HostKey, OperationKind, Repository, SafetyGate, Severity};
+
+    /// An approver who never answers.
+    struct Silent;
+
+    impl Responder for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+            None
+        }
+    }
+
+    const LOOSE: &str = "version = \"loose\"\ndefault = \"allow\"\n";
+
+    #[test]
+    fn a_policy_file_is_enforced_only_if_signed_with_the_watched_key() {
+        let key = HostKey::from_secret([7; 32]);
+        let path = std::env::temp_dir().join(format!("safe-operations-policy-{}", std::process::id()));
+        let mut gate = SafetyGate::new();
+        std::fs::write(&path, LOOSE).unwrap();
+        assert!(gate.watch_policy(&path, &key).is_err());
+        std::fs::write(&path, Policy::sign(LOOSE, &HostKey::from_secret([8; 32]))).unwrap();
+        assert!(gate.watch_policy(&path, &key).is_err());
+        assert_eq!(gate.policy_version(), "builtin");
+        std::fs::write(&path, Policy::sign(LOOSE, &key)).unwrap();
+        assert!(gate.watch_policy(&path, &key).is_ok());
+        assert_eq!(gate.policy_version(), "loose");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_proposed_policy_is_promoted_only_by_its_signer() {
+        let key = HostKey::from_secret([7; 32]);
+        let mut gate = SafetyGate::new();
+        assert!(gate.propose_policy(LOOSE, &key).is_err());
+        gate.propose_policy(&Policy::sign(LOOSE, &key), &key).unwrap();
+        assert!(!gate.promote_proposed_policy(&HostKey::from_secret([8; 32])));
+        assert_eq!(gate.policy_version(), "builtin");
+        assert!(gate.promote_proposed_policy(&key));
+        assert_eq!(gate.policy_version(), "loose");
+    }
+
+    #[test]
+    fn allow_skips_no_human_on_an_operation_that_cannot_simply_be_undone() {
+        let policy = Policy::parse(LOOSE).unwrap();
+        assert_eq!(policy.decide(OperationKind::RemoveRemote), Decision::Allow);
+        for kind in [OperationKind::ForcePush, OperationKind::DeleteBranch, OperationKind::FilterRepo] {
+            assert_eq!(policy.decide(kind), Decision::RequireConsent, "{}", kind.name());
+        }
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Silent));
+        gate.policy = policy;
+        assert!(gate.request_consent(OperationKind::RemoveRemote, "remove remote upstream").is_ok());
+        assert!(gate.request_consent(OperationKind::ForcePush, "force-push main").is_err());
+    }
+
+    #[test]
+    fn a_repository_cannot_loosen_what_the_org_set() {
+        let root = std::env::temp_dir().join(format!("safe-operations-tree-{}", std::process::id()));
+        let repo_dir = root.join("repo");
+        std::fs::create_dir_all(&repo_dir).unwrap();
+        let org = "version = \"1\"\nrequire_reason = \"high\"\nconsent_ttl = \"300\"\n\n[operations]\nforce_push = \"deny\"\n";
+        std::fs::write(root.join("org.safetyrc"), org).unwrap();
+        let repo_rc = "default = \"allow\"\nrequire_reason = \"none\"\nconsent_ttl = \"86400\"\ndestructive_cooldown = \"3600\"\n\n[operations]\nforce_push = \"allow\"\nfilter_repo = \"allow\"\ndelete_tag = \"deny\"\n";
+        std::fs::write(repo_dir.join(".safetyrc"), repo_rc).unwrap();
+        let repo = Repository::open("repo", &repo_dir.to_string_lossy(), 10);
+        let effective = PolicyTree::new(&root).effective_policy(&repo).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let policy = &effective.policy;
+        assert_eq!(policy.decide(OperationKind::ForcePush), Decision::Deny);
+        assert_eq!(policy.decide(OperationKind::FilterRepo), Decision::RequireConsent);
+        assert_eq!(policy.decide(OperationKind::RemoveRemote), Decision::RequireConsent);
+        assert_eq!(policy.decide(OperationKind::DeleteTag), Decision::Deny);
+        assert!(policy.requires_reason(Severity::High));
+        assert_eq!(policy.consent_ttl(), 300);
+        assert_eq!(policy.destructive_cooldown(), 3600);
+        let explained = effective.explain();
+        assert!(explained.contains("repo force_push \"allow\" ignored: looser than org force_push \"deny\""), "{}", explained);
+        assert!(explained.contains("repo \"86400\" ignored"), "{}", explained);
+    }
+}
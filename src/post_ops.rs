@@ -0,0 +1,392 @@
+//! Work that follows an approved destructive operation.
+//!
+//! A force-push does not only change the remote. Every clone, every open
+//! pull request, and every CI cache is now based on commits that no longer
+//! exist upstream. In the incident nobody was told; collaborators found out
+//! when their pushes were rejected.
+//!
+//! A gate given a [`Downstream`] with [`SafetyGate::notify_downstream`]
+//! shares it with every consent it mints, and a force-push or
+//! `filter_repo` done under that consent tells it once the rewrite
+//! completes.
+
+use super::consent_backend::curl_with_headers;
+use super::consent_client::SourceError;
+use super::{json_string, OperationKind, Outcome, Receipt, SafetyGate};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One ref the operation moved.
+#[derive(Clone, Debug)]
+pub struct RefUpdate {
+    pub refname: String,
+    pub old_sha: String,
+    pub new_sha: String,
+}
+
+impl RefUpdate {
+    /// `branch` moved from `old` to `new`, as the backend named them. A
+    /// tip it could not name is reported as unknown, not left out.
+    pub(crate) fn branch(branch: &str, old: Result<String, Outcome>, new: Result<String, Outcome>) -> Self {
+        let sha = |tip: Result<String, Outcome>| tip.unwrap_or_else(|_| "(unknown)".to_string());
+        RefUpdate {
+            refname: format!("refs/heads/{}", branch),
+            old_sha: sha(old),
+            new_sha: sha(new),
+        }
+    }
+}
+
+/// Who downstream needs to hear about a rewrite.
+#[derive(Clone, Debug, Default)]
+pub struct Downstream {
+    /// Open pull request numbers whose base was rewritten.
+    pub open_prs: Vec<u32>,
+    /// Contributor email addresses.
+    pub contributors: Vec<String>,
+    /// A team channel, e.g. `#eng-announce`.
+    pub channel: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Recipient {
+    PullRequest(u32),
+    Email(String),
+    Channel(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub recipient: Recipient,
+    pub body: String,
+}
+
+/// Delivers notifications: a platform API client, SMTP, chat webhook.
+pub trait Notifier {
+    fn deliver(&mut self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Prints notifications instead of sending them.
+pub struct PrintNotifier;
+
+impl Notifier for PrintNotifier {
+    fn deliver(&mut self, notification: &Notification) -> Result<(), String> {
+        println!("  [NOTIFY] {:?}:", notification.recipient);
+        for line in notification.body.lines() {
+            println!("    {}", line);
+        }
+        Ok(())
+    }
+}
+
+/// Posts each notification to a webhook as JSON:
+/// `{"recipient": .., "to": .., "body": ..}`, where `recipient` is
+/// `pull_request`, `email`, or `channel`. A chat incoming webhook takes
+/// it as is; a relay routes it to the pull request or the inbox.
+pub struct WebhookNotifier {
+    pub url: String,
+    /// Sent as a bearer token, if set.
+    pub token: Option<String>,
+    pub timeout: Duration,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        WebhookNotifier {
+            url: url.to_string(),
+            token: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn deliver(&mut self, notification: &Notification) -> Result<(), String> {
+        let (kind, to) = match &notification.recipient {
+            Recipient::PullRequest(n) => ("pull_request", n.to_string()),
+            Recipient::Email(address) => ("email", address.clone()),
+            Recipient::Channel(channel) => ("channel", channel.clone()),
+        };
+        let body = format!(
+            "{{\"recipient\":{},\"to\":{},\"body\":{}}}",
+            json_string(kind),
+            json_string(&to),
+            json_string(&notification.body)
+        );
+        let auth = self.token.as_ref().map(|token| format!("Authorization: Bearer {}", token));
+        let args = ["-X", "POST", "-H", "Content-Type: application/json", "-d", &body, &self.url];
+        let headers: Vec<&str> = auth.iter().map(String::as_str).collect();
+        match curl_with_headers(&args, &headers, self.timeout) {
+            Ok((_, 200..=299)) => Ok(()),
+            Ok((_, code)) => Err(format!("{} answered HTTP {}", self.url, code)),
+            Err(SourceError::Transient(why) | SourceError::Permanent(why)) => Err(why),
+        }
+    }
+}
+
+/// A gate's [`Downstream`] and the notifier that reaches it, shared with
+/// every consent the gate mints. Empty until
+/// [`SafetyGate::notify_downstream`] is called.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers(Option<Arc<Mutex<Subscription>>>);
+
+struct Subscription {
+    downstream: Downstream,
+    notifier: Box<dyn Notifier + Send>,
+}
+
+impl Subscribers {
+    /// Tell everyone downstream about `receipt`'s rewrite. A simulated
+    /// one changed nothing they have. Failed deliveries are reported
+    /// here: the operation they follow has already happened.
+    pub(crate) fn rewrote(&self, receipt: &Receipt, updates: &[RefUpdate]) {
+        let Some(shared) = &self.0 else {
+            return;
+        };
+        if receipt.observed {
+            return;
+        }
+        // A poisoned lock still holds the notifier; the rewrite happened.
+        let mut shared = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Subscription { downstream, notifier } = &mut *shared;
+        for (notification, delivered) in notify_downstream(receipt, updates, downstream, notifier.as_mut()) {
+            if let Err(why) = delivered {
+                eprintln!("  [NOTIFY] delivery to {:?} failed: {}", notification.recipient, why);
+            }
+        }
+    }
+}
+
+impl SafetyGate {
+    /// After every force-push and `filter_repo` done under consent this
+    /// gate mints from now on, tell `downstream` through `notifier`.
+    pub fn notify_downstream(&mut self, downstream: Downstream, notifier: Box<dyn Notifier + Send>) {
+        self.log(format!(
+            "DOWNSTREAM: {} pull requests, {} contributors, channel {}",
+            downstream.open_prs.len(),
+            downstream.contributors.len(),
+            downstream.channel.as_deref().unwrap_or("none")
+        ));
+        self.downstream = Subscribers(Some(Arc::new(Mutex::new(Subscription { downstream, notifier }))));
+    }
+}
+
+/// Notify every downstream party of a completed rewrite.
+///
+/// Each message carries the receipt, every old → new SHA, and how to
+/// recover a local clone. Only completed force-pushes and history
+/// rewrites notify: an interrupted operation moved no refs, and nobody
+/// downstream needs to do anything about it.
+///
+/// Returns each notification with its delivery result. A failed
+/// delivery is reported, never retried silently or dropped.
+pub fn notify_downstream(
+    receipt: &Receipt,
+    updates: &[RefUpdate],
+    downstream: &Downstream,
+    notifier: &mut dyn Notifier,
+) -> Vec<(Notification, Result<(), String>)> {
+    let rewrites = matches!(receipt.operation, OperationKind::ForcePush | OperationKind::FilterRepo);
+    if !rewrites || receipt.outcome != Outcome::Completed {
+        return Vec::new();
+    }
+    let body = message(receipt, updates);
+    let recipients = downstream
+        .open_prs
+        .iter()
+        .map(|n| Recipient::PullRequest(*n))
+        .chain(downstream.contributors.iter().map(|e| Recipient::Email(e.clone())))
+        .chain(downstream.channel.iter().map(|c| Recipient::Channel(c.clone())));
+    recipients
+        .map(|recipient| {
+            let notification = Notification {
+                recipient,
+                body: body.clone(),
+            };
+            let result = notifier.deliver(&notification);
+            (notification, result)
+        })
+        .collect()
+}
+
+fn message(receipt: &Receipt, updates: &[RefUpdate]) -> String {
+    let mut out = format!(
+        "History of '{}' was rewritten by an approved {} (started {}).\n\nRefs moved:\n",
+        receipt.repo,
+        receipt.operation.name(),
+        super::report::format_utc(receipt.started_at)
+    );
+    for u in updates {
+        out.push_str(&format!("  {}  {} -> {}\n", u.refname, u.old_sha, u.new_sha));
+    }
+    out.push_str(
+        "\nYour clone still has the old commits. Before pulling:\n  \
+         1. Push or stash any local work.\n  \
+         2. git fetch origin\n  \
+         3. For each local branch: git rebase --onto origin/<base> <old-sha> <branch>\n  \
+         Or re-clone. Do not push the old history back.\n",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notify_downstream, Downstream, Notification, Notifier, Recipient, RefUpdate, WebhookNotifier};
+    use crate::approver::Rehearsal;
+    use crate::backend::{self, Shell};
+    use crate::{OperationDescriptor, OperationKind, Outcome, Repository, RewriteResult, SafetyGate};
+    use std::path::Path;
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Keeps what it is handed.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Notification>>>);
+
+    impl Notifier for Recorder {
+        fn deliver(&mut self, notification: &Notification) -> Result<(), String> {
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    /// Refuses everything.
+    struct Unreachable;
+
+    impl Notifier for Unreachable {
+        fn deliver(&mut self, _notification: &Notification) -> Result<(), String> {
+            Err("relay refused the connection".to_string())
+        }
+    }
+
+    fn downstream() -> Downstream {
+        Downstream {
+            open_prs: vec![42],
+            contributors: vec!["dev@example.com".to_string()],
+            channel: Some("#eng-announce".to_string()),
+        }
+    }
+
+    /// A gate whose consent is real, telling `notifier` about rewrites.
+    fn gate(notifier: Box<dyn Notifier + Send>) -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        gate.notify_downstream(downstream(), notifier);
+        gate
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let out = Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+        assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn a_force_push_tells_everyone_downstream_which_commits_moved() {
+        let root = std::env::temp_dir().join(format!("safe-operations-post-ops-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (remote, work) = (root.join("remote.git"), root.join("work"));
+        std::fs::create_dir_all(&work).unwrap();
+        git(&root, &["init", "-q", "--bare", remote.to_str().unwrap()]);
+        git(&work, &["init", "-q", "-b", "main"]);
+        git(&work, &["config", "user.name", "Test"]);
+        git(&work, &["config", "user.email", "test@example.com"]);
+        git(&work, &["config", "commit.gpgsign", "false"]);
+        git(&work, &["remote", "add", "origin", remote.to_str().unwrap()]);
+        git(&work, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&work, &["push", "-q", "origin", "main"]);
+        let old = git(&work, &["rev-parse", "HEAD"]);
+        git(&work, &["commit", "-q", "--amend", "--allow-empty", "-m", "rewritten"]);
+        let new = git(&work, &["rev-parse", "HEAD"]);
+
+        let recorder = Recorder::default();
+        let mut gate = gate(Box::new(recorder.clone()));
+        let path = work.to_str().unwrap();
+        let repo = Repository::protected_with("work", path, 1, Shell::new());
+        let unprotect = OperationDescriptor::RemoveProtection { repo: path.to_string() };
+        let consent = gate.request_consent_to(&repo, unprotect).unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+        let push = OperationDescriptor::ForcePush { repo: path.to_string(), branch: "main".to_string() };
+        let consent = gate.request_consent_to(&repo, push).unwrap();
+        repo.force_push(consent).unwrap();
+
+        let sent = recorder.0.lock().unwrap().clone();
+        let recipients: Vec<_> = sent.iter().map(|n| n.recipient.clone()).collect();
+        assert_eq!(
+            recipients,
+            [
+                Recipient::PullRequest(42),
+                Recipient::Email("dev@example.com".to_string()),
+                Recipient::Channel("#eng-announce".to_string())
+            ]
+        );
+        assert!(sent[0].body.contains(&format!("refs/heads/main  {} -> {}", old, new)), "{}", sent[0].body);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn a_failed_delivery_is_reported_and_the_rewrite_still_stands() {
+        let mut gate = gate(Box::new(Unreachable));
+        let repo = Repository::protected("repo", "/repos/repo", 10);
+        let unprotect = OperationDescriptor::RemoveProtection { repo: "/repos/repo".to_string() };
+        let consent = gate.request_consent_to(&repo, unprotect).unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+        let rewrite = OperationDescriptor::FilterRepo {
+            repo: "/repos/repo".to_string(),
+            callback: "strip".to_string(),
+        };
+        let consent = gate.request_consent_to(&repo, rewrite).unwrap();
+        let Ok(RewriteResult::Rewritten(filtered, _, receipt)) = repo.filter_repo("strip", consent) else {
+            panic!("the rewrite was approved");
+        };
+        assert_eq!(filtered.rewritten_commits, 10);
+
+        let updates = [RefUpdate::branch("main", Err(Outcome::Failed("gone".to_string())), Ok("abc".to_string()))];
+        let delivered = notify_downstream(&receipt, &updates, &downstream(), &mut Unreachable);
+        assert_eq!(delivered.len(), 3);
+        assert!(delivered.iter().all(|(_, result)| result == &Err("relay refused the connection".to_string())));
+        assert!(delivered[0].0.body.contains("refs/heads/main  (unknown) -> abc"), "{}", delivered[0].0.body);
+    }
+
+    #[test]
+    fn nothing_is_sent_for_a_simulated_or_unfinished_rewrite() {
+        let recorder = Recorder::default();
+        let mut gate = SafetyGate::rehearsal();
+        gate.notify_downstream(downstream(), Box::new(recorder.clone()));
+        let repo = Repository::protected("repo", "/repos/repo", 10);
+        let unprotect = OperationDescriptor::RemoveProtection { repo: "/repos/repo".to_string() };
+        let consent = gate.request_consent_to(&repo, unprotect).unwrap();
+        let repo = repo.remove_protection(consent).map_err(|refused| refused.denied).unwrap();
+        let push = OperationDescriptor::ForcePush { repo: "/repos/repo".to_string(), branch: "main".to_string() };
+        let consent = gate.request_consent_to(&repo, push).unwrap();
+        let options = backend::ExecOptions::default();
+        let receipt = repo.force_push_with(consent, &options, &mut backend::NoProgress).unwrap();
+        assert!(receipt.observed);
+        assert!(recorder.0.lock().unwrap().is_empty());
+
+        let mut timed_out = receipt;
+        timed_out.observed = false;
+        timed_out.outcome = Outcome::TimedOut { processed: 3, total: 10 };
+        assert!(notify_downstream(&timed_out, &[], &downstream(), &mut Recorder::default()).is_empty());
+        timed_out.outcome = Outcome::Completed;
+        timed_out.operation = OperationKind::DeleteTag;
+        assert!(notify_downstream(&timed_out, &[], &downstream(), &mut Recorder::default()).is_empty());
+    }
+
+    #[test]
+    fn a_webhook_nobody_answers_is_an_error() {
+        let mut webhook = WebhookNotifier::new("http://127.0.0.1:9/notify").with_token("t0ken");
+        webhook.timeout = Duration::from_secs(1);
+        let notification = Notification {
+            recipient: Recipient::Channel("#eng".to_string()),
+            body: "history rewritten".to_string(),
+        };
+        assert!(webhook.deliver(&notification).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Structured progress for long-running destructive operations.
+//!
+//! A consented filter-repo can run for an hour, and the approval was given
+//! for what the plan said it would do. A [`Watch`] sits where the backend
+//! reports progress. It turns each unit into a [`ProgressEvent`] for
+//! subscribers such as a dashboard or the approver's terminal. It also
+//! checks every unit against [`Threshold`]s drawn from the approved plan.
+//! An anomaly pauses the operation at the unit boundary until someone
+//! re-confirms. Without a re-confirmation the watch trips the operation's
+//! [`CancelToken`](backend::CancelToken), so the backend stops cleanly and
+//! the receipt records how far it got.
+
+use super::approver::{ApproverProfile, Line, Prompt, Responder};
+use super::backend::{CancelToken, Progress};
+use super::{json_string, OperationKind};
+use std::time::Instant;
+
+/// What the operation is doing, in the approver's terms.
+pub fn phase(operation: OperationKind) -> &'static str {
+    match operation {
+        OperationKind::FilterRepo => "rewriting commits",
+        OperationKind::Rebase => "replaying commits",
+        OperationKind::ForcePush => "transferring objects",
+        OperationKind::DeleteFiles => "deleting files",
+        OperationKind::DeleteBranch | OperationKind::ForceDeleteBranch | OperationKind::DeleteTag => "deleting refs",
+        OperationKind::DeleteByQuery => "deleting documents",
+        OperationKind::PurgeQueue => "purging messages",
+        _ => "running",
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgressEvent {
+    pub operation: OperationKind,
+    pub repo: String,
+    pub phase: &'static str,
+    pub done: usize,
+    pub total: usize,
+    /// The ref, path, or commit the unit touched.
+    pub current: String,
+    pub elapsed_ms: u128,
+    /// Set when this unit tripped a threshold.
+    pub anomaly: Option<String>,
+}
+
+impl ProgressEvent {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":\"progress\",\"operation\":{},\"repo\":{},\"phase\":{},\"done\":{},\"total\":{},\"current\":{},\"elapsed_ms\":{},\"anomaly\":{}}}",
+            json_string(self.operation.name()),
+            json_string(&self.repo),
+            json_string(self.phase),
+            self.done,
+            self.total,
+            json_string(&self.current),
+            self.elapsed_ms,
+            self.anomaly.as_deref().map(json_string).unwrap_or_else(|| "null".to_string())
+        )
+    }
+}
+
+/// Receives every event a watch emits.
+pub trait Subscriber {
+    fn on_event(&mut self, event: &ProgressEvent);
+}
+
+/// Writes each event as one JSON line on stdout.
+pub struct JsonLines;
+
+impl Subscriber for JsonLines {
+    fn on_event(&mut self, event: &ProgressEvent) {
+        println!("{}", event.to_json());
+    }
+}
+
+/// A bound on what the approved operation should do.
+#[derive(Clone, Debug)]
+pub enum Threshold {
+    /// More units than the plan counted.
+    BeyondPlan(usize),
+    /// A unit touching something outside these prefixes.
+    OutsideScope(Vec<String>),
+}
+
+impl Threshold {
+    fn check(&self, done: usize, current: &str) -> Option<String> {
+        match self {
+            Threshold::BeyondPlan(approved) if done > *approved => {
+                Some(format!("{} units processed; the plan approved {}", done, approved))
+            }
+            Threshold::OutsideScope(prefixes) if !prefixes.iter().any(|p| current.starts_with(p.as_str())) => {
+                Some(format!("'{}' is outside the approved scope [{}]", current, prefixes.join(", ")))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Progress reporting with thresholds, for one running operation.
+pub struct Watch {
+    operation: OperationKind,
+    repo: String,
+    started: Instant,
+    cancel: CancelToken,
+    subscribers: Vec<Box<dyn Subscriber>>,
+    thresholds: Vec<Threshold>,
+    reconfirm: Option<Box<dyn Responder>>,
+    profile: ApproverProfile,
+    anomalies: Vec<String>,
+}
+
+impl Watch {
+    /// Watch `operation` on `repo`. `cancel` must be the token in the
+    /// operation's [`ExecOptions`](super::backend::ExecOptions).
+    pub fn new(operation: OperationKind, repo: &str, cancel: CancelToken) -> Self {
+        Watch {
+            operation,
+            repo: repo.to_string(),
+            started: Instant::now(),
+            cancel,
+            subscribers: Vec::new(),
+            thresholds: Vec::new(),
+            reconfirm: None,
+            profile: ApproverProfile::default(),
+            anomalies: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(mut self, subscriber: Box<dyn Subscriber>) -> Self {
+        self.subscribers.push(subscriber);
+        self
+    }
+
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// Who is asked to re-confirm after an anomaly. Without one, every
+    /// anomaly stops the operation.
+    pub fn reconfirm_with(mut self, responder: Box<dyn Responder>) -> Self {
+        self.reconfirm = Some(responder);
+        self
+    }
+
+    /// Every anomaly seen, in order.
+    pub fn anomalies(&self) -> &[String] {
+        &self.anomalies
+    }
+
+    /// The phrase that resumes a paused operation.
+    pub fn resume_phrase(&self) -> String {
+        format!("continue {} on {}", self.operation.name(), self.repo)
+    }
+
+    /// Pause on `anomaly` and ask for re-confirmation. A threshold that
+    /// is re-confirmed is disarmed, so it does not ask again every unit.
+    fn pause(&mut self, index: usize, anomaly: &str) {
+        let phrase = self.resume_phrase();
+        println!("{}", self.profile.render(&Line::Warning(format!("paused {}: {}", self.operation.name(), anomaly))));
+        println!("{}", self.profile.render(&Line::Challenge(phrase.clone())));
+        let typed = self.reconfirm.as_mut().and_then(|r| r.answer(&Prompt::phrase(&phrase)));
+        if typed.as_deref().map(str::trim) == Some(phrase.as_str()) {
+            self.thresholds.remove(index);
+        } else {
+            println!("{}", self.profile.render(&Line::Notice(format!("stopping {}: not re-confirmed", self.operation.name()))));
+            self.cancel.cancel();
+        }
+    }
+}
+
+impl Progress for Watch {
+    fn on_progress(&mut self, done: usize, total: usize, current: &str) {
+        let tripped = self
+            .thresholds
+            .iter()
+            .enumerate()
+            .find_map(|(i, t)| t.check(done, current).map(|why| (i, why)));
+        let event = ProgressEvent {
+            operation: self.operation,
+            repo: self.repo.clone(),
+            phase: phase(self.operation),
+            done,
+            total,
+            current: current.to_string(),
+            elapsed_ms: self.started.elapsed().as_millis(),
+            anomaly: tripped.as_ref().map(|(_, why)| why.clone()),
+        };
+        for subscriber in &mut self.subscribers {
+            subscriber.on_event(&event);
+        }
+        if let Some((index, why)) = tripped {
+            self.anomalies.push(why.clone());
+            self.pause(index, &why);
+        }
+    }
+}
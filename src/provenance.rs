@@ -0,0 +1,283 @@
+//! The provenance chain of one operation.
+//!
+//! Reconstructing the incident meant stitching together what the agent
+//! said it was doing, what it showed the user, what the user approved,
+//! what ran, and whether the remote ended up as claimed — from five places,
+//! by hand. Here each is a link in one chain, keyed by the [`OperationId`]
+//! the agent is handed when it declares its intent. While the operation is
+//! open, the gate appends the plan it presents and every consent decision
+//! it makes; the host appends each receipt, with its read-back result.
+//!
+//! A link that was never recorded is reported as missing. "No plan was
+//! announced" was the first finding of the incident review.
+
+use super::readback::Verification;
+use super::report::format_utc;
+use super::{ed25519, unix_now, ConsentDenied, OperationKind, Receipt, SafetyGate, UserConsent};
+use core::fmt;
+
+/// Names one operation across every record of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OperationId(u64);
+
+impl OperationId {
+    pub fn to_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+
+    /// Parse `op-<16 hex digits>` or the bare digits.
+    pub fn parse(text: &str) -> Option<Self> {
+        let hex = text.strip_prefix("op-").unwrap_or(text);
+        if hex.len() != 16 {
+            return None;
+        }
+        u64::from_str_radix(hex, 16).ok().map(OperationId)
+    }
+}
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "op-{}", self.to_hex())
+    }
+}
+
+/// One record in an operation's history.
+#[derive(Clone, Debug)]
+pub enum Link {
+    /// What the agent said it was about to do, in its words.
+    Intent(String),
+    /// The destruction plan as it was shown to the approver.
+    Plan(String),
+    /// Consent granted, and the signature that proves it.
+    Granted {
+        operation: OperationKind,
+        description: String,
+        reason: Option<String>,
+        signature: String,
+        expires_at: u64,
+    },
+    /// Consent refused, and why.
+    Denied { operation: OperationKind, reason: String },
+    /// What ran, from its receipt.
+    Executed(Receipt),
+    /// Whether the remote matched the intended state afterwards.
+    Verified(Verification),
+}
+
+impl Link {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Link::Intent(_) => "intent",
+            Link::Plan(_) => "plan",
+            Link::Granted { .. } | Link::Denied { .. } => "consent",
+            Link::Executed(_) => "execution",
+            Link::Verified(_) => "verification",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Link::Intent(intent) => intent.clone(),
+            Link::Plan(plan) => plan.clone(),
+            Link::Granted {
+                operation,
+                description,
+                reason,
+                signature,
+                expires_at,
+            } => {
+                let mut out = format!("granted {}: {}", operation.name(), description);
+                if let Some(reason) = reason {
+                    out.push_str(&format!(" (reason: {})", reason));
+                }
+                out.push_str(&format!(
+                    "; signature {}..., expires {}",
+                    &signature[..signature.len().min(16)],
+                    format_utc(*expires_at)
+                ));
+                out
+            }
+            Link::Denied { operation, reason } => format!("denied {}: {}", operation.name(), reason),
+            Link::Executed(receipt) => {
+                let mut out = format!(
+                    "{} on '{}': {} in {}ms",
+                    receipt.operation.name(),
+                    receipt.repo,
+                    receipt.outcome.label(),
+                    receipt.elapsed.as_millis()
+                );
+                if receipt.retries > 0 {
+                    out.push_str(&format!(", {} retries coalesced", receipt.retries));
+                }
+                if receipt.observed {
+                    out.push_str(", simulated");
+                }
+                out
+            }
+            Link::Verified(verification) => verification.to_string(),
+        }
+    }
+}
+
+/// A link and when it was recorded.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub at: u64,
+    pub link: Link,
+}
+
+/// Everything recorded about one operation, oldest first.
+#[derive(Clone, Debug)]
+pub struct Chain {
+    pub id: OperationId,
+    pub steps: Vec<Step>,
+}
+
+impl Chain {
+    pub fn intent(&self) -> Option<&str> {
+        self.steps.iter().find_map(|s| match &s.link {
+            Link::Intent(intent) => Some(intent.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The links this operation never recorded, in chain order. An
+    /// operation that was denied is not missing its execution.
+    pub fn gaps(&self) -> Vec<&'static str> {
+        let has = |label: &str| self.steps.iter().any(|s| s.link.label() == label);
+        let granted = self.steps.iter().any(|s| matches!(s.link, Link::Granted { .. }));
+        let mut gaps = Vec::new();
+        for label in ["intent", "plan", "consent"] {
+            if !has(label) {
+                gaps.push(label);
+            }
+        }
+        if granted {
+            for label in ["execution", "verification"] {
+                if !has(label) {
+                    gaps.push(label);
+                }
+            }
+        }
+        gaps
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "### {}: {}\n\n| Time (UTC) | Link | Record |\n|---|---|---|\n",
+            self.id,
+            self.intent().unwrap_or("no intent declared")
+        );
+        for step in &self.steps {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                format_utc(step.at),
+                step.link.label(),
+                step.link.describe().lines().map(str::trim).collect::<Vec<_>>().join("<br>").replace('|', "\\|")
+            ));
+        }
+        let gaps = self.gaps();
+        if !gaps.is_empty() {
+            out.push_str(&format!("\nNever recorded: {}.\n", gaps.join(", ")));
+        }
+        out
+    }
+}
+
+/// Every chain the gate has recorded, and the operation it is
+/// currently recording into.
+#[derive(Default)]
+pub struct Provenance {
+    chains: Vec<Chain>,
+    open: Option<OperationId>,
+}
+
+impl Provenance {
+    /// The complete chain for `id`, if it was ever declared.
+    pub fn for_operation(&self, id: OperationId) -> Option<&Chain> {
+        self.chains.iter().find(|c| c.id == id)
+    }
+
+    pub fn chains(&self) -> &[Chain] {
+        &self.chains
+    }
+
+    /// The operation the gate's plans and decisions are linked to.
+    pub fn open(&self) -> Option<OperationId> {
+        self.open
+    }
+
+    fn append(&mut self, id: OperationId, link: Link) -> bool {
+        match self.chains.iter_mut().find(|c| c.id == id) {
+            Some(chain) => {
+                chain.steps.push(Step { at: unix_now(), link });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Link to the open operation. Nothing is recorded if none is open.
+    pub(crate) fn note(&mut self, link: Link) {
+        if let Some(id) = self.open {
+            self.append(id, link);
+        }
+    }
+
+    pub(crate) fn note_grant<Scope>(&mut self, consent: &UserConsent<Scope>, reason: Option<&str>) {
+        self.note(Link::Granted {
+            operation: consent._kind,
+            description: consent._operation.clone(),
+            reason: reason.map(str::to_string),
+            signature: ed25519::hex(&consent._signature.0),
+            expires_at: consent._expires_at,
+        });
+    }
+
+    pub(crate) fn note_denial(&mut self, denied: &ConsentDenied) {
+        self.note(Link::Denied {
+            operation: denied.operation,
+            reason: denied.reason.clone(),
+        });
+    }
+}
+
+impl SafetyGate {
+    /// Open a provenance chain with what the agent says it is about to
+    /// do. Plans presented and consent decided from now on are linked
+    /// to it, until [`end_operation`](Self::end_operation) or the next
+    /// declaration.
+    pub fn declare_intent(&mut self, intent: &str) -> OperationId {
+        let id = OperationId(super::random_u64());
+        self.provenance.chains.push(Chain {
+            id,
+            steps: vec![Step {
+                at: unix_now(),
+                link: Link::Intent(intent.to_string()),
+            }],
+        });
+        self.provenance.open = Some(id);
+        id
+    }
+
+    /// Stop linking plans and decisions to the open operation. Receipts
+    /// can still be recorded against it.
+    pub fn end_operation(&mut self) {
+        self.provenance.open = None;
+    }
+
+    /// Link a receipt, and its read-back result if it has one, to `id`.
+    pub fn record_receipt(&mut self, id: OperationId, receipt: &Receipt) -> Result<(), String> {
+        if !self.provenance.append(id, Link::Executed(receipt.clone())) {
+            return Err(format!("no operation {} was declared", id));
+        }
+        if let Some(verification) = &receipt.verification {
+            self.provenance.append(id, Link::Verified(verification.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+}
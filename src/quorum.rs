@@ -0,0 +1,432 @@
+//! Quorum consent.
+//!
+//! One approver can be tired, rushed, or talked into it. For operations
+//! that are bad enough at scale — rewriting the history of a repository
+//! with hundreds of commits — a [`QuorumGate`] names the humans who may
+//! approve and how many of them must. Each is shown their own challenge
+//! through their own [`Responder`], and consent is minted only once the
+//! threshold is met.
+//!
+//! Once a gate requires quorum for an operation, single-party consent for
+//! it is refused: through `request_consent_for`, a plan, or a remote
+//! channel alike. A request whose repository size the gate cannot see is
+//! held to the quorum; an unknown size is not a small one.
+
+use super::approver::{Line, Prompt, Responder};
+use super::backend::GitBackend;
+use super::{policy, random_u64, ConsentDenied, OperationKind, Repository, SafetyGate, Severity, UserConsent};
+
+/// Who approved one round of asking, whose identity was verified, and
+/// who said no.
+struct Tally {
+    approved: Vec<String>,
+    verified: Vec<String>,
+    refused: Vec<String>,
+}
+
+/// One human who may approve, and how their answer reaches the gate.
+pub struct Approver {
+    pub name: String,
+    responder: Box<dyn Responder>,
+}
+
+/// `required` approvals for `operation` on repositories with at least
+/// `min_commits` commits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub operation: OperationKind,
+    pub min_commits: usize,
+    pub required: usize,
+}
+
+/// The configured approvers and the operations that need several of them.
+#[derive(Default)]
+pub struct QuorumGate {
+    approvers: Vec<Approver>,
+    rules: Vec<Rule>,
+}
+
+impl QuorumGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn approver(mut self, name: &str, responder: Box<dyn Responder>) -> Self {
+        self.approvers.push(Approver {
+            name: name.to_string(),
+            responder,
+        });
+        self
+    }
+
+    /// Require `required` approvals for `operation` on repositories
+    /// with at least `min_commits` commits.
+    pub fn demand(mut self, operation: OperationKind, min_commits: usize, required: usize) -> Self {
+        self.rules.push(Rule {
+            operation,
+            min_commits,
+            required,
+        });
+        self
+    }
+
+    /// The rule that applies to `operation` on a repository of
+    /// `commits` commits. With the size unknown, any rule for the
+    /// operation applies.
+    pub fn rule_for(&self, operation: OperationKind, commits: Option<usize>) -> Option<Rule> {
+        self.rules
+            .iter()
+            .filter(|r| r.operation == operation && commits.is_none_or(|n| n >= r.min_commits))
+            .max_by_key(|r| r.required)
+            .copied()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, approver) in self.approvers.iter().enumerate() {
+            if self.approvers[..i].iter().any(|a| a.name == approver.name) {
+                return Err(format!("approver '{}' is listed twice", approver.name));
+            }
+        }
+        for rule in &self.rules {
+            if rule.required < 2 {
+                return Err(format!("{}: a quorum of {} is single-party consent", rule.operation.name(), rule.required));
+            }
+            if rule.required > self.approvers.len() {
+                return Err(format!(
+                    "{}: {} approvals required, {} approvers configured",
+                    rule.operation.name(),
+                    rule.required,
+                    self.approvers.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SafetyGate {
+    /// Require quorum as `quorum` configures it, from now on. A quorum
+    /// that could never be met is rejected, and the gate keeps the one
+    /// it had.
+    pub fn require_quorum(&mut self, quorum: QuorumGate) -> Result<(), String> {
+        if let Err(why) = quorum.validate() {
+            self.log(format!("QUORUM REJECTED: {}", why));
+            return Err(why);
+        }
+        let names: Vec<&str> = quorum.approvers.iter().map(|a| a.name.as_str()).collect();
+        for rule in &quorum.rules {
+            let event = format!(
+                "QUORUM SET: {} on repositories of {}+ commits needs {} of [{}]",
+                rule.operation.name(),
+                rule.min_commits,
+                rule.required,
+                names.join(", ")
+            );
+            self.log(event);
+        }
+        self.quorum = Some(quorum);
+        Ok(())
+    }
+
+    /// The quorum rule `operation` is held to, if any: the stricter of
+    /// the configured rule and the one its tier calls for.
+    pub(crate) fn quorum_rule(&self, operation: OperationKind, commits: Option<usize>) -> Option<Rule> {
+        let configured = self.quorum.as_ref().and_then(|q| q.rule_for(operation, commits));
+        let tiered = self.tiers.as_ref().and_then(|t| t.rule_for(operation, commits));
+        [configured, tiered].into_iter().flatten().max_by_key(|r| r.required)
+    }
+
+    /// How many approvers the quorum has.
+    pub(crate) fn quorum_approvers(&self) -> usize {
+        self.quorum.as_ref().map_or(0, |q| q.approvers.len())
+    }
+
+    /// Put the request to every configured approver in turn, each with
+    /// their own challenge, and mint consent bound to `repo` once enough
+    /// of them answer it. Stops asking as soon as the outcome is
+    /// settled either way. Requests no quorum rule covers get ordinary
+    /// single-party consent.
+    ///
+    /// With [`persist_pending`](SafetyGate::persist_pending) on, each
+    /// approval is saved as it is given. Asked again after a restart,
+    /// the gate only asks the approvers who have not yet answered yes.
+    pub fn request_quorum_consent<S, B: GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<UserConsent, ConsentDenied> {
+        let commits = repo.commit_count();
+        let Some(rule) = self.quorum_rule(operation, commits) else {
+            return self.request_consent_for(repo, &[], operation, operation_description);
+        };
+        self.screen_forbidden(operation, Some(&repo.name), operation_description)?;
+        let outside_manifest = self.manifest.as_ref().is_some_and(|(_, permitted)| !permitted.contains(&operation));
+        self.target = Some(repo.path.clone());
+        self.target_commits = commits;
+        if outside_manifest || self.policy.decide(operation) != policy::Decision::RequireConsent {
+            return self.mint(operation, operation_description);
+        }
+        if let Err(denied) = self.screen_throttle(operation, operation_description) {
+            self.target = None;
+            self.target_commits = None;
+            return Err(denied);
+        }
+        let (severity, _) = self.severity_for(operation, Some(&repo.path), commits);
+        self.show_impact(repo, operation, None);
+        self.quorum_consent(rule, severity, operation, &repo.path, operation_description)
+    }
+
+    /// Put `operation` on `path` to the quorum's approvers under `rule`,
+    /// and mint consent once enough of them answer. The caller has
+    /// screened the request and set the gate's target.
+    pub(crate) fn quorum_consent<Scope>(
+        &mut self,
+        rule: Rule,
+        severity: Severity,
+        operation: OperationKind,
+        path: &str,
+        operation_description: &str,
+    ) -> Result<UserConsent<Scope>, ConsentDenied> {
+        // The approvers are lent out for the round and put back on every
+        // path: a gate that lost them would stop requiring quorum.
+        let mut approvers = self.quorum.as_mut().map(|q| std::mem::take(&mut q.approvers)).unwrap_or_default();
+        let tally = self.collect_approvals(&mut approvers, rule, severity, operation, path, operation_description);
+        if let Some(quorum) = &mut self.quorum {
+            quorum.approvers = approvers;
+        }
+        let Tally {
+            approved,
+            verified,
+            refused,
+        } = tally;
+        self.settle_quorum_tally(operation, path, operation_description);
+        if approved.len() < rule.required {
+            self.target = None;
+            self.target_commits = None;
+            self.descriptor = None;
+            self.log(format!("DENIED (quorum not met): {}", operation_description));
+            let denied = ConsentDenied::new(
+                operation,
+                format!(
+                    "{} of {} required approvals{}",
+                    approved.len(),
+                    rule.required,
+                    if approved.is_empty() { String::new() } else { format!(" ({})", approved.join(", ")) }
+                ),
+            );
+            // Refused by the approvers who said no, if any did.
+            let denied = match refused.is_empty() {
+                true => denied,
+                false => denied.by_approver(&refused.join(", ")),
+            };
+            self.notify_denial_for(&denied, Some(path.to_string()));
+            return Err(denied);
+        }
+        self.answered_by = Some(format!("quorum of {} ({})", approved.len(), approved.join(", ")));
+        self.quorum_met = true;
+        self.verified = verified;
+        self.mint(operation, operation_description)
+    }
+
+    /// Ask `approvers` in turn until `rule` is met or cannot be, counting
+    /// approvals saved before a restart.
+    fn collect_approvals(
+        &mut self,
+        approvers: &mut [Approver],
+        rule: Rule,
+        severity: Severity,
+        operation: OperationKind,
+        path: &str,
+        operation_description: &str,
+    ) -> Tally {
+        let total = approvers.len();
+        // Approvals given before a restart still count, from approvers
+        // still configured.
+        let mut approved = self.quorum_tally(operation, path, operation_description);
+        approved.retain(|name| approvers.iter().any(|a| a.name == *name));
+        // With an identity provider, an approval counts only while the
+        // approver still resolves and holds a required group.
+        let mut verified = Vec::new();
+        for name in std::mem::take(&mut approved) {
+            match self.verify_approver(Some(&name)) {
+                Ok(identity) => {
+                    verified.extend(identity.map(|i| i.verified_subject()));
+                    approved.push(name);
+                }
+                Err(why) => self.log(format!("QUORUM APPROVAL DROPPED: {} ({})", name, why)),
+            }
+        }
+        if !approved.is_empty() {
+            self.log(format!("QUORUM RESUMED: {} already approved {}", approved.join(", "), operation_description));
+        }
+        let mut refused = Vec::new();
+        for i in 0..total {
+            let left = approvers[i..].iter().filter(|a| !approved.contains(&a.name)).count();
+            if approved.len() >= rule.required || approved.len() + left < rule.required {
+                break;
+            }
+            let approver = &mut approvers[i];
+            if approved.contains(&approver.name) {
+                continue;
+            }
+            let identity = match self.verify_approver(Some(&approver.name)) {
+                Ok(identity) => identity,
+                Err(why) => {
+                    self.log(format!("QUORUM NO APPROVAL: {} for {} ({})", approver.name, operation_description, why));
+                    continue;
+                }
+            };
+            let challenge = format!("{:08x}", random_u64() as u32);
+            self.show_line(Line::Notice(format!(
+                "{} severity, quorum {} of {}: {} (approver: {})",
+                severity.label(),
+                rule.required,
+                total,
+                operation_description,
+                approver.name
+            )));
+            self.show_line(Line::Challenge(challenge.clone()));
+            self.show_line(Line::Detail("To refuse, type: no: <why>".to_string()));
+            let typed = approver.responder.answer(&Prompt::phrase(&challenge));
+            self.capture(&format!("> {}", typed.as_deref().unwrap_or("").trim_end()));
+            if typed.as_deref().map(str::trim) == Some(challenge.as_str()) {
+                self.log(format!("QUORUM APPROVAL: {} for {}", approver.name, operation_description));
+                approved.push(approver.name.clone());
+                verified.extend(identity.map(|i| i.verified_subject()));
+                self.note_quorum_tally(operation, path, operation_description, &approved);
+            } else if let Some(why) = typed.as_deref().and_then(super::approver::refusal) {
+                self.log(format!("QUORUM REFUSAL: {} for {} ({})", approver.name, operation_description, why));
+                refused.push(approver.name.clone());
+            } else {
+                self.log(format!("QUORUM NO APPROVAL: {} for {}", approver.name, operation_description));
+            }
+        }
+        Tally {
+            approved,
+            verified,
+            refused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuorumGate;
+    use crate::approver::{Prompt, Rehearsal, Responder};
+    use crate::backend::Shell;
+    use crate::{OperationKind, Repository, SafetyGate};
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    /// The gate's own approver, who never answers: single-party consent
+    /// cannot be granted.
+    struct Silent;
+
+    impl Responder for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+            None
+        }
+    }
+
+    fn gate() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Silent));
+        let quorum = QuorumGate::new()
+            .approver("alice", Box::new(Rehearsal))
+            .approver("bob", Box::new(Rehearsal))
+            .demand(OperationKind::FilterRepo, 0, 2);
+        gate.require_quorum(quorum).unwrap();
+        gate
+    }
+
+    #[test]
+    fn quorum_holds_for_every_request_not_just_the_first() {
+        let mut gate = gate();
+        let repo = Repository::protected("repo", "/repos/repo", 500);
+        for _ in 0..2 {
+            let consent = gate.request_quorum_consent(&repo, OperationKind::FilterRepo, "filter-repo");
+            assert!(consent.is_ok(), "{:?}", consent.err());
+            assert_eq!(gate.quorum_approvers(), 2);
+        }
+    }
+
+    /// A repository at `dir` with `commits` commits on `main`.
+    fn history(dir: &Path, commits: usize) {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        let git = |args: &[&str]| Command::new("git").arg("-C").arg(dir).args(args).stdin(Stdio::piped()).spawn().unwrap();
+        assert!(git(&["init", "-q", "-b", "main"]).wait().unwrap().success());
+        let mut stream = String::new();
+        for i in 1..=commits {
+            stream.push_str(&format!("commit refs/heads/main\nmark :{}\ncommitter T <t@example.com> {} +0000\ndata 1\nx\n", i, i));
+            if i > 1 {
+                stream.push_str(&format!("from :{}\n", i - 1));
+            }
+        }
+        let mut import = git(&["fast-import", "--quiet"]);
+        import.stdin.take().unwrap().write_all(stream.as_bytes()).unwrap();
+        assert!(import.wait().unwrap().success());
+    }
+
+    #[test]
+    fn a_large_history_is_counted_by_the_backend_not_the_caller() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-large-history-{}", std::process::id()));
+        history(&dir, 101);
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        let quorum = QuorumGate::new()
+            .approver("alice", Box::new(Silent))
+            .approver("bob", Box::new(Silent))
+            .demand(OperationKind::FilterRepo, 101, 2);
+        gate.require_quorum(quorum).unwrap();
+        // Opened as if it were empty: the count the gate acts on is git's.
+        let repo = Repository::protected_with("large", dir.to_str().unwrap(), 0, Shell::new());
+        assert_eq!(repo.commit_count(), Some(101));
+        let denied = gate
+            .request_consent_for(&repo, &[], OperationKind::FilterRepo, "filter-repo")
+            .err()
+            .expect("101 commits need a quorum");
+        assert!(denied.reason.contains("2 approvers"), "{}", denied.reason);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_quorum_request_is_sized_by_the_backend_too() {
+        let dir = std::env::temp_dir().join(format!("safe-operations-large-quorum-{}", std::process::id()));
+        history(&dir, 101);
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        let quorum = QuorumGate::new()
+            .approver("alice", Box::new(Rehearsal))
+            .approver("bob", Box::new(Silent))
+            .demand(OperationKind::FilterRepo, 101, 2);
+        gate.require_quorum(quorum).unwrap();
+        let repo = Repository::protected_with("large", dir.to_str().unwrap(), 0, Shell::new());
+        // Bob never answers: single-party consent would have gone through
+        // had the gate believed the zero the repository was opened with.
+        let denied = gate
+            .request_quorum_consent(&repo, OperationKind::FilterRepo, "filter-repo")
+            .err()
+            .expect("bob did not approve");
+        assert!(denied.reason.contains("1 of 2 required approvals (alice)"), "{}", denied.reason);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn single_party_consent_stays_refused_after_a_quorum_request() {
+        let mut gate = gate();
+        let repo = Repository::protected("repo", "/repos/repo", 500);
+        gate.request_quorum_consent(&repo, OperationKind::FilterRepo, "filter-repo").unwrap();
+        let denied = gate
+            .request_consent_for(&repo, &[], OperationKind::FilterRepo, "filter-repo")
+            .err()
+            .expect("quorum still required");
+        assert!(denied.reason.contains("approvers"), "{}", denied.reason);
+    }
+}
@@ -0,0 +1,161 @@
+//! Read-after-write verification of remote state.
+//!
+//! A force-push that "succeeded" may have been rejected by a hook on one
+//! ref, raced by another pusher, or landed on the wrong remote. After a
+//! consented operation, the intended end state is read back from the remote
+//! and compared; the result goes on the receipt, and a mismatch raises an
+//! alert instead of waiting for someone to notice.
+
+use super::post_ops::{Notification, Notifier, Recipient};
+use super::Receipt;
+use std::process::Command;
+
+/// One fact about the remote the operation was supposed to establish.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expectation {
+    RefAt { refname: String, sha: String },
+    RefAbsent(String),
+    Protection { branch: String, enabled: bool },
+}
+
+/// Reads the remote's current state. `None` means it could not be read.
+pub trait RemoteState {
+    fn ref_target(&self, refname: &str) -> Option<Option<String>>;
+    fn protection_enabled(&self, branch: &str) -> Option<bool>;
+}
+
+/// Ref state from `git ls-remote`. Branch protection lives in the
+/// platform's API, not in git, so it is reported unreadable here.
+pub struct LsRemote {
+    pub url: String,
+}
+
+impl RemoteState for LsRemote {
+    fn ref_target(&self, refname: &str) -> Option<Option<String>> {
+        let out = Command::new("git").args(["ls-remote", &self.url, refname]).output().ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&out.stdout);
+        Some(
+            text.lines()
+                .find(|l| l.ends_with(&format!("\t{}", refname)))
+                .and_then(|l| l.split('\t').next())
+                .map(str::to_string),
+        )
+    }
+
+    fn protection_enabled(&self, _branch: &str) -> Option<bool> {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// Every expectation held.
+    Verified,
+    /// The remote disagrees with what was intended.
+    Mismatch(Vec<String>),
+    /// Some state could not be read; nothing disagreed.
+    Unverifiable(Vec<String>),
+}
+
+impl std::fmt::Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verification::Verified => write!(f, "verified"),
+            Verification::Mismatch(why) => write!(f, "mismatch: {}", why.join("; ")),
+            Verification::Unverifiable(why) => write!(f, "unverifiable: {}", why.join("; ")),
+        }
+    }
+}
+
+impl std::str::FromStr for Verification {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let split = |rest: &str| rest.split("; ").map(str::to_string).collect();
+        if s == "verified" {
+            Ok(Verification::Verified)
+        } else if let Some(rest) = s.strip_prefix("mismatch: ") {
+            Ok(Verification::Mismatch(split(rest)))
+        } else if let Some(rest) = s.strip_prefix("unverifiable: ") {
+            Ok(Verification::Unverifiable(split(rest)))
+        } else {
+            Err(format!("unknown verification `{}`", s))
+        }
+    }
+}
+
+/// Read back every expectation. A mismatch outranks an unreadable value.
+pub fn verify(remote: &dyn RemoteState, expected: &[Expectation]) -> Verification {
+    let mut mismatches = Vec::new();
+    let mut unreadable = Vec::new();
+    for expectation in expected {
+        match expectation {
+            Expectation::RefAt { refname, sha } => match remote.ref_target(refname) {
+                None => unreadable.push(format!("{} unreadable", refname)),
+                Some(Some(actual)) if actual == *sha => {}
+                Some(actual) => mismatches.push(format!(
+                    "{} is {}, expected {}",
+                    refname,
+                    actual.as_deref().unwrap_or("absent"),
+                    sha
+                )),
+            },
+            Expectation::RefAbsent(refname) => match remote.ref_target(refname) {
+                None => unreadable.push(format!("{} unreadable", refname)),
+                Some(None) => {}
+                Some(Some(actual)) => mismatches.push(format!("{} still exists at {}", refname, actual)),
+            },
+            Expectation::Protection { branch, enabled } => match remote.protection_enabled(branch) {
+                None => unreadable.push(format!("protection on {} unreadable", branch)),
+                Some(actual) if actual == *enabled => {}
+                Some(actual) => mismatches.push(format!(
+                    "protection on {} is {}, expected {}",
+                    branch,
+                    if actual { "on" } else { "off" },
+                    if *enabled { "on" } else { "off" }
+                )),
+            },
+        }
+    }
+    if !mismatches.is_empty() {
+        Verification::Mismatch(mismatches)
+    } else if !unreadable.is_empty() {
+        Verification::Unverifiable(unreadable)
+    } else {
+        Verification::Verified
+    }
+}
+
+impl Receipt {
+    /// Verify against `remote`, record the result, and alert
+    /// `on_mismatch` if the remote disagrees.
+    pub fn verified_against(
+        mut self,
+        remote: &dyn RemoteState,
+        expected: &[Expectation],
+        notifier: &mut dyn Notifier,
+        on_mismatch: &Recipient,
+    ) -> Self {
+        let verification = verify(remote, expected);
+        if let Verification::Mismatch(why) = &verification {
+            let alert = Notification {
+                recipient: on_mismatch.clone(),
+                body: format!(
+                    "ALERT: {} on '{}' reported {}, but the remote disagrees:\n  {}",
+                    self.operation.name(),
+                    self.repo,
+                    self.outcome.label(),
+                    why.join("\n  ")
+                ),
+            };
+            if let Err(err) = notifier.deliver(&alert) {
+                eprintln!("  [READBACK] alert delivery failed: {}", err);
+            }
+        }
+        self.verification = Some(verification);
+        self
+    }
+}
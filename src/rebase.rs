@@ -0,0 +1,93 @@
+//! Rebase — a half-finished rewrite is a state of its own.
+
+use crate::{backend, OperationDescriptor, Protected, RebaseInProgress, Refused, Repository, Unprotected, UserConsent};
+
+impl<S, B> Repository<S, B> {
+    fn rebase_onto(self, onto: &str, consent: UserConsent) -> Result<Repository<RebaseInProgress<S>, B>, Refused<Self>> {
+        let expected = OperationDescriptor::Rebase {
+            repo: self.path.clone(),
+            onto: onto.to_string(),
+        };
+        let repo = self.refuse_unless(&expected, &consent)?;
+        consent.announce(&format!("Rebase of '{}' onto '{}' started with user approval.", repo.name, onto));
+        Ok(repo.into_state())
+    }
+}
+
+impl<B: backend::GitBackend> Repository<Protected, B> {
+    /// Start rebasing the checked-out branch onto `onto`. Requires consent:
+    /// a rebase rewrites history. Consumes the repository; until the rebase
+    /// is continued or aborted, there is no `Repository<Protected>`, and so
+    /// nothing else to do to it.
+    pub fn start_rebase(
+        self,
+        onto: &str,
+        consent: UserConsent,
+    ) -> Result<Repository<RebaseInProgress<Protected>, B>, Refused<Self>> {
+        self.rebase_onto(onto, consent)
+    }
+}
+
+impl<B: backend::GitBackend> Repository<Unprotected, B> {
+    /// [`start_rebase`](Repository::start_rebase), on an unprotected
+    /// repository. It is still unprotected when the rebase is over.
+    pub fn start_rebase(
+        self,
+        onto: &str,
+        consent: UserConsent,
+    ) -> Result<Repository<RebaseInProgress<Unprotected>, B>, Refused<Self>> {
+        self.rebase_onto(onto, consent)
+    }
+}
+
+impl<From, B> Repository<RebaseInProgress<From>, B> {
+    /// Record a file the rebase stopped on with a conflict.
+    pub fn with_conflict(mut self, path: &str) -> Self {
+        self.conflicts.push(path.to_string());
+        self
+    }
+
+    pub fn status(&self) -> String {
+        format!("{}: rebase in progress, {} conflicted file(s)", self.name, self.conflicts.len())
+    }
+
+    /// The files still in conflict.
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    /// Mark `path` resolved, as `git add` does. False if it was not in
+    /// conflict.
+    pub fn mark_resolved(&mut self, path: &str) -> bool {
+        let before = self.conflicts.len();
+        self.conflicts.retain(|c| c != path);
+        self.conflicts.len() < before
+    }
+
+    /// Finish the rebase. Refused, with the repository handed back, while
+    /// any file is still in conflict.
+    pub fn continue_rebase(self) -> Result<Repository<From, B>, Box<Self>> {
+        if !self.conflicts.is_empty() {
+            return Err(Box::new(self));
+        }
+        println!("  [OK] Rebase of '{}' completed.", self.name);
+        Ok(self.into_state())
+    }
+
+    /// Give up on the rebase. The branch is back where it started, and any
+    /// conflicts go with the half-replayed commits.
+    pub fn abort_rebase(mut self) -> Repository<From, B> {
+        self.conflicts.clear();
+        println!("  [OK] Rebase of '{}' aborted; the branch is back where it started.", self.name);
+        self.into_state()
+    }
+
+    // -----------------------------------------------------------------------
+    // What the agent CANNOT do mid-rebase:
+    //
+    //   repo.commit(..), repo.push(), repo.force_push(..), ...
+    //
+    // None of them exist on Repository<RebaseInProgress>. A half-replayed
+    // branch is finished or abandoned before anything else touches it.
+    // -----------------------------------------------------------------------
+}
@@ -0,0 +1,89 @@
+//! Receipts and execution — how a destructive operation actually ended.
+
+use crate::OperationKind;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// How a backend operation ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    /// The deadline passed. `processed` of `total` units were done.
+    TimedOut { processed: usize, total: usize },
+    /// The cancel token was tripped. `processed` of `total` units were done.
+    Cancelled { processed: usize, total: usize },
+    /// The backend refused or could not run it, and says why. Nothing it
+    /// counts as done was done.
+    Failed(String),
+}
+
+impl Outcome {
+    pub fn label(&self) -> String {
+        match self {
+            Outcome::Completed => "completed".to_string(),
+            Outcome::TimedOut { processed, total } => format!("timed-out ({}/{})", processed, total),
+            Outcome::Cancelled { processed, total } => format!("cancelled ({}/{})", processed, total),
+            Outcome::Failed(why) => format!("failed: {}", why),
+        }
+    }
+}
+
+/// An operation the backend did not carry out. The repository is handed
+/// back as it was.
+pub struct Failed<R> {
+    pub repo: Box<R>,
+    pub outcome: Outcome,
+}
+
+/// The record of one executed destructive operation.
+///
+/// A timeout is not a failure and not a success; it gets its own outcome so
+/// nobody reading receipts later mistakes a half-run rewrite for either.
+#[derive(Clone, Debug)]
+pub struct Receipt {
+    pub operation: OperationKind,
+    pub repo: String,
+    /// Where the repository is: the target its consent was granted for.
+    pub path: String,
+    pub outcome: Outcome,
+    /// When execution started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    pub elapsed: core::time::Duration,
+    /// Duplicate submissions coalesced into this execution instead of
+    /// running again. See [`dedup`].
+    pub retries: u32,
+    /// Digest of the recorded approval session, if one was captured.
+    /// See [`ceremony`].
+    pub ceremony: Option<String>,
+    /// The gates a federated consent passed through, requester first and
+    /// approving gate last. Empty for consent granted locally.
+    pub gate_chain: Vec<String>,
+    /// Whether the remote was read back and matched the intended state.
+    /// See [`readback`](crate::readback).
+    #[cfg(feature = "std")]
+    pub verification: Option<crate::readback::Verification>,
+    /// Why the approver approved, in their words.
+    pub approval_reason: Option<String>,
+    /// Executed under observe-mode consent: the run was simulated and
+    /// nothing real changed. See [`observe`].
+    pub observed: bool,
+    /// Who approved, as the organization's identity provider verified
+    /// them. Empty if no provider was configured. See [`identity`].
+    pub approved_by: Vec<String>,
+}
+
+impl Receipt {
+    /// Record the approver's reason, from [`SafetyGate::approval_reason`].
+    pub fn with_approval_reason(mut self, reason: Option<&str>) -> Self {
+        self.approval_reason = reason.map(str::to_string);
+        self
+    }
+
+    /// Record the verified approvers, from [`SafetyGate::approved_by`].
+    pub fn with_approved_by(mut self, subjects: &[String]) -> Self {
+        self.approved_by = subjects.to_vec();
+        self
+    }
+}
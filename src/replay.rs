@@ -0,0 +1,306 @@
+//! Golden-file checks of the gate's wire protocols.
+//!
+//! A fixture is a recorded exchange between the gate and an approval
+//! channel: the request lines the gate sent, what came back, the audit
+//! events the gate logged, and how the request ended. Replaying it runs
+//! the current gate against the recorded answers and fails on the first
+//! difference, so a change that alters what goes over the socket or
+//! webhook, how answers are read, or what the gate records is caught before
+//! a deployed approval bot finds it. Fixtures may also pin receipts at any
+//! supported wire version: each must still decode, and re-encode as
+//! recorded.
+//!
+//! ```text
+//! # fixtures/protocol/socket-retry-grant.replay
+//! channel socket /run/approvals.sock
+//! request force_push Force-push 'my-repo' to origin/main
+//! key 00000000000004d2
+//! > ask 00000000000004d2 force_push Force-push 'my-repo' to origin/main
+//! ! transient connection refused
+//! > ask 00000000000004d2 force_push Force-push 'my-repo' to origin/main
+//! < granted rollback rehearsed on staging
+//! expect CHANNEL: answered on attempt 2 for key 00000000000004d2
+//! expect REQUEST: force_push by 'unbound'
+//! expect CHALLENGE ANSWERED via socket /run/approvals.sock: Force-push 'my-repo' to origin/main
+//! expect GRANTED force_push: Force-push 'my-repo' to origin/main (reason: rollback rehearsed on staging)
+//! outcome granted
+//! ```
+//!
+//! Requests are written in the socket protocol's form whichever channel
+//! was recorded; a webhook's body carries the same three fields. `policy`
+//! lines are a policy file for the replaying gate. `expect` lines
+//! are every audit event the request logs, in order; durations in them
+//! (`1762ms`) are wall-clock and not compared. `receipt` lines must
+//! decode; an `encodes` line after one is its exact current encoding.
+//!
+//! `--record <socket> <operation> <description> <file>` asks a live socket
+//! channel and writes the exchange as a fixture; `make replay` replays
+//! every fixture in `fixtures/protocol`.
+
+use super::consent_client::{
+    answer_line, parse_answer, socket_line, Answer, Backoff, ConsentRequest, ConsentSource, Retrying, SourceError,
+};
+use super::dedup::IdempotencyKey;
+use super::{approver, policy, wire, ConsentDenied, OperationKind, SafetyGate, UserConsent};
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// One fixture, parsed.
+#[derive(Clone, Debug, Default)]
+pub struct Fixture {
+    /// The recorded channel's name, as its events cite it.
+    pub channel: String,
+    pub policy: Vec<String>,
+    pub request: Option<(OperationKind, String)>,
+    pub key: Option<IdempotencyKey>,
+    /// Each request line the gate must send, and the recorded reply.
+    pub exchange: Vec<(String, Result<Answer, SourceError>)>,
+    pub expect: Vec<String>,
+    /// `None` for granted, or the denial reason.
+    pub outcome: Option<Option<String>>,
+    /// Receipts, each with its expected re-encoding if pinned.
+    pub receipts: Vec<(String, Option<String>)>,
+}
+
+impl Fixture {
+    pub fn parse(text: &str) -> Result<Fixture, String> {
+        let mut fixture = Fixture::default();
+        let mut sent: Option<String> = None;
+        for (i, line) in text.lines().enumerate() {
+            let err = |message: String| format!("line {}: {}", i + 1, message);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (directive, rest) = line.split_once(' ').unwrap_or((line, ""));
+            if sent.is_some() && !matches!(directive, "<" | "!") {
+                return Err(err("a `>` line must be followed by `<` or `!`".to_string()));
+            }
+            match directive {
+                "channel" => fixture.channel = rest.to_string(),
+                "policy" => fixture.policy.push(rest.to_string()),
+                "request" => {
+                    let (name, description) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let operation =
+                        OperationKind::from_name(name).ok_or_else(|| err(format!("unknown operation `{}`", name)))?;
+                    fixture.request = Some((operation, description.to_string()));
+                }
+                "key" => {
+                    fixture.key = Some(IdempotencyKey::from_hex(rest).ok_or_else(|| err(format!("bad key `{}`", rest)))?)
+                }
+                ">" => sent = Some(rest.to_string()),
+                "<" | "!" => {
+                    let sent = sent.take().ok_or_else(|| err(format!("`{}` without a `>` before it", directive)))?;
+                    let reply = match (directive, rest.split_once(' ').unwrap_or((rest, ""))) {
+                        ("<", _) => Ok(parse_answer(rest).map_err(|e| err(format!("{:?}", e)))?),
+                        (_, ("transient", why)) => Err(SourceError::Transient(why.to_string())),
+                        (_, ("permanent", why)) => Err(SourceError::Permanent(why.to_string())),
+                        _ => return Err(err(format!("expected `! transient|permanent <why>`, found `{}`", line))),
+                    };
+                    fixture.exchange.push((sent, reply));
+                }
+                "expect" => fixture.expect.push(rest.to_string()),
+                "outcome" => {
+                    fixture.outcome = Some(match rest.split_once(' ').unwrap_or((rest, "")) {
+                        ("granted", "") => None,
+                        ("denied", why) => Some(why.to_string()),
+                        _ => return Err(err(format!("expected `granted` or `denied <reason>`, found `{}`", rest))),
+                    })
+                }
+                "receipt" => fixture.receipts.push((rest.to_string(), None)),
+                "encodes" => match fixture.receipts.last_mut() {
+                    Some((_, pinned @ None)) => *pinned = Some(rest.to_string()),
+                    _ => return Err(err("`encodes` must follow a `receipt` line".to_string())),
+                },
+                other => return Err(err(format!("unknown directive `{}`", other))),
+            }
+        }
+        if sent.is_some() {
+            return Err("the last `>` line has no reply".to_string());
+        }
+        if fixture.request.is_some() != fixture.outcome.is_some() {
+            return Err("a fixture with a `request` needs an `outcome`, and only then".to_string());
+        }
+        Ok(fixture)
+    }
+}
+
+/// A channel that answers from a fixture and notes the first request
+/// line that differs from the recording.
+struct Scripted {
+    channel: String,
+    replies: VecDeque<(String, Result<Answer, SourceError>)>,
+    mismatch: Option<String>,
+}
+
+impl ConsentSource for Scripted {
+    fn name(&self) -> String {
+        self.channel.clone()
+    }
+
+    fn ask(&mut self, request: &ConsentRequest) -> Result<Answer, SourceError> {
+        let line = socket_line(request);
+        match self.replies.pop_front() {
+            Some((expected, reply)) if expected == line => reply,
+            Some((expected, _)) => {
+                self.mismatch.get_or_insert(format!("sent `{}`, recording has `{}`", line, expected));
+                Err(SourceError::Permanent("replay mismatch".to_string()))
+            }
+            None => {
+                self.mismatch.get_or_insert(format!("sent `{}` after the recording ended", line));
+                Err(SourceError::Permanent("replay mismatch".to_string()))
+            }
+        }
+    }
+}
+
+/// A channel that passes requests to `inner` and writes down the
+/// exchange.
+pub struct Recorder<S> {
+    inner: S,
+    lines: Vec<String>,
+}
+
+impl<S: ConsentSource> Recorder<S> {
+    pub fn new(inner: S) -> Self {
+        Recorder { inner, lines: Vec::new() }
+    }
+}
+
+impl<S: ConsentSource> ConsentSource for Recorder<S> {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn ask(&mut self, request: &ConsentRequest) -> Result<Answer, SourceError> {
+        self.lines.push(format!("> {}", socket_line(request)));
+        let reply = self.inner.ask(request);
+        self.lines.push(match &reply {
+            Ok(answer) => format!("< {}", answer_line(answer)),
+            Err(SourceError::Transient(why)) => format!("! transient {}", why),
+            Err(SourceError::Permanent(why)) => format!("! permanent {}", why),
+        });
+        reply
+    }
+}
+
+fn no_sleep(_: std::time::Duration) {}
+
+/// `text` with every `<digits>ms` replaced by `_ms`.
+fn without_durations(text: &str) -> String {
+    let mut out = String::new();
+    let mut digits = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        if !digits.is_empty() && c == 'm' && chars.peek() == Some(&'s') {
+            out.push('_');
+        } else {
+            out.push_str(&digits);
+        }
+        digits.clear();
+        out.push(c);
+    }
+    out + &digits
+}
+
+/// Ask `source` through `gate` and write the whole exchange as a
+/// fixture, alongside the gate's answer.
+pub fn record<S: ConsentSource>(
+    gate: &mut SafetyGate,
+    source: S,
+    key: IdempotencyKey,
+    operation: OperationKind,
+    description: &str,
+) -> (Result<UserConsent, ConsentDenied>, String) {
+    let start = gate.audit.len();
+    let mut client = Retrying::new(Recorder::new(source), Backoff::default());
+    let result = gate.consent_via(&mut client, key, operation, description);
+    let mut out = format!(
+        "channel {}\nrequest {} {}\nkey {}\n",
+        client.source_name(),
+        operation.name(),
+        description,
+        key.to_hex()
+    );
+    for line in &client.source().lines {
+        out.push_str(&format!("{}\n", line));
+    }
+    for entry in &gate.audit.entries()[start..] {
+        out.push_str(&format!("expect {}\n", entry.event));
+    }
+    match &result {
+        Ok(_) => out.push_str("outcome granted\n"),
+        Err(denied) => out.push_str(&format!("outcome denied {}\n", denied.reason)),
+    }
+    (result, out)
+}
+
+/// Replay one fixture against the current gate. `Err` says what
+/// differed.
+pub fn check(fixture: &Fixture) -> Result<(), String> {
+    if let Some((operation, description)) = &fixture.request {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(approver::Rehearsal));
+        if !fixture.policy.is_empty() {
+            gate.policy = policy::Policy::parse(&fixture.policy.join("\n")).map_err(|e| format!("policy: {}", e))?;
+        }
+        let key = fixture.key.unwrap_or_else(|| IdempotencyKey::new(*operation, "", description));
+        let scripted = Scripted {
+            channel: fixture.channel.clone(),
+            replies: fixture.exchange.iter().cloned().collect(),
+            mismatch: None,
+        };
+        let mut client = Retrying::new(scripted, Backoff::default()).with_sleep(no_sleep);
+        let start = gate.audit.len();
+        let result = gate.consent_via(&mut client, key, *operation, description);
+        if let Some(mismatch) = client.source().mismatch.clone() {
+            return Err(mismatch);
+        }
+        if !client.source().replies.is_empty() {
+            return Err(format!("{} recorded replies were never asked for", client.source().replies.len()));
+        }
+        let events: Vec<String> = gate.audit.entries()[start..].iter().map(|e| without_durations(&e.event)).collect();
+        if events != fixture.expect.iter().map(|e| without_durations(e)).collect::<Vec<_>>() {
+            return Err(format!("audit events differ:\n    got:      {}\n    recorded: {}", events.join(" | "), fixture.expect.join(" | ")));
+        }
+        let got = result.err().map(|denied| without_durations(&denied.reason));
+        if Some(&got) != fixture.outcome.as_ref().map(|o| o.as_deref().map(without_durations)).as_ref() {
+            return Err(format!("outcome {:?}, recorded {:?}", got, fixture.outcome));
+        }
+    }
+    for (line, pinned) in &fixture.receipts {
+        let receipt = wire::decode_receipt(line).map_err(|e| format!("receipt no longer decodes ({:?}): {}", e, line))?;
+        let encoded = wire::encode_receipt(&receipt);
+        if let Some(pinned) = pinned.as_ref().filter(|p| **p != encoded) {
+            return Err(format!("receipt re-encodes as\n    {}\n  not\n    {}", encoded, pinned));
+        }
+    }
+    Ok(())
+}
+
+/// A fixture's file name, and whether it replayed.
+pub type Verdict = (String, Result<(), String>);
+
+/// Replay every `.replay` file in `dir`, in name order.
+pub fn run(dir: &Path) -> Result<Vec<Verdict>, String> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "replay"))
+        .collect();
+    paths.sort();
+    Ok(paths
+        .iter()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let result = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Fixture::parse(&text))
+                .and_then(|fixture| check(&fixture));
+            (name, result)
+        })
+        .collect())
+}
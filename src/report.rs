@@ -0,0 +1,243 @@
+//! Incident timeline correlation.
+//!
+//! The February 25 reconstruction was stitched together by hand from the
+//! agent transcript, zsh history, the GitHub audit log, and reflogs — each
+//! with its own clock. `correlate` does that stitching mechanically: every
+//! source is shifted onto the gate's trusted clock and merged into one
+//! ordered timeline.
+
+use super::{json_string, provenance::Chain, AuditLog};
+pub use super::clock::format_utc;
+
+/// Where a timeline event came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    /// The gate's own audit log. Its clock is the trusted time source.
+    AuditLog,
+    /// Shell history files (`~/.zsh_history`, `~/.bash_history`).
+    ShellHistory,
+    /// CI job logs.
+    CiLog,
+    /// Platform webhook event exports (pushes, protection changes).
+    WebhookExport,
+}
+
+impl SourceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceKind::AuditLog => "audit-log",
+            SourceKind::ShellHistory => "shell-history",
+            SourceKind::CiLog => "ci-log",
+            SourceKind::WebhookExport => "webhook-export",
+        }
+    }
+}
+
+/// One source of timestamped events, on its own clock.
+pub struct TimelineSource {
+    pub name: String,
+    pub kind: SourceKind,
+    events: Vec<(u64, String)>,
+    /// Seconds to add to this source's timestamps to land on the
+    /// trusted clock. Zero for the audit log by definition.
+    skew: i64,
+}
+
+impl TimelineSource {
+    pub fn new(name: &str, kind: SourceKind) -> Self {
+        TimelineSource {
+            name: name.to_string(),
+            kind,
+            events: Vec::new(),
+            skew: 0,
+        }
+    }
+
+    /// The gate's audit log. It defines trusted time, so it is never
+    /// skew-corrected.
+    pub fn from_audit_log(log: &AuditLog) -> Self {
+        let mut source = TimelineSource::new("gate", SourceKind::AuditLog);
+        for entry in log.entries() {
+            source.events.push((entry.at, entry.event.clone()));
+        }
+        source
+    }
+
+    /// Parse a shell history file.
+    ///
+    /// Understands zsh extended history (`: 1772031791:0;git push -f`)
+    /// and bash `HISTTIMEFORMAT` history (`#1772031791` on the line
+    /// before the command). Lines without a timestamp are dropped —
+    /// an event with no time cannot be placed on a timeline.
+    pub fn shell_history(name: &str, contents: &str) -> Self {
+        let mut source = TimelineSource::new(name, SourceKind::ShellHistory);
+        let mut pending: Option<u64> = None;
+        for line in contents.lines() {
+            if let Some(rest) = line.strip_prefix(": ") {
+                if let Some((meta, command)) = rest.split_once(';') {
+                    let stamp = meta.split(':').next().unwrap_or("");
+                    if let Ok(at) = stamp.parse() {
+                        source.events.push((at, command.to_string()));
+                    }
+                }
+            } else if let Some(stamp) = line.strip_prefix('#') {
+                pending = stamp.trim().parse().ok();
+            } else if let Some(at) = pending.take() {
+                source.events.push((at, line.to_string()));
+            }
+        }
+        source
+    }
+
+    /// Parse a CI log whose lines start with an RFC 3339 UTC timestamp
+    /// (`2026-02-25T14:03:11Z step output`). Other lines are dropped.
+    pub fn ci_log(name: &str, contents: &str) -> Self {
+        let mut source = TimelineSource::new(name, SourceKind::CiLog);
+        for line in contents.lines() {
+            let (stamp, text) = line.split_once(' ').unwrap_or((line, ""));
+            if let Some(at) = parse_utc(stamp) {
+                source.events.push((at, text.trim().to_string()));
+            }
+        }
+        source
+    }
+
+    /// Add an event by hand — for webhook exports or any source that
+    /// was already parsed elsewhere.
+    pub fn event(mut self, at: u64, description: &str) -> Self {
+        self.events.push((at, description.to_string()));
+        self
+    }
+
+    /// Anchor this source to the trusted clock.
+    ///
+    /// `local_at` is when this source recorded some event; `trusted_at`
+    /// is when the gate recorded the same event. The difference is the
+    /// source's clock skew, applied to every event it contributes.
+    pub fn anchored(mut self, local_at: u64, trusted_at: u64) -> Self {
+        self.skew = trusted_at as i64 - local_at as i64;
+        self
+    }
+
+    pub fn skew(&self) -> i64 {
+        self.skew
+    }
+}
+
+/// One event on the correlated timeline, in trusted time.
+pub struct TimelineEvent {
+    pub at: u64,
+    /// The timestamp as the source recorded it, before skew correction.
+    pub source_at: u64,
+    pub source: String,
+    pub kind: SourceKind,
+    pub description: String,
+}
+
+/// The merged, ordered incident timeline.
+pub struct Timeline {
+    pub events: Vec<TimelineEvent>,
+}
+
+/// Merge every source into one timeline ordered by trusted time.
+///
+/// Ties keep source order, so when the audit log is passed first, the
+/// gate's record of an event precedes the echoes of it elsewhere.
+pub fn correlate(sources: &[TimelineSource]) -> Timeline {
+    let mut events = Vec::new();
+    for source in sources {
+        for (at, description) in &source.events {
+            events.push(TimelineEvent {
+                at: (*at as i64 + source.skew).max(0) as u64,
+                source_at: *at,
+                source: source.name.clone(),
+                kind: source.kind,
+                description: description.clone(),
+            });
+        }
+    }
+    events.sort_by_key(|e| e.at);
+    Timeline { events }
+}
+
+/// The incident report: the correlated timeline, then each operation's
+/// provenance chain end to end, from declared intent to read-back.
+pub fn incident_report(title: &str, timeline: &Timeline, operations: &[&Chain]) -> String {
+    let mut out = format!("# {}
+
+## Timeline
+
+{}
+## Operations
+", title, timeline.to_markdown());
+    if operations.is_empty() {
+        out.push_str("
+No operation declared its intent.
+");
+    }
+    for chain in operations {
+        out.push('\n');
+        out.push_str(&chain.to_markdown());
+    }
+    out
+}
+
+impl Timeline {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("| Time (UTC) | Source | Event |\n|---|---|---|\n");
+        for e in &self.events {
+            out.push_str(&format!(
+                "| {} | {} ({}) | {} |\n",
+                format_utc(e.at),
+                e.source,
+                e.kind.label(),
+                e.description.replace('|', "\\|")
+            ));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .map(|e| {
+                format!(
+                    "{{\"at\":{},\"time\":{},\"source_at\":{},\"source\":{},\"kind\":{},\"event\":{}}}",
+                    e.at,
+                    json_string(&format_utc(e.at)),
+                    e.source_at,
+                    json_string(&e.source),
+                    json_string(e.kind.label()),
+                    json_string(&e.description)
+                )
+            })
+            .collect();
+        format!("{{\"events\":[{}]}}", events.join(","))
+    }
+}
+
+/// Parse `YYYY-MM-DDTHH:MM:SSZ` into Unix seconds.
+pub fn parse_utc(s: &str) -> Option<u64> {
+    let s = s.strip_suffix('Z')?;
+    let (date, time) = s.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<u64>().ok());
+    let (hh, mm, ss) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    let days = days_from_civil(y, m, d);
+    u64::try_from(days).ok().map(|days| days * 86_400 + hh * 3600 + mm * 60 + ss)
+}
+
+// Howard Hinnant's civil calendar algorithm; its inverse is in the clock.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
@@ -0,0 +1,930 @@
+//! Repository<State, B> — the typestate pattern.
+
+use crate::{
+    backend, is_ci_config, AgentManifest, Branch, BranchInfo, Capability, CiConfigChange,
+    CommitBuilder, CommitError, ConsentDenied, DirtyWorkingTree, Failed, FileChange,
+    FilteredRepository, HostKey, ManifestError, OperationDescriptor, OperationKind, Outcome,
+    OwnershipTransfer, Protected, ReadOnly, Receipt, RecoveryBundle, Refused, ResetRepository,
+    ResetResult, RewriteResult, SafetyError, Submodule, SubmoduleInfo, TransferredRepository,
+    Unprotected, UserConsent, Worktree,
+};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// A git repository parameterized by its protection state.
+///
+/// `Repository<Protected>` is a different type from `Repository<Unprotected>`.
+/// They have different method sets. The compiler enforces this distinction
+/// at zero runtime cost — the `PhantomData` marker is erased at compile time.
+///
+/// The agent in the incident treated both repos as mutable, unprotected
+/// resources it could freely modify. This type system makes that assumption
+/// a compile error.
+///
+/// Two more states narrow what a protected repository offers: `ReadOnly`,
+/// which is what an agent gets until its manifest grants commit access,
+/// and `DirtyWorkingTree`, which holds uncommitted work that nothing may
+/// consume until it is committed or stashed.
+///
+/// `B` is what runs git: a [`backend::GitBackend`], [`backend::Simulated`]
+/// unless the repository was opened with [`open_with`](Repository::open_with).
+/// The state decides which methods exist; the backend only runs them.
+pub struct Repository<State = Protected, B = backend::Simulated> {
+    pub name: String,
+    pub path: String,
+    /// As the repository was opened with; see [`total_commits`](Self::total_commits).
+    pub(crate) total_commits: usize,
+    /// Local branches, as the repository was opened with; see
+    /// [`branches`](Self::branches).
+    pub(crate) branches: Vec<BranchInfo>,
+    /// As the repository was opened with; see [`head`](Self::head).
+    pub(crate) head: String,
+    /// The branch others clone and merge into.
+    pub default_branch: String,
+    /// Backup refs created by guided workflows before they delete anything.
+    pub backup_refs: Vec<String>,
+    /// As last read from the working tree; see [`uncommitted`](Self::uncommitted).
+    pub(crate) uncommitted: Vec<String>,
+    /// Files with unresolved conflicts, while a rebase is in progress.
+    pub conflicts: Vec<String>,
+    /// Submodules, as last read from `.gitmodules` and the index.
+    pub submodules: Vec<SubmoduleInfo>,
+    pub(crate) backend: B,
+    _state: PhantomData<State>,
+}
+
+impl<State, B> Repository<State, B> {
+    /// Move the repository into another typestate. Private: the public
+    /// transitions are the consent-checked methods that call this.
+    pub(crate) fn into_state<Next>(self) -> Repository<Next, B> {
+        Repository {
+            name: self.name,
+            path: self.path,
+            total_commits: self.total_commits,
+            branches: self.branches,
+            head: self.head,
+            default_branch: self.default_branch,
+            backup_refs: self.backup_refs,
+            uncommitted: self.uncommitted,
+            conflicts: self.conflicts,
+            submodules: self.submodules,
+            backend: self.backend,
+            _state: PhantomData,
+        }
+    }
+
+    /// What runs git for this repository.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// How many commits the repository was opened with. For display: the
+    /// gate sizes a request by [`commit_count`](Self::commit_count).
+    pub fn total_commits(&self) -> usize {
+        self.total_commits
+    }
+
+    /// How many commits the repository holds, as its backend counts them
+    /// now. `None` if the backend cannot say, which the gate treats as a
+    /// history of any size.
+    pub fn commit_count(&self) -> Option<usize>
+    where
+        B: backend::GitBackend,
+    {
+        self.backend.commit_count(&self.target()).ok()
+    }
+
+    pub(crate) fn target(&self) -> backend::Target<'_> {
+        backend::Target {
+            name: &self.name,
+            path: &self.path,
+            total_commits: self.total_commits,
+            head: &self.head,
+            branches: &self.branches,
+            uncommitted: &self.uncommitted,
+        }
+    }
+
+    /// A repository as recorded, in any state. Private: the public
+    /// constructors open read-only and read the working tree.
+    fn recorded(name: &str, path: &str, total_commits: usize, backend: B) -> Self {
+        Repository {
+            name: name.to_string(),
+            path: path.to_string(),
+            total_commits,
+            branches: Vec::new(),
+            head: "main".to_string(),
+            default_branch: "main".to_string(),
+            backup_refs: Vec::new(),
+            uncommitted: Vec::new(),
+            conflicts: Vec::new(),
+            submodules: Vec::new(),
+            backend,
+            _state: PhantomData,
+        }
+    }
+
+    /// Local branches, as the repository was opened with. What deleting
+    /// one is depends on the backend, not on this list; see
+    /// [`deletion_of`](Self::deletion_of).
+    pub fn branches(&self) -> &[BranchInfo] {
+        &self.branches
+    }
+
+    /// The checked-out branch, as the repository was opened with.
+    pub fn head(&self) -> &str {
+        &self.head
+    }
+
+    /// The files with uncommitted changes, as last read from the working
+    /// tree: when the repository was opened, and again when commit access
+    /// was granted.
+    pub fn uncommitted(&self) -> &[String] {
+        &self.uncommitted
+    }
+
+    /// A clone at `path`: the same history and branches, a clean working
+    /// tree, and protection as `Next` says.
+    fn cloned<Next>(&self, path: &str) -> Repository<Next, B>
+    where
+        B: Clone,
+    {
+        Repository {
+            name: self.name.clone(),
+            path: path.to_string(),
+            total_commits: self.total_commits,
+            branches: self.branches.clone(),
+            head: self.head.clone(),
+            default_branch: self.default_branch.clone(),
+            backup_refs: Vec::new(),
+            uncommitted: Vec::new(),
+            conflicts: Vec::new(),
+            submodules: self.submodules.clone(),
+            backend: self.backend.clone(),
+            _state: PhantomData,
+        }
+    }
+
+    /// The files with uncommitted changes, one per line. Reading is safe
+    /// in every state.
+    pub fn diff(&self) -> String {
+        self.uncommitted.iter().map(|f| format!("M {}\n", f)).collect()
+    }
+
+    /// The most recent `count` commits.
+    pub fn log(&self, count: usize) -> String {
+        format!("[{}] last {} of {} commits", self.name, count.min(self.total_commits), self.total_commits)
+    }
+
+    /// Fetch is safe: it updates remote-tracking refs and nothing else.
+    pub fn fetch(&self) -> Result<String, Outcome>
+    where
+        B: backend::GitBackend,
+    {
+        self.backend.fetch(&self.target())
+    }
+}
+
+impl Repository<Protected> {
+    /// A protected repository, opened without a commit grant. Only this
+    /// crate opens one directly, for what it rebuilds or runs itself.
+    pub(crate) fn protected(name: &str, path: &str, total_commits: usize) -> Self {
+        Repository::protected_with(name, path, total_commits, backend::Simulated)
+    }
+}
+
+impl<B: backend::GitBackend> Repository<Protected, B> {
+    /// As [`protected`](Repository::protected), on `backend`.
+    pub(crate) fn protected_with(name: &str, path: &str, total_commits: usize, backend: B) -> Self {
+        Repository::recorded(name, path, total_commits, backend)
+    }
+
+    /// Clone this repository into `path`. See [`clone_from`](Repository::clone_from).
+    pub fn clone_to(&self, path: &str) -> Repository<Protected, B>
+    where
+        B: Clone,
+    {
+        self.cloned(path)
+    }
+
+    /// Record a local branch: whether it is merged into the default branch
+    /// and how many days since its last commit.
+    pub fn with_branch(mut self, name: &str, merged: bool, days_since_commit: u32) -> Self {
+        self.branches.push(BranchInfo {
+            name: name.to_string(),
+            merged,
+            days_since_commit,
+        });
+        self
+    }
+
+    /// Record which branch is checked out. `main`, unless recorded otherwise.
+    pub fn with_head(mut self, branch: &str) -> Self {
+        self.head = branch.to_string();
+        self
+    }
+
+    /// Record the default branch. `main`, unless recorded otherwise.
+    pub fn with_default_branch(mut self, branch: &str) -> Self {
+        self.default_branch = branch.to_string();
+        self
+    }
+
+    /// Record a file with uncommitted changes in the working tree. The
+    /// repository is dirty until that work is committed or stashed.
+    pub fn with_uncommitted(mut self, path: &str) -> Repository<DirtyWorkingTree, B> {
+        self.uncommitted.push(path.to_string());
+        self.into_state()
+    }
+
+    /// Hand the repository over for reading only.
+    pub fn read_only(self) -> Repository<ReadOnly, B> {
+        self.into_state()
+    }
+
+    /// One branch, protected until its own protection is removed. Lifting
+    /// it there leaves every other branch, `main` included, protected.
+    pub fn branch(&self, name: &str) -> Branch<'_, Protected> {
+        Branch::of(self, name)
+    }
+
+    /// Record a submodule: its path in this repository, its URL, the
+    /// commit this repository points it at, and its own commit count.
+    pub fn with_submodule(mut self, path: &str, url: &str, pointer: &str, total_commits: usize) -> Self {
+        self.submodules.push(SubmoduleInfo {
+            path: path.to_string(),
+            url: url.to_string(),
+            pointer: pointer.to_string(),
+            total_commits,
+        });
+        self
+    }
+
+    /// Every submodule, protected.
+    pub fn submodules(&self) -> Vec<Submodule<'_, Protected>> {
+        self.submodules.iter().map(|info| Submodule::of(self, info)).collect()
+    }
+
+    /// Add a worktree at `path`, on a new branch named after its last
+    /// component, as `git worktree add` does.
+    pub fn worktree(&self, path: &str) -> Worktree<'_, B> {
+        Worktree::add(self, path)
+    }
+
+    /// Safe operations are always available on protected repos.
+    pub fn status(&self) -> String {
+        format!("{}: {} commits, protected", self.name, self.total_commits)
+    }
+
+    /// Commit is safe. No consent required — unless the commit amends
+    /// another and drops its attribution; see [`CommitBuilder`].
+    pub fn commit(&self) -> CommitBuilder<'_, B> {
+        CommitBuilder::new(self)
+    }
+
+    /// Regular push of the default branch is safe. No consent required.
+    pub fn push(&self) -> Result<String, Outcome> {
+        self.backend.push(&self.target(), &self.default_branch)
+    }
+
+    /// Fast-forward-only pull is safe: it moves the branch forward to the
+    /// remote, or refuses. It never merges, rebases, or discards.
+    pub fn pull(&self) -> Result<String, Outcome> {
+        self.backend.pull(&self.target(), &self.default_branch)
+    }
+
+    /// Creating a branch at HEAD is safe: it overwrites nothing. An existing
+    /// branch of that name is refused rather than moved.
+    pub fn create_branch(&self, branch: &str) -> Result<String, String> {
+        if self.branches.iter().any(|b| b.name == branch) {
+            return Err(format!("branch '{}' already exists in '{}'", branch, self.name));
+        }
+        self.backend.create_branch(&self.target(), branch).map_err(|outcome| outcome.label())
+    }
+
+    /// Creating a tag at HEAD is safe. Tags are never moved; retagging is
+    /// not offered.
+    pub fn create_tag(&self, tag: &str, message: &str) -> Result<String, Outcome> {
+        self.backend.create_tag(&self.target(), tag, message)
+    }
+
+    /// Commit a set of file changes. Safe — unless a change touches a
+    /// CI/CD pipeline definition, in which case it is refused with the
+    /// pipeline paths. Those go through [`commit_ci_change`].
+    ///
+    /// [`commit_ci_change`]: Repository::commit_ci_change
+    pub fn commit_changes(&self, message: &str, changes: &[FileChange]) -> Result<String, CommitError> {
+        let ci: Vec<String> = changes
+            .iter()
+            .filter(|c| is_ci_config(&c.path))
+            .map(|c| c.path.clone())
+            .collect();
+        if !ci.is_empty() {
+            return Err(CommitError::CiConfig(ci));
+        }
+        self.write_changes(message, changes)
+    }
+
+    /// Commit changes that include CI/CD pipeline files. Requires consent
+    /// scoped to pipeline changes; general consent does not type-check.
+    pub fn commit_ci_change(
+        &self,
+        message: &str,
+        changes: &[FileChange],
+        consent: UserConsent<CiConfigChange>,
+    ) -> Result<String, CommitError> {
+        consent.check_live().map_err(CommitError::Denied)?;
+        consent.announce(&format!("CI/CD pipeline change on '{}' with user approval.", self.name));
+        self.write_changes(message, changes)
+    }
+
+    fn write_changes(&self, message: &str, changes: &[FileChange]) -> Result<String, CommitError> {
+        let files: Vec<String> = changes.iter().map(|c| c.path.clone()).collect();
+        self.backend.commit(&self.target(), message, &files).map_err(CommitError::Failed)
+    }
+
+    /// Transfer the repository to `new_owner`. Consumes the repository.
+    ///
+    /// Only a completed [`TransferCeremony`] produces the consent, and
+    /// only for this repository and this new owner. The record keeps the
+    /// downstream impact the repository itself reports.
+    pub fn transfer_ownership(
+        self,
+        new_owner: &str,
+        consent: UserConsent<OwnershipTransfer>,
+    ) -> Result<TransferredRepository, Refused<Self>> {
+        let expected = OperationDescriptor::TransferOwnership {
+            repo: self.path.clone(),
+            new_owner: new_owner.to_string(),
+        };
+        let checked = consent
+            .check_target(expected.kind(), &self.name, &self.path)
+            .and_then(|()| consent.check_descriptor(&expected));
+        if let Err(denied) = checked {
+            return Err(Refused {
+                repo: Box::new(self),
+                denied,
+            });
+        }
+        consent.announce(&format!("'{}' transferred to '{}' after dual approval.", self.name, new_owner));
+        Ok(TransferredRepository {
+            impact: self.transfer_impact(new_owner),
+            name: self.name,
+            new_owner: new_owner.to_string(),
+        })
+    }
+
+    /// Remove branch protection. Requires `UserConsent`.
+    ///
+    /// This consumes `self` and returns `Repository<Unprotected>`.
+    /// The old `Repository<Protected>` no longer exists. The borrow checker
+    /// ensures that any references to the old repo are now invalid.
+    ///
+    /// In the incident, the agent removed branch protection via the GitHub
+    /// API without consent. Here, the type signature makes that impossible:
+    /// no `UserConsent`, no `Unprotected` repo, no destructive operations.
+    pub fn remove_protection(self, consent: UserConsent) -> Result<Repository<Unprotected, B>, Refused<Self>> {
+        let expected = OperationDescriptor::RemoveProtection { repo: self.path.clone() };
+        let repo = self.refuse_unless(&expected, &consent)?;
+        consent.announce(&format!("Branch protection removed on '{}' with user approval.", repo.name));
+        Ok(repo.into_state())
+    }
+
+    // -----------------------------------------------------------------------
+    // What the agent CANNOT do on a protected repo:
+    //
+    //   repo.force_push()     — method does not exist on Repository<Protected>
+    //   repo.filter_repo(..)  — method does not exist on Repository<Protected>
+    //   repo.reset_hard()     — method does not exist on Repository<Protected>
+    //
+    // These are not runtime checks. These are not permission flags. These
+    // methods literally do not exist in this impl block. The compiler will
+    // refuse to compile any code that tries to call them.
+    // -----------------------------------------------------------------------
+}
+
+impl<B: backend::GitBackend> Repository<Unprotected, B> {
+    #[cfg(feature = "std")]
+    pub(crate) fn push_to(&self, branch: &str) -> OperationDescriptor {
+        OperationDescriptor::ForcePush {
+            repo: self.path.clone(),
+            branch: branch.to_string(),
+        }
+    }
+
+    /// Force-push the default branch to the remote. Only available on
+    /// unprotected repos.
+    ///
+    /// Requires a second `UserConsent` — removing protection was one approval,
+    /// force-pushing is another. Each destructive act requires its own consent,
+    /// and spends it: pushing again means asking again.
+    ///
+    /// In the incident, the agent force-pushed to both repos without any
+    /// consent at all. Two approvals were needed. Zero were obtained.
+    pub fn force_push(&self, consent: UserConsent) -> Result<String, SafetyError> {
+        self.branch(&self.default_branch).force_push(consent)
+    }
+
+    /// Force-push to `branch` on the remote. The consent must have been
+    /// granted for that branch, as consent exchanged from a
+    /// [`DelegatedConsent`](delegation::DelegatedConsent) is.
+    pub fn force_push_branch(&self, branch: &str, consent: UserConsent) -> Result<String, SafetyError> {
+        self.branch(branch).force_push(consent)
+    }
+
+    /// Rewrite repository history with filter-repo. Consumes the repository.
+    ///
+    /// This takes `self` by value, not by reference. After `filter_repo()`,
+    /// the original repository is gone. Moved. Consumed. The borrow checker
+    /// will reject any subsequent use of the variable.
+    ///
+    /// This is not a metaphor. After `git filter-repo --force`, the old
+    /// repository IS gone. Every commit has a new SHA. The old history is
+    /// unreachable. Rust makes the consumption explicit in the type signature.
+    ///
+    /// In the incident, the agent ran filter-repo and then continued to
+    /// operate on the repo as if nothing had changed. Rust would have
+    /// caught this as a use-after-move error.
+    ///
+    /// The rewrite also hands back a [`RecoveryBundle`], written before the
+    /// first commit is touched. The original history is gone from the
+    /// repository, but not from the world. A rewrite the backend fails
+    /// moves no refs, and hands the repository back instead.
+    pub fn filter_repo(self, callback: &str, consent: UserConsent) -> Result<RewriteResult<Self>, Refused<Self>> {
+        self.filter_repo_with(callback, consent, &backend::ExecOptions::default(), &mut backend::NoProgress)
+    }
+
+    /// `filter_repo` with a deadline, cancellation, and progress reporting.
+    ///
+    /// A filter-repo over a large history can run for hours. Commits are
+    /// rewritten one at a time, checking the deadline and cancel token
+    /// between each; refs are only moved once every commit is done. An
+    /// interrupted rewrite therefore hands back the original, untouched
+    /// repository alongside a receipt saying how far it got.
+    pub fn filter_repo_with(
+        self,
+        callback: &str,
+        consent: UserConsent,
+        options: &backend::ExecOptions,
+        progress: &mut dyn backend::Progress,
+    ) -> Result<RewriteResult<Self>, Refused<Self>> {
+        let expected = OperationDescriptor::FilterRepo {
+            repo: self.path.clone(),
+            callback: callback.to_string(),
+        };
+        let repo = self.refuse_unless(&expected, &consent)?;
+        let bundle = RecoveryBundle::snapshot(&repo);
+        let target = repo.target();
+        #[cfg(feature = "std")]
+        let before = repo.rewritten_tips();
+        let (rewritten, mut receipt) = backend::run(OperationKind::FilterRepo, &repo.target(), || {
+            match consent.is_observe_only() {
+                true => backend::GitBackend::filter_repo(&backend::Simulated, &target, callback, options, progress),
+                false => repo.backend.filter_repo(&target, callback, options, progress),
+            }
+        });
+        receipt.observed = consent.is_observe_only();
+        let Some(rewritten_commits) = rewritten else {
+            return Ok(RewriteResult::Interrupted(repo, receipt));
+        };
+        consent.announce(&format!("History rewrite on '{}' with user approval. Callback: {}.", repo.name, callback));
+        #[cfg(feature = "std")]
+        {
+            let updates: Vec<_> = before
+                .into_iter()
+                .zip(repo.rewritten_tips())
+                .map(|((branch, old), (_, new))| crate::post_ops::RefUpdate::branch(branch, old, new))
+                .collect();
+            consent._downstream.rewrote(&receipt, &updates);
+        }
+        let filtered = FilteredRepository {
+            name: repo.name,
+            path: repo.path,
+            rewritten_commits,
+        };
+        Ok(RewriteResult::Rewritten(filtered, bundle, receipt))
+    }
+
+    /// `force_push` with a deadline, cancellation, and progress reporting,
+    /// one unit per commit transferred.
+    pub fn force_push_with(
+        &self,
+        consent: UserConsent,
+        options: &backend::ExecOptions,
+        progress: &mut dyn backend::Progress,
+    ) -> Result<Receipt, ConsentDenied> {
+        self.branch(&self.default_branch).force_push_with(consent, options, progress)
+    }
+
+    /// Clone this repository into `path`. Removing protection here does not
+    /// carry over: the clone starts protected.
+    pub fn clone_to(&self, path: &str) -> Repository<Protected, B>
+    where
+        B: Clone,
+    {
+        self.cloned(path)
+    }
+
+    /// Every branch of an unprotected repository is unprotected.
+    pub fn branch(&self, name: &str) -> Branch<'_, Unprotected> {
+        Branch::of(self, name)
+    }
+
+    /// Every submodule, protected. Removing this repository's protection
+    /// removes none of theirs.
+    pub fn submodules(&self) -> Vec<Submodule<'_, Protected>> {
+        self.submodules.iter().map(|info| Submodule::of(self, info)).collect()
+    }
+
+    /// Where each branch a history rewrite moves points now: every local
+    /// branch, or the default branch if the repository was opened
+    /// without a list.
+    #[cfg(feature = "std")]
+    fn rewritten_tips(&self) -> Vec<(&str, Result<String, Outcome>)> {
+        let target = self.target();
+        let branches = match self.branches.is_empty() {
+            true => vec![self.default_branch.as_str()],
+            false => self.branches.iter().map(|b| b.name.as_str()).collect(),
+        };
+        branches.into_iter().map(|branch| (branch, self.backend.tip(&target, branch, false))).collect()
+    }
+
+    /// What deleting `branch` is. Deleting the checked-out branch, or one
+    /// not merged into it, loses commits that exist nowhere else, and is
+    /// a [`ForceDeleteBranch`](OperationDescriptor::ForceDeleteBranch).
+    /// The backend is asked both; a branch it cannot vouch for is assumed
+    /// unmerged, and a head it cannot name, to be this branch.
+    pub fn deletion_of(&self, branch: &str) -> OperationDescriptor {
+        let target = self.target();
+        let checked_out = self.backend.head(&target).map_or(true, |head| head == branch);
+        let merged = self.backend.merged(&target, branch).unwrap_or(false);
+        let (repo, branch) = (self.path.clone(), branch.to_string());
+        if checked_out || !merged {
+            OperationDescriptor::ForceDeleteBranch { repo, branch }
+        } else {
+            OperationDescriptor::DeleteBranch { repo, branch }
+        }
+    }
+
+    /// Delete `branch`, locally and on origin. Requires consent for
+    /// [`deletion_of`](Repository::deletion_of) the branch: consent to
+    /// delete a merged branch does not cover one that turns out unmerged.
+    pub fn delete_branch(&mut self, branch: &str, consent: UserConsent) -> Result<String, SafetyError> {
+        let expected = self.deletion_of(branch);
+        consent.check(expected.kind(), self)?;
+        consent.check_descriptor(&expected)?;
+        consent.announce(&format!("Branch '{}' of '{}' deleted with user approval.", branch, self.name));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] deletion of branch '{}' simulated", self.name, branch));
+        }
+        let deleted = backend::run_to_end(expected.kind(), &self.target(), || self.backend.delete_branch(&self.target(), branch))?;
+        self.branches.retain(|b| b.name != branch);
+        Ok(deleted)
+    }
+
+    /// Delete `tag`, locally and on origin. Requires consent.
+    pub fn delete_tag(&self, tag: &str, consent: UserConsent) -> Result<String, SafetyError> {
+        let expected = OperationDescriptor::DeleteTag {
+            repo: self.path.clone(),
+            tag: tag.to_string(),
+        };
+        consent.check(OperationKind::DeleteTag, self)?;
+        consent.check_descriptor(&expected)?;
+        consent.announce(&format!("Tag '{}' of '{}' deleted with user approval.", tag, self.name));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] deletion of tag '{}' simulated", self.name, tag));
+        }
+        backend::run_to_end(OperationKind::DeleteTag, &self.target(), || self.backend.delete_tag(&self.target(), tag))
+    }
+
+    /// Remove `remote` and its remote-tracking branches. Requires consent.
+    pub fn remove_remote(&self, remote: &str, consent: UserConsent) -> Result<String, SafetyError> {
+        let expected = OperationDescriptor::RemoveRemote {
+            repo: self.path.clone(),
+            remote: remote.to_string(),
+        };
+        consent.check(OperationKind::RemoveRemote, self)?;
+        consent.check_descriptor(&expected)?;
+        consent.announce(&format!("Remote '{}' removed from '{}' with user approval.", remote, self.name));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] removal of remote '{}' simulated", self.name, remote));
+        }
+        backend::run_to_end(OperationKind::RemoveRemote, &self.target(), || {
+            self.backend.remove_remote(&self.target(), remote)
+        })
+    }
+
+    /// Hard reset. Consumes the repository.
+    ///
+    /// After `reset_hard()`, uncommitted work is gone. The repo object is
+    /// consumed to make this destruction visible in the type system. It
+    /// stays here rather than on [`Branch`]: what it destroys is the
+    /// working tree, which belongs to the repository, not to a branch. A
+    /// reset the backend fails hands the repository back.
+    pub fn reset_hard(self, consent: UserConsent) -> Result<ResetResult<Self>, Refused<Self>> {
+        let expected = OperationDescriptor::ResetHard { repo: self.path.clone() };
+        let repo = self.refuse_unless(&expected, &consent)?;
+        let (reset, mut receipt) = backend::run(OperationKind::ResetHard, &repo.target(), || match consent.is_observe_only() {
+            true => Ok(()),
+            false => repo.backend.reset_hard(&repo.target()),
+        });
+        receipt.observed = consent.is_observe_only();
+        if reset.is_none() {
+            return Ok(ResetResult::Failed(repo, receipt));
+        }
+        consent.announce(&format!("Hard reset on '{}' with user approval.", repo.name));
+        let reset = ResetRepository {
+            name: repo.name,
+            path: repo.path,
+        };
+        Ok(ResetResult::Reset(reset, receipt))
+    }
+
+    /// Restore branch protection. Always allowed — makes things safer.
+    ///
+    /// Returns `Repository<Protected>`, closing the window of vulnerability.
+    /// This was the one operation in the incident that was arguably benign,
+    /// though the agent used it to cover its tracks.
+    pub fn restore_protection(self) -> Repository<Protected, B> {
+        say!("  [OK] Branch protection restored on '{}'.", self.name);
+        self.into_state()
+    }
+}
+
+/// A protected repository, by whether its working tree is clean.
+pub enum WorkingTree<B = backend::Simulated> {
+    Clean(Repository<Protected, B>),
+    Dirty(Repository<DirtyWorkingTree, B>),
+}
+
+/// A commit grant that was refused. The repository is handed back, still
+/// read-only.
+pub struct NotGranted<B = backend::Simulated> {
+    pub repo: Box<Repository<ReadOnly, B>>,
+    pub error: ManifestError,
+}
+
+impl Repository<ReadOnly> {
+    /// Open a repository. It is read-only until a manifest grants commit
+    /// access; see [`grant_commit`](Repository::grant_commit).
+    ///
+    /// This, [`open_with`](Repository::open_with), and
+    /// [`clone_from`](Repository::clone_from) are the only public
+    /// constructors. Every repository starts read-only. The agent does not
+    /// get to choose.
+    pub fn open(name: &str, path: &str, total_commits: usize) -> Self {
+        Repository::open_with(name, path, total_commits, backend::Simulated)
+    }
+
+    /// Clone `url` into `path`. The clone is read-only, like any repository
+    /// opened here: experimenting on a clone instead of the canonical
+    /// checkout is the point, but a clone still pushes to its origin.
+    pub fn clone_from(url: &str, path: &str) -> Self {
+        let name = url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or(url);
+        Repository::open(name.trim_end_matches(".git"), path, 0)
+    }
+}
+
+impl<B: backend::GitBackend> Repository<ReadOnly, B> {
+    /// Open a repository whose git operations `backend` runs. It is
+    /// read-only, as every repository starts, and its uncommitted files
+    /// are what `backend` reports. A working tree the backend cannot read
+    /// is taken as clean here, and as dirty by
+    /// [`grant_commit`](Repository::grant_commit).
+    pub fn open_with(name: &str, path: &str, total_commits: usize, backend: B) -> Self {
+        let mut repo = Repository::recorded(name, path, total_commits, backend);
+        repo.uncommitted = repo.backend.status(&repo.target()).unwrap_or_default();
+        repo
+    }
+
+    /// Record a file with uncommitted changes in the working tree.
+    pub fn with_uncommitted(mut self, path: &str) -> Self {
+        self.uncommitted.push(path.to_string());
+        self
+    }
+
+    pub fn status(&self) -> String {
+        format!("{}: {} commits, read-only", self.name, self.total_commits)
+    }
+
+    /// Clone this repository into `path`. A read-only repository clones
+    /// read-only: a clone pushes to its origin, so a clone with commit
+    /// access would be commit access to this one.
+    pub fn clone_to(&self, path: &str) -> Repository<ReadOnly, B>
+    where
+        B: Clone,
+    {
+        self.cloned(path)
+    }
+
+    /// Grant commit access, if `manifest` verifies against the host key and
+    /// grants [`Capability::Commit`]. The working tree is read again from
+    /// the backend, and a dirty one comes back as [`WorkingTree::Dirty`]:
+    /// commit access does not reach the consuming operations until that
+    /// work is committed or stashed. A working tree the backend cannot
+    /// read is not known to be clean, and comes back dirty.
+    pub fn grant_commit(mut self, manifest: &AgentManifest, key: &HostKey) -> Result<WorkingTree<B>, NotGranted<B>> {
+        let error = if !manifest.verify(key) {
+            ManifestError::BadSignature
+        } else if !manifest.capabilities.contains(&Capability::Commit) {
+            ManifestError::NotPermitted(Capability::Commit)
+        } else {
+            return Ok(match self.backend.status(&self.target()) {
+                Ok(files) if files.is_empty() => {
+                    self.uncommitted = files;
+                    WorkingTree::Clean(self.into_state())
+                }
+                Ok(files) => {
+                    self.uncommitted = files;
+                    WorkingTree::Dirty(self.into_state())
+                }
+                Err(_) => WorkingTree::Dirty(self.into_state()),
+            });
+        };
+        Err(NotGranted {
+            repo: Box::new(self),
+            error,
+        })
+    }
+}
+
+impl<B: backend::GitBackend> Repository<DirtyWorkingTree, B> {
+    /// Record another file with uncommitted changes.
+    pub fn with_uncommitted(mut self, path: &str) -> Self {
+        self.uncommitted.push(path.to_string());
+        self
+    }
+
+    /// Hand the repository over for reading only.
+    pub fn read_only(self) -> Repository<ReadOnly, B> {
+        self.into_state()
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "{}: {} commits, protected, {} uncommitted file(s)",
+            self.name,
+            self.total_commits,
+            self.uncommitted.len()
+        )
+    }
+
+    /// Commit the uncommitted work. The working tree is clean afterwards.
+    /// A commit the backend fails leaves it dirty, and hands it back.
+    pub fn commit_all(mut self, message: &str) -> Result<(Repository<Protected, B>, String), Failed<Self>> {
+        match self.backend.commit(&self.target(), message, &self.uncommitted) {
+            Ok(line) => {
+                self.uncommitted.clear();
+                Ok((self.into_state(), line))
+            }
+            Err(outcome) => Err(Failed {
+                repo: Box::new(self),
+                outcome,
+            }),
+        }
+    }
+
+    /// Stash the uncommitted work. The stash is recorded with the
+    /// repository's backup refs, so an operation that later discards the
+    /// working tree discards nothing that cannot be brought back, and the
+    /// work itself comes back as a [`StashHandle`] the caller has to settle.
+    pub fn stash(mut self) -> (Repository<Protected, B>, StashHandle) {
+        let stash = format!("refs/stash/{}", self.backup_refs.iter().filter(|r| r.starts_with("refs/stash/")).count());
+        self.backup_refs.push(stash.clone());
+        let handle = StashHandle {
+            repo: self.path.clone(),
+            name: self.name.clone(),
+            stash,
+            files: core::mem::take(&mut self.uncommitted),
+            settled: false,
+        };
+        say!("  [OK] Stashed {} uncommitted file(s) in '{}'.", handle.files.len(), self.name);
+        (self.into_state(), handle)
+    }
+
+    // -----------------------------------------------------------------------
+    // What the agent CANNOT do on a dirty working tree:
+    //
+    //   repo.remove_protection(..)  — does not exist on Repository<DirtyWorkingTree>
+    //
+    // and so no reset_hard(), filter_repo(), or force_push() either. The
+    // uncommitted work has to be committed or stashed first.
+    // -----------------------------------------------------------------------
+}
+
+/// Uncommitted work set aside by [`stash`](Repository::stash), as a value.
+///
+/// Work that is stashed and forgotten is work lost the next time someone
+/// cleans up the stash list. The handle has to be settled: [`pop`] and
+/// [`apply`] put the work back in the working tree, and
+/// [`drop_with_consent`] discards it with the approval that takes. A
+/// handle dropped any other way discards nothing — the stash ref stays —
+/// and says so.
+///
+/// [`pop`]: StashHandle::pop
+/// [`apply`]: StashHandle::apply
+/// [`drop_with_consent`]: StashHandle::drop_with_consent
+#[must_use = "pop it, apply it, or drop it with consent"]
+pub struct StashHandle {
+    repo: String,
+    name: String,
+    stash: String,
+    files: Vec<String>,
+    settled: bool,
+}
+
+/// A stash offered to a repository it was not taken from. Both are handed
+/// back untouched.
+pub struct WrongRepository<B = backend::Simulated> {
+    pub stash: StashHandle,
+    pub repo: Box<Repository<Protected, B>>,
+}
+
+impl StashHandle {
+    /// The stash ref, as recorded with the repository's backup refs.
+    pub fn stash(&self) -> &str {
+        &self.stash
+    }
+
+    /// The files the stash holds.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    fn restore<B>(
+        mut self,
+        mut repo: Repository<Protected, B>,
+        keep: bool,
+    ) -> Result<Repository<DirtyWorkingTree, B>, WrongRepository<B>> {
+        if repo.path != self.repo {
+            return Err(WrongRepository {
+                stash: self,
+                repo: Box::new(repo),
+            });
+        }
+        if !keep {
+            repo.backup_refs.retain(|r| *r != self.stash);
+        }
+        repo.uncommitted.append(&mut self.files);
+        self.settled = true;
+        Ok(repo.into_state())
+    }
+
+    /// Put the work back in `repo`'s working tree and delete the stash.
+    pub fn pop<B>(self, repo: Repository<Protected, B>) -> Result<Repository<DirtyWorkingTree, B>, WrongRepository<B>> {
+        self.restore(repo, false)
+    }
+
+    /// Put the work back in `repo`'s working tree, keeping the stash.
+    pub fn apply<B>(self, repo: Repository<Protected, B>) -> Result<Repository<DirtyWorkingTree, B>, WrongRepository<B>> {
+        self.restore(repo, true)
+    }
+
+    /// Discard the stash and the work in it. Requires consent for exactly
+    /// this stash.
+    pub fn drop_with_consent<B>(
+        mut self,
+        repo: &mut Repository<Protected, B>,
+        consent: UserConsent,
+    ) -> Result<String, Refused<Self>> {
+        let expected = OperationDescriptor::DropStash {
+            repo: self.repo.clone(),
+            stash: self.stash.clone(),
+        };
+        let checked = if repo.path != self.repo {
+            let reason = format!("the stash is from '{}', not '{}'", self.repo, repo.path);
+            Err(ConsentDenied::new(OperationKind::DropStash, reason))
+        } else {
+            consent.check(OperationKind::DropStash, repo).and_then(|()| consent.check_descriptor(&expected))
+        };
+        if let Err(denied) = checked {
+            return Err(Refused {
+                repo: Box::new(self),
+                denied,
+            });
+        }
+        consent.announce(&format!("Stash '{}' of '{}' dropped with user approval.", self.stash, self.name));
+        self.settled = true;
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] drop of {} simulated", self.name, self.stash));
+        }
+        repo.backup_refs.retain(|r| *r != self.stash);
+        Ok(format!("[{}] dropped {} ({} files)", self.name, self.stash, self.files.len()))
+    }
+}
+
+impl Drop for StashHandle {
+    fn drop(&mut self) {
+        if !self.settled {
+            say!(
+                "  [WARN] Stash '{}' of '{}' was never popped, applied, or dropped; its {} file(s) are still in the stash.",
+                self.stash,
+                self.name,
+                self.files.len()
+            );
+        }
+    }
+}
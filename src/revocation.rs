@@ -0,0 +1,113 @@
+//! Revocable consent.
+//!
+//! An approver who says yes and then, while the agent is still planning,
+//! realizes they should not have, needs a way to say no again. Every
+//! consent a gate mints carries the gate's [`Registry`]; a
+//! [`ConsentHandle`] marks one consent revoked in it, and the destructive
+//! methods refuse revoked consent the same way they refuse expired
+//! consent. Revoking consent that has already been used changes nothing:
+//! what it authorized has happened.
+//!
+//! The handle goes to the approver, never to the agent. It can be moved
+//! to another thread — a UI, a chat bot — and used from there.
+
+use super::backend::GitBackend;
+use super::{ConsentDenied, OperationKind, Repository, SafetyGate, UserConsent};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// The tokens of revoked consent, shared by a gate and everything it
+/// minted.
+#[derive(Clone, Debug, Default)]
+pub struct Registry(Arc<Mutex<BTreeSet<u64>>>);
+
+impl Registry {
+    fn revoke(&self, token: u64) {
+        // A poisoned lock is still the registry; revoking must not fail.
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(token);
+    }
+
+    /// Whether `token` was revoked. A registry that cannot be read
+    /// answers yes: unknown is not a yes.
+    pub(crate) fn is_revoked(&self, token: u64) -> bool {
+        self.0.lock().map(|revoked| revoked.contains(&token)).unwrap_or(true)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().map(|revoked| revoked.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The approver's hold on one outstanding consent.
+#[derive(Clone, Debug)]
+pub struct ConsentHandle {
+    token: u64,
+    pub operation: OperationKind,
+    pub description: String,
+    registry: Registry,
+}
+
+impl ConsentHandle {
+    pub(crate) fn new(token: u64, operation: OperationKind, description: &str, registry: Registry) -> Self {
+        ConsentHandle {
+            token,
+            operation,
+            description: description.to_string(),
+            registry,
+        }
+    }
+
+    /// Revoke the consent. It is refused the next time it is presented.
+    pub fn revoke(&self) {
+        self.registry.revoke(self.token);
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.registry.is_revoked(self.token)
+    }
+}
+
+impl<Scope> UserConsent<Scope> {
+    fn handle(&self) -> ConsentHandle {
+        ConsentHandle::new(self._nonce, self._kind, &self._operation, self._revocations.clone())
+    }
+
+    /// Refuse this consent if its handle revoked it.
+    pub(crate) fn check_revoked(&self) -> Result<(), ConsentDenied> {
+        if !self._revocations.is_revoked(self._nonce) {
+            return Ok(());
+        }
+        Err(ConsentDenied::new(self._kind, "the approver revoked this consent".to_string()).by_approver("the approver"))
+    }
+}
+
+impl SafetyGate {
+    /// [`request_consent_for`](SafetyGate::request_consent_for), with a
+    /// handle that can revoke the consent until it is used.
+    pub fn request_revocable_consent_for<S, B: GitBackend>(
+        &mut self,
+        repo: &Repository<S, B>,
+        paths: &[&str],
+        operation: OperationKind,
+        operation_description: &str,
+    ) -> Result<(UserConsent, ConsentHandle), ConsentDenied> {
+        let consent = self.request_consent_for(repo, paths, operation, operation_description)?;
+        let handle = consent.handle();
+        Ok((consent, handle))
+    }
+
+    /// Revoke through `handle` and record it in the audit log.
+    pub fn revoke(&mut self, handle: &ConsentHandle) {
+        handle.revoke();
+        self.log(format!("REVOKED: {}", handle.description));
+    }
+
+    /// The registry every consent this gate mints is checked against.
+    pub fn revocations(&self) -> &Registry {
+        &self.revocations
+    }
+}
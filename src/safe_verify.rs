@@ -0,0 +1,426 @@
+//! safe_verify.rs — check incident evidence without running the gate.
+//!
+//! An auditor given a receipt should not have to trust the system that
+//! produced it. This binary shares no code with the `safe_operations` library: every
+//! check is re-implemented here from the formats alone, so a bug in the gate
+//! cannot vouch for itself.
+//!
+//! It reads one evidence bundle, as written by `AuditArchive::export_bundle`:
+//!
+//! ```text
+//! bundle/
+//!   receipt.json        one receipt, in wire format
+//!   receipt.sig         the archive's signature over receipt.json
+//!   segments/*.seg      the audit segments covering the receipt
+//!   ceremony.cast       the recorded approval, if the receipt names one
+//! ```
+//!
+//! and prints one line per check and a verdict. Exit status is 0 only if
+//! every check passed.
+//!
+//! Segment headers are Ed25519 signatures over each segment's SHA-256
+//! chain link, and `receipt.sig` is one over the receipt, so nothing the
+//! receipt says — who approved it included — can be edited after export.
+//! The bundle carries no key: the auditor passes the
+//! archive's public key (`AuditArchive::public_key`) as published by its
+//! operator, so whoever assembled the bundle cannot also choose the key
+//! it is checked against.
+//!
+//! ```text
+//! cargo build --release --bin safe-verify --target x86_64-unknown-linux-musl
+//! safe-verify path/to/bundle <archive public key, 64 hex digits>
+//! ```
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const GENESIS: &str = "genesis";
+
+fn sha256(hasher: Sha256) -> String {
+    let hex: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// A segment's chain link: SHA-256 of the previous digest and its lines.
+fn chain_digest(prev: &str, entries: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    for line in entries {
+        hasher.update(line);
+        hasher.update(b"\n");
+    }
+    sha256(hasher)
+}
+
+/// Whether `signature` (hex) is `key`'s Ed25519 signature of `message`.
+fn verify_signature(key: &VerifyingKey, message: &str, signature: &str) -> bool {
+    let Some(signature) = unhex(signature).and_then(|bytes| <[u8; 64]>::try_from(bytes).ok()) else {
+        return false;
+    };
+    key.verify_strict(message.as_bytes(), &Signature::from_bytes(&signature)).is_ok()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+/// The receipt fields checked here, from its wire format. Fields added
+/// after version 1 default to empty when an older receipt lacks them.
+#[derive(Deserialize)]
+struct Receipt {
+    operation: String,
+    repo: String,
+    outcome: String,
+    started_at: u64,
+    #[serde(default)]
+    ceremony: String,
+    #[serde(default)]
+    approval_reason: String,
+    #[serde(default)]
+    observed: u8,
+    #[serde(default)]
+    approved_by: String,
+    #[serde(default)]
+    path: String,
+}
+
+/// The audit entry fields checked here.
+#[derive(Deserialize)]
+struct Entry {
+    at: u64,
+    event: String,
+}
+
+/// The operation and target a `GRANTED` event records. They come first,
+/// before the requester's own wording, as `GRANTED <operation>` and then,
+/// if the consent named one, ` on <target as a JSON string>`.
+fn granted(event: &str) -> Option<(&str, Option<String>)> {
+    let rest = event.strip_prefix("GRANTED ")?;
+    let end = rest.find([' ', ':']).unwrap_or(rest.len());
+    let (operation, rest) = rest.split_at(end);
+    let Some(quoted) = rest.strip_prefix(" on ") else {
+        return rest.starts_with(':').then_some((operation, None));
+    };
+    let mut target = serde_json::Deserializer::from_str(quoted).into_iter::<String>();
+    let parsed = target.next()?.ok()?;
+    quoted[target.byte_offset()..].starts_with([':', ' ']).then_some((operation, Some(parsed)))
+}
+
+/// Whether `event` records consent for exactly the receipt's operation
+/// on exactly its repository.
+fn grants(event: &str, receipt: &Receipt) -> bool {
+    granted(event).is_some_and(|(operation, target)| {
+        operation == receipt.operation && target.is_some_and(|target| target == receipt.path)
+    })
+}
+
+fn gunzip(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(bytes);
+    }
+    let mut out = Vec::new();
+    GzDecoder::new(bytes.as_slice())
+        .read_to_end(&mut out)
+        .map_err(|e| format!("cannot decompress the segment: {}", e))?;
+    Ok(out)
+}
+
+struct Segment {
+    index: u64,
+    prev: String,
+    digest: String,
+    entries: Vec<String>,
+}
+
+/// Read and check one segment: its signature and its digest.
+fn read_segment(path: &Path, key: &VerifyingKey) -> Result<Segment, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let text = String::from_utf8(gunzip(bytes)?).map_err(|_| "not UTF-8".to_string())?;
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap_or("").split(' ').collect();
+    let ["segment", index, _first, _last, prev, digest, signature] = header.as_slice() else {
+        return Err("no segment header".to_string());
+    };
+    let index: u64 = index.parse().map_err(|_| "bad index".to_string())?;
+    if !verify_signature(key, &format!("segment|{}|{}|{}", index, prev, digest), signature) {
+        return Err(format!("segment {}: signature does not verify", index));
+    }
+    let entries: Vec<String> = lines.map(str::to_string).collect();
+    let recomputed = chain_digest(prev, &entries);
+    if recomputed != *digest {
+        return Err(format!("segment {}: entries hash to {}, header says {}", index, recomputed, digest));
+    }
+    Ok(Segment {
+        index,
+        prev: prev.to_string(),
+        digest: digest.to_string(),
+        entries,
+    })
+}
+
+struct Checks {
+    failed: usize,
+}
+
+impl Checks {
+    fn report(&mut self, name: &str, result: Result<String, String>) {
+        match result {
+            Ok(detail) => println!("  [PASS] {}: {}", name, detail),
+            Err(why) => {
+                self.failed += 1;
+                println!("  [FAIL] {}: {}", name, why);
+            }
+        }
+    }
+}
+
+fn verify(bundle: &Path, key: &VerifyingKey) -> Result<usize, String> {
+    let mut checks = Checks { failed: 0 };
+    let line = fs::read_to_string(bundle.join("receipt.json")).map_err(|e| format!("receipt.json: {}", e))?;
+    let receipt: Receipt = serde_json::from_str(&line).map_err(|e| format!("receipt.json: {}", e))?;
+    let (operation, repo, started_at) = (&receipt.operation, &receipt.repo, receipt.started_at);
+    println!("Receipt: {} on '{}' started at {}, outcome {}", operation, repo, started_at, receipt.outcome);
+    let signed = fs::read_to_string(bundle.join("receipt.sig"))
+        .map_err(|e| format!("receipt.sig is missing: {}", e))
+        .and_then(|signature| match verify_signature(key, &format!("receipt|{}", line.trim_end()), signature.trim()) {
+            true => Ok("signed by the archive".to_string()),
+            false => Err("signature does not verify".to_string()),
+        });
+    let signed_ok = signed.is_ok();
+    checks.report("receipt", signed);
+    if !receipt.approval_reason.is_empty() {
+        println!("Approver's reason: {}", receipt.approval_reason);
+    }
+    if !receipt.approved_by.is_empty() {
+        // The gate checked these subjects with the identity provider when
+        // consent was granted; the signature is what vouches for the record.
+        let vouched = match signed_ok {
+            true => "as the gate verified them, under the archive's signature",
+            false => "unverified: the receipt's signature did not check",
+        };
+        println!("Approved by ({}): {}", vouched, receipt.approved_by.replace(';', "; "));
+    }
+    if receipt.observed == 1 {
+        println!("Observe mode: this run was simulated and changed nothing real");
+    }
+
+    let mut paths: Vec<_> = fs::read_dir(bundle.join("segments"))
+        .map_err(|e| format!("segments/: {}", e))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "seg"))
+        .collect();
+    paths.sort();
+    let mut segments = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match read_segment(path, key) {
+            Ok(segment) => {
+                checks.report(&name, Ok(format!("signed, {} entries match {}", segment.entries.len(), segment.digest)));
+                segments.push(segment);
+            }
+            Err(why) => checks.report(&name, Err(why)),
+        }
+    }
+    if segments.is_empty() {
+        checks.report("segments", Err("no verifiable segments in the bundle".to_string()));
+    }
+
+    // Consecutive segments must chain. The first may start mid-archive,
+    // so it is only required to be genesis if it is segment 0.
+    let links = segments.windows(2).try_for_each(|pair| {
+        if pair[1].index != pair[0].index + 1 || pair[1].prev != pair[0].digest {
+            Err(format!("segment {} does not follow segment {}", pair[1].index, pair[0].index))
+        } else {
+            Ok(())
+        }
+    });
+    let links = links.and_then(|()| match segments.first() {
+        Some(first) if first.index == 0 && first.prev != GENESIS => Err("segment 0 does not start at genesis".to_string()),
+        Some(first) => Ok(format!("{} segments chained from segment {}", segments.len(), first.index)),
+        None => Ok("nothing to chain".to_string()),
+    });
+    checks.report("chain", links);
+
+    let grant = segments
+        .iter()
+        .rev()
+        .flat_map(|s| s.entries.iter().rev())
+        .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+        .find(|entry| entry.at <= started_at && grants(&entry.event, &receipt));
+    checks.report(
+        "consent",
+        match grant {
+            Some(entry) => Ok(format!("recorded at {}: {}", entry.at, entry.event)),
+            None if receipt.path.is_empty() => Err("the receipt records no path to match consent against".to_string()),
+            None => Err(format!("no GRANTED entry for {} on {} before {}", operation, receipt.path, started_at)),
+        },
+    );
+
+    match Some(&receipt.ceremony).filter(|c| !c.is_empty()) {
+        Some(expected) => {
+            let cast = fs::read(bundle.join("ceremony.cast"));
+            checks.report(
+                "ceremony",
+                match cast {
+                    _ if !expected.starts_with("sha256:") => {
+                        Err(format!("receipt names {}, which is not a SHA-256 digest", expected))
+                    }
+                    Ok(bytes) if sha256(Sha256::new_with_prefix(&bytes)) == *expected => {
+                        Ok(format!("cast matches {}", expected))
+                    }
+                    Ok(_) => Err(format!("ceremony.cast does not hash to {}", expected)),
+                    Err(e) => Err(format!("receipt names {} but ceremony.cast is missing: {}", expected, e)),
+                },
+            );
+        }
+        None => println!("  [SKIP] ceremony: the receipt names no recording"),
+    }
+    Ok(checks.failed)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [bundle, key] = args.as_slice() else {
+        eprintln!("usage: safe-verify <bundle-dir> <archive-public-key>");
+        std::process::exit(2);
+    };
+    let Some(key) = unhex(key)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+    else {
+        eprintln!("not an Ed25519 public key: {}", key);
+        std::process::exit(2);
+    };
+    match verify(Path::new(bundle), &key) {
+        Ok(0) => println!("VERDICT: VALID"),
+        Ok(failed) => {
+            println!("VERDICT: INVALID ({} checks failed)", failed);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("cannot read bundle: {}", err);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chain_digest, granted, grants, verify, Receipt, GENESIS};
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// A bundle of one segment holding `events`, and `receipt` signed by
+    /// the same key as the segment.
+    fn bundle(name: &str, key: &SigningKey, events: &[&str], receipt: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("safe-operations-verify-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("segments")).unwrap();
+        let entries: Vec<String> = events
+            .iter()
+            .map(|event| serde_json::json!({"v": 1, "type": "audit_entry", "at": 5, "event": event}).to_string())
+            .collect();
+        let digest = chain_digest(GENESIS, &entries);
+        let sign = |message: String| -> String {
+            key.sign(message.as_bytes()).to_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+        };
+        let signature = sign(format!("segment|0|{}|{}", GENESIS, digest));
+        let header = format!("segment 0 5 5 {} {} {}", GENESIS, digest, signature);
+        fs::write(dir.join("segments/00000000.seg"), format!("{}\n{}\n", header, entries.join("\n"))).unwrap();
+        fs::write(dir.join("receipt.json"), format!("{}\n", receipt)).unwrap();
+        fs::write(dir.join("receipt.sig"), sign(format!("receipt|{}", receipt))).unwrap();
+        dir
+    }
+
+    const RECEIPT: &str = concat!(
+        r#"{"v":12,"type":"receipt","operation":"force_push","repo":"a","outcome":"completed","started_at":10,"#,
+        r#""approved_by":"oidc:https://login.example.com|alice","path":"/repos/a"}"#
+    );
+
+    fn receipt(operation: &str, path: &str) -> Receipt {
+        let line = format!(
+            r#"{{"operation":"{}","repo":"my-repo","outcome":"completed","started_at":10,"path":"{}"}}"#,
+            operation, path
+        );
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn a_grant_is_read_as_fields() {
+        assert_eq!(
+            granted(r#"GRANTED force_push on "/repos/a": Force-push 'a'"#),
+            Some(("force_push", Some("/repos/a".to_string())))
+        );
+        assert_eq!(granted("GRANTED force_push: Force-push 'a'"), Some(("force_push", None)));
+        assert_eq!(
+            granted(r#"GRANTED reset_hard on "/repos/a \"b\"" BY gate 'eu': reset (chain: us -> eu)"#),
+            Some(("reset_hard", Some("/repos/a \"b\"".to_string())))
+        );
+        assert_eq!(granted("GRANTED at 10"), None);
+        assert_eq!(granted("BUDGET GRANTED: 3 force_push"), None);
+    }
+
+    #[test]
+    fn consent_must_match_both_operation_and_repository() {
+        let receipt = receipt("force_push", "/repos/a");
+        assert!(grants(r#"GRANTED force_push on "/repos/a": Force-push 'a'"#, &receipt));
+        assert!(!grants(r#"GRANTED reset_hard on "/repos/a": Reset 'a'"#, &receipt), "another operation");
+        assert!(!grants(r#"GRANTED force_push on "/repos/b": Force-push 'b'"#, &receipt), "another repository");
+        assert!(!grants("GRANTED force_push: Force-push '/repos/a'", &receipt), "no target");
+    }
+
+    #[test]
+    fn the_requester_wording_cannot_supply_the_fields() {
+        let receipt = receipt("force_push", "/repos/a");
+        let worded = r#"GRANTED delete_tag on "/repos/b": force_push on "/repos/a""#;
+        assert!(!grants(worded, &receipt));
+    }
+
+    #[test]
+    fn a_signed_bundle_with_matching_consent_is_valid() {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let dir = bundle("valid", &key, &[r#"GRANTED force_push on "/repos/a": Force-push 'a'"#], RECEIPT);
+        assert_eq!(verify(&dir, &key.verifying_key()), Ok(0));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn an_edited_receipt_or_unmatched_consent_fails() {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let dir = bundle("edited", &key, &[r#"GRANTED force_push on "/repos/a": Force-push 'a'"#], RECEIPT);
+        let edited = RECEIPT.replace("alice", "mallory");
+        fs::write(dir.join("receipt.json"), edited).unwrap();
+        assert_eq!(verify(&dir, &key.verifying_key()), Ok(1), "the receipt's signature");
+        let _ = fs::remove_dir_all(dir);
+
+        let dir = bundle("unmatched", &key, &[r#"GRANTED reset_hard on "/repos/a": force_push "/repos/a""#], RECEIPT);
+        assert_eq!(verify(&dir, &key.verifying_key()), Ok(1), "the consent");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_ceremony_is_checked_by_sha256_and_nothing_weaker() {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let grant = r#"GRANTED force_push on "/repos/a": Force-push 'a'"#;
+        let named = |ceremony: &str| RECEIPT.replace(r#""path""#, &format!(r#""ceremony":"{}","path""#, ceremony));
+        for (digest, failed) in [
+            ("sha256:667bf2cbcfd57752c5f34a8f0725cd3c08ce8c093fc25eda30a869475c4c50d3", 0),
+            ("sha256:0000000000000000000000000000000000000000000000000000000000000000", 1),
+            ("fnv1a64:00c0ffee00c0ffee", 1),
+        ] {
+            let dir = bundle("ceremony", &key, &[grant], &named(digest));
+            fs::write(dir.join("ceremony.cast"), "{\"version\": 2}\n").unwrap();
+            assert_eq!(verify(&dir, &key.verifying_key()), Ok(failed), "{}", digest);
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
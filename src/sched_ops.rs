@@ -0,0 +1,166 @@
+//! Guarded crontab editing.
+//!
+//! Adding a scheduled job is safe: everything that ran before still runs.
+//! Removing or rewriting one is not, and the job an agent "tidies up" is
+//! too often the nightly backup. Those edits need consent, and the approver
+//! sees the whole before/after diff.
+
+use super::approver::Line;
+use super::{ConsentDenied, OperationKind, SafetyGate, Severity, UserConsent};
+
+/// Consent scope for removing or modifying scheduled jobs.
+pub struct ScheduleChange;
+
+/// Commands that look like backups or monitoring. Touching them
+/// escalates the edit to High severity.
+pub const CRITICAL_JOB_PATTERNS: &[&str] = &[
+    "backup", "restic", "borg", "rsync", "pg_dump", "mysqldump", "snapshot", "monitor",
+    "healthcheck", "heartbeat", "prometheus", "exporter",
+];
+
+/// A crontab, line for line. Comments and environment lines are kept
+/// so that rendering what was parsed reproduces it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crontab {
+    lines: Vec<String>,
+}
+
+impl Crontab {
+    pub fn parse(text: &str) -> Self {
+        Crontab {
+            lines: text.lines().map(str::to_string).collect(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        self.lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+
+    /// The scheduled jobs: every line that is not blank, a comment, or
+    /// an environment assignment. Returned with their line indices.
+    pub fn entries(&self) -> Vec<(usize, &str)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| {
+                let t = l.trim();
+                !t.is_empty() && !t.starts_with('#') && !t.split_whitespace().next().unwrap_or("").contains('=')
+            })
+            .map(|(i, l)| (i, l.as_str()))
+            .collect()
+    }
+
+    /// Add a job. Safe: nothing that ran before stops running.
+    pub fn with_entry(&self, line: &str) -> Crontab {
+        let mut next = self.clone();
+        next.lines.push(line.to_string());
+        next
+    }
+
+    /// Propose removing the job on line `index`. Needs consent to install.
+    pub fn without_line(&self, index: usize) -> Crontab {
+        let mut next = self.clone();
+        if index < next.lines.len() {
+            next.lines.remove(index);
+        }
+        next
+    }
+
+    /// Propose replacing line `index`. Needs consent to install.
+    pub fn with_line_replaced(&self, index: usize, line: &str) -> Crontab {
+        let mut next = self.clone();
+        if let Some(slot) = next.lines.get_mut(index) {
+            *slot = line.to_string();
+        }
+        next
+    }
+
+    /// Whether `proposed` keeps every current line, in order.
+    pub fn is_additive(&self, proposed: &Crontab) -> bool {
+        proposed.lines.len() >= self.lines.len() && proposed.lines[..self.lines.len()] == self.lines[..]
+    }
+
+    /// Lines removed (`-`) and added (`+`) going from self to `proposed`.
+    pub fn diff(&self, proposed: &Crontab) -> String {
+        let mut out = String::new();
+        for line in self.lines.iter().filter(|l| !proposed.lines.contains(l)) {
+            out.push_str(&format!("- {}\n", line));
+        }
+        for line in proposed.lines.iter().filter(|l| !self.lines.contains(l)) {
+            out.push_str(&format!("+ {}\n", line));
+        }
+        out
+    }
+
+    /// Removed or changed lines that look like backups or monitoring.
+    pub fn critical_losses(&self, proposed: &Crontab) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter(|l| !proposed.lines.contains(l))
+            .filter(|l| {
+                let lower = l.to_lowercase();
+                CRITICAL_JOB_PATTERNS.iter().any(|p| lower.contains(p))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Install a proposed crontab that only adds jobs.
+pub fn install(current: &Crontab, proposed: Crontab) -> Result<Crontab, String> {
+    if !current.is_additive(&proposed) {
+        return Err(format!(
+            "proposed crontab removes or modifies existing jobs:\n{}",
+            current.diff(&proposed)
+        ));
+    }
+    Ok(proposed)
+}
+
+/// Consent to install exactly one proposed crontab.
+pub struct ScheduleApproval {
+    current: Crontab,
+    proposed: Crontab,
+    pub severity: Severity,
+    _consent: UserConsent<ScheduleChange>,
+}
+
+/// Install an approved crontab. Refused if the live crontab is no
+/// longer the one the diff was computed against.
+pub fn install_approved(live: &Crontab, approval: ScheduleApproval) -> Result<Crontab, String> {
+    if *live != approval.current {
+        return Err("crontab changed since the diff was approved".to_string());
+    }
+    Ok(approval.proposed)
+}
+
+impl SafetyGate {
+    /// Request consent to replace `current` with `proposed`, showing
+    /// the approver the full diff.
+    pub fn request_schedule_consent(
+        &mut self,
+        current: &Crontab,
+        proposed: &Crontab,
+    ) -> Result<ScheduleApproval, ConsentDenied> {
+        let operation = OperationKind::EditSchedule;
+        let losses = current.critical_losses(proposed);
+        let severity = if losses.is_empty() { operation.severity() } else { Severity::High };
+        self.show_line(Line::Notice(format!("{} severity: crontab change", severity.label())));
+        for line in current.diff(proposed).lines() {
+            self.show_line(Line::Diff(line.to_string()));
+        }
+        for lost in &losses {
+            self.show_line(Line::Warning(format!("removes or changes a backup/monitoring job: {}", lost)));
+        }
+        let consent = self.mint(
+            operation,
+            &format!("Edit crontab: {} backup/monitoring jobs affected [{}]", losses.len(), severity.label()),
+        )?;
+        Ok(ScheduleApproval {
+            current: current.clone(),
+            proposed: proposed.clone(),
+            severity,
+            _consent: consent,
+        })
+    }
+}
@@ -0,0 +1,239 @@
+//! Per-operation environments and credentials for backend subprocesses.
+//!
+//! A `Command` inherits the whole parent environment: `GITHUB_TOKEN`,
+//! `AWS_*`, `SSH_AUTH_SOCK`, whatever the agent's shell had. A filter-repo
+//! run does not need any of it, and a push needs push access to one remote
+//! for a few minutes. [`command`] starts from an empty environment, adds a
+//! short allowlist, points `HOME` at a scratch directory so no stored
+//! credentials are found, and passes only a [`ScopedToken`] the gate minted
+//! for this operation, if the operation reaches a remote at all.
+
+use super::{ed25519, os_random, unix_now, OperationKind, Repository, SafetyGate, UserConsent};
+use std::process::Command;
+use std::time::Duration;
+
+/// Variables copied from the parent. Nothing else is.
+pub const ALLOWLIST: &[&str] = &["PATH", "LANG", "LC_ALL", "TZ", "TMPDIR"];
+
+/// What an operation's subprocesses need to talk to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reach {
+    /// The local repository only. No credentials at all.
+    Local,
+    /// A git remote, over HTTPS.
+    GitRemote,
+    /// A hosting platform's API, through its CLI.
+    PlatformApi,
+}
+
+impl OperationKind {
+    pub fn reach(self) -> Reach {
+        match self {
+            OperationKind::FilterRepo
+            | OperationKind::ResetHard
+            | OperationKind::DeleteFiles
+            | OperationKind::EditSchedule
+            | OperationKind::CiConfigChange
+            | OperationKind::RemoveRemote
+            | OperationKind::Rebase
+            | OperationKind::DropStash
+            | OperationKind::StripAttribution => Reach::Local,
+            OperationKind::ForcePush
+            | OperationKind::DeleteBranch
+            | OperationKind::ForceDeleteBranch
+            | OperationKind::DeleteTag => Reach::GitRemote,
+            OperationKind::RemoveProtection
+            | OperationKind::TransferOwnership
+            | OperationKind::DeleteImageTag
+            | OperationKind::OverwriteImageTag
+            | OperationKind::DeleteImageRepository
+            | OperationKind::DeleteIndex
+            | OperationKind::DeleteByQuery
+            | OperationKind::CloseIndex
+            | OperationKind::DeleteTopic
+            | OperationKind::PurgeQueue
+            | OperationKind::ResetConsumerOffsets
+            | OperationKind::ChangeVisibility
+            | OperationKind::MakePublic
+            | OperationKind::ChangeLicense
+            | OperationKind::DeleteBackup
+            | OperationKind::ShortenRetention
+            | OperationKind::DisableBackupSchedule => Reach::PlatformApi,
+        }
+    }
+}
+
+/// A credential for one operation on one repository, valid until
+/// `expires_at`.
+pub struct ScopedToken {
+    value: String,
+    pub operation: OperationKind,
+    /// The path of the repository the consent was granted for.
+    pub repo: String,
+    /// Seconds since the Unix epoch.
+    pub expires_at: u64,
+}
+
+impl ScopedToken {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Enough of the token to correlate logs, not enough to use it.
+    pub fn fingerprint(&self) -> &str {
+        &self.value[..8]
+    }
+}
+
+impl std::fmt::Debug for ScopedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ScopedToken({}…, {} on {}, expires {})",
+            self.fingerprint(),
+            self.operation.name(),
+            self.repo,
+            self.expires_at
+        )
+    }
+}
+
+/// A subprocess for `operation`: empty environment plus the allowlist,
+/// a scratch `HOME`, no interactive prompts, and `token` if the
+/// operation reaches a remote. A token for another operation, an
+/// expired token, or a token for a local-only operation is refused.
+pub fn command(program: &str, operation: OperationKind, token: Option<&ScopedToken>) -> Result<Command, String> {
+    let reach = operation.reach();
+    if let Some(token) = token {
+        if token.operation != operation {
+            return Err(format!(
+                "token was minted for {}, not {}",
+                token.operation.name(),
+                operation.name()
+            ));
+        }
+        if token.is_expired(unix_now()) {
+            return Err(format!("token {} expired", token.fingerprint()));
+        }
+        if reach == Reach::Local {
+            return Err(format!("{} is local-only and gets no credentials", operation.name()));
+        }
+    }
+    let mut cmd = uncredentialed(program)?;
+    match (reach, token) {
+        (Reach::GitRemote, Some(token)) => {
+            cmd.env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+                .env("GIT_CONFIG_VALUE_0", format!("Authorization: Bearer {}", token.value));
+        }
+        (Reach::PlatformApi, Some(token)) => {
+            cmd.env("GH_TOKEN", &token.value);
+        }
+        _ => {}
+    }
+    Ok(cmd)
+}
+
+/// A subprocess for an operation that is not destructive — a fetch, a
+/// commit, a backup ref: [`command`]'s environment, with no credentials
+/// at all.
+pub fn uncredentialed(program: &str) -> Result<Command, String> {
+    let home = std::env::temp_dir().join(format!("safe-operations-home-{}", std::process::id()));
+    std::fs::create_dir_all(&home).map_err(|e| e.to_string())?;
+    let mut cmd = Command::new(program);
+    cmd.env_clear();
+    for name in ALLOWLIST {
+        if let Some(value) = std::env::var_os(name) {
+            cmd.env(name, value);
+        }
+    }
+    cmd.env("HOME", &home)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GH_PROMPT_DISABLED", "1");
+    Ok(cmd)
+}
+
+impl SafetyGate {
+    /// Mint a credential for `operation` on `repo`, valid for `ttl`. The
+    /// consent must have been granted for that operation on that
+    /// repository.
+    ///
+    /// Here the token is random; in a deployment this is where the gate
+    /// exchanges its own long-lived credential for a platform token
+    /// narrowed to this repository and permission (a GitHub App
+    /// installation token, an STS session), so the long-lived one
+    /// never reaches a subprocess.
+    pub fn mint_credential<S, B>(
+        &mut self,
+        repo: &Repository<S, B>,
+        operation: OperationKind,
+        consent: UserConsent,
+        ttl: Duration,
+    ) -> Result<ScopedToken, String> {
+        consent.check(operation, repo).map_err(|denied| denied.reason)?;
+        if operation.reach() == Reach::Local {
+            return Err(format!("{} is local-only and gets no credentials", operation.name()));
+        }
+        let mut secret = [0u8; 16];
+        os_random(&mut secret).map_err(|e| format!("cannot read the OS random source: {}", e))?;
+        let token = ScopedToken {
+            value: ed25519::hex(&secret),
+            operation,
+            repo: repo.path.clone(),
+            expires_at: self.clock() + ttl.as_secs(),
+        };
+        self.log(format!(
+            "CREDENTIAL MINTED: {} for {} on '{}', expires {}",
+            token.fingerprint(),
+            operation.name(),
+            repo.name,
+            token.expires_at
+        ));
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::approver::Rehearsal;
+    use crate::{OperationKind, Repository, SafetyGate};
+    use std::time::Duration;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn gate() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        gate
+    }
+
+    #[test]
+    fn a_credential_is_minted_only_for_the_consented_operation_and_repository() {
+        let (app, other) = (Repository::protected("app", "/repos/app", 10), Repository::protected("lib", "/repos/lib", 10));
+        let mut gate = gate();
+        let consent = gate.request_consent_for(&app, &[], OperationKind::DeleteTag, "delete tag v1").unwrap();
+        let refused = gate.mint_credential(&app, OperationKind::ForcePush, consent, TTL).unwrap_err();
+        assert!(refused.contains("granted for delete_tag"), "{}", refused);
+        let consent = gate.request_consent_for(&app, &[], OperationKind::ForcePush, "force-push main").unwrap();
+        let refused = gate.mint_credential(&other, OperationKind::ForcePush, consent, TTL).unwrap_err();
+        assert!(refused.contains("/repos/lib"), "{}", refused);
+        let consent = gate.request_consent_for(&app, &[], OperationKind::ForcePush, "force-push main").unwrap();
+        let token = gate.mint_credential(&app, OperationKind::ForcePush, consent, TTL).unwrap();
+        assert_eq!((token.operation, token.repo.as_str()), (OperationKind::ForcePush, "/repos/app"));
+    }
+
+    #[test]
+    fn credentials_are_random_and_not_printed() {
+        let app = Repository::protected("app", "/repos/app", 10);
+        let mut gate = gate();
+        let mut mint = || {
+            let consent = gate.request_consent_for(&app, &[], OperationKind::ForcePush, "force-push main").unwrap();
+            gate.mint_credential(&app, OperationKind::ForcePush, consent, TTL).unwrap()
+        };
+        let (a, b) = (mint(), mint());
+        assert_eq!(a.value.len(), 32);
+        assert_ne!(a.value, b.value);
+        assert!(!format!("{:?}", a).contains(&a.value));
+    }
+}
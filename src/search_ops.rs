@@ -0,0 +1,275 @@
+//! Guarded search-index operations.
+//!
+//! "Clean up the old test indices" has ended with production indices gone,
+//! because `logs-*` matched more than the agent thought. Searching, counting,
+//! and indexing documents are free. Deleting an index, deleting by query,
+//! and closing an index go through an [`IndexPlan`] that counts the
+//! documents affected and looks for a snapshot that covers them; with no
+//! snapshot, the plan is irreversible and needs the typed acknowledgment.
+
+use super::{
+    ConsentDenied, DestructionPlan, OperationKind, Protected, RecoveryCost, SafetyGate, UserConsent,
+};
+use core::marker::PhantomData;
+
+/// Consent scope for destructive index changes.
+pub struct IndexChange;
+
+/// The cluster API the plan's checks go through. A query is a single
+/// term matched against document bodies; `*` matches every document.
+pub trait SearchBackend {
+    fn indices(&self) -> Vec<String>;
+    fn is_open(&self, index: &str) -> bool;
+    fn search(&self, index: &str, query: &str) -> Vec<(String, String)>;
+    fn count(&self, index: &str, query: &str) -> u64 {
+        self.search(index, query).len() as u64
+    }
+    /// Completed snapshots that include `index`.
+    fn snapshots_covering(&self, index: &str) -> Vec<String>;
+    fn index_document(&mut self, index: &str, id: &str, body: &str);
+    fn delete_index(&mut self, index: &str);
+    fn delete_by_query(&mut self, index: &str, query: &str) -> u64;
+    fn close_index(&mut self, index: &str);
+}
+
+/// A cluster held in memory, for demonstrations and fixtures.
+#[derive(Default)]
+pub struct InMemoryCluster {
+    /// `(index, open)`.
+    pub indices: Vec<(String, bool)>,
+    /// `(index, id, body)`.
+    pub documents: Vec<(String, String, String)>,
+    /// `(snapshot, indices it covers)`.
+    pub snapshots: Vec<(String, Vec<String>)>,
+}
+
+fn matches(body: &str, query: &str) -> bool {
+    query == "*" || body.contains(query)
+}
+
+impl SearchBackend for InMemoryCluster {
+    fn indices(&self) -> Vec<String> {
+        self.indices.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn is_open(&self, index: &str) -> bool {
+        self.indices.iter().any(|(name, open)| name == index && *open)
+    }
+
+    fn search(&self, index: &str, query: &str) -> Vec<(String, String)> {
+        self.documents
+            .iter()
+            .filter(|(i, _, body)| i == index && matches(body, query))
+            .map(|(_, id, body)| (id.clone(), body.clone()))
+            .collect()
+    }
+
+    fn snapshots_covering(&self, index: &str) -> Vec<String> {
+        self.snapshots
+            .iter()
+            .filter(|(_, covered)| covered.iter().any(|i| i == index))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn index_document(&mut self, index: &str, id: &str, body: &str) {
+        if !self.indices.iter().any(|(name, _)| name == index) {
+            self.indices.push((index.to_string(), true));
+        }
+        self.documents.retain(|(i, d, _)| !(i == index && d == id));
+        self.documents.push((index.to_string(), id.to_string(), body.to_string()));
+    }
+
+    fn delete_index(&mut self, index: &str) {
+        self.indices.retain(|(name, _)| name != index);
+        self.documents.retain(|(i, _, _)| i != index);
+    }
+
+    fn delete_by_query(&mut self, index: &str, query: &str) -> u64 {
+        let before = self.documents.len();
+        self.documents.retain(|(i, _, body)| !(i == index && matches(body, query)));
+        (before - self.documents.len()) as u64
+    }
+
+    fn close_index(&mut self, index: &str) {
+        for (name, open) in &mut self.indices {
+            if name == index {
+                *open = false;
+            }
+        }
+    }
+}
+
+/// What a destructive index change does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexAction {
+    DeleteIndex,
+    DeleteByQuery(String),
+    CloseIndex,
+}
+
+impl IndexAction {
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            IndexAction::DeleteIndex => OperationKind::DeleteIndex,
+            IndexAction::DeleteByQuery(_) => OperationKind::DeleteByQuery,
+            IndexAction::CloseIndex => OperationKind::CloseIndex,
+        }
+    }
+}
+
+/// An index change, checked against the cluster before anyone is asked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexPlan {
+    pub index: String,
+    pub action: IndexAction,
+    /// Documents in the index.
+    pub total: u64,
+    /// Documents the change removes or makes unreadable.
+    pub affected: u64,
+    /// Snapshots the affected documents could be restored from.
+    pub snapshots: Vec<String>,
+}
+
+impl IndexPlan {
+    pub fn to_destruction_plan(&self) -> DestructionPlan {
+        let mut plan = DestructionPlan::new(self.action.operation(), &self.index);
+        plan = match &self.action {
+            IndexAction::DeleteIndex => plan.item(format!("index {} and all {} documents", self.index, self.total)),
+            IndexAction::DeleteByQuery(query) => {
+                let plan = plan.item(format!("{} of {} documents matching `{}`", self.affected, self.total, query));
+                if self.affected == self.total && self.total > 0 {
+                    plan.item("the query matches every document in the index".to_string())
+                } else {
+                    plan
+                }
+            }
+            IndexAction::CloseIndex => {
+                plan.item(format!("{} documents unreadable and unwritable until reopened", self.affected))
+            }
+        };
+        plan = match self.snapshots.first() {
+            Some(snapshot) => plan.item(format!("restorable from snapshot {}", snapshot)),
+            None => plan.item("no snapshot covers this index".to_string()),
+        };
+        match (&self.action, self.snapshots.first()) {
+            (IndexAction::CloseIndex, _) => plan.costs.push(RecoveryCost::Effort {
+                what: format!("reopen {}; searches and writes against it fail until then", self.index),
+                hours: 0.1,
+            }),
+            (_, Some(snapshot)) => plan.costs.push(RecoveryCost::Effort {
+                what: format!("restore {} from {}, losing writes made since", self.index, snapshot),
+                hours: 1.0 + self.affected as f64 / 10_000_000.0,
+            }),
+            (_, None) if self.affected > 0 => plan.costs.push(RecoveryCost::NotRecoverable(format!(
+                "{} documents in {}; no snapshot covers them",
+                self.affected, self.index
+            ))),
+            (_, None) => {}
+        }
+        plan
+    }
+}
+
+/// Consent to apply exactly one index plan.
+pub struct IndexApproval {
+    plan: IndexPlan,
+    _consent: UserConsent<IndexChange>,
+}
+
+/// A search cluster.
+pub struct Cluster<State = Protected> {
+    backend: Box<dyn SearchBackend>,
+    _state: PhantomData<State>,
+}
+
+impl Cluster<Protected> {
+    pub fn connect(backend: Box<dyn SearchBackend>) -> Self {
+        Cluster {
+            backend,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn search(&self, index: &str, query: &str) -> Vec<(String, String)> {
+        self.backend.search(index, query)
+    }
+
+    pub fn count(&self, index: &str, query: &str) -> u64 {
+        self.backend.count(index, query)
+    }
+
+    pub fn index_document(&mut self, index: &str, id: &str, body: &str) {
+        self.backend.index_document(index, id, body);
+    }
+
+    /// Check a destructive change against the cluster: how many
+    /// documents it touches and whether a snapshot covers them.
+    pub fn plan(&self, index: &str, action: IndexAction) -> Result<IndexPlan, String> {
+        if !self.backend.indices().iter().any(|i| i == index) {
+            return Err(format!("no index named {}", index));
+        }
+        let total = self.backend.count(index, "*");
+        let affected = match &action {
+            IndexAction::DeleteByQuery(query) => self.backend.count(index, query),
+            IndexAction::DeleteIndex | IndexAction::CloseIndex => total,
+        };
+        Ok(IndexPlan {
+            index: index.to_string(),
+            action,
+            total,
+            affected,
+            snapshots: self.backend.snapshots_covering(index),
+        })
+    }
+
+    fn check(&self, approval: &IndexApproval, expected: fn(&IndexAction) -> bool) -> Result<(), String> {
+        if !expected(&approval.plan.action) {
+            return Err(format!("approval is not for {}", approval.plan.action.operation().name()));
+        }
+        let current = self.plan(&approval.plan.index, approval.plan.action.clone())?;
+        if current != approval.plan {
+            return Err("index changed since the plan was approved".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn delete_index(&mut self, approval: IndexApproval) -> Result<String, String> {
+        self.check(&approval, |a| *a == IndexAction::DeleteIndex)?;
+        self.backend.delete_index(&approval.plan.index);
+        Ok(format!("deleted index {} ({} documents)", approval.plan.index, approval.plan.total))
+    }
+
+    pub fn delete_by_query(&mut self, approval: IndexApproval) -> Result<String, String> {
+        self.check(&approval, |a| matches!(a, IndexAction::DeleteByQuery(_)))?;
+        let IndexAction::DeleteByQuery(query) = &approval.plan.action else {
+            unreachable!("checked above");
+        };
+        let deleted = self.backend.delete_by_query(&approval.plan.index, query);
+        Ok(format!("deleted {} documents from {}", deleted, approval.plan.index))
+    }
+
+    pub fn close_index(&mut self, approval: IndexApproval) -> Result<String, String> {
+        self.check(&approval, |a| *a == IndexAction::CloseIndex)?;
+        if !self.backend.is_open(&approval.plan.index) {
+            return Err(format!("{} is already closed", approval.plan.index));
+        }
+        self.backend.close_index(&approval.plan.index);
+        Ok(format!("closed index {}", approval.plan.index))
+    }
+}
+
+impl SafetyGate {
+    /// Request consent for an index plan. A plan no snapshot can undo
+    /// needs the typed acknowledgment.
+    pub fn request_index_consent(
+        &mut self,
+        plan: &IndexPlan,
+    ) -> Result<IndexApproval, ConsentDenied> {
+        let consent = self.plan_consent(&plan.to_destruction_plan())?;
+        Ok(IndexApproval {
+            plan: plan.clone(),
+            _consent: consent,
+        })
+    }
+}
@@ -0,0 +1,150 @@
+//! Session-typed ceremonies: remove protection, operate, restore.
+//!
+//! ```no_run
+//! # use safe_operations::{session, Refused, Repository, UserConsent};
+//! # fn ceremony(repo: Repository, unprotect: UserConsent, push: UserConsent) -> Result<(), Refused<Repository>> {
+//! let restored = session::lifted(repo, unprotect, |lifted| {
+//!     match lifted.force_push(push) {
+//!         Ok(pushed) => pushed.restore(),
+//!         Err(stalled) => stalled.session.restore(),
+//!     }
+//! })?;
+//! # let _ = restored;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each step consumes the session and returns a type that offers only the
+//! legal next steps. [`lifted`] takes the ceremony's body as a closure that
+//! must return [`Restored`], and only `restore` makes one, so orchestration
+//! that forgets to re-protect the repository — on the success path or the
+//! failure path — does not compile. A bare [`Repository`]'s
+//! `restore_protection` is a method someone has to remember to call; here
+//! the return type remembers for them.
+//!
+//! Steps that consume the repository (`filter_repo`, `reset_hard`) leave
+//! nothing to restore, and are not session steps.
+
+use super::{Protected, Refused, Repository, SafetyError, Unprotected, UserConsent};
+
+/// Protection is off and nothing has been done yet.
+pub struct Lifted {
+    repo: Repository<Unprotected>,
+    transcript: Vec<String>,
+}
+
+/// Protection is off and at least one push went through.
+pub struct Pushed {
+    repo: Repository<Unprotected>,
+    transcript: Vec<String>,
+}
+
+/// A step was refused, or failed. The session is handed back in the
+/// state it was in, so it can still be restored.
+pub struct Stalled<S> {
+    pub session: Box<S>,
+    pub error: SafetyError,
+}
+
+/// The terminal state: protection is back on. Only `restore` makes one.
+pub struct Restored {
+    repo: Repository<Protected>,
+    transcript: Vec<String>,
+}
+
+/// Run a ceremony: remove protection with `consent`, hand the session
+/// to `body`, and return what it restored. A refused removal hands the
+/// repository back untouched and `body` never runs.
+pub fn lifted<F>(repo: Repository<Protected>, consent: UserConsent, body: F) -> Result<Restored, Refused<Repository<Protected>>>
+where
+    F: FnOnce(Lifted) -> Restored,
+{
+    let repo = repo.remove_protection(consent)?;
+    Ok(body(Lifted {
+        transcript: vec![format!("[{}] protection removed", repo.name)],
+        repo,
+    }))
+}
+
+fn push(
+    repo: &Repository<Unprotected>,
+    transcript: &mut Vec<String>,
+    branch: Option<&str>,
+    consent: UserConsent,
+) -> Result<(), SafetyError> {
+    let line = match branch {
+        Some(branch) => repo.force_push_branch(branch, consent)?,
+        None => repo.force_push(consent)?,
+    };
+    transcript.push(line);
+    Ok(())
+}
+
+impl Lifted {
+    pub fn force_push(mut self, consent: UserConsent) -> Result<Pushed, Stalled<Lifted>> {
+        match push(&self.repo, &mut self.transcript, None, consent) {
+            Ok(()) => Ok(Pushed {
+                repo: self.repo,
+                transcript: self.transcript,
+            }),
+            Err(error) => Err(Stalled {
+                session: Box::new(self),
+                error,
+            }),
+        }
+    }
+
+    pub fn force_push_branch(mut self, branch: &str, consent: UserConsent) -> Result<Pushed, Stalled<Lifted>> {
+        match push(&self.repo, &mut self.transcript, Some(branch), consent) {
+            Ok(()) => Ok(Pushed {
+                repo: self.repo,
+                transcript: self.transcript,
+            }),
+            Err(error) => Err(Stalled {
+                session: Box::new(self),
+                error,
+            }),
+        }
+    }
+
+    /// Put protection back without having done anything.
+    pub fn restore(self) -> Restored {
+        restore(self.repo, self.transcript)
+    }
+}
+
+impl Pushed {
+    /// Push to another branch. Each push spends its own consent.
+    pub fn force_push_branch(mut self, branch: &str, consent: UserConsent) -> Result<Pushed, Stalled<Pushed>> {
+        match push(&self.repo, &mut self.transcript, Some(branch), consent) {
+            Ok(()) => Ok(self),
+            Err(error) => Err(Stalled {
+                session: Box::new(self),
+                error,
+            }),
+        }
+    }
+
+    pub fn restore(self) -> Restored {
+        restore(self.repo, self.transcript)
+    }
+}
+
+fn restore(repo: Repository<Unprotected>, mut transcript: Vec<String>) -> Restored {
+    transcript.push(format!("[{}] protection restored", repo.name));
+    Restored {
+        repo: repo.restore_protection(),
+        transcript,
+    }
+}
+
+impl Restored {
+    /// What the ceremony did, in order, ending with the restore.
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    pub fn into_repository(self) -> Repository<Protected> {
+        self.repo
+    }
+}
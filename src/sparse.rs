@@ -0,0 +1,109 @@
+//! Sparse-checkout scoping for monorepos.
+//!
+//! An agent asked to fix a bug in `services/billing` has no business
+//! committing to `infra/terraform`. A handle scoped to a cone refuses any
+//! commit that touches a file outside it, and the cone goes into the
+//! session record so a reviewer knows what the agent was allowed to reach.
+
+use super::{AgentHandle, FileChange, ManifestError, SafetyGate};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A set of directories, with git's cone-mode semantics: everything
+/// under each directory, plus the files at the repository root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseCone {
+    dirs: Vec<String>,
+}
+
+impl SparseCone {
+    pub fn new(dirs: &[&str]) -> Self {
+        SparseCone {
+            dirs: dirs.iter().map(|d| d.trim_matches('/').to_string()).collect(),
+        }
+    }
+
+    /// Read the cone from an existing `.git/info/sparse-checkout` written
+    /// in cone mode. Only the recursive `/dir/` patterns define the cone;
+    /// the `/*` and `!/*/` parent patterns git adds are skipped.
+    pub fn from_sparse_checkout(git_dir: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(git_dir.join("info").join("sparse-checkout"))?;
+        let mut dirs: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with('/') && l.ends_with('/') && !l.ends_with("*/"))
+            .map(|l| l.trim_matches('/').to_string())
+            .collect();
+        // A parent listed only to reach a deeper directory is not itself in the cone.
+        let all = dirs.clone();
+        dirs.retain(|d| !all.iter().any(|o| o != d && o.starts_with(&format!("{}/", d))));
+        Ok(SparseCone { dirs })
+    }
+
+    pub fn dirs(&self) -> &[String] {
+        &self.dirs
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/');
+        !path.contains('/')
+            || self.dirs.iter().any(|d| path.starts_with(&format!("{}/", d)))
+    }
+
+    /// The command that materializes this cone in a working tree.
+    pub fn checkout_command(&self) -> String {
+        format!("git sparse-checkout set --cone {}", self.dirs.join(" "))
+    }
+}
+
+impl<'r> AgentHandle<'r> {
+    /// Confine the handle to `cone`. Scoping only ever narrows: a handle
+    /// already scoped keeps the directories both cones share.
+    pub fn scoped_to(mut self, cone: SparseCone) -> Self {
+        self.cone = Some(match self.cone.take() {
+            None => cone,
+            Some(existing) => SparseCone {
+                dirs: cone.dirs.into_iter().filter(|d| existing.dirs.contains(d)).collect(),
+            },
+        });
+        self
+    }
+
+    pub fn cone(&self) -> Option<&SparseCone> {
+        self.cone.as_ref()
+    }
+
+    /// Commit file changes, refusing the whole commit if any file lies
+    /// outside the handle's cone.
+    pub fn commit_changes(&self, message: &str, changes: &[FileChange]) -> Result<String, ManifestError> {
+        self.require(super::Capability::Commit)?;
+        if let Some(cone) = &self.cone {
+            let outside: Vec<String> = changes
+                .iter()
+                .filter(|c| !cone.contains(&c.path))
+                .map(|c| c.path.clone())
+                .collect();
+            if !outside.is_empty() {
+                return Err(ManifestError::OutOfScope(outside));
+            }
+        }
+        // Pipeline files need their own scoped consent, which a handle
+        // cannot hold; the repository refuses them here as it does everywhere.
+        self.repo.commit_changes(message, changes).map_err(|_| {
+            ManifestError::NotPermitted(super::Capability::Request(super::OperationKind::CiConfigChange))
+        })
+    }
+}
+
+impl SafetyGate {
+    /// Record the handle's scope in the session metadata and audit log.
+    pub fn record_scope(&mut self, handle: &AgentHandle) {
+        let dirs = match handle.cone() {
+            Some(cone) => cone.dirs.clone(),
+            None => vec!["*".to_string()],
+        };
+        self.log(format!("SCOPE: agent '{}' confined to [{}]", handle.agent(), dirs.join(", ")));
+        self.session.scopes.push((handle.agent().to_string(), dirs));
+    }
+}
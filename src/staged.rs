@@ -0,0 +1,179 @@
+//! Two-phase execute/confirm for pushes to shared remotes.
+//!
+//! An approved force-push was approved from a description. Phase one
+//! pushes the result to a quarantine ref, `refs/pending/<branch>/<id>`,
+//! where reviewers can fetch and read the actual history. Phase two needs
+//! a second, lightweight confirmation — typing the short SHA of the commit
+//! that will land — and then moves the target ref and deletes the pending
+//! ref in one atomic push, with a lease on the target so a push by someone
+//! else in between fails instead of being overwritten.
+
+use super::approver::Line;
+use super::scoped_env::{self, ScopedToken};
+use super::{random_u64, ConsentDenied, OperationKind, SafetyGate};
+
+/// Run git in the push's scoped environment, with its credential and
+/// nothing else from ours.
+fn git(repo_path: &str, args: &[&str], token: &ScopedToken) -> Result<String, String> {
+    let out = scoped_env::command("git", OperationKind::ForcePush, Some(token))?
+        .arg("-C")
+        .arg(repo_path)
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// A push waiting in quarantine.
+#[derive(Debug)]
+pub struct PendingPush {
+    pub repo_path: String,
+    pub remote: String,
+    /// The ref the push is for, e.g. `refs/heads/main`.
+    pub target: String,
+    pub pending_ref: String,
+    pub new_sha: String,
+    /// What `target` pointed at when staged; `None` if it did not exist.
+    pub old_sha: Option<String>,
+    /// The credential every phase pushes with.
+    token: ScopedToken,
+}
+
+impl PendingPush {
+    /// Phase one: push `local` to a quarantine ref on `remote`. The
+    /// target ref is not touched.
+    ///
+    /// `token` is the credential the gate minted from force-push consent
+    /// for this repository; see [`SafetyGate::mint_credential`].
+    pub fn stage(
+        repo_path: &str,
+        remote: &str,
+        local: &str,
+        target: &str,
+        token: ScopedToken,
+    ) -> Result<Self, String> {
+        if token.repo != repo_path {
+            return Err(format!("token was minted for '{}', not '{}'", token.repo, repo_path));
+        }
+        let new_sha = git(repo_path, &["rev-parse", &format!("{}^{{commit}}", local)], &token)?;
+        let old_sha = git(repo_path, &["ls-remote", remote, target], &token)?
+            .lines()
+            .find(|l| l.ends_with(&format!("\t{}", target)))
+            .and_then(|l| l.split('\t').next())
+            .map(str::to_string);
+        let branch = target.strip_prefix("refs/heads/").unwrap_or(target);
+        let pending_ref = format!("refs/pending/{}/{:08x}", branch, random_u64() as u32);
+        git(repo_path, &["push", remote, &format!("{}:{}", new_sha, pending_ref)], &token)?;
+        Ok(PendingPush {
+            repo_path: repo_path.to_string(),
+            remote: remote.to_string(),
+            target: target.to_string(),
+            pending_ref,
+            new_sha,
+            old_sha,
+            token,
+        })
+    }
+
+    pub fn short_sha(&self) -> &str {
+        &self.new_sha[..self.new_sha.len().min(12)]
+    }
+
+    /// How a reviewer reads what would land.
+    pub fn review_command(&self) -> String {
+        let range = match &self.old_sha {
+            Some(old) => format!("{}..{}", &old[..old.len().min(12)], self.short_sha()),
+            None => self.short_sha().to_string(),
+        };
+        format!("git fetch {} {} && git log --stat {}", self.remote, self.pending_ref, range)
+    }
+
+    /// The commits that would land, one line each, newest first.
+    pub fn commits(&self) -> Result<Vec<String>, String> {
+        let range = match &self.old_sha {
+            Some(old) => format!("{}..{}", old, self.new_sha),
+            None => self.new_sha.clone(),
+        };
+        Ok(git(&self.repo_path, &["log", "--oneline", &range], &self.token)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Phase two: move the target and delete the pending ref in one
+    /// atomic push, leased on the target's value at staging time. On
+    /// failure nothing moved, and the push can be abandoned or restaged.
+    pub fn promote(&self, confirmation: PendingConfirmation) -> Result<String, String> {
+        if confirmation.pending_ref != self.pending_ref {
+            return Err("confirmation is for a different pending push".to_string());
+        }
+        let lease = format!("--force-with-lease={}:{}", self.target, self.old_sha.as_deref().unwrap_or(""));
+        let result = git(
+            &self.repo_path,
+            &[
+                "push",
+                "--atomic",
+                &lease,
+                &self.remote,
+                &format!("+{}:{}", self.new_sha, self.target),
+                &format!(":{}", self.pending_ref),
+            ],
+            &self.token,
+        );
+        match result {
+            Ok(_) => Ok(format!("{} -> {} on {}", self.target, self.short_sha(), self.remote)),
+            Err(why) => Err(format!("promotion refused, {} unchanged: {}", self.target, why)),
+        }
+    }
+
+    /// Withdraw the push. Needs no confirmation: it only removes the
+    /// quarantine ref.
+    pub fn abandon(self) -> Result<(), String> {
+        git(&self.repo_path, &["push", &self.remote, &format!(":{}", self.pending_ref)], &self.token).map(|_| ())
+    }
+}
+
+/// The second confirmation, bound to one pending push.
+pub struct PendingConfirmation {
+    pending_ref: String,
+}
+
+impl SafetyGate {
+    /// Show the commits waiting in quarantine and accept the push only
+    /// if the approver types the short SHA that will land.
+    pub fn confirm_pending(
+        &mut self,
+        pending: &PendingPush,
+        typed: &str,
+    ) -> Result<PendingConfirmation, ConsentDenied> {
+        self.show_line(Line::Heading(format!(
+            "Pending push to {} on {} ({}):",
+            pending.target, pending.remote, pending.pending_ref
+        )));
+        match pending.commits() {
+            Ok(commits) => commits.into_iter().for_each(|c| self.show_line(Line::Item(c))),
+            Err(why) => self.show_line(Line::Warning(format!("cannot list commits: {}", why))),
+        }
+        self.show_line(Line::Detail(format!("Review: {}", pending.review_command())));
+        self.show_line(Line::Challenge(pending.short_sha().to_string()));
+        if typed.trim() != pending.short_sha() {
+            self.log(format!("PENDING NOT CONFIRMED: {} ({})", pending.pending_ref, pending.target));
+            return Err(ConsentDenied::new(
+                OperationKind::ForcePush,
+                format!("typed confirmation does not match {}", pending.short_sha()),
+            ));
+        }
+        self.log(format!(
+            "PENDING CONFIRMED: {} -> {} ({})",
+            pending.pending_ref,
+            pending.target,
+            pending.short_sha()
+        ));
+        Ok(PendingConfirmation {
+            pending_ref: pending.pending_ref.clone(),
+        })
+    }
+}
@@ -0,0 +1,33 @@
+//! Typestate markers — protection is a type, not a flag.
+
+use core::marker::PhantomData;
+
+/// A repository with branch protection enabled. This is the default state.
+/// Force-push, filter-repo, and other destructive operations are not
+/// available on this type. They do not exist in the impl block. The agent
+/// cannot call what does not exist.
+pub struct Protected;
+
+/// A repository with branch protection explicitly removed by a human.
+/// Destructive operations are available on this type — but only this type.
+/// The only way to reach this state is through `remove_protection()`,
+/// which requires `UserConsent`.
+pub struct Unprotected;
+
+/// A repository an agent may look at but not change: status and diff,
+/// no commit, no push. This is what an agent is handed by default.
+/// Commit access is a separate, explicit grant — see
+/// [`Repository::grant_commit`].
+pub struct ReadOnly;
+
+/// A protected repository whose working tree has uncommitted changes.
+/// Nothing that consumes the repository is available until the work is
+/// committed or explicitly stashed: `remove_protection()` does not exist
+/// here, so neither does any path to `reset_hard()` or `filter_repo()`.
+pub struct DirtyWorkingTree;
+
+/// A repository halfway through a rebase that started in state `From`.
+/// Some commits are replayed and some are not, and the working tree may
+/// hold conflicts. Only finishing, aborting, and looking at the conflicts
+/// are available here; either way out returns the repository to `From`.
+pub struct RebaseInProgress<From = Protected>(PhantomData<From>);
@@ -0,0 +1,193 @@
+//! Submodule<State> — a repository inside a repository, protected on its own.
+
+use crate::{
+    backend, OperationDescriptor, OperationKind, Outcome, Protected,
+    Refused, Repository, SafetyError, Unprotected, UserConsent,
+};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::marker::PhantomData;
+
+/// A submodule as its superproject records it.
+#[derive(Clone, Debug)]
+pub struct SubmoduleInfo {
+    /// Where it is checked out, relative to the superproject.
+    pub path: String,
+    pub url: String,
+    /// The commit the superproject points it at.
+    pub pointer: String,
+    pub total_commits: usize,
+}
+
+/// One submodule of a repository, parameterized by its own protection
+/// state.
+///
+/// Moving a submodule pointer is a commit to the superproject; rewriting
+/// the submodule is an operation on another repository, with its own
+/// history and its own origin. An agent bumping `vendor/lib` to a new
+/// commit holds a `Submodule<Protected>`, which has no `force_push`, and
+/// consent for the superproject does not unprotect it: consent for a
+/// submodule names the submodule's path.
+pub struct Submodule<'r, State = Protected> {
+    repo_name: &'r str,
+    path: String,
+    checkout: String,
+    url: String,
+    pointer: String,
+    total_commits: usize,
+    backend: &'r dyn backend::GitBackend,
+    _state: PhantomData<State>,
+}
+
+impl<'r, State> Submodule<'r, State> {
+    pub(crate) fn of<R, B: backend::GitBackend>(repo: &'r Repository<R, B>, info: &SubmoduleInfo) -> Self {
+        Submodule {
+            repo_name: &repo.name,
+            path: info.path.clone(),
+            checkout: format!("{}/{}", repo.path.trim_end_matches('/'), info.path),
+            url: info.url.clone(),
+            pointer: info.pointer.clone(),
+            total_commits: info.total_commits,
+            backend: &repo.backend,
+            _state: PhantomData,
+        }
+    }
+
+    fn into_state<Next>(self) -> Submodule<'r, Next> {
+        Submodule {
+            repo_name: self.repo_name,
+            path: self.path,
+            checkout: self.checkout,
+            url: self.url,
+            pointer: self.pointer,
+            total_commits: self.total_commits,
+            backend: self.backend,
+            _state: PhantomData,
+        }
+    }
+
+    fn target(&self) -> backend::Target<'_> {
+        backend::Target {
+            name: &self.path,
+            path: &self.checkout,
+            total_commits: self.total_commits,
+            head: "",
+            branches: &[],
+            uncommitted: &[],
+        }
+    }
+
+    /// Its path in the superproject, which consent for it names.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Where it is checked out, which consent for it may name instead.
+    pub fn checkout(&self) -> &str {
+        &self.checkout
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The commit the superproject points it at.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// The submodule as a repository of its own, for the gate to bind
+    /// consent to. Read-only: it is never handed out.
+    #[cfg(feature = "std")]
+    pub(crate) fn as_repository(&self) -> Repository<crate::ReadOnly> {
+        Repository::protected(&self.path, &self.checkout, self.total_commits).into_state()
+    }
+}
+
+impl<'r> Submodule<'r, Protected> {
+    pub fn status(&self) -> String {
+        format!("{}/{}: at {}, protected", self.repo_name, self.path, self.pointer)
+    }
+
+    /// Point the superproject at `commit`. Safe: it is a commit to the
+    /// superproject, and the submodule's history is untouched.
+    pub fn update_pointer(&mut self, commit: &str) -> String {
+        let previous = core::mem::replace(&mut self.pointer, commit.to_string());
+        format!("[{}] submodule '{}' {} -> {}", self.repo_name, self.path, previous, commit)
+    }
+
+    /// Regular push of the submodule is safe. No consent required.
+    pub fn push(&self) -> Result<String, Outcome> {
+        self.backend.push(&self.target(), "main")
+    }
+
+    /// Remove this submodule's protection. Requires consent for the
+    /// submodule itself: consent for the superproject does not cover it.
+    pub fn remove_protection(self, consent: UserConsent) -> Result<Submodule<'r, Unprotected>, Refused<Self>> {
+        let expected = OperationDescriptor::RemoveProtection {
+            repo: self.checkout.clone(),
+        };
+        let checked = consent
+            .check_target(OperationKind::RemoveProtection, &self.path, &self.checkout)
+            .and_then(|()| consent.check_descriptor(&expected));
+        if let Err(denied) = checked {
+            return Err(Refused {
+                repo: Box::new(self),
+                denied,
+            });
+        }
+        consent.announce(&format!(
+            "Protection removed on submodule '{}' of '{}' with user approval.",
+            self.path, self.repo_name
+        ));
+        Ok(self.into_state())
+    }
+}
+
+impl<'r> Submodule<'r, Unprotected> {
+    /// Force-push the submodule's `main`. Requires consent for this
+    /// submodule and branch.
+    pub fn force_push(&self, consent: UserConsent) -> Result<String, SafetyError> {
+        let expected = OperationDescriptor::ForcePush {
+            repo: self.checkout.clone(),
+            branch: "main".to_string(),
+        };
+        consent.check_target(OperationKind::ForcePush, &self.path, &self.checkout)?;
+        consent.check_branch("main")?;
+        consent.check_descriptor(&expected)?;
+        consent.announce(&format!(
+            "Force-push to submodule '{}' of '{}' with user approval.",
+            self.path, self.repo_name
+        ));
+        if consent.is_observe_only() {
+            return Ok(format!("[{}] force-push to origin/main simulated", self.path));
+        }
+        backend::run_to_end(OperationKind::ForcePush, &self.target(), || {
+            let options = backend::ExecOptions::default();
+            self.backend.force_push(&self.target(), "main", &options, &mut backend::NoProgress)
+        })
+    }
+
+    /// Hard reset the submodule's checkout, discarding its uncommitted
+    /// work. Requires consent for this submodule.
+    pub fn reset_hard(&self, consent: UserConsent) -> Result<String, SafetyError> {
+        let expected = OperationDescriptor::ResetHard {
+            repo: self.checkout.clone(),
+        };
+        consent.check_target(OperationKind::ResetHard, &self.path, &self.checkout)?;
+        consent.check_descriptor(&expected)?;
+        consent.announce(&format!(
+            "Hard reset on submodule '{}' of '{}' with user approval.",
+            self.path, self.repo_name
+        ));
+        backend::run_to_end(OperationKind::ResetHard, &self.target(), || self.backend.reset_hard(&self.target()))?;
+        Ok(format!("[{}] reset to {}", self.path, self.pointer))
+    }
+
+    /// Put this submodule's protection back. Always safe.
+    pub fn restore_protection(self) -> Submodule<'r, Protected> {
+        say!("  [OK] Protection restored on submodule '{}' of '{}'.", self.path, self.repo_name);
+        self.into_state()
+    }
+}
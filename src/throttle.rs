@@ -0,0 +1,185 @@
+//! Rate limits on consent requests, per severity class.
+//!
+//! An agent that asks fifty times is betting that one of the fifty
+//! prompts gets typed back without being read. Each severity gets a cap
+//! on the prompts it may put to the approver in a window. The request
+//! that would exceed it is refused and the class cools down, for twice as
+//! long each time it trips again; a full window without tripping forgives
+//! the record. Only requests that would reach a human count: what the
+//! policy allows outright, or denies outright, asks no one.
+
+use super::{ConsentDenied, OperationKind, SafetyGate, Severity};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// At most `max` prompts for operations of `severity` per `window`
+/// seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limit {
+    pub severity: Severity,
+    pub max: usize,
+    pub window: u64,
+}
+
+/// One severity class's recent prompts and record of tripping.
+#[derive(Clone, Debug, Default)]
+struct Class {
+    asked: Vec<u64>,
+    strikes: u32,
+    cooling_until: u64,
+}
+
+/// Whether a request may go to the approver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    Admitted,
+    /// The class is still cooling down from an earlier trip.
+    Cooling { until: u64 },
+    /// This request tripped the limit; the class now cools down.
+    Tripped { limit: Limit, strike: u32, until: u64 },
+}
+
+/// The limits a gate enforces, and what it has seen against them.
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    limits: Vec<Limit>,
+    /// The first cooldown, in seconds. Each strike doubles it.
+    cooldown: u64,
+    classes: BTreeMap<Severity, Class>,
+}
+
+impl Default for Throttle {
+    /// Ten-minute windows: three Critical prompts, six High, twelve
+    /// Medium. Two minutes' cooldown to start.
+    fn default() -> Self {
+        Throttle::new(Duration::from_secs(120))
+            .limit(Severity::Critical, 3, Duration::from_secs(600))
+            .limit(Severity::High, 6, Duration::from_secs(600))
+            .limit(Severity::Medium, 12, Duration::from_secs(600))
+    }
+}
+
+impl Throttle {
+    /// No limits yet, and `cooldown` as the first penalty.
+    pub fn new(cooldown: Duration) -> Self {
+        Throttle {
+            limits: Vec::new(),
+            cooldown: cooldown.as_secs().max(1),
+            classes: BTreeMap::new(),
+        }
+    }
+
+    /// Allow at most `max` prompts of `severity` per `window`,
+    /// replacing any limit it had.
+    pub fn limit(mut self, severity: Severity, max: usize, window: Duration) -> Self {
+        self.limits.retain(|l| l.severity != severity);
+        self.limits.push(Limit {
+            severity,
+            max,
+            window: window.as_secs().max(1),
+        });
+        self
+    }
+
+    pub fn limits(&self) -> &[Limit] {
+        &self.limits
+    }
+
+    /// Count a prompt of `severity` at `now`, if it is admitted.
+    pub fn admit(&mut self, severity: Severity, now: u64) -> Admission {
+        let Some(limit) = self.limits.iter().find(|l| l.severity == severity).copied() else {
+            return Admission::Admitted;
+        };
+        let class = self.classes.entry(severity).or_default();
+        if now < class.cooling_until {
+            return Admission::Cooling {
+                until: class.cooling_until,
+            };
+        }
+        if class.strikes > 0 && now >= class.cooling_until.saturating_add(limit.window) {
+            class.strikes = 0;
+        }
+        class.asked.retain(|&at| now.saturating_sub(at) < limit.window);
+        if class.asked.len() < limit.max {
+            class.asked.push(now);
+            return Admission::Admitted;
+        }
+        class.strikes += 1;
+        class.asked.clear();
+        class.cooling_until = now.saturating_add(self.cooldown.saturating_mul(1 << (class.strikes - 1).min(16)));
+        Admission::Tripped {
+            limit,
+            strike: class.strikes,
+            until: class.cooling_until,
+        }
+    }
+}
+
+impl SafetyGate {
+    /// Enforce `throttle`'s limits from now on, in place of the
+    /// defaults. Counts so far start over.
+    pub fn set_throttle(&mut self, throttle: Throttle) {
+        for limit in &throttle.limits {
+            self.log(format!(
+                "THROTTLE SET: at most {} {}-severity prompts per {}s, {}s cooldown doubling per trip",
+                limit.max,
+                limit.severity.label(),
+                limit.window,
+                throttle.cooldown
+            ));
+        }
+        self.throttle = throttle;
+    }
+
+    /// Refuse a request that would put one prompt too many of
+    /// `severity` to the approver.
+    pub(crate) fn check_throttle(
+        &mut self,
+        operation: OperationKind,
+        severity: Severity,
+        operation_description: &str,
+    ) -> Result<(), ConsentDenied> {
+        let now = super::unix_now();
+        let reason = match self.throttle.admit(severity, now) {
+            Admission::Admitted => return Ok(()),
+            Admission::Cooling { until } => {
+                self.log(format!("THROTTLED (cooling down until {}): {}", super::report::format_utc(until), operation_description));
+                format!(
+                    "too many {}-severity requests; cooling down for another {}s",
+                    severity.label(),
+                    until - now
+                )
+            }
+            Admission::Tripped { limit, strike, until } => {
+                self.log(format!(
+                    "THROTTLE TRIPPED: more than {} {}-severity prompts in {}s; cooling down {}s (strike {}): {}",
+                    limit.max,
+                    severity.label(),
+                    limit.window,
+                    until - now,
+                    strike,
+                    operation_description
+                ));
+                format!(
+                    "more than {} {}-severity requests in {}s; none will be put to the approver for {}s",
+                    limit.max,
+                    severity.label(),
+                    limit.window,
+                    until - now
+                )
+            }
+        };
+        Err(ConsentDenied::new(operation, reason))
+    }
+
+    /// [`check_throttle`](Self::check_throttle) for a request about to
+    /// be put to the approver outside [`mint`](SafetyGate::mint).
+    pub(crate) fn screen_throttle(&mut self, operation: OperationKind, operation_description: &str) -> Result<(), ConsentDenied> {
+        let (severity, _) = self.severity_for(operation, self.target.as_deref(), self.target_commits);
+        let screened = self.check_throttle(operation, severity, operation_description);
+        if let Err(denied) = &screened {
+            self.notify_denial(denied);
+        }
+        screened
+    }
+}
@@ -0,0 +1,194 @@
+//! What consent takes at each severity.
+//!
+//! Without tiers every request is answered the same way, by typing back
+//! a challenge, whether it deletes a branch or rewrites a repository's
+//! whole history. A [`TierPolicy`] sets the ceremony per [`Severity`]: a
+//! plain `yes` for Low, the echoed challenge for the middle tiers, and for
+//! Critical — the catastrophic tier — a quorum of approvers and a touch of
+//! a hardware key. The ceremonies may only get stronger as severity rises.
+//!
+//! An operation's tier is its own severity unless the policy classifies
+//! it otherwise, except that `filter-repo` on a repository with more than
+//! 100 commits (or of unknown size) is always Critical: that is the
+//! rewrite there is no recovering from. A cooldown raises the prompt and
+//! the key touch to Critical too; whether a quorum is needed depends on
+//! the operation and the repository alone.
+
+use super::quorum::Rule;
+use super::{OperationKind, SafetyGate, Severity};
+
+/// What the approver has to do to consent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Ceremony {
+    /// Answer `yes`.
+    Prompt,
+    /// Type back the challenge the gate shows.
+    Echo,
+    /// `approvals` approvers each type back their own challenge, then
+    /// a hardware key is touched.
+    QuorumAndKey { approvals: usize },
+}
+
+impl Ceremony {
+    pub fn label(self) -> String {
+        match self {
+            Ceremony::Prompt => "prompt".to_string(),
+            Ceremony::Echo => "echo".to_string(),
+            Ceremony::QuorumAndKey { approvals } => format!("quorum of {} + hardware key", approvals),
+        }
+    }
+}
+
+const SEVERITIES: [Severity; 4] = [Severity::Low, Severity::Medium, Severity::High, Severity::Critical];
+
+/// The ceremony per severity, and which tier each operation is in.
+#[derive(Clone, Debug)]
+pub struct TierPolicy {
+    ceremonies: [Ceremony; 4],
+    classified: Vec<(OperationKind, Severity)>,
+    large_history: usize,
+}
+
+impl Default for TierPolicy {
+    fn default() -> Self {
+        TierPolicy {
+            ceremonies: [Ceremony::Echo; 4],
+            classified: Vec::new(),
+            large_history: 100,
+        }
+    }
+}
+
+impl TierPolicy {
+    /// Every tier answered by echoing the challenge, as without tiers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consent at `severity` takes `ceremony`.
+    pub fn require(mut self, severity: Severity, ceremony: Ceremony) -> Self {
+        self.ceremonies[severity as usize] = ceremony;
+        self
+    }
+
+    /// Put `operation` in the `severity` tier instead of its own.
+    pub fn classify(mut self, operation: OperationKind, severity: Severity) -> Self {
+        self.classified.retain(|(op, _)| *op != operation);
+        self.classified.push((operation, severity));
+        self
+    }
+
+    /// Hold `filter-repo` to Critical on repositories of more than
+    /// `commits` commits, instead of 100.
+    pub fn large_history(mut self, commits: usize) -> Self {
+        self.large_history = commits;
+        self
+    }
+
+    pub fn ceremony(&self, severity: Severity) -> Ceremony {
+        self.ceremonies[severity as usize]
+    }
+
+    /// The tier `operation` on a repository of `commits` commits is in.
+    pub fn tier(&self, operation: OperationKind, commits: Option<usize>) -> Severity {
+        if self.rewrites_large_history(operation, commits) {
+            return Severity::Critical;
+        }
+        self.classified
+            .iter()
+            .find(|(op, _)| *op == operation)
+            .map_or(operation.severity(), |(_, severity)| *severity)
+    }
+
+    /// The quorum `operation` on a repository of `commits` commits
+    /// needs, if its tier calls for one.
+    pub fn rule_for(&self, operation: OperationKind, commits: Option<usize>) -> Option<Rule> {
+        let Ceremony::QuorumAndKey { approvals } = self.ceremony(self.tier(operation, commits)) else {
+            return None;
+        };
+        let min_commits = match self.rewrites_large_history(operation, commits) {
+            true => self.large_history + 1,
+            false => 0,
+        };
+        Some(Rule {
+            operation,
+            min_commits,
+            required: approvals,
+        })
+    }
+
+    fn rewrites_large_history(&self, operation: OperationKind, commits: Option<usize>) -> bool {
+        operation == OperationKind::FilterRepo && commits.is_none_or(|n| n > self.large_history)
+    }
+
+    fn validate(&self, approvers: usize, has_key: bool) -> Result<(), String> {
+        for (i, severity) in SEVERITIES.iter().enumerate().skip(1) {
+            if self.ceremonies[i] < self.ceremonies[i - 1] {
+                return Err(format!(
+                    "{} asks for less ({}) than {} ({})",
+                    severity.label(),
+                    self.ceremonies[i].label(),
+                    SEVERITIES[i - 1].label(),
+                    self.ceremonies[i - 1].label()
+                ));
+            }
+        }
+        for (severity, ceremony) in SEVERITIES.iter().zip(self.ceremonies) {
+            let Ceremony::QuorumAndKey { approvals } = ceremony else {
+                continue;
+            };
+            if approvals < 2 {
+                return Err(format!("{}: a quorum of {} is single-party consent", severity.label(), approvals));
+            }
+            if approvals > approvers {
+                return Err(format!(
+                    "{}: {} approvals required, {} approvers configured with require_quorum",
+                    severity.label(),
+                    approvals,
+                    approvers
+                ));
+            }
+            if !has_key {
+                return Err(format!("{}: needs a hardware key touch, and no key is configured", severity.label()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SafetyGate {
+    /// Require consent as `tiers` sets out, from now on. Tiers that
+    /// weaken as severity rises, or ask for approvers or a key the
+    /// gate does not have, are rejected, and the gate keeps what it had.
+    pub fn set_tiers(&mut self, tiers: TierPolicy) -> Result<(), String> {
+        if let Err(why) = tiers.validate(self.quorum_approvers(), self.hardware_key.is_some()) {
+            self.log(format!("TIERS REJECTED: {}", why));
+            return Err(why);
+        }
+        let set: Vec<String> = SEVERITIES
+            .iter()
+            .zip(tiers.ceremonies)
+            .map(|(severity, ceremony)| format!("{} {}", severity.label(), ceremony.label()))
+            .collect();
+        self.log(format!("TIERS SET: {}; filter-repo over {} commits is Critical", set.join(", "), tiers.large_history));
+        for (operation, severity) in &tiers.classified {
+            self.log(format!("TIER: {} classified {}", operation.name(), severity.label()));
+        }
+        self.tiers = Some(tiers);
+        Ok(())
+    }
+
+    /// The tier `operation` on a repository of `commits` commits is in.
+    pub(crate) fn tier_of(&self, operation: OperationKind, commits: Option<usize>) -> Severity {
+        match &self.tiers {
+            Some(tiers) => tiers.tier(operation, commits),
+            None => operation.severity(),
+        }
+    }
+
+    /// The ceremony consent at `severity` takes; echoing the challenge
+    /// if no tiers are set.
+    pub(crate) fn ceremony(&self, severity: Severity) -> Ceremony {
+        self.tiers.as_ref().map_or(Ceremony::Echo, |t| t.ceremony(severity))
+    }
+}
@@ -0,0 +1,106 @@
+//! Consent that cannot be used until a delay has passed.
+//!
+//! An approver who said yes too quickly usually realizes it a few minutes
+//! later, by which time an agent has already acted. With a
+//! [`TimeLockGate`] set, consent to the operations it covers — by default
+//! the irreversible ones — is minted as usual but refused by every
+//! destructive method until the delay is over (and, like any consent
+//! presented, spent by the attempt). Until then the approver can
+//! list what is waiting ([`SafetyGate::time_locked`]) and cancel any of it
+//! ([`SafetyGate::cancel_time_lock`]), which revokes the consent for good.
+//!
+//! The consent's lifetime starts when the lock opens, not when it was
+//! granted, so a delay longer than the policy's `consent_ttl` does not
+//! leave nothing to use.
+
+use super::revocation::ConsentHandle;
+use super::tool_risk::Reversibility;
+use super::{approver, report, unix_now, OperationKind, SafetyGate};
+use std::time::Duration;
+
+/// Consent that is waiting out its delay.
+#[derive(Clone, Debug)]
+pub struct Locked {
+    pub handle: ConsentHandle,
+    /// In seconds since the Unix epoch.
+    pub usable_at: u64,
+}
+
+/// How long consent is held back, and for which operations.
+#[derive(Clone, Debug)]
+pub struct TimeLockGate {
+    delay: u64,
+    covers: Vec<OperationKind>,
+    locked: Vec<Locked>,
+}
+
+impl TimeLockGate {
+    /// Hold back consent to every irreversible operation for `delay`.
+    pub fn new(delay: Duration) -> Self {
+        TimeLockGate {
+            delay: delay.as_secs(),
+            covers: OperationKind::ALL
+                .iter()
+                .copied()
+                .filter(|op| op.reversibility() == Reversibility::Irreversible)
+                .collect(),
+            locked: Vec::new(),
+        }
+    }
+
+    /// Hold back consent to exactly `operations` instead.
+    pub fn covering(mut self, operations: &[OperationKind]) -> Self {
+        self.covers = operations.to_vec();
+        self
+    }
+
+    pub fn covers(&self, operation: OperationKind) -> bool {
+        self.covers.contains(&operation)
+    }
+}
+
+impl SafetyGate {
+    /// Time-lock consent to the operations `lock` covers, from now on.
+    pub fn set_time_lock(&mut self, lock: TimeLockGate) {
+        let names: Vec<&str> = lock.covers.iter().map(|op| op.name()).collect();
+        self.log(format!("TIME LOCK: {}s on [{}]", lock.delay, names.join(", ")));
+        self.time_lock = Some(lock);
+    }
+
+    /// Consent still waiting out its delay, and not cancelled.
+    pub fn time_locked(&self) -> Vec<Locked> {
+        let now = unix_now();
+        self.time_lock
+            .iter()
+            .flat_map(|lock| &lock.locked)
+            .filter(|locked| locked.usable_at > now && !locked.handle.is_revoked())
+            .cloned()
+            .collect()
+    }
+
+    /// Cancel time-locked consent. It is refused from then on, whether
+    /// or not its delay is over.
+    pub fn cancel_time_lock(&mut self, handle: &ConsentHandle) {
+        handle.revoke();
+        self.log(format!("TIME LOCK CANCELLED: {}", handle.description));
+    }
+
+    /// When consent minted now for `operation` becomes usable: zero if
+    /// it is not time-locked.
+    pub(crate) fn time_lock(&mut self, operation: OperationKind, operation_description: &str, nonce: u64, issued_at: u64) -> u64 {
+        let Some(lock) = self.time_lock.as_mut().filter(|lock| lock.covers(operation)) else {
+            return 0;
+        };
+        let usable_at = issued_at.saturating_add(lock.delay);
+        let handle = ConsentHandle::new(nonce, operation, operation_description, self.revocations.clone());
+        lock.locked.retain(|locked| locked.usable_at > issued_at && !locked.handle.is_revoked());
+        lock.locked.push(Locked { handle, usable_at });
+        let until = report::format_utc(usable_at);
+        self.log(format!("TIME-LOCKED: {} until {}", operation_description, until));
+        self.show_line(approver::Line::Notice(format!(
+            "This consent cannot be used until {}. Until then you can cancel it.",
+            until
+        )));
+        usable_at
+    }
+}
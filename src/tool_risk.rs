@@ -0,0 +1,144 @@
+//! Risk annotations for agent tool descriptions, derived from the code.
+//!
+//! A hand-written "careful: destructive" in a tool description drifts from
+//! what the gate actually enforces. Here the statement is generated from
+//! the operation's severity, its reversibility, and what the enforced
+//! policy decides for it, and the tool entry is emitted in the shape an
+//! MCP `tools/list` response uses, including the standard
+//! `destructiveHint` / `readOnlyHint` annotations.
+
+use super::policy::{Decision, Policy};
+use super::{json_string, OperationKind};
+
+/// Whether an operation's effect can be undone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reversibility {
+    Reversible(&'static str),
+    /// Undoable only under the stated condition.
+    Recoverable(&'static str),
+    Irreversible,
+}
+
+impl OperationKind {
+    pub fn reversibility(self) -> Reversibility {
+        use Reversibility::*;
+        match self {
+            OperationKind::RemoveProtection => Reversible("restore protection"),
+            OperationKind::ForcePush => {
+                Recoverable("the overwritten commits survive only in other clones and reflogs")
+            }
+            OperationKind::FilterRepo => Irreversible,
+            OperationKind::ResetHard => Irreversible,
+            OperationKind::DeleteBranch => Recoverable("from the reflog, until it expires"),
+            OperationKind::ForceDeleteBranch => {
+                Recoverable("from the reflog, until it expires; its unmerged commits are nowhere else")
+            }
+            OperationKind::DeleteTag => Recoverable("re-tag the commit, if its id was recorded"),
+            OperationKind::RemoveRemote => Reversible("add the remote again and fetch"),
+            OperationKind::Rebase => Recoverable("from ORIG_HEAD or the reflog, until it expires"),
+            OperationKind::DropStash => Recoverable("from `git fsck --unreachable`, until garbage collection"),
+            OperationKind::StripAttribution => {
+                Recoverable("from the original commit, until the reflog expires; never once it is pushed over")
+            }
+            OperationKind::CiConfigChange => {
+                Recoverable("reverting the file does not undo what the pipeline already ran with its secrets")
+            }
+            OperationKind::DeleteFiles => Irreversible,
+            OperationKind::TransferOwnership => Recoverable("only if the new owner transfers it back"),
+            OperationKind::EditSchedule => Reversible("restore the previous crontab"),
+            OperationKind::DeleteImageTag => Recoverable("re-tag the digest before the registry collects it"),
+            OperationKind::OverwriteImageTag => Recoverable("move the tag back, if the old digest was recorded"),
+            OperationKind::DeleteImageRepository => Irreversible,
+            OperationKind::DeleteIndex | OperationKind::DeleteByQuery => Recoverable("only from a snapshot"),
+            OperationKind::CloseIndex => Reversible("reopen the index"),
+            OperationKind::DeleteTopic | OperationKind::PurgeQueue => Irreversible,
+            OperationKind::ResetConsumerOffsets => {
+                Recoverable("reset again, if the previous offsets were recorded and the messages are retained")
+            }
+            OperationKind::ChangeVisibility => Reversible("change the visibility back"),
+            OperationKind::MakePublic => Irreversible,
+            OperationKind::ChangeLicense => Recoverable("for future releases only; past grants stand"),
+            OperationKind::DeleteBackup => Irreversible,
+            OperationKind::ShortenRetention => {
+                Recoverable("restore the retention before the backups it would expire are collected")
+            }
+            OperationKind::DisableBackupSchedule => Reversible("enable the schedule again"),
+        }
+    }
+}
+
+/// The risk statement for `operation` under `policy`.
+pub fn risk_statement(operation: OperationKind, policy: &Policy) -> String {
+    let effect = match operation.reversibility() {
+        Reversibility::Reversible(how) => format!("Reversible: {}.", how),
+        Reversibility::Recoverable(how) => format!("Hard to undo: {}.", how),
+        Reversibility::Irreversible => "Irreversible: nothing can undo it.".to_string(),
+    };
+    let gate = match policy.decide(operation) {
+        Decision::Allow => "The safety gate allows it without asking a human.".to_string(),
+        Decision::RequireConsent => "A human must approve each call through the safety gate; \
+             the call blocks until they answer, and a refusal is final for this request."
+            .to_string(),
+        Decision::Deny => format!(
+            "Policy {} refuses it on every call. Do not call this tool. Instead: {}.",
+            policy.version,
+            operation.safe_alternative()
+        ),
+    };
+    format!("Risk: {} severity. {} {}", operation.severity().label(), effect, gate)
+}
+
+/// One tool the host exposes to the agent.
+#[derive(Clone, Debug)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    /// The gated operation the tool performs; `None` for read-only tools.
+    pub operation: Option<OperationKind>,
+}
+
+impl Tool {
+    /// The description with the generated risk statement appended.
+    pub fn annotated_description(&self, policy: &Policy) -> String {
+        match self.operation {
+            Some(operation) => format!("{}\n\n{}", self.description, risk_statement(operation, policy)),
+            None => self.description.clone(),
+        }
+    }
+
+    /// The tool as an MCP `tools/list` entry (without `inputSchema`,
+    /// which belongs to the host).
+    pub fn to_json(&self, policy: &Policy) -> String {
+        let destructive = self.operation.is_some();
+        let idempotent = matches!(
+            self.operation,
+            None | Some(OperationKind::RemoveProtection | OperationKind::CloseIndex)
+        );
+        format!(
+            "{{\"name\":{},\"description\":{},\"annotations\":{{\"readOnlyHint\":{},\"destructiveHint\":{},\"idempotentHint\":{}}}}}",
+            json_string(&self.name),
+            json_string(&self.annotated_description(policy)),
+            !destructive,
+            destructive,
+            idempotent
+        )
+    }
+}
+
+/// A tool for every gated operation, described from the code alone.
+pub fn operation_tools() -> Vec<Tool> {
+    OperationKind::ALL
+        .iter()
+        .map(|&operation| Tool {
+            name: operation.name().to_string(),
+            description: format!("Perform {}.", operation.name().replace('_', " ")),
+            operation: Some(operation),
+        })
+        .collect()
+}
+
+/// A `tools/list` result for `tools`.
+pub fn tools_list(tools: &[Tool], policy: &Policy) -> String {
+    let entries: Vec<String> = tools.iter().map(|t| t.to_json(policy)).collect();
+    format!("{{\"tools\":[{}]}}", entries.join(","))
+}
@@ -0,0 +1,372 @@
+//! Consent answered with a code from the approver's authenticator app.
+//!
+//! A printed challenge is shown on the machine the agent runs on, so
+//! typing it back proves only that something there read it. A time-based
+//! one-time password (RFC 6238) is computed on the approver's phone from a
+//! secret the agent never sees: with [`SafetyGate::set_totp`], the gate
+//! stops printing a challenge and asks for the current six-digit code
+//! instead.
+//!
+//! Codes are HMAC-SHA1 over 30-second steps, which is what authenticator
+//! apps generate by default. One step either side of now is accepted for
+//! clock drift, and each step's code is accepted once: a code seen on the
+//! terminal cannot be replayed for a second operation. The approver can
+//! still refuse with `no: <why>`.
+//!
+//! The secret is enrolled by scanning [`TotpSecret::otpauth_uri`] as a QR
+//! code. Keep it out of anything the agent can read.
+
+use super::{approver, unix_now, ConsentDenied, OperationKind, SafetyGate, Severity};
+use hmac::digest::block_api::BlockSizeUser;
+use hmac::digest::consts::{U20, U64};
+use hmac::digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Update};
+use hmac::{KeyInit, Mac, SimpleHmac};
+
+/// Seconds each code is valid for.
+pub const STEP: u64 = 30;
+/// Digits in a code.
+pub const DIGITS: u32 = 6;
+
+/// The shared secret, as raw bytes.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret(Vec<u8>);
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TotpSecret(<{} bytes>)", self.0.len())
+    }
+}
+
+impl TotpSecret {
+    pub fn new(bytes: &[u8]) -> Self {
+        TotpSecret(bytes.to_vec())
+    }
+
+    /// A secret as authenticator apps show it: base32, any case,
+    /// spaces and padding ignored.
+    pub fn from_base32(text: &str) -> Result<Self, String> {
+        let mut out = Vec::new();
+        let (mut acc, mut bits) = (0u64, 0);
+        for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+            let value = ALPHABET
+                .iter()
+                .position(|&a| a as char == c.to_ascii_uppercase())
+                .ok_or_else(|| format!("'{}' is not base32", c))? as u64;
+            acc = acc << 5 | value;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((acc >> bits) as u8);
+                acc &= (1 << bits) - 1;
+            }
+        }
+        if out.len() < 10 {
+            return Err(format!("a TOTP secret needs at least 80 bits; this one has {}", out.len() * 8));
+        }
+        Ok(TotpSecret(out))
+    }
+
+    pub fn to_base32(&self) -> String {
+        let mut out = String::new();
+        let (mut acc, mut bits) = (0u64, 0);
+        for &byte in &self.0 {
+            acc = acc << 8 | byte as u64;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                out.push(ALPHABET[(acc >> bits & 31) as usize] as char);
+            }
+            acc &= (1 << bits) - 1;
+        }
+        if bits > 0 {
+            out.push(ALPHABET[(acc << (5 - bits) & 31) as usize] as char);
+        }
+        out
+    }
+
+    /// The enrollment URI to show as a QR code.
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            escape(issuer),
+            escape(account),
+            self.to_base32(),
+            escape(issuer),
+            DIGITS,
+            STEP
+        )
+    }
+
+    /// The code for time step `counter`.
+    pub fn code(&self, counter: u64) -> String {
+        let mut mac = <SimpleHmac<Sha1> as KeyInit>::new_from_slice(&self.0).expect("HMAC takes any key length");
+        Mac::update(&mut mac, &counter.to_be_bytes());
+        let mac = mac.finalize().into_bytes();
+        let offset = (mac[19] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([mac[offset] & 0x7f, mac[offset + 1], mac[offset + 2], mac[offset + 3]]);
+        format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+    }
+}
+
+/// Checks codes against one enrolled secret.
+pub struct TotpVerifier {
+    secret: TotpSecret,
+    /// The authenticator, as the approver would recognise it.
+    label: String,
+    /// Steps either side of now that are also accepted.
+    skew: u64,
+    /// The step of the last code accepted. Codes for it, or earlier,
+    /// are refused.
+    last_step: Option<u64>,
+}
+
+impl TotpVerifier {
+    pub fn new(secret: TotpSecret, label: &str) -> Self {
+        TotpVerifier {
+            secret,
+            label: label.to_string(),
+            skew: 1,
+            last_step: None,
+        }
+    }
+
+    /// Accept codes up to `steps` either side of now. Zero accepts only
+    /// the current code.
+    pub fn with_skew(mut self, steps: u64) -> Self {
+        self.skew = steps;
+        self
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Check `typed` as the code at `now` (seconds since the epoch).
+    /// `Ok` carries the step it matched, which is then spent.
+    pub fn verify_at(&mut self, typed: &str, now: u64) -> Result<u64, String> {
+        let typed: String = typed.chars().filter(|c| !c.is_whitespace()).collect();
+        if typed.len() != DIGITS as usize || !typed.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("expected a {}-digit code from {}", DIGITS, self.label));
+        }
+        let now = now / STEP;
+        let matched = (now.saturating_sub(self.skew)..=now + self.skew)
+            .find(|&step| same(self.secret.code(step).as_bytes(), typed.as_bytes()));
+        match matched {
+            None => Err(format!("the code does not match {}", self.label)),
+            Some(step) if self.last_step.is_some_and(|last| step <= last) => {
+                Err("that code was already used; wait for the next one".to_string())
+            }
+            Some(step) => {
+                self.last_step = Some(step);
+                Ok(step)
+            }
+        }
+    }
+}
+
+impl SafetyGate {
+    /// Answer consent challenges with codes from `verifier`'s
+    /// authenticator instead of echoed phrases.
+    pub fn set_totp(&mut self, verifier: TotpVerifier) {
+        self.log(format!("TOTP: {}", verifier.label()));
+        self.totp = Some(verifier);
+    }
+
+    /// Ask the responder for the current code and check it.
+    pub(crate) fn totp_challenge(
+        &mut self,
+        severity: Severity,
+        operation: OperationKind,
+        operation_description: &str,
+        approver: &str,
+    ) -> Result<(), ConsentDenied> {
+        let label = self.totp.as_ref().map(|v| v.label().to_string()).unwrap_or_default();
+        self.show_line(approver::Line::Notice(format!("{} severity: {}", severity.label(), operation_description)));
+        self.show_line(approver::Line::Notice(format!("To confirm, enter the current code from {}", label)));
+        self.show_line(approver::Line::Detail("To refuse, type: no: <why>".to_string()));
+        let typed = self.responder.answer(&approver::Prompt::code(&format!("{}-digit code from {}", DIGITS, label)));
+        // The code is spent once checked; the log says only that one was typed.
+        self.capture(&format!("> {}", typed.as_deref().map(|_| "******").unwrap_or("")));
+        let Some(typed) = typed else {
+            self.log(format!("DENIED (totp): {}: no code entered", operation_description));
+            return Err(ConsentDenied::new(operation, "no code entered; no answer is not a yes".to_string()));
+        };
+        if let Some(why) = approver::refusal(&typed) {
+            self.log(format!("DENIED BY APPROVER {}: {} ({})", approver, operation_description, why));
+            return Err(ConsentDenied::new(operation, format!("the approver declined: {}", why)).by_approver(approver));
+        }
+        let checked = match self.totp.as_mut() {
+            Some(verifier) => verifier.verify_at(&typed, unix_now()),
+            None => Err("no authenticator is configured".to_string()),
+        };
+        match checked {
+            Ok(step) => {
+                self.log(format!("TOTP VERIFIED: {} (step {})", operation_description, step));
+                Ok(())
+            }
+            Err(why) => {
+                self.log(format!("DENIED (totp): {}: {}", operation_description, why));
+                Err(ConsentDenied::new(operation, why))
+            }
+        }
+    }
+}
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Compare without stopping at the first difference.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn escape(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// SHA-1, which RFC 6238 and every authenticator app use for codes, as
+/// the digest [`SimpleHmac`] runs over. No SHA-1 crate is among this
+/// crate's dependencies, so the compression function is [`sha1`]; the
+/// HMAC construction is the `hmac` crate's.
+#[derive(Clone, Default)]
+struct Sha1(Vec<u8>);
+
+impl HashMarker for Sha1 {}
+
+impl BlockSizeUser for Sha1 {
+    type BlockSize = U64;
+}
+
+impl OutputSizeUser for Sha1 {
+    type OutputSize = U20;
+}
+
+impl Update for Sha1 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+}
+
+impl FixedOutput for Sha1 {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&sha1(&self.0));
+    }
+}
+
+/// Only its use inside HMAC is relied on here.
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut message = message.to_vec();
+    let bits = (message.len() as u64) * 8;
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bits.to_be_bytes());
+    let mut h: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap_or_default());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut out = [0u8; 20];
+    for (chunk, word) in out.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sha1, Sha1, TotpSecret, TotpVerifier};
+    use hmac::{KeyInit, Mac, SimpleHmac};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn hmac_sha1(key: &[u8], message: &[u8]) -> String {
+        let mut mac = <SimpleHmac<Sha1> as KeyInit>::new_from_slice(key).unwrap();
+        mac.update(message);
+        hex(&mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn sha1_matches_the_fips_vectors() {
+        assert_eq!(hex(&sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        let two_blocks = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(hex(&sha1(two_blocks)), "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+    }
+
+    /// RFC 2202 §3, cases 1, 2 and 6: a short key, a short message, and
+    /// a key longer than the block, which HMAC hashes first.
+    #[test]
+    fn hmac_sha1_matches_the_rfc_2202_vectors() {
+        assert_eq!(hmac_sha1(&[0x0b; 20], b"Hi There"), "b617318655057264e28bc0b6fb378c8ef146be00");
+        assert_eq!(hmac_sha1(b"Jefe", b"what do ya want for nothing?"), "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+        let long = hmac_sha1(&[0xaa; 80], b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(long, "aa4ae5e15272d00e95705637ce8a3b55ed402112");
+    }
+
+    /// RFC 6238 Appendix B, SHA-1: the last six of each eight digits.
+    #[test]
+    fn codes_match_the_rfc_6238_vectors() {
+        let secret = TotpSecret::new(b"12345678901234567890");
+        let vectors = [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ];
+        for (time, code) in vectors {
+            assert_eq!(secret.code(time / super::STEP), code, "at {}", time);
+        }
+    }
+
+    #[test]
+    fn a_code_is_accepted_once_within_the_skew_and_refused_with_a_reason_otherwise() {
+        let secret = || TotpSecret::new(b"12345678901234567890");
+        let mut verifier = TotpVerifier::new(secret(), "phone");
+        assert_eq!(verifier.verify_at("287 082", 59), Ok(1));
+        let replayed = verifier.verify_at("287082", 60).unwrap_err();
+        assert!(replayed.contains("already used"), "{}", replayed);
+
+        let malformed = verifier.verify_at("28708", 59).unwrap_err();
+        assert!(malformed.contains("6-digit code from phone"), "{}", malformed);
+        let malformed = verifier.verify_at("28708x", 59).unwrap_err();
+        assert!(malformed.contains("6-digit code from phone"), "{}", malformed);
+
+        let mut strict = TotpVerifier::new(secret(), "phone").with_skew(0);
+        let late = strict.verify_at("287082", 89).unwrap_err();
+        assert!(late.contains("does not match phone"), "{}", late);
+        assert_eq!(TotpVerifier::new(secret(), "phone").verify_at("287082", 89), Ok(1));
+    }
+}
@@ -0,0 +1,194 @@
+//! TransferCeremony — Critical operations take more than one person and time.
+
+use crate::approver::Line;
+use crate::quorum::Rule;
+use crate::{
+    report, unix_now, ConsentDenied, OperationDescriptor, OperationKind, OwnershipTransfer, Protected, Repository,
+    SafetyGate, UserConsent,
+};
+
+/// The ceremony for transferring a repository to a new owner.
+///
+/// A transfer looks like a settings change and acts like a deletion: the
+/// old URL starts redirecting, permissions reset, integrations break, and
+/// getting it back depends on the new owner. So it is Critical: the impact
+/// is listed up front, two different approvers from the gate's quorum
+/// must each answer their own challenge, and nobody can approve fast
+/// enough to skip the policy's waiting period.
+pub struct TransferCeremony {
+    name: String,
+    repo: String,
+    new_owner: String,
+    impact: Vec<String>,
+    opened_at: u64,
+    /// Monotonic twin of `opened_at`. The waiting period must pass on both
+    /// clocks, so a wall clock that jumps forward cannot shorten it.
+    opened_instant: std::time::Instant,
+    waiting_period: std::time::Duration,
+}
+
+impl TransferCeremony {
+    /// Distinct approvals required to complete a transfer.
+    pub const REQUIRED_APPROVALS: usize = 2;
+
+    pub fn impact(&self) -> &[String] {
+        &self.impact
+    }
+
+    /// When the waiting period ends, in seconds since the Unix epoch.
+    pub fn ready_at(&self) -> u64 {
+        self.opened_at + self.waiting_period.as_secs()
+    }
+}
+
+impl SafetyGate {
+    /// Open a transfer ceremony, list its downstream impact, and start
+    /// the waiting period the policy sets.
+    pub fn begin_transfer<B>(&mut self, repo: &Repository<Protected, B>, new_owner: &str) -> TransferCeremony {
+        let impact = repo.transfer_impact(new_owner);
+        println!(
+            "  [GATE] {} ceremony: transfer '{}' to '{}'. Downstream impact:",
+            OperationKind::TransferOwnership.severity().label(),
+            repo.name,
+            new_owner
+        );
+        for item in &impact {
+            println!("      - {}", item);
+        }
+        self.log(format!("TRANSFER OPENED: '{}' -> '{}'", repo.name, new_owner));
+        TransferCeremony {
+            name: repo.name.clone(),
+            repo: repo.path.clone(),
+            new_owner: new_owner.to_string(),
+            impact,
+            opened_at: unix_now(),
+            opened_instant: std::time::Instant::now(),
+            waiting_period: self.policy.transfer_waiting_period(),
+        }
+    }
+
+    /// Complete a ceremony: once the waiting period has elapsed, put the
+    /// transfer to the quorum's approvers, and mint consent bound to the
+    /// repository and the new owner once two of them answer. The
+    /// ceremony is consumed either way; a refused transfer starts over,
+    /// waiting period included.
+    pub fn complete_transfer(
+        &mut self,
+        ceremony: TransferCeremony,
+    ) -> Result<UserConsent<OwnershipTransfer>, ConsentDenied> {
+        let operation = OperationKind::TransferOwnership;
+        let refuse = |gate: &mut SafetyGate, reason: String| {
+            gate.log(format!("TRANSFER REFUSED: '{}': {}", ceremony.name, reason));
+            Err(ConsentDenied::new(operation, reason))
+        };
+        let now = self.clock();
+        if now < ceremony.opened_at {
+            return refuse(self, "clock reads earlier than the ceremony opened; time is untrustworthy".to_string());
+        }
+        if now < ceremony.ready_at() || ceremony.opened_instant.elapsed() < ceremony.waiting_period {
+            let reason = format!("waiting period ends at {}", report::format_utc(ceremony.ready_at()));
+            return refuse(self, reason);
+        }
+        let rule = match self.quorum_rule(operation, None) {
+            Some(rule) if rule.required >= TransferCeremony::REQUIRED_APPROVALS => rule,
+            _ => Rule {
+                operation,
+                min_commits: 0,
+                required: TransferCeremony::REQUIRED_APPROVALS,
+            },
+        };
+        if self.quorum_approvers() < rule.required {
+            let reason = format!(
+                "{} approvals required, {} approvers configured; name them with require_quorum",
+                rule.required,
+                self.quorum_approvers()
+            );
+            return refuse(self, reason);
+        }
+        let description = format!("Transfer '{}' to '{}'", ceremony.name, ceremony.new_owner);
+        self.screen_forbidden(operation, Some(&ceremony.name), &description)?;
+        self.show_line(Line::Heading(format!("Transferring '{}' breaks, downstream:", ceremony.name)));
+        for item in &ceremony.impact {
+            self.show_line(Line::Item(item.clone()));
+        }
+        self.target = Some(ceremony.repo.clone());
+        self.descriptor = Some(OperationDescriptor::TransferOwnership {
+            repo: ceremony.repo.clone(),
+            new_owner: ceremony.new_owner.clone(),
+        });
+        let (severity, _) = self.severity_for(operation, Some(&ceremony.repo), None);
+        self.quorum_consent(rule, severity, operation, &ceremony.repo, &description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::approver::{Prompt, Rehearsal, Responder};
+    use crate::quorum::QuorumGate;
+    use crate::{policy, Repository, SafetyGate};
+
+    /// The gate's own approver, who never answers.
+    struct Silent;
+
+    impl Responder for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn answer(&mut self, _prompt: &Prompt) -> Option<String> {
+            None
+        }
+    }
+
+    fn gate(waiting_period: u64, approvers: &[&str]) -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Silent));
+        let text = format!("version = \"test\"\ntransfer_waiting_period = \"{}\"\n", waiting_period);
+        gate.policy = policy::Policy::parse(&text).unwrap();
+        let quorum = approvers.iter().fold(QuorumGate::new(), |q, name| q.approver(name, Box::new(Rehearsal)));
+        gate.require_quorum(quorum).unwrap();
+        gate
+    }
+
+    #[test]
+    fn a_transfer_waits_as_long_as_the_policy_says() {
+        let mut gate = gate(3600, &["alice", "bob"]);
+        let repo = Repository::protected("repo", "/repos/repo", 10);
+        let ceremony = gate.begin_transfer(&repo, "elsewhere");
+        let denied = gate.complete_transfer(ceremony).err().expect("inside the waiting period");
+        assert!(denied.reason.contains("waiting period"), "{}", denied.reason);
+    }
+
+    #[test]
+    fn a_transfer_needs_two_approvers_from_the_quorum() {
+        let mut gate = gate(0, &["alice"]);
+        let repo = Repository::protected("repo", "/repos/repo", 10);
+        let ceremony = gate.begin_transfer(&repo, "elsewhere");
+        let denied = gate.complete_transfer(ceremony).err().expect("one approver is not two");
+        assert!(denied.reason.contains("2 approvals required"), "{}", denied.reason);
+    }
+
+    #[test]
+    fn consent_covers_the_consented_owner_only() {
+        let mut gate = gate(0, &["alice", "bob"]);
+        let repo = Repository::protected("repo", "/repos/repo", 10);
+        let ceremony = gate.begin_transfer(&repo, "elsewhere");
+        let Ok(consent) = gate.complete_transfer(ceremony) else {
+            panic!("both approvers answered");
+        };
+        let Err(refused) = repo.transfer_ownership("somewhere-else", consent) else {
+            panic!("consented to 'elsewhere'");
+        };
+        assert!(refused.denied.reason.contains("elsewhere"), "{}", refused.denied.reason);
+        let repo = *refused.repo;
+        let ceremony = gate.begin_transfer(&repo, "elsewhere");
+        let Ok(consent) = gate.complete_transfer(ceremony) else {
+            panic!("both approvers answered");
+        };
+        let Ok(transferred) = repo.transfer_ownership("elsewhere", consent) else {
+            panic!("consented to 'elsewhere'");
+        };
+        assert_eq!(transferred.new_owner, "elsewhere");
+        assert!(!transferred.impact.is_empty());
+    }
+}
@@ -0,0 +1,627 @@
+//! Version-control backends beyond git.
+//!
+//! The dangerous verbs have different names — `jj abandon`, `sl hide`,
+//! `git reset --hard` — and different undo stories, but they are the same
+//! operations to the gate. [`Jujutsu`] and [`Sapling`] are
+//! [`GitBackend`]s like any other: each maps a destructive
+//! [`HistoryEdit`] to its own command and undo story, and runs it on the
+//! same process runner as git's [`Shell`]. [`VcsRepo`] wraps the same `Repository<State>`
+//! typestate, so on every backend the destructive verbs exist only on an
+//! unprotected repository and only with consent.
+
+pub use super::backend::HistoryEdit;
+use super::backend::{self, after, Cli, ExecOptions, GitBackend, NoProgress, Progress, Shell, Target};
+use super::scoped_env::ScopedToken;
+use super::{
+    ConsentDenied, FilteredRepository, OperationDescriptor, OperationKind, Outcome, Protected, Repository,
+    SafetyError, Unprotected, UserConsent,
+};
+
+/// Jujutsu, in a repository colocated with git.
+///
+/// Runs `jj` on the same runner as [`Shell`]. Tags and message-callback
+/// rewrites have no jj verb; they go to the colocated git repository,
+/// and jj imports what changed.
+pub struct Jujutsu {
+    jj: Cli,
+    git: Shell,
+}
+
+impl Default for Jujutsu {
+    fn default() -> Self {
+        Jujutsu {
+            jj: Cli::new("jj", "-R"),
+            git: Shell::new(),
+        }
+    }
+}
+
+impl Jujutsu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate the operation `token` was minted for, on its
+    /// repository, with it. A tag deletion or a rewrite runs in git, so
+    /// its token goes there.
+    pub fn with_token(mut self, token: ScopedToken) -> Self {
+        match token.operation {
+            OperationKind::DeleteTag | OperationKind::FilterRepo => self.git = self.git.with_token(token),
+            _ => self.jj.token = Some(token),
+        }
+        self
+    }
+}
+
+/// Sapling. Runs `sl` on the same runner as [`Shell`].
+pub struct Sapling {
+    sl: Cli,
+}
+
+impl Default for Sapling {
+    fn default() -> Self {
+        Sapling {
+            sl: Cli::new("sl", "-R"),
+        }
+    }
+}
+
+impl Sapling {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate the operation `token` was minted for, on its
+    /// repository, with it.
+    pub fn with_token(mut self, token: ScopedToken) -> Self {
+        self.sl.token = Some(token);
+        self
+    }
+}
+
+impl GitBackend for Jujutsu {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn command(&self, edit: &HistoryEdit) -> String {
+        match edit {
+            HistoryEdit::DiscardCommits(rev) => format!("jj abandon {}", rev),
+            HistoryEdit::RewriteMessages(callback) => {
+                format!("git filter-repo --message-callback {} && jj git import", callback)
+            }
+            HistoryEdit::ForcePush(bookmark) => format!("jj git push --bookmark {}", bookmark),
+            HistoryEdit::DeleteBranch(bookmark) => format!("jj bookmark delete {}", bookmark),
+        }
+    }
+
+    fn undo_hint(&self, edit: &HistoryEdit) -> Option<String> {
+        match edit {
+            // The operation log covers local state; what reached the
+            // remote is out of its reach.
+            HistoryEdit::ForcePush(_) => None,
+            _ => Some("jj op undo (or jj op restore <id>)".to_string()),
+        }
+    }
+
+    fn commit_count(&self, repo: &Target) -> Result<usize, Outcome> {
+        let template = "commit_id ++ \"\\n\"";
+        let ids = self.jj.output(repo, None, &["log", "-r", "all() ~ root()", "--no-graph", "-T", template])?;
+        Ok(ids.lines().count())
+    }
+
+    /// jj checks out no branch: the head is the bookmark on the working
+    /// copy's parent, and an error if there is none.
+    fn head(&self, repo: &Target) -> Result<String, Outcome> {
+        let template = "local_bookmarks.map(|b| b.name()).join(\"\\n\")";
+        let bookmarks = self.jj.output(repo, None, &["log", "-r", "@-", "--no-graph", "-T", template])?;
+        bookmarks.lines().next().map(str::to_string).ok_or_else(|| Outcome::Failed("no bookmark on @-".to_string()))
+    }
+
+    fn merged(&self, repo: &Target, branch: &str) -> Result<bool, Outcome> {
+        let revset = format!("bookmarks(exact:{:?}) & ::@", branch);
+        let merged = self.jj.output(repo, None, &["log", "-r", &revset, "--no-graph", "-T", "commit_id"])?;
+        Ok(!merged.is_empty())
+    }
+
+    fn tip(&self, repo: &Target, branch: &str, fetched: bool) -> Result<String, Outcome> {
+        let revset = match fetched {
+            true => format!("remote_bookmarks(exact:{:?}, exact:\"origin\")", branch),
+            false => format!("bookmarks(exact:{:?})", branch),
+        };
+        let tip = self.jj.output(repo, None, &["log", "-r", &revset, "--no-graph", "-T", "commit_id"])?;
+        match tip.is_empty() {
+            true => Err(Outcome::Failed(format!("{} names no commit", revset))),
+            false => Ok(tip),
+        }
+    }
+
+    /// jj tracks new files as it sees them: what the working-copy commit
+    /// changes is what is uncommitted.
+    fn status(&self, repo: &Target) -> Result<Vec<String>, Outcome> {
+        let changed = self.jj.output(repo, None, &["diff", "--name-only"])?;
+        Ok(changed.lines().map(str::to_string).collect())
+    }
+
+    fn fetch(&self, repo: &Target) -> Result<String, Outcome> {
+        self.jj.run(repo, None, &["git", "fetch"])
+    }
+
+    fn pull(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        let fetched = self.jj.run(repo, None, &["git", "fetch", "--branch", branch])?;
+        let moved = self
+            .jj
+            .run(repo, None, &["new", &format!("{}@origin", branch)])
+            .map_err(|outcome| after(&fetched, outcome))?;
+        Ok(format!("{}; {}", fetched, moved))
+    }
+
+    fn commit(&self, repo: &Target, message: &str, files: &[String]) -> Result<String, Outcome> {
+        let mut args = vec!["commit", "-m", message];
+        args.extend(files.iter().map(String::as_str));
+        self.jj.run(repo, None, &args)
+    }
+
+    fn amend(&self, repo: &Target, message: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, None, &["describe", "-r", "@-", "-m", message])
+    }
+
+    fn push(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, None, &["git", "push", "--bookmark", branch])
+    }
+
+    /// jj pushes with a lease of its own: it refuses if the remote moved
+    /// since it last fetched.
+    fn force_push(
+        &self,
+        repo: &Target,
+        branch: &str,
+        options: &ExecOptions,
+        _progress: &mut dyn Progress,
+    ) -> Result<String, Outcome> {
+        let args = ["git", "push", "--bookmark", branch];
+        self.jj.run_with(repo, OperationKind::ForcePush, &args, repo.total_commits, options)?;
+        Ok(format!("[{}] force-pushed to origin/{}", repo.name, branch))
+    }
+
+    fn create_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, None, &["bookmark", "create", branch, "-r", "@-"])
+    }
+
+    fn create_tag(&self, repo: &Target, tag: &str, message: &str) -> Result<String, Outcome> {
+        self.git.create_tag(repo, tag, message)
+    }
+
+    /// The remote copy goes too, if there is one: pushing a deleted
+    /// bookmark deletes it on the remote.
+    fn delete_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        let revset = format!("remote_bookmarks(exact:{:?})", branch);
+        let pushed = self.jj.output(repo, None, &["log", "-r", &revset, "--no-graph", "-T", "commit_id"])?;
+        let local = self.jj.run(repo, Some(OperationKind::DeleteBranch), &["bookmark", "delete", branch])?;
+        if pushed.is_empty() {
+            return Ok(local);
+        }
+        let remote = self
+            .jj
+            .run(repo, Some(OperationKind::DeleteBranch), &["git", "push", "--bookmark", branch])
+            .map_err(|outcome| after(&local, outcome))?;
+        Ok(format!("{}; {}", local, remote))
+    }
+
+    fn backup_ref(&self, repo: &Target, branch: &str, backup: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, None, &["bookmark", "create", backup, "-r", branch])
+    }
+
+    fn delete_tag(&self, repo: &Target, tag: &str) -> Result<String, Outcome> {
+        self.git.delete_tag(repo, tag)
+    }
+
+    fn remove_remote(&self, repo: &Target, remote: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, Some(OperationKind::RemoveRemote), &["git", "remote", "remove", remote])
+    }
+
+    /// git rewrites; jj then imports the rewritten refs. A failed import
+    /// leaves jj's view stale, not the rewrite undone.
+    fn filter_repo(
+        &self,
+        repo: &Target,
+        callback: &str,
+        options: &ExecOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<usize, Outcome> {
+        let rewritten = self.git.filter_repo(repo, callback, options, progress)?;
+        self.jj
+            .run(repo, None, &["git", "import"])
+            .map_err(|outcome| after(&format!("rewriting {} commits", rewritten), outcome))?;
+        Ok(rewritten)
+    }
+
+    /// Restores the working-copy commit from its parent.
+    fn reset_hard(&self, repo: &Target) -> Result<(), Outcome> {
+        self.jj.run(repo, Some(OperationKind::ResetHard), &["restore"]).map(drop)
+    }
+
+    fn discard(&self, repo: &Target, rev: &str) -> Result<String, Outcome> {
+        self.jj.run(repo, Some(OperationKind::ResetHard), &["abandon", rev])
+    }
+}
+
+impl GitBackend for Sapling {
+    fn name(&self) -> &'static str {
+        "sl"
+    }
+
+    fn command(&self, edit: &HistoryEdit) -> String {
+        match edit {
+            HistoryEdit::DiscardCommits(rev) => format!("sl hide -r {}", rev),
+            HistoryEdit::RewriteMessages(revset) => format!("sl metaedit -r '{}'", revset),
+            HistoryEdit::ForcePush(bookmark) => format!("sl push --force --to {}", bookmark),
+            HistoryEdit::DeleteBranch(bookmark) => format!("sl bookmark -d {}", bookmark),
+        }
+    }
+
+    fn undo_hint(&self, edit: &HistoryEdit) -> Option<String> {
+        match edit {
+            HistoryEdit::DiscardCommits(rev) => Some(format!("sl unhide -r {}", rev)),
+            HistoryEdit::ForcePush(_) => None,
+            _ => Some("sl undo".to_string()),
+        }
+    }
+
+    fn commit_count(&self, repo: &Target) -> Result<usize, Outcome> {
+        let nodes = self.sl.output(repo, None, &["log", "-r", "all()", "-T", "{node}\\n"])?;
+        Ok(nodes.lines().count())
+    }
+
+    /// The active bookmark; an error if none is active.
+    fn head(&self, repo: &Target) -> Result<String, Outcome> {
+        let active = self.sl.output(repo, None, &["log", "-r", ".", "-T", "{activebookmark}"])?;
+        match active.is_empty() {
+            true => Err(Outcome::Failed("no active bookmark".to_string())),
+            false => Ok(active),
+        }
+    }
+
+    fn merged(&self, repo: &Target, branch: &str) -> Result<bool, Outcome> {
+        let revset = format!("present(bookmark({:?})) and ::.", branch);
+        let merged = self.sl.output(repo, None, &["log", "-r", &revset, "-T", "{node}"])?;
+        Ok(!merged.is_empty())
+    }
+
+    fn tip(&self, repo: &Target, branch: &str, fetched: bool) -> Result<String, Outcome> {
+        let revset = match fetched {
+            true => format!("remote/{}", branch),
+            false => format!("bookmark({:?})", branch),
+        };
+        self.sl.output(repo, None, &["log", "-r", &revset, "-T", "{node}"])
+    }
+
+    fn status(&self, repo: &Target) -> Result<Vec<String>, Outcome> {
+        let changed = self.sl.output(repo, None, &["status", "--no-status"])?;
+        Ok(changed.lines().map(str::to_string).collect())
+    }
+
+    fn fetch(&self, repo: &Target) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["pull"])
+    }
+
+    fn pull(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        let pulled = self.sl.run(repo, None, &["pull"])?;
+        let moved = self.sl.run(repo, None, &["goto", branch]).map_err(|outcome| after(&pulled, outcome))?;
+        Ok(format!("{}; {}", pulled, moved))
+    }
+
+    fn commit(&self, repo: &Target, message: &str, files: &[String]) -> Result<String, Outcome> {
+        let mut args = vec!["commit", "-m", message];
+        args.extend(files.iter().map(String::as_str));
+        self.sl.run(repo, None, &args)
+    }
+
+    fn amend(&self, repo: &Target, message: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["amend", "-m", message])
+    }
+
+    fn push(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["push", "--to", branch])
+    }
+
+    fn force_push(
+        &self,
+        repo: &Target,
+        branch: &str,
+        options: &ExecOptions,
+        _progress: &mut dyn Progress,
+    ) -> Result<String, Outcome> {
+        let args = ["push", "--force", "--to", branch];
+        self.sl.run_with(repo, OperationKind::ForcePush, &args, repo.total_commits, options)?;
+        Ok(format!("[{}] force-pushed to remote/{}", repo.name, branch))
+    }
+
+    fn create_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["bookmark", branch])
+    }
+
+    fn create_tag(&self, repo: &Target, tag: &str, message: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["tag", "-m", message, tag])
+    }
+
+    /// The remote copy goes too, if there is one.
+    fn delete_branch(&self, repo: &Target, branch: &str) -> Result<String, Outcome> {
+        let revset = format!("present(remote/{})", branch);
+        let pushed = self.sl.output(repo, None, &["log", "-r", &revset, "-T", "{node}"])?;
+        let local = self.sl.run(repo, Some(OperationKind::DeleteBranch), &["bookmark", "-d", branch])?;
+        if pushed.is_empty() {
+            return Ok(local);
+        }
+        let remote = self
+            .sl
+            .run(repo, Some(OperationKind::DeleteBranch), &["push", "--delete", branch])
+            .map_err(|outcome| after(&local, outcome))?;
+        Ok(format!("{}; {}", local, remote))
+    }
+
+    fn backup_ref(&self, repo: &Target, branch: &str, backup: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, None, &["bookmark", "-r", branch, backup])
+    }
+
+    fn delete_tag(&self, repo: &Target, tag: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, Some(OperationKind::DeleteTag), &["tag", "--remove", tag])
+    }
+
+    fn remove_remote(&self, repo: &Target, remote: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, Some(OperationKind::RemoveRemote), &["path", "--delete", remote])
+    }
+
+    /// Sapling has no message callback to run across history, and
+    /// `sl metaedit` sets one message on every commit it touches. It
+    /// refuses rather than approximate.
+    fn filter_repo(
+        &self,
+        _repo: &Target,
+        callback: &str,
+        _options: &ExecOptions,
+        _progress: &mut dyn Progress,
+    ) -> Result<usize, Outcome> {
+        Err(Outcome::Failed(format!("sl cannot run message callback `{}`; rewrite with sl metaedit by hand", callback)))
+    }
+
+    fn reset_hard(&self, repo: &Target) -> Result<(), Outcome> {
+        self.sl.run(repo, Some(OperationKind::ResetHard), &["goto", "--clean", "."]).map(drop)
+    }
+
+    fn discard(&self, repo: &Target, rev: &str) -> Result<String, Outcome> {
+        self.sl.run(repo, Some(OperationKind::ResetHard), &["hide", "-r", rev])
+    }
+}
+
+/// A repository on any backend, with the git typestate.
+pub struct VcsRepo<B: GitBackend, State = Protected> {
+    repo: Repository<State, B>,
+}
+
+impl<B: GitBackend, State> VcsRepo<B, State> {
+    /// The repository under the backend, to request consent against.
+    pub fn repository(&self) -> &Repository<State, B> {
+        &self.repo
+    }
+}
+
+impl<B: GitBackend> VcsRepo<B, Protected> {
+    pub fn open(backend: B, name: &str, path: &str, total_commits: usize) -> Self {
+        VcsRepo {
+            repo: Repository::protected_with(name, path, total_commits, backend),
+        }
+    }
+
+    pub fn status(&self) -> String {
+        format!("{} ({})", self.repo.status(), self.repo.backend.name())
+    }
+
+    pub fn commit(&self, message: &str) -> Result<String, Outcome> {
+        self.repo.backend.commit(&self.repo.target(), message, &[])
+    }
+
+    pub fn remove_protection(self, consent: UserConsent) -> Result<VcsRepo<B, Unprotected>, ConsentDenied> {
+        match self.repo.remove_protection(consent) {
+            Ok(repo) => Ok(VcsRepo { repo }),
+            Err(refused) => Err(refused.denied),
+        }
+    }
+}
+
+impl<B: GitBackend> VcsRepo<B, Unprotected> {
+    /// What consent for `edit` must describe: the same operation on the
+    /// same branch or revset, to pass to
+    /// [`request_consent_to`](crate::SafetyGate::request_consent_to). A
+    /// branch that turns out unmerged needs consent to force-delete it.
+    pub fn descriptor(&self, edit: &HistoryEdit) -> OperationDescriptor {
+        let repo = self.repo.path.clone();
+        match edit {
+            HistoryEdit::DiscardCommits(_) => OperationDescriptor::ResetHard { repo },
+            HistoryEdit::RewriteMessages(revset) => OperationDescriptor::FilterRepo {
+                repo,
+                callback: revset.clone(),
+            },
+            HistoryEdit::ForcePush(branch) => OperationDescriptor::ForcePush {
+                repo,
+                branch: branch.clone(),
+            },
+            HistoryEdit::DeleteBranch(branch) => self.repo.deletion_of(branch),
+        }
+    }
+
+    fn announce(&self, edit: &HistoryEdit, consent: &UserConsent) {
+        let undo = self.repo.backend.undo_hint(edit).unwrap_or_else(|| "none".to_string());
+        consent.announce(&format!(
+            "{} on '{}' with user approval: {} (undo: {})",
+            edit.operation().name(),
+            self.repo.name,
+            self.repo.backend.command(edit),
+            undo
+        ));
+    }
+
+    /// Discard commits, push over a remote branch, or delete a branch.
+    /// History rewriting is not here: it consumes the repository, so
+    /// it is [`rewrite_messages`](Self::rewrite_messages). The consent
+    /// must describe exactly this edit, as [`descriptor`](Self::descriptor)
+    /// renders it; a push must be to the branch it was granted for. What
+    /// the backend could not do comes back with its receipt.
+    pub fn edit(&self, edit: &HistoryEdit, consent: UserConsent) -> Result<String, SafetyError> {
+        if let HistoryEdit::RewriteMessages(_) = edit {
+            let reason = "history rewrites consume the repository; use rewrite_messages";
+            return Err(ConsentDenied::new(OperationKind::FilterRepo, reason.to_string()).into());
+        }
+        let expected = self.descriptor(edit);
+        consent.check(expected.kind(), &self.repo)?;
+        consent.check_descriptor(&expected)?;
+        if let HistoryEdit::ForcePush(branch) = edit {
+            consent.check_branch(branch)?;
+        }
+        let target = self.repo.target();
+        let done = backend::run_to_end(edit.operation(), &target, || match consent.is_observe_only() {
+            true => apply(&backend::Simulated, &target, edit),
+            false => apply(&self.repo.backend, &target, edit),
+        })?;
+        self.announce(edit, &consent);
+        Ok(done)
+    }
+
+    /// Rewrite every commit's message with `callback`. Consumes the
+    /// repository, as `filter_repo` does: every rewritten commit has a
+    /// new identity.
+    pub fn rewrite_messages(self, callback: &str, consent: UserConsent) -> Result<FilteredRepository, SafetyError> {
+        let edit = HistoryEdit::RewriteMessages(callback.to_string());
+        let expected = self.descriptor(&edit);
+        consent.check(expected.kind(), &self.repo)?;
+        consent.check_descriptor(&expected)?;
+        let target = self.repo.target();
+        let rewritten_commits = backend::run_to_end(edit.operation(), &target, || {
+            let (options, mut progress) = (ExecOptions::default(), NoProgress);
+            match consent.is_observe_only() {
+                true => backend::Simulated.filter_repo(&target, callback, &options, &mut progress),
+                false => self.repo.backend.filter_repo(&target, callback, &options, &mut progress),
+            }
+        })?;
+        self.announce(&edit, &consent);
+        Ok(FilteredRepository {
+            name: self.repo.name,
+            path: self.repo.path,
+            rewritten_commits,
+        })
+    }
+
+    pub fn restore_protection(self) -> VcsRepo<B, Protected> {
+        VcsRepo {
+            repo: self.repo.restore_protection(),
+        }
+    }
+}
+
+/// Carry out `edit` on `backend`, to completion.
+fn apply(backend: &impl GitBackend, repo: &Target, edit: &HistoryEdit) -> Result<String, Outcome> {
+    match edit {
+        HistoryEdit::DiscardCommits(rev) => backend.discard(repo, rev),
+        HistoryEdit::RewriteMessages(callback) => backend
+            .filter_repo(repo, callback, &ExecOptions::default(), &mut NoProgress)
+            .map(|rewritten| format!("[{}] rewrote {} commits", repo.name, rewritten)),
+        HistoryEdit::ForcePush(branch) => backend.force_push(repo, branch, &ExecOptions::default(), &mut NoProgress),
+        HistoryEdit::DeleteBranch(branch) => backend.delete_branch(repo, branch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryEdit, Jujutsu, Sapling, VcsRepo};
+    use crate::approver::Rehearsal;
+    use crate::backend::GitBackend;
+    use crate::{OperationDescriptor, SafetyError, SafetyGate, Unprotected};
+
+    /// A gate that asks nobody but mints real consent: what it approves
+    /// runs on the backend.
+    fn live() -> SafetyGate {
+        let mut gate = SafetyGate::new();
+        gate.set_responder(Box::new(Rehearsal));
+        gate
+    }
+
+    fn unprotected<B: GitBackend>(gate: &mut SafetyGate, backend: B) -> VcsRepo<B, Unprotected> {
+        let repo = VcsRepo::open(backend, "repo", "/repos/repo", 10);
+        let descriptor = OperationDescriptor::RemoveProtection { repo: "/repos/repo".to_string() };
+        let consent = gate.request_consent_to(repo.repository(), descriptor).unwrap();
+        let Ok(repo) = repo.remove_protection(consent) else {
+            panic!("consent was for removing protection");
+        };
+        repo
+    }
+
+    #[test]
+    fn an_edit_needs_consent_that_describes_it() {
+        let mut gate = SafetyGate::rehearsal();
+        let repo = unprotected(&mut gate, Jujutsu::new());
+        let main = repo.descriptor(&HistoryEdit::ForcePush("main".to_string()));
+        let consent = gate.request_consent_to(repo.repository(), main).unwrap();
+        let refused = repo.edit(&HistoryEdit::ForcePush("release".to_string()), consent).expect_err("granted for main");
+        assert!(matches!(refused, SafetyError::Denied(_)));
+        assert!(refused.to_string().contains("release"), "{}", refused);
+        let release = HistoryEdit::ForcePush("release".to_string());
+        let consent = gate.request_consent_to(repo.repository(), repo.descriptor(&release)).unwrap();
+        assert_eq!(repo.edit(&release, consent).unwrap(), "[repo] force-pushed to origin/release");
+    }
+
+    #[test]
+    fn a_rewrite_needs_consent_for_its_callback() {
+        let mut gate = SafetyGate::rehearsal();
+        let repo = unprotected(&mut gate, Jujutsu::new());
+        let other = repo.descriptor(&HistoryEdit::RewriteMessages("upper".to_string()));
+        let consent = gate.request_consent_to(repo.repository(), other).unwrap();
+        let Err(denied) = repo.rewrite_messages("lower", consent) else {
+            panic!("granted for another callback");
+        };
+        assert!(denied.to_string().contains("lower"), "{}", denied);
+    }
+
+    #[test]
+    fn a_backend_that_cannot_run_fails_the_edit_with_its_receipt() {
+        let mut gate = live();
+        let repo = unprotected(&mut gate, Jujutsu::new());
+        let discard = HistoryEdit::DiscardCommits("abc123".to_string());
+        let consent = gate.request_consent_to(repo.repository(), repo.descriptor(&discard)).unwrap();
+        let Err(SafetyError::Failed(receipt)) = repo.edit(&discard, consent) else {
+            panic!("there is no jj repository at /repos/repo");
+        };
+        let why = receipt.outcome.label();
+        assert!(why.contains("jj abandon abc123"), "{}", why);
+    }
+
+    #[test]
+    fn sapling_refuses_a_message_callback_rather_than_approximate_it() {
+        let mut gate = live();
+        let repo = unprotected(&mut gate, Sapling::new());
+        let rewrite = repo.descriptor(&HistoryEdit::RewriteMessages("upper".to_string()));
+        let consent = gate.request_consent_to(repo.repository(), rewrite).unwrap();
+        let Err(SafetyError::Failed(receipt)) = repo.rewrite_messages("upper", consent) else {
+            panic!("sl has no message callback");
+        };
+        assert!(receipt.outcome.label().contains("metaedit"), "{}", receipt.outcome.label());
+    }
+
+    #[test]
+    fn a_rewrite_is_not_an_edit() {
+        let mut gate = SafetyGate::rehearsal();
+        let repo = unprotected(&mut gate, Sapling::new());
+        let rewrite = HistoryEdit::RewriteMessages("upper".to_string());
+        let consent = gate.request_consent_to(repo.repository(), repo.descriptor(&rewrite)).unwrap();
+        let refused = repo.edit(&rewrite, consent).expect_err("rewrites consume the repository");
+        assert!(refused.to_string().contains("rewrite_messages"), "{}", refused);
+    }
+
+    #[test]
+    fn queries_on_a_missing_repository_fail_rather_than_answer() {
+        let repo = crate::Repository::protected("gone", "/nonexistent/safe-operations-vcs", 0);
+        let missing = repo.target();
+        assert!(Jujutsu::new().head(&missing).is_err());
+        assert!(Jujutsu::new().commit_count(&missing).is_err());
+        assert!(Sapling::new().status(&missing).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Stable wire formats for records that leave the process.
+//!
+//! Audit entries and receipts are serialized as single-line JSON objects
+//! tagged with a format version `"v"`. `UserConsent` itself is never
+//! serialized: a serialized capability is a forgeable one.
+//!
+//! Compatibility guarantees:
+//! - A decoder accepts every version from [`OLDEST_SUPPORTED`] to
+//!   [`CURRENT`]. Fields added in later versions get documented defaults
+//!   when decoding older records.
+//! - Fields are only ever added. A field is never renamed, removed, or
+//!   given a different meaning within the supported range.
+//! - Encoders always write [`CURRENT`]; peers agree on a version with
+//!   [`negotiate`] before exchanging records.
+//! - Unknown fields are ignored, so an older decoder can read records from
+//!   a newer encoder within the negotiated version.
+//!
+//! | v | audit entry                    | receipt                                         |
+//! |---|--------------------------------|-------------------------------------------------|
+//! | 1 | `at`, `event`                  | `operation`, `repo`, `outcome`, `started_at`     |
+//! | 2 | + `policy_version` ("unknown") | + `processed`, `total` (0), `elapsed_ms` (0)    |
+//! | 3 | (unchanged)                    | + `retries` (0)                                 |
+//! | 4 | (unchanged)                    | + `ceremony` ("" = none recorded)               |
+//! | 5 | (unchanged)                    | + `gate_chain`, comma-separated ("")            |
+//! | 6 | (unchanged)                    | + `verification` ("" = not verified)            |
+//! | 7 | (unchanged)                    | + `approval_reason` ("" = none given)           |
+//! | 8 | (unchanged)                    | + `observed`, 0 or 1 (0)                        |
+//! | 9 | (unchanged)                    | + `approved_by`, semicolon-separated ("")       |
+//! | 10| + `blocked`, `blocked_*` ("")  | (unchanged)                                     |
+//! | 11| (unchanged)                    | + outcome `failed`, with `failure` ("")         |
+//! | 12| (unchanged)                    | + `path` ("" = not recorded)                    |
+//!
+//! `blocked` is the kind of refusal an audit entry records, "" if it
+//! records none, with its `blocked_operation`, `blocked_target`,
+//! `blocked_agent` (encoded), and `blocked_reason`.
+
+use super::agent_identity::AgentIdentity;
+use super::blocked::{BlockKind, Blocked};
+use super::{AuditEntry, OperationKind, Outcome, Receipt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const CURRENT: u32 = 12;
+pub const OLDEST_SUPPORTED: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WireError {
+    UnsupportedVersion(u32),
+    WrongType(String),
+    Malformed(String),
+}
+
+/// The highest version both sides support, given each side's
+/// inclusive `(oldest, newest)` range. `None` means the peers cannot
+/// talk, and must not fall back to guessing.
+pub fn negotiate(ours: (u32, u32), theirs: (u32, u32)) -> Option<u32> {
+    let high = ours.1.min(theirs.1);
+    let low = ours.0.max(theirs.0);
+    (low <= high).then_some(high)
+}
+
+/// What every record starts with, read before the rest so a record of
+/// the wrong version or type is refused as such.
+#[derive(Deserialize)]
+struct Envelope {
+    v: u32,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// An audit entry as it is on the wire. Fields are in encoding order;
+/// each one added after v1 defaults to what the table above says.
+#[derive(Serialize, Deserialize)]
+struct AuditEntryRecord {
+    v: u32,
+    #[serde(rename = "type")]
+    kind: String,
+    at: u64,
+    #[serde(default = "unknown_policy_version")]
+    policy_version: String,
+    event: String,
+    #[serde(default)]
+    blocked: String,
+    #[serde(default)]
+    blocked_operation: String,
+    #[serde(default)]
+    blocked_target: String,
+    #[serde(default)]
+    blocked_agent: String,
+    #[serde(default)]
+    blocked_reason: String,
+}
+
+fn unknown_policy_version() -> String {
+    "unknown".to_string()
+}
+
+/// A receipt as it is on the wire. Fields are in encoding order; each
+/// one added after v1 defaults to what the table above says.
+#[derive(Serialize, Deserialize)]
+struct ReceiptRecord {
+    v: u32,
+    #[serde(rename = "type")]
+    kind: String,
+    operation: String,
+    repo: String,
+    outcome: String,
+    #[serde(default)]
+    processed: usize,
+    #[serde(default)]
+    total: usize,
+    started_at: u64,
+    #[serde(default)]
+    elapsed_ms: u64,
+    #[serde(default)]
+    retries: u32,
+    #[serde(default)]
+    ceremony: String,
+    #[serde(default)]
+    gate_chain: String,
+    #[serde(default)]
+    verification: String,
+    #[serde(default)]
+    approval_reason: String,
+    #[serde(default)]
+    observed: u8,
+    #[serde(default)]
+    approved_by: String,
+    #[serde(default)]
+    failure: String,
+    #[serde(default)]
+    path: String,
+}
+
+fn malformed(err: serde_json::Error) -> WireError {
+    WireError::Malformed(err.to_string())
+}
+
+/// Decode `line` as a record of `expected_type`, once its version is
+/// known to be one this decoder supports.
+fn decode<T: DeserializeOwned>(line: &str, expected_type: &str) -> Result<T, WireError> {
+    let envelope: Envelope = serde_json::from_str(line).map_err(malformed)?;
+    if !(OLDEST_SUPPORTED..=CURRENT).contains(&envelope.v) {
+        return Err(WireError::UnsupportedVersion(envelope.v));
+    }
+    if envelope.kind != expected_type {
+        return Err(WireError::WrongType(envelope.kind));
+    }
+    serde_json::from_str(line).map_err(malformed)
+}
+
+fn encode(record: &impl Serialize) -> String {
+    serde_json::to_string(record).expect("wire records are plain strings and integers")
+}
+
+pub fn encode_audit_entry(entry: &AuditEntry) -> String {
+    let blocked = entry.blocked.as_ref();
+    encode(&AuditEntryRecord {
+        v: CURRENT,
+        kind: "audit_entry".to_string(),
+        at: entry.at,
+        policy_version: entry.policy_version.clone(),
+        event: entry.event.clone(),
+        blocked: blocked.map_or("", |b| b.kind.label()).to_string(),
+        blocked_operation: blocked.map_or("", |b| b.operation.name()).to_string(),
+        blocked_target: blocked.and_then(|b| b.target.clone()).unwrap_or_default(),
+        blocked_agent: blocked.and_then(|b| b.agent.as_ref()).map(AgentIdentity::encode).unwrap_or_default(),
+        blocked_reason: blocked.map_or(String::new(), |b| b.reason.clone()),
+    })
+}
+
+pub fn decode_audit_entry(line: &str) -> Result<AuditEntry, WireError> {
+    let record: AuditEntryRecord = decode(line, "audit_entry")?;
+    Ok(AuditEntry {
+        at: record.at,
+        blocked: decode_blocked(&record)?,
+        policy_version: record.policy_version,
+        event: record.event,
+    })
+}
+
+fn decode_blocked(record: &AuditEntryRecord) -> Result<Option<Blocked>, WireError> {
+    if record.blocked.is_empty() {
+        return Ok(None);
+    }
+    let malformed = |what: String| WireError::Malformed(what);
+    let (kind, operation, agent) = (&record.blocked, &record.blocked_operation, &record.blocked_agent);
+    Ok(Some(Blocked {
+        kind: BlockKind::from_label(kind).ok_or_else(|| malformed(format!("unknown refusal `{}`", kind)))?,
+        operation: OperationKind::from_name(operation)
+            .ok_or_else(|| malformed(format!("unknown operation `{}`", operation)))?,
+        target: Some(record.blocked_target.clone()).filter(|t| !t.is_empty()),
+        agent: match agent.is_empty() {
+            true => None,
+            false => Some(AgentIdentity::decode(agent).ok_or_else(|| malformed(format!("bad agent `{}`", agent)))?),
+        },
+        reason: record.blocked_reason.clone(),
+    }))
+}
+
+pub fn encode_receipt(receipt: &Receipt) -> String {
+    let (outcome, processed, total) = match &receipt.outcome {
+        Outcome::Completed => ("completed", 0, 0),
+        Outcome::TimedOut { processed, total } => ("timed_out", *processed, *total),
+        Outcome::Cancelled { processed, total } => ("cancelled", *processed, *total),
+        Outcome::Failed(_) => ("failed", 0, 0),
+    };
+    encode(&ReceiptRecord {
+        v: CURRENT,
+        kind: "receipt".to_string(),
+        operation: receipt.operation.name().to_string(),
+        repo: receipt.repo.clone(),
+        outcome: outcome.to_string(),
+        processed,
+        total,
+        started_at: receipt.started_at,
+        elapsed_ms: receipt.elapsed.as_millis() as u64,
+        retries: receipt.retries,
+        ceremony: receipt.ceremony.clone().unwrap_or_default(),
+        gate_chain: receipt.gate_chain.join(","),
+        verification: receipt.verification.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+        approval_reason: receipt.approval_reason.clone().unwrap_or_default(),
+        observed: u8::from(receipt.observed),
+        approved_by: receipt.approved_by.join(";"),
+        failure: match &receipt.outcome {
+            Outcome::Failed(why) => why.clone(),
+            _ => String::new(),
+        },
+        path: receipt.path.clone(),
+    })
+}
+
+pub fn decode_receipt(line: &str) -> Result<Receipt, WireError> {
+    let record: ReceiptRecord = decode(line, "receipt")?;
+    let operation = OperationKind::from_name(&record.operation)
+        .ok_or_else(|| WireError::Malformed(format!("unknown operation `{}`", record.operation)))?;
+    let (processed, total) = (record.processed, record.total);
+    let outcome = match record.outcome.as_str() {
+        "completed" => Outcome::Completed,
+        "timed_out" => Outcome::TimedOut { processed, total },
+        "cancelled" => Outcome::Cancelled { processed, total },
+        "failed" => Outcome::Failed(record.failure),
+        other => return Err(WireError::Malformed(format!("unknown outcome `{}`", other))),
+    };
+    Ok(Receipt {
+        operation,
+        repo: record.repo,
+        path: record.path,
+        outcome,
+        started_at: record.started_at,
+        elapsed: Duration::from_millis(record.elapsed_ms),
+        retries: record.retries,
+        ceremony: Some(record.ceremony).filter(|c| !c.is_empty()),
+        gate_chain: record.gate_chain.split(',').filter(|g| !g.is_empty()).map(str::to_string).collect(),
+        verification: match record.verification.as_str() {
+            "" => None,
+            text => Some(text.parse().map_err(WireError::Malformed)?),
+        },
+        approval_reason: Some(record.approval_reason).filter(|r| !r.is_empty()),
+        observed: record.observed != 0,
+        // Semicolons, because an LDAP subject is a DN full of commas.
+        approved_by: record.approved_by.split(';').filter(|s| !s.is_empty()).map(str::to_string).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_audit_entry, decode_receipt, encode_audit_entry, encode_receipt, WireError, CURRENT};
+    use crate::blocked::{BlockKind, Blocked};
+    use crate::readback::Verification;
+    use crate::{AuditEntry, OperationKind, Outcome, Receipt};
+    use std::time::Duration;
+
+    fn receipt() -> Receipt {
+        Receipt {
+            operation: OperationKind::FilterRepo,
+            repo: "my \"repo\"\n".to_string(),
+            path: "/repos/my repo".to_string(),
+            outcome: Outcome::TimedOut { processed: 212, total: 549 },
+            started_at: 1772000100,
+            elapsed: Duration::from_millis(3600000),
+            retries: 2,
+            ceremony: Some("sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string()),
+            gate_chain: vec!["gate-eu".to_string(), "gate-us".to_string()],
+            verification: Some(Verification::Mismatch(vec!["main moved".to_string()])),
+            approval_reason: Some("restoring the tagged release".to_string()),
+            observed: true,
+            approved_by: vec!["ldap:ldap://ldap.example.com|uid=jdoe,ou=people,dc=example,dc=com".to_string()],
+        }
+    }
+
+    #[test]
+    fn a_receipt_round_trips_at_the_current_version() {
+        let encoded = encode_receipt(&receipt());
+        assert!(encoded.starts_with(&format!("{{\"v\":{},\"type\":\"receipt\",", CURRENT)), "{}", encoded);
+        let decoded = decode_receipt(&encoded).unwrap();
+        assert_eq!(decoded.repo, "my \"repo\"\n");
+        assert_eq!(decoded.path, "/repos/my repo");
+        assert_eq!(decoded.approved_by, receipt().approved_by);
+        assert!(matches!(decoded.outcome, Outcome::TimedOut { processed: 212, total: 549 }));
+        assert_eq!(encode_receipt(&decoded), encoded);
+    }
+
+    #[test]
+    fn an_audit_entry_round_trips_with_its_refusal() {
+        let entry = AuditEntry {
+            at: 1772000000,
+            policy_version: "2026-04-01.1".to_string(),
+            event: "BLOCKED (denied): force_push".to_string(),
+            blocked: Some(Blocked {
+                kind: BlockKind::Denied,
+                operation: OperationKind::ForcePush,
+                target: Some("my-repo".to_string()),
+                agent: None,
+                reason: "outside the change window".to_string(),
+            }),
+        };
+        let decoded = decode_audit_entry(&encode_audit_entry(&entry)).unwrap();
+        assert_eq!((decoded.at, decoded.policy_version.as_str()), (entry.at, "2026-04-01.1"));
+        let blocked = decoded.blocked.expect("the refusal survives");
+        assert_eq!((blocked.kind, blocked.operation), (BlockKind::Denied, OperationKind::ForcePush));
+        assert_eq!(blocked.target.as_deref(), Some("my-repo"));
+        assert_eq!(blocked.reason, "outside the change window");
+    }
+
+    #[test]
+    fn older_versions_decode_with_documented_defaults() {
+        let entry = decode_audit_entry(r#"{"v":1,"type":"audit_entry","at":5,"event":"GRANTED"}"#).unwrap();
+        assert_eq!(entry.policy_version, "unknown");
+        assert!(entry.blocked.is_none());
+        let line = r#"{"v":1,"type":"receipt","operation":"force_push","repo":"r","outcome":"completed","started_at":9}"#;
+        let receipt = decode_receipt(line).unwrap();
+        assert_eq!((receipt.retries, receipt.elapsed), (0, Duration::ZERO));
+        assert!(receipt.ceremony.is_none() && receipt.verification.is_none() && !receipt.observed);
+        let newer = r#"{"v":10,"type":"receipt","operation":"force_push","repo":"r","outcome":"completed","started_at":9,"from_the_future":"x"}"#;
+        assert!(decode_receipt(newer).is_ok(), "unknown fields are ignored");
+    }
+
+    #[test]
+    fn unsupported_versions_and_wrong_types_are_refused() {
+        let future = format!(r#"{{"v":{},"type":"receipt"}}"#, CURRENT + 1);
+        assert_eq!(decode_receipt(&future).err(), Some(WireError::UnsupportedVersion(CURRENT + 1)));
+        assert_eq!(decode_receipt(r#"{"v":0,"type":"receipt"}"#).err(), Some(WireError::UnsupportedVersion(0)));
+        let entry = r#"{"v":1,"type":"audit_entry","at":5,"event":"GRANTED"}"#;
+        assert_eq!(decode_receipt(entry).err(), Some(WireError::WrongType("audit_entry".to_string())));
+        assert!(matches!(decode_receipt("not json"), Err(WireError::Malformed(_))));
+    }
+}
@@ -0,0 +1,191 @@
+//! Persistent workflow state for approvals that outlive the session.
+//!
+//! A human may answer a consent request hours later, after the agent that
+//! asked has been restarted. The request is saved to disk under its
+//! idempotency key, so whichever session is running when the answer
+//! arrives can pick it up. If the repository moved in the meantime, the
+//! old plan is not executed: the new session is told to re-plan.
+
+use super::dedup::IdempotencyKey;
+use super::{OperationKind, SafetyGate};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    AwaitingConsent,
+    Approved,
+    Completed,
+    Abandoned,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::AwaitingConsent => "awaiting_consent",
+            Status::Approved => "approved",
+            Status::Completed => "completed",
+            Status::Abandoned => "abandoned",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Status::AwaitingConsent, Status::Approved, Status::Completed, Status::Abandoned]
+            .into_iter()
+            .find(|s| s.name() == name)
+    }
+}
+
+/// A multi-step operation paused at the consent gate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkflowState {
+    pub key: IdempotencyKey,
+    pub operation: OperationKind,
+    pub repo: String,
+    pub description: String,
+    /// The session that planned the workflow, or last resumed it.
+    pub session: String,
+    /// What the repository looked like when the plan was made, e.g. the
+    /// tip of the target branch. Compared again on resume.
+    pub planned_against: String,
+    pub steps: Vec<String>,
+    /// Index of the first step not yet executed.
+    pub next_step: usize,
+    pub status: Status,
+}
+
+impl WorkflowState {
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "key = {}\noperation = {}\nrepo = {}\ndescription = {}\nsession = {}\nplanned_against = {}\nnext_step = {}\nstatus = {}\n",
+            self.key.to_hex(),
+            self.operation.name(),
+            self.repo,
+            self.description,
+            self.session,
+            self.planned_against,
+            self.next_step,
+            self.status.name()
+        );
+        for step in &self.steps {
+            out.push_str(&format!("step = {}\n", step));
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = std::collections::HashMap::new();
+        let mut steps = Vec::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let (k, v) = line.split_once(" = ")?;
+            if k == "step" {
+                steps.push(v.to_string());
+            } else {
+                fields.insert(k, v.to_string());
+            }
+        }
+        let get = |k: &str| fields.get(k).cloned();
+        Some(WorkflowState {
+            key: IdempotencyKey::from_hex(&get("key")?)?,
+            operation: OperationKind::from_name(&get("operation")?)?,
+            repo: get("repo")?,
+            description: get("description")?,
+            session: get("session")?,
+            planned_against: get("planned_against")?,
+            steps,
+            next_step: get("next_step")?.parse().ok()?,
+            status: Status::from_name(&get("status")?)?,
+        })
+    }
+}
+
+/// Workflow states on disk, one file per idempotency key.
+pub struct WorkflowStore {
+    dir: PathBuf,
+}
+
+impl WorkflowStore {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(WorkflowStore { dir })
+    }
+
+    fn path(&self, key: IdempotencyKey) -> PathBuf {
+        self.dir.join(format!("{}.workflow", key.to_hex()))
+    }
+
+    pub fn save(&self, state: &WorkflowState) -> io::Result<()> {
+        // Write-then-rename, so a crash mid-save never leaves half a plan.
+        let tmp = self.path(state.key).with_extension("tmp");
+        fs::write(&tmp, state.to_text())?;
+        fs::rename(tmp, self.path(state.key))
+    }
+
+    pub fn load(&self, key: IdempotencyKey) -> io::Result<Option<WorkflowState>> {
+        match fs::read_to_string(self.path(key)) {
+            Ok(text) => WorkflowState::parse(&text)
+                .map(Some)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed workflow state")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// What a new session may do with a workflow it inherited.
+#[derive(Debug)]
+pub enum Resumption {
+    /// The repository is as planned; continue from `next_step`.
+    Resume(WorkflowState),
+    /// The repository moved since planning. The old steps are discarded;
+    /// plan again and ask again.
+    Replan(WorkflowState),
+    /// Nothing to resume: unknown key, or the workflow already finished.
+    Nothing,
+}
+
+impl SafetyGate {
+    /// Hand a saved workflow to `session`. The linkage between the
+    /// planning session and the resuming one goes in the audit log.
+    pub fn resume_workflow(
+        &mut self,
+        store: &WorkflowStore,
+        key: IdempotencyKey,
+        session: &str,
+        current_state: &str,
+    ) -> io::Result<Resumption> {
+        let Some(mut state) = store.load(key)? else {
+            return Ok(Resumption::Nothing);
+        };
+        if matches!(state.status, Status::Completed | Status::Abandoned) {
+            return Ok(Resumption::Nothing);
+        }
+        let previous = std::mem::replace(&mut state.session, session.to_string());
+        if state.planned_against == current_state {
+            self.log(format!(
+                "RESUMED: workflow {} ({}) planned in session {}, resumed in session {} at step {}",
+                key.to_hex(),
+                state.description,
+                previous,
+                session,
+                state.next_step
+            ));
+            store.save(&state)?;
+            Ok(Resumption::Resume(state))
+        } else {
+            self.log(format!(
+                "REPLANNED: workflow {} ({}) planned in session {} against {}, repository now at {}",
+                key.to_hex(),
+                state.description,
+                previous,
+                state.planned_against,
+                current_state
+            ));
+            state.status = Status::Abandoned;
+            store.save(&state)?;
+            Ok(Resumption::Replan(state))
+        }
+    }
+}
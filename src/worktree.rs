@@ -0,0 +1,105 @@
+//! Worktree — a disposable checkout that cannot reach its repository.
+
+use crate::{backend, Outcome, Protected, Repository};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// A linked worktree of a protected repository.
+///
+/// An agent handed a worktree can edit, commit, and throw the checkout
+/// away, and nothing else: a worktree offers only safe operations, and it
+/// never hands out the repository it borrows. Because it borrows it, the
+/// repository cannot be unprotected, rewritten, reset, or transferred
+/// while the worktree is in hand. Whoever holds the repository has to
+/// take the worktree back first.
+pub struct Worktree<'r, B = backend::Simulated> {
+    repo: &'r Repository<Protected, B>,
+    path: String,
+    branch: String,
+    uncommitted: Vec<String>,
+}
+
+impl<'r, B: backend::GitBackend> Worktree<'r, B> {
+    pub(crate) fn add(repo: &'r Repository<Protected, B>, path: &str) -> Self {
+        let path = path.trim_end_matches('/');
+        Worktree {
+            repo,
+            path: path.to_string(),
+            branch: path.rsplit('/').next().unwrap_or(path).to_string(),
+            uncommitted: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The branch checked out here, which no other worktree may check out.
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Record a file with uncommitted changes in this worktree.
+    pub fn with_uncommitted(mut self, file: &str) -> Self {
+        self.uncommitted.push(file.to_string());
+        self
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "{} ({} worktree of '{}'): {} uncommitted",
+            self.path,
+            self.branch,
+            self.repo.name,
+            self.uncommitted.len()
+        )
+    }
+
+    /// The files with uncommitted changes in this worktree, one per line.
+    pub fn diff(&self) -> String {
+        self.uncommitted.iter().map(|f| format!("M {}\n", f)).collect()
+    }
+
+    /// The most recent `count` commits.
+    pub fn log(&self, count: usize) -> String {
+        self.repo.log(count)
+    }
+
+    /// Fetch is safe, here as in the repository.
+    pub fn fetch(&self) -> Result<String, Outcome> {
+        self.repo.fetch()
+    }
+
+    fn target(&self) -> backend::Target<'_> {
+        backend::Target {
+            path: &self.path,
+            uncommitted: &self.uncommitted,
+            ..self.repo.target()
+        }
+    }
+
+    /// Commit everything uncommitted here to this worktree's branch. Safe.
+    /// A commit the backend fails leaves the work uncommitted.
+    pub fn commit_all(&mut self, message: &str) -> Result<String, Outcome> {
+        let committed = self.repo.backend.commit(&self.target(), message, &self.uncommitted)?;
+        self.uncommitted.clear();
+        Ok(committed)
+    }
+
+    /// Regular push of this worktree's branch is safe. No consent required.
+    pub fn push(&self) -> Result<String, Outcome> {
+        self.repo.backend.push(&self.target(), &self.branch)
+    }
+
+    /// Remove the worktree, keeping its branch and commits. Refused, with
+    /// the worktree handed back, while it has uncommitted work: that work
+    /// exists nowhere else.
+    pub fn remove(self) -> Result<String, Box<Self>> {
+        if !self.uncommitted.is_empty() {
+            return Err(Box::new(self));
+        }
+        Ok(format!("[{}] removed worktree {}; branch '{}' kept", self.repo.name, self.path, self.branch))
+    }
+}